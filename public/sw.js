@@ -0,0 +1,163 @@
+// Service worker of the Wasm Knowledge Chatbot. Precaches the app shell (the page,
+// its hashed wasm/js/css bundles and the scripts it loads from CDNs) so the app starts
+// without a connection. WebLLM keeps downloaded models in its own caches; once a model
+// is there, chatting works fully offline.
+//
+// Registered from Rust (`src/utils/pwa.rs`) as `sw.js?v=<app version>`, so every
+// release installs a new worker and the page can offer to switch to it.
+
+const VERSION = new URL(self.location.href).searchParams.get("v") || "dev";
+const SHELL_CACHE = `knowledge-chatbot-shell-${VERSION}`;
+const RUNTIME_CACHE = "knowledge-chatbot-runtime";
+const CACHE_PREFIX = "knowledge-chatbot-";
+const OFFLINE_PAGE = "offline.html";
+// Files copied next to the page that it does not reference itself
+const EXTRA_ASSETS = [OFFLINE_PAGE, "manifest.webmanifest", "logo-kg.png"];
+// Libraries loaded from CDNs, cached as they are used. Model weights are not listed:
+// WebLLM caches them itself.
+const CDN_HOSTS = ["unpkg.com", "esm.run", "cdn.jsdelivr.net"];
+// Entries kept in the runtime cache, oldest dropped first
+const RUNTIME_LIMIT = 150;
+
+self.addEventListener("install", (event) => {
+  event.waitUntil(precache());
+});
+
+self.addEventListener("activate", (event) => {
+  event.waitUntil(
+    (async () => {
+      const names = await caches.keys();
+      await Promise.all(
+        names
+          .filter((name) => name.startsWith(CACHE_PREFIX))
+          .filter((name) => name !== SHELL_CACHE && name !== RUNTIME_CACHE)
+          .map((name) => caches.delete(name)),
+      );
+      await self.clients.claim();
+    })(),
+  );
+});
+
+// The page asks the waiting worker to take over once the user accepts the update
+self.addEventListener("message", (event) => {
+  if (event.data && event.data.type === "skip-waiting") {
+    self.skipWaiting();
+  }
+});
+
+self.addEventListener("fetch", (event) => {
+  const request = event.request;
+  if (request.method !== "GET") {
+    return;
+  }
+  const url = new URL(request.url);
+  if (request.mode === "navigate") {
+    event.respondWith(navigation(request));
+  } else if (url.origin === self.location.origin) {
+    event.respondWith(cacheFirst(request));
+  } else if (CDN_HOSTS.includes(url.hostname)) {
+    event.respondWith(staleWhileRevalidate(event));
+  }
+});
+
+// Cache the page and every asset it references. An asset that cannot be fetched does
+// not stop the install; it is cached the first time the page loads it.
+async function precache() {
+  const cache = await caches.open(SHELL_CACHE);
+  const scope = self.registration.scope;
+  const page = await fetch(scope, { cache: "no-cache" });
+  if (!page.ok) {
+    throw new Error(`Failed to fetch the app page: ${page.status}`);
+  }
+  const html = await page.clone().text();
+  await cache.put(scope, page);
+
+  const assets = new Set(EXTRA_ASSETS.map((asset) => new URL(asset, scope).href));
+  for (const url of referencedUrls(html)) {
+    assets.add(new URL(url, scope).href);
+  }
+  await Promise.all(
+    [...assets].map(async (asset) => {
+      try {
+        const sameOrigin = new URL(asset).origin === self.location.origin;
+        const response = await fetch(asset, { mode: sameOrigin ? "same-origin" : "cors" });
+        if (response.ok) {
+          await cache.put(asset, response);
+        }
+      } catch (err) {
+        console.warn(`Not precached: ${asset}`, err);
+      }
+    }),
+  );
+}
+
+// URLs in attributes (quoted or not, the release page is minified) and in module imports
+function referencedUrls(html) {
+  const urls = [];
+  const patterns = [
+    /\s(?:href|src)=["']?([^"'\s>]+)/g,
+    /(?:from\s*|import\s*\(\s*|module_or_path:\s*)["']([^"']+)["']/g,
+  ];
+  for (const pattern of patterns) {
+    for (const match of html.matchAll(pattern)) {
+      if (!match[1].startsWith("data:")) {
+        urls.push(match[1]);
+      }
+    }
+  }
+  return urls;
+}
+
+// Latest page when online; the cached one, then the offline page, without a connection
+async function navigation(request) {
+  const scope = self.registration.scope;
+  try {
+    const response = await fetch(request);
+    if (response.ok && new URL(request.url).href === scope) {
+      const cache = await caches.open(SHELL_CACHE);
+      await cache.put(scope, response.clone());
+    }
+    return response;
+  } catch (err) {
+    const cached = (await caches.match(request)) || (await caches.match(scope));
+    return cached || (await caches.match(new URL(OFFLINE_PAGE, scope).href)) || Response.error();
+  }
+}
+
+// Bundles carry a content hash in their name, so a cached copy never goes stale
+async function cacheFirst(request) {
+  const cached = await caches.match(request);
+  if (cached) {
+    return cached;
+  }
+  const response = await fetch(request);
+  if (response.ok) {
+    await putRuntime(request, response.clone());
+  }
+  return response;
+}
+
+async function staleWhileRevalidate(event) {
+  const request = event.request;
+  const cached = await caches.match(request);
+  const refreshed = fetch(request)
+    .then(async (response) => {
+      if (response.ok || response.type === "opaque") {
+        await putRuntime(request, response.clone());
+      }
+      return response;
+    })
+    .catch(() => cached || Response.error());
+  // The refresh outlives the response when the cached copy is served
+  event.waitUntil(refreshed);
+  return cached || refreshed;
+}
+
+async function putRuntime(request, response) {
+  const cache = await caches.open(RUNTIME_CACHE);
+  await cache.put(request, response);
+  const keys = await cache.keys();
+  for (const key of keys.slice(0, Math.max(0, keys.length - RUNTIME_LIMIT))) {
+    await cache.delete(key);
+  }
+}
@@ -1,5 +1,7 @@
+use crate::models::graph_store::GraphStore;
 use crate::pagerank_reranking::GraphAccess;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct HyDEConfig {
@@ -147,4 +149,231 @@ impl CommunityDetectionEngine {
         }
         groups.into_iter().filter(|g| !g.is_empty()).collect()
     }
+
+    /// Louvain modularity optimisation: repeated local moving of nodes between
+    /// communities followed by aggregation of each community into a single node.
+    /// Returns a dense community label per node, numbered in order of first appearance.
+    pub fn louvain(&self, graph: &WeightedGraph) -> Vec<usize> {
+        let n = graph.node_count();
+        let resolution = self.config.resolution as f64;
+        let m2 = graph.total_weight();
+        if n == 0 || m2 <= 0.0 {
+            return (0..n).collect();
+        }
+
+        let mut membership: Vec<usize> = (0..n).collect();
+        let mut adjacency = graph.adjacency.clone();
+        loop {
+            let size = adjacency.len();
+            let degree: Vec<f64> = adjacency
+                .iter()
+                .map(|row| row.iter().map(|(_, w)| w).sum())
+                .collect();
+            let mut community: Vec<usize> = (0..size).collect();
+            let mut totals = degree.clone();
+            let mut moved_any = false;
+
+            // Phase 1: move each node to the neighbouring community with the best gain
+            let mut links = vec![0.0f64; size];
+            let mut touched: Vec<usize> = Vec::new();
+            for _ in 0..self.config.max_iterations.max(1) {
+                let mut moved = false;
+                for u in 0..size {
+                    let current = community[u];
+                    for &(v, w) in &adjacency[u] {
+                        if v == u {
+                            continue;
+                        }
+                        let c = community[v];
+                        if links[c] == 0.0 {
+                            touched.push(c);
+                        }
+                        links[c] += w;
+                    }
+                    totals[current] -= degree[u];
+                    let gain = |c: usize| links[c] - resolution * totals[c] * degree[u] / m2;
+                    let mut best = current;
+                    let mut best_gain = gain(current);
+                    for &c in &touched {
+                        let g = gain(c);
+                        if g > best_gain + 1e-12 {
+                            best = c;
+                            best_gain = g;
+                        }
+                    }
+                    totals[best] += degree[u];
+                    if best != current {
+                        community[u] = best;
+                        moved = true;
+                        moved_any = true;
+                    }
+                    for c in touched.drain(..) {
+                        links[c] = 0.0;
+                    }
+                }
+                if !moved {
+                    break;
+                }
+            }
+            if !moved_any {
+                break;
+            }
+
+            // Phase 2: collapse communities into nodes of the next level
+            let dense = relabel(&community);
+            let next_size = dense.iter().max().map_or(0, |m| m + 1);
+            for m in membership.iter_mut() {
+                *m = dense[*m];
+            }
+            let mut merged: Vec<HashMap<usize, f64>> = vec![HashMap::new(); next_size];
+            for (u, row) in adjacency.iter().enumerate() {
+                for &(v, w) in row {
+                    *merged[dense[u]].entry(dense[v]).or_insert(0.0) += w;
+                }
+            }
+            adjacency = merged
+                .into_iter()
+                .map(|row| {
+                    let mut row: Vec<(usize, f64)> = row.into_iter().collect();
+                    row.sort_by_key(|(v, _)| *v);
+                    row
+                })
+                .collect();
+            if next_size == size {
+                break;
+            }
+        }
+        relabel(&membership)
+    }
+
+    /// Run Louvain over the whole store and record each node's community in its
+    /// metadata (`"community": <id>`). Returns the number of communities found.
+    pub fn assign_communities(&self, store: &mut GraphStore) -> usize {
+        let graph = WeightedGraph::from_graph_store(store);
+        let labels = self.louvain(&graph);
+        for (node, label) in store.nodes.iter_mut().zip(&labels) {
+            match node.metadata.as_object_mut() {
+                Some(obj) => {
+                    obj.insert("community".to_string(), (*label).into());
+                }
+                None => node.metadata = serde_json::json!({ "community": label }),
+            }
+        }
+        labels.iter().max().map_or(0, |m| m + 1)
+    }
+}
+
+/// Undirected weighted graph used for modularity optimisation. Each edge appears in
+/// the rows of both endpoints; a self-loop appears once with twice its weight.
+#[derive(Clone, Debug, Default)]
+pub struct WeightedGraph {
+    adjacency: Vec<Vec<(usize, f64)>>,
+    neighbors: Vec<Vec<usize>>,
+}
+
+impl WeightedGraph {
+    pub fn from_edges(node_count: usize, edges: &[(usize, usize, f64)]) -> Self {
+        let mut rows: Vec<HashMap<usize, f64>> = vec![HashMap::new(); node_count];
+        for &(u, v, w) in edges {
+            if u >= node_count || v >= node_count || w <= 0.0 {
+                continue;
+            }
+            if u == v {
+                *rows[u].entry(u).or_insert(0.0) += 2.0 * w;
+            } else {
+                *rows[u].entry(v).or_insert(0.0) += w;
+                *rows[v].entry(u).or_insert(0.0) += w;
+            }
+        }
+        let adjacency: Vec<Vec<(usize, f64)>> = rows
+            .into_iter()
+            .map(|row| {
+                let mut row: Vec<(usize, f64)> = row.into_iter().collect();
+                row.sort_by_key(|(v, _)| *v);
+                row
+            })
+            .collect();
+        let neighbors = adjacency
+            .iter()
+            .map(|row| row.iter().map(|(v, _)| *v).collect())
+            .collect();
+        Self {
+            adjacency,
+            neighbors,
+        }
+    }
+
+    /// Graph over the store's nodes (in store order); edge direction is ignored and
+    /// edges pointing at unknown nodes are skipped.
+    pub fn from_graph_store(store: &GraphStore) -> Self {
+        let index: HashMap<&str, usize> = store
+            .nodes
+            .iter()
+            .enumerate()
+            .map(|(i, n)| (n.id.as_str(), i))
+            .collect();
+        let edges: Vec<(usize, usize, f64)> = store
+            .edges
+            .iter()
+            .filter_map(|e| {
+                Some((
+                    *index.get(e.from.as_str())?,
+                    *index.get(e.to.as_str())?,
+                    e.weight as f64,
+                ))
+            })
+            .collect();
+        Self::from_edges(store.nodes.len(), &edges)
+    }
+
+    /// Sum of all weighted degrees (twice the total edge weight)
+    pub fn total_weight(&self) -> f64 {
+        self.adjacency.iter().flatten().map(|(_, w)| w).sum()
+    }
+
+    /// Newman modularity of a partition at the given resolution
+    pub fn modularity(&self, labels: &[usize], resolution: f64) -> f64 {
+        let m2 = self.total_weight();
+        if m2 <= 0.0 {
+            return 0.0;
+        }
+        let mut internal: HashMap<usize, f64> = HashMap::new();
+        let mut totals: HashMap<usize, f64> = HashMap::new();
+        for (u, row) in self.adjacency.iter().enumerate() {
+            for &(v, w) in row {
+                *totals.entry(labels[u]).or_insert(0.0) += w;
+                if labels[u] == labels[v] {
+                    *internal.entry(labels[u]).or_insert(0.0) += w;
+                }
+            }
+        }
+        totals
+            .iter()
+            .map(|(c, tot)| {
+                internal.get(c).copied().unwrap_or(0.0) / m2 - resolution * (tot / m2).powi(2)
+            })
+            .sum()
+    }
+}
+
+impl GraphAccess for WeightedGraph {
+    fn node_count(&self) -> usize {
+        self.adjacency.len()
+    }
+
+    fn out_neighbors(&self, u: usize) -> &[usize] {
+        &self.neighbors[u]
+    }
+}
+
+/// Renumber labels densely in order of first appearance
+fn relabel(labels: &[usize]) -> Vec<usize> {
+    let mut map: HashMap<usize, usize> = HashMap::new();
+    labels
+        .iter()
+        .map(|l| {
+            let next = map.len();
+            *map.entry(*l).or_insert(next)
+        })
+        .collect()
 }
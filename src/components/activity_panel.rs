@@ -0,0 +1,231 @@
+use crate::features::graphrag::GraphRAGPipeline;
+use crate::graphrag_config::{with_graphrag_manager, GraphRAGConfig};
+use crate::models::activity::{ActivityCategory, ActivityEvent};
+use crate::router::RouterContext;
+use crate::state::{use_event_bus, GraphRAGStateContext};
+use crate::storage::ConversationStorage;
+use crate::utils::download::DownloadUtils;
+use crate::utils::generation::GenerationUtils;
+use crate::utils::icons::schedule_icon_render;
+use crate::utils::scenario::{ScenarioBundle, ScenarioOptions, ScenarioSource};
+use crate::webllm_binding::loaded_engine;
+use leptos::prelude::*;
+use wasm_bindgen_futures::JsFuture;
+
+fn format_time(ts: f64) -> String {
+    let date = js_sys::Date::new(&wasm_bindgen::JsValue::from(ts));
+    date.to_locale_time_string("en-US")
+        .as_string()
+        .unwrap_or_default()
+}
+
+/// Chronological feed of background activity recorded on the event bus
+#[component]
+pub fn ActivityPanel() -> impl IntoView {
+    let bus = use_event_bus();
+    let events = bus.events();
+    // None = all categories
+    let (filter, set_filter) = signal(None::<ActivityCategory>);
+
+    let visible = Memo::new(move |_| {
+        let f = filter.get();
+        let mut v: Vec<ActivityEvent> = events
+            .get()
+            .into_iter()
+            .filter(|e| match f {
+                Some(c) => e.category == c,
+                None => true,
+            })
+            .collect();
+        v.reverse();
+        v
+    });
+
+    Effect::new(move |_| {
+        let _ = visible.get();
+        schedule_icon_render();
+    });
+
+    // Scenario bundles for bug reports
+    let router = use_context::<RouterContext>();
+    let graphrag_ctx = use_context::<GraphRAGStateContext>();
+    let include_contents = RwSignal::new(false);
+    let (scenario_status, set_scenario_status) = signal(None::<String>);
+    let scenario_input = NodeRef::<leptos::html::Input>::new();
+
+    let export_scenario = move |_| {
+        let messages = router
+            .and_then(|r| r.route_now().conversation)
+            .and_then(|id| {
+                ConversationStorage::new()
+                    .ok()?
+                    .load_conversation(&id)
+                    .ok()?
+            })
+            .unwrap_or_default();
+        let mut graphrag_config = GraphRAGConfig::default();
+        with_graphrag_manager(|m| graphrag_config = m.get_config_untracked());
+        let source = ScenarioSource {
+            model: loaded_engine().map(|(id, _)| id),
+            graphrag_config,
+            generation_settings: GenerationUtils::load_settings(),
+            documents: GraphRAGPipeline::new()
+                .indexed_documents()
+                .unwrap_or_default(),
+            messages,
+            events: bus.events_now(),
+        };
+        let options = ScenarioOptions {
+            include_contents: include_contents.get_untracked(),
+        };
+        let now = js_sys::Date::now();
+        let bundle = ScenarioBundle::record(source, options, now);
+        let filename = format!("scenario-{}.json", now as u64);
+        match bundle
+            .to_json()
+            .and_then(|json| DownloadUtils::download_text(&filename, &json))
+        {
+            Ok(()) => {
+                set_scenario_status.set(Some(format!("Saved {}", filename)));
+                bus.record(ActivityCategory::Conversation, "Scenario exported");
+            }
+            Err(e) => {
+                set_scenario_status.set(Some(format!("Export failed: {}", e)));
+                bus.error("Scenario export failed", e.to_string());
+            }
+        }
+    };
+
+    let load_scenario = move |ev: leptos::ev::Event| {
+        let target: web_sys::HtmlInputElement = event_target(&ev);
+        let Some(file) = target.files().and_then(|f| f.item(0)) else {
+            return;
+        };
+        target.set_value("");
+        let graphrag_ctx = graphrag_ctx.clone();
+        wasm_bindgen_futures::spawn_local(async move {
+            let text = JsFuture::from(file.text())
+                .await
+                .map(|v| v.as_string().unwrap_or_default())
+                .map_err(|e| format!("{:?}", e));
+            let loaded = text.and_then(|json| {
+                let bundle = ScenarioBundle::from_json(&json).map_err(|e| e.to_string())?;
+                bundle.apply().map_err(|e| e.to_string())
+            });
+            match loaded {
+                Ok(conversation_id) => {
+                    if let Some(router) = router {
+                        router.set_conversation(Some(conversation_id));
+                    }
+                    if let Some(ctx) = graphrag_ctx {
+                        ctx.reindex();
+                    }
+                    set_scenario_status.set(Some("Scenario loaded".to_string()));
+                    bus.record(ActivityCategory::Conversation, "Scenario loaded");
+                }
+                Err(e) => {
+                    set_scenario_status.set(Some(format!("Load failed: {}", e)));
+                    bus.error("Scenario load failed", e);
+                }
+            }
+        });
+    };
+
+    let tab = move |label: &'static str, value: Option<ActivityCategory>| {
+        view! {
+            <a
+                role="tab"
+                class=move || if filter.get() == value { "tab tab-active" } else { "tab" }
+                on:click=move |_| set_filter.set(value)
+            >
+                {label}
+            </a>
+        }
+    };
+
+    view! {
+        <div class="flex flex-col gap-3 min-w-[20rem]" id="activity-panel">
+            <div class="flex items-center justify-between gap-2">
+                <div role="tablist" class="tabs tabs-boxed tabs-sm flex-wrap">
+                    {tab("All", None)}
+                    {ActivityCategory::ALL
+                        .into_iter()
+                        .map(|c| tab(c.label(), Some(c)))
+                        .collect_view()}
+                </div>
+                <button class="btn btn-ghost btn-xs" on:click=move |_| bus.clear()>
+                    "Clear"
+                </button>
+            </div>
+
+            <div class="flex flex-wrap items-center gap-2 text-xs">
+                <button class="btn btn-outline btn-xs" on:click=export_scenario>
+                    "Export scenario"
+                </button>
+                <button
+                    class="btn btn-ghost btn-xs"
+                    on:click=move |_| {
+                        if let Some(input) = scenario_input.get() {
+                            input.click();
+                        }
+                    }
+                >
+                    "Load scenario"
+                </button>
+                <label class="flex items-center gap-1 cursor-pointer" title="Otherwise letters and digits are replaced by x">
+                    <input
+                        type="checkbox"
+                        class="checkbox checkbox-xs"
+                        prop:checked=move || include_contents.get()
+                        on:change=move |ev| include_contents.set(event_target_checked(&ev))
+                    />
+                    <span>"Include message and document text"</span>
+                </label>
+                <input
+                    node_ref=scenario_input
+                    type="file"
+                    accept=".json,application/json"
+                    style="display:none"
+                    on:change=load_scenario
+                />
+                {move || scenario_status.get().map(|s| view! { <span class="opacity-70">{s}</span> })}
+            </div>
+
+            <Show
+                when=move || !visible.get().is_empty()
+                fallback=|| view! {
+                    <div class="text-sm text-base-content/60 py-6 text-center">
+                        "No activity recorded in this session yet."
+                    </div>
+                }
+            >
+                <ul class="flex flex-col gap-1 max-h-[60vh] overflow-y-auto">
+                    <For
+                        each=move || visible.get()
+                        key=|e| e.id.clone()
+                        children=move |e| {
+                            let is_error = e.category == ActivityCategory::Error;
+                            view! {
+                                <li class="flex items-start gap-2 p-2 rounded hover:bg-base-200">
+                                    <i
+                                        data-lucide=e.category.icon()
+                                        class=if is_error { "w-4 h-4 mt-0.5 text-error" } else { "w-4 h-4 mt-0.5 opacity-70" }
+                                    ></i>
+                                    <div class="flex-1 min-w-0">
+                                        <div class="text-sm">{e.message.clone()}</div>
+                                        {e.detail.clone().map(|d| view! {
+                                            <div class="text-xs text-base-content/60 break-words">{d}</div>
+                                        })}
+                                    </div>
+                                    <span class="text-xs font-mono text-base-content/50 shrink-0">
+                                        {format_time(e.timestamp)}
+                                    </span>
+                                </li>
+                            }
+                        }
+                    />
+                </ul>
+            </Show>
+        </div>
+    }
+}
@@ -0,0 +1,287 @@
+use crate::storage::backup::{self, BackupSection, ConflictPolicy, LoadedBackup, PlannedItem};
+use crate::storage::encryption;
+use crate::utils::download::DownloadUtils;
+use crate::utils::format::FormatUtils;
+use crate::utils::notifications::{LongTask, NotificationUtils};
+use leptos::prelude::*;
+use leptos::task::spawn_local;
+use wasm_bindgen_futures::JsFuture;
+
+/// A backup file picked for restoring, with its preview
+#[derive(Clone)]
+struct PendingRestore {
+    backup: LoadedBackup,
+    plan: Vec<PlannedItem>,
+}
+
+/// "Backup everything" to one zip file, and restoring such a file
+#[component]
+pub fn BackupPanel() -> impl IntoView {
+    let busy = RwSignal::new(false);
+    let status = RwSignal::new(None::<Result<String, String>>);
+    let pending = RwSignal::new(None::<PendingRestore>);
+    let sections = RwSignal::new(Vec::<BackupSection>::new());
+    let policy = RwSignal::new(ConflictPolicy::default());
+    let restored = RwSignal::new(false);
+
+    let create = move |_| {
+        busy.set(true);
+        status.set(None);
+        spawn_local(async move {
+            let outcome = backup::create().await.and_then(|(bytes, manifest)| {
+                let filename = format!(
+                    "knowledge-chatbot-backup-{}.zip",
+                    manifest.created_at as u64
+                );
+                DownloadUtils::download_bytes(&filename, &bytes)
+                    .map(|_| manifest.summary())
+                    .map_err(|e| e.to_string())
+            });
+            match &outcome {
+                Ok(summary) => {
+                    NotificationUtils::task_finished(LongTask::Backup, Ok(summary.as_str()))
+                }
+                Err(e) => NotificationUtils::task_finished(LongTask::Backup, Err(e.as_str())),
+            }
+            status.set(Some(
+                outcome
+                    .map(|summary| format!("Backed up {}", summary))
+                    .map_err(|e| format!("Backup failed: {}", e)),
+            ));
+            busy.set(false);
+        });
+    };
+    let load_restore = move |ev: leptos::ev::Event| {
+        let target: web_sys::HtmlInputElement = event_target(&ev);
+        let Some(file) = target.files().and_then(|f| f.item(0)) else {
+            return;
+        };
+        target.set_value("");
+        busy.set(true);
+        status.set(None);
+        spawn_local(async move {
+            let bytes = JsFuture::from(file.array_buffer())
+                .await
+                .map(|buffer| js_sys::Uint8Array::new(&buffer).to_vec())
+                .map_err(|e| format!("{:?}", e));
+            let loaded = match bytes {
+                Ok(bytes) => backup::open(&bytes).await,
+                Err(e) => Err(e),
+            };
+            match loaded {
+                Ok(loaded) => {
+                    sections.set(loaded.sections());
+                    pending.set(Some(PendingRestore {
+                        plan: loaded.plan(),
+                        backup: loaded,
+                    }));
+                }
+                Err(e) => {
+                    pending.set(None);
+                    status.set(Some(Err(format!("Restore refused: {}", e))));
+                }
+            }
+            busy.set(false);
+        });
+    };
+    let restore = move || {
+        let Some(PendingRestore { backup, .. }) = pending.get_untracked() else {
+            return;
+        };
+        let report = backup.restore(&sections.get_untracked(), policy.get_untracked());
+        pending.set(None);
+        restored.set(true);
+        if report.errors.is_empty() {
+            status.set(Some(Ok(report.summary())));
+        } else {
+            status.set(Some(Err(format!(
+                "{}. Not restored: {}",
+                report.summary(),
+                report.errors.join("; ")
+            ))));
+        }
+    };
+    let toggle_section = move |section: BackupSection| {
+        sections.update(|s| {
+            if let Some(at) = s.iter().position(|x| *x == section) {
+                s.remove(at);
+            } else {
+                s.push(section);
+            }
+        })
+    };
+    let restore_input = NodeRef::<leptos::html::Input>::new();
+
+    view! {
+        <div class="flex flex-col gap-3 text-sm" id="backup">
+            <div>
+                <div class="font-medium">"Backup"</div>
+                <div class="text-xs text-base-content/70">
+                    "Conversations, documents, the knowledge graph, CRM, tasks and settings in one zip file."
+                </div>
+                <Show when=encryption::is_enabled>
+                    <div class="text-xs text-warning mt-1">
+                        "The backup file is not encrypted, even though stored data is."
+                    </div>
+                </Show>
+            </div>
+
+            <div class="flex gap-2">
+                <button class="btn btn-primary btn-sm" on:click=create disabled=move || busy.get()>
+                    <i data-lucide="archive" class="w-4 h-4"></i>
+                    {move || if busy.get() { "Working…" } else { "Backup everything" }}
+                </button>
+                <button
+                    class="btn btn-sm"
+                    disabled=move || busy.get()
+                    on:click=move |_| {
+                        if let Some(input) = restore_input.get() {
+                            input.click();
+                        }
+                    }
+                >
+                    <i data-lucide="archive-restore" class="w-4 h-4"></i>
+                    "Restore…"
+                </button>
+                <input
+                    type="file"
+                    accept=".zip,application/zip"
+                    class="hidden"
+                    node_ref=restore_input
+                    on:change=load_restore
+                />
+            </div>
+
+            {move || {
+                status
+                    .get()
+                    .map(|s| {
+                        let (class, msg) = match s {
+                            Ok(msg) => ("alert alert-success text-sm", msg),
+                            Err(msg) => ("alert alert-error text-sm", msg),
+                        };
+                        view! { <div class=class>{msg}</div> }
+                    })
+            }}
+
+            // Preview of the picked backup; nothing is written before Restore
+            <Show when=move || pending.with(|p| p.is_some())>
+                <div class="p-3 rounded-xl border border-info bg-base-200 space-y-3">
+                    <div class="text-xs text-base-content/70">
+                        {move || {
+                            pending
+                                .with(|p| {
+                                    p.as_ref().map(|p| {
+                                        format!(
+                                            "Backup of {}: {}",
+                                            FormatUtils::format_timestamp(p.backup.manifest.created_at),
+                                            p.backup.manifest.summary()
+                                        )
+                                    })
+                                })
+                                .unwrap_or_default()
+                        }}
+                    </div>
+                    {move || {
+                        let Some(p) = pending.get() else {
+                            return Vec::new();
+                        };
+                        p.backup
+                            .sections()
+                            .into_iter()
+                            .map(|section| {
+                                let items: Vec<_> = p
+                                    .plan
+                                    .iter()
+                                    .filter(|item| item.section == section)
+                                    .map(|item| {
+                                        let class = match item.status {
+                                            backup::RecordStatus::Differs => "text-warning",
+                                            _ => "text-base-content/60",
+                                        };
+                                        view! {
+                                            <li class="flex justify-between gap-2">
+                                                <span>{item.label.clone()}</span>
+                                                <span class=class>{item.status.label()}</span>
+                                            </li>
+                                        }
+                                    })
+                                    .collect();
+                                view! {
+                                    <div>
+                                        <label class="flex items-center gap-2 cursor-pointer font-medium">
+                                            <input
+                                                type="checkbox"
+                                                class="checkbox checkbox-xs"
+                                                prop:checked=move || sections.with(|s| s.contains(&section))
+                                                on:change=move |_| toggle_section(section)
+                                            />
+                                            {section.label()}
+                                        </label>
+                                        <ul class="ml-6 text-xs space-y-0.5">{items}</ul>
+                                    </div>
+                                }
+                            })
+                            .collect::<Vec<_>>()
+                    }}
+                    {move || {
+                        pending
+                            .with(|p| p.as_ref().map(|p| p.backup.warnings.clone()).unwrap_or_default())
+                            .into_iter()
+                            .map(|w| view! { <p class="text-xs text-warning">{w}</p> })
+                            .collect_view()
+                    }}
+                    <div class="flex flex-col gap-1">
+                        <div class="font-medium">"When data is both here and in the backup"</div>
+                        {ConflictPolicy::ALL
+                            .into_iter()
+                            .map(|option| {
+                                view! {
+                                    <label class="flex items-center gap-2 cursor-pointer text-xs">
+                                        <input
+                                            type="radio"
+                                            name="backup-conflict-policy"
+                                            class="radio radio-xs"
+                                            prop:checked=move || policy.get() == option
+                                            on:change=move |_| policy.set(option)
+                                        />
+                                        {option.label()}
+                                    </label>
+                                }
+                            })
+                            .collect_view()}
+                    </div>
+                    <div class="flex justify-end gap-2">
+                        <button class="btn btn-ghost btn-xs" on:click=move |_| pending.set(None)>
+                            "Cancel"
+                        </button>
+                        <button
+                            class="btn btn-primary btn-xs"
+                            disabled=move || sections.with(|s| s.is_empty())
+                            on:click=move |_| restore()
+                        >
+                            "Restore"
+                        </button>
+                    </div>
+                </div>
+            </Show>
+
+            // Open views still show the data from before the restore
+            <Show when=move || restored.get()>
+                <div class="flex items-center justify-between gap-2">
+                    <span class="text-base-content/70">"Reload to see the restored data."</span>
+                    <button
+                        class="btn btn-ghost btn-xs"
+                        on:click=move |_| {
+                            if let Some(win) = web_sys::window() {
+                                let _ = win.location().reload();
+                            }
+                        }
+                    >
+                        "Reload"
+                    </button>
+                </div>
+            </Show>
+        </div>
+    }
+}
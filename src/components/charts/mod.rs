@@ -0,0 +1,6 @@
+// Inline chart rendering for structured assistant output
+pub mod spec;
+pub mod svg_chart;
+
+pub use spec::{split_chart_blocks, ChartKind, ChartSeries, ChartSpec, ContentSegment};
+pub use svg_chart::SvgChart;
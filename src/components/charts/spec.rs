@@ -0,0 +1,233 @@
+use serde::{Deserialize, Serialize};
+use std::f64::consts::PI;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChartKind {
+    #[default]
+    Bar,
+    Line,
+    Pie,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ChartSeries {
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(alias = "values")]
+    pub data: Vec<f64>,
+}
+
+/// Chart description emitted by the assistant in a fenced `chart` block
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ChartSpec {
+    #[serde(rename = "type", default)]
+    pub kind: ChartKind,
+    #[serde(default)]
+    pub title: Option<String>,
+    pub labels: Vec<String>,
+    pub series: Vec<ChartSeries>,
+}
+
+impl ChartSpec {
+    pub fn parse(json: &str) -> Result<Self, String> {
+        let spec: ChartSpec = serde_json::from_str(json).map_err(|e| e.to_string())?;
+        spec.validate()?;
+        Ok(spec)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.labels.is_empty() {
+            return Err("chart has no labels".to_string());
+        }
+        if self.series.is_empty() {
+            return Err("chart has no series".to_string());
+        }
+        for (i, s) in self.series.iter().enumerate() {
+            if s.data.len() != self.labels.len() {
+                return Err(format!(
+                    "series {} has {} values for {} labels",
+                    i + 1,
+                    s.data.len(),
+                    self.labels.len()
+                ));
+            }
+            if s.data.iter().any(|v| !v.is_finite()) {
+                return Err(format!("series {} contains non-numeric values", i + 1));
+            }
+        }
+        if self.kind == ChartKind::Pie && self.series[0].data.iter().any(|v| *v < 0.0) {
+            return Err("pie charts need non-negative values".to_string());
+        }
+        Ok(())
+    }
+
+    /// Largest and smallest value across all series (0 is always included)
+    pub fn value_range(&self) -> (f64, f64) {
+        self.series
+            .iter()
+            .flat_map(|s| s.data.iter().copied())
+            .fold((0.0_f64, 0.0_f64), |(lo, hi), v| (lo.min(v), hi.max(v)))
+    }
+}
+
+/// Piece of a message: plain text or a chart block
+#[derive(Clone, Debug, PartialEq)]
+pub enum ContentSegment {
+    Text(String),
+    Chart(ChartSpec),
+    InvalidChart { raw: String, error: String },
+}
+
+/// Split message content on ```chart fences; other fences are kept as text
+pub fn split_chart_blocks(content: &str) -> Vec<ContentSegment> {
+    let mut out = Vec::new();
+    let mut text = String::new();
+    let mut chart: Option<String> = None;
+
+    for line in content.split_inclusive('\n') {
+        let trimmed = line.trim();
+        match chart.as_mut() {
+            None if trimmed == "```chart" => {
+                if !text.trim().is_empty() {
+                    out.push(ContentSegment::Text(std::mem::take(&mut text)));
+                }
+                text.clear();
+                chart = Some(String::new());
+            }
+            None => text.push_str(line),
+            Some(_) if trimmed == "```" => {
+                let raw = chart.take().unwrap_or_default();
+                out.push(match ChartSpec::parse(&raw) {
+                    Ok(spec) => ContentSegment::Chart(spec),
+                    Err(error) => ContentSegment::InvalidChart { raw, error },
+                });
+            }
+            Some(buf) => buf.push_str(line),
+        }
+    }
+    // Unterminated fence (e.g. mid-stream): keep it visible as text
+    if let Some(raw) = chart {
+        text.push_str("```chart\n");
+        text.push_str(&raw);
+    }
+    if !text.trim().is_empty() {
+        out.push(ContentSegment::Text(text));
+    }
+    out
+}
+
+/// Round an axis maximum up to 1, 2, 2.5 or 5 times a power of ten
+pub fn nice_ceiling(value: f64) -> f64 {
+    if value <= 0.0 {
+        return 0.0;
+    }
+    let magnitude = 10f64.powf(value.log10().floor());
+    let normalized = value / magnitude;
+    let step = [1.0, 2.0, 2.5, 5.0, 10.0]
+        .into_iter()
+        .find(|s| normalized <= *s)
+        .unwrap_or(10.0);
+    step * magnitude
+}
+
+/// SVG path for a pie slice between two angles (radians, clockwise from 12 o'clock)
+pub fn pie_slice_path(cx: f64, cy: f64, r: f64, start: f64, end: f64) -> String {
+    // A full circle cannot be drawn as a single arc
+    if end - start >= 2.0 * PI - 1e-9 {
+        return format!(
+            "M {cx} {top} A {r} {r} 0 1 1 {cx} {bottom} A {r} {r} 0 1 1 {cx} {top} Z",
+            cx = cx,
+            r = r,
+            top = cy - r,
+            bottom = cy + r
+        );
+    }
+    let point = |a: f64| (cx + r * a.sin(), cy - r * a.cos());
+    let (x0, y0) = point(start);
+    let (x1, y1) = point(end);
+    let large = if end - start > PI { 1 } else { 0 };
+    format!(
+        "M {cx} {cy} L {x0:.2} {y0:.2} A {r} {r} 0 {large} 1 {x1:.2} {y1:.2} Z",
+        cx = cx,
+        cy = cy,
+        x0 = x0,
+        y0 = y0,
+        r = r,
+        large = large,
+        x1 = x1,
+        y1 = y1
+    )
+}
+
+/// Start/end angles for each value as a share of the total
+pub fn pie_angles(values: &[f64]) -> Vec<(f64, f64)> {
+    let total: f64 = values.iter().sum();
+    let mut acc = 0.0;
+    values
+        .iter()
+        .map(|v| {
+            let start = acc;
+            if total > 0.0 {
+                acc += v / total * 2.0 * PI;
+            }
+            (start, acc)
+        })
+        .collect()
+}
+
+/// Compact value label for axes and tooltips
+pub fn format_value(v: f64) -> String {
+    if v.fract() == 0.0 && v.abs() < 1e15 {
+        format!("{}", v as i64)
+    } else {
+        format!("{:.2}", v)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_chart_blocks() {
+        let content = "Here you go:\n```chart\n{\"type\": \"pie\", \"labels\": [\"a\", \"b\"], \"series\": [{\"values\": [1, 3]}]}\n```\nDone.";
+        let segs = split_chart_blocks(content);
+        assert_eq!(segs.len(), 3);
+        assert!(matches!(&segs[0], ContentSegment::Text(t) if t.starts_with("Here")));
+        match &segs[1] {
+            ContentSegment::Chart(spec) => {
+                assert_eq!(spec.kind, ChartKind::Pie);
+                assert_eq!(spec.series[0].data, vec![1.0, 3.0]);
+            }
+            other => panic!("expected chart, got {:?}", other),
+        }
+        assert_eq!(segs[2], ContentSegment::Text("Done.".to_string()));
+    }
+
+    #[test]
+    fn test_invalid_and_unterminated_charts() {
+        let segs = split_chart_blocks(
+            "```chart\n{\"labels\": [\"a\"], \"series\": [{\"data\": [1, 2]}]}\n```\n",
+        );
+        assert!(matches!(&segs[0], ContentSegment::InvalidChart { .. }));
+
+        let segs = split_chart_blocks("```chart\n{\"labels\":");
+        assert!(matches!(&segs[0], ContentSegment::Text(t) if t.contains("```chart")));
+    }
+
+    #[test]
+    fn test_nice_ceiling() {
+        assert_eq!(nice_ceiling(0.0), 0.0);
+        assert_eq!(nice_ceiling(7.0), 10.0);
+        assert_eq!(nice_ceiling(180.0), 200.0);
+        assert_eq!(nice_ceiling(2300.0), 2500.0);
+    }
+
+    #[test]
+    fn test_pie_angles() {
+        let a = pie_angles(&[1.0, 1.0]);
+        assert!((a[0].1 - PI).abs() < 1e-9);
+        assert!((a[1].1 - 2.0 * PI).abs() < 1e-9);
+    }
+}
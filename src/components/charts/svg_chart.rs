@@ -0,0 +1,303 @@
+use super::spec::{format_value, nice_ceiling, pie_angles, pie_slice_path, ChartKind, ChartSpec};
+use crate::utils::download::DownloadUtils;
+use leptos::prelude::*;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+
+const WIDTH: f64 = 400.0;
+const HEIGHT: f64 = 240.0;
+const MARGIN_LEFT: f64 = 44.0;
+const MARGIN_RIGHT: f64 = 12.0;
+const MARGIN_TOP: f64 = 28.0;
+const MARGIN_BOTTOM: f64 = 32.0;
+const TICKS: usize = 4;
+
+// Explicit colors so the exported PNG matches what is on screen
+const PALETTE: [&str; 8] = [
+    "#3b82f6", "#ef4444", "#10b981", "#f59e0b", "#8b5cf6", "#ec4899", "#14b8a6", "#64748b",
+];
+
+fn color(i: usize) -> &'static str {
+    PALETTE[i % PALETTE.len()]
+}
+
+/// SVG rendering of a `ChartSpec` with hover values and PNG export
+#[component]
+pub fn SvgChart(spec: ChartSpec) -> impl IntoView {
+    let chart_id = format!("chart-{}", uuid::Uuid::new_v4().simple());
+    let hovered = RwSignal::new(None::<String>);
+    let title = spec.title.clone();
+
+    let body = match spec.kind {
+        ChartKind::Pie => pie_view(&spec, hovered).into_any(),
+        ChartKind::Bar | ChartKind::Line => axes_view(&spec, hovered).into_any(),
+    };
+
+    let legend: Vec<(String, &'static str)> = if spec.kind == ChartKind::Pie {
+        spec.labels
+            .iter()
+            .enumerate()
+            .map(|(i, l)| (l.clone(), color(i)))
+            .collect()
+    } else {
+        spec.series
+            .iter()
+            .enumerate()
+            .filter_map(|(i, s)| s.name.clone().map(|n| (n, color(i))))
+            .collect()
+    };
+
+    let export_id = chart_id.clone();
+    let export_name = title.clone().unwrap_or_else(|| "chart".to_string());
+
+    view! {
+        <div class="chart-block my-2 rounded-lg bg-base-100 text-base-content p-2">
+            <svg
+                id=chart_id
+                xmlns="http://www.w3.org/2000/svg"
+                viewBox=format!("0 0 {} {}", WIDTH, HEIGHT)
+                class="w-full max-w-md h-auto"
+                font-family="sans-serif"
+                font-size="10"
+            >
+                {title.map(|t| view! {
+                    <text x=WIDTH / 2.0 y=16 text-anchor="middle" font-size="12" font-weight="bold" fill="currentColor">{t}</text>
+                })}
+                {body}
+            </svg>
+            <div class="flex flex-wrap items-center gap-3 text-xs mt-1">
+                {legend.into_iter().map(|(name, c)| view! {
+                    <span class="flex items-center gap-1">
+                        <span class="inline-block w-2.5 h-2.5 rounded-sm" style=format!("background:{}", c)></span>
+                        {name}
+                    </span>
+                }).collect_view()}
+                <span class="ml-auto font-mono opacity-70 min-h-[1em]">{move || hovered.get().unwrap_or_default()}</span>
+                <button
+                    class="btn btn-ghost btn-xs"
+                    title="Download as PNG"
+                    on:click=move |_| {
+                        if let Err(e) = export_png(&export_id, &export_name) {
+                            log::error!("Chart export failed: {:?}", e);
+                        }
+                    }
+                >
+                    "PNG"
+                </button>
+            </div>
+        </div>
+    }
+}
+
+fn axes_view(spec: &ChartSpec, hovered: RwSignal<Option<String>>) -> impl IntoView {
+    let (lo, hi) = spec.value_range();
+    let top = nice_ceiling(hi);
+    let bottom = -nice_ceiling(-lo);
+    let span = if top - bottom > 0.0 {
+        top - bottom
+    } else {
+        1.0
+    };
+    let plot_w = WIDTH - MARGIN_LEFT - MARGIN_RIGHT;
+    let plot_h = HEIGHT - MARGIN_TOP - MARGIN_BOTTOM;
+    let y = move |v: f64| MARGIN_TOP + plot_h * (top - v) / span;
+    let n = spec.labels.len();
+    let group_w = plot_w / n as f64;
+
+    let grid = (0..=TICKS)
+        .map(|i| {
+            let v = bottom + span * i as f64 / TICKS as f64;
+            let yy = y(v);
+            view! {
+                <line x1=MARGIN_LEFT x2=WIDTH - MARGIN_RIGHT y1=yy y2=yy stroke="#94a3b8" stroke-opacity="0.3" />
+                <text x=MARGIN_LEFT - 4.0 y=yy + 3.0 text-anchor="end" fill="currentColor">{format_value(v)}</text>
+            }
+        })
+        .collect_view();
+
+    let x_labels = spec
+        .labels
+        .iter()
+        .enumerate()
+        .map(|(i, l)| {
+            let x = MARGIN_LEFT + group_w * (i as f64 + 0.5);
+            view! {
+                <text x=x y=HEIGHT - MARGIN_BOTTOM + 14.0 text-anchor="middle" fill="currentColor">{l.clone()}</text>
+            }
+        })
+        .collect_view();
+
+    let marks = match spec.kind {
+        ChartKind::Line => spec
+            .series
+            .iter()
+            .enumerate()
+            .map(|(si, s)| {
+                let pts: Vec<(f64, f64)> = s
+                    .data
+                    .iter()
+                    .enumerate()
+                    .map(|(i, v)| (MARGIN_LEFT + group_w * (i as f64 + 0.5), y(*v)))
+                    .collect();
+                let points = pts
+                    .iter()
+                    .map(|(x, y)| format!("{:.1},{:.1}", x, y))
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                let dots = pts
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, (cx, cy))| {
+                        let label = hover_label(spec, si, i);
+                        view! {
+                            <circle
+                                cx=cx cy=cy r="4" fill=color(si)
+                                on:mouseenter=move |_| hovered.set(Some(label.clone()))
+                                on:mouseleave=move |_| hovered.set(None)
+                            />
+                        }
+                    })
+                    .collect_view();
+                view! {
+                    <polyline points=points fill="none" stroke=color(si) stroke-width="2" />
+                    {dots}
+                }
+            })
+            .collect_view()
+            .into_any(),
+        _ => {
+            let k = spec.series.len() as f64;
+            let bar_w = group_w * 0.8 / k;
+            let zero = y(0.0);
+            spec.series
+                .iter()
+                .enumerate()
+                .flat_map(|(si, s)| s.data.iter().enumerate().map(move |(i, v)| (si, i, *v)))
+                .map(|(si, i, v)| {
+                    let x = MARGIN_LEFT + group_w * i as f64 + group_w * 0.1 + bar_w * si as f64;
+                    let (ry, rh) = if v >= 0.0 {
+                        (y(v), zero - y(v))
+                    } else {
+                        (zero, y(v) - zero)
+                    };
+                    let label = hover_label(spec, si, i);
+                    view! {
+                        <rect
+                            x=x y=ry width=bar_w height=rh.max(0.5) fill=color(si)
+                            on:mouseenter=move |_| hovered.set(Some(label.clone()))
+                            on:mouseleave=move |_| hovered.set(None)
+                        />
+                    }
+                })
+                .collect_view()
+                .into_any()
+        }
+    };
+
+    view! {
+        <g>{grid}</g>
+        <g>{marks}</g>
+        <g>{x_labels}</g>
+    }
+}
+
+fn pie_view(spec: &ChartSpec, hovered: RwSignal<Option<String>>) -> impl IntoView {
+    let values = &spec.series[0].data;
+    let cx = WIDTH / 2.0;
+    let cy = MARGIN_TOP + (HEIGHT - MARGIN_TOP - 8.0) / 2.0;
+    let r = (HEIGHT - MARGIN_TOP - 8.0) / 2.0;
+    let total: f64 = values.iter().sum();
+    pie_angles(values)
+        .into_iter()
+        .enumerate()
+        .filter(|(_, (a0, a1))| a1 > a0)
+        .map(|(i, (a0, a1))| {
+            let pct = if total > 0.0 {
+                values[i] / total * 100.0
+            } else {
+                0.0
+            };
+            let label = format!(
+                "{}: {} ({:.1}%)",
+                spec.labels[i],
+                format_value(values[i]),
+                pct
+            );
+            view! {
+                <path
+                    d=pie_slice_path(cx, cy, r, a0, a1)
+                    fill=color(i)
+                    stroke="#ffffff"
+                    stroke-width="1"
+                    on:mouseenter=move |_| hovered.set(Some(label.clone()))
+                    on:mouseleave=move |_| hovered.set(None)
+                />
+            }
+        })
+        .collect_view()
+}
+
+fn hover_label(spec: &ChartSpec, series: usize, index: usize) -> String {
+    let value = format_value(spec.series[series].data[index]);
+    match &spec.series[series].name {
+        Some(name) => format!("{} · {}: {}", name, spec.labels[index], value),
+        None => format!("{}: {}", spec.labels[index], value),
+    }
+}
+
+/// Rasterize the rendered SVG through an offscreen canvas and download it
+fn export_png(svg_id: &str, name: &str) -> Result<(), JsValue> {
+    let document = web_sys::window()
+        .and_then(|w| w.document())
+        .ok_or_else(|| JsValue::from_str("no document"))?;
+    let svg = document
+        .get_element_by_id(svg_id)
+        .ok_or_else(|| JsValue::from_str("chart not found"))?;
+    let markup = web_sys::XmlSerializer::new()?.serialize_to_string(&svg)?;
+    let src = format!(
+        "data:image/svg+xml;charset=utf-8,{}",
+        js_sys::encode_uri_component(&markup)
+    );
+
+    let scale = 2.0;
+    let canvas: web_sys::HtmlCanvasElement = document.create_element("canvas")?.dyn_into()?;
+    canvas.set_width((WIDTH * scale) as u32);
+    canvas.set_height((HEIGHT * scale) as u32);
+    let image = web_sys::HtmlImageElement::new()?;
+    let filename = format!(
+        "{}.png",
+        crate::utils::format::FormatUtils::to_safe_filename(name)
+    );
+
+    let img = image.clone();
+    // Freed once it runs, so exports do not pile up closures
+    let onload = Closure::once_into_js(move || {
+        let ctx = canvas
+            .get_context("2d")
+            .ok()
+            .flatten()
+            .and_then(|c| c.dyn_into::<web_sys::CanvasRenderingContext2d>().ok());
+        if let Some(ctx) = ctx {
+            ctx.set_fill_style_str("#ffffff");
+            ctx.fill_rect(0.0, 0.0, WIDTH * scale, HEIGHT * scale);
+            let _ = ctx.draw_image_with_html_image_element_and_dw_and_dh(
+                &img,
+                0.0,
+                0.0,
+                WIDTH * scale,
+                HEIGHT * scale,
+            );
+            match canvas.to_data_url_with_type("image/png") {
+                Ok(url) => {
+                    if let Err(e) = DownloadUtils::download_url(&filename, &url) {
+                        log::error!("Chart export failed: {}", e);
+                    }
+                }
+                Err(e) => log::error!("Chart export failed: {:?}", e),
+            }
+        }
+    });
+    image.set_onload(Some(onload.unchecked_ref()));
+    image.set_src(&src);
+    Ok(())
+}
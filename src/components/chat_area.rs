@@ -1,28 +1,70 @@
 use crate::advanced_graphrag::{HyDEConfig, HyDEEngine};
 use crate::components::ui_primitives::{Button, Input, ProgressBar};
-use crate::components::{input_area::InputArea, message_bubble::MessageBubble};
+use crate::components::{
+    conversation_search::jump_to_message,
+    input_area::InputArea,
+    message_bubble::{
+        CompareAction, EditAction, ForkAction, MessageBubble, PinAction, RegenerateAction,
+        ThreadAction,
+    },
+    message_thread::MessageThread,
+    source_panel::SourcePanel,
+};
+use crate::features::analytics::{topics, TopicsPanel};
+use crate::features::graphrag::knowledge_impact::{PREAMBLE_SNIPPETS, PREAMBLE_SNIPPET_CHARS};
+use crate::features::graphrag::preamble::{self, PreambleParts};
+use crate::features::graphrag::query_planning;
 use crate::features::graphrag::retrieval::Retriever;
+use crate::features::graphrag::GraphRAGPipeline;
+use crate::features::webllm::service;
 use crate::graphrag_config::{
     GraphRAGConfig, GraphRAGConfigManager, GraphRAGMetrics, PerformanceMetrics,
 };
-use crate::models::graphrag::RAGQuery;
-use crate::models::{Message, MessageMetadata, MessageRole, SourceAttribution};
-use crate::storage::ConversationStorage;
+use crate::models::graphrag::{RAGQuery, RAGResult, RetrievalTrace};
+use crate::models::webllm::{LLMModel, ModelStatus};
+use crate::models::{
+    ActivityCategory, AnswerStyle, Attachments, Collections, CompletionIssue, GenerationSettings,
+    HistoryPolicy, KnowledgeScope, LatencyBreakdown, Message, MessageMetadata, MessageRole,
+    OutputFormat, QueryPlan, RegenerateMode, RegenerateOptions, ReplyAttempt, SourceAttribution,
+    StyleCue, Task, CITED_SNIPPET_CHARS,
+};
+use crate::pagerank_reranking::{ResultSynthesizer, SynthesisConfig, SynthesisSource};
+use crate::state::{
+    CRMStateContext, EventBusContext, GraphRAGStateContext, TasksStateContext, WebLLMStateContext,
+};
+use crate::storage::{
+    tab_sync, BranchInfo, ConversationStorage, ThreadInfo, TieredCache, CONVERSATIONS_KEY,
+};
+use crate::utils::answer_style;
+use crate::utils::brainstorm::{self, BRAINSTORM_REPLIES};
+use crate::utils::compute_usage::TokenTotals;
+use crate::utils::confidence::{self, ReplyEvidence};
+use crate::utils::context_window::ContextManager;
+use crate::utils::datetime::{self, LocalNow};
+use crate::utils::download::DownloadUtils;
+use crate::utils::exporters::{CitationStyle, ConversationExporter};
+use crate::utils::format::FormatUtils;
+use crate::utils::generation::GenerationUtils;
 use crate::utils::icons::schedule_icon_render;
-use crate::utils::storage::StorageUtils;
-use crate::webllm_binding::{init_webllm_with_progress, send_message_to_llm};
+use crate::utils::json_output;
+use crate::utils::latency::{LatencyStage, RequestTrace};
+use crate::utils::prefill;
+use crate::utils::tasks::TaskExtractionUtils;
+use crate::utils::titling;
+use crate::utils::tools::{complete_with_tools, ToolCall, ToolContext, ToolRegistry};
+use crate::webllm_binding::{
+    engine_for, loaded_engine, send_message_to_llm, send_message_to_llm_streaming,
+};
 use gloo_timers::future::TimeoutFuture;
 use leptos::prelude::*;
 use leptos::task::spawn_local;
 use log::info;
-use wasm_bindgen::JsCast;
 
 #[component]
 pub fn ChatArea(
     knowledge_enabled: ReadSignal<bool>,
     set_knowledge_enabled: WriteSignal<bool>,
     set_status_message: WriteSignal<String>,
-    selected_llm: ReadSignal<String>,
     graphrag_config: Signal<GraphRAGConfig>,
     graphrag_metrics: Signal<GraphRAGMetrics>,
     graphrag_manager: GraphRAGConfigManager,
@@ -30,6 +72,8 @@ pub fn ChatArea(
     current_conversation_id: ReadSignal<Option<String>>,
     set_current_conversation_id: WriteSignal<Option<String>>,
     set_conversation_list_refresh: WriteSignal<u32>,
+    /// Token totals of the open conversation, for the status bar
+    set_conversation_tokens: WriteSignal<TokenTotals>,
 ) -> impl IntoView {
     // Existing state
     let (messages, set_messages) = signal(vec![Message::new(
@@ -38,48 +82,107 @@ pub fn ChatArea(
             .to_string(),
     )]);
 
+    Effect::new(move |_| {
+        set_conversation_tokens.set(messages.with(|m| TokenTotals::of_messages(m)));
+    });
+
     // Remove local storage state since it's now passed as props
 
     let (input_value, set_input_value) = signal(String::new());
     let (is_loading, set_is_loading) = signal(false);
+    // Partial assistant reply while tokens are streaming in
+    let streaming_text = RwSignal::new(None::<String>);
+    // Brainstorm mode, and the replies it produced until one is picked
+    let brainstorm_mode = RwSignal::new(false);
+    let brainstorm_pick = RwSignal::new(None::<BrainstormPick>);
+    // Activity feed (absent in isolated component tests)
+    let events = use_context::<EventBusContext>();
+    // Copy handle so the async send task can publish its retrieval result
+    let record_rag_result = use_context::<GraphRAGStateContext>()
+        .map(|ctx| Callback::new(move |result: RAGResult| ctx.record_result(result)));
 
     // Menu state
     let (menu_open, set_menu_open) = signal(false);
+    // Citation style used when saving the conversation as markdown
+    let (citation_style, set_citation_style) = signal(CitationStyle::default());
     let (show_delete_confirm, set_show_delete_confirm) = signal(false);
     let (show_rename_dialog, set_show_rename_dialog) = signal(false);
     let (conversation_title, set_conversation_title) = signal("Chat".to_string());
+    // Conversations sharing the current one's branch tree
+    let (branches, set_branches) = signal(Vec::<BranchInfo>::new());
+    // Side threads on this conversation's messages, and the one expanded
+    let (threads, set_threads) = signal(Vec::<ThreadInfo>::new());
+    let (threads_refresh, set_threads_refresh) = signal(0u32);
+    let open_thread = RwSignal::new(None::<String>);
     let (rename_input, set_rename_input) = signal(String::new());
+    // Topic overview, and the topic the transcript is filtered to
+    let (show_topics, set_show_topics) = signal(false);
+    let topic_filter = RwSignal::new(None::<String>);
 
     // System prompt UI state
     let (_show_edit_global_prompt, set_show_edit_global_prompt) = signal(false);
     let (show_edit_conv_prompt, set_show_edit_conv_prompt) = signal(false);
     let (_global_prompt_input, set_global_prompt_input) = signal(String::new());
     let (conv_prompt_input, set_conv_prompt_input) = signal(String::new());
+    let (conv_stops_input, set_conv_stops_input) = signal(String::new());
 
     // Cached prompts
     let (global_system_prompt, set_global_system_prompt) = signal(Option::<String>::None);
     let (conversation_system_prompt, set_conversation_system_prompt) =
         signal(Option::<String>::None);
+    // History sent with each prompt, and its edited value in the prompt dialog
+    let (history_policy, set_history_policy) = signal(HistoryPolicy::default());
+    let policy_input = RwSignal::new(HistoryPolicy::default());
+    // Reply format, and its edited value in the prompt dialog
+    let (output_format, set_output_format) = signal(OutputFormat::default());
+    let format_input = RwSignal::new(OutputFormat::default());
+    // Answer preferences, and their edited value in the prompt dialog
+    let (answer_style, set_answer_style) = signal(AnswerStyle::default());
+    let style_input = RwSignal::new(AnswerStyle::default());
+    // Documents retrieval is limited to, its edited value and the choices in the dialog
+    let (knowledge_scope, set_knowledge_scope) = signal(KnowledgeScope::default());
+    let scope_input = RwSignal::new(KnowledgeScope::default());
+    let scope_choices = RwSignal::new(ScopeChoices::default());
+
+    // Action item extraction
+    let (is_extracting, set_is_extracting) = signal(false);
+    let tasks_ctx = use_context::<TasksStateContext>();
+    let crm_ctx = use_context::<CRMStateContext>().unwrap_or_else(|| {
+        let crm = CRMStateContext::new();
+        crm.sync_with_other_tabs();
+        crm
+    });
 
-    // WebLLM state - using a simple boolean to track readiness
-    let (model_ready, set_model_ready) = signal(false);
-    let (loading_progress, set_loading_progress) = signal(0.0);
-    let (loading_text, set_loading_text) = signal("Initializing...".to_string());
+    // Model readiness comes from the WebLLM state. Models are loaded by the model
+    // picker (`service::init_model`), the only writer of the engine pool.
+    let webllm_ctx = use_context::<WebLLMStateContext>();
+    let webllm_state = webllm_ctx.clone().unwrap_or_default().state;
+    let model_ready =
+        Signal::derive(move || webllm_state.with(|s| s.model_status == ModelStatus::Ready));
+    let loading_progress =
+        Signal::derive(move || webllm_state.with(|s| s.initialization_progress as f64));
+    let loading_text = Signal::derive(move || {
+        webllm_state.with(|s| {
+            let name = s.current_model.as_ref().map(|m| m.name.as_str());
+            match (&s.model_status, name) {
+                (ModelStatus::Error { message }, _) => message.clone(),
+                (ModelStatus::NotInitialized, _) | (_, None) => {
+                    "Model not loaded: pick one in the sidebar".to_string()
+                }
+                (_, Some(name)) => format!("Loading {}...", name),
+            }
+        })
+    });
 
     // Derived percent for ProgressBar primitive (0-100)
     let progress_percent =
         Signal::derive(move || (loading_progress.get() * 100.0_f64).round() as u32);
 
-    // Store the engine in a static location using thread_local
-    use std::cell::RefCell;
-    thread_local! {
-        static WEBLLM_ENGINE: RefCell<Option<wasm_bindgen::JsValue>> = const { RefCell::new(None) };
-    }
-
     // Empty conversation cleanup is now handled in ConversationList
 
     // Function to load conversation history
     let load_conversation = move |conversation_id: String| {
+        topic_filter.set(None);
         if let Some(ref storage) = storage.get() {
             // Load conversation title from the list
             match storage.list_conversations() {
@@ -141,11 +244,81 @@ pub fn ChatArea(
             load_conversation(conversation_id);
         }
     });
+    // Another tab wrote to the conversations; a reply being generated here is not
+    // interrupted and saves on top
+    tab_sync::watch(&[CONVERSATIONS_KEY], move || {
+        if is_loading.get_untracked() {
+            return;
+        }
+        if let Some(conversation_id) = current_conversation_id.get_untracked() {
+            load_conversation(conversation_id);
+        }
+    });
+
+    // Reload the branch switcher when the conversation changes
+    Effect::new(move |_| {
+        let listed = match (storage.get(), current_conversation_id.get()) {
+            (Some(storage), Some(id)) => storage.list_branches(&id).unwrap_or_else(|e| {
+                log::error!("Failed to list branches: {:?}", e);
+                Vec::new()
+            }),
+            _ => Vec::new(),
+        };
+        set_branches.set(listed);
+    });
+
+    // Reload the side threads when the conversation changes or one is written to
+    Effect::new(move |_| {
+        threads_refresh.track();
+        let listed = match (storage.get(), current_conversation_id.get()) {
+            (Some(storage), Some(id)) => storage.list_threads(&id).unwrap_or_else(|e| {
+                log::error!("Failed to list threads: {:?}", e);
+                Vec::new()
+            }),
+            _ => Vec::new(),
+        };
+        set_threads.set(listed);
+    });
+    Effect::new(move |_| {
+        current_conversation_id.track();
+        open_thread.set(None);
+    });
+    let thread_changed = Callback::new(move |_: ()| set_threads_refresh.update(|n| *n += 1));
+    let thread_run = Callback::new(move |message_id: String| open_thread.set(Some(message_id)));
+
+    // "You discussed this before": past conversations resembling the first message
+    // being typed into a new chat, until dismissed
+    let (similar_dismissed, set_similar_dismissed) = signal(false);
+    Effect::new(move |_| {
+        current_conversation_id.track();
+        set_similar_dismissed.set(false);
+    });
+    let similar = Memo::new(move |_| {
+        if similar_dismissed.get()
+            || !GenerationUtils::load_settings()
+                .suggestions
+                .similar_conversations
+            || messages.with(|m| m.iter().any(|m| m.role == MessageRole::User))
+        {
+            return Vec::new();
+        }
+        let draft = input_value.get();
+        let current = current_conversation_id.get();
+        storage
+            .get()
+            .map(|s| {
+                s.similar_conversations(&draft, current.as_deref())
+                    .unwrap_or_else(|e| {
+                        log::error!("Similar conversation search failed: {:?}", e);
+                        Vec::new()
+                    })
+            })
+            .unwrap_or_default()
+    });
 
     // Load global prompt once and on demand
     Effect::new(move |_| {
-        // Attempt to load from localStorage
-        if let Ok(Some(p)) = StorageUtils::retrieve_local::<String>("global_system_prompt") {
+        if let Some(p) = TieredCache::get::<String>("global_system_prompt") {
             set_global_system_prompt.set(Some(p));
         }
     });
@@ -158,163 +331,224 @@ pub fn ChatArea(
             if let Ok(p) = storage.load_conversation_system_prompt(conv_id) {
                 set_conversation_system_prompt.set(p);
             }
+            set_history_policy.set(
+                storage
+                    .load_conversation_history_policy(conv_id)
+                    .unwrap_or_default(),
+            );
+            set_output_format.set(
+                storage
+                    .load_conversation_output_format(conv_id)
+                    .unwrap_or_default(),
+            );
+            set_answer_style.set(
+                storage
+                    .load_conversation_answer_style(conv_id)
+                    .unwrap_or_default(),
+            );
+            set_knowledge_scope.set(
+                storage
+                    .load_conversation_knowledge_scope(conv_id)
+                    .unwrap_or_default(),
+            );
         } else {
             set_conversation_system_prompt.set(None);
+            set_history_policy.set(HistoryPolicy::default());
+            set_output_format.set(OutputFormat::default());
+            set_answer_style.set(AnswerStyle::default());
+            set_knowledge_scope.set(KnowledgeScope::default());
         }
     });
 
+    // "Sending 14 of 52 messages" under the input when the policy leaves some out
+    let history_note = Signal::derive(move || {
+        let settings = GenerationUtils::load_settings();
+        let manager = ContextManager::with_policy(&settings, history_policy.get());
+        messages.with(|m| manager.preview(m).describe())
+    });
+
     // Create initial conversation if none exists
     Effect::new(move |_| {
         if storage.get().is_some() && current_conversation_id.get().is_none() {
             if let Some(ref storage) = storage.get() {
-                match storage.create_conversation("New Chat".to_string()) {
+                match storage.create_conversation(titling::DEFAULT_TITLE.to_string()) {
                     Ok(conversation_id) => {
                         set_current_conversation_id.set(Some(conversation_id));
                         // Don't refresh conversation list yet - wait for first user message
                         info!("Initial conversation created (not yet in history)");
+                        if let Some(bus) = events {
+                            bus.record(ActivityCategory::Conversation, "Conversation created");
+                        }
                     }
                     Err(e) => {
                         log::error!("Failed to create initial conversation: {:?}", e);
+                        if let Some(bus) = events {
+                            bus.error("Failed to create conversation", format!("{:?}", e));
+                        }
                     }
                 }
             }
         }
     });
 
-    // Initialize WebLLM when component loads or model changes
-    Effect::new(move |_| {
-        let current_model = selected_llm.get();
-        spawn_local(async move {
-            set_model_ready.set(false);
-            set_loading_progress.set(0.0);
-            set_loading_text.set("Initializing...".to_string());
-            set_status_message.set("Loading model...".to_string());
-            info!("Initializing WebLLM with model: {}", current_model);
-
-            // Create progress callback
-            let progress_callback = move |text: String, progress: f64| {
-                set_loading_text.set(text.clone());
-                set_loading_progress.set(progress);
-                set_status_message.set(format!("{} ({:.1}%)", text, progress * 100.0));
-            };
-
-            match init_webllm_with_progress(&current_model, progress_callback).await {
-                Ok(engine) => {
-                    info!(
-                        "WebLLM initialized successfully with model: {}",
-                        current_model
-                    );
-                    WEBLLM_ENGINE.with(|e| {
-                        *e.borrow_mut() = Some(engine);
-                    });
-                    set_model_ready.set(true);
-                    set_loading_progress.set(1.0);
-                    set_loading_text.set("- Completed".to_string());
-                    set_status_message.set("- Ready".to_string());
+    // Mirror the model status in the status line and the activity feed
+    Effect::new(move |prev: Option<ModelStatus>| {
+        let (status, model) = webllm_state.with(|s| {
+            let model = s.current_model.as_ref().map(|m| m.id.clone());
+            (s.model_status.clone(), model.unwrap_or_default())
+        });
+        let changed = prev
+            .as_ref()
+            .is_some_and(|p| std::mem::discriminant(p) != std::mem::discriminant(&status));
+        match &status {
+            ModelStatus::NotInitialized => {
+                set_status_message.set("Model not loaded: pick one in the sidebar".to_string());
+            }
+            ModelStatus::Downloading { progress, .. } | ModelStatus::Loading { progress } => {
+                set_status_message.set(format!("Loading {}... ({:.1}%)", model, progress * 100.0));
+            }
+            ModelStatus::Ready => {
+                set_status_message.set("- Ready".to_string());
+                if let Some(bus) = events.filter(|_| changed) {
+                    bus.record(ActivityCategory::Model, format!("Model loaded: {}", model));
                 }
-                Err(e) => {
-                    log::error!("WebLLM initialization error: {:?}", e);
-                    set_loading_text.set("Error".to_string());
-                    set_status_message.set("Model loading error".to_string());
+            }
+            ModelStatus::Error { message } => {
+                set_status_message.set("Model loading error".to_string());
+                if let Some(bus) = events.filter(|_| changed) {
+                    bus.error(format!("Model failed to load: {}", model), message.clone());
                 }
             }
-        });
+        }
+        status
     });
 
-    // Send message function with WebLLM integration
-    let send_message_cb: std::rc::Rc<dyn Fn(leptos::ev::MouseEvent) + 'static> =
-        std::rc::Rc::new(move |_| {
-            let content = input_value.get();
-            if content.trim().is_empty() || is_loading.get() || !model_ready.get() {
-                return;
-            }
+    // Stage timings of the last retrieval, for the knowledge toggle's explanation
+    let last_query = Signal::derive({
+        let mgr = graphrag_manager.clone();
+        move || mgr.get_performance_metrics()
+    });
 
-            // Process with GraphRAG when knowledge is enabled (internal processing only)
+    // CRM records the lookup tool searches
+    let crm_for_tools = crm_ctx.clone();
+
+    // Runs a completion for a history ending with the prompt and places the reply
+    let generate_reply: std::rc::Rc<dyn Fn(ReplyRequest) + 'static> =
+        std::rc::Rc::new(move |request: ReplyRequest| {
+            let ReplyRequest {
+                history,
+                use_knowledge,
+                perf,
+                regenerate,
+                temperature_delta,
+                brainstorm,
+                model,
+            } = request;
             let cfg = graphrag_config.get();
-            let mut perf = PerformanceMetrics::default();
-
-            if knowledge_enabled.get() && cfg.hyde_enabled {
-                let t0 = js_sys::Date::now();
-                let hyde = HyDEEngine::new(HyDEConfig::default());
-                let _hypothetical_docs = hyde.generate_hypothetical_docs(&content);
-                // Note: HyDE docs are for internal search enhancement, not sent to LLM
-                let t1 = js_sys::Date::now();
-                perf.hyde_time_ms = (t1 - t0) as u32;
-            }
-
-            // Placeholders for other toggled phases (no-ops for now)
-            if knowledge_enabled.get() && cfg.community_detection_enabled {
-                // TODO: integrate CommunityDetectionEngine with a real graph implementing GraphAccess
-                perf.community_detection_time_ms = 0;
-            }
-            if knowledge_enabled.get() && cfg.pagerank_enabled {
-                // TODO: integrate PageRankEngine::score_nodes with a GraphAccess graph
-                perf.pagerank_time_ms = 0;
-            }
-            if knowledge_enabled.get() && cfg.reranking_enabled {
-                // TODO: integrate AdvancedReranker on candidate scores
-                perf.reranking_time_ms = 0;
-            }
-            if knowledge_enabled.get() && cfg.synthesis_enabled {
-                // TODO: integrate ResultSynthesizer on top snippets
-                perf.synthesis_time_ms = 0;
-            }
-
-            let user_message = Message::new(MessageRole::User, content.clone());
-            set_messages.update(|msgs| msgs.push(user_message.clone()));
-            set_input_value.set(String::new());
             set_is_loading.set(true);
             set_status_message.set("AI is thinking...".to_string());
 
-            // Save user message to storage
-            if let (Some(ref storage), Some(ref conv_id)) =
-                (storage.get(), current_conversation_id.get())
-            {
-                if let Err(e) = storage.save_message(conv_id, &user_message) {
-                    log::error!("Failed to save user message: {:?}", e);
-                } else {
-                    // Always refresh the conversation list when a user message is saved
-                    // This ensures the conversation appears in history immediately
-                    info!("User message saved, refreshing conversation list");
-                    set_conversation_list_refresh.update(|n| {
-                        let new_value = *n + 1;
-                        info!("Updated refresh signal to: {}", new_value);
-                        *n = new_value;
-                    });
-                }
-            }
-
-            // Re-render icons for new message
-            schedule_icon_render();
-
             if model_ready.get() {
                 let start_ms = js_sys::Date::now();
                 let mgr = graphrag_manager.clone();
                 let mut perf_local = perf.clone();
-                let current_messages = messages.get();
-                // Snapshot flags and prompt for async move
-                let use_knowledge = knowledge_enabled.get();
-                let prompt_text = content.clone();
-                let model_id = selected_llm.get();
-                // Snapshot prompts for async move (refresh global from localStorage to reflect sidebar edits)
-                let global_prompt_snapshot =
-                    StorageUtils::retrieve_local::<String>("global_system_prompt")
-                        .ok()
-                        .flatten()
-                        .or_else(|| global_system_prompt.get());
+                let current_messages = history;
+                // Snapshot the prompt for async move
+                let prompt_text = current_messages
+                    .iter()
+                    .rev()
+                    .find(|m| m.role == MessageRole::User)
+                    .map(|m| m.content.clone())
+                    .unwrap_or_default();
+                // Snapshot prompts for async move (re-read the global one to reflect sidebar edits)
+                let global_prompt_snapshot = TieredCache::get::<String>("global_system_prompt")
+                    .or_else(|| global_system_prompt.get());
                 let conv_prompt_snapshot = conversation_system_prompt.get();
+                let history_policy_snapshot = history_policy.get();
+                // Global generation settings merged with this conversation's stop sequences
+                let conversation_snapshot = current_conversation_id.get();
+                let conv_stops = match (storage.get(), conversation_snapshot.clone()) {
+                    (Some(s), Some(id)) => {
+                        s.load_conversation_stop_sequences(&id).unwrap_or_default()
+                    }
+                    _ => Vec::new(),
+                };
+                let output_format_snapshot = output_format.get();
+                let scope_snapshot = knowledge_scope.get();
+                // Style requests the user keeps making become preferences of the conversation
+                let mut answer_style_snapshot = answer_style.get();
+                if regenerate.is_none() {
+                    let learned = answer_style::observe(&mut answer_style_snapshot, &prompt_text);
+                    if answer_style_snapshot != answer_style.get_untracked() {
+                        if let (Some(s), Some(id)) = (storage.get(), conversation_snapshot.as_ref())
+                        {
+                            let _ = s.update_conversation_answer_style(
+                                id,
+                                answer_style_snapshot.clone(),
+                            );
+                        }
+                        set_answer_style.set(answer_style_snapshot.clone());
+                    }
+                    if let (Some(bus), false) = (events, learned.is_empty()) {
+                        let labels: Vec<&str> = learned.iter().map(|c| c.label()).collect();
+                        bus.record(
+                            ActivityCategory::Conversation,
+                            format!("Answer style noted: {}", labels.join(", ")),
+                        );
+                    }
+                }
+                let mut generation_settings = GenerationUtils::load_settings()
+                    .with_conversation_stops(&conv_stops)
+                    .with_output_format(&output_format_snapshot);
+                if temperature_delta != 0.0 {
+                    let temperature = &mut generation_settings.sampling.temperature;
+                    *temperature = (*temperature + temperature_delta).clamp(0.0, 2.0);
+                }
                 // Use configured search strategy
                 let strategy_to_use = cfg.search_strategy;
+                // Tools offered to the model, with the app data they read
+                let tool_registry = ToolRegistry::new(&generation_settings.tools);
+                let tool_context = ToolContext {
+                    search_strategy: strategy_to_use.clone(),
+                    timezone: generation_settings.datetime.timezone.clone(),
+                    customers: if tool_registry.is_empty() {
+                        Vec::new()
+                    } else {
+                        crm_for_tools.customers_now()
+                    },
+                    leads: if tool_registry.is_empty() {
+                        Vec::new()
+                    } else {
+                        crm_for_tools.leads_now()
+                    },
+                };
 
-                spawn_local(async move {
-                    // Get the engine from thread local storage
-                    let engine_opt = WEBLLM_ENGINE.with(|e| e.borrow().clone());
+                // Set by the first streamed token of the reply
+                let first_token_at = StoredValue::new(None::<f64>);
 
-                    if let Some(engine) = engine_opt {
+                spawn_local(async move {
+                    // Spans of this request, shown as the reply's latency breakdown
+                    let mut trace = RequestTrace::new();
+                    let mut queued_since = start_ms;
+                    // Get the engine of the loaded model, or of the model asked for
+                    let engine_opt = match &model {
+                        Some(id) => engine_for(id).map(|engine| (id.clone(), engine)),
+                        None => loaded_engine(),
+                    };
+
+                    if let Some((model_id, engine)) = engine_opt {
                         // Optionally run GraphRAG retrieval and inject system preamble
                         let mut provenance: Option<Vec<SourceAttribution>> = None;
+                        let mut retrieval_note: Option<String> = None;
+                        let mut query_plan: Option<QueryPlan> = None;
+                        let mut retrieval_trace: Option<RetrievalTrace> = None;
                         // Start with any system prompts (global, per-conversation)
                         let mut sys_msgs: Vec<Message> = Vec::new();
+                        if let Some(line) =
+                            datetime::system_context(&generation_settings.datetime, &prompt_text)
+                        {
+                            sys_msgs.push(Message::new(MessageRole::System, line));
+                        }
                         if let Some(ref gp) = global_prompt_snapshot {
                             if !gp.trim().is_empty() {
                                 sys_msgs.push(Message::new(MessageRole::System, gp.clone()));
@@ -325,36 +559,146 @@ pub fn ChatArea(
                                 sys_msgs.push(Message::new(MessageRole::System, cp.clone()));
                             }
                         }
+                        sys_msgs.extend(tool_registry.system_message());
+                        if let Some(line) = json_output::instruction(&output_format_snapshot) {
+                            sys_msgs.push(Message::new(MessageRole::System, line));
+                        }
+                        if let Some(line) = answer_style::instruction(&answer_style_snapshot) {
+                            sys_msgs.push(Message::new(MessageRole::System, line));
+                        }
 
-                        let augmented_messages = if use_knowledge {
+                        let system_messages = if use_knowledge {
                             // Build a minimal RAG query from prompt and current toggles
                             let mut q = RAGQuery::new(prompt_text.clone());
                             q.config.max_results = 5;
                             q.config.use_hyde = cfg.hyde_enabled;
                             q.config.use_community_detection = cfg.community_detection_enabled;
                             q.config.use_reranking = cfg.reranking_enabled;
+                            q.filters.conversation_id = conversation_snapshot.clone();
+                            q.filters.scope = scope_snapshot.clone();
 
                             let retriever = Retriever::new();
-                            let rag_result = retriever.search(&q, strategy_to_use).await;
-
-                            // Compose a short system preamble from summary + top snippets
-                            let mut preamble = String::new();
-                            if let Some(summary) = rag_result.metadata.summary.clone() {
-                                preamble.push_str("Knowledge summary: ");
-                                preamble.push_str(&summary);
-                                preamble.push_str("\n\n");
-                            }
-                            if !rag_result.nodes.is_empty() {
-                                preamble.push_str("Top snippets:\n");
-                                for n in rag_result.nodes.iter().take(3) {
-                                    let mut snip = n.content.clone();
-                                    if snip.len() > 300 {
-                                        snip.truncate(300);
+                            let retrieval_start = js_sys::Date::now();
+                            // Multi-part questions are searched one part at a time
+                            let sub_queries = if cfg.query_decomposition_enabled
+                                && query_planning::needs_planning(&prompt_text)
+                            {
+                                set_status_message.set("Planning the search...".to_string());
+                                query_planning::plan_query(&engine, &prompt_text).await
+                            } else {
+                                None
+                            };
+                            let rag_result = match sub_queries {
+                                Some(sub_queries) => {
+                                    let mut results = Vec::new();
+                                    for text in sub_queries {
+                                        let mut sub = q.clone();
+                                        sub.text = text.clone();
+                                        results.push((
+                                            text,
+                                            retriever.search(&sub, strategy_to_use.clone()).await,
+                                        ));
                                     }
-                                    preamble.push_str("- ");
-                                    preamble.push_str(&snip);
-                                    preamble.push('\n');
+                                    let (merged, plan) = query_planning::merge_results(
+                                        results,
+                                        q.config.max_results,
+                                    );
+                                    query_plan = Some(plan);
+                                    set_status_message.set("AI is thinking...".to_string());
+                                    merged
                                 }
+                                None => retriever.search(&q, strategy_to_use).await,
+                            };
+                            queued_since = js_sys::Date::now();
+                            trace.record(LatencyStage::Queue, start_ms, retrieval_start);
+                            trace.record(LatencyStage::Retrieval, retrieval_start, queued_since);
+                            // Lets the graph view highlight what this answer was built from
+                            if let Some(record) = record_rag_result {
+                                record.run(rag_result.clone());
+                            }
+                            // Keep the stage timings the retriever measured
+                            perf_local = perf_local
+                                .with_retrieval_stages(&mgr.get_performance_metrics_untracked());
+                            retrieval_note = rag_result
+                                .metadata
+                                .below_threshold
+                                .as_ref()
+                                .map(|b| b.explanation());
+
+                            // Optionally let the model write a cited summary of the top chunks
+                            let synthesizer = ResultSynthesizer::new(SynthesisConfig {
+                                abstractive: cfg.synthesis_enabled
+                                    && cfg.abstractive_synthesis_enabled,
+                                ..SynthesisConfig::default()
+                            });
+                            let sources: Vec<SynthesisSource> = rag_result
+                                .nodes
+                                .iter()
+                                .take(synthesizer.config.max_sources)
+                                .map(|n| SynthesisSource {
+                                    title: n
+                                        .metadata
+                                        .source
+                                        .clone()
+                                        .unwrap_or_else(|| "Untitled source".to_string()),
+                                    content: n.content.chars().take(CITED_SNIPPET_CHARS).collect(),
+                                })
+                                .collect();
+                            let abstractive = if synthesizer.config.abstractive
+                                && !sources.is_empty()
+                            {
+                                set_status_message.set("Summarizing sources...".to_string());
+                                let t_s0 = js_sys::Date::now();
+                                let summary = synthesizer
+                                    .synthesize_abstractive(&engine, &prompt_text, &sources)
+                                    .await;
+                                // Added to the extractive summary the retriever timed
+                                perf_local.synthesis_time_ms += (js_sys::Date::now() - t_s0) as u32;
+                                set_status_message.set("AI is thinking...".to_string());
+                                summary
+                            } else {
+                                None
+                            };
+
+                            // Fill the configured preamble template; the cited summary
+                            // stands in for the quoted snippets
+                            let quoted = match &abstractive {
+                                Some(_) => sources.len(),
+                                None => sources.len().min(PREAMBLE_SNIPPETS),
+                            };
+                            let parts = PreambleParts {
+                                summary: abstractive
+                                    .clone()
+                                    .or_else(|| rag_result.metadata.summary.clone())
+                                    .unwrap_or_default(),
+                                snippets: match &abstractive {
+                                    Some(_) => Vec::new(),
+                                    None => sources[..quoted]
+                                        .iter()
+                                        .map(|s| {
+                                            s.content.chars().take(PREAMBLE_SNIPPET_CHARS).collect()
+                                        })
+                                        .collect(),
+                                },
+                                citations: sources[..quoted]
+                                    .iter()
+                                    .map(|s| s.title.clone())
+                                    .collect(),
+                                date: LocalNow::current(
+                                    generation_settings.datetime.timezone.as_deref(),
+                                )
+                                .date
+                                .iso(),
+                            };
+                            let preamble = preamble::render(&cfg.preamble_template, &parts);
+                            retrieval_trace = rag_result.metadata.trace.clone().map(|mut trace| {
+                                trace.prompt_chunks = rag_result.nodes[..quoted]
+                                    .iter()
+                                    .map(|n| n.id.clone())
+                                    .collect();
+                                trace
+                            });
+                            if !rag_result.nodes.is_empty() {
                                 // Build provenance from top results
                                 let mut attrs: Vec<SourceAttribution> = Vec::new();
                                 for n in rag_result.nodes.iter().take(5) {
@@ -367,6 +711,9 @@ pub fn ChatArea(
                                         source_id: n.id.clone(),
                                         title,
                                         confidence: n.metadata.confidence,
+                                        snippet: Some(
+                                            n.content.chars().take(CITED_SNIPPET_CHARS).collect(),
+                                        ),
                                     });
                                 }
                                 if !attrs.is_empty() {
@@ -374,27 +721,229 @@ pub fn ChatArea(
                                 }
                             }
 
-                            let mut aug =
-                                Vec::with_capacity(sys_msgs.len() + current_messages.len() + 1);
                             // system prompts first
-                            aug.extend(sys_msgs);
+                            let mut system = sys_msgs;
                             if !preamble.is_empty() {
-                                aug.push(Message::new(MessageRole::System, preamble));
+                                system.push(Message::new(MessageRole::System, preamble));
                             }
-                            aug.extend(current_messages.clone());
-                            aug
+                            system
                         } else {
-                            let mut aug =
-                                Vec::with_capacity(sys_msgs.len() + current_messages.len());
-                            aug.extend(sys_msgs);
-                            aug.extend(current_messages.clone());
-                            aug
+                            sys_msgs
                         };
 
-                        match send_message_to_llm(&engine, augmented_messages).await {
-                            Ok(response) => {
+                        // Fold older turns into a summary when the window is nearly full
+                        let context = ContextManager::with_policy(
+                            &generation_settings,
+                            history_policy_snapshot,
+                        );
+                        if context.plan(&system_messages, &current_messages).is_some() {
+                            set_status_message.set("Summarizing earlier messages...".to_string());
+                        }
+                        let fitted = context
+                            .fit(
+                                &engine,
+                                conversation_snapshot.as_deref(),
+                                system_messages,
+                                current_messages.clone(),
+                            )
+                            .await;
+                        if fitted.summarized > 0 || fitted.dropped > 0 {
+                            if let Some(bus) = events {
+                                bus.record(
+                                    ActivityCategory::Model,
+                                    format!(
+                                        "Context trimmed: {} message(s) summarized, {} dropped",
+                                        fitted.summarized, fitted.dropped
+                                    ),
+                                );
+                            }
+                            set_status_message.set("AI is thinking...".to_string());
+                        }
+                        let augmented_messages = fitted.messages;
+
+                        let on_delta = move |delta: &str| {
+                            if first_token_at.get_value().is_none() {
+                                first_token_at.set_value(Some(js_sys::Date::now()));
+                            }
+                            streaming_text.update(|t| {
+                                if let Some(t) = t {
+                                    t.push_str(delta);
+                                }
+                            })
+                        };
+                        // A tool call restarts the streamed text for the answer that follows
+                        let on_tool_call = move |call: &ToolCall| {
+                            set_status_message.set(format!("Using tool: {}...", call.name));
+                            streaming_text.set(Some(String::new()));
+                            if let Some(bus) = events {
+                                bus.record(
+                                    ActivityCategory::Model,
+                                    format!("Tool called: {}", call.name),
+                                );
+                            }
+                        };
+                        streaming_text.set(Some(String::new()));
+                        let sent_at = js_sys::Date::now();
+                        trace.record(LatencyStage::Queue, queued_since, sent_at);
+                        let run = complete_with_tools(
+                            &engine,
+                            augmented_messages,
+                            &generation_settings,
+                            &tool_registry,
+                            &tool_context,
+                            on_delta,
+                            on_tool_call,
+                        )
+                        .await;
+                        let mut streamed = run.result;
+                        // Tool results stay in the prompt of a retry
+                        let augmented_messages = run.messages;
+                        let tools_used: Vec<String> =
+                            run.outcomes.into_iter().map(|o| o.name).collect();
+
+                        // Empty or degenerate replies get one retry with cooler sampling
+                        let first_text = streamed.as_ref().ok().map(|(t, _)| t.clone());
+                        let issue = first_text.as_deref().and_then(|t| {
+                            GenerationUtils::validate_completion(
+                                t,
+                                &prompt_text,
+                                &generation_settings.validation,
+                            )
+                        });
+                        let mut retry_reason = None;
+                        if let Some(issue) = issue {
+                            let reason = issue.describe();
+                            log::warn!("Completion rejected ({}), retrying once", reason);
+                            set_status_message.set("Retrying...".to_string());
+                            streaming_text.set(Some(String::new()));
+                            let retry = send_message_to_llm_streaming(
+                                &engine,
+                                augmented_messages.clone(),
+                                &generation_settings.for_retry(),
+                                on_delta,
+                            )
+                            .await;
+                            // Both attempts ran on the GPU, so both count in the usage
+                            match retry {
+                                // Keep the first reply if the retry came back empty
+                                Ok((text, retry_usage))
+                                    if text.trim().is_empty()
+                                        && issue != CompletionIssue::Empty =>
+                                {
+                                    if let Ok((_, usage)) = &mut streamed {
+                                        usage.add_attempt(&retry_usage);
+                                    }
+                                }
+                                Ok((text, mut usage)) => {
+                                    if let Ok((_, first)) = &streamed {
+                                        usage.add_attempt(first);
+                                    }
+                                    streamed = Ok((text, usage));
+                                }
+                                Err(e) => log::warn!("Retry failed: {:?}", e),
+                            }
+                            if let Some(bus) = events {
+                                bus.record(
+                                    ActivityCategory::Model,
+                                    format!("Reply regenerated: {}", reason),
+                                );
+                            }
+                            retry_reason = Some(reason);
+                        }
+
+                        // A JSON reply that fails its check gets one repair round
+                        let mut structured_error = None;
+                        if output_format_snapshot.is_json() {
+                            if let Ok((text, _)) = &streamed {
+                                if let Err(error) =
+                                    json_output::check(text, &output_format_snapshot)
+                                {
+                                    log::warn!("JSON reply rejected ({}), repairing", error);
+                                    set_status_message.set("Repairing JSON...".to_string());
+                                    streaming_text.set(Some(String::new()));
+                                    let mut repair_messages = augmented_messages.clone();
+                                    repair_messages
+                                        .push(Message::new(MessageRole::Assistant, text.clone()));
+                                    repair_messages.push(json_output::repair_message(&error));
+                                    match send_message_to_llm_streaming(
+                                        &engine,
+                                        repair_messages,
+                                        &generation_settings,
+                                        on_delta,
+                                    )
+                                    .await
+                                    {
+                                        Ok(repaired) => streamed = Ok(repaired),
+                                        Err(e) => log::warn!("JSON repair failed: {:?}", e),
+                                    }
+                                    if let Ok((text, _)) = &streamed {
+                                        structured_error =
+                                            json_output::check(text, &output_format_snapshot).err();
+                                    }
+                                    if let Some(bus) = events {
+                                        bus.record(
+                                            ActivityCategory::Model,
+                                            format!("JSON reply repaired: {}", error),
+                                        );
+                                    }
+                                }
+                            }
+                        }
+
+                        // Brainstorm mode samples more replies for the user to pick from
+                        let mut alternatives: Vec<ReplyAttempt> = Vec::new();
+                        if brainstorm && streamed.is_ok() {
+                            let extra = brainstorm::variations(
+                                &generation_settings.sampling,
+                                BRAINSTORM_REPLIES - 1,
+                                js_sys::Date::now() as u32,
+                            );
+                            for (i, sampling) in extra.into_iter().enumerate() {
+                                set_status_message.set(format!(
+                                    "Brainstorming reply {} of {}...",
+                                    i + 2,
+                                    BRAINSTORM_REPLIES
+                                ));
+                                streaming_text.set(Some(String::new()));
+                                let temperature = sampling.temperature;
+                                let settings = GenerationSettings {
+                                    sampling,
+                                    ..generation_settings.clone()
+                                };
+                                match send_message_to_llm_streaming(
+                                    &engine,
+                                    augmented_messages.clone(),
+                                    &settings,
+                                    on_delta,
+                                )
+                                .await
+                                {
+                                    Ok((text, _)) if !text.trim().is_empty() => {
+                                        alternatives.push(ReplyAttempt {
+                                            content: text,
+                                            timestamp: js_sys::Date::now(),
+                                            model_used: Some(model_id.clone()),
+                                            temperature: Some(temperature),
+                                        })
+                                    }
+                                    Ok(_) => {}
+                                    Err(e) => log::warn!("Brainstorm reply failed: {:?}", e),
+                                }
+                            }
+                        }
+                        streaming_text.set(None);
+                        let done_at = js_sys::Date::now();
+                        match first_token_at.get_value() {
+                            Some(first) => {
+                                trace.record(LatencyStage::FirstToken, sent_at, first);
+                                trace.record(LatencyStage::Generation, first, done_at);
+                            }
+                            None => trace.record(LatencyStage::FirstToken, sent_at, done_at),
+                        }
+
+                        match streamed {
+                            Ok((response, usage)) => {
                                 let mut ai_message = Message::new(MessageRole::Assistant, response);
-                                set_messages.update(|msgs| msgs.push(ai_message.clone()));
                                 set_status_message.set("Ready".to_string());
                                 // Update GraphRAG metrics (elapsed time, keep memory placeholder)
                                 let elapsed = js_sys::Date::now() - start_ms;
@@ -404,32 +953,181 @@ pub fn ChatArea(
                                 mgr.update_performance_metrics(perf_local.clone());
 
                                 // Attach provenance and metadata to assistant message
+                                let tokens_used =
+                                    match (usage.prompt_tokens, usage.completion_tokens) {
+                                        (None, None) => None,
+                                        (p, c) => Some(p.unwrap_or(0) + c.unwrap_or(0)),
+                                    };
+                                let temperature = if retry_reason.is_some() {
+                                    generation_settings.for_retry().sampling.temperature
+                                } else {
+                                    generation_settings.sampling.temperature
+                                };
+                                let source_scores: Option<Vec<f32>> = use_knowledge.then(|| {
+                                    provenance.iter().flatten().map(|s| s.confidence).collect()
+                                });
+                                let confidence = confidence::calibrate(
+                                    &ReplyEvidence {
+                                        source_scores: source_scores.as_deref(),
+                                        retried: retry_reason.is_some(),
+                                        structured_error: structured_error.is_some(),
+                                        reply: &ai_message.content,
+                                    },
+                                    &generation_settings.confidence,
+                                );
                                 let md = MessageMetadata {
-                                    tokens_used: None,
+                                    tokens_used,
                                     processing_time_ms: Some(elapsed as u32),
                                     model_used: Some(model_id.clone()),
                                     graphrag_enhanced: use_knowledge,
-                                    error: None,
+                                    error: structured_error.clone(),
                                     provenance,
+                                    prompt_tokens: usage.prompt_tokens,
+                                    completion_tokens: usage.completion_tokens,
+                                    decode_tokens_per_sec: usage.decode_tokens_per_sec,
+                                    retry_reason,
+                                    retrieval_note,
+                                    temperature: Some(temperature),
+                                    // A regenerated reply keeps the ones it supersedes
+                                    attempts: regenerate
+                                        .as_ref()
+                                        .filter(|(_, mode)| *mode != RegenerateMode::Compare)
+                                        .map(|(previous, _)| previous.reply_attempts())
+                                        .unwrap_or_default(),
+                                    tools_used,
+                                    structured: output_format_snapshot.is_json()
+                                        && structured_error.is_none(),
+                                    latency: Some(LatencyBreakdown {
+                                        prefill_reused_tokens: usage.prefill_reused_tokens,
+                                        prefill_saved_ms: prefill::saved_ms(
+                                            usage.prefill_reused_tokens,
+                                            usage.prefill_tokens_per_sec,
+                                        ),
+                                        ..trace.breakdown()
+                                    }),
+                                    confidence: Some(confidence),
+                                    query_plan,
+                                    retrieval_trace,
+                                    compared: Vec::new(),
                                 };
                                 ai_message = ai_message.with_metadata(md);
 
-                                // Update the pushed message with metadata
+                                // A compared reply is kept beside the one it answers alongside
+                                if let Some((previous, RegenerateMode::Compare)) = &regenerate {
+                                    let mut kept = previous.clone();
+                                    if let Some(reply) = ai_message.reply_attempts().pop() {
+                                        kept.add_compared(reply);
+                                    }
+                                    ai_message = kept;
+                                }
+
+                                // Brainstormed replies are placed once the user picks one
+                                if !alternatives.is_empty() {
+                                    brainstorm_pick.set(Some(BrainstormPick {
+                                        conversation_id: conversation_snapshot.clone(),
+                                        reply: ai_message,
+                                        alternatives,
+                                    }));
+                                    set_status_message.set("Pick a reply".to_string());
+                                    set_is_loading.set(false);
+                                    schedule_icon_render();
+                                    return;
+                                }
+
+                                // Replace the regenerated reply in place, otherwise append
+                                let replaced_id = match &regenerate {
+                                    Some((
+                                        previous,
+                                        RegenerateMode::Replace | RegenerateMode::Compare,
+                                    )) => Some(previous.id.clone()),
+                                    _ => None,
+                                };
                                 set_messages.update(|msgs| {
-                                    if let Some(last) = msgs.last_mut() {
-                                        *last = ai_message.clone();
+                                    let slot = replaced_id
+                                        .as_ref()
+                                        .and_then(|id| msgs.iter().position(|m| &m.id == id));
+                                    match slot {
+                                        Some(i) => msgs[i] = ai_message.clone(),
+                                        None => msgs.push(ai_message.clone()),
                                     }
                                 });
+                                match (events, &regenerate) {
+                                    (Some(bus), Some((_, RegenerateMode::Compare))) => {
+                                        bus.record(
+                                            ActivityCategory::Model,
+                                            format!("Reply compared with {}", model_id),
+                                        );
+                                    }
+                                    (Some(bus), Some((previous, _))) => {
+                                        bus.record(
+                                            ActivityCategory::Model,
+                                            format!(
+                                                "Reply regenerated on request (attempt {})",
+                                                previous.reply_attempts().len() + 1
+                                            ),
+                                        );
+                                    }
+                                    _ => {}
+                                }
 
                                 // Save AI message to storage
                                 if let (Some(ref storage), Some(ref conv_id)) =
                                     (storage.get(), current_conversation_id.get())
                                 {
-                                    if let Err(e) = storage.save_message(conv_id, &ai_message) {
+                                    let saved = match &replaced_id {
+                                        Some(id) => {
+                                            storage.replace_message(conv_id, id, &ai_message)
+                                        }
+                                        None => storage.save_message(conv_id, &ai_message),
+                                    };
+                                    if let Err(e) = saved {
                                         log::error!("Failed to save AI message: {:?}", e);
                                     } else {
                                         set_conversation_list_refresh.update(|n| *n += 1);
                                     }
+
+                                    // Name the conversation after its first exchange
+                                    let title = storage
+                                        .list_conversations()
+                                        .ok()
+                                        .and_then(|list| {
+                                            list.into_iter().find(|c| &c.id == conv_id)
+                                        })
+                                        .map(|c| c.title)
+                                        .unwrap_or_default();
+                                    if regenerate.is_none()
+                                        && messages
+                                            .with_untracked(|m| titling::needs_title(&title, m))
+                                    {
+                                        set_status_message
+                                            .set("Naming conversation...".to_string());
+                                        let request = titling::title_request(
+                                            &prompt_text,
+                                            &ai_message.content,
+                                        );
+                                        let raw = send_message_to_llm(&engine, request)
+                                            .await
+                                            .unwrap_or_default();
+                                        let title = titling::clean_title(&raw, &prompt_text);
+                                        match storage
+                                            .update_conversation_title(conv_id, title.clone())
+                                        {
+                                            Ok(()) => {
+                                                set_conversation_title.set(title.clone());
+                                                set_conversation_list_refresh.update(|n| *n += 1);
+                                                if let Some(bus) = events {
+                                                    bus.record(
+                                                        ActivityCategory::Conversation,
+                                                        format!("Conversation named \"{}\"", title),
+                                                    );
+                                                }
+                                            }
+                                            Err(e) => {
+                                                log::error!("Failed to save title: {:?}", e)
+                                            }
+                                        }
+                                        set_status_message.set("Ready".to_string());
+                                    }
                                 }
 
                                 // Re-render icons for AI response
@@ -437,12 +1135,18 @@ pub fn ChatArea(
                             }
                             Err(e) => {
                                 log::error!("AI response error: {:?}", e);
-                                let error_message = Message::new(
-                                    MessageRole::Assistant,
-                                    "Sorry, I had a problem responding. Please try again."
-                                        .to_string(),
-                                );
-                                set_messages.update(|msgs| msgs.push(error_message));
+                                if let Some(bus) = events {
+                                    bus.error("Model failed to respond", format!("{:?}", e));
+                                }
+                                // A failed regeneration keeps the reply it was replacing
+                                if regenerate.is_none() {
+                                    let error_message = Message::new(
+                                        MessageRole::Assistant,
+                                        "Sorry, I had a problem responding. Please try again."
+                                            .to_string(),
+                                    );
+                                    set_messages.update(|msgs| msgs.push(error_message));
+                                }
                                 set_status_message.set("AI Error".to_string());
                                 // Record failed attempt time as well
                                 let elapsed = js_sys::Date::now() - start_ms;
@@ -498,6 +1202,391 @@ pub fn ChatArea(
             }
         });
 
+    // Store the brainstormed reply the user picked, the others kept as earlier replies
+    let pick_reply = Callback::new(move |index: usize| {
+        let Some(pick) = brainstorm_pick.get_untracked() else {
+            return;
+        };
+        brainstorm_pick.set(None);
+        let count = pick.alternatives.len() + 1;
+        let conversation_id = pick.conversation_id.clone();
+        let reply = pick.choose(index);
+        if current_conversation_id.get_untracked() == conversation_id {
+            set_messages.update(|msgs| msgs.push(reply.clone()));
+        }
+        if let (Some(storage), Some(conv_id)) = (storage.get_untracked(), conversation_id) {
+            match storage.save_message(&conv_id, &reply) {
+                Ok(()) => set_conversation_list_refresh.update(|n| *n += 1),
+                Err(e) => log::error!("Failed to save AI message: {:?}", e),
+            }
+        }
+        if let Some(bus) = events {
+            bus.record(
+                ActivityCategory::Model,
+                format!("Brainstorm: reply {} of {} kept", index + 1, count),
+            );
+        }
+        set_status_message.set("Ready".to_string());
+        schedule_icon_render();
+    });
+
+    // Knowledge base chosen in the knowledge popover: one collection, or everything
+    let select_collection = Callback::new(move |collection_id: Option<String>| {
+        let scope = collection_id
+            .as_deref()
+            .map(KnowledgeScope::only)
+            .unwrap_or_default();
+        if let (Some(storage), Some(conv_id)) = (
+            storage.get_untracked(),
+            current_conversation_id.get_untracked(),
+        ) {
+            if let Err(e) = storage.update_conversation_knowledge_scope(&conv_id, scope.clone()) {
+                log::error!("Failed to save knowledge scope: {:?}", e);
+            }
+        }
+        let searched = collection_id
+            .and_then(|id| {
+                Collections::load()
+                    .unwrap_or_default()
+                    .collections
+                    .into_iter()
+                    .find(|c| c.id == id)
+            })
+            .map(|c| format!("Knowledge base: {}", c.name))
+            .unwrap_or_else(|| "Knowledge base: all documents".to_string());
+        set_status_message.set(searched);
+        set_knowledge_scope.set(scope);
+    });
+
+    // Send message function with WebLLM integration
+    let send_message_cb: std::rc::Rc<dyn Fn(leptos::ev::MouseEvent) + 'static> = {
+        let generate_reply = generate_reply.clone();
+        std::rc::Rc::new(move |_| {
+            let content = input_value.get();
+            if content.trim().is_empty() || is_loading.get() || !model_ready.get() {
+                return;
+            }
+            // Moving on without picking keeps the first brainstormed reply
+            if brainstorm_pick.with_untracked(|p| p.is_some()) {
+                pick_reply.run(0);
+            }
+
+            // Process with GraphRAG when knowledge is enabled (internal processing only)
+            let cfg = graphrag_config.get();
+            let mut perf = PerformanceMetrics::default();
+
+            if knowledge_enabled.get() && cfg.hyde_enabled {
+                let t0 = js_sys::Date::now();
+                let hyde = HyDEEngine::new(HyDEConfig::default());
+                let _hypothetical_docs = hyde.generate_hypothetical_docs(&content);
+                // Note: HyDE docs are for internal search enhancement, not sent to LLM
+                let t1 = js_sys::Date::now();
+                perf.hyde_time_ms = (t1 - t0) as u32;
+            }
+
+            // Community detection, PageRank weighting, reranking and the extractive
+            // summary run and are timed inside Retriever::search.
+
+            let user_message = Message::new(MessageRole::User, content.clone());
+            set_messages.update(|msgs| msgs.push(user_message.clone()));
+            set_input_value.set(String::new());
+
+            // Save user message to storage
+            if let (Some(ref storage), Some(ref conv_id)) =
+                (storage.get(), current_conversation_id.get())
+            {
+                if let Err(e) = storage.save_message(conv_id, &user_message) {
+                    log::error!("Failed to save user message: {:?}", e);
+                } else {
+                    // Always refresh the conversation list when a user message is saved
+                    // This ensures the conversation appears in history immediately
+                    info!("User message saved, refreshing conversation list");
+                    set_conversation_list_refresh.update(|n| {
+                        let new_value = *n + 1;
+                        info!("Updated refresh signal to: {}", new_value);
+                        *n = new_value;
+                    });
+                }
+            }
+
+            // Re-render icons for new message
+            schedule_icon_render();
+
+            generate_reply(ReplyRequest {
+                history: messages.get(),
+                use_knowledge: knowledge_enabled.get(),
+                perf,
+                regenerate: None,
+                temperature_delta: 0.0,
+                brainstorm: brainstorm_mode.get(),
+                model: None,
+            });
+        })
+    };
+
+    // Regenerate the last assistant reply from the history it answered
+    let regenerate_reply = {
+        let generate_reply = generate_reply.clone();
+        move |options: RegenerateOptions| {
+            if is_loading.get_untracked() || !model_ready.get_untracked() {
+                return;
+            }
+            let current = messages.get_untracked();
+            let Some(pos) = current
+                .iter()
+                .rposition(|m| m.role == MessageRole::Assistant)
+            else {
+                return;
+            };
+            let history = current[..pos].to_vec();
+            // The welcome message answers no prompt
+            if !history.iter().any(|m| m.role == MessageRole::User) {
+                return;
+            }
+            let previous = current[pos].clone();
+            let use_knowledge = previous
+                .metadata
+                .as_ref()
+                .map(|m| m.graphrag_enhanced)
+                .unwrap_or_else(|| knowledge_enabled.get_untracked());
+            generate_reply(ReplyRequest {
+                history,
+                use_knowledge,
+                perf: PerformanceMetrics::default(),
+                regenerate: Some((previous, options.mode)),
+                temperature_delta: options.temperature_delta,
+                brainstorm: false,
+                model: None,
+            });
+        }
+    };
+    // Copy handle for the message list rows
+    let regenerate_fn = StoredValue::new_local(regenerate_reply);
+    let regenerate_run =
+        Callback::new(move |options: RegenerateOptions| regenerate_fn.with_value(|f| f(options)));
+
+    // Ask another model the prompt of the last reply; its answer is shown beside it.
+    // A model that is not loaded is loaded warm, so the chat keeps its own.
+    let compare_reply = {
+        let generate_reply = generate_reply.clone();
+        let webllm_ctx = webllm_ctx.clone();
+        move |model_id: String| {
+            if is_loading.get_untracked() || !model_ready.get_untracked() {
+                return;
+            }
+            let current = messages.get_untracked();
+            let Some(pos) = current
+                .iter()
+                .rposition(|m| m.role == MessageRole::Assistant)
+            else {
+                return;
+            };
+            let history = current[..pos].to_vec();
+            if !history.iter().any(|m| m.role == MessageRole::User) {
+                return;
+            }
+            let previous = current[pos].clone();
+            let use_knowledge = previous
+                .metadata
+                .as_ref()
+                .map(|m| m.graphrag_enhanced)
+                .unwrap_or_else(|| knowledge_enabled.get_untracked());
+            let Some(ctx) = webllm_ctx.clone() else {
+                return;
+            };
+            let generate_reply = generate_reply.clone();
+            spawn_local(async move {
+                if engine_for(&model_id).is_none() {
+                    let model = ctx
+                        .get_available_models()
+                        .into_iter()
+                        .find(|m| m.id == model_id)
+                        .unwrap_or_else(|| {
+                            LLMModel::new(
+                                model_id.clone(),
+                                model_id.clone(),
+                                "WebLLM".to_string(),
+                                String::new(),
+                            )
+                        });
+                    set_is_loading.set(true);
+                    set_status_message.set(format!("Loading {}...", model_id));
+                    let progress_callback = move |text: String, progress: f64| {
+                        set_status_message.set(format!("{} ({:.1}%)", text, progress * 100.0));
+                    };
+                    // Admitted like a pre-warm, so it never crowds out the chat model
+                    let loaded = service::load_alongside(&ctx, &model, progress_callback).await;
+                    set_is_loading.set(false);
+                    if let Err(e) = loaded {
+                        log::error!("Failed to load {} for comparison: {}", model_id, e);
+                        set_status_message.set(format!("Cannot compare: {}", e));
+                        if let Some(bus) = events {
+                            bus.error(format!("Model failed to load: {}", model_id), e);
+                        }
+                        return;
+                    }
+                }
+                generate_reply(ReplyRequest {
+                    history,
+                    use_knowledge,
+                    perf: PerformanceMetrics::default(),
+                    regenerate: Some((previous, RegenerateMode::Compare)),
+                    temperature_delta: 0.0,
+                    brainstorm: false,
+                    model: Some(model_id),
+                });
+            });
+        }
+    };
+    let compare_fn = StoredValue::new_local(compare_reply);
+    let compare_run = Callback::new(move |model_id: String| compare_fn.with_value(|f| f(model_id)));
+    // Models a reply can be compared with: those this device runs, but the chat's own
+    let compare_models = Signal::derive(move || {
+        let Some(ctx) = webllm_ctx.as_ref() else {
+            return Vec::new();
+        };
+        let active = loaded_engine().map(|(id, _)| id);
+        let models = ctx.get_available_models();
+        let runnable = match ctx.get_capabilities() {
+            Some(device) => device.compatible(&models),
+            None => models,
+        };
+        runnable
+            .into_iter()
+            .filter(|m| Some(&m.id) != active.as_ref())
+            .map(|m| (m.id, m.name))
+            .collect::<Vec<_>>()
+    });
+
+    // Replace a user message, drop everything after it and answer it again
+    let edit_message = {
+        let generate_reply = generate_reply.clone();
+        move |(message_id, content): (String, String)| {
+            if content.trim().is_empty()
+                || is_loading.get_untracked()
+                || !model_ready.get_untracked()
+            {
+                return;
+            }
+            let current = messages.get_untracked();
+            let Some(pos) = current
+                .iter()
+                .position(|m| m.id == message_id && m.role == MessageRole::User)
+            else {
+                return;
+            };
+            if current[pos].content == content {
+                return;
+            }
+            // A fresh id re-renders the row
+            let edited = Message::new(MessageRole::User, content);
+            let mut history = current[..pos].to_vec();
+            history.push(edited.clone());
+            let dropped = current.len() - pos - 1;
+            set_messages.set(history.clone());
+
+            if let (Some(ref storage), Some(ref conv_id)) = (
+                storage.get_untracked(),
+                current_conversation_id.get_untracked(),
+            ) {
+                if let Err(e) = storage.truncate_at(conv_id, &message_id, &edited) {
+                    log::error!("Failed to save edited message: {:?}", e);
+                    if let Some(bus) = events {
+                        bus.error("Failed to save edited message", format!("{:?}", e));
+                    }
+                } else {
+                    set_conversation_list_refresh.update(|n| *n += 1);
+                }
+            }
+            if let Some(bus) = events {
+                bus.record(
+                    ActivityCategory::Conversation,
+                    format!("Message edited, {} later message(s) removed", dropped),
+                );
+            }
+            schedule_icon_render();
+
+            generate_reply(ReplyRequest {
+                history,
+                use_knowledge: knowledge_enabled.get_untracked(),
+                perf: PerformanceMetrics::default(),
+                regenerate: None,
+                temperature_delta: 0.0,
+                brainstorm: brainstorm_mode.get_untracked(),
+                model: None,
+            });
+        }
+    };
+    let edit_fn = StoredValue::new_local(edit_message);
+    let edit_run = Callback::new(move |edit: (String, String)| edit_fn.with_value(|f| f(edit)));
+
+    // Source shown in the side panel after clicking a citation chip
+    let open_source = RwSignal::new(None::<SourceAttribution>);
+    let open_source_run =
+        Callback::new(move |source: SourceAttribution| open_source.set(Some(source)));
+
+    // Continue the conversation up to a message in a new branch of the same tree
+    let fork_run = Callback::new(move |message_id: String| {
+        if is_loading.get_untracked() {
+            return;
+        }
+        let (Some(storage), Some(conv_id)) = (
+            storage.get_untracked(),
+            current_conversation_id.get_untracked(),
+        ) else {
+            return;
+        };
+        match storage.fork_conversation(&conv_id, &message_id) {
+            Ok(branch_id) => {
+                set_current_conversation_id.set(Some(branch_id));
+                set_conversation_list_refresh.update(|n| *n += 1);
+                set_status_message.set("Branch created".to_string());
+                if let Some(bus) = events {
+                    bus.record(ActivityCategory::Conversation, "Conversation branched");
+                }
+            }
+            Err(e) => {
+                log::error!("Failed to branch conversation: {:?}", e);
+                set_status_message.set("Failed to create branch".to_string());
+                if let Some(bus) = events {
+                    bus.error("Failed to branch conversation", format!("{:?}", e));
+                }
+            }
+        }
+    });
+
+    // Pin or unpin a stored message
+    let pin_run = Callback::new(move |message_id: String| {
+        let Some(pinned) =
+            messages.with_untracked(|m| m.iter().find(|x| x.id == message_id).map(|x| !x.pinned))
+        else {
+            return;
+        };
+        if let (Some(storage), Some(conv_id)) = (
+            storage.get_untracked(),
+            current_conversation_id.get_untracked(),
+        ) {
+            if let Err(e) = storage.set_message_pinned(&conv_id, &message_id, pinned) {
+                log::error!("Failed to pin message: {:?}", e);
+                set_status_message.set("Failed to pin message".to_string());
+                return;
+            }
+        }
+        set_messages.update(|m| {
+            if let Some(x) = m.iter_mut().find(|x| x.id == message_id) {
+                x.pinned = pinned;
+            }
+        });
+    });
+    let pinned_messages = Memo::new(move |_| {
+        messages.with(|m| {
+            m.iter()
+                .filter(|x| x.pinned)
+                .map(|x| (x.id.clone(), x.role.clone(), x.content.clone()))
+                .collect::<Vec<_>>()
+        })
+    });
+
     // Show delete confirmation (no-arg)
     let _show_delete_confirmation = move || {
         set_show_delete_confirm.set(true);
@@ -604,6 +1693,17 @@ pub fn ChatArea(
     // Show per-conversation prompt editor
     let _show_edit_conv = move || {
         set_conv_prompt_input.set(conversation_system_prompt.get().unwrap_or_default());
+        set_conv_stops_input.set(match (storage.get(), current_conversation_id.get()) {
+            (Some(s), Some(id)) => GenerationUtils::format_stop_sequences(
+                &s.load_conversation_stop_sequences(&id).unwrap_or_default(),
+            ),
+            _ => String::new(),
+        });
+        policy_input.set(history_policy.get());
+        format_input.set(output_format.get());
+        style_input.set(answer_style.get());
+        scope_input.set(knowledge_scope.get());
+        scope_choices.set(ScopeChoices::load());
         set_show_edit_conv_prompt.set(true);
         set_menu_open.set(false);
     };
@@ -646,7 +1746,7 @@ pub fn ChatArea(
     };
 
     // Save as markdown function (no-arg)
-    let _save_as_markdown = move || {
+    let save_as_markdown = move || {
         let current_messages = messages.get();
         if current_messages.is_empty() {
             set_status_message.set("No messages to save".to_string());
@@ -654,85 +1754,110 @@ pub fn ChatArea(
             return;
         }
 
-        // Generate markdown content
-        let mut markdown_content = String::new();
-        let title = conversation_title.get();
-        markdown_content.push_str(&format!("# {}\n\n", title));
-
-        // Add export timestamp
-        let export_date = js_sys::Date::new(&wasm_bindgen::JsValue::from(js_sys::Date::now()));
-        let export_timestamp = export_date.to_locale_string("en-US", &js_sys::Object::new());
-        markdown_content.push_str(&format!(
-            "*Exported on: {}*\n\n---\n\n",
-            export_timestamp.as_string().unwrap_or_default()
-        ));
-
-        for message in current_messages {
-            let role = match message.role {
-                MessageRole::User => "## 👤 You",
-                MessageRole::Assistant => "## 🤖 Assistant",
-                MessageRole::System => "## ⚙️ System",
-            };
-
-            // Format timestamp
-            let date = js_sys::Date::new(&wasm_bindgen::JsValue::from(message.timestamp));
-            let formatted_date = date.to_locale_string("en-US", &js_sys::Object::new());
-            let timestamp = formatted_date.as_string().unwrap_or_default();
-
-            markdown_content.push_str(&format!(
-                "{}\n*{}*\n\n{}\n\n",
-                role, timestamp, message.content
-            ));
+        let markdown_content = ConversationExporter::to_markdown(
+            &conversation_title.get(),
+            &current_messages,
+            &FormatUtils::format_timestamp(js_sys::Date::now()),
+            citation_style.get(),
+            FormatUtils::format_timestamp,
+        );
+
+        // Create a safe filename from the conversation title
+        let safe_title = conversation_title
+            .get()
+            .chars()
+            .map(|c| if c.is_alphanumeric() { c } else { '_' })
+            .collect::<String>();
+        let filename = format!("{}.md", safe_title);
+        match DownloadUtils::download_text(&filename, &markdown_content) {
+            Ok(()) => set_status_message.set("Conversation saved as markdown".to_string()),
+            Err(e) => {
+                log::error!("Failed to save conversation: {}", e);
+                set_status_message.set("Failed to save conversation".to_string());
+            }
         }
 
-        // Create and download the file
-        let blob_parts = js_sys::Array::new();
-        blob_parts.push(&wasm_bindgen::JsValue::from_str(&markdown_content));
-
-        match web_sys::Blob::new_with_str_sequence(&blob_parts) {
-            Ok(blob) => {
-                match web_sys::Url::create_object_url_with_blob(&blob) {
-                    Ok(url) => {
-                        // Create download link
-                        if let Some(window) = web_sys::window() {
-                            if let Some(document) = window.document() {
-                                if let Ok(link) = document.create_element("a") {
-                                    if let Ok(link) = link.dyn_into::<web_sys::HtmlAnchorElement>()
-                                    {
-                                        link.set_href(&url);
-                                        // Create a safe filename from the conversation title
-                                        let safe_title = conversation_title
-                                            .get()
-                                            .chars()
-                                            .map(|c| if c.is_alphanumeric() { c } else { '_' })
-                                            .collect::<String>();
-                                        let filename = format!("{}.md", safe_title);
-                                        link.set_download(&filename);
-                                        link.click();
-
-                                        // Clean up
-                                        let _ = web_sys::Url::revoke_object_url(&url);
+        set_menu_open.set(false);
+    };
 
-                                        set_status_message
-                                            .set("Conversation saved as markdown".to_string());
-                                    }
-                                }
-                            }
+    // Extract action items from the current conversation into the tasks store
+    let extract_action_items = move || {
+        set_menu_open.set(false);
+        if is_extracting.get() || is_loading.get() {
+            return;
+        }
+        let Some(tasks_ctx) = tasks_ctx.clone() else {
+            set_status_message.set("Tasks store unavailable".to_string());
+            return;
+        };
+        let current_messages = messages.get();
+        if !current_messages
+            .iter()
+            .any(|m| matches!(m.role, MessageRole::User))
+        {
+            set_status_message.set("No conversation to extract tasks from".to_string());
+            return;
+        }
+        let Some((_, engine)) = loaded_engine() else {
+            set_status_message.set("Model not available".to_string());
+            return;
+        };
+        let conv_id = current_conversation_id.get();
+        let crm = crm_ctx.clone();
+        let settings = GenerationUtils::load_settings();
+        let today = LocalNow::current(settings.datetime.timezone.as_deref()).date;
+        let today_label = format!("{}, {}", today.weekday_name(), today.iso());
+
+        set_is_extracting.set(true);
+        set_status_message.set("Extracting action items...".to_string());
+        spawn_local(async move {
+            let request =
+                TaskExtractionUtils::build_extraction_messages(&current_messages, &today_label);
+            match send_message_to_llm(&engine, request).await {
+                Ok(response) => match TaskExtractionUtils::parse_action_items(&response) {
+                    Ok(items) => {
+                        let (customers, leads, deals) =
+                            (crm.customers_now(), crm.leads_now(), crm.deals_now());
+                        let new_tasks: Vec<Task> = items
+                            .into_iter()
+                            .map(|item| {
+                                let link = item.related_to.as_deref().and_then(|r| {
+                                    TaskExtractionUtils::match_crm_record(
+                                        r, &customers, &leads, &deals,
+                                    )
+                                });
+                                Task::new(item.title)
+                                    .with_due_date(item.due.as_deref().and_then(|d| {
+                                        TaskExtractionUtils::resolve_due_date(d, today)
+                                    }))
+                                    .with_conversation(conv_id.clone())
+                                    .with_crm_link(link)
+                            })
+                            .collect();
+                        let added = tasks_ctx.add_tasks(new_tasks);
+                        set_status_message.set(format!("Extracted {} action item(s)", added));
+                        if let Some(bus) = events {
+                            bus.record(
+                                ActivityCategory::Tasks,
+                                format!("Extracted {} action item(s)", added),
+                            );
                         }
                     }
                     Err(e) => {
-                        log::error!("Failed to create object URL: {:?}", e);
-                        set_status_message.set("Failed to save conversation".to_string());
+                        log::error!("Failed to parse action items: {}", e);
+                        if let Some(bus) = events {
+                            bus.error("Could not parse action items", e.to_string());
+                        }
+                        set_status_message.set("Could not parse action items".to_string());
                     }
+                },
+                Err(e) => {
+                    log::error!("Action item extraction error: {:?}", e);
+                    set_status_message.set("AI Error".to_string());
                 }
             }
-            Err(e) => {
-                log::error!("Failed to create blob: {:?}", e);
-                set_status_message.set("Failed to save conversation".to_string());
-            }
-        }
-
-        set_menu_open.set(false);
+            set_is_extracting.set(false);
+        });
     };
 
     // Toggle menu function (no-arg for Button callbacks)
@@ -772,6 +1897,19 @@ pub fn ChatArea(
                                     on_click=Box::new({
                                         move || {
                                             set_conv_prompt_input.set(conversation_system_prompt.get().unwrap_or_default());
+                                            set_conv_stops_input.set(
+                                                match (storage.get(), current_conversation_id.get()) {
+                                                    (Some(s), Some(id)) => GenerationUtils::format_stop_sequences(
+                                                        &s.load_conversation_stop_sequences(&id).unwrap_or_default(),
+                                                    ),
+                                                    _ => String::new(),
+                                                },
+                                            );
+                                            policy_input.set(history_policy.get());
+                                            format_input.set(output_format.get());
+                                            style_input.set(answer_style.get());
+                                            scope_input.set(knowledge_scope.get());
+                                            scope_choices.set(ScopeChoices::load());
                                             set_show_edit_conv_prompt.set(true);
                                             set_menu_open.set(false);
                                         }
@@ -793,18 +1931,42 @@ pub fn ChatArea(
                                     label=Signal::derive(|| "Save as Markdown".to_string())
                                     variant=Signal::derive(|| "btn-ghost w-full justify-start text-left whitespace-nowrap".to_string())
                                     icon=Signal::derive(|| "download".to_string())
-                                    on_click=Box::new({
-                                        move || {
-                                            let current_messages = messages.get();
-                                            if current_messages.is_empty() {
-                                                set_status_message.set("No messages to save".to_string());
-                                                set_menu_open.set(false);
-                                            } else {
-                                                // Existing save logic placeholder
-                                            }
+                                    on_click=Box::new(save_as_markdown)
+                                />
+                                <label class="flex items-center justify-between gap-2 px-4 py-1 text-xs opacity-80">
+                                    <span>"Citations"</span>
+                                    <select
+                                        class="select select-bordered select-xs"
+                                        on:change=move |ev| {
+                                            set_citation_style
+                                                .set(CitationStyle::from_key(&event_target_value(&ev)));
                                         }
+                                        prop:value=move || citation_style.get().key().to_string()
+                                    >
+                                        {CitationStyle::ALL
+                                            .into_iter()
+                                            .map(|style| {
+                                                view! { <option value=style.key()>{style.label()}</option> }
+                                            })
+                                            .collect_view()}
+                                    </select>
+                                </label>
+                                <Button
+                                    label=Signal::derive(|| "Topics".to_string())
+                                    variant=Signal::derive(|| "btn-ghost w-full justify-start text-left whitespace-nowrap".to_string())
+                                    icon=Signal::derive(|| "cloud".to_string())
+                                    on_click=Box::new(move || {
+                                        set_show_topics.update(|open| *open = !*open);
+                                        set_menu_open.set(false);
                                     })
                                 />
+                                <Button
+                                    label=Signal::derive(|| "Extract Action Items".to_string())
+                                    variant=Signal::derive(|| "btn-ghost w-full justify-start text-left whitespace-nowrap".to_string())
+                                    icon=Signal::derive(|| "list-checks".to_string())
+                                    disabled=Signal::derive(move || is_extracting.get() || !model_ready.get())
+                                    on_click=Box::new(extract_action_items.clone())
+                                />
                                 <Button
                                     label=Signal::derive(|| "Delete Conversation".to_string())
                                     variant=Signal::derive(|| "btn-ghost w-full justify-start text-left whitespace-nowrap text-error".to_string())
@@ -823,21 +1985,267 @@ pub fn ChatArea(
                 <div class="font-semibold truncate" title=move || conversation_title.get()>
                     {move || conversation_title.get()}
                 </div>
+                <div class="ml-auto flex items-center gap-1">
+                    // Pinned messages; picking one jumps to it in the transcript
+                    <Show when=move || pinned_messages.with(|p| !p.is_empty())>
+                        <div class="dropdown dropdown-end">
+                            <div tabindex="0" role="button" class="btn btn-ghost btn-sm gap-1" title="Pinned messages">
+                                <i data-lucide="pin" class="h-4 w-4"></i>
+                                <span class="text-xs">{move || pinned_messages.with(|p| p.len())}</span>
+                            </div>
+                            <ul tabindex="0" class="dropdown-content menu menu-sm bg-base-200 rounded-box z-20 w-80 p-1 shadow">
+                                <li class="menu-title">"Pinned"</li>
+                                <For
+                                    each=move || pinned_messages.get()
+                                    key=|(id, _, _)| id.clone()
+                                    children=move |(id, role, content)| {
+                                        let excerpt = FormatUtils::truncate_text(&content, 80);
+                                        view! {
+                                            <li>
+                                                <a on:click=move |_| {
+                                                    topic_filter.set(None);
+                                                    jump_to_message(id.clone());
+                                                }>
+                                                    <span class="text-xs opacity-60 w-8">
+                                                        {if role == MessageRole::User { "You" } else { "AI" }}
+                                                    </span>
+                                                    <span class="truncate flex-1">{excerpt}</span>
+                                                </a>
+                                            </li>
+                                        }
+                                    }
+                                />
+                            </ul>
+                        </div>
+                    </Show>
+                    // Branch switcher, shown once the conversation has been forked
+                    <Show when=move || branches.with(|b| b.len() > 1)>
+                        <div class="dropdown dropdown-end">
+                            <div tabindex="0" role="button" class="btn btn-ghost btn-sm gap-1" title="Switch branch">
+                                <i data-lucide="git-branch" class="h-4 w-4"></i>
+                                <span class="text-xs">{move || branches.with(|b| b.len())}</span>
+                            </div>
+                            <ul tabindex="0" class="dropdown-content menu menu-sm bg-base-200 rounded-box z-20 w-72 p-1 shadow">
+                                <For
+                                    each=move || branches.get()
+                                    key=|b| b.id.clone()
+                                    children=move |branch: BranchInfo| {
+                                        let id = branch.id.clone();
+                                        let is_current = {
+                                            let id = id.clone();
+                                            move || current_conversation_id.with(|c| c.as_deref() == Some(id.as_str()))
+                                        };
+                                        view! {
+                                            <li>
+                                                <a
+                                                    class=move || if is_current() { "active" } else { "" }
+                                                    style=format!("padding-left: {}rem", 0.75 + branch.depth as f32)
+                                                    on:click=move |_| set_current_conversation_id.set(Some(id.clone()))
+                                                >
+                                                    <span class="truncate flex-1">{branch.title.clone()}</span>
+                                                    <span class="text-xs opacity-60">{branch.message_count}</span>
+                                                </a>
+                                            </li>
+                                        }
+                                    }
+                                />
+                            </ul>
+                        </div>
+                    </Show>
+                </div>
             </div>
 
+        <Show when=move || show_topics.get()>
+            <TopicsPanel
+                messages=messages
+                selected=topic_filter
+                on_close=Callback::new(move |_| {
+                    set_show_topics.set(false);
+                    topic_filter.set(None);
+                })
+            />
+        </Show>
+
         // Messages area
         <div class="flex-1 overflow-y-auto custom-scrollbar" on:click=move |_| close_menu()>
             <div class="h-full flex flex-col">
                 <div class="flex-1 px-6 py-8">
                     <div class="max-w-4xl mx-auto w-full space-y-4">
+                        {move || topic_filter.get().map(|topic| {
+                            let count = messages.with(|m| {
+                                m.iter().filter(|msg| topics::mentions(msg, &topic)).count()
+                            });
+                            view! {
+                                <div class="alert alert-info py-2 text-sm flex items-center justify-between">
+                                    <span>{format!("{} message(s) mention \"{}\"", count, topic)}</span>
+                                    <button class="btn btn-ghost btn-xs" on:click=move |_| topic_filter.set(None)>
+                                        "Show all"
+                                    </button>
+                                </div>
+                            }
+                        })}
                         <For
-                            each=messages
-                            key=|msg| msg.id.clone()
-                            children=move |msg| view! { <MessageBubble message=msg /> }
+                            each=move || {
+                                let topic = topic_filter.get();
+                                messages.with(|m| {
+                                    m.iter()
+                                        .filter(|msg| {
+                                            topic.as_deref().is_none_or(|t| topics::mentions(msg, t))
+                                        })
+                                        .cloned()
+                                        .collect::<Vec<_>>()
+                                })
+                            }
+                            // Keyed on the compared replies too: adding one keeps the
+                            // message id, and its row must still be rebuilt to show it
+                            key=|msg| {
+                                let compared = msg.metadata.as_ref().map_or(0, |m| m.compared.len());
+                                (msg.id.clone(), compared)
+                            }
+                            children=move |msg| {
+                                // Only the last reply to a prompt can be regenerated
+                                let id = msg.id.clone();
+                                let regenerate = (msg.role == MessageRole::Assistant).then(|| {
+                                    RegenerateAction {
+                                        run: regenerate_run,
+                                        available: Signal::derive(move || {
+                                            !is_loading.get()
+                                                && model_ready.get()
+                                                && messages.with(|m| {
+                                                    m.last().is_some_and(|l| l.id == id)
+                                                        && m.iter().any(|x| x.role == MessageRole::User)
+                                                })
+                                        }),
+                                    }
+                                });
+                                // Compared replies are kept on the last reply that has metadata
+                                let compare_id = msg.id.clone();
+                                let compare = (msg.role == MessageRole::Assistant
+                                    && msg.metadata.is_some())
+                                .then(|| CompareAction {
+                                    run: compare_run,
+                                    models: compare_models,
+                                    available: Signal::derive(move || {
+                                        !is_loading.get()
+                                            && model_ready.get()
+                                            && messages.with(|m| {
+                                                m.last().is_some_and(|l| l.id == compare_id)
+                                            })
+                                    }),
+                                });
+                                let edit = (msg.role == MessageRole::User).then(|| EditAction {
+                                    run: edit_run,
+                                    available: Signal::derive(move || {
+                                        !is_loading.get() && model_ready.get()
+                                    }),
+                                });
+                                let fork = ForkAction {
+                                    run: fork_run,
+                                    available: Signal::derive(move || {
+                                        !is_loading.get() && current_conversation_id.with(|c| c.is_some())
+                                    }),
+                                };
+                                let pin_id = msg.id.clone();
+                                let pin = PinAction {
+                                    run: pin_run,
+                                    pinned: Signal::derive(move || {
+                                        messages.with(|m| m.iter().any(|x| x.id == pin_id && x.pinned))
+                                    }),
+                                };
+                                let thread = ThreadAction {
+                                    run: thread_run,
+                                    available: Signal::derive(move || {
+                                        current_conversation_id.with(|c| c.is_some())
+                                    }),
+                                };
+                                let thread_id = msg.id.clone();
+                                let thread_info = Signal::derive(move || {
+                                    threads.with(|t| {
+                                        t.iter().find(|t| t.message_id == thread_id).cloned()
+                                    })
+                                });
+                                view! {
+                                    <div>
+                                        <MessageBubble
+                                            message=msg.clone()
+                                            regenerate=regenerate
+                                            compare=compare
+                                            edit=edit
+                                            fork=Some(fork)
+                                            pin=Some(pin)
+                                            thread=Some(thread)
+                                            open_source=Some(open_source_run)
+                                        />
+                                        <MessageThread
+                                            parent=msg
+                                            storage=storage
+                                            conversation_id=current_conversation_id
+                                            info=thread_info
+                                            open=open_thread
+                                            model_ready=model_ready
+                                            on_changed=thread_changed
+                                        />
+                                    </div>
+                                }
+                            }
                         />
 
-                        // Loading indicator
-                        <Show when=move || is_loading.get()>
+                        // Reply being streamed in
+                        {move || {
+                            streaming_text
+                                .get()
+                                .filter(|t| !t.is_empty())
+                                .map(|t| view! {
+                                    <MessageBubble message=Message::new(MessageRole::Assistant, t) />
+                                })
+                        }}
+
+                        // Brainstormed replies to pick from
+                        {move || {
+                            brainstorm_pick.with(|pick| pick.as_ref().map(BrainstormPick::candidates)).map(|candidates| {
+                                view! {
+                                    <div class="rounded-box border border-base-300 p-3">
+                                        <div class="flex items-center gap-2 mb-2 text-sm font-medium">
+                                            <i data-lucide="lightbulb" class="h-4 w-4"></i>
+                                            "Pick a reply to keep"
+                                        </div>
+                                        <div class="grid grid-cols-1 md:grid-cols-2 gap-2">
+                                            {candidates
+                                                .into_iter()
+                                                .enumerate()
+                                                .map(|(i, candidate)| {
+                                                    let label = match candidate.temperature {
+                                                        Some(t) => format!("Reply {} · temperature {:.1}", i + 1, t),
+                                                        None => format!("Reply {}", i + 1),
+                                                    };
+                                                    view! {
+                                                        <div class="card card-compact bg-base-200">
+                                                            <div class="card-body gap-2">
+                                                                <div class="text-xs opacity-60">{label}</div>
+                                                                <div class="text-sm whitespace-pre-wrap max-h-64 overflow-y-auto">
+                                                                    {candidate.content}
+                                                                </div>
+                                                                <div class="card-actions justify-end">
+                                                                    <button class="btn btn-xs btn-primary" on:click=move |_| pick_reply.run(i)>
+                                                                        "Keep this reply"
+                                                                    </button>
+                                                                </div>
+                                                            </div>
+                                                        </div>
+                                                    }
+                                                })
+                                                .collect_view()}
+                                        </div>
+                                    </div>
+                                }
+                            })
+                        }}
+
+                        // Loading indicator (until the first token arrives)
+                        <Show when=move || {
+                            is_loading.get()
+                                && streaming_text.with(|t| t.as_deref().unwrap_or("").is_empty())
+                        }>
                             <div class="flex justify-start">
                                 <div class="bg-base-200 rounded-lg p-3 max-w-xs">
                                     <div class="flex space-x-1">
@@ -857,6 +2265,13 @@ pub fn ChatArea(
 
             // Global system prompt modal removed from ChatArea (moved to Sidebar)
 
+            // Source opened from a citation chip
+            {move || {
+                open_source.get().map(|source| view! {
+                    <SourcePanel source=source on_close=Callback::new(move |_| open_source.set(None)) />
+                })
+            }}
+
             // Per-conversation system prompt modal (opened from burger menu)
             <Show when=move || show_edit_conv_prompt.get()>
                 <div class="fixed inset-0 bg-black/50 flex items-center justify-center z-50">
@@ -870,6 +2285,265 @@ pub fn ChatArea(
                                 on:input=move |ev| set_conv_prompt_input.set(event_target_value(&ev))
                             ></textarea>
                         </div>
+                        <div class="mb-4">
+                            <label class="block text-sm font-medium text-base-content/70 mb-2">
+                                "Stop sequences (one per line, \\n for a newline)"
+                            </label>
+                            <textarea
+                                class="textarea textarea-bordered w-full min-h-[80px] font-mono text-sm"
+                                placeholder="\\nUser:"
+                                prop:value=move || conv_stops_input.get()
+                                on:input=move |ev| set_conv_stops_input.set(event_target_value(&ev))
+                            ></textarea>
+                        </div>
+                        <div class="mb-4">
+                            <label class="block text-sm font-medium text-base-content/70 mb-2">
+                                "History sent to the model"
+                            </label>
+                            <div class="flex gap-2">
+                                <select
+                                    class="select select-bordered select-sm flex-1"
+                                    on:change=move |ev| {
+                                        let policy = match event_target_value(&ev).as_str() {
+                                            "last_turns" => HistoryPolicy::LastTurns { turns: 6 },
+                                            "token_budget" => HistoryPolicy::TokenBudget { tokens: 2048 },
+                                            "summary_plus_recent" => HistoryPolicy::SummaryPlusRecent { turns: 4 },
+                                            _ => HistoryPolicy::Auto,
+                                        };
+                                        policy_input.set(policy);
+                                    }
+                                >
+                                    {[
+                                        ("auto", "Automatic (global context settings)"),
+                                        ("last_turns", "Last N turns"),
+                                        ("token_budget", "Token budget"),
+                                        ("summary_plus_recent", "Summary + last N turns"),
+                                    ]
+                                        .into_iter()
+                                        .map(|(key, label)| {
+                                            view! {
+                                                <option
+                                                    value=key
+                                                    selected=move || policy_kind(&policy_input.get()) == key
+                                                >
+                                                    {label}
+                                                </option>
+                                            }
+                                        })
+                                        .collect_view()}
+                                </select>
+                                <Show when=move || policy_input.get() != HistoryPolicy::Auto>
+                                    <input
+                                        type="number"
+                                        min="1"
+                                        class="input input-bordered input-sm w-28"
+                                        title=move || match policy_input.get() {
+                                            HistoryPolicy::TokenBudget { .. } => "Tokens",
+                                            _ => "Turns",
+                                        }
+                                        prop:value=move || match policy_input.get() {
+                                            HistoryPolicy::LastTurns { turns }
+                                            | HistoryPolicy::SummaryPlusRecent { turns } => turns.to_string(),
+                                            HistoryPolicy::TokenBudget { tokens } => tokens.to_string(),
+                                            HistoryPolicy::Auto => String::new(),
+                                        }
+                                        on:input=move |ev| {
+                                            let Ok(n) = event_target_value(&ev).parse::<u32>() else {
+                                                return;
+                                            };
+                                            let n = n.max(1);
+                                            policy_input.update(|p| match p {
+                                                HistoryPolicy::LastTurns { turns }
+                                                | HistoryPolicy::SummaryPlusRecent { turns } => *turns = n as usize,
+                                                HistoryPolicy::TokenBudget { tokens } => *tokens = n,
+                                                HistoryPolicy::Auto => {}
+                                            });
+                                        }
+                                    />
+                                </Show>
+                            </div>
+                        </div>
+                        <div class="mb-4">
+                            <label class="block text-sm font-medium text-base-content/70 mb-2">
+                                "Reply format"
+                            </label>
+                            <select
+                                class="select select-bordered select-sm w-full"
+                                on:change=move |ev| {
+                                    let format = match event_target_value(&ev).as_str() {
+                                        "json" => OutputFormat::Json { schema: None },
+                                        "schema" => OutputFormat::Json {
+                                            schema: Some(json_output::SCHEMA_PRESETS[0].1.to_string()),
+                                        },
+                                        _ => OutputFormat::Text,
+                                    };
+                                    format_input.set(format);
+                                }
+                            >
+                                {[
+                                    ("text", "Text"),
+                                    ("json", "JSON"),
+                                    ("schema", "JSON matching a schema"),
+                                ]
+                                    .into_iter()
+                                    .map(|(key, label)| {
+                                        view! {
+                                            <option
+                                                value=key
+                                                selected=move || format_kind(&format_input.get()) == key
+                                            >
+                                                {label}
+                                            </option>
+                                        }
+                                    })
+                                    .collect_view()}
+                            </select>
+                            <Show when=move || format_kind(&format_input.get()) == "schema">
+                                <div class="flex flex-wrap gap-1 mt-2">
+                                    {json_output::SCHEMA_PRESETS
+                                        .iter()
+                                        .map(|(label, schema)| {
+                                            view! {
+                                                <button
+                                                    class="btn btn-xs btn-ghost border border-base-300"
+                                                    on:click=move |_| {
+                                                        format_input.set(OutputFormat::Json {
+                                                            schema: Some(schema.to_string()),
+                                                        })
+                                                    }
+                                                >
+                                                    {*label}
+                                                </button>
+                                            }
+                                        })
+                                        .collect_view()}
+                                </div>
+                                <textarea
+                                    class="textarea textarea-bordered w-full min-h-[120px] font-mono text-xs mt-2"
+                                    prop:value=move || match format_input.get() {
+                                        OutputFormat::Json { schema: Some(schema) } => schema,
+                                        _ => String::new(),
+                                    }
+                                    on:input=move |ev| {
+                                        format_input.set(OutputFormat::Json {
+                                            schema: Some(event_target_value(&ev)),
+                                        })
+                                    }
+                                ></textarea>
+                                {move || {
+                                    json_output::parse_schema(
+                                            &match format_input.get() {
+                                                OutputFormat::Json { schema: Some(schema) } => schema,
+                                                _ => String::new(),
+                                            },
+                                        )
+                                        .err()
+                                        .map(|e| view! { <p class="text-xs text-error mt-1">{e}</p> })
+                                }}
+                            </Show>
+                        </div>
+                        <div class="mb-4">
+                            <label class="block text-sm font-medium text-base-content/70 mb-2">
+                                "Answer style"
+                            </label>
+                            <p class="text-xs text-base-content/60 mb-2">
+                                {format!(
+                                    "Asking for the same style {} times, e.g. \"shorter please\", adds it here.",
+                                    answer_style::LEARN_AFTER,
+                                )}
+                            </p>
+                            <div class="flex flex-wrap gap-1">
+                                {StyleCue::ALL
+                                    .into_iter()
+                                    .map(|cue| {
+                                        view! {
+                                            <button
+                                                class=move || {
+                                                    format!(
+                                                        "btn btn-xs {}",
+                                                        if style_input.with(|s| s.has(cue)) { "btn-primary" } else { "btn-ghost border border-base-300" },
+                                                    )
+                                                }
+                                                aria-pressed=move || style_input.with(|s| s.has(cue)).to_string()
+                                                on:click=move |_| {
+                                                    style_input.update(|s| {
+                                                        if s.has(cue) {
+                                                            s.forget(cue);
+                                                        } else {
+                                                            s.prefer(cue);
+                                                        }
+                                                    })
+                                                }
+                                            >
+                                                {cue.label()}
+                                            </button>
+                                        }
+                                    })
+                                    .collect_view()}
+                            </div>
+                            <textarea
+                                class="textarea textarea-bordered w-full min-h-[60px] text-sm mt-2"
+                                placeholder="Other preferences, e.g. quote prices in euros"
+                                prop:value=move || style_input.with(|s| s.notes.clone())
+                                on:input=move |ev| style_input.update(|s| s.notes = event_target_value(&ev))
+                            ></textarea>
+                        </div>
+                        <div class="mb-4">
+                            <label class="block text-sm font-medium text-base-content/70 mb-2">
+                                "Knowledge scope"
+                            </label>
+                            <p class="text-xs text-base-content/60 mb-2">
+                                "Limit retrieval in this conversation to some collections or files. Nothing selected searches the whole knowledge base; attached files are always searched."
+                            </p>
+                            <div class="max-h-48 overflow-y-auto flex flex-col gap-1">
+                                {move || {
+                                    scope_choices.with(|choices| {
+                                        let collections = choices.collections.iter().map(|(id, name)| {
+                                            let id = id.clone();
+                                            let checked = id.clone();
+                                            view! {
+                                                <label class="flex items-center gap-2 text-sm cursor-pointer">
+                                                    <input
+                                                        type="checkbox"
+                                                        class="checkbox checkbox-xs"
+                                                        prop:checked=move || scope_input.with(|s| s.collections.contains(&checked))
+                                                        on:change=move |_| scope_input.update(|s| s.toggle_collection(&id))
+                                                    />
+                                                    <i data-lucide="folder" class="h-3 w-3 opacity-60"></i>
+                                                    <span class="truncate">{name.clone()}</span>
+                                                </label>
+                                            }
+                                        });
+                                        let files = choices.files.iter().map(|file| {
+                                            let name = file.clone();
+                                            let checked = file.clone();
+                                            view! {
+                                                <label class="flex items-center gap-2 text-sm cursor-pointer">
+                                                    <input
+                                                        type="checkbox"
+                                                        class="checkbox checkbox-xs"
+                                                        prop:checked=move || scope_input.with(|s| s.documents.contains(&checked))
+                                                        on:change=move |_| scope_input.update(|s| s.toggle_document(&name))
+                                                    />
+                                                    <i data-lucide="file-text" class="h-3 w-3 opacity-60"></i>
+                                                    <span class="truncate">{file.clone()}</span>
+                                                </label>
+                                            }
+                                        });
+                                        view! {
+                                            {collections.collect_view()}
+                                            {files.collect_view()}
+                                        }
+                                    })
+                                }}
+                                <Show when=move || scope_choices.with(|c| c.collections.is_empty() && c.files.is_empty())>
+                                    <p class="text-xs text-base-content/50">"Nothing is indexed yet."</p>
+                                </Show>
+                            </div>
+                            <p class="text-xs text-base-content/60 mt-1">
+                                {move || format!("Searched: {}", scope_input.with(|s| s.summary()))}
+                            </p>
+                        </div>
                         <div class="flex gap-3 justify-end">
                             <Button
                                 label=Signal::derive(|| "Cancel".to_string())
@@ -880,19 +2554,48 @@ pub fn ChatArea(
                                 })
                             />
                             {
-                                let can_save = Signal::derive(move || !conv_prompt_input.get().trim().is_empty());
+                                let can_save = Signal::derive(move || {
+                                    !conv_prompt_input.get().trim().is_empty()
+                                        || !conv_stops_input.get().trim().is_empty()
+                                        || policy_input.get() != history_policy.get()
+                                        || format_input.get() != output_format.get()
+                                || style_input.get() != answer_style.get()
+                                || scope_input.get() != knowledge_scope.get()
+                                });
+                                let schema_error = Signal::derive(move || match format_input.get() {
+                                    OutputFormat::Json { schema: Some(schema) } => {
+                                        json_output::parse_schema(&schema).err()
+                                    }
+                                    _ => None,
+                                });
                                 view! {
                                     <Button
                                         label=Signal::derive(|| "Save".to_string())
                                         variant=Signal::derive(|| "btn-primary".to_string())
-                                        disabled=Signal::derive(move || !can_save.get())
+                                        disabled=Signal::derive(move || {
+                                            !can_save.get() || schema_error.get().is_some()
+                                        })
                                         on_click=Box::new({
                                             let set_show = set_show_edit_conv_prompt;
                                             move || {
                                                 if let (Some(ref storage), Some(ref conv_id)) = (storage.get(), current_conversation_id.get()) {
                                                     let text = conv_prompt_input.get();
                                                     let _ = storage.update_conversation_system_prompt(conv_id, Some(text.clone()));
-                                                    set_conversation_system_prompt.set(Some(text));
+                                                    set_conversation_system_prompt.set(Some(text).filter(|t| !t.trim().is_empty()));
+                                                    let stops = GenerationUtils::parse_stop_sequences(&conv_stops_input.get());
+                                                    let _ = storage.update_conversation_stop_sequences(conv_id, stops);
+                                                    let policy = policy_input.get();
+                                                    let _ = storage.update_conversation_history_policy(conv_id, policy);
+                                                    set_history_policy.set(policy);
+                                                    let format = format_input.get();
+                                                    let _ = storage.update_conversation_output_format(conv_id, format.clone());
+                                                    set_output_format.set(format);
+                                                    let style = style_input.get();
+                                                    let _ = storage.update_conversation_answer_style(conv_id, style.clone());
+                                                    set_answer_style.set(style);
+                                                    let scope = scope_input.get();
+                                                    let _ = storage.update_conversation_knowledge_scope(conv_id, scope.clone());
+                                                    set_knowledge_scope.set(scope);
                                                     set_status_message.set("Conversation prompt saved".to_string());
                                                 }
                                                 set_show.set(false);
@@ -983,17 +2686,154 @@ pub fn ChatArea(
 
             // Input area
             <div class="border-t border-base-300 p-2">
+                <Show when=move || similar.with(|s| !s.is_empty())>
+                    <div class="flex items-center gap-2 mb-2 px-2 py-1 rounded-lg bg-base-200 text-sm">
+                        <i data-lucide="history" class="w-4 h-4 opacity-60"></i>
+                        <span class="text-base-content/70 shrink-0">"You discussed this before:"</span>
+                        <div class="flex flex-wrap gap-1 flex-1 min-w-0">
+                            <For each=move || similar.get() key=|s| s.conversation_id.clone() let:s>
+                                {
+                                    let id = s.conversation_id.clone();
+                                    view! {
+                                        <button
+                                            class="btn btn-xs btn-ghost font-normal truncate max-w-56"
+                                            title=format!("Open \"{}\"", s.title)
+                                            on:click=move |_| set_current_conversation_id.set(Some(id.clone()))
+                                        >
+                                            {s.title.clone()}
+                                        </button>
+                                    }
+                                }
+                            </For>
+                        </div>
+                        <button
+                            class="btn btn-xs btn-ghost btn-square"
+                            title="Dismiss"
+                            aria-label="Dismiss similar conversations"
+                            on:click=move |_| set_similar_dismissed.set(true)
+                        >
+                            <i data-lucide="x" class="w-3 h-3"></i>
+                        </button>
+                    </div>
+                </Show>
                 <InputArea
                     input_value=input_value
                     set_input_value=set_input_value
                     on_send={send_message_cb.clone()}
                     knowledge_enabled=knowledge_enabled
                     set_knowledge_enabled=set_knowledge_enabled
+                    graphrag_config=graphrag_config
+                    last_query=last_query
                     is_loading=is_loading
                     set_status_message=set_status_message
+                    history_note=history_note
+                    conversation_id=current_conversation_id
+                    knowledge_scope=knowledge_scope
+                    on_select_collection=select_collection
+                    brainstorm=brainstorm_mode
                 />
             </div>
         </div>
         </div>
     }
 }
+
+/// Option value of a format in the reply format select
+fn format_kind(format: &OutputFormat) -> &'static str {
+    match format {
+        OutputFormat::Text => "text",
+        OutputFormat::Json { schema: None } => "json",
+        OutputFormat::Json { schema: Some(_) } => "schema",
+    }
+}
+
+/// Option value of a policy in the history select
+fn policy_kind(policy: &HistoryPolicy) -> &'static str {
+    match policy {
+        HistoryPolicy::Auto => "auto",
+        HistoryPolicy::LastTurns { .. } => "last_turns",
+        HistoryPolicy::TokenBudget { .. } => "token_budget",
+        HistoryPolicy::SummaryPlusRecent { .. } => "summary_plus_recent",
+    }
+}
+
+/// Collections and uploaded files offered in the knowledge scope of the prompt dialog
+#[derive(Clone, Default)]
+struct ScopeChoices {
+    /// Collection ids and names
+    collections: Vec<(String, String)>,
+    files: Vec<String>,
+}
+
+impl ScopeChoices {
+    /// Indexed files, leaving out those attached to a conversation
+    fn load() -> Self {
+        let attachments = Attachments::load().unwrap_or_default();
+        let mut files: Vec<String> = GraphRAGPipeline::new()
+            .indexed_documents()
+            .unwrap_or_default()
+            .iter()
+            .map(|d| d.source_file().to_string())
+            .filter(|f| !attachments.files.contains_key(f))
+            .collect();
+        files.sort();
+        files.dedup();
+        Self {
+            collections: Collections::load()
+                .unwrap_or_default()
+                .collections
+                .into_iter()
+                .map(|c| (c.id, c.name))
+                .collect(),
+            files,
+        }
+    }
+}
+
+/// One completion run of the chat
+struct ReplyRequest {
+    /// Conversation so far, ending with the prompt to answer
+    history: Vec<Message>,
+    use_knowledge: bool,
+    perf: PerformanceMetrics,
+    /// Reply being regenerated and where the new one goes
+    regenerate: Option<(Message, RegenerateMode)>,
+    /// Added to the configured sampling temperature
+    temperature_delta: f32,
+    /// Sample several replies and let the user pick one
+    brainstorm: bool,
+    /// Model answering instead of the chat's, loaded beforehand
+    model: Option<String>,
+}
+
+/// Brainstormed replies waiting for the user to pick one
+#[derive(Clone)]
+struct BrainstormPick {
+    /// Conversation the replies answer
+    conversation_id: Option<String>,
+    /// First reply, carrying the metadata of the run
+    reply: Message,
+    alternatives: Vec<ReplyAttempt>,
+}
+
+impl BrainstormPick {
+    /// Replies in the order shown, the first one first
+    fn candidates(&self) -> Vec<ReplyAttempt> {
+        let first = self.reply.reply_attempts().pop();
+        first.into_iter().chain(self.alternatives.clone()).collect()
+    }
+
+    /// Reply to store when candidate `index` is picked; the others become its
+    /// earlier replies
+    fn choose(self, index: usize) -> Message {
+        let mut candidates = self.candidates();
+        let chosen = candidates.remove(index.min(candidates.len() - 1));
+        let mut reply = self.reply;
+        reply.content = chosen.content;
+        if let Some(md) = reply.metadata.as_mut() {
+            md.temperature = chosen.temperature;
+            md.attempts.extend(candidates);
+        }
+        reply
+    }
+}
@@ -0,0 +1,404 @@
+use crate::features::graphrag::embeddings::{VectorIndex, EMBEDDING_MODELS};
+use crate::features::graphrag::reembed::{self, ReembedProgress};
+use crate::features::graphrag::GraphRAGPipeline;
+use crate::models::graphrag::DocumentIndex;
+use crate::models::{Collection, Collections};
+use crate::state::GraphRAGStateContext;
+use leptos::prelude::*;
+
+/// Indexed document with the model its vectors came from, if it has any
+#[derive(Clone)]
+struct Embedded {
+    doc: DocumentIndex,
+    model: Option<String>,
+}
+
+fn load_embedded() -> Vec<Embedded> {
+    let vectors = VectorIndex::load();
+    GraphRAGPipeline::new()
+        .indexed_documents()
+        .unwrap_or_default()
+        .into_iter()
+        .map(|doc| Embedded {
+            model: vectors.model_of(&doc.id).map(str::to_string),
+            doc,
+        })
+        .collect()
+}
+
+fn update_collections(
+    collections: RwSignal<Collections>,
+    error: RwSignal<Option<String>>,
+    f: impl FnOnce(&mut Collections),
+) {
+    collections.update(f);
+    match collections.with_untracked(|c| c.save()) {
+        Ok(()) => error.set(None),
+        Err(e) => error.set(Some(format!("Collections not saved: {}", e))),
+    }
+}
+
+fn format_duration(ms: f64) -> String {
+    let secs = (ms / 1000.0).ceil() as u64;
+    if secs < 60 {
+        format!("{}s", secs.max(1))
+    } else {
+        format!("{}m {}s", secs / 60, secs % 60)
+    }
+}
+
+/// Named groups of uploaded files, each embedded and searched with its own model and
+/// offered as a knowledge base to search on its own.
+/// Vectors of different models cannot be compared, so documents still embedded with
+/// another model are flagged with a re-embed action.
+#[component]
+pub fn CollectionsPanel() -> impl IntoView {
+    let graphrag_ctx = use_context::<GraphRAGStateContext>();
+    let indexing = graphrag_ctx.as_ref().map(|c| c.is_indexing());
+    let collections = RwSignal::new(Collections::load().unwrap_or_default());
+    let new_name = RwSignal::new(String::new());
+    let error = RwSignal::new(None::<String>);
+    let default_model = GraphRAGPipeline::new().config().embedding_model.clone();
+    let embeddings_enabled = GraphRAGPipeline::new().config().embeddings_enabled;
+
+    let progress = graphrag_ctx
+        .as_ref()
+        .map(|c| Signal::from(c.reembed_progress()))
+        .unwrap_or_else(|| Signal::derive(|| None));
+    let reembedding = Signal::derive(move || progress.with(|p| p.is_some()));
+
+    // Indexed documents, reloaded whenever an indexing run or re-embedding ends
+    let embedded = RwSignal::new(Vec::<Embedded>::new());
+    Effect::new(move |_| {
+        if !indexing.is_some_and(|i| i.get()) && !reembedding.get() {
+            embedded.set(load_embedded());
+        }
+    });
+    let files = Signal::derive(move || {
+        let mut files: Vec<String> =
+            embedded.with(|e| e.iter().map(|e| e.doc.source_file().to_string()).collect());
+        files.sort();
+        files.dedup();
+        files
+    });
+    // Documents whose vectors come from a model other than their collection's
+    let mismatched = Signal::derive({
+        let default_model = default_model.clone();
+        move || {
+            collections.with(|c| {
+                embedded.with(|e| {
+                    e.iter()
+                        .filter(|e| {
+                            e.model.as_ref().is_some_and(|m| {
+                                *m != c.embedding_model_for(&e.doc, &default_model)
+                            })
+                        })
+                        .count()
+                })
+            })
+        }
+    });
+
+    // What a re-embedding would process now
+    let estimate = Signal::derive({
+        let default_model = default_model.clone();
+        move || {
+            collections.with(|c| {
+                embedded.with(|e| {
+                    let docs: Vec<DocumentIndex> = e.iter().map(|e| e.doc.clone()).collect();
+                    let items = reembed::plan(&docs, &VectorIndex::load(), |d| {
+                        c.embedding_model_for(d, &default_model)
+                    });
+                    ReembedProgress::new(&items)
+                })
+            })
+        }
+    });
+
+    let add = move || {
+        let name = new_name.get_untracked().trim().to_string();
+        if name.is_empty() {
+            return;
+        }
+        update_collections(collections, error, |c| {
+            c.collections.push(Collection::new(name))
+        });
+        new_name.set(String::new());
+    };
+
+    view! {
+        <div class="card bg-base-100 shadow-sm border border-base-300 rounded-xl">
+            <div class="card-body p-4 gap-3">
+                <div>
+                    <h3 class="card-title text-lg">"Collections"</h3>
+                    <p class="text-xs text-base-content/60 mt-1">
+                        {format!(
+                            "Group files and choose the embedding model each group is searched with. Files outside a collection use {}.",
+                            default_model,
+                        )}
+                    </p>
+                    <Show when=move || !embeddings_enabled>
+                        <p class="text-xs text-base-content/60">
+                            "Embedding retrieval is off in the GraphRAG settings; models apply once it is on."
+                        </p>
+                    </Show>
+                </div>
+
+                <Show when=move || { mismatched.get() > 0 && !reembedding.get() }>
+                    <div class="alert alert-warning shadow-sm rounded-lg">
+                        <i data-lucide="refresh-cw" class="w-5 h-5"></i>
+                        <div class="text-sm">
+                            <p>
+                                {move || format!(
+                                    "{} document(s) were embedded with a different model than their collection uses. They are searched by keyword only until re-embedded.",
+                                    mismatched.get(),
+                                )}
+                            </p>
+                            <p class="text-xs opacity-70">
+                                {move || {
+                                    let estimate = estimate.get();
+                                    format!(
+                                        "{} chunk(s), about {}. Searches keep using the current vectors until every document is done.",
+                                        estimate.total_chunks,
+                                        format_duration(estimate.eta_ms),
+                                    )
+                                }}
+                            </p>
+                        </div>
+                        <button
+                            class="btn btn-sm btn-primary"
+                            disabled=move || indexing.is_some_and(|i| i.get())
+                            on:click={
+                                let ctx = graphrag_ctx.clone();
+                                move |_| {
+                                    if let Some(ctx) = ctx.as_ref() {
+                                        ctx.start_reembed();
+                                    }
+                                }
+                            }
+                        >
+                            "Re-embed"
+                        </button>
+                    </div>
+                </Show>
+
+                <Show when=move || reembedding.get()>
+                    <div class="p-3 rounded-lg bg-base-200 space-y-2">
+                        <div class="flex items-center justify-between text-sm">
+                            <span class="font-medium">
+                                {move || {
+                                    progress
+                                        .get()
+                                        .map(|p| {
+                                            format!(
+                                                "{} {}/{} documents",
+                                                if p.paused { "Paused at" } else { "Re-embedding" },
+                                                p.done_docs,
+                                                p.total_docs,
+                                            )
+                                        })
+                                        .unwrap_or_default()
+                                }}
+                            </span>
+                            <span class="text-xs text-base-content/60">
+                                {move || {
+                                    progress
+                                        .get()
+                                        .map(|p| format!("about {} left", format_duration(p.eta_ms)))
+                                        .unwrap_or_default()
+                                }}
+                            </span>
+                        </div>
+                        <progress
+                            class="progress progress-primary w-full"
+                            max="100"
+                            value=move || {
+                                progress.get().map(|p| (p.fraction() * 100.0).round()).unwrap_or(0.0)
+                            }
+                        ></progress>
+                        <p class="text-xs text-base-content/60">
+                            "Searches use the current vectors until the new ones are switched in together."
+                        </p>
+                        <div class="flex gap-2">
+                            <button
+                                class="btn btn-xs"
+                                on:click={
+                                    let ctx = graphrag_ctx.clone();
+                                    move |_| {
+                                        if let Some(ctx) = ctx.as_ref() {
+                                            ctx.toggle_reembed_pause();
+                                        }
+                                    }
+                                }
+                            >
+                                {move || {
+                                    if progress.get().is_some_and(|p| p.paused) { "Resume" } else { "Pause" }
+                                }}
+                            </button>
+                            <button
+                                class="btn btn-xs btn-ghost"
+                                on:click={
+                                    let ctx = graphrag_ctx.clone();
+                                    move |_| {
+                                        if let Some(ctx) = ctx.as_ref() {
+                                            ctx.cancel_reembed();
+                                        }
+                                    }
+                                }
+                            >
+                                "Cancel"
+                            </button>
+                        </div>
+                    </div>
+                </Show>
+
+                <datalist id="embedding-model-options">
+                    {EMBEDDING_MODELS
+                        .iter()
+                        .map(|(id, label)| view! { <option value=*id>{*label}</option> })
+                        .collect_view()}
+                </datalist>
+
+                <div class="flex gap-2">
+                    <input
+                        class="input input-bordered input-sm flex-1"
+                        placeholder="New collection name"
+                        prop:value=move || new_name.get()
+                        on:input=move |ev| new_name.set(event_target_value(&ev))
+                        on:keydown=move |ev| {
+                            if ev.key() == "Enter" {
+                                add();
+                            }
+                        }
+                    />
+                    <button
+                        class="btn btn-sm btn-primary"
+                        disabled=move || new_name.with(|n| n.trim().is_empty())
+                        on:click=move |_| add()
+                    >
+                        "Add"
+                    </button>
+                </div>
+
+                <For
+                    each=move || collections.get().collections
+                    key=|c| {
+                        (
+                            c.id.clone(),
+                            c.name.clone(),
+                            c.description.clone(),
+                            c.documents.len(),
+                            c.embedding_model.clone(),
+                        )
+                    }
+                    let:collection
+                >
+                    {
+                        let id = collection.id.clone();
+                        let describe_id = collection.id.clone();
+                        let remove_id = collection.id.clone();
+                        view! {
+                            <div class="flex flex-wrap items-center gap-2 p-2 rounded-lg bg-base-200">
+                                <span class="font-medium text-sm">{collection.name.clone()}</span>
+                                <span class="badge badge-ghost badge-sm">
+                                    {format!("{} file(s)", collection.documents.len())}
+                                </span>
+                                <input
+                                    class="input input-bordered input-xs flex-1 min-w-48 font-mono"
+                                    list="embedding-model-options"
+                                    placeholder=default_model.clone()
+                                    title="Embedding model id; empty uses the GraphRAG settings"
+                                    prop:value=collection.embedding_model.clone().unwrap_or_default()
+                                    on:change=move |ev| {
+                                        let model = event_target_value(&ev).trim().to_string();
+                                        let id = id.clone();
+                                        update_collections(collections, error, move |c| {
+                                            if let Some(c) = c.collections.iter_mut().find(|c| c.id == id) {
+                                                c.embedding_model = Some(model).filter(|m| !m.is_empty());
+                                            }
+                                        });
+                                    }
+                                />
+                                <button
+                                    class="btn btn-ghost btn-xs"
+                                    title="Delete collection (files are kept)"
+                                    on:click=move |_| {
+                                        let id = remove_id.clone();
+                                        update_collections(collections, error, move |c| {
+                                            c.collections.retain(|c| c.id != id)
+                                        });
+                                    }
+                                >
+                                    <i data-lucide="trash-2" class="h-3 w-3"></i>
+                                </button>
+                                <input
+                                    class="input input-bordered input-xs w-full"
+                                    placeholder="Description, shown when choosing a knowledge base"
+                                    prop:value=collection.description.clone()
+                                    on:change=move |ev| {
+                                        let description = event_target_value(&ev).trim().to_string();
+                                        let id = describe_id.clone();
+                                        update_collections(collections, error, move |c| {
+                                            if let Some(c) = c.collections.iter_mut().find(|c| c.id == id) {
+                                                c.description = description;
+                                            }
+                                        });
+                                    }
+                                />
+                            </div>
+                        }
+                    }
+                </For>
+
+                <Show when=move || { !files.get().is_empty() && !collections.with(|c| c.collections.is_empty()) }>
+                    <div class="space-y-1 max-h-64 overflow-auto">
+                        <For each=move || files.get() key=|f| f.clone() let:file>
+                            {
+                                let file_for_select = file.clone();
+                                let file_for_options = file.clone();
+                                view! {
+                                    <div class="flex items-center justify-between gap-2 text-sm">
+                                        <span class="truncate" title=file.clone()>{file.clone()}</span>
+                                        <select
+                                            class="select select-bordered select-xs w-40"
+                                            on:change=move |ev| {
+                                                let target = event_target_value(&ev);
+                                                let file = file_for_select.clone();
+                                                update_collections(collections, error, move |c| {
+                                                    c.assign(&file, Some(target.as_str()).filter(|t| !t.is_empty()))
+                                                });
+                                            }
+                                        >
+                                            <option value="">"No collection"</option>
+                                            {move || {
+                                                let current = collections
+                                                    .with(|c| c.of_file(&file_for_options).map(|c| c.id.clone()));
+                                                collections
+                                                    .get()
+                                                    .collections
+                                                    .into_iter()
+                                                    .map(|c| {
+                                                        let selected = current.as_deref() == Some(c.id.as_str());
+                                                        view! {
+                                                            <option value=c.id.clone() selected=selected>
+                                                                {c.name.clone()}
+                                                            </option>
+                                                        }
+                                                    })
+                                                    .collect_view()
+                                            }}
+                                        </select>
+                                    </div>
+                                }
+                            }
+                        </For>
+                    </div>
+                </Show>
+
+                <Show when=move || error.get().is_some()>
+                    <p class="text-xs text-error">{move || error.get().unwrap_or_default()}</p>
+                </Show>
+            </div>
+        </div>
+    }
+}
@@ -4,6 +4,7 @@ use crate::components::ui_primitives::Button;
 use leptos::prelude::*;
 
 use crate::models::{Message, MessageRole};
+use crate::state::ConversationStateContext;
 use crate::storage::{ConversationInfo, ConversationStorage};
 use log::info;
 
@@ -19,6 +20,20 @@ where
 {
     let (conversations, set_conversations) = signal::<Vec<ConversationInfo>>(vec![]);
     let (loading, set_loading) = signal(false);
+    let recency = use_context::<ConversationStateContext>();
+    // Quick-switcher entries: recently opened conversations that are still listed
+    let recent = Memo::new(move |_| {
+        let Some(recency) = recency else {
+            return Vec::new();
+        };
+        let ids = recency.recent_ids();
+        conversations.with(|list| {
+            ids.iter()
+                .filter_map(|id| list.iter().find(|c| &c.id == id))
+                .map(|c| (c.id.clone(), c.title.clone()))
+                .collect::<Vec<_>>()
+        })
+    });
 
     // Helper function to check if conversation has user messages
     let has_user_messages = move |messages: &Vec<Message>| -> bool {
@@ -83,6 +98,13 @@ where
                         "✅ Loaded {} valid conversations",
                         valid_conversations.len()
                     );
+                    // Deleted conversations leave the quick-switcher
+                    if let Some(recency) = recency {
+                        recency.prune_recent(|id| {
+                            Some(id) == current_conv_id.as_deref()
+                                || valid_conversations.iter().any(|c| c.id == id)
+                        });
+                    }
                     set_conversations.set(valid_conversations);
                 }
                 Err(e) => {
@@ -128,6 +150,47 @@ where
     view! {
         <div class="flex-1 overflow-y-auto custom-scrollbar">
             <div class="p-4">
+                <Show when=move || recent.with(|r| r.len() > 1)>
+                    <div class="mb-4">
+                        <h3
+                            class="text-sm font-medium text-base-content/70 mb-2"
+                            title="Ctrl+` cycles through these, Ctrl+Shift+` goes back"
+                        >
+                            "Quick Switch"
+                        </h3>
+                        <div class="flex flex-col gap-1">
+                            <For
+                                each=move || recent.get()
+                                key=|(id, _)| id.clone()
+                                children={
+                                    let on_conversation_select = on_conversation_select.clone();
+                                    move |(id, title)| {
+                                        let active = {
+                                            let id = id.clone();
+                                            move || current_conversation_id.with(|c| c.as_deref() == Some(id.as_str()))
+                                        };
+                                        let on_conversation_select = on_conversation_select.clone();
+                                        view! {
+                                            <button
+                                                class=move || {
+                                                    format!(
+                                                        "btn btn-xs justify-start truncate font-normal {}",
+                                                        if active() { "btn-active" } else { "btn-ghost" },
+                                                    )
+                                                }
+                                                title=title.clone()
+                                                on:click=move |_| on_conversation_select(id.clone())
+                                            >
+                                                <i data-lucide="clock" class="h-3 w-3 shrink-0 opacity-60"></i>
+                                                <span class="truncate">{title}</span>
+                                            </button>
+                                        }
+                                    }
+                                }
+                            />
+                        </div>
+                    </div>
+                </Show>
                 <div class="flex justify-between items-center mb-3">
                     <h3 class="text-sm font-medium text-base-content/70">"Recent Conversations"</h3>
                     <Button
@@ -155,6 +218,7 @@ where
                             let id = conv.id.clone();
                             let title = conv.title.clone();
                             let updated_at = conv.updated_at;
+                            let is_branch = conv.root_id.is_some();
                             let on_click = {
                                 let id = id.clone();
                                 let on_conversation_select = on_conversation_select.clone();
@@ -185,7 +249,10 @@ where
                                     on:click=on_click
                                 >
                                     <div class="flex flex-col items-start w-full">
-                                        <span class="text-sm font-medium truncate w-full">
+                                        <span class="text-sm font-medium truncate w-full flex items-center gap-1">
+                                            <Show when=move || is_branch>
+                                                <i data-lucide="git-branch" class="h-3.5 w-3.5 opacity-60 shrink-0"></i>
+                                            </Show>
                                             {title}
                                         </span>
                                         <span class="text-xs opacity-60">{formatted_date}</span>
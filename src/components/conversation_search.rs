@@ -0,0 +1,130 @@
+use crate::models::MessageRole;
+use crate::storage::{ConversationStorage, MessageSearchHit};
+use crate::utils::format::FormatUtils;
+use gloo_timers::future::TimeoutFuture;
+use leptos::prelude::*;
+use leptos::task::spawn_local;
+
+// Time for the selected conversation to render before scrolling to the message
+const JUMP_DELAY_MS: u32 = 150;
+// How long the matched message stays highlighted
+const HIGHLIGHT_MS: u32 = 2000;
+
+/// Full-text search across every stored conversation
+#[component]
+pub fn ConversationSearch<F>(
+    storage: ReadSignal<Option<ConversationStorage>>,
+    on_conversation_select: F,
+) -> impl IntoView
+where
+    F: Fn(String) + Clone + Send + 'static,
+{
+    let (query, set_query) = signal(String::new());
+    let (hits, set_hits) = signal::<Vec<MessageSearchHit>>(vec![]);
+
+    let run_search = move |q: String| {
+        let results = match storage.get_untracked() {
+            Some(s) if !q.trim().is_empty() => s.search_messages(&q).unwrap_or_else(|e| {
+                log::error!("Conversation search failed: {:?}", e);
+                Vec::new()
+            }),
+            _ => Vec::new(),
+        };
+        set_hits.set(results);
+    };
+
+    view! {
+        <div class="px-4 pt-4">
+            <label class="input input-bordered input-sm flex items-center gap-2">
+                <i data-lucide="search" class="h-4 w-4 opacity-60"></i>
+                <input
+                    type="search"
+                    class="grow"
+                    placeholder="Search all chats"
+                    prop:value=query
+                    on:input=move |ev| {
+                        let q = event_target_value(&ev);
+                        set_query.set(q.clone());
+                        run_search(q);
+                    }
+                />
+            </label>
+            <Show when=move || !query.get().trim().is_empty()>
+                <div class="mt-2 max-h-72 overflow-y-auto custom-scrollbar space-y-1">
+                    <Show when=move || hits.get().is_empty()>
+                        <p class="text-xs text-base-content/60 py-2 text-center">"No matching messages"</p>
+                    </Show>
+                    <For
+                        each=move || hits.get()
+                        key=|hit| format!("{}:{}", hit.conversation_id, hit.message_id)
+                        children={
+                            let on_conversation_select = on_conversation_select.clone();
+                            move |hit| {
+                                let on_click = {
+                                    let on_conversation_select = on_conversation_select.clone();
+                                    let conversation_id = hit.conversation_id.clone();
+                                    let message_id = hit.message_id.clone();
+                                    move |_| {
+                                        on_conversation_select(conversation_id.clone());
+                                        jump_to_message(message_id.clone());
+                                    }
+                                };
+                                let role = match hit.role {
+                                    MessageRole::User => "You",
+                                    MessageRole::Assistant => "Assistant",
+                                    MessageRole::System => "System",
+                                };
+                                view! {
+                                    <button
+                                        class="btn btn-ghost w-full justify-start text-left p-2 h-auto min-h-0 font-normal hover:bg-base-300"
+                                        on:click=on_click
+                                    >
+                                        <div class="flex flex-col items-start w-full gap-0.5">
+                                            <span class="text-xs font-medium truncate w-full">
+                                                {hit.conversation_title.clone()}
+                                            </span>
+                                            <span class="text-xs opacity-80 line-clamp-2">
+                                                {hit
+                                                    .snippet
+                                                    .into_iter()
+                                                    .map(|(text, is_match)| {
+                                                        if is_match {
+                                                            view! { <mark class="bg-warning/40 rounded px-0.5">{text}</mark> }.into_any()
+                                                        } else {
+                                                            view! { <span>{text}</span> }.into_any()
+                                                        }
+                                                    })
+                                                    .collect_view()}
+                                            </span>
+                                            <span class="text-[10px] opacity-50">
+                                                {format!("{} · {}", role, FormatUtils::format_relative_time(hit.timestamp))}
+                                            </span>
+                                        </div>
+                                    </button>
+                                }
+                            }
+                        }
+                    />
+                </div>
+            </Show>
+        </div>
+    }
+}
+
+/// Scroll the rendered message into view and flash a highlight ring around it
+pub(crate) fn jump_to_message(message_id: String) {
+    spawn_local(async move {
+        TimeoutFuture::new(JUMP_DELAY_MS).await;
+        let Some(el) = web_sys::window()
+            .and_then(|w| w.document())
+            .and_then(|d| d.get_element_by_id(&format!("message-{}", message_id)))
+        else {
+            return;
+        };
+        el.scroll_into_view();
+        let classes = el.class_list();
+        let _ = classes.add_2("ring-2", "ring-warning");
+        TimeoutFuture::new(HIGHLIGHT_MS).await;
+        let _ = classes.remove_2("ring-2", "ring-warning");
+    });
+}
@@ -0,0 +1,65 @@
+use crate::state::webllm_state_simple::use_webllm_state;
+use leptos::prelude::*;
+
+/// What this browser can run: WebGPU, WebAssembly SIMD and device memory, with the
+/// models hidden because of it
+#[component]
+pub fn DeviceReport() -> impl IntoView {
+    let ctx = use_webllm_state();
+    let report = Signal::derive(move || {
+        let device = ctx.get_capabilities()?;
+        let models = ctx.get_available_models();
+        let hidden: Vec<(String, String)> = models
+            .iter()
+            .filter_map(|m| {
+                device
+                    .check(m)
+                    .err()
+                    .map(|reason| (m.name.clone(), reason.to_string()))
+            })
+            .collect();
+        Some((device.report(), hidden))
+    });
+
+    view! {
+        <div class="flex flex-col gap-2 text-sm" id="device-report">
+            <div class="font-medium">"This device"</div>
+            {move || match report.get() {
+                None => view! { <p class="text-base-content/60">"Checking what this browser supports…"</p> }.into_any(),
+                Some((rows, hidden)) => {
+                    view! {
+                        <table class="table table-xs">
+                            <tbody>
+                                {rows
+                                    .into_iter()
+                                    .map(|(label, finding)| view! {
+                                        <tr>
+                                            <td class="text-base-content/70">{label}</td>
+                                            <td>{finding}</td>
+                                        </tr>
+                                    })
+                                    .collect_view()}
+                            </tbody>
+                        </table>
+                        <Show
+                            when={
+                                let none_hidden = hidden.is_empty();
+                                move || !none_hidden
+                            }
+                            fallback=|| view! { <p class="text-base-content/60">"Every listed model can run here."</p> }
+                        >
+                            <div class="text-base-content/70">"Hidden from the model list:"</div>
+                            <ul class="list-disc list-inside text-xs text-base-content/70">
+                                {hidden
+                                    .iter()
+                                    .map(|(name, reason)| view! { <li>{format!("{}: {}", name, reason)}</li> })
+                                    .collect_view()}
+                            </ul>
+                        </Show>
+                    }
+                        .into_any()
+                }
+            }}
+        </div>
+    }
+}
@@ -0,0 +1,435 @@
+use crate::features::graphrag::GraphRAGPipeline;
+use crate::models::graph_store::GraphStore;
+use crate::models::graphrag::DocumentIndex;
+use crate::models::SourceAttribution;
+use crate::router::RouterContext;
+use crate::state::KnowledgeStorageContext;
+use crate::storage::{Conversation, ConversationStorage};
+use crate::utils::format::FormatUtils;
+use crate::utils::storage::StorageInfo;
+use leptos::prelude::*;
+use std::collections::{HashMap, HashSet};
+
+/// Entity or concept the knowledge graph links to passages of a file
+#[derive(Clone, Debug, PartialEq)]
+pub struct MentionedEntity {
+    pub label: String,
+    pub node_type: String,
+    /// Passages of the file linked to it
+    pub passages: usize,
+}
+
+/// Conversation with replies citing a file
+#[derive(Clone, Debug, PartialEq)]
+pub struct Citation {
+    pub conversation_id: String,
+    pub title: String,
+    pub replies: usize,
+    pub last_cited: f64,
+}
+
+/// Non-document nodes connected to the document nodes of `passage_ids`, the most
+/// linked first
+pub fn mentioned_entities(store: &GraphStore, passage_ids: &HashSet<&str>) -> Vec<MentionedEntity> {
+    let documents: HashSet<&str> = store
+        .nodes
+        .iter()
+        .filter(|n| {
+            n.node_type == "document"
+                && n.source_document_id
+                    .as_deref()
+                    .is_some_and(|d| passage_ids.contains(d))
+        })
+        .map(|n| n.id.as_str())
+        .collect();
+    let mut linked: HashMap<&str, HashSet<&str>> = HashMap::new();
+    for e in &store.edges {
+        match (
+            documents.contains(e.from.as_str()),
+            documents.contains(e.to.as_str()),
+        ) {
+            (true, false) => linked
+                .entry(e.to.as_str())
+                .or_default()
+                .insert(e.from.as_str()),
+            (false, true) => linked
+                .entry(e.from.as_str())
+                .or_default()
+                .insert(e.to.as_str()),
+            _ => false,
+        };
+    }
+    let mut out: Vec<MentionedEntity> = store
+        .nodes
+        .iter()
+        .filter(|n| n.node_type != "document")
+        .filter_map(|n| {
+            Some(MentionedEntity {
+                label: n.label.clone().unwrap_or_else(|| n.id.clone()),
+                node_type: n.node_type.clone(),
+                passages: linked.get(n.id.as_str())?.len(),
+            })
+        })
+        .collect();
+    out.sort_by(|a, b| {
+        b.passages
+            .cmp(&a.passages)
+            .then_with(|| a.label.cmp(&b.label))
+    });
+    out
+}
+
+/// File a source was cut from, from its passage anchor
+fn cited_file(source: &SourceAttribution) -> Option<&str> {
+    let anchor = source.anchor()?;
+    Some(anchor.split_once('#').map_or(anchor, |(file, _)| file))
+}
+
+/// Conversations whose replies cite `file`, most recently cited first. Side threads
+/// count for the conversation they belong to.
+pub fn citing_conversations(conversations: &[Conversation], file: &str) -> Vec<Citation> {
+    let titles: HashMap<&str, &str> = conversations
+        .iter()
+        .map(|c| (c.id.as_str(), c.title.as_str()))
+        .collect();
+    let mut out: Vec<Citation> = Vec::new();
+    for c in conversations {
+        let citing: Vec<f64> = c
+            .messages
+            .iter()
+            .filter(|m| {
+                m.metadata
+                    .as_ref()
+                    .and_then(|meta| meta.provenance.as_ref())
+                    .is_some_and(|sources| sources.iter().any(|s| cited_file(s) == Some(file)))
+            })
+            .map(|m| m.timestamp)
+            .collect();
+        if citing.is_empty() {
+            continue;
+        }
+        let id = c
+            .thread_of
+            .as_ref()
+            .map_or(c.id.as_str(), |t| t.conversation_id.as_str());
+        let last = citing.iter().copied().fold(f64::MIN, f64::max);
+        match out.iter_mut().find(|x| x.conversation_id == id) {
+            Some(existing) => {
+                existing.replies += citing.len();
+                existing.last_cited = existing.last_cited.max(last);
+            }
+            None => out.push(Citation {
+                conversation_id: id.to_string(),
+                title: titles.get(id).copied().unwrap_or(&c.title).to_string(),
+                replies: citing.len(),
+                last_cited: last,
+            }),
+        }
+    }
+    out.sort_by(|a, b| b.last_cited.total_cmp(&a.last_cited));
+    out
+}
+
+/// Everything known about one uploaded file: its extracted text, the passages it was
+/// indexed as, the entities linked to them and the conversations citing it.
+/// `focus` is the id of a passage to highlight.
+#[component]
+pub fn DocumentInspector(
+    file: String,
+    #[prop(optional)] focus: Option<String>,
+    on_close: Callback<()>,
+) -> impl IntoView {
+    let router = use_context::<RouterContext>();
+    let text = KnowledgeStorageContext::new().buffer_document(&file);
+    let chunks: Vec<DocumentIndex> = GraphRAGPipeline::new()
+        .indexed_documents()
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|d| d.source_file() == file)
+        .collect();
+    let passage_ids: HashSet<&str> = chunks.iter().map(|d| d.id.as_str()).collect();
+    let entities = mentioned_entities(&GraphStore::load().unwrap_or_default(), &passage_ids);
+    let citations = ConversationStorage::new()
+        .ok()
+        .and_then(|s| s.load_all_conversations().ok())
+        .map(|all| citing_conversations(&all, &file))
+        .unwrap_or_default();
+    let tab = RwSignal::new(if focus.is_some() { "chunks" } else { "text" });
+    let counts = [
+        ("text", "Text".to_string()),
+        ("chunks", format!("Chunks ({})", chunks.len())),
+        ("entities", format!("Entities ({})", entities.len())),
+        ("cited", format!("Cited in ({})", citations.len())),
+    ];
+    let summary = format!(
+        "{} · {} passage(s) · {} node(s)",
+        StorageInfo::format_size(text.as_ref().map_or(0, String::len)),
+        chunks.len(),
+        chunks.iter().map(|d| d.node_count).sum::<usize>(),
+    );
+
+    // Text tab: the file as extracted at upload
+    let text_view = match text {
+        Some(text) if !text.is_empty() => view! {
+            <pre class="text-xs whitespace-pre-wrap break-words font-mono">{text}</pre>
+        }
+        .into_any(),
+        _ => view! {
+            <p class="text-sm opacity-70">
+                "The extracted text is no longer in the upload buffer; the indexed passages are listed under Chunks."
+            </p>
+        }
+        .into_any(),
+    };
+
+    // Chunks tab: passages in index order, the focused one opened
+    let chunks_view = chunks
+        .into_iter()
+        .map(|d| {
+            let focused = focus.as_deref() == Some(d.id.as_str());
+            let anchor = d
+                .id
+                .split_once('#')
+                .map_or_else(|| "whole file".to_string(), |(_, a)| a.to_string());
+            let details = format!(
+                "{} · nodes: {}",
+                StorageInfo::format_size(d.size_bytes as usize),
+                d.node_count
+            );
+            let defines = (!d.symbols.is_empty()).then(|| format!("Defines: {}", d.symbols.join(", ")));
+            view! {
+                <details
+                    class=if focused { "rounded bg-primary/10 px-2 py-1" } else { "rounded bg-base-200 px-2 py-1" }
+                    open=focused
+                >
+                    <summary class="cursor-pointer text-xs flex items-center gap-2">
+                        <span class="font-medium truncate" title=d.title.clone()>{d.title}</span>
+                        <span class="badge badge-ghost badge-sm font-mono">{anchor}</span>
+                        <span class="opacity-60 ml-auto shrink-0">{details}</span>
+                    </summary>
+                    {defines.map(|defines| view! { <p class="text-xs opacity-70 mt-1">{defines}</p> })}
+                    <pre class="text-xs whitespace-pre-wrap break-words font-mono mt-1">{d.content}</pre>
+                </details>
+            }
+        })
+        .collect_view();
+
+    // Entities tab: what the graph links to the file
+    let entities_view = if entities.is_empty() {
+        view! { <p class="text-sm opacity-70">"No entities were extracted from this file."</p> }
+            .into_any()
+    } else {
+        view! {
+            <div class="flex flex-wrap gap-2">
+                {entities
+                    .into_iter()
+                    .map(|e| view! {
+                        <span
+                            class="badge badge-outline gap-1"
+                            title=format!("{} linked to {} passage(s)", e.node_type, e.passages)
+                        >
+                            {e.label}
+                            <span class="opacity-60 font-mono">{e.passages}</span>
+                        </span>
+                    })
+                    .collect_view()}
+            </div>
+        }
+        .into_any()
+    };
+
+    // Cited tab: conversations to jump back to
+    let cited_view = if citations.is_empty() {
+        view! { <p class="text-sm opacity-70">"No conversation has cited this file yet."</p> }
+            .into_any()
+    } else {
+        view! {
+            <ul class="menu w-full p-0">
+                {citations
+                    .into_iter()
+                    .map(|c| {
+                        let id = c.conversation_id.clone();
+                        view! {
+                            <li>
+                                <button
+                                    class="flex items-center justify-between gap-2"
+                                    disabled=router.is_none()
+                                    on:click=move |_| {
+                                        if let Some(router) = router {
+                                            router.set_conversation(Some(id.clone()));
+                                            on_close.run(());
+                                        }
+                                    }
+                                >
+                                    <span class="truncate">{c.title}</span>
+                                    <span class="text-xs opacity-60 shrink-0">
+                                        {format!(
+                                            "{} repl{} · {}",
+                                            c.replies,
+                                            if c.replies == 1 { "y" } else { "ies" },
+                                            FormatUtils::format_relative_time(c.last_cited),
+                                        )}
+                                    </span>
+                                </button>
+                            </li>
+                        }
+                    })
+                    .collect_view()}
+            </ul>
+        }
+        .into_any()
+    };
+
+    view! {
+        <div class="fixed inset-0 z-50 flex items-center justify-center">
+            <div class="absolute inset-0 bg-black/40" on:click=move |_| on_close.run(())></div>
+            <div class="relative bg-base-100 rounded-lg shadow-xl border border-base-300 w-[48rem] max-w-[95vw]">
+                <div class="flex items-center justify-between px-4 py-3 border-b border-base-300 gap-3">
+                    <div class="min-w-0">
+                        <h3 class="font-semibold text-base truncate" title=file.clone()>{file.clone()}</h3>
+                        <p class="text-xs opacity-70">{summary}</p>
+                    </div>
+                    <button class="btn btn-ghost btn-sm" on:click=move |_| on_close.run(())>
+                        Close
+                    </button>
+                </div>
+                <div role="tablist" class="tabs tabs-boxed tabs-sm mx-4 mt-3">
+                    {counts
+                        .into_iter()
+                        .map(|(key, label)| view! {
+                            <a
+                                role="tab"
+                                class=move || if tab.get() == key { "tab tab-active" } else { "tab" }
+                                on:click=move |_| tab.set(key)
+                            >
+                                {label}
+                            </a>
+                        })
+                        .collect_view()}
+                </div>
+                <div class="p-4 overflow-auto" style="max-height: 60vh;">
+                    <div class:hidden=move || tab.get() != "text">{text_view}</div>
+                    <div class="space-y-2" class:hidden=move || tab.get() != "chunks">{chunks_view}</div>
+                    <div class:hidden=move || tab.get() != "entities">{entities_view}</div>
+                    <div class:hidden=move || tab.get() != "cited">{cited_view}</div>
+                </div>
+            </div>
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::graph_store::{GraphEdge, GraphNode};
+    use serde_json::json;
+
+    fn node(id: &str, label: &str, node_type: &str, source: Option<&str>) -> GraphNode {
+        GraphNode {
+            id: id.to_string(),
+            label: Some(label.to_string()),
+            node_type: node_type.to_string(),
+            source_document_id: source.map(str::to_string),
+            metadata: json!({}),
+        }
+    }
+
+    fn edge(from: &str, to: &str) -> GraphEdge {
+        GraphEdge {
+            id: format!("{}->{}", from, to),
+            from: from.to_string(),
+            to: to.to_string(),
+            relation: "mentions".to_string(),
+            weight: 1.0,
+            metadata: json!({}),
+        }
+    }
+
+    fn conversation(id: &str, thread_of: Option<&str>, cites: &[(&str, f64)]) -> Conversation {
+        let messages: Vec<_> = cites
+            .iter()
+            .map(|(source, at)| {
+                json!({
+                    "id": format!("{}-{}", id, at), "role": "Assistant", "content": "", "timestamp": at,
+                    "metadata": {
+                        "graphrag_enhanced": true,
+                        "provenance": [{"source_id": source, "title": "", "confidence": 0.5}]
+                    }
+                })
+            })
+            .collect();
+        serde_json::from_value(json!({
+            "id": id, "title": format!("Chat {}", id), "created_at": 0.0, "updated_at": 0.0,
+            "messages": messages,
+            "thread_of": thread_of.map(|c| json!({"conversation_id": c, "message_id": "m"})),
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_entities_and_citations_of_a_file() {
+        let store = GraphStore {
+            version: 1,
+            nodes: vec![
+                node(
+                    "doc:1",
+                    "guide.md (part 1)",
+                    "document",
+                    Some("7:guide.md#chunk=1"),
+                ),
+                node(
+                    "doc:2",
+                    "guide.md (part 2)",
+                    "document",
+                    Some("7:guide.md#chunk=2"),
+                ),
+                node("doc:3", "other.md", "document", Some("7:other.md")),
+                node("ent:acme", "Acme", "entity", None),
+                node("ent:lyon", "Lyon", "entity", None),
+                node("ent:none", "Unrelated", "entity", None),
+            ],
+            edges: vec![
+                edge("doc:1", "ent:acme"),
+                edge("doc:2", "ent:acme"),
+                edge("ent:lyon", "doc:2"),
+                edge("doc:3", "ent:none"),
+                edge("doc:1", "doc:3"),
+            ],
+        };
+        let ids: HashSet<&str> = ["7:guide.md#chunk=1", "7:guide.md#chunk=2"].into();
+        let entities = mentioned_entities(&store, &ids);
+        let found: Vec<(&str, usize)> = entities
+            .iter()
+            .map(|e| (e.label.as_str(), e.passages))
+            .collect();
+        assert_eq!(found, vec![("Acme", 2), ("Lyon", 1)]);
+
+        let conversations = vec![
+            conversation(
+                "a",
+                None,
+                &[("7:guide.md#chunk=2", 10.0), ("3:other.md", 11.0)],
+            ),
+            conversation("b", None, &[("7:other.md", 20.0)]),
+            conversation("t", Some("a"), &[("8:guide.md#chunk=1", 30.0)]),
+            conversation("c", None, &[("5:guide.md", 5.0)]),
+        ];
+        let citations = citing_conversations(&conversations, "guide.md");
+        let found: Vec<(&str, &str, usize, f64)> = citations
+            .iter()
+            .map(|c| {
+                (
+                    c.conversation_id.as_str(),
+                    c.title.as_str(),
+                    c.replies,
+                    c.last_cited,
+                )
+            })
+            .collect();
+        // The side thread counts for its conversation
+        assert_eq!(
+            found,
+            vec![("a", "Chat a", 2, 30.0), ("c", "Chat c", 1, 5.0)]
+        );
+    }
+}
@@ -1,13 +1,37 @@
+use crate::components::collections_panel::CollectionsPanel;
+use crate::components::drop_zone::DroppedFiles;
+use crate::components::freshness_panel::FreshnessPanel;
+use crate::components::original_files_panel::OriginalFilesPanel;
+use crate::components::table_paste::{pasted_table, TablePasteOffer};
 use crate::components::ui_primitives::Button;
 use crate::error_handling::AppError;
-use crate::state::GraphRAGStateContext;
+use crate::features::graphrag::summarizer::{
+    summarize_in_background, Summarizer, COLLECTION_OVERVIEW_TITLE,
+};
+use crate::models::ActivityCategory;
+use crate::state::knowledge_storage_context::KnowledgeStorageContext;
+use crate::state::{EventBusContext, GraphRAGStateContext};
+use crate::storage::originals;
+use crate::storage::persistent::PersistentStore;
 use crate::storage::ConversationStorage;
-use crate::utils::storage::StorageUtils;
+use crate::utils::code::CodeLanguage;
+use crate::utils::dedup::{find_duplicate, merge_texts, Duplicate, Signature};
+use crate::utils::epub::epub_to_markdown;
+use crate::utils::import_queue::{
+    read_bytes, read_text, ImportQueue, ImportSettings, ImportStatus, MAX_PARALLELISM,
+};
+use crate::utils::pdf::PdfUtils;
+use crate::utils::structured::StructuredFormat;
+use crate::utils::tables::{is_csv_document, Table};
+use crate::utils::web_page::{fetch_page, normalize_url, readable_text};
 use leptos::html::Input;
 use leptos::prelude::*;
-use std::cell::RefCell;
+use std::cell::Cell;
 use std::rc::Rc;
-use wasm_bindgen_futures::JsFuture;
+
+// Cooperative summarization tasks and sentences kept per document
+const SUMMARY_WORKERS: usize = 4;
+const SUMMARY_SENTENCES: usize = 3;
 
 #[component]
 pub fn DocumentManagerSimple() -> impl IntoView {
@@ -25,15 +49,21 @@ pub fn DocumentManagerSimple() -> impl IntoView {
     let (merge, set_merge) = signal(true);
     let (error_msg, set_error_msg) = signal(Option::<String>::None);
     let (success_msg, set_success_msg) = signal(Option::<String>::None);
-    // Import progress state
-    let (_importing, set_importing) = signal(false);
-    let (import_total, set_import_total) = signal(0u32);
-    let (import_done, set_import_done) = signal(0u32);
+    // Files of the running upload and how many are read at once
+    let import_queue = RwSignal::new(ImportQueue::default());
+    let import_parallelism = RwSignal::new(ImportSettings::load().parallelism);
+    // Whether uploaded files are kept as they were, and a count of the ones kept
+    let keep_originals = RwSignal::new(ImportSettings::load().keep_originals);
+    let originals_revision = RwSignal::new(0u32);
+    // Collection summarization progress
+    let (summarize_total, set_summarize_total) = signal(0usize);
+    let (summarize_done, set_summarize_done) = signal(0usize);
     // Removed inline knowledge search; main chat handles knowledge queries
     let file_input: NodeRef<Input> = NodeRef::new();
 
     // Optional GraphRAG context (present when provider is mounted)
     let graphrag_ctx = use_context::<GraphRAGStateContext>();
+    let events = use_context::<EventBusContext>();
     // Derived progress signal to avoid moving ctx into closures
     let ctx_for_progress = graphrag_ctx.clone();
     let index_progress = Signal::derive(move || {
@@ -41,6 +71,14 @@ pub fn DocumentManagerSimple() -> impl IntoView {
             .as_ref()
             .and_then(|c| c.index_progress().get())
     });
+    let ctx_for_staleness = graphrag_ctx.clone();
+    let staleness = Signal::derive(move || {
+        ctx_for_staleness
+            .as_ref()
+            .map(|c| c.staleness().get())
+            .unwrap_or_default()
+    });
+    let graphrag_ctx_on_stale = graphrag_ctx.clone();
 
     // Helpers
     let show_error = move |err: AppError| {
@@ -88,8 +126,7 @@ pub fn DocumentManagerSimple() -> impl IntoView {
                 Ok(()) => {
                     show_success("Import completed.");
                     // Persist current buffer for KnowledgeStorageContext
-                    let _ =
-                        StorageUtils::store_local("knowledge_upload_buffer_v1", &json_text.get());
+                    let _ = PersistentStore::write("knowledge_upload_buffer_v1", &json_text.get());
                     // Prompt to reindex now
                     let confirm = web_sys::window()
                         .and_then(|w| w.confirm_with_message("Index with GraphRAG now?").ok())
@@ -101,6 +138,9 @@ pub fn DocumentManagerSimple() -> impl IntoView {
                         // Do not show a persistent "started" banner; progress alert will be shown separately
                         set_success_msg.set(None);
                     } else {
+                        if let Some(ctx) = graphrag_ctx_on_import.clone() {
+                            ctx.refresh_staleness();
+                        }
                         set_success_msg.set(Some(
                             "Import completed. You can index later from GraphRAG settings."
                                 .to_string(),
@@ -112,8 +152,310 @@ pub fn DocumentManagerSimple() -> impl IntoView {
         }
     });
 
+    let graphrag_ctx_on_summarize = graphrag_ctx.clone();
+    let on_summarize = Box::new(move || {
+        if summarize_done.get_untracked() < summarize_total.get_untracked() {
+            return; // already running
+        }
+        let docs: Vec<_> = KnowledgeStorageContext::new()
+            .get_documents_for_indexing()
+            .into_iter()
+            .filter(|d| d.source_file() != COLLECTION_OVERVIEW_TITLE)
+            .collect();
+        if docs.is_empty() {
+            show_error(AppError::Validation(
+                "Load documents before summarizing the collection".into(),
+            ));
+            return;
+        }
+        set_summarize_done.set(0);
+        set_summarize_total.set(docs.len());
+        let ctx = graphrag_ctx_on_summarize.clone();
+        summarize_in_background(
+            docs,
+            SUMMARY_WORKERS,
+            SUMMARY_SENTENCES,
+            move |done, _total| set_summarize_done.set(done),
+            move |summaries| {
+                let overview = Summarizer::new().synthesize_overview(&summaries);
+                match KnowledgeStorageContext::new()
+                    .upsert_buffer_document(COLLECTION_OVERVIEW_TITLE, &overview)
+                {
+                    Ok(buffer) => {
+                        set_json_text.set(buffer);
+                        show_success(&format!(
+                            "Summarized {} document(s) into \"{}\".",
+                            summaries.len(),
+                            COLLECTION_OVERVIEW_TITLE
+                        ));
+                        if let Some(bus) = events {
+                            bus.record(
+                                ActivityCategory::Knowledge,
+                                format!("Summarized {} document(s)", summaries.len()),
+                            );
+                        }
+                        if let Some(ctx) = ctx {
+                            ctx.reindex_changed();
+                        }
+                    }
+                    Err(e) => show_error(AppError::Storage(format!("saving overview failed: {e}"))),
+                }
+            },
+        );
+    });
+
+    // Read files from the picker or dropped anywhere in the app into the upload
+    // buffer, then reindex what changed
+    let ingest = Rc::new(move |files: Vec<web_sys::File>| {
+        let len = files.len();
+        if len == 0 {
+            return;
+        }
+        if import_queue.with_untracked(|q| q.is_running()) {
+            show_error(AppError::Validation(
+                "Wait for the current import to finish".into(),
+            ));
+            return;
+        }
+        set_error_msg.set(None);
+        set_success_msg.set(Some(format!("Reading {} file(s)...", len)));
+        let supported: Vec<web_sys::File> = files.into_iter().filter(is_supported_upload).collect();
+        if supported.is_empty() {
+            show_error(AppError::Validation(
+                "No supported files selected (.md/.txt/.pdf/.html/.epub/.csv/.json/.yaml/.rs/.py/.ts)"
+                    .into(),
+            ));
+            return;
+        }
+        import_queue.set(ImportQueue::new(
+            supported.iter().map(|f| (f.name(), f.size())),
+        ));
+        // A bounded number of readers take files off the queue in turn
+        let parallelism = import_parallelism
+            .get_untracked()
+            .clamp(1, MAX_PARALLELISM)
+            .min(supported.len());
+        let supported = Rc::new(supported);
+        let reindexed = Rc::new(Cell::new(false));
+        for _ in 0..parallelism {
+            let supported = supported.clone();
+            let reindexed = reindexed.clone();
+            let graphrag_ctx = graphrag_ctx.clone();
+            leptos::task::spawn_local(async move {
+                while let Some(i) = import_queue.try_update(|q| q.claim()).flatten() {
+                    let file = &supported[i];
+                    let name = file.name();
+                    let result = read_upload(
+                        file,
+                        |read| import_queue.update(|q| q.set_read(i, read)),
+                        || import_queue.with_untracked(|q| q.is_cancelled(i)),
+                    )
+                    .await;
+                    if import_queue.with_untracked(|q| q.is_cancelled(i)) {
+                        // Cancelled while reading: nothing is kept
+                    } else {
+                        match result.map(|content| {
+                            let choice = check_duplicate(&name, &content);
+                            (content, choice)
+                        }) {
+                            Ok((_, DuplicateChoice::Skip(reason))) => {
+                                set_error_msg.set(Some(format!("Skipped {}: {}", name, reason)));
+                                import_queue.update(|q| q.skip(i, reason));
+                            }
+                            Ok((_, DuplicateChoice::Merge(into, merged))) => {
+                                match KnowledgeStorageContext::new()
+                                    .upsert_buffer_document(&into, &merged)
+                                {
+                                    Ok(buffer) => {
+                                        set_json_text.set(buffer);
+                                        import_queue.update(|q| q.finish(i, Ok(())));
+                                    }
+                                    Err(e) => {
+                                        set_error_msg.set(Some(format!(
+                                            "Merging {} into {} failed: {}",
+                                            name, into, e
+                                        )));
+                                        import_queue.update(|q| q.finish(i, Err(e.to_string())));
+                                    }
+                                }
+                            }
+                            Ok((content, DuplicateChoice::Import)) => {
+                                let mut current = json_text.get_untracked();
+                                if !current.is_empty() {
+                                    current.push_str("\n\n---\n\n");
+                                }
+                                current.push_str(&format!("# File: {}\n\n{}", name, content));
+                                set_json_text.set(current);
+                                let _ = PersistentStore::write(
+                                    "knowledge_upload_buffer_v1",
+                                    &json_text.get_untracked(),
+                                );
+                                set_error_msg.set(None);
+                                import_queue.update(|q| q.finish(i, Ok(())));
+                            }
+                            Err(e) => {
+                                set_success_msg.set(None);
+                                set_error_msg.set(Some(format!("Failed to read {}: {}", name, e)));
+                                web_sys::console::error_1(
+                                    &format!("Markdown upload: failed {} -> {}", name, e).into(),
+                                );
+                                import_queue.update(|q| q.finish(i, Err(e)));
+                            }
+                        }
+                    }
+                    // The reader finishing the last file starts the reindex
+                    let (running, loaded, skipped) =
+                        import_queue.with_untracked(|q| (q.is_running(), q.loaded(), q.skipped()));
+                    if !running && !reindexed.replace(true) {
+                        set_success_msg.set(match (loaded, skipped) {
+                            (0, _) => None,
+                            (_, 0) => Some(format!("Loaded {} file(s)", loaded)),
+                            _ => Some(format!(
+                                "Loaded {} file(s), skipped {} duplicate(s)",
+                                loaded, skipped
+                            )),
+                        });
+                        if loaded > 0 {
+                            if let Some(ctx) = graphrag_ctx.clone() {
+                                ctx.reindex_changed();
+                            }
+                        }
+                    }
+                    let imported = import_queue
+                        .with_untracked(|q| matches!(q.items[i].status, ImportStatus::Loaded));
+                    if imported && keep_originals.get_untracked() && originals::can_retain() {
+                        match originals::retain(file).await {
+                            Ok(_) => originals_revision.update(|r| *r += 1),
+                            Err(e) => log::warn!("Original of {} not kept: {}", name, e),
+                        }
+                    }
+                }
+            });
+        }
+    });
+
+    // Web page added by URL: fetched, reduced to its readable text and indexed under
+    // its URL so citations link back to it
+    let page_url = RwSignal::new(String::new());
+    let proxy_url = RwSignal::new(ImportSettings::load().proxy_url.unwrap_or_default());
+    let fetching_page = RwSignal::new(false);
+    let graphrag_ctx_on_url = graphrag_ctx.clone();
+    let add_from_url = Rc::new(move || {
+        if fetching_page.get_untracked() {
+            return;
+        }
+        let url = match normalize_url(&page_url.get_untracked()) {
+            Ok(url) => url,
+            Err(e) => {
+                show_error(AppError::Validation(e));
+                return;
+            }
+        };
+        let proxy = Some(proxy_url.get_untracked()).filter(|p| !p.trim().is_empty());
+        fetching_page.set(true);
+        set_error_msg.set(None);
+        set_success_msg.set(Some(format!("Fetching {}...", url)));
+        let ctx = graphrag_ctx_on_url.clone();
+        leptos::task::spawn_local(async move {
+            let fetched = fetch_page(&url, proxy.as_deref()).await;
+            fetching_page.set(false);
+            let page = match fetched {
+                Ok(page) if page.text.trim().is_empty() => {
+                    show_error(AppError::Processing(format!(
+                        "no readable text found at {}",
+                        url
+                    )));
+                    return;
+                }
+                Ok(page) => page,
+                Err(e) => {
+                    show_error(AppError::Network(e));
+                    return;
+                }
+            };
+            let saved = match check_duplicate(&url, &page.document()) {
+                DuplicateChoice::Skip(reason) => {
+                    page_url.set(String::new());
+                    show_error(AppError::Validation(format!(
+                        "{} was not added: {}",
+                        url, reason
+                    )));
+                    return;
+                }
+                DuplicateChoice::Merge(into, merged) => {
+                    KnowledgeStorageContext::new().upsert_buffer_document(&into, &merged)
+                }
+                DuplicateChoice::Import => {
+                    KnowledgeStorageContext::new().upsert_buffer_document(&url, &page.document())
+                }
+            };
+            match saved {
+                Ok(buffer) => {
+                    set_json_text.set(buffer);
+                    page_url.set(String::new());
+                    show_success(&format!(
+                        "Added \"{}\" from {}",
+                        page.title.as_deref().unwrap_or(&url),
+                        url
+                    ));
+                    if let Some(bus) = events {
+                        bus.record(
+                            ActivityCategory::Knowledge,
+                            format!("Added web page \"{}\"", url),
+                        );
+                    }
+                    if let Some(ctx) = ctx {
+                        ctx.reindex_changed();
+                    }
+                }
+                Err(e) => show_error(AppError::Storage(format!("saving the page failed: {e}"))),
+            }
+        });
+    });
+    let save_proxy = move |proxy: String| {
+        let settings = ImportSettings {
+            proxy_url: Some(proxy.trim().to_string()).filter(|p| !p.is_empty()),
+            ..ImportSettings::load()
+        };
+        proxy_url.set(settings.proxy_url.clone().unwrap_or_default());
+        if let Err(e) = settings.save() {
+            show_error(AppError::Storage(format!(
+                "saving import settings failed: {e}"
+            )));
+        }
+    };
+
+    // Files dropped onto the app are handed over once the manager is open and any
+    // running import has finished
+    if let Some(dropped) = use_context::<DroppedFiles>() {
+        let ingest = ingest.clone();
+        Effect::new(move |_| {
+            if dropped.pending() && !import_queue.with(|q| q.is_running()) {
+                ingest(dropped.take());
+            }
+        });
+    }
+
+    // Table found in the last paste, offered as CRM records or a table document
+    let pasted = RwSignal::new(None);
+    let table_done = Callback::new(move |message: String| {
+        // An indexed table was added to the upload buffer shown here
+        if let Ok(Some(buffer)) = PersistentStore::read::<String>("knowledge_upload_buffer_v1") {
+            set_json_text.set(buffer);
+        }
+        set_error_msg.set(None);
+        set_success_msg.set(Some(message));
+    });
+
     view! {
-        <div class="p-6 space-y-6">
+        <div
+            class="p-6 space-y-6"
+            on:paste=move |ev| {
+                if let Some(table) = pasted_table(&ev) {
+                    pasted.set(Some(table));
+                }
+            }
+        >
             // Header Section (simplified)
             <div class="flex flex-col sm:flex-row sm:items-center justify-between gap-4">
                 <div>
@@ -128,10 +470,10 @@ pub fn DocumentManagerSimple() -> impl IntoView {
             <div class="card bg-base-100 shadow-sm border border-base-300 rounded-xl">
                 <div class="card-body p-4">
                     <h3 class="card-title text-lg mb-3">"Quick Actions"</h3>
-                    <div class="grid grid-cols-1 sm:grid-cols-3 gap-3 w-full">
-                        <div class="tooltip" attr:data-tip="Load .md/.txt files">
+                    <div class="grid grid-cols-1 sm:grid-cols-2 lg:grid-cols-4 gap-3 w-full">
+                        <div class="tooltip" attr:data-tip="Load .md/.txt/.pdf/.html/.epub/.csv/.json/.yaml files or .rs/.py/.ts source">
                             <Button
-                                label=Signal::derive(|| "Load Markdown".to_string())
+                                label=Signal::derive(|| "Load Documents".to_string())
                                 on_click=Box::new({
                                     move || {
                                         if let Some(input) = file_input.get() {
@@ -168,6 +510,17 @@ pub fn DocumentManagerSimple() -> impl IntoView {
                                 icon_position=Signal::derive(|| "left".to_string())
                             />
                         </div>
+                        <div class="tooltip" attr:data-tip="Summarize every document into an overview">
+                            <Button
+                                label=Signal::derive(|| "Summarize Collection".to_string())
+                                on_click=on_summarize
+                                variant=Signal::derive(|| {
+                                    "btn-outline btn-lg w-full rounded-lg".to_string()
+                                })
+                                icon=Signal::derive(|| "file-stack".to_string())
+                                icon_position=Signal::derive(|| "left".to_string())
+                            />
+                        </div>
                     </div>
 
                     // Modern Toggle Switch
@@ -188,37 +541,229 @@ pub fn DocumentManagerSimple() -> impl IntoView {
                             </span>
                         </label>
                     </div>
+                    <div class="form-control">
+                        <label class="label justify-start gap-3">
+                            <input
+                                type="number"
+                                min="1"
+                                max=MAX_PARALLELISM.to_string()
+                                class="input input-bordered input-sm w-20"
+                                prop:value=move || import_parallelism.get().to_string()
+                                on:change=move |ev| {
+                                    if let Ok(n) = event_target_value(&ev).parse::<usize>() {
+                                        let settings = ImportSettings {
+                                            parallelism: n.clamp(1, MAX_PARALLELISM),
+                                            ..ImportSettings::load()
+                                        };
+                                        import_parallelism.set(settings.parallelism);
+                                        if let Err(e) = settings.save() {
+                                            show_error(AppError::Storage(format!("saving import settings failed: {e}")));
+                                        }
+                                    }
+                                }
+                            />
+                            <span class="label-text font-medium">"Files read at once"</span>
+                            <span class="label-text-alt text-base-content/60">
+                                "Lower it when importing many large files"
+                            </span>
+                        </label>
+                    </div>
+                    <Show when=originals::is_supported>
+                        <div class="form-control">
+                            <label class="label cursor-pointer justify-start gap-3">
+                                <input
+                                    type="checkbox"
+                                    class="toggle toggle-primary rounded-full"
+                                    prop:checked=move || keep_originals.get() && originals::can_retain()
+                                    disabled=move || !originals::can_retain()
+                                    on:change=move |ev| {
+                                        let settings = ImportSettings {
+                                            keep_originals: event_target_checked(&ev),
+                                            ..ImportSettings::load()
+                                        };
+                                        keep_originals.set(settings.keep_originals);
+                                        if let Err(e) = settings.save() {
+                                            show_error(AppError::Storage(format!("saving import settings failed: {e}")));
+                                        }
+                                    }
+                                />
+                                <span class="label-text font-medium">"Keep original files"</span>
+                                <span class="label-text-alt text-base-content/60">
+                                    {move || if originals::can_retain() {
+                                        "For preview and download; uses browser storage"
+                                    } else {
+                                        "Not kept while stored data is encrypted"
+                                    }}
+                                </span>
+                            </label>
+                        </div>
+                    </Show>
+
+                    // Add from URL
+                    <div class="divider my-1"></div>
+                    <div class="flex flex-col gap-2">
+                        <div class="flex gap-2">
+                            <input
+                                type="url"
+                                class="input input-bordered input-sm flex-1"
+                                placeholder="https://example.com/article"
+                                prop:value=move || page_url.get()
+                                on:input=move |ev| page_url.set(event_target_value(&ev))
+                                on:keydown={
+                                    let add_from_url = add_from_url.clone();
+                                    move |ev: leptos::ev::KeyboardEvent| {
+                                        if ev.key() == "Enter" {
+                                            add_from_url();
+                                        }
+                                    }
+                                }
+                            />
+                            <button
+                                class="btn btn-sm btn-primary"
+                                disabled=move || fetching_page.get() || page_url.with(|u| u.trim().is_empty())
+                                on:click={
+                                    let add_from_url = add_from_url.clone();
+                                    move |_| add_from_url()
+                                }
+                            >
+                                <Show when=move || fetching_page.get()>
+                                    <span class="loading loading-spinner loading-xs"></span>
+                                </Show>
+                                <i data-lucide="globe" class="h-4 w-4"></i>
+                                "Add from URL"
+                            </button>
+                        </div>
+                        <label class="label justify-start gap-3 py-0">
+                            <input
+                                class="input input-bordered input-xs flex-1 font-mono"
+                                placeholder="Optional proxy, e.g. https://proxy.example/?url= or …?u={url}"
+                                prop:value=move || proxy_url.get()
+                                on:change=move |ev| save_proxy(event_target_value(&ev))
+                            />
+                            <span class="label-text-alt text-base-content/60">
+                                "Used for sites that block reading from the browser (CORS)"
+                            </span>
+                        </label>
+                    </div>
                 </div>
             </div>
 
+            <CollectionsPanel />
+
+            <FreshnessPanel />
+
+            <OriginalFilesPanel revision=originals_revision />
+
             // Import Progress
-            <Show when=move || { import_total.get() > 0 && import_done.get() < import_total.get() }>
+            <Show when=move || import_queue.with(|q| q.is_running())>
+                <div class="card bg-base-100 shadow-sm border border-base-300 rounded-xl">
+                    <div class="card-body p-4 gap-2">
+                        <div class="flex items-center justify-between">
+                            <h3 class="card-title text-sm">"Importing files"</h3>
+                            <div class="flex items-center gap-2">
+                                <span class="text-xs opacity-70 font-mono">
+                                    {move || {
+                                        import_queue.with(|q| {
+                                            let done = q.items.iter().filter(|f| f.status.is_finished()).count();
+                                            format!("{}/{} ({:.0}%)", done, q.items.len(), q.progress() * 100.0)
+                                        })
+                                    }}
+                                </span>
+                                <button class="btn btn-ghost btn-xs" on:click=move |_| import_queue.update(|q| q.cancel_all())>
+                                    "Cancel all"
+                                </button>
+                            </div>
+                        </div>
+                        <progress
+                            class="progress progress-primary w-full"
+                            max="100"
+                            value=move || import_queue.with(|q| (q.progress() * 100.0).round())
+                        ></progress>
+                        <ul class="max-h-48 overflow-auto text-xs space-y-1">
+                            {move || {
+                                import_queue
+                                    .get()
+                                    .items
+                                    .into_iter()
+                                    .enumerate()
+                                    .map(|(i, f)| {
+                                        let state = match &f.status {
+                                            ImportStatus::Queued => "queued".to_string(),
+                                            ImportStatus::Reading if f.size_bytes > 0.0 => {
+                                                format!("{:.0}%", f.read_bytes / f.size_bytes * 100.0)
+                                            }
+                                            ImportStatus::Reading => "reading".to_string(),
+                                            ImportStatus::Loaded => "loaded".to_string(),
+                                            ImportStatus::Failed(_) => "failed".to_string(),
+                                            ImportStatus::Cancelled => "cancelled".to_string(),
+                                            ImportStatus::Skipped(_) => "duplicate".to_string(),
+                                        };
+                                        let cancellable = !f.status.is_finished();
+                                        view! {
+                                            <li class="flex items-center justify-between gap-2">
+                                                <span class="truncate" title=f.name.clone()>{f.name.clone()}</span>
+                                                <span class="flex items-center gap-1 shrink-0">
+                                                    <span class="font-mono opacity-70">{state}</span>
+                                                    <Show when=move || cancellable>
+                                                        <button
+                                                            class="btn btn-ghost btn-xs"
+                                                            title="Cancel this file"
+                                                            on:click=move |_| import_queue.update(|q| q.cancel(i))
+                                                        >
+                                                            <i data-lucide="x" class="h-3 w-3"></i>
+                                                        </button>
+                                                    </Show>
+                                                </span>
+                                            </li>
+                                        }
+                                    })
+                                    .collect_view()
+                            }}
+                        </ul>
+                    </div>
+                </div>
+            </Show>
+
+            // Summarization Progress
+            <Show when=move || { summarize_total.get() > 0 && summarize_done.get() < summarize_total.get() }>
                 <div class="card bg-base-100 shadow-sm border border-base-300 rounded-xl">
                     <div class="card-body p-4">
                         <div class="flex items-center justify-between mb-2">
-                            <h3 class="card-title text-sm">"Importing files"</h3>
+                            <h3 class="card-title text-sm">"Summarizing collection"</h3>
                             <span class="text-xs opacity-70 font-mono">
-                                {move || {
-                                    let done = import_done.get();
-                                    let total = import_total.get();
-                                    let pct = if total > 0 {
-                                        (done as f32 / total as f32) * 100.0
-                                    } else {
-                                        0.0
-                                    };
-                                    format!("{}/{} ({:.0}%)", done, total, pct)
-                                }}
+                                {move || format!("{}/{}", summarize_done.get(), summarize_total.get())}
                             </span>
                         </div>
                         <progress
-                            class="progress progress-primary w-full"
-                            max=move || import_total.get().to_string()
-                            value=move || import_done.get().to_string()
+                            class="progress progress-secondary w-full"
+                            max=move || summarize_total.get().to_string()
+                            value=move || summarize_done.get().to_string()
                         ></progress>
                     </div>
                 </div>
             </Show>
 
+            // Stale index banner with incremental reindex
+            <Show when=move || staleness.get().is_stale() && index_progress.get().is_none()>
+                <div class="alert alert-info shadow-sm rounded-lg">
+                    <i data-lucide="refresh-cw" class="w-5 h-5"></i>
+                    <span class="text-sm">{move || staleness.get().summary()}</span>
+                    <button
+                        class="btn btn-sm btn-primary"
+                        on:click={
+                            let ctx = graphrag_ctx_on_stale.clone();
+                            move |_| {
+                                if let Some(ctx) = ctx.as_ref() {
+                                    ctx.reindex_changed();
+                                }
+                            }
+                        }
+                    >
+                        "Update Index"
+                    </button>
+                </div>
+            </Show>
+
             // GraphRAG indexing status (shows percent with DaisyUI alert)
             <Show when=move || index_progress.get().is_some() fallback=|| view! { <></> }>
                 {move || {
@@ -297,6 +842,8 @@ pub fn DocumentManagerSimple() -> impl IntoView {
                 }}
             </Show>
 
+            <TablePasteOffer table=pasted on_done=table_done />
+
             // Status Messages
             <Show when=move || error_msg.get().is_some() || success_msg.get().is_some()>
                 <div class="space-y-2">
@@ -333,7 +880,7 @@ pub fn DocumentManagerSimple() -> impl IntoView {
                     <textarea
                         class="textarea textarea-bordered w-full font-mono text-sm rounded-xl focus:ring-2 focus:ring-primary/30"
                         rows="14"
-                        placeholder="Paste export data here or load markdown files above..."
+                        placeholder="Paste export data here or load documents above..."
                         prop:value=json_text
                         on:input=move |ev| {
                             let value = event_target_value(&ev);
@@ -347,121 +894,154 @@ pub fn DocumentManagerSimple() -> impl IntoView {
             <input
                 node_ref=file_input
                 type="file"
-                accept=".md,.markdown,.txt,text/markdown,text/plain"
+                accept=".md,.markdown,.txt,.pdf,.html,.htm,.epub,.csv,.tsv,.json,.yaml,.yml,.rs,.py,.ts,.tsx,text/markdown,text/plain,text/html,application/pdf,application/epub+zip,application/json,text/csv"
                 multiple
                 style="display:none"
-                on:change=move |ev| {
-                    let target: web_sys::HtmlInputElement = event_target(&ev);
-                    if let Some(files) = target.files() {
-                        web_sys::console::log_1(&"Markdown upload: change event".into());
-                        let len = files.length();
-                        if len == 0 {
-                            return;
-                        }
-                        set_error_msg.set(None);
-                        set_success_msg.set(Some(format!("Reading {} file(s)...", len)));
-                        let mut supported_total: u32 = 0;
-                        for i in 0..len {
-                            if let Some(file) = files.item(i) {
-                                let name = file.name();
-                                let mime = file.type_();
-                                let is_text = name.ends_with(".md") || name.ends_with(".markdown")
-                                    || name.ends_with(".txt") || mime == "text/markdown"
-                                    || mime == "text/plain";
-                                if is_text {
-                                    supported_total += 1;
-                                }
-                            }
+                on:change={
+                    let ingest = ingest.clone();
+                    move |ev| {
+                        let target: web_sys::HtmlInputElement = event_target(&ev);
+                        if let Some(files) = target.files() {
+                            let files: Vec<web_sys::File> =
+                                (0..files.length()).filter_map(|i| files.item(i)).collect();
+                            target.set_value("");
+                            ingest(files);
                         }
-                        if supported_total == 0 {
-                            show_error(
-                                AppError::Validation(
-                                    "No supported files selected (.md/.txt)".into(),
-                                ),
-                            );
-                            return;
-                        }
-                        set_import_done.set(0);
-                        set_import_total.set(supported_total);
-                        set_importing.set(true);
-                        let completed: Rc<RefCell<u32>> = Rc::new(RefCell::new(0));
-                        let graphrag_ctx_after = graphrag_ctx.clone();
-                        let total_supported = supported_total;
-                        let mut handled: u32 = 0;
-                        for i in 0..len {
-                            if let Some(file) = files.item(i) {
-                                let name = file.name();
-                                let mime = file.type_();
-                                let is_text = name.ends_with(".md") || name.ends_with(".markdown")
-                                    || name.ends_with(".txt") || mime == "text/markdown"
-                                    || mime == "text/plain";
-                                if !is_text {
-                                    continue;
-                                }
-                                let set_json_text = set_json_text;
-                                let set_error_msg = set_error_msg;
-                                let set_success_msg = set_success_msg;
-                                let set_import_done = set_import_done;
-                                let set_importing = set_importing;
-                                let json_text = json_text;
-                                let completed_cl = completed.clone();
-                                let graphrag_ctx_done = graphrag_ctx_after.clone();
-                                leptos::task::spawn_local(async move {
-                                    match JsFuture::from(file.text()).await {
-                                        Ok(js_val) => {
-                                            let content = js_val.as_string().unwrap_or_default();
-                                            let mut current = json_text.get_untracked();
-                                            if !current.is_empty() {
-                                                current.push_str("\n\n---\n\n");
-                                            }
-                                            current
-                                                .push_str(&format!("# File: {}\n\n{}", name, content));
-                                            set_json_text.set(current);
-                                            let _ = StorageUtils::store_local(
-                                                "knowledge_upload_buffer_v1",
-                                                &json_text.get_untracked(),
-                                            );
-                                            set_error_msg.set(None);
-                                            set_success_msg.set(Some(format!("Loaded: {}", name)));
-                                            web_sys::console::log_1(
-                                                &format!("Markdown upload: loaded {}", name).into(),
-                                            );
-                                        }
-                                        Err(e) => {
-                                            set_success_msg.set(None);
-                                            set_error_msg
-                                                .set(Some(format!("Failed to read {}: {:?}", name, e)));
-                                            web_sys::console::error_1(
-                                                &format!("Markdown upload: failed {} -> {:?}", name, e)
-                                                    .into(),
-                                            );
-                                        }
-                                    }
-                                    let mut done = completed_cl.borrow_mut();
-                                    *done += 1;
-                                    set_import_done.set(*done);
-                                    if *done == total_supported && total_supported > 0 {
-                                        set_importing.set(false);
-                                        if let Some(ctx) = graphrag_ctx_done.clone() {
-                                            ctx.reindex();
-                                        }
-                                        set_success_msg.set(None);
-                                    }
-                                });
-                                handled += 1;
-                            }
-                        }
-                        target.set_value("");
-                        web_sys::console::log_1(
-                            &format!(
-                                "Markdown upload: started reading {} supported file(s)",
-                                handled,
-                            )
-                                .into(),
-                        );
                     }
                 }
             />
         </div>
     }
 }
+
+fn confirm(message: &str) -> bool {
+    web_sys::window()
+        .and_then(|w| w.confirm_with_message(message).ok())
+        .unwrap_or(false)
+}
+
+/// What becomes of imported text that repeats a document of the knowledge base
+enum DuplicateChoice {
+    Import,
+    /// Fold the new paragraphs into the named document: (name, merged text)
+    Merge(String, String),
+    /// Leave it out, with the reason shown to the user
+    Skip(String),
+}
+
+/// Compare imported text with the buffer documents. Exact copies are skipped; for a
+/// near-duplicate the user picks between merging, importing it anyway and skipping it.
+fn check_duplicate(name: &str, content: &str) -> DuplicateChoice {
+    let documents = KnowledgeStorageContext::new().buffer_documents();
+    let signatures: Vec<Signature> = documents
+        .iter()
+        .map(|(name, text)| Signature::of(name, text))
+        .collect();
+    match find_duplicate(&signatures, &Signature::of(name, content)) {
+        None => DuplicateChoice::Import,
+        Some(Duplicate::Exact { name: original }) if original == name => {
+            DuplicateChoice::Skip("already in the knowledge base, unchanged".to_string())
+        }
+        Some(Duplicate::Exact { name: original }) => {
+            DuplicateChoice::Skip(format!("same content as {}", original))
+        }
+        Some(Duplicate::Near {
+            name: original,
+            similarity,
+        }) => {
+            let percent = (similarity * 100.0).round();
+            if confirm(&format!(
+                "{} is {}% similar to {}.\n\nMerge its new paragraphs into {}? Cancel to import or skip it instead.",
+                name, percent, original, original
+            )) {
+                let existing = documents
+                    .iter()
+                    .find(|(n, _)| *n == original)
+                    .map(|(_, text)| text.as_str())
+                    .unwrap_or_default();
+                DuplicateChoice::Merge(original.clone(), merge_texts(existing, content))
+            } else if confirm(&format!(
+                "Import {} as a separate document anyway? Cancel to skip it.",
+                name
+            )) {
+                DuplicateChoice::Import
+            } else {
+                DuplicateChoice::Skip(format!("{}% similar to {}", percent, original))
+            }
+        }
+    }
+}
+
+fn is_pdf(file: &web_sys::File) -> bool {
+    file.name().to_lowercase().ends_with(".pdf") || file.type_() == "application/pdf"
+}
+
+fn is_html(file: &web_sys::File) -> bool {
+    let name = file.name().to_lowercase();
+    name.ends_with(".html") || name.ends_with(".htm") || file.type_() == "text/html"
+}
+
+fn is_epub(file: &web_sys::File) -> bool {
+    file.name().to_lowercase().ends_with(".epub") || file.type_() == "application/epub+zip"
+}
+
+pub(crate) fn is_supported_upload(file: &web_sys::File) -> bool {
+    let name = file.name();
+    let mime = file.type_();
+    is_pdf(file)
+        || is_html(file)
+        || is_epub(file)
+        || name.ends_with(".md")
+        || name.ends_with(".markdown")
+        || name.ends_with(".txt")
+        || is_csv_document(&name)
+        || StructuredFormat::of(&name).is_some()
+        || CodeLanguage::of(&name).is_some()
+        || mime == "text/markdown"
+        || mime == "text/plain"
+        || mime == "application/json"
+        || mime == "text/csv"
+}
+
+/// Read an uploaded file as buffer text; PDFs are extracted page by page with page markers,
+/// HTML pages and EPUB books become Markdown with their headings, JSON/YAML/CSV files
+/// are checked to parse
+pub(crate) async fn read_upload(
+    file: &web_sys::File,
+    on_progress: impl FnMut(f64),
+    cancelled: impl Fn() -> bool,
+) -> Result<String, String> {
+    if is_epub(file) {
+        let bytes = read_bytes(file, on_progress, cancelled).await?;
+        return epub_to_markdown(&bytes).await;
+    }
+    if is_html(file) {
+        let page = readable_text(&read_text(file, on_progress, cancelled).await?);
+        if page.text.is_empty() {
+            return Err("no readable text in the page".to_string());
+        }
+        return Ok(page.document());
+    }
+    if !is_pdf(file) {
+        let text = read_text(file, on_progress, cancelled).await?;
+        // Structured files are chunked by field at indexing time, so they must parse
+        if let Some(format) = StructuredFormat::of(&file.name()) {
+            format
+                .parse(&text)
+                .map_err(|e| format!("invalid {}: {}", format.file_type().to_uppercase(), e))?;
+        }
+        // CSV datasets are indexed a row at a time
+        if is_csv_document(&file.name()) && Table::from_csv(&text).is_none() {
+            return Err("no header with at least two columns and a data row".to_string());
+        }
+        return Ok(text);
+    }
+    let bytes = read_bytes(file, on_progress, cancelled).await?;
+    let pages = PdfUtils::extract_pages(&js_sys::Uint8Array::from(bytes.as_slice()))
+        .await
+        .map_err(|e| format!("PDF extraction failed: {:?}", e))?;
+    if pages.iter().all(|p| p.trim().is_empty()) {
+        return Err("no extractable text (scanned PDF?)".to_string());
+    }
+    Ok(PdfUtils::format_pages(&pages))
+}
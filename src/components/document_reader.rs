@@ -0,0 +1,251 @@
+use crate::models::graphrag::DocumentIndex;
+use crate::state::use_narration;
+use crate::utils::tts;
+use leptos::prelude::*;
+
+/// Speeds offered in the reader, as (rate, label)
+const SPEEDS: [(f32, &str); 6] = [
+    (0.75, "0.75x"),
+    (1.0, "1x"),
+    (1.25, "1.25x"),
+    (1.5, "1.5x"),
+    (1.75, "1.75x"),
+    (2.0, "2x"),
+];
+
+/// A document shown paragraph by paragraph and read aloud continuously, with the
+/// paragraph being read highlighted. Clicking a paragraph jumps there.
+#[component]
+pub fn DocumentReader(document: DocumentIndex, on_close: Callback<()>) -> impl IntoView {
+    let narration = use_narration();
+    let supported = tts::is_supported();
+    let owner = document.id.clone();
+    let paragraphs = StoredValue::new(tts::paragraphs(&document.content));
+    let total = paragraphs.with_value(|p| p.len());
+    // Where playback starts when idle; follows the paragraph being read otherwise
+    let cursor = RwSignal::new(0usize);
+    let current = Memo::new({
+        let owner = owner.clone();
+        move |_| {
+            narration
+                .playback()
+                .get()
+                .filter(|p| p.owner == owner)
+                .map(|p| (p.paragraph, p.paused))
+        }
+    });
+
+    Effect::new(move |_| {
+        let Some((paragraph, _)) = current.get() else {
+            return;
+        };
+        cursor.set(paragraph);
+        if let Some(el) = web_sys::window()
+            .and_then(|w| w.document())
+            .and_then(|d| d.get_element_by_id(&format!("reader-paragraph-{}", paragraph)))
+        {
+            el.scroll_into_view();
+        }
+    });
+
+    // Closing the reader ends its playback
+    on_cleanup({
+        let owner = owner.clone();
+        move || {
+            if narration
+                .playback()
+                .get_untracked()
+                .is_some_and(|p| p.owner == owner)
+            {
+                narration.stop();
+            }
+        }
+    });
+
+    let go_to = move |paragraph: usize| {
+        let paragraph = paragraph.min(total.saturating_sub(1));
+        if current.get_untracked().is_some() {
+            narration.seek(paragraph);
+        } else {
+            cursor.set(paragraph);
+        }
+    };
+    let play_pause = {
+        let owner = owner.clone();
+        move || {
+            if current.get_untracked().is_some() {
+                narration.toggle_pause();
+            } else {
+                let start = cursor.get_untracked();
+                paragraphs.with_value(|p| narration.play(&owner, p.clone(), start));
+            }
+        }
+    };
+    let step = move |forward: bool| {
+        let at = cursor.get_untracked();
+        go_to(if forward {
+            at + 1
+        } else {
+            at.saturating_sub(1)
+        });
+    };
+    // Clicking a paragraph reads from there, starting playback if needed
+    let read_from = {
+        let play_pause = play_pause.clone();
+        move |paragraph: usize| {
+            let idle = current.get_untracked().is_none();
+            go_to(paragraph);
+            if idle {
+                play_pause();
+            }
+        }
+    };
+
+    view! {
+        <div class="fixed inset-0 z-50 flex items-center justify-center">
+            <div class="absolute inset-0 bg-black/40" on:click=move |_| on_close.run(())></div>
+            <div class="relative bg-base-100 rounded-lg shadow-xl border border-base-300 w-[48rem] max-w-[95vw]">
+                <div class="flex items-center justify-between px-4 py-3 border-b border-base-300">
+                    <h3 class="font-semibold text-base truncate" title=document.title.clone()>
+                        {document.title.clone()}
+                    </h3>
+                    <button class="btn btn-ghost btn-sm" on:click=move |_| on_close.run(())>
+                        Close
+                    </button>
+                </div>
+                <Show
+                    when=move || supported
+                    fallback=|| {
+                        view! {
+                            <p class="px-4 py-2 text-xs opacity-70 border-b border-base-300">
+                                "Reading aloud is not supported in this browser."
+                            </p>
+                        }
+                    }
+                >
+                    <div class="flex flex-wrap items-center gap-2 px-4 py-2 border-b border-base-300">
+                        <button
+                            class="btn btn-ghost btn-sm"
+                            title="Previous paragraph"
+                            disabled=move || cursor.get() == 0
+                            on:click=move |_| step(false)
+                        >
+                            <i data-lucide="skip-back" class="h-4 w-4"></i>
+                        </button>
+                        <button
+                            class="btn btn-primary btn-sm w-24"
+                            on:click={
+                                let play_pause = play_pause.clone();
+                                move |_| play_pause()
+                            }
+                        >
+                            {move || match current.get() {
+                                Some((_, false)) => "Pause",
+                                Some((_, true)) => "Resume",
+                                None => "Play",
+                            }}
+                        </button>
+                        <button
+                            class="btn btn-ghost btn-sm"
+                            title="Next paragraph"
+                            disabled=move || cursor.get() + 1 >= total
+                            on:click=move |_| step(true)
+                        >
+                            <i data-lucide="skip-forward" class="h-4 w-4"></i>
+                        </button>
+                        <button
+                            class="btn btn-ghost btn-sm"
+                            title="Stop"
+                            disabled=move || current.get().is_none()
+                            on:click=move |_| narration.stop()
+                        >
+                            <i data-lucide="square" class="h-4 w-4"></i>
+                        </button>
+                        <input
+                            type="range"
+                            class="range range-xs flex-1 min-w-32"
+                            min="0"
+                            max=total.saturating_sub(1).to_string()
+                            prop:value=move || cursor.get().to_string()
+                            title="Position"
+                            on:change=move |ev| {
+                                if let Ok(i) = event_target_value(&ev).parse::<usize>() {
+                                    go_to(i);
+                                }
+                            }
+                        />
+                        <span class="text-xs font-mono opacity-70">
+                            {move || format!("{}/{}", (cursor.get() + 1).min(total), total)}
+                        </span>
+                        <select
+                            class="select select-bordered select-xs"
+                            title="Reading speed"
+                            on:change=move |ev| {
+                                if let Ok(rate) = event_target_value(&ev).parse::<f32>() {
+                                    narration.set_rate(rate);
+                                }
+                            }
+                        >
+                            {SPEEDS
+                                .iter()
+                                .map(|(rate, label)| {
+                                    let rate = *rate;
+                                    view! {
+                                        <option
+                                            value=rate.to_string()
+                                            selected=move || (narration.rate().get() - rate).abs() < 0.01
+                                        >
+                                            {*label}
+                                        </option>
+                                    }
+                                })
+                                .collect_view()}
+                        </select>
+                    </div>
+                </Show>
+                <div class="p-4 overflow-auto space-y-2" style="max-height: 60vh;">
+                    {move || {
+                        if total == 0 {
+                            return view! { <p class="text-sm opacity-70">"This document has no readable text."</p> }
+                                .into_any();
+                        }
+                        paragraphs
+                            .get_value()
+                            .into_iter()
+                            .enumerate()
+                            .map(|(i, text)| {
+                                let read_from = read_from.clone();
+                                view! {
+                                    <p
+                                        id=format!("reader-paragraph-{}", i)
+                                        class=move || {
+                                            let active = current.get().is_some_and(|(p, _)| p == i);
+                                            format!(
+                                                "text-sm leading-relaxed rounded px-2 py-1 transition-colors {}",
+                                                if active {
+                                                    "bg-primary/15"
+                                                } else if supported {
+                                                    "hover:bg-base-200 cursor-pointer"
+                                                } else {
+                                                    ""
+                                                },
+                                            )
+                                        }
+                                        on:click=move |_| {
+                                            if supported {
+                                                read_from(i)
+                                            }
+                                        }
+                                    >
+                                        {text}
+                                    </p>
+                                }
+                            })
+                            .collect_view()
+                            .into_any()
+                    }}
+                </div>
+            </div>
+        </div>
+    }
+}
@@ -0,0 +1,131 @@
+use crate::components::document_manager_simple::is_supported_upload;
+use crate::router::{Modal, RouterContext};
+use crate::utils::icons::schedule_icon_render;
+use leptos::prelude::*;
+
+/// Files dropped onto the app, waiting for the Document Manager to read them
+#[derive(Clone, Copy)]
+pub struct DroppedFiles(RwSignal<Vec<web_sys::File>, LocalStorage>);
+
+impl Default for DroppedFiles {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DroppedFiles {
+    pub fn new() -> Self {
+        Self(RwSignal::new_local(Vec::new()))
+    }
+
+    pub fn push(&self, files: Vec<web_sys::File>) {
+        self.0.update(|f| f.extend(files));
+    }
+
+    pub fn pending(&self) -> bool {
+        self.0.with(|f| !f.is_empty())
+    }
+
+    pub fn take(&self) -> Vec<web_sys::File> {
+        let mut taken = Vec::new();
+        self.0.update(|f| taken = std::mem::take(f));
+        taken
+    }
+}
+
+fn carries_files(ev: &web_sys::DragEvent) -> bool {
+    ev.data_transfer()
+        .is_some_and(|dt| dt.types().includes(&"Files".into(), 0))
+}
+
+/// Overlay shown while files are dragged over the window. Dropped files the upload
+/// accepts go to the Document Manager, which is opened to show their progress.
+#[component]
+pub fn GlobalDropZone(set_status_message: WriteSignal<String>) -> impl IntoView {
+    let dropped = use_context::<DroppedFiles>();
+    let router = use_context::<RouterContext>();
+    // dragenter/dragleave fire for every element crossed; the overlay stays up
+    // until as many leaves as enters were seen
+    let depth = RwSignal::new(0i32);
+
+    let enter = window_event_listener(leptos::ev::dragenter, move |ev| {
+        if carries_files(&ev) {
+            ev.prevent_default();
+            depth.update(|d| *d += 1);
+        }
+    });
+    let over = window_event_listener(leptos::ev::dragover, move |ev| {
+        if carries_files(&ev) {
+            // Accepting dragover is what makes the window a drop target
+            ev.prevent_default();
+            if let Some(dt) = ev.data_transfer() {
+                dt.set_drop_effect("copy");
+            }
+        }
+    });
+    let leave = window_event_listener(leptos::ev::dragleave, move |ev| {
+        if carries_files(&ev) {
+            depth.update(|d| *d = (*d - 1).max(0));
+        }
+    });
+    let land = window_event_listener(leptos::ev::drop, move |ev| {
+        if !carries_files(&ev) {
+            return;
+        }
+        ev.prevent_default();
+        depth.set(0);
+        let files: Vec<web_sys::File> = ev
+            .data_transfer()
+            .and_then(|dt| dt.files())
+            .map(|list| (0..list.length()).filter_map(|i| list.item(i)).collect())
+            .unwrap_or_default();
+        let total = files.len();
+        let supported: Vec<web_sys::File> = files.into_iter().filter(is_supported_upload).collect();
+        if supported.is_empty() {
+            set_status_message.set(
+                "No supported files dropped (.md/.txt/.pdf/.json/.yaml/.rs/.py/.ts)".to_string(),
+            );
+            return;
+        }
+        let skipped = total - supported.len();
+        set_status_message.set(if skipped > 0 {
+            format!(
+                "Importing {} dropped file(s), {} unsupported skipped",
+                supported.len(),
+                skipped
+            )
+        } else {
+            format!("Importing {} dropped file(s)", supported.len())
+        });
+        if let Some(dropped) = dropped {
+            dropped.push(supported);
+        }
+        if let Some(router) = router {
+            router.open(Modal::Documents);
+        }
+    });
+    on_cleanup(move || {
+        enter.remove();
+        over.remove();
+        leave.remove();
+        land.remove();
+    });
+
+    Effect::new(move |_| {
+        if depth.get() > 0 {
+            schedule_icon_render();
+        }
+    });
+
+    view! {
+        <Show when=move || depth.get() > 0>
+            <div class="fixed inset-0 z-[60] pointer-events-none flex items-center justify-center bg-base-100/70">
+                <div class="border-4 border-dashed border-primary rounded-2xl px-10 py-8 text-center bg-base-100 shadow-xl">
+                    <i data-lucide="upload" class="h-10 w-10 mx-auto text-primary"></i>
+                    <p class="mt-3 text-lg font-semibold">"Drop files to add them to the knowledge base"</p>
+                    <p class="text-sm text-base-content/70">".md .txt .pdf .json .yaml and source files"</p>
+                </div>
+            </div>
+        </Show>
+    }
+}
@@ -0,0 +1,201 @@
+use crate::components::document_inspector::DocumentInspector;
+use crate::features::graphrag::GraphRAGPipeline;
+use crate::models::freshness::{DocumentExpiry, Freshness, MS_PER_DAY};
+use crate::state::GraphRAGStateContext;
+use crate::utils::datetime::CivilDate;
+use leptos::prelude::*;
+
+// Days added by the "extend" action on an expired document
+const EXTEND_DAYS: f64 = 30.0;
+
+fn indexed_files() -> Vec<String> {
+    let mut files: Vec<String> = GraphRAGPipeline::new()
+        .indexed_documents()
+        .unwrap_or_default()
+        .iter()
+        .map(|d| d.source_file().to_string())
+        .collect();
+    files.sort();
+    files.dedup();
+    files
+}
+
+fn update_expiry(
+    expiry: RwSignal<DocumentExpiry>,
+    error: RwSignal<Option<String>>,
+    f: impl FnOnce(&mut DocumentExpiry),
+) {
+    expiry.update(f);
+    match expiry.with_untracked(|e| e.save()) {
+        Ok(()) => error.set(None),
+        Err(e) => error.set(Some(format!("Expiry dates not saved: {}", e))),
+    }
+}
+
+fn iso_date(ms: f64) -> String {
+    CivilDate::from_days((ms / MS_PER_DAY).floor() as i64).iso()
+}
+
+/// Optional expiry dates for uploaded files. Expired files are left out of retrieval
+/// unless the GraphRAG settings include them, and are listed for review here.
+#[component]
+pub fn FreshnessPanel() -> impl IntoView {
+    let graphrag_ctx = use_context::<GraphRAGStateContext>();
+    let indexing = graphrag_ctx.as_ref().map(|c| c.is_indexing());
+    let expiry = RwSignal::new(DocumentExpiry::load().unwrap_or_default());
+    let error = RwSignal::new(None::<String>);
+    let include_expired = GraphRAGPipeline::new().config().include_expired;
+
+    // Uploaded files, reloaded whenever an indexing run ends
+    let files = RwSignal::new(Vec::<String>::new());
+    Effect::new(move |_| {
+        if !indexing.is_some_and(|i| i.get()) {
+            files.set(indexed_files());
+        }
+    });
+    // File open in the inspector
+    let inspecting = RwSignal::new(None::<String>);
+    let expired = Signal::derive(move || {
+        let now = js_sys::Date::now();
+        files.with(|f| expiry.with(|e| e.expired_files(f.iter().map(String::as_str), now)))
+    });
+
+    view! {
+        <div class="card bg-base-100 shadow-sm border border-base-300 rounded-xl">
+            <div class="card-body p-4 gap-3">
+                <div>
+                    <h3 class="card-title text-lg">"Freshness"</h3>
+                    <p class="text-xs text-base-content/60 mt-1">
+                        {if include_expired {
+                            "Set expiry dates on content that goes stale, such as pricing or schedules. Expired files are still searched: the GraphRAG settings include them."
+                        } else {
+                            "Set expiry dates on content that goes stale, such as pricing or schedules. Expired files are left out of search until their date is extended or cleared."
+                        }}
+                    </p>
+                </div>
+
+                <Show when=move || !expired.with(|e| e.is_empty())>
+                    <div class="alert alert-warning shadow-sm rounded-lg flex-col items-stretch gap-2">
+                        <div class="flex items-center gap-2">
+                            <i data-lucide="calendar-x" class="w-5 h-5"></i>
+                            <span class="text-sm font-medium">
+                                {move || format!("Review stale documents: {} file(s) expired", expired.with(|e| e.len()))}
+                            </span>
+                        </div>
+                        <For each=move || expired.get() key=|f| f.clone() let:file>
+                            {
+                                let file_to_extend = file.clone();
+                                let file_to_clear = file.clone();
+                                view! {
+                                    <div class="flex items-center justify-between gap-2 text-sm">
+                                        <span class="truncate" title=file.clone()>{file.clone()}</span>
+                                        <div class="flex gap-1 shrink-0">
+                                            <button
+                                                class="btn btn-xs"
+                                                title="Keep it for another 30 days"
+                                                on:click=move |_| {
+                                                    let file = file_to_extend.clone();
+                                                    let until = js_sys::Date::now() + EXTEND_DAYS * MS_PER_DAY;
+                                                    update_expiry(expiry, error, move |e| e.set(&file, Some(until)));
+                                                }
+                                            >
+                                                "Extend 30 days"
+                                            </button>
+                                            <button
+                                                class="btn btn-xs btn-ghost"
+                                                title="Remove the expiry date"
+                                                on:click=move |_| {
+                                                    let file = file_to_clear.clone();
+                                                    update_expiry(expiry, error, move |e| e.set(&file, None));
+                                                }
+                                            >
+                                                "Clear"
+                                            </button>
+                                        </div>
+                                    </div>
+                                }
+                            }
+                        </For>
+                    </div>
+                </Show>
+
+                <Show
+                    when=move || !files.with(|f| f.is_empty())
+                    fallback=|| view! { <p class="text-xs text-base-content/60">"No indexed files yet."</p> }
+                >
+                    <div class="space-y-1 max-h-64 overflow-auto">
+                        <For each=move || files.get() key=|f| f.clone() let:file>
+                            {
+                                let file_for_status = file.clone();
+                                let file_for_value = file.clone();
+                                let file_for_change = file.clone();
+                                let file_to_inspect = file.clone();
+                                view! {
+                                    <div class="flex items-center justify-between gap-2 text-sm">
+                                        <span class="truncate" title=file.clone()>{file.clone()}</span>
+                                        <div class="flex items-center gap-2 shrink-0">
+                                            <button
+                                                class="btn btn-ghost btn-xs"
+                                                title="Inspect: text, chunks, entities and citations"
+                                                on:click=move |_| inspecting.set(Some(file_to_inspect.clone()))
+                                            >
+                                                <i data-lucide="file-search" class="h-3 w-3"></i>
+                                            </button>
+                                            {move || {
+                                                let freshness = expiry
+                                                    .with(|e| e.freshness_of_file(&file_for_status, js_sys::Date::now()));
+                                                match freshness {
+                                                    Freshness::Expired { .. } => Some(view! {
+                                                        <span class="badge badge-error badge-sm">"Expired"</span>
+                                                    }),
+                                                    Freshness::ExpiringSoon { .. } => Some(view! {
+                                                        <span class="badge badge-warning badge-sm">"Expires soon"</span>
+                                                    }),
+                                                    _ => None,
+                                                }
+                                            }}
+                                            <input
+                                                type="date"
+                                                class="input input-bordered input-xs w-36"
+                                                title="Expiry date; empty never expires"
+                                                prop:value=move || {
+                                                    expiry
+                                                        .with(|e| e.expires_at(&file_for_value))
+                                                        .map(iso_date)
+                                                        .unwrap_or_default()
+                                                }
+                                                on:change=move |ev| {
+                                                    let until = CivilDate::parse_iso(&event_target_value(&ev))
+                                                        .map(|d| d.timestamp_ms());
+                                                    let file = file_for_change.clone();
+                                                    update_expiry(expiry, error, move |e| e.set(&file, until));
+                                                }
+                                            />
+                                        </div>
+                                    </div>
+                                }
+                            }
+                        </For>
+                    </div>
+                </Show>
+
+                <Show when=move || error.get().is_some()>
+                    <p class="text-xs text-error">{move || error.get().unwrap_or_default()}</p>
+                </Show>
+            </div>
+        </div>
+
+        {move || {
+            inspecting
+                .get()
+                .map(|file| {
+                    view! {
+                        <DocumentInspector
+                            file=file
+                            on_close=Callback::new(move |_| inspecting.set(None))
+                        />
+                    }
+                })
+        }}
+    }
+}
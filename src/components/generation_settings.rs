@@ -0,0 +1,483 @@
+use crate::models::generation::{
+    CompletionValidation, ConfidenceSettings, ContextSettings, DateTimeSettings,
+    GenerationSettings, InputLimits, PostProcessor, SuggestionSettings, ToolSettings,
+};
+use crate::utils::datetime::{is_valid_timezone, LocalNow};
+use crate::utils::generation::GenerationUtils;
+use crate::utils::tools::TOOLS;
+use leptos::prelude::*;
+
+/// Editor for global stop sequences and the completion post-processing pipeline
+#[component]
+pub fn GenerationSettingsPanel(
+    /// Called after the settings were persisted
+    #[prop(optional)]
+    on_saved: Option<Box<dyn Fn() + 'static>>,
+) -> impl IntoView {
+    let initial = GenerationUtils::load_settings();
+    let stops_input = RwSignal::new(GenerationUtils::format_stop_sequences(
+        &initial.stop_sequences,
+    ));
+    let trim_boilerplate = RwSignal::new(initial.has_processor(&PostProcessor::TrimBoilerplate));
+    let strip_prefixes = RwSignal::new(initial.has_processor(&PostProcessor::StripRolePrefixes));
+    let retry_invalid = RwSignal::new(initial.validation.enabled);
+    let rules = RwSignal::new(
+        initial
+            .post_processors
+            .into_iter()
+            .filter(|p| matches!(p, PostProcessor::RegexReplace { .. }))
+            .collect::<Vec<_>>(),
+    );
+    let soft_chars = RwSignal::new(initial.input_limits.soft_chars);
+    let hard_chars = RwSignal::new(initial.input_limits.hard_chars);
+    let summarize = RwSignal::new(initial.context.summarize);
+    let window_tokens = RwSignal::new(initial.context.window_tokens);
+    let keep_recent = RwSignal::new(initial.context.keep_recent);
+    let inject_datetime = RwSignal::new(initial.datetime.inject);
+    let include_time = RwSignal::new(initial.datetime.include_time);
+    let timezone = RwSignal::new(initial.datetime.timezone.clone().unwrap_or_default());
+    let datetime_preview = move || {
+        let tz = timezone.get();
+        let tz = Some(tz.trim()).filter(|t| !t.is_empty());
+        LocalNow::current(tz).system_line(include_time.get())
+    };
+    let tools_enabled = RwSignal::new(initial.tools.enabled);
+    let suggest_similar = RwSignal::new(initial.suggestions.similar_conversations);
+    let tool_rounds = RwSignal::new(initial.tools.max_rounds);
+    let confidence = RwSignal::new(initial.confidence.clone());
+    let disabled_tools = RwSignal::new(initial.tools.disabled.clone());
+    let new_pattern = RwSignal::new(String::new());
+    let new_replacement = RwSignal::new(String::new());
+    let error = RwSignal::new(None::<String>);
+
+    let add_rule = move || {
+        let rule = PostProcessor::RegexReplace {
+            pattern: new_pattern.get_untracked(),
+            replacement: new_replacement.get_untracked(),
+        };
+        match GenerationUtils::validate_processor(&rule) {
+            Ok(()) => {
+                rules.update(|r| r.push(rule));
+                new_pattern.set(String::new());
+                new_replacement.set(String::new());
+                error.set(None);
+            }
+            Err(e) => error.set(Some(e.to_string())),
+        }
+    };
+
+    let save = move || {
+        // Built-in processors run before user rules
+        let mut post_processors = Vec::new();
+        if strip_prefixes.get_untracked() {
+            post_processors.push(PostProcessor::StripRolePrefixes);
+        }
+        if trim_boilerplate.get_untracked() {
+            post_processors.push(PostProcessor::TrimBoilerplate);
+        }
+        post_processors.extend(rules.get_untracked());
+        let base = GenerationUtils::load_settings();
+        let (soft, hard) = (soft_chars.get_untracked(), hard_chars.get_untracked());
+        if soft == 0 || hard < soft {
+            error.set(Some(
+                "The hard input limit must be at least the soft limit".to_string(),
+            ));
+            return;
+        }
+        if window_tokens.get_untracked() <= base.sampling.max_tokens {
+            error.set(Some(format!(
+                "The context window must be larger than the {} tokens kept for the reply",
+                base.sampling.max_tokens
+            )));
+            return;
+        }
+        let confidence = confidence.get_untracked();
+        if confidence.medium_at > confidence.high_at {
+            error.set(Some(
+                "The high confidence threshold must be at least the medium one".to_string(),
+            ));
+            return;
+        }
+        let tz = timezone.get_untracked().trim().to_string();
+        if !tz.is_empty() && !is_valid_timezone(&tz) {
+            error.set(Some(format!("Unknown timezone: {}", tz)));
+            return;
+        }
+        let settings = GenerationSettings {
+            stop_sequences: GenerationUtils::parse_stop_sequences(&stops_input.get_untracked()),
+            post_processors,
+            validation: CompletionValidation {
+                enabled: retry_invalid.get_untracked(),
+                ..base.validation.clone()
+            },
+            input_limits: InputLimits {
+                soft_chars: soft,
+                hard_chars: hard,
+            },
+            context: ContextSettings {
+                summarize: summarize.get_untracked(),
+                window_tokens: window_tokens.get_untracked(),
+                keep_recent: keep_recent.get_untracked().max(1),
+                ..base.context.clone()
+            },
+            datetime: DateTimeSettings {
+                inject: inject_datetime.get_untracked(),
+                include_time: include_time.get_untracked(),
+                timezone: Some(tz).filter(|t| !t.is_empty()),
+            },
+            tools: ToolSettings {
+                enabled: tools_enabled.get_untracked(),
+                max_rounds: tool_rounds.get_untracked().max(1),
+                disabled: disabled_tools.get_untracked(),
+            },
+            suggestions: SuggestionSettings {
+                similar_conversations: suggest_similar.get_untracked(),
+            },
+            confidence,
+            ..base
+        };
+        match GenerationUtils::save_settings(&settings) {
+            Ok(()) => {
+                error.set(None);
+                if let Some(cb) = on_saved.as_ref() {
+                    cb();
+                }
+            }
+            Err(e) => error.set(Some(e.to_string())),
+        }
+    };
+
+    view! {
+        <div class="flex flex-col gap-4" id="generation-settings">
+            <div>
+                <label class="block text-sm font-medium text-base-content/70 mb-2">
+                    "Stop sequences (one per line, \\n for a newline)"
+                </label>
+                <textarea
+                    class="textarea textarea-bordered w-full min-h-[80px] font-mono text-sm"
+                    placeholder="\\nUser:"
+                    prop:value=move || stops_input.get()
+                    on:input=move |ev| stops_input.set(event_target_value(&ev))
+                ></textarea>
+                <p class="text-xs text-base-content/60 mt-1">
+                    "Conversations can add their own stop sequences from the Local Prompt dialog."
+                </p>
+            </div>
+
+            <div class="flex flex-col gap-2">
+                <span class="text-sm font-medium text-base-content/70">"Post-processing"</span>
+                <label class="label cursor-pointer justify-start gap-2">
+                    <input
+                        type="checkbox"
+                        class="checkbox checkbox-sm"
+                        prop:checked=move || strip_prefixes.get()
+                        on:change=move |ev| strip_prefixes.set(event_target_checked(&ev))
+                    />
+                    <span class="label-text">"Strip role prefixes (\"Assistant:\")"</span>
+                </label>
+                <label class="label cursor-pointer justify-start gap-2">
+                    <input
+                        type="checkbox"
+                        class="checkbox checkbox-sm"
+                        prop:checked=move || trim_boilerplate.get()
+                        on:change=move |ev| trim_boilerplate.set(event_target_checked(&ev))
+                    />
+                    <span class="label-text">"Trim boilerplate openers and closers"</span>
+                </label>
+                <label class="label cursor-pointer justify-start gap-2">
+                    <input
+                        type="checkbox"
+                        class="checkbox checkbox-sm"
+                        prop:checked=move || retry_invalid.get()
+                        on:change=move |ev| retry_invalid.set(event_target_checked(&ev))
+                    />
+                    <span class="label-text">"Retry empty, repetitive or wrong-language replies once"</span>
+                </label>
+            </div>
+
+            <div class="flex flex-col gap-2">
+                <span class="text-sm font-medium text-base-content/70">"Message length limits (characters)"</span>
+                <div class="flex gap-2">
+                    <label class="form-control flex-1">
+                        <span class="label-text text-xs">"Warn above"</span>
+                        <input
+                            type="number"
+                            min="1"
+                            class="input input-bordered input-sm"
+                            prop:value=move || soft_chars.get().to_string()
+                            on:input=move |ev| {
+                                if let Ok(v) = event_target_value(&ev).parse::<usize>() {
+                                    soft_chars.set(v);
+                                }
+                            }
+                        />
+                    </label>
+                    <label class="form-control flex-1">
+                        <span class="label-text text-xs">"Block above"</span>
+                        <input
+                            type="number"
+                            min="1"
+                            class="input input-bordered input-sm"
+                            prop:value=move || hard_chars.get().to_string()
+                            on:input=move |ev| {
+                                if let Ok(v) = event_target_value(&ev).parse::<usize>() {
+                                    hard_chars.set(v);
+                                }
+                            }
+                        />
+                    </label>
+                </div>
+                <p class="text-xs text-base-content/60">
+                    "Longer input can be saved as a knowledge document instead of being sent."
+                </p>
+            </div>
+
+            <div class="flex flex-col gap-2">
+                <span class="text-sm font-medium text-base-content/70">"Context window"</span>
+                <label class="label cursor-pointer justify-start gap-2">
+                    <input
+                        type="checkbox"
+                        class="checkbox checkbox-sm"
+                        prop:checked=move || summarize.get()
+                        on:change=move |ev| summarize.set(event_target_checked(&ev))
+                    />
+                    <span class="label-text">"Summarize older messages when the window fills up"</span>
+                </label>
+                <div class="flex gap-2">
+                    <label class="form-control flex-1">
+                        <span class="label-text text-xs">"Window (tokens)"</span>
+                        <input
+                            type="number"
+                            min="512"
+                            step="512"
+                            class="input input-bordered input-sm"
+                            prop:value=move || window_tokens.get().to_string()
+                            on:input=move |ev| {
+                                if let Ok(v) = event_target_value(&ev).parse::<u32>() {
+                                    window_tokens.set(v);
+                                }
+                            }
+                        />
+                    </label>
+                    <label class="form-control flex-1">
+                        <span class="label-text text-xs">"Recent messages kept verbatim"</span>
+                        <input
+                            type="number"
+                            min="1"
+                            class="input input-bordered input-sm"
+                            prop:value=move || keep_recent.get().to_string()
+                            on:input=move |ev| {
+                                if let Ok(v) = event_target_value(&ev).parse::<usize>() {
+                                    keep_recent.set(v);
+                                }
+                            }
+                        />
+                    </label>
+                </div>
+                <p class="text-xs text-base-content/60">
+                    "Without summarization, the oldest messages are left out instead."
+                </p>
+            </div>
+
+            <div class="flex flex-col gap-2">
+                <span class="text-sm font-medium text-base-content/70">"Date and time"</span>
+                <label class="label cursor-pointer justify-start gap-2">
+                    <input
+                        type="checkbox"
+                        class="checkbox checkbox-sm"
+                        prop:checked=move || inject_datetime.get()
+                        on:change=move |ev| inject_datetime.set(event_target_checked(&ev))
+                    />
+                    <span class="label-text">"Tell the model today's date and resolve dates like \"next Tuesday\""</span>
+                </label>
+                <label class="label cursor-pointer justify-start gap-2">
+                    <input
+                        type="checkbox"
+                        class="checkbox checkbox-sm"
+                        prop:checked=move || include_time.get()
+                        on:change=move |ev| include_time.set(event_target_checked(&ev))
+                    />
+                    <span class="label-text">"Include the time of day"</span>
+                </label>
+                <label class="form-control">
+                    <span class="label-text text-xs">"Timezone (blank for the browser's)"</span>
+                    <input
+                        class="input input-bordered input-sm font-mono"
+                        placeholder="Europe/Rome"
+                        prop:value=move || timezone.get()
+                        on:input=move |ev| timezone.set(event_target_value(&ev))
+                    />
+                </label>
+                <p class="text-xs text-base-content/60">{datetime_preview}</p>
+            </div>
+
+            <div class="flex flex-col gap-2">
+                <span class="text-sm font-medium text-base-content/70">"Suggestions"</span>
+                <label class="label cursor-pointer justify-start gap-2">
+                    <input
+                        type="checkbox"
+                        class="checkbox checkbox-sm"
+                        prop:checked=move || suggest_similar.get()
+                        on:change=move |ev| suggest_similar.set(event_target_checked(&ev))
+                    />
+                    <span class="label-text">"Point to similar past conversations when starting a new chat"</span>
+                </label>
+            </div>
+
+            <div class="flex flex-col gap-2">
+                <span class="text-sm font-medium text-base-content/70">"Answer confidence"</span>
+                <div class="grid grid-cols-3 gap-2">
+                    {confidence_input(confidence, "Retrieval weight", |c| &mut c.retrieval_weight)}
+                    {confidence_input(confidence, "Verification weight", |c| &mut c.verification_weight)}
+                    {confidence_input(confidence, "Self-assessment weight", |c| &mut c.self_assessment_weight)}
+                    {confidence_input(confidence, "High at", |c| &mut c.high_at)}
+                    {confidence_input(confidence, "Medium at", |c| &mut c.medium_at)}
+                </div>
+                <p class="text-xs text-base-content/60">
+                    "Weights are relative and spread over the signals a reply has; replies without knowledge retrieval have no retrieval signal. Scores range from 0 to 1."
+                </p>
+            </div>
+
+            <div class="flex flex-col gap-2">
+                <span class="text-sm font-medium text-base-content/70">"Tools"</span>
+                <label class="label cursor-pointer justify-start gap-2">
+                    <input
+                        type="checkbox"
+                        class="checkbox checkbox-sm"
+                        prop:checked=move || tools_enabled.get()
+                        on:change=move |ev| tools_enabled.set(event_target_checked(&ev))
+                    />
+                    <span class="label-text">"Let the model call tools while answering"</span>
+                </label>
+                <div class="flex flex-wrap gap-x-4 gap-y-1">
+                    {TOOLS
+                        .into_iter()
+                        .map(|tool| {
+                            let name = tool.name();
+                            view! {
+                                <label class="label cursor-pointer justify-start gap-2" title=tool.description()>
+                                    <input
+                                        type="checkbox"
+                                        class="checkbox checkbox-xs"
+                                        disabled=move || !tools_enabled.get()
+                                        prop:checked=move || disabled_tools.with(|d| !d.iter().any(|n| n == name))
+                                        on:change=move |ev| {
+                                            let on = event_target_checked(&ev);
+                                            disabled_tools.update(|d| {
+                                                d.retain(|n| n != name);
+                                                if !on {
+                                                    d.push(name.to_string());
+                                                }
+                                            });
+                                        }
+                                    />
+                                    <span class="label-text font-mono text-xs">{name}</span>
+                                </label>
+                            }
+                        })
+                        .collect_view()}
+                </div>
+                <label class="form-control w-40">
+                    <span class="label-text text-xs">"Tool rounds per reply"</span>
+                    <input
+                        type="number"
+                        min="1"
+                        max="8"
+                        class="input input-bordered input-sm"
+                        disabled=move || !tools_enabled.get()
+                        prop:value=move || tool_rounds.get().to_string()
+                        on:input=move |ev| {
+                            if let Ok(v) = event_target_value(&ev).parse::<u8>() {
+                                tool_rounds.set(v.clamp(1, 8));
+                            }
+                        }
+                    />
+                </label>
+                <p class="text-xs text-base-content/60">
+                    "Small models may call tools poorly; each call costs an extra completion."
+                </p>
+            </div>
+
+            <div class="flex flex-col gap-2">
+                <span class="text-sm font-medium text-base-content/70">"Regex replacements"</span>
+                <ul class="flex flex-col gap-1">
+                    {move || {
+                        rules
+                            .get()
+                            .into_iter()
+                            .enumerate()
+                            .map(|(i, r)| {
+                                view! {
+                                    <li class="flex items-center gap-2 text-sm">
+                                        <code class="flex-1 truncate">{r.label()}</code>
+                                        <button
+                                            class="btn btn-ghost btn-xs"
+                                            title="Remove rule"
+                                            on:click=move |_| rules.update(|v| {
+                                                if i < v.len() {
+                                                    v.remove(i);
+                                                }
+                                            })
+                                        >
+                                            "✕"
+                                        </button>
+                                    </li>
+                                }
+                            })
+                            .collect_view()
+                    }}
+                </ul>
+                <div class="flex gap-2">
+                    <input
+                        class="input input-bordered input-sm flex-1 font-mono"
+                        placeholder="Pattern"
+                        prop:value=move || new_pattern.get()
+                        on:input=move |ev| new_pattern.set(event_target_value(&ev))
+                    />
+                    <input
+                        class="input input-bordered input-sm flex-1 font-mono"
+                        placeholder="Replacement ($1)"
+                        prop:value=move || new_replacement.get()
+                        on:input=move |ev| new_replacement.set(event_target_value(&ev))
+                    />
+                    <button class="btn btn-sm" on:click=move |_| add_rule()>"Add"</button>
+                </div>
+            </div>
+
+            {move || error.get().map(|e| view! { <div class="alert alert-error text-sm">{e}</div> })}
+
+            <div class="flex justify-end">
+                <button class="btn btn-primary btn-sm" on:click=move |_| save()>"Save"</button>
+            </div>
+        </div>
+    }
+}
+
+/// Number input editing one field of the confidence settings
+fn confidence_input(
+    confidence: RwSignal<ConfidenceSettings>,
+    label: &'static str,
+    field: fn(&mut ConfidenceSettings) -> &mut f32,
+) -> impl IntoView {
+    view! {
+        <label class="form-control">
+            <span class="label-text text-xs">{label}</span>
+            <input
+                type="number"
+                min="0"
+                max="1"
+                step="0.05"
+                class="input input-bordered input-sm"
+                prop:value=move || {
+                    let mut c = confidence.get();
+                    format!("{:.2}", *field(&mut c))
+                }
+                on:input=move |ev| {
+                    if let Ok(v) = event_target_value(&ev).parse::<f32>() {
+                        confidence.update(|c| *field(c) = v.clamp(0.0, 1.0));
+                    }
+                }
+            />
+        </label>
+    }
+}
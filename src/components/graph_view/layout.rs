@@ -0,0 +1,389 @@
+use crate::models::graph_store::GraphStore;
+use std::collections::{HashMap, HashSet};
+
+pub const WIDTH: f64 = 800.0;
+pub const HEIGHT: f64 = 600.0;
+const MARGIN: f64 = 20.0;
+/// Nodes drawn at most; the best connected ones are kept
+pub const MAX_NODES: usize = 300;
+pub const LAYOUT_ITERATIONS: usize = 150;
+// Pull toward the center so disconnected components stay on screen
+const GRAVITY: f64 = 0.05;
+
+/// Node type and community filters applied before layout
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GraphFilter {
+    pub hidden_types: HashSet<String>,
+    pub community: Option<usize>,
+}
+
+impl GraphFilter {
+    fn accepts(&self, node_type: &str, community: Option<usize>) -> bool {
+        !self.hidden_types.contains(node_type)
+            && match self.community {
+                Some(c) => community == Some(c),
+                None => true,
+            }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlacedNode {
+    pub id: String,
+    pub label: String,
+    pub node_type: String,
+    pub community: Option<usize>,
+    pub degree: usize,
+    pub x: f64,
+    pub y: f64,
+}
+
+impl PlacedNode {
+    pub fn radius(&self) -> f64 {
+        (4.0 + (self.degree as f64).sqrt() * 2.0).min(14.0)
+    }
+}
+
+/// Edge between two placed nodes, by index into `GraphLayout::nodes`
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlacedEdge {
+    pub id: String,
+    pub from: usize,
+    pub to: usize,
+    pub relation: String,
+}
+
+/// Positioned subset of a `GraphStore` ready to draw in a `WIDTH`x`HEIGHT` viewBox
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GraphLayout {
+    pub nodes: Vec<PlacedNode>,
+    pub edges: Vec<PlacedEdge>,
+    /// Nodes matching the filter, including those dropped by `MAX_NODES`
+    pub matching_nodes: usize,
+}
+
+impl GraphLayout {
+    pub fn build(store: &GraphStore, filter: &GraphFilter, iterations: usize) -> Self {
+        let matching: Vec<usize> = store
+            .nodes
+            .iter()
+            .enumerate()
+            .filter(|(_, n)| filter.accepts(&n.node_type, n.community()))
+            .map(|(i, _)| i)
+            .collect();
+        let matching_nodes = matching.len();
+
+        // Keep the best connected nodes when there are too many to draw
+        let kept: Vec<usize> = if matching.len() > MAX_NODES {
+            let ids: HashSet<&str> = matching
+                .iter()
+                .map(|&i| store.nodes[i].id.as_str())
+                .collect();
+            let degree = degrees(store, &ids);
+            let mut ranked = matching.clone();
+            ranked.sort_by_key(|&i| {
+                std::cmp::Reverse(degree.get(store.nodes[i].id.as_str()).copied().unwrap_or(0))
+            });
+            ranked.truncate(MAX_NODES);
+            ranked.sort_unstable();
+            ranked
+        } else {
+            matching
+        };
+
+        let index: HashMap<&str, usize> = kept
+            .iter()
+            .enumerate()
+            .map(|(pos, &i)| (store.nodes[i].id.as_str(), pos))
+            .collect();
+        let mut seen_pairs: HashSet<(usize, usize)> = HashSet::new();
+        let mut edges = Vec::new();
+        for e in &store.edges {
+            let (Some(&from), Some(&to)) = (index.get(e.from.as_str()), index.get(e.to.as_str()))
+            else {
+                continue;
+            };
+            // One line per node pair; parallel relations would overlap anyway
+            if from == to || !seen_pairs.insert((from.min(to), from.max(to))) {
+                continue;
+            }
+            edges.push(PlacedEdge {
+                id: e.id.clone(),
+                from,
+                to,
+                relation: e.relation.clone(),
+            });
+        }
+
+        let pairs: Vec<(usize, usize)> = edges.iter().map(|e| (e.from, e.to)).collect();
+        let positions = force_layout(kept.len(), &pairs, iterations);
+        let mut degree = vec![0usize; kept.len()];
+        for &(a, b) in &pairs {
+            degree[a] += 1;
+            degree[b] += 1;
+        }
+        let nodes = kept
+            .iter()
+            .zip(positions)
+            .zip(degree)
+            .map(|((&i, (x, y)), degree)| {
+                let n = &store.nodes[i];
+                PlacedNode {
+                    id: n.id.clone(),
+                    label: n.label.clone().unwrap_or_else(|| n.id.clone()),
+                    node_type: n.node_type.clone(),
+                    community: n.community(),
+                    degree,
+                    x,
+                    y,
+                }
+            })
+            .collect();
+        Self {
+            nodes,
+            edges,
+            matching_nodes,
+        }
+    }
+}
+
+fn degrees<'a>(store: &'a GraphStore, ids: &HashSet<&str>) -> HashMap<&'a str, usize> {
+    let mut degree: HashMap<&str, usize> = HashMap::new();
+    for e in &store.edges {
+        if ids.contains(e.from.as_str()) && ids.contains(e.to.as_str()) {
+            *degree.entry(e.from.as_str()).or_insert(0) += 1;
+            *degree.entry(e.to.as_str()).or_insert(0) += 1;
+        }
+    }
+    degree
+}
+
+/// Fruchterman-Reingold placement of `n` nodes inside the viewBox. Deterministic:
+/// nodes start on a circle and the temperature cools linearly.
+pub fn force_layout(n: usize, edges: &[(usize, usize)], iterations: usize) -> Vec<(f64, f64)> {
+    let (cx, cy) = (WIDTH / 2.0, HEIGHT / 2.0);
+    let radius = (WIDTH.min(HEIGHT) / 2.0 - MARGIN) * 0.8;
+    let mut pos: Vec<(f64, f64)> = (0..n)
+        .map(|i| {
+            let angle = 2.0 * std::f64::consts::PI * i as f64 / n.max(1) as f64;
+            // Small per-node offset breaks the symmetry of the circle
+            let r = radius * (0.9 + 0.1 * ((i * 7919) % 13) as f64 / 13.0);
+            (cx + r * angle.cos(), cy + r * angle.sin())
+        })
+        .collect();
+    if n < 2 {
+        return pos;
+    }
+
+    let k = ((WIDTH * HEIGHT) / n as f64).sqrt();
+    let start_temp = WIDTH / 10.0;
+    for step in 0..iterations {
+        let temp = start_temp * (1.0 - step as f64 / iterations as f64);
+        let mut disp = vec![(0.0f64, 0.0f64); n];
+        for i in 0..n {
+            for j in (i + 1)..n {
+                let (dx, dy) = (pos[i].0 - pos[j].0, pos[i].1 - pos[j].1);
+                let dist = (dx * dx + dy * dy).sqrt().max(0.01);
+                let force = k * k / dist;
+                let (fx, fy) = (dx / dist * force, dy / dist * force);
+                disp[i].0 += fx;
+                disp[i].1 += fy;
+                disp[j].0 -= fx;
+                disp[j].1 -= fy;
+            }
+        }
+        for &(a, b) in edges {
+            if a == b || a >= n || b >= n {
+                continue;
+            }
+            let (dx, dy) = (pos[a].0 - pos[b].0, pos[a].1 - pos[b].1);
+            let dist = (dx * dx + dy * dy).sqrt().max(0.01);
+            let force = dist * dist / k;
+            let (fx, fy) = (dx / dist * force, dy / dist * force);
+            disp[a].0 -= fx;
+            disp[a].1 -= fy;
+            disp[b].0 += fx;
+            disp[b].1 += fy;
+        }
+        for (p, d) in pos.iter_mut().zip(disp.iter_mut()) {
+            d.0 += (cx - p.0) * GRAVITY * k;
+            d.1 += (cy - p.1) * GRAVITY * k;
+            let len = (d.0 * d.0 + d.1 * d.1).sqrt();
+            if len > 0.0 {
+                let moved = len.min(temp);
+                p.0 += d.0 / len * moved;
+                p.1 += d.1 / len * moved;
+            }
+            p.0 = p.0.clamp(MARGIN, WIDTH - MARGIN);
+            p.1 = p.1.clamp(MARGIN, HEIGHT - MARGIN);
+        }
+    }
+    pos
+}
+
+/// Node and edge ids behind a RAG answer: the document nodes of the retrieved
+/// documents, their direct neighbours and the edges connecting them
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AnswerSubgraph {
+    pub nodes: HashSet<String>,
+    pub edges: HashSet<String>,
+}
+
+impl AnswerSubgraph {
+    pub fn from_documents(store: &GraphStore, doc_ids: &[String]) -> Self {
+        let mut out = Self::default();
+        for n in &store.nodes {
+            let retrieved = doc_ids.contains(&n.id)
+                || (n.node_type == "document"
+                    && n.source_document_id
+                        .as_ref()
+                        .map(|d| doc_ids.contains(d))
+                        .unwrap_or(false));
+            if retrieved {
+                out.nodes.insert(n.id.clone());
+            }
+        }
+        let roots = out.nodes.clone();
+        for e in &store.edges {
+            if roots.contains(&e.from) || roots.contains(&e.to) {
+                out.edges.insert(e.id.clone());
+                out.nodes.insert(e.from.clone());
+                out.nodes.insert(e.to.clone());
+            }
+        }
+        out
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+}
+
+/// Distinct node types, sorted
+pub fn node_types(store: &GraphStore) -> Vec<String> {
+    let mut types: Vec<String> = store
+        .nodes
+        .iter()
+        .map(|n| n.node_type.clone())
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+    types.sort();
+    types
+}
+
+/// Distinct communities recorded on nodes, sorted
+pub fn communities(store: &GraphStore) -> Vec<usize> {
+    let mut out: Vec<usize> = store
+        .nodes
+        .iter()
+        .filter_map(|n| n.community())
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+    out.sort_unstable();
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::graph_store::{GraphEdge, GraphNode};
+    use serde_json::json;
+
+    fn node(id: &str, node_type: &str, community: usize) -> GraphNode {
+        GraphNode {
+            id: id.to_string(),
+            label: Some(id.to_string()),
+            node_type: node_type.to_string(),
+            source_document_id: node_type
+                .eq("document")
+                .then(|| id.trim_start_matches("doc:").to_string()),
+            metadata: json!({ "community": community }),
+        }
+    }
+
+    fn edge(from: &str, to: &str) -> GraphEdge {
+        GraphEdge {
+            id: format!("{}->{}", from, to),
+            from: from.to_string(),
+            to: to.to_string(),
+            relation: "mentions".to_string(),
+            weight: 1.0,
+            metadata: json!({}),
+        }
+    }
+
+    fn store() -> GraphStore {
+        GraphStore {
+            version: 1,
+            nodes: vec![
+                node("doc:a", "document", 0),
+                node("ent:x", "entity", 0),
+                node("doc:b", "document", 1),
+                node("ent:y", "entity", 1),
+            ],
+            edges: vec![
+                edge("doc:a", "ent:x"),
+                edge("doc:b", "ent:y"),
+                edge("ent:x", "ent:y"),
+            ],
+        }
+    }
+
+    fn dist(a: (f64, f64), b: (f64, f64)) -> f64 {
+        ((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2)).sqrt()
+    }
+
+    #[test]
+    fn test_force_layout_is_bounded_and_pulls_neighbours_together() {
+        // Two triangles joined by nothing
+        let edges = [(0, 1), (1, 2), (2, 0), (3, 4), (4, 5), (5, 3)];
+        let pos = force_layout(6, &edges, LAYOUT_ITERATIONS);
+        assert_eq!(pos, force_layout(6, &edges, LAYOUT_ITERATIONS));
+        assert!(pos
+            .iter()
+            .all(|&(x, y)| (MARGIN..=WIDTH - MARGIN).contains(&x)
+                && (MARGIN..=HEIGHT - MARGIN).contains(&y)));
+        let within = dist(pos[0], pos[1]).max(dist(pos[3], pos[4]));
+        let across = dist(pos[0], pos[3]).min(dist(pos[1], pos[4]));
+        assert!(within < across, "within {} across {}", within, across);
+        assert_eq!(force_layout(1, &[], 10).len(), 1);
+    }
+
+    #[test]
+    fn test_build_applies_type_and_community_filters() {
+        let all = GraphLayout::build(&store(), &GraphFilter::default(), 20);
+        assert_eq!(all.nodes.len(), 4);
+        assert_eq!(all.edges.len(), 3);
+
+        let entities = GraphFilter {
+            hidden_types: ["document".to_string()].into_iter().collect(),
+            community: None,
+        };
+        let layout = GraphLayout::build(&store(), &entities, 20);
+        assert_eq!(layout.nodes.len(), 2);
+        assert_eq!(layout.edges.len(), 1);
+        assert_eq!(layout.nodes[0].degree, 1);
+
+        let community = GraphFilter {
+            community: Some(1),
+            ..Default::default()
+        };
+        let layout = GraphLayout::build(&store(), &community, 20);
+        let ids: Vec<&str> = layout.nodes.iter().map(|n| n.id.as_str()).collect();
+        assert_eq!(ids, vec!["doc:b", "ent:y"]);
+        assert_eq!(node_types(&store()), vec!["document", "entity"]);
+        assert_eq!(communities(&store()), vec![0, 1]);
+    }
+
+    #[test]
+    fn test_answer_subgraph_covers_retrieved_documents_and_neighbours() {
+        let sub = AnswerSubgraph::from_documents(&store(), &["a".to_string()]);
+        let mut nodes: Vec<&String> = sub.nodes.iter().collect();
+        nodes.sort();
+        assert_eq!(nodes, vec!["doc:a", "ent:x"]);
+        assert_eq!(sub.edges.len(), 1);
+        assert!(AnswerSubgraph::from_documents(&store(), &[]).is_empty());
+    }
+}
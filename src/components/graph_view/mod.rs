@@ -0,0 +1,6 @@
+// Interactive knowledge graph visualization
+pub mod layout;
+pub mod view;
+
+pub use layout::{AnswerSubgraph, GraphFilter, GraphLayout};
+pub use view::GraphView;
@@ -0,0 +1,485 @@
+use super::layout::{
+    communities, node_types, AnswerSubgraph, GraphFilter, GraphLayout, PlacedNode, HEIGHT,
+    LAYOUT_ITERATIONS, MAX_NODES, WIDTH,
+};
+use crate::features::graphrag::GraphRAGPipeline;
+use crate::models::graph_store::{GraphFormat, GraphImportMode, GraphStore};
+use crate::state::GraphRAGStateContext;
+use crate::utils::download::DownloadUtils;
+use leptos::prelude::*;
+use leptos::task::spawn_local;
+use std::collections::HashMap;
+use wasm_bindgen_futures::JsFuture;
+
+const MIN_SCALE: f64 = 0.25;
+const MAX_SCALE: f64 = 6.0;
+// Characters of document content shown for a selected document node
+const PREVIEW_CHARS: usize = 600;
+
+fn type_fill(node_type: &str) -> &'static str {
+    match node_type {
+        "document" => "fill-primary",
+        "entity" => "fill-secondary",
+        _ => "fill-accent",
+    }
+}
+
+/// Interactive force-directed view of the persisted `GraphStore`: zoom and pan,
+/// node details, type/community filters and the subgraph behind the last RAG answer
+#[component]
+pub fn GraphView() -> impl IntoView {
+    let store = RwSignal::new(GraphStore::load().unwrap_or_default());
+    let contents: StoredValue<HashMap<String, String>> = StoredValue::new(
+        GraphRAGPipeline::new()
+            .indexed_documents()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|d| (d.id, d.content))
+            .collect(),
+    );
+    let all_types = Memo::new(move |_| store.with(node_types));
+    let all_communities = Memo::new(move |_| store.with(communities));
+    let total_nodes = Memo::new(move |_| store.with(|s| s.nodes.len()));
+
+    let filter = RwSignal::new(GraphFilter::default());
+    let selected = RwSignal::new(None::<String>);
+    let highlight = RwSignal::new(true);
+    let scale = RwSignal::new(1.0f64);
+    let pan = RwSignal::new((0.0f64, 0.0f64));
+    let drag_from = RwSignal::new(None::<(f64, f64)>);
+
+    let layout = Memo::new(move |_| {
+        let f = filter.get();
+        store.with(|s| GraphLayout::build(s, &f, LAYOUT_ITERATIONS))
+    });
+
+    // Retrieved documents of the last RAG result and everything they connect to
+    let graphrag_state = use_context::<GraphRAGStateContext>();
+    let last_result = graphrag_state.as_ref().map(|c| c.last_result());
+    let answer = Memo::new(move |_| {
+        let doc_ids: Vec<String> = last_result
+            .and_then(|r| r.get())
+            .map(|r| r.nodes.into_iter().map(|n| n.id).collect())
+            .unwrap_or_default();
+        store.with(|s| AnswerSubgraph::from_documents(s, &doc_ids))
+    });
+    let highlighting = move || highlight.get() && !answer.with(|a| a.is_empty());
+
+    let zoom = move |factor: f64| scale.update(|s| *s = (*s * factor).clamp(MIN_SCALE, MAX_SCALE));
+    let reset_view = move |_| {
+        scale.set(1.0);
+        pan.set((0.0, 0.0));
+    };
+
+    // Whole graph, unfiltered, in a format other tools open
+    let menu_message = RwSignal::new(None::<Result<String, Vec<String>>>);
+    let download = move |format: GraphFormat| {
+        let content = store.with_untracked(|s| s.export(format));
+        let filename = format!(
+            "knowledge-graph-{}.{}",
+            js_sys::Date::now() as u64,
+            format.extension()
+        );
+        menu_message.set(
+            DownloadUtils::download_text(&filename, &content)
+                .err()
+                .map(|e| Err(vec![format!("Download failed: {}", e)])),
+        );
+    };
+
+    // External GraphML or JSON graph, merged into the index or replacing its graph
+    let import_input = NodeRef::<leptos::html::Input>::new();
+    let import_mode = RwSignal::new(GraphImportMode::Merge);
+    let pick_import = move |mode: GraphImportMode| {
+        import_mode.set(mode);
+        if let Some(input) = import_input.get_untracked() {
+            input.click();
+        }
+    };
+    let graphrag_state = StoredValue::new_local(graphrag_state);
+    let load_import = move |ev: leptos::ev::Event| {
+        let target: web_sys::HtmlInputElement = event_target(&ev);
+        let Some(file) = target.files().and_then(|f| f.item(0)) else {
+            return;
+        };
+        target.set_value("");
+        spawn_local(async move {
+            let text = match JsFuture::from(file.text()).await {
+                Ok(text) => text.as_string().unwrap_or_default(),
+                Err(e) => {
+                    menu_message.set(Some(Err(vec![format!(
+                        "{} could not be read: {:?}",
+                        file.name(),
+                        e
+                    )])));
+                    return;
+                }
+            };
+            let imported = match GraphStore::import(&text) {
+                Ok(imported) => imported,
+                Err(problems) => {
+                    menu_message.set(Some(Err(problems)));
+                    return;
+                }
+            };
+            let mode = import_mode.get_untracked();
+            let current = store.with_untracked(|s| s.nodes.len());
+            if mode == GraphImportMode::Replace
+                && current > 0
+                && !web_sys::window()
+                    .and_then(|w| {
+                        w.confirm_with_message(&format!(
+                            "Replace the knowledge graph ({} nodes) with the {} nodes of {}?",
+                            current,
+                            imported.nodes.len(),
+                            file.name()
+                        ))
+                        .ok()
+                    })
+                    .unwrap_or(false)
+            {
+                return;
+            }
+            let Some(state) = graphrag_state.get_value() else {
+                menu_message.set(Some(Err(vec!["GraphRAG is not available".to_string()])));
+                return;
+            };
+            match state.import_graph(imported, mode) {
+                Ok(summary) => {
+                    store.set(GraphStore::load().unwrap_or_default());
+                    selected.set(None);
+                    menu_message.set(Some(Ok(format!(
+                        "Imported {}: {} node(s) added, {} updated, {} edge(s) added, {} updated",
+                        file.name(),
+                        summary.nodes_added,
+                        summary.nodes_updated,
+                        summary.edges_added,
+                        summary.edges_updated
+                    ))));
+                }
+                Err(e) => menu_message.set(Some(Err(vec![e.to_string()]))),
+            }
+        });
+    };
+
+    let toggle_type = move |t: String| {
+        filter.update(|f| {
+            if !f.hidden_types.remove(&t) {
+                f.hidden_types.insert(t);
+            }
+        })
+    };
+
+    let edge_lines = move || {
+        let l = layout.get();
+        let lit = highlighting();
+        l.edges
+            .iter()
+            .map(|e| {
+                let (a, b) = (&l.nodes[e.from], &l.nodes[e.to]);
+                let on = answer.with(|s| s.edges.contains(&e.id));
+                let class = match (lit, on) {
+                    (true, true) => "stroke-warning",
+                    (true, false) => "stroke-base-content/10",
+                    _ => "stroke-base-content/30",
+                };
+                view! {
+                    <line
+                        x1=a.x y1=a.y x2=b.x y2=b.y
+                        class=class
+                        stroke-width=if lit && on { 2.0 } else { 1.0 }
+                    />
+                }
+            })
+            .collect_view()
+    };
+
+    let node_marks = move || {
+        let lit = highlighting();
+        layout
+            .get()
+            .nodes
+            .into_iter()
+            .map(|n: PlacedNode| {
+                let on = answer.with(|s| s.nodes.contains(&n.id));
+                let fill = type_fill(&n.node_type);
+                let node_id = n.id.clone();
+                let is_selected = move || selected.with(|s| s.as_deref() == Some(node_id.as_str()));
+                let stroke = {
+                    let is_selected = is_selected.clone();
+                    move || {
+                        if is_selected() {
+                            format!("{} stroke-info", fill)
+                        } else if lit && on {
+                            format!("{} stroke-warning", fill)
+                        } else {
+                            format!("{} stroke-base-100", fill)
+                        }
+                    }
+                };
+                let id = n.id.clone();
+                let r = n.radius();
+                let opacity = if lit && !on { 0.25 } else { 1.0 };
+                view! {
+                    <g
+                        class="cursor-pointer"
+                        opacity=opacity
+                        on:mousedown=|ev| ev.stop_propagation()
+                        on:click=move |ev| {
+                            ev.stop_propagation();
+                            selected.set(Some(id.clone()));
+                        }
+                    >
+                        <circle
+                            cx=n.x cy=n.y r=r
+                            class=stroke
+                            stroke-width=move || if is_selected() || (lit && on) { 3 } else { 1 }
+                        />
+                        <text
+                            x=n.x + r + 2.0 y=n.y + 3.0
+                            class="fill-base-content text-[9px] pointer-events-none select-none"
+                        >
+                            {n.label.chars().take(24).collect::<String>()}
+                        </text>
+                    </g>
+                }
+            })
+            .collect_view()
+    };
+
+    let details = move || {
+        let id = selected.get()?;
+        let (node, relations) = store.with(|s| {
+            let node = s.nodes.iter().find(|n| n.id == id).cloned()?;
+            let label_of = |nid: &str| {
+                s.nodes
+                    .iter()
+                    .find(|n| n.id == nid)
+                    .and_then(|n| n.label.clone())
+                    .unwrap_or_else(|| nid.to_string())
+            };
+            let relations: Vec<String> = s
+                .edges
+                .iter()
+                .filter(|e| e.from == id || e.to == id)
+                .take(20)
+                .map(|e| {
+                    if e.from == id {
+                        format!("{} → {}", e.relation, label_of(&e.to))
+                    } else {
+                        format!("{} ← {}", e.relation, label_of(&e.from))
+                    }
+                })
+                .collect();
+            Some((node, relations))
+        })?;
+        let content = node.source_document_id.as_ref().and_then(|d| {
+            contents.with_value(|c| {
+                c.get(d)
+                    .map(|t| t.chars().take(PREVIEW_CHARS).collect::<String>())
+            })
+        });
+        let metadata = serde_json::to_string_pretty(&node.metadata).unwrap_or_default();
+        Some(view! {
+            <div class="flex flex-col gap-2 text-sm" id="graph-node-details">
+                <div class="flex items-start justify-between gap-2">
+                    <div>
+                        <div class="font-semibold break-all">{node.label.clone().unwrap_or_else(|| node.id.clone())}</div>
+                        <div class="text-xs text-base-content/60 break-all">{node.id.clone()}</div>
+                    </div>
+                    <button class="btn btn-ghost btn-xs" on:click=move |_| selected.set(None)>"✕"</button>
+                </div>
+                <div class="flex flex-wrap gap-1">
+                    <span class="badge badge-sm badge-outline">{node.node_type.clone()}</span>
+                    {node.community().map(|c| view! { <span class="badge badge-sm badge-ghost">{format!("community {}", c)}</span> })}
+                </div>
+                {content.map(|c| view! {
+                    <div>
+                        <div class="text-xs font-medium text-base-content/70">"Content"</div>
+                        <p class="text-xs whitespace-pre-wrap max-h-40 overflow-y-auto">{c}</p>
+                    </div>
+                })}
+                {(!relations.is_empty()).then(|| view! {
+                    <div>
+                        <div class="text-xs font-medium text-base-content/70">"Relations"</div>
+                        <ul class="text-xs list-disc list-inside">
+                            {relations.into_iter().map(|r| view! { <li class="truncate">{r}</li> }).collect_view()}
+                        </ul>
+                    </div>
+                })}
+                <div>
+                    <div class="text-xs font-medium text-base-content/70">"Metadata"</div>
+                    <pre class="text-[10px] bg-base-200 rounded p-2 max-h-40 overflow-auto">{metadata}</pre>
+                </div>
+            </div>
+        })
+    };
+
+    view! {
+        <div class="flex flex-col gap-3 min-w-[20rem]" id="graph-view">
+            <div class="flex items-start justify-end gap-2 text-sm">
+                {move || menu_message.get().map(|message| match message {
+                    Ok(done) => view! { <span class="text-xs text-success mr-auto">{done}</span> }.into_any(),
+                    Err(problems) => view! {
+                        <ul class="text-xs text-error mr-auto list-disc list-inside max-h-24 overflow-auto">
+                            {problems.into_iter().map(|p| view! { <li>{p}</li> }).collect_view()}
+                        </ul>
+                    }
+                    .into_any(),
+                })}
+                <input
+                    type="file"
+                    accept=".graphml,.xml,.json,application/json"
+                    class="hidden"
+                    node_ref=import_input
+                    on:change=load_import
+                />
+                <div class="dropdown dropdown-end">
+                    <div tabindex="0" role="button" class="btn btn-xs gap-1" title="Graph menu">
+                        <i data-lucide="share-2" class="h-3 w-3"></i>
+                        "Graph"
+                    </div>
+                    <ul tabindex="0" class="dropdown-content menu menu-sm bg-base-200 rounded-box z-20 w-56 p-1 shadow">
+                        <li class="menu-title">"Download as"</li>
+                        {GraphFormat::ALL
+                            .iter()
+                            .map(|&format| view! {
+                                <li class:disabled=move || total_nodes.get() == 0>
+                                    <a on:click=move |_| download(format)>{format.label()}</a>
+                                </li>
+                            })
+                            .collect_view()}
+                        <li class="menu-title">"Import GraphML or JSON"</li>
+                        <li><a on:click=move |_| pick_import(GraphImportMode::Merge)>"Merge into the graph"</a></li>
+                        <li><a on:click=move |_| pick_import(GraphImportMode::Replace)>"Replace the graph"</a></li>
+                    </ul>
+                </div>
+            </div>
+            <Show
+                when=move || total_nodes.get() > 0
+                fallback=|| view! {
+                    <div class="text-sm text-base-content/60 py-6 text-center">
+                        "The knowledge graph is empty. Index some documents or import a graph first."
+                    </div>
+                }
+            >
+                <div class="flex flex-wrap items-center gap-2 text-sm">
+                    {move || all_types
+                        .get()
+                        .into_iter()
+                        .map(|t| {
+                            let key = t.clone();
+                            view! {
+                                <label class="label cursor-pointer gap-1 py-0">
+                                    <input
+                                        type="checkbox"
+                                        class="checkbox checkbox-xs"
+                                        prop:checked=move || filter.with(|f| !f.hidden_types.contains(&key))
+                                        on:change={
+                                            let t = t.clone();
+                                            move |_| toggle_type(t.clone())
+                                        }
+                                    />
+                                    <span class="label-text text-xs">{t.clone()}</span>
+                                </label>
+                            }
+                        })
+                        .collect_view()}
+                    <Show when=move || all_communities.with(|c| !c.is_empty())>
+                        <select
+                            class="select select-bordered select-xs"
+                            aria-label="Filter by community"
+                            on:change=move |ev| {
+                                let v = event_target_value(&ev);
+                                filter.update(|f| f.community = v.parse().ok());
+                            }
+                        >
+                            <option value="" selected=move || filter.with(|f| f.community.is_none())>"All communities"</option>
+                            {move || all_communities
+                                .get()
+                                .into_iter()
+                                .map(|c| view! {
+                                    <option value=c.to_string() selected=move || filter.with(|f| f.community == Some(c))>
+                                        {format!("Community {}", c)}
+                                    </option>
+                                })
+                                .collect_view()}
+                        </select>
+                    </Show>
+                    <label class="label cursor-pointer gap-1 py-0" title="Highlight the subgraph behind the last knowledge answer">
+                        <input
+                            type="checkbox"
+                            class="checkbox checkbox-xs checkbox-warning"
+                            disabled=move || answer.with(|a| a.is_empty())
+                            prop:checked=move || highlight.get()
+                            on:change=move |ev| highlight.set(event_target_checked(&ev))
+                        />
+                        <span class="label-text text-xs">"Last answer"</span>
+                    </label>
+                    <div class="join ml-auto">
+                        <button class="btn btn-xs join-item" title="Zoom in" on:click=move |_| zoom(1.25)>"+"</button>
+                        <button class="btn btn-xs join-item" title="Zoom out" on:click=move |_| zoom(0.8)>"-"</button>
+                        <button class="btn btn-xs join-item" title="Reset view" on:click=reset_view>"Reset"</button>
+                    </div>
+                </div>
+
+                <div class="flex flex-col md:flex-row gap-3">
+                    <svg
+                        viewBox=format!("0 0 {} {}", WIDTH, HEIGHT)
+                        class="w-full md:flex-1 h-[60vh] bg-base-200 rounded-lg cursor-grab select-none"
+                        on:wheel=move |ev| {
+                            ev.prevent_default();
+                            zoom(if ev.delta_y() < 0.0 { 1.1 } else { 1.0 / 1.1 });
+                        }
+                        on:mousedown=move |ev| drag_from.set(Some((ev.client_x() as f64, ev.client_y() as f64)))
+                        on:mousemove=move |ev| {
+                            if let Some((x0, y0)) = drag_from.get_untracked() {
+                                let (x, y) = (ev.client_x() as f64, ev.client_y() as f64);
+                                pan.update(|p| {
+                                    p.0 += x - x0;
+                                    p.1 += y - y0;
+                                });
+                                drag_from.set(Some((x, y)));
+                            }
+                        }
+                        on:mouseup=move |_| drag_from.set(None)
+                        on:mouseleave=move |_| drag_from.set(None)
+                        on:click=move |_| selected.set(None)
+                    >
+                        <g transform=move || {
+                            let (px, py) = pan.get();
+                            let s = scale.get();
+                            // Zoom around the center of the viewBox
+                            format!(
+                                "translate({} {}) translate({} {}) scale({}) translate({} {})",
+                                px, py, WIDTH / 2.0, HEIGHT / 2.0, s, -WIDTH / 2.0, -HEIGHT / 2.0
+                            )
+                        }>
+                            <g>{edge_lines}</g>
+                            <g>{node_marks}</g>
+                        </g>
+                    </svg>
+                    <Show when=move || selected.with(|s| s.is_some())>
+                        <div class="md:w-72 bg-base-100 border border-base-300 rounded-lg p-3 max-h-[60vh] overflow-y-auto">
+                            {details}
+                        </div>
+                    </Show>
+                </div>
+
+                <div class="text-xs text-base-content/60">
+                    {move || {
+                        let l = layout.get();
+                        if l.matching_nodes > l.nodes.len() {
+                            format!(
+                                "Showing the {} best connected of {} nodes ({} edges). Filter to see more.",
+                                MAX_NODES, l.matching_nodes, l.edges.len()
+                            )
+                        } else {
+                            format!("{} nodes, {} edges", l.nodes.len(), l.edges.len())
+                        }
+                    }}
+                </div>
+            </Show>
+        </div>
+    }
+}
@@ -1,7 +1,18 @@
+use crate::features::graphrag::chunking::ChunkingStrategy;
+use crate::features::graphrag::config_bundle::{self, ImportedConfig};
+use crate::features::graphrag::content_store;
+use crate::features::graphrag::embeddings::EMBEDDING_MODELS;
+use crate::features::graphrag::preamble::{
+    DEFAULT_PREAMBLE_TEMPLATE, PLACEHOLDERS as PREAMBLE_PLACEHOLDERS,
+};
+use crate::features::graphrag::profiles;
 use crate::graphrag_config::{GraphRAGConfig, GraphRAGConfigManager, GraphRAGMetrics};
+use crate::state::GraphRAGStateContext;
+use crate::utils::download::DownloadUtils;
 use gloo_timers::future::TimeoutFuture;
 use leptos::prelude::*;
 use leptos::task::spawn_local;
+use wasm_bindgen_futures::JsFuture;
 
 // Simplified GraphRAG Status Metrics component for StatusBar
 #[component]
@@ -76,6 +87,113 @@ pub fn GraphRAGSettings(
     let _ = metrics.get_untracked();
     let _ = manager.clone();
 
+    let graphrag_state = use_context::<GraphRAGStateContext>();
+    let has_graphrag_state = graphrag_state.is_some();
+
+    // Sharing settings as a JSON file; an import waits here until it is applied
+    let pending_import = RwSignal::new(None::<ImportedConfig>);
+    let share_status = RwSignal::new(None::<String>);
+    let export_settings = {
+        let m = manager.clone();
+        move |_| {
+            let filename = format!("graphrag-settings-{}.json", js_sys::Date::now() as u64);
+            match DownloadUtils::download_text(&filename, &m.export_config()) {
+                Ok(()) => share_status.set(None),
+                Err(e) => share_status.set(Some(format!("Export failed: {}", e))),
+            }
+        }
+    };
+    let load_import = move |ev: leptos::ev::Event| {
+        let target: web_sys::HtmlInputElement = event_target(&ev);
+        let Some(file) = target.files().and_then(|f| f.item(0)) else {
+            return;
+        };
+        target.set_value("");
+        spawn_local(async move {
+            let loaded = JsFuture::from(file.text())
+                .await
+                .map(|v| v.as_string().unwrap_or_default())
+                .map_err(|e| format!("{:?}", e))
+                .and_then(|json| config_bundle::parse(&json));
+            match loaded {
+                Ok(imported) => {
+                    share_status.set(None);
+                    pending_import.set(Some(imported));
+                }
+                Err(e) => {
+                    pending_import.set(None);
+                    share_status.set(Some(format!("Import refused: {}", e)));
+                }
+            }
+        });
+    };
+    let import_changes = Signal::derive(move || {
+        pending_import.with(|p| {
+            p.as_ref()
+                .map(|p| config_bundle::diff(&config.get(), &p.config))
+                .unwrap_or_default()
+        })
+    });
+    let import_input = NodeRef::<leptos::html::Input>::new();
+
+    // Profiles: the built-in presets and the user's own, switched as a whole
+    let builtin_profiles = profiles::builtin_profiles();
+    let active_profile = {
+        let m = manager.clone();
+        Memo::new(move |_| m.active_profile())
+    };
+    let custom_profiles = {
+        let m = manager.clone();
+        Memo::new(move |_| m.custom_profiles())
+    };
+    let new_profile_name = RwSignal::new(String::new());
+    let profile_status = RwSignal::new(None::<Result<String, String>>);
+    let save_profile = {
+        let m = manager.clone();
+        move |_| {
+            let name = new_profile_name.get_untracked();
+            match m.save_profile(&name) {
+                Ok(()) => {
+                    new_profile_name.set(String::new());
+                    profile_status.set(Some(Ok(format!("Saved profile \"{}\"", name.trim()))));
+                }
+                Err(e) => profile_status.set(Some(Err(e))),
+            }
+        }
+    };
+    let export_profiles = {
+        let m = manager.clone();
+        move |_| {
+            let filename = format!("graphrag-profiles-{}.json", js_sys::Date::now() as u64);
+            if let Err(e) = DownloadUtils::download_text(&filename, &m.export_profiles()) {
+                profile_status.set(Some(Err(format!("Export failed: {}", e))));
+            }
+        }
+    };
+    let load_profiles = {
+        let m = manager.clone();
+        move |ev: leptos::ev::Event| {
+            let target: web_sys::HtmlInputElement = event_target(&ev);
+            let Some(file) = target.files().and_then(|f| f.item(0)) else {
+                return;
+            };
+            target.set_value("");
+            let m = m.clone();
+            spawn_local(async move {
+                let imported = JsFuture::from(file.text())
+                    .await
+                    .map(|v| v.as_string().unwrap_or_default())
+                    .map_err(|e| format!("{:?}", e))
+                    .and_then(|json| m.import_profiles(&json));
+                profile_status.set(Some(match imported {
+                    Ok(n) => Ok(format!("Imported {} profile(s)", n)),
+                    Err(e) => Err(format!("Import refused: {}", e)),
+                }));
+            });
+        }
+    };
+    let profiles_input = NodeRef::<leptos::html::Input>::new();
+
     view! {
         <div class="space-y-4">
             <div class="card bg-base-100 shadow">
@@ -83,6 +201,31 @@ pub fn GraphRAGSettings(
                     <div class="flex items-center justify-between mb-3">
                         <h2 class="card-title text-sm">"GraphRAG Configuration"</h2>
                         <div class="flex gap-1">
+                            <button
+                                class="btn btn-ghost btn-xs btn-circle"
+                                on:click=export_settings
+                                title="Export settings as JSON"
+                            >
+                                <i data-lucide="download" class="w-3 h-3"></i>
+                            </button>
+                            <button
+                                class="btn btn-ghost btn-xs btn-circle"
+                                on:click=move |_| {
+                                    if let Some(input) = import_input.get() {
+                                        input.click();
+                                    }
+                                }
+                                title="Import settings from JSON"
+                            >
+                                <i data-lucide="upload" class="w-3 h-3"></i>
+                            </button>
+                            <input
+                                type="file"
+                                accept=".json,application/json"
+                                class="hidden"
+                                node_ref=import_input
+                                on:change=load_import
+                            />
                             <button
                                 class="btn btn-ghost btn-xs btn-circle"
                                 on:click=move |_| set_show_config_explanation.set(true)
@@ -100,6 +243,185 @@ pub fn GraphRAGSettings(
                         </div>
                     </div>
 
+                    <Show when=move || share_status.get().is_some()>
+                        <p class="text-xs text-error mb-2">{move || share_status.get().unwrap_or_default()}</p>
+                    </Show>
+
+                    // Preview of an imported file against the current settings
+                    <Show when=move || pending_import.with(|p| p.is_some())>
+                        <div class="p-3 mb-3 rounded-xl border border-info bg-base-200 space-y-2 text-xs">
+                            <div class="font-medium text-sm">
+                                {move || match import_changes.with(|c| c.len()) {
+                                    0 => "The imported settings match the current ones".to_string(),
+                                    n => format!("Importing changes {} setting(s)", n),
+                                }}
+                            </div>
+                            {move || {
+                                pending_import
+                                    .with(|p| p.as_ref().map(|p| p.profiles.len()).unwrap_or_default())
+                                    .gt(&0)
+                                    .then(|| view! {
+                                        <p>
+                                            {move || format!(
+                                                "Also adds {} profile(s), replacing ones of the same name",
+                                                pending_import.with(|p| p.as_ref().map(|p| p.profiles.len()).unwrap_or_default())
+                                            )}
+                                        </p>
+                                    })
+                            }}
+                            {move || {
+                                pending_import
+                                    .with(|p| p.as_ref().map(|p| p.warnings.clone()).unwrap_or_default())
+                                    .into_iter()
+                                    .map(|w| view! { <p class="text-warning">{w}</p> })
+                                    .collect_view()
+                            }}
+                            <div class="max-h-48 overflow-auto">
+                                <table class="table table-xs">
+                                    <thead>
+                                        <tr><th>"Setting"</th><th>"Current"</th><th>"Imported"</th></tr>
+                                    </thead>
+                                    <tbody>
+                                        {move || {
+                                            import_changes
+                                                .get()
+                                                .into_iter()
+                                                .map(|c| view! {
+                                                    <tr>
+                                                        <td class="font-mono">{c.field}</td>
+                                                        <td class="font-mono opacity-70 break-all">{c.current}</td>
+                                                        <td class="font-mono break-all">{c.incoming}</td>
+                                                    </tr>
+                                                })
+                                                .collect_view()
+                                        }}
+                                    </tbody>
+                                </table>
+                            </div>
+                            <div class="flex justify-end gap-2">
+                                <button class="btn btn-ghost btn-xs" on:click=move |_| pending_import.set(None)>
+                                    "Cancel"
+                                </button>
+                                <button
+                                    class="btn btn-primary btn-xs"
+                                    disabled=move || {
+                                        import_changes.with(|c| c.is_empty())
+                                            && pending_import.with(|p| p.as_ref().is_none_or(|p| p.profiles.is_empty()))
+                                    }
+                                    on:click={
+                                        let m = manager.clone();
+                                        move |_| {
+                                            if let Some(imported) = pending_import.get_untracked() {
+                                                m.apply_import(imported);
+                                            }
+                                            pending_import.set(None);
+                                        }
+                                    }
+                                >
+                                    "Apply"
+                                </button>
+                            </div>
+                        </div>
+                    </Show>
+
+                    // Profiles
+                    <div class="p-3 mb-3 bg-base-200 rounded-xl space-y-2" role="group" aria-label="Configuration profiles">
+                        <div class="flex items-center gap-2">
+                            <div class="tooltip tooltip-right" data-tip="Presets of every toggle and weight; models, chunking and limits are kept">
+                                <span class="font-medium text-sm">Profile</span>
+                            </div>
+                            <select
+                                class="select select-bordered select-xs flex-1"
+                                aria-label="Configuration profile"
+                                prop:value=move || active_profile.get().unwrap_or_default()
+                                on:change={
+                                    let m = manager.clone();
+                                    move |ev| m.apply_profile(&event_target_value(&ev))
+                                }
+                            >
+                                <option value="" disabled>"Custom"</option>
+                                {builtin_profiles
+                                    .iter()
+                                    .map(|p| view! { <option value=p.name.clone()>{p.name.clone()}</option> })
+                                    .collect_view()}
+                                {move || {
+                                    custom_profiles
+                                        .get()
+                                        .into_iter()
+                                        .map(|p| view! { <option value=p.name.clone()>{p.name}</option> })
+                                        .collect_view()
+                                }}
+                            </select>
+                            <button
+                                class="btn btn-ghost btn-xs btn-circle"
+                                title="Delete this profile"
+                                aria-label="Delete profile"
+                                disabled=move || {
+                                    active_profile.get().is_none_or(|name| profiles::is_builtin(&name))
+                                }
+                                on:click={
+                                    let m = manager.clone();
+                                    move |_| {
+                                        if let Some(name) = active_profile.get_untracked() {
+                                            m.delete_profile(&name);
+                                            profile_status.set(Some(Ok(format!("Deleted profile \"{}\"", name))));
+                                        }
+                                    }
+                                }
+                            >
+                                <i data-lucide="trash-2" class="w-3 h-3"></i>
+                            </button>
+                            <button
+                                class="btn btn-ghost btn-xs btn-circle"
+                                title="Export your profiles as JSON"
+                                aria-label="Export profiles"
+                                disabled=move || custom_profiles.with(|p| p.is_empty())
+                                on:click=export_profiles
+                            >
+                                <i data-lucide="download" class="w-3 h-3"></i>
+                            </button>
+                            <button
+                                class="btn btn-ghost btn-xs btn-circle"
+                                title="Import profiles from JSON"
+                                aria-label="Import profiles"
+                                on:click=move |_| {
+                                    if let Some(input) = profiles_input.get() {
+                                        input.click();
+                                    }
+                                }
+                            >
+                                <i data-lucide="upload" class="w-3 h-3"></i>
+                            </button>
+                            <input
+                                type="file"
+                                accept=".json,application/json"
+                                class="hidden"
+                                node_ref=profiles_input
+                                on:change=load_profiles
+                            />
+                        </div>
+                        <div class="flex items-center gap-2">
+                            <input
+                                class="input input-bordered input-xs flex-1"
+                                placeholder="Save current settings as..."
+                                aria-label="New profile name"
+                                prop:value=move || new_profile_name.get()
+                                on:input=move |ev| new_profile_name.set(event_target_value(&ev))
+                            />
+                            <button
+                                class="btn btn-xs"
+                                disabled=move || new_profile_name.with(|n| n.trim().is_empty())
+                                on:click=save_profile
+                            >
+                                "Save"
+                            </button>
+                        </div>
+                        {move || profile_status.get().map(|status| match status {
+                            Ok(msg) => view! { <p class="text-xs text-success">{msg}</p> }.into_any(),
+                            Err(msg) => view! { <p class="text-xs text-error">{msg}</p> }.into_any(),
+                        })}
+                    </div>
+
                     <div class="space-y-3">
                         // Hybrid Retrieval Toggle
                         <div class="flex items-center justify-between p-3 bg-base-200 rounded-xl" role="group" aria-label="Hybrid retrieval configuration">
@@ -168,6 +490,291 @@ pub fn GraphRAGSettings(
                             </div>
                             <div id="fusion-weights-help" class="text-xs opacity-60">"Weights are normalized to sum to 1.00"</div>
                         </div>
+                        // Embeddings Toggle and semantic weight
+                        <div class="p-3 bg-base-200 rounded-xl space-y-2" role="group" aria-label="Embedding retrieval configuration">
+                            <div class="flex items-center justify-between">
+                                <div class="tooltip tooltip-right" data-tip="Embed chunks at index time and blend cosine similarity with the lexical score">
+                                    <span class="font-medium text-sm">Embeddings</span>
+                                </div>
+                                <input
+                                    type="checkbox"
+                                    class="toggle toggle-success rounded-full"
+                                    checked={move || config.get().embeddings_enabled}
+                                    aria-checked={move || config.get().embeddings_enabled}
+                                    aria-label="Enable or disable embedding retrieval"
+                                    title="Enable or disable embedding retrieval"
+                                    on:change={
+                                        let m = manager.clone();
+                                        move |_| m.toggle_embeddings()
+                                    }
+                                />
+                            </div>
+                            <div class="flex items-center justify-between">
+                                <span class="text-sm">Semantic Weight</span>
+                                <div class="flex items-center gap-2" role="group" aria-label="Semantic weight controls">
+                                    <button class="btn btn-xs" title="Increase semantic weight" aria-label="Increase semantic weight" on:click={
+                                        let m = manager.clone();
+                                        move |_| m.update_config(|c| c.semantic_weight = (c.semantic_weight + 0.05).clamp(0.0, 1.0))
+                                    }>"+"</button>
+                                    <button class="btn btn-xs" title="Decrease semantic weight" aria-label="Decrease semantic weight" on:click={
+                                        let m = manager.clone();
+                                        move |_| m.update_config(|c| c.semantic_weight = (c.semantic_weight - 0.05).clamp(0.0, 1.0))
+                                    }>"-"</button>
+                                    <span class="badge badge-ghost">{move || format!("{:.2}", config.get().semantic_weight)}</span>
+                                </div>
+                            </div>
+                            <div class="flex items-center gap-2">
+                                <input
+                                    class="input input-bordered input-xs flex-1 font-mono"
+                                    list="default-embedding-models"
+                                    title="Embedding model for files outside a collection; re-embed after changing it"
+                                    aria-label="Default embedding model"
+                                    prop:value={move || config.get().embedding_model}
+                                    on:change={
+                                        let m = manager.clone();
+                                        move |ev| {
+                                            let model = event_target_value(&ev).trim().to_string();
+                                            if !model.is_empty() {
+                                                m.update_config(|c| c.embedding_model = model);
+                                            }
+                                        }
+                                    }
+                                />
+                                <datalist id="default-embedding-models">
+                                    {EMBEDDING_MODELS
+                                        .iter()
+                                        .map(|(id, label)| view! { <option value=*id>{*label}</option> })
+                                        .collect_view()}
+                                </datalist>
+                            </div>
+                            <div class="text-xs opacity-60">"Re-index after enabling or changing the model"</div>
+                        </div>
+                        // LLM extraction toggle
+                        <div class="flex items-center justify-between p-3 bg-base-200 rounded-xl">
+                            <div class="tooltip tooltip-right" data-tip="Ask the loaded chat model for entities and typed relations while indexing; slower than the heuristic extractor">
+                                <span class="font-medium text-sm">LLM Extraction</span>
+                            </div>
+                            <input
+                                type="checkbox"
+                                class="toggle toggle-success rounded-full"
+                                checked={move || config.get().llm_extraction_enabled}
+                                aria-checked={move || config.get().llm_extraction_enabled}
+                                aria-label="Enable or disable LLM entity extraction"
+                                title="Enable or disable LLM entity extraction"
+                                on:change={
+                                    let m = manager.clone();
+                                    move |_| m.toggle_llm_extraction()
+                                }
+                            />
+                        </div>
+                        // Entity resolution toggle, with a manual pass over the current graph
+                        <div class="flex items-center justify-between p-3 bg-base-200 rounded-xl">
+                            <div class="tooltip tooltip-right" data-tip="Merge entities named differently (case, plurals, company suffixes, acronyms) after every index build">
+                                <span class="font-medium text-sm">Entity Resolution</span>
+                            </div>
+                            <div class="flex items-center gap-2">
+                                <button
+                                    class="btn btn-xs"
+                                    title="Merge duplicate entities in the current graph now"
+                                    aria-label="Merge duplicate entities now"
+                                    disabled=!has_graphrag_state
+                                    on:click={
+                                        let state = graphrag_state.clone();
+                                        move |_| {
+                                            if let Some(state) = &state {
+                                                state.resolve_entities();
+                                            }
+                                        }
+                                    }
+                                >
+                                    "Merge now"
+                                </button>
+                                <input
+                                    type="checkbox"
+                                    class="toggle toggle-success rounded-full"
+                                    checked={move || config.get().entity_resolution_enabled}
+                                    aria-checked={move || config.get().entity_resolution_enabled}
+                                    aria-label="Enable or disable entity resolution"
+                                    title="Enable or disable entity resolution"
+                                    on:change={
+                                        let m = manager.clone();
+                                        move |_| m.toggle_entity_resolution()
+                                    }
+                                />
+                            </div>
+                        </div>
+                        // Relevance threshold for injected knowledge
+                        <div class="flex items-center justify-between p-3 bg-base-200 rounded-xl" role="group" aria-label="Relevance threshold configuration">
+                            <div class="tooltip tooltip-right" data-tip="Documents scoring below this relevance are never injected into the prompt">
+                                <span class="font-medium text-sm">Min Relevance</span>
+                            </div>
+                            <div class="flex items-center gap-2" role="group" aria-label="Relevance threshold controls">
+                                <button class="btn btn-xs" title="Raise relevance threshold" aria-label="Raise relevance threshold" on:click={
+                                    let m = manager.clone();
+                                    move |_| m.update_config(|c| c.min_relevance = (c.min_relevance + 0.05).clamp(0.0, 1.0))
+                                }>"+"</button>
+                                <button class="btn btn-xs" title="Lower relevance threshold" aria-label="Lower relevance threshold" on:click={
+                                    let m = manager.clone();
+                                    move |_| m.update_config(|c| c.min_relevance = (c.min_relevance - 0.05).clamp(0.0, 1.0))
+                                }>"-"</button>
+                                <span class="badge badge-ghost">{move || format!("{:.2}", config.get().min_relevance)}</span>
+                            </div>
+                        </div>
+                        // Diversity of the passages injected into the prompt
+                        <div class="flex items-center justify-between p-3 bg-base-200 rounded-xl" role="group" aria-label="Result diversity configuration">
+                            <div class="tooltip tooltip-right" data-tip="Skip passages that repeat ones already selected; lower lambda favors variety over relevance">
+                                <span class="font-medium text-sm">Diversify Results</span>
+                            </div>
+                            <div class="flex items-center gap-2" role="group" aria-label="Result diversity controls">
+                                <button class="btn btn-xs" title="Favor relevance" aria-label="Raise diversity lambda" disabled=move || !config.get().mmr_enabled on:click={
+                                    let m = manager.clone();
+                                    move |_| m.update_config(|c| c.mmr_lambda = (c.mmr_lambda + 0.1).clamp(0.0, 1.0))
+                                }>"+"</button>
+                                <button class="btn btn-xs" title="Favor variety" aria-label="Lower diversity lambda" disabled=move || !config.get().mmr_enabled on:click={
+                                    let m = manager.clone();
+                                    move |_| m.update_config(|c| c.mmr_lambda = (c.mmr_lambda - 0.1).clamp(0.0, 1.0))
+                                }>"-"</button>
+                                <span class="badge badge-ghost">{move || format!("λ {:.1}", config.get().mmr_lambda)}</span>
+                                <input
+                                    type="checkbox"
+                                    class="toggle toggle-accent rounded-full"
+                                    checked={move || config.get().mmr_enabled}
+                                    aria-checked={move || config.get().mmr_enabled}
+                                    aria-label="Enable or disable result diversification"
+                                    title="Enable or disable result diversification"
+                                    on:change={
+                                        let m = manager.clone();
+                                        move |_| m.toggle_mmr()
+                                    }
+                                />
+                            </div>
+                        </div>
+                        // Documents past their expiry date
+                        <div class="flex items-center justify-between p-3 bg-base-200 rounded-xl">
+                            <div class="tooltip tooltip-right" data-tip="Search documents past their expiry date too; expiry dates are set in the Document Manager">
+                                <span class="font-medium text-sm">Include Expired</span>
+                            </div>
+                            <input
+                                type="checkbox"
+                                class="toggle toggle-warning rounded-full"
+                                checked={move || config.get().include_expired}
+                                aria-checked={move || config.get().include_expired}
+                                aria-label="Include or exclude expired documents"
+                                title="Include or exclude expired documents"
+                                on:change={
+                                    let m = manager.clone();
+                                    move |_| m.toggle_include_expired()
+                                }
+                            />
+                        </div>
+                        // Chunking of prose documents at index time
+                        <div class="p-3 bg-base-200 rounded-xl space-y-2" role="group" aria-label="Chunking configuration">
+                            <div class="flex items-center justify-between">
+                                <div class="tooltip tooltip-right" data-tip="How text and markdown files are cut into indexed passages">
+                                    <span class="font-medium text-sm">Chunking</span>
+                                </div>
+                                <select
+                                    class="select select-bordered select-xs"
+                                    aria-label="Chunking strategy"
+                                    on:change={
+                                        let m = manager.clone();
+                                        move |ev| {
+                                            if let Some(strategy) = ChunkingStrategy::from_id(&event_target_value(&ev)) {
+                                                m.update_config(|c| c.chunking_strategy = strategy);
+                                            }
+                                        }
+                                    }
+                                >
+                                    {ChunkingStrategy::ALL
+                                        .into_iter()
+                                        .map(|s| {
+                                            view! {
+                                                <option value=s.id() selected=move || config.get().chunking_strategy == s>
+                                                    {s.label()}
+                                                </option>
+                                            }
+                                        })
+                                        .collect_view()}
+                                </select>
+                            </div>
+                            <div class="flex items-center justify-between">
+                                <span class="text-sm">Chunk Size</span>
+                                <div class="flex items-center gap-2" role="group" aria-label="Chunk size controls">
+                                    <button class="btn btn-xs" title="Increase chunk size" aria-label="Increase chunk size" on:click={
+                                        let m = manager.clone();
+                                        move |_| m.update_config(|c| c.chunk_size = (c.chunk_size + 250).min(8000))
+                                    }>"+"</button>
+                                    <button class="btn btn-xs" title="Decrease chunk size" aria-label="Decrease chunk size" on:click={
+                                        let m = manager.clone();
+                                        move |_| m.update_config(|c| c.chunk_size = c.chunk_size.saturating_sub(250).max(250))
+                                    }>"-"</button>
+                                    <span class="badge badge-ghost">{move || format!("{} chars", config.get().chunk_size)}</span>
+                                </div>
+                            </div>
+                            <Show when=move || config.get().chunking_strategy == ChunkingStrategy::OverlapWindow>
+                                <div class="flex items-center justify-between">
+                                    <span class="text-sm">Overlap</span>
+                                    <div class="flex items-center gap-2" role="group" aria-label="Chunk overlap controls">
+                                        <button class="btn btn-xs" title="Increase chunk overlap" aria-label="Increase chunk overlap" on:click={
+                                            let m = manager.clone();
+                                            move |_| m.update_config(|c| c.chunk_overlap = (c.chunk_overlap + 50).min(c.chunk_size / 2))
+                                        }>"+"</button>
+                                        <button class="btn btn-xs" title="Decrease chunk overlap" aria-label="Decrease chunk overlap" on:click={
+                                            let m = manager.clone();
+                                            move |_| m.update_config(|c| c.chunk_overlap = c.chunk_overlap.saturating_sub(50))
+                                        }>"-"</button>
+                                        <span class="badge badge-ghost">{move || format!("{} chars", config.get().chunk_overlap)}</span>
+                                    </div>
+                                </div>
+                            </Show>
+                            <div class="text-xs opacity-60">"Re-index after changing how documents are chunked"</div>
+                        </div>
+                        // Large knowledge bases: sketches in memory, full texts on disk
+                        <div class="p-3 bg-base-200 rounded-xl space-y-2" role="group" aria-label="Large knowledge base mode">
+                            <div class="flex items-center justify-between">
+                                <div class="tooltip tooltip-right" data-tip="Keep only passage sketches in memory and read full texts from disk when a query needs them">
+                                    <span class="font-medium text-sm">Large Knowledge Base</span>
+                                </div>
+                                <input
+                                    type="checkbox"
+                                    class="toggle toggle-info rounded-full"
+                                    checked={move || config.get().large_kb_mode}
+                                    aria-checked={move || config.get().large_kb_mode}
+                                    aria-label="Enable or disable large knowledge base mode"
+                                    title="Enable or disable large knowledge base mode"
+                                    on:change={
+                                        let m = manager.clone();
+                                        move |_| m.toggle_large_kb_mode()
+                                    }
+                                />
+                            </div>
+                            <div class="flex items-center justify-between">
+                                <span class="text-sm">Memory Ceiling</span>
+                                <div class="flex items-center gap-2" role="group" aria-label="Memory ceiling controls">
+                                    <button class="btn btn-xs" title="Raise the memory ceiling" aria-label="Raise the memory ceiling" on:click={
+                                        let m = manager.clone();
+                                        move |_| m.update_config(|c| c.max_memory_mb = (c.max_memory_mb + 32).min(2048))
+                                    }>"+"</button>
+                                    <button class="btn btn-xs" title="Lower the memory ceiling" aria-label="Lower the memory ceiling" on:click={
+                                        let m = manager.clone();
+                                        move |_| m.update_config(|c| c.max_memory_mb = c.max_memory_mb.saturating_sub(32).max(16))
+                                    }>"-"</button>
+                                    <span class="badge badge-ghost">{move || format!("{} MB", config.get().max_memory_mb)}</span>
+                                </div>
+                            </div>
+                            <div class="text-xs opacity-60">
+                                {move || {
+                                    let on_disk = content_store::load_index().len();
+                                    format!(
+                                        "Passage cache {:.1} of {} MB · {} passage(s) on disk",
+                                        metrics.get().memory_usage_mb,
+                                        config.get().max_memory_mb,
+                                        on_disk
+                                    )
+                                }}
+                            </div>
+                            <div class="text-xs opacity-60">"Applies to passages indexed from now on; re-index to move existing ones"</div>
+                        </div>
                         // HyDE Toggle with DaisyUI toggle switch
                         <div class="flex items-center justify-between p-3 bg-base-200 rounded-xl">
                             <div class="flex items-center gap-3">
@@ -189,6 +796,25 @@ pub fn GraphRAGSettings(
                             />
                         </div>
 
+                        // Query decomposition toggle
+                        <div class="flex items-center justify-between p-3 bg-base-200 rounded-xl">
+                            <div class="tooltip tooltip-right" data-tip="Split multi-part questions into sub-queries with the loaded chat model and search each one">
+                                <span class="font-medium text-sm">Decomposition</span>
+                            </div>
+                            <input
+                                type="checkbox"
+                                class="toggle toggle-success rounded-full"
+                                checked={move || config.get().query_decomposition_enabled}
+                                aria-checked={move || config.get().query_decomposition_enabled}
+                                aria-label="Enable or disable query decomposition"
+                                title="Enable or disable query decomposition"
+                                on:change={
+                                    let m = manager.clone();
+                                    move |_| m.toggle_query_decomposition()
+                                }
+                            />
+                        </div>
+
                         // Community Detection Toggle
                         <div class="flex items-center justify-between p-3 bg-base-200 rounded-xl">
                             <div class="tooltip tooltip-right" data-tip="Groups related entities for better context">
@@ -252,6 +878,70 @@ pub fn GraphRAGSettings(
                                 }
                             />
                         </div>
+
+                        // Abstractive synthesis toggle; only used while synthesis is on
+                        <div class="flex items-center justify-between p-3 bg-base-200 rounded-xl">
+                            <div class="tooltip tooltip-right" data-tip="Have the loaded chat model summarize the top passages with [n] citations instead of quoting them; one extra model call per question">
+                                <span class="font-medium text-sm">Abstractive Summary</span>
+                            </div>
+                            <input
+                                type="checkbox"
+                                class="toggle toggle-info rounded-full"
+                                checked={move || config.get().abstractive_synthesis_enabled}
+                                aria-checked={move || config.get().abstractive_synthesis_enabled}
+                                aria-label="Enable or disable abstractive synthesis"
+                                title="Enable or disable abstractive synthesis"
+                                disabled={move || !config.get().synthesis_enabled}
+                                on:change={
+                                    let m = manager.clone();
+                                    move |_| m.toggle_abstractive_synthesis()
+                                }
+                            />
+                        </div>
+
+                        // Knowledge preamble template
+                        <div class="p-3 bg-base-200 rounded-xl space-y-2">
+                            <div class="flex items-center justify-between">
+                                <div class="tooltip tooltip-right" data-tip="System message that injects retrieved knowledge; a paragraph whose placeholder is empty is left out">
+                                    <span class="font-medium text-sm">Preamble Template</span>
+                                </div>
+                                <button
+                                    class="btn btn-xs"
+                                    title="Restore the default preamble template"
+                                    aria-label="Reset preamble template"
+                                    disabled={move || config.get().preamble_template == DEFAULT_PREAMBLE_TEMPLATE}
+                                    on:click={
+                                        let m = manager.clone();
+                                        move |_| m.update_config(|c| {
+                                            c.preamble_template = DEFAULT_PREAMBLE_TEMPLATE.to_string()
+                                        })
+                                    }
+                                >
+                                    "Reset"
+                                </button>
+                            </div>
+                            <textarea
+                                class="textarea textarea-bordered textarea-xs w-full font-mono"
+                                rows="5"
+                                aria-label="Knowledge preamble template"
+                                prop:value={move || config.get().preamble_template}
+                                on:change={
+                                    let m = manager.clone();
+                                    move |ev| {
+                                        let template = event_target_value(&ev);
+                                        m.update_config(|c| c.preamble_template = template);
+                                    }
+                                }
+                            ></textarea>
+                            <div class="text-xs opacity-60 space-y-0.5">
+                                {PREAMBLE_PLACEHOLDERS
+                                    .iter()
+                                    .map(|(placeholder, meaning)| view! {
+                                        <div><code>{*placeholder}</code>" — "{*meaning}</div>
+                                    })
+                                    .collect_view()}
+                            </div>
+                        </div>
                     </div>
 
                     // Detailed Descriptions Panel
@@ -1,8 +1,11 @@
 use crate::components::graphrag_settings::GraphRAGSettings;
 use crate::components::ui_primitives::{Button, Toggle};
+use crate::features::graphrag::coverage::{CoverageLevel, CoverageReport};
 use crate::graphrag_config::{GraphRAGConfig, GraphRAGConfigManager};
 use crate::models::graphrag::{RAGQuery, SearchStrategy};
 use crate::state::GraphRAGStateContext;
+use crate::utils::download::DownloadUtils;
+use crate::utils::notifications::{LongTask, NotificationUtils};
 use leptos::prelude::*;
 use leptos::task::spawn_local;
 
@@ -31,6 +34,11 @@ pub fn GraphRAGSettingsModal(show: ReadSignal<bool>, set_show: WriteSignal<bool>
     let is_searching = ctx.is_searching();
     let index_progress = ctx.index_progress();
 
+    // Knowledge coverage report over a list of anticipated questions
+    let (coverage_questions, set_coverage_questions) = signal(String::new());
+    let (coverage_report, set_coverage_report) = signal(None::<CoverageReport>);
+    let (coverage_running, set_coverage_running) = signal(false);
+
     // Handlers will be provided inline to avoid FnOnce moves and Rc Send/Sync issues
 
     // Derive signals from the same manager instance so state stays consistent
@@ -157,6 +165,121 @@ pub fn GraphRAGSettingsModal(show: ReadSignal<bool>, set_show: WriteSignal<bool>
                                 </div>
                             </div>
                         </div>
+                        // Coverage report (retrieval only)
+                        <div class="space-y-3">
+                            <h3 class="font-medium text-base-content">"Knowledge Coverage"</h3>
+                            <p class="text-sm text-base-content/60">
+                                "One anticipated question per line; shows which ones the knowledge base can answer"
+                            </p>
+                            <textarea
+                                class="textarea textarea-bordered w-full h-24 text-sm"
+                                placeholder="What does the enterprise plan include?"
+                                prop:value=coverage_questions
+                                on:input=move |ev| set_coverage_questions.set(event_target_value(&ev))
+                            ></textarea>
+                            <div class="flex items-center gap-2">
+                                <button
+                                    class="btn btn-primary btn-sm"
+                                    disabled=move || coverage_running.get() || is_indexing.get()
+                                    on:click=move |_| {
+                                        let questions = coverage_questions.get();
+                                        if questions.trim().is_empty() {
+                                            return;
+                                        }
+                                        set_coverage_running.set(true);
+                                        spawn_local(async move {
+                                            let report = CoverageReport::run(&questions).await;
+                                            NotificationUtils::task_finished(
+                                                LongTask::CoverageReport,
+                                                Ok(&format!(
+                                                    "{} question(s): {} strong, {} weak, {} without support",
+                                                    report.questions.len(),
+                                                    report.count(CoverageLevel::Strong),
+                                                    report.count(CoverageLevel::Weak),
+                                                    report.count(CoverageLevel::None)
+                                                )),
+                                            );
+                                            set_coverage_report.set(Some(report));
+                                            set_coverage_running.set(false);
+                                        });
+                                    }
+                                >
+                                    {move || if coverage_running.get() { "Checking..." } else { "Check Coverage" }}
+                                </button>
+                                <Show when=move || coverage_report.get().is_some()>
+                                    <button
+                                        class="btn btn-ghost btn-sm"
+                                        on:click=move |_| {
+                                            if let Some(report) = coverage_report.get() {
+                                                if let Err(e) = DownloadUtils::download_text(
+                                                    "coverage_report.md",
+                                                    &report.to_markdown(),
+                                                ) {
+                                                    log::error!("Coverage report download failed: {}", e);
+                                                }
+                                            }
+                                        }
+                                    >
+                                        "Download Report"
+                                    </button>
+                                </Show>
+                            </div>
+                            {move || {
+                                coverage_report
+                                    .get()
+                                    .map(|report| {
+                                        let gaps = report.gaps();
+                                        view! {
+                                            <div class="card bg-base-200">
+                                                <div class="card-body p-3 gap-2 text-sm">
+                                                    <div class="flex flex-wrap gap-2">
+                                                        <span class="badge badge-success badge-sm">
+                                                            {format!("{} strong", report.count(CoverageLevel::Strong))}
+                                                        </span>
+                                                        <span class="badge badge-warning badge-sm">
+                                                            {format!("{} weak", report.count(CoverageLevel::Weak))}
+                                                        </span>
+                                                        <span class="badge badge-error badge-sm">
+                                                            {format!("{} unsupported", report.count(CoverageLevel::None))}
+                                                        </span>
+                                                    </div>
+                                                    <Show when={
+                                                        let has_gaps = !gaps.is_empty();
+                                                        move || has_gaps
+                                                    }>
+                                                        <p class="text-xs">
+                                                            <span class="font-semibold">"Gaps: "</span>
+                                                            {gaps.join(", ")}
+                                                        </p>
+                                                    </Show>
+                                                    <ul class="space-y-1">
+                                                        {report
+                                                            .questions
+                                                            .into_iter()
+                                                            .map(|q| {
+                                                                let sources = q.sources.iter().take(3).cloned().collect::<Vec<_>>().join(", ");
+                                                                view! {
+                                                                    <li class="flex items-start gap-2">
+                                                                        <span class=format!("badge badge-xs mt-1 {}", q.level.badge_class())>
+                                                                            {q.level.label()}
+                                                                        </span>
+                                                                        <div class="flex-1 min-w-0">
+                                                                            <div class="truncate" title=q.question.clone()>{q.question.clone()}</div>
+                                                                            <div class="text-xs opacity-60 truncate">
+                                                                                {if sources.is_empty() { "No supporting documents".to_string() } else { sources }}
+                                                                            </div>
+                                                                        </div>
+                                                                    </li>
+                                                                }
+                                                            })
+                                                            .collect_view()}
+                                                    </ul>
+                                                </div>
+                                            </div>
+                                        }
+                                    })
+                            }}
+                        </div>
                         // Enable/Disable GraphRAG
                         <div class="space-y-2">
                             <h3 class="font-medium text-base-content">"Enable GraphRAG"</h3>
@@ -1,8 +1,25 @@
+use crate::components::document_manager_simple::{is_supported_upload, read_upload};
+use crate::components::table_paste::{pasted_table, TablePasteOffer};
 use crate::components::ui_primitives::{Button, Input};
+use crate::features::graphrag::knowledge_impact::KnowledgeImpact;
+use crate::graphrag_config::{GraphRAGConfig, PerformanceMetrics};
+use crate::models::attachment::{unique_file_name, Attachments};
+use crate::models::generation::InputLength;
+use crate::models::{ActivityCategory, Collections, KnowledgeScope};
+use crate::state::knowledge_storage_context::KnowledgeStorageContext;
+use crate::state::{EventBusContext, GraphRAGStateContext};
+use crate::storage::{CachePolicy, TieredCache};
+use crate::utils::brainstorm::BRAINSTORM_REPLIES;
+use crate::utils::generation::GenerationUtils;
+use crate::utils::speech::{self, SpeechInput, SPEECH_LANGUAGES, SPEECH_LANGUAGE_KEY};
 use leptos::ev;
 use leptos::prelude::*;
+use leptos::task::spawn_local;
 use std::rc::Rc;
 
+// Characters of the first line used as the title of a document saved from the input
+const DOCUMENT_TITLE_CHARS: usize = 60;
+
 // ---------- Facoltativo: tipi per future estensioni ----------
 // #[derive(Clone, PartialEq)]
 // pub enum Role {
@@ -25,13 +42,203 @@ pub fn InputArea(
     on_send: Rc<dyn Fn(ev::MouseEvent)>,
     knowledge_enabled: ReadSignal<bool>,
     set_knowledge_enabled: WriteSignal<bool>,
+    graphrag_config: Signal<GraphRAGConfig>,
+    /// Stage timings of the last retrieval
+    last_query: Signal<PerformanceMetrics>,
     is_loading: ReadSignal<bool>,
     set_status_message: WriteSignal<String>,
+    /// How much of the conversation the next prompt carries, when not all of it
+    history_note: Signal<Option<String>>,
+    /// Conversation that attached files are scoped to
+    conversation_id: ReadSignal<Option<String>>,
+    /// Documents the conversation's retrieval is limited to
+    knowledge_scope: ReadSignal<KnowledgeScope>,
+    /// Switch the conversation to one collection, or to every document with `None`
+    on_select_collection: Callback<Option<String>>,
+    /// Answer each prompt with several replies to pick from
+    brainstorm: RwSignal<bool>,
 ) -> impl IntoView {
+    let events = use_context::<EventBusContext>();
+    let graphrag_ctx = use_context::<GraphRAGStateContext>();
+    // Re-read on focus so limits saved in the settings apply without a reload
+    let limits = RwSignal::new(GenerationUtils::load_settings().input_limits);
+    let refresh_limits = move |_| limits.set(GenerationUtils::load_settings().input_limits);
+    let input_chars = Signal::derive(move || input_value.with(|v| v.chars().count()));
+    let length = Signal::derive(move || limits.with(|l| l.classify(input_chars.get())));
+    // What the knowledge toggle adds to a reply, recomputed as config and index change
+    let staleness = graphrag_ctx.as_ref().map(|ctx| ctx.staleness());
+    let impact = Memo::new(move |_| {
+        let staleness = staleness.map(|s| s.get()).unwrap_or_default();
+        KnowledgeImpact::estimate(&graphrag_config.get(), &staleness, &last_query.get())
+    });
+    // Collections offered as knowledge bases, re-read when the popover opens
+    let knowledge_bases = RwSignal::new(Collections::load().unwrap_or_default().collections);
+    let refresh_knowledge_bases =
+        move |_| knowledge_bases.set(Collections::load().unwrap_or_default().collections);
+    // Pasted text that was kept out of the input because it exceeds the hard limit
+    let held_paste = RwSignal::new(None::<String>);
+    // Table found in the last paste, offered as CRM records or a table document
+    let pasted = RwSignal::new(None);
+    let table_done = Callback::new(move |message: String| set_status_message.set(message));
+
+    let handle_paste = move |ev: ev::ClipboardEvent| {
+        if let Some(table) = pasted_table(&ev) {
+            pasted.set(Some(table));
+        }
+        let Some(pasted) = ev.clipboard_data().and_then(|d| d.get_data("text").ok()) else {
+            return;
+        };
+        let total = input_chars.get_untracked() + pasted.chars().count();
+        if limits.with_untracked(|l| l.classify(total)) == InputLength::OverHard {
+            ev.prevent_default();
+            held_paste.set(Some(pasted));
+        }
+    };
+
+    let save_as_document = Callback::new(move |_: ()| {
+        let content = held_paste
+            .get_untracked()
+            .unwrap_or_else(|| input_value.get_untracked());
+        if content.trim().is_empty() {
+            return;
+        }
+        let title = document_title(&content, js_sys::Date::now());
+        match KnowledgeStorageContext::new().upsert_buffer_document(&title, &content) {
+            Ok(_) => {
+                if held_paste.get_untracked().is_some() {
+                    held_paste.set(None);
+                } else {
+                    set_input_value.set(String::new());
+                }
+                set_status_message.set(format!("Saved as knowledge document \"{}\"", title));
+                if let Some(bus) = events {
+                    bus.record(
+                        ActivityCategory::Knowledge,
+                        format!("Saved long input as \"{}\"", title),
+                    );
+                }
+                if let Some(ctx) = graphrag_ctx.as_ref() {
+                    ctx.reindex_changed();
+                }
+            }
+            Err(e) => {
+                if let Some(bus) = events {
+                    bus.error("Saving input as a document failed", e.to_string());
+                }
+                set_status_message.set("Failed to save the input as a document".to_string());
+            }
+        }
+    });
+
+    // Files attached in this conversation, re-read after each attach or detach
+    let attachments_rev = RwSignal::new(0u32);
+    let attached = Memo::new(move |_| {
+        attachments_rev.track();
+        conversation_id
+            .get()
+            .map(|c| Attachments::load().unwrap_or_default().files_of(&c))
+            .unwrap_or_default()
+    });
+    let attaching = RwSignal::new(false);
+    let attach_graphrag = use_context::<GraphRAGStateContext>();
+    let attach_input = NodeRef::<leptos::html::Input>::new();
+
+    let attach_file = move |ev: ev::Event| {
+        let target: web_sys::HtmlInputElement = event_target(&ev);
+        let Some(file) = target.files().and_then(|f| f.item(0)) else {
+            return;
+        };
+        target.set_value("");
+        let Some(conversation) = conversation_id.get_untracked() else {
+            set_status_message.set("Start a conversation before attaching files".to_string());
+            return;
+        };
+        if !is_supported_upload(&file) {
+            set_status_message.set(format!("Unsupported file type: {}", file.name()));
+            return;
+        }
+        let ctx = KnowledgeStorageContext::new();
+        let attachments = Attachments::load().unwrap_or_default();
+        // Re-attaching in the same conversation replaces the earlier copy
+        let name = unique_file_name(&file.name(), |n| {
+            ctx.has_buffer_document(n)
+                && attachments.files.get(n).map(String::as_str) != Some(conversation.as_str())
+        });
+        attaching.set(true);
+        let graphrag_ctx = attach_graphrag.clone();
+        spawn_local(async move {
+            let saved = read_upload(&file, |_| {}, || false)
+                .await
+                .and_then(|content| {
+                    ctx.upsert_buffer_document(&name, &content)
+                        .map_err(|e| e.to_string())
+                })
+                .and_then(|_| {
+                    let mut attachments = Attachments::load().unwrap_or_default();
+                    attachments.attach(&name, &conversation);
+                    attachments.save().map_err(|e| e.to_string())
+                });
+            attaching.set(false);
+            match saved {
+                Ok(()) => {
+                    attachments_rev.update(|r| *r += 1);
+                    set_status_message.set(format!("Attached \"{}\" to this conversation", name));
+                    if let Some(bus) = events {
+                        bus.record(
+                            ActivityCategory::Knowledge,
+                            format!("Attached \"{}\" to a conversation", name),
+                        );
+                    }
+                    if let Some(ctx) = graphrag_ctx.as_ref() {
+                        ctx.reindex_changed();
+                    }
+                }
+                Err(e) => {
+                    if let Some(bus) = events {
+                        bus.error("Attaching a file failed", e);
+                    }
+                    set_status_message.set(format!("Failed to attach {}", name));
+                }
+            }
+        });
+    };
+
+    let detach_file = Callback::new({
+        let graphrag_ctx = attach_graphrag.clone();
+        move |name: String| {
+            let removed = KnowledgeStorageContext::new()
+                .remove_buffer_document(&name)
+                .and_then(|_| {
+                    let mut attachments = Attachments::load().unwrap_or_default();
+                    attachments.detach(&name);
+                    attachments.save()
+                });
+            match removed {
+                Ok(()) => {
+                    attachments_rev.update(|r| *r += 1);
+                    set_status_message.set(format!("Removed attachment \"{}\"", name));
+                    if let Some(ctx) = graphrag_ctx.as_ref() {
+                        ctx.reindex_changed();
+                    }
+                }
+                Err(e) => {
+                    if let Some(bus) = events {
+                        bus.error("Removing an attachment failed", e.to_string());
+                    }
+                    set_status_message.set(format!("Failed to remove {}", name));
+                }
+            }
+        }
+    });
+
     let handle_keypress = {
         let on_send_key = on_send.clone();
         move |ev: ev::KeyboardEvent| {
-            if ev.key() == "Enter" && !ev.shift_key() && !is_loading.get() {
+            if ev.key() == "Enter"
+                && !ev.shift_key()
+                && !is_loading.get()
+                && length.get_untracked() != InputLength::OverHard
+            {
                 ev.prevent_default();
                 let mouse_ev = ev::MouseEvent::new("click").unwrap();
                 on_send_key(mouse_ev);
@@ -41,21 +248,243 @@ pub fn InputArea(
     };
 
     // Avoid overwriting the global status message when toggling Knowledge.
-    let disabled_sig =
-        Signal::derive(move || input_value.get().trim().is_empty() || is_loading.get());
+    let disabled_sig = Signal::derive(move || {
+        input_value.get().trim().is_empty()
+            || is_loading.get()
+            || length.get() == InputLength::OverHard
+    });
+
+    let offer = move || {
+        let held = held_paste.with(|p| p.as_ref().map(|p| p.chars().count()));
+        let hard = limits.with(|l| l.hard_chars);
+        let message = match (held, length.get()) {
+            (Some(chars), _) => format!(
+                "The pasted text ({} characters) is over the {} character limit.",
+                chars, hard
+            ),
+            (None, InputLength::OverHard) => format!(
+                "This message is over the {} character limit and cannot be sent.",
+                hard
+            ),
+            (None, InputLength::OverSoft) => "This message is long.".to_string(),
+            _ => return None,
+        };
+        Some(view! {
+            <div class="alert alert-warning text-sm py-2">
+                <i data-lucide="file-text" class="h-4 w-4"></i>
+                <span>{message} " Save it as a knowledge document instead?"</span>
+                <div class="flex gap-2">
+                    <button class="btn btn-sm btn-primary" on:click=move |_| save_as_document.run(())>
+                        "Save as document"
+                    </button>
+                    {held
+                        .is_some()
+                        .then(|| {
+                            view! {
+                                <button class="btn btn-sm btn-ghost" on:click=move |_| held_paste.set(None)>
+                                    "Discard"
+                                </button>
+                            }
+                        })}
+                </div>
+            </div>
+        })
+    };
+
+    // Dictation through the Web Speech API, appended to what is already typed
+    let speech_supported = speech::is_supported();
+    let listening = RwSignal::new(false);
+    let dictation = StoredValue::new_local(None::<SpeechInput>);
+    let speech_lang = RwSignal::new(
+        TieredCache::get::<String>(SPEECH_LANGUAGE_KEY).unwrap_or_else(speech::default_language),
+    );
+    let toggle_dictation = move || {
+        if listening.get_untracked() {
+            dictation.with_value(|d| {
+                if let Some(d) = d {
+                    d.stop();
+                }
+            });
+            return;
+        }
+        let typed = input_value.get_untracked();
+        let started = SpeechInput::start(
+            &speech_lang.get_untracked(),
+            move |spoken| set_input_value.set(speech::merge_transcript(&typed, &spoken)),
+            move |error| {
+                listening.set(false);
+                if let Some(code) = error {
+                    set_status_message.set(speech::describe_error(&code).to_string());
+                }
+            },
+        );
+        match started {
+            Ok(input) => {
+                // Replacing the previous dictation releases its callbacks
+                dictation.set_value(Some(input));
+                listening.set(true);
+                set_status_message.set("Listening...".to_string());
+            }
+            Err(e) => set_status_message.set(e.to_string()),
+        }
+    };
+
+    let counter_class = move || match length.get() {
+        InputLength::Ok => "text-base-content/50",
+        InputLength::NearSoft | InputLength::OverSoft => "text-warning",
+        InputLength::OverHard => "text-error",
+    };
 
     view! {
+        <div class="flex flex-col gap-2 w-full" on:focusin=refresh_limits on:paste=handle_paste>
+        {offer}
+        <TablePasteOffer table=pasted on_done=table_done />
+        // Files attached to this conversation, retrieved here before the knowledge base
+        <Show when=move || attached.with(|a| !a.is_empty())>
+            <div class="flex flex-wrap items-center gap-1 px-2">
+                <i data-lucide="paperclip" class="h-3 w-3 opacity-60"></i>
+                <For each=move || attached.get() key=|name| name.clone() let:name>
+                    <span class="badge badge-outline gap-1" title="Attached to this conversation">
+                        {name.clone()}
+                        <button
+                            class="opacity-60 hover:opacity-100"
+                            title="Remove attachment"
+                            on:click=move |_| detach_file.run(name.clone())
+                        >
+                            "✕"
+                        </button>
+                    </span>
+                </For>
+            </div>
+        </Show>
         <div class="flex items-center gap-4 px-2 py-2 w-full">
-            // Knowledge switch (simple daisyUI toggle)
-            <label class="flex items-center gap-2">
-                <input
-                    type="checkbox"
-                    class="toggle toggle-primary"
-                    prop:checked=move || knowledge_enabled.get()
-                    on:change=move |ev| set_knowledge_enabled.set(event_target_checked(&ev))
-                />
-                <span class="text-sm">{"Knowledge"}</span>
-            </label>
+            // Knowledge switch (simple daisyUI toggle) explaining what it adds on hover
+            <div class="dropdown dropdown-top dropdown-hover" on:mouseenter=refresh_knowledge_bases>
+                <label class="flex items-center gap-2" tabindex="0">
+                    <input
+                        type="checkbox"
+                        class="toggle toggle-primary"
+                        prop:checked=move || knowledge_enabled.get()
+                        on:change=move |ev| set_knowledge_enabled.set(event_target_checked(&ev))
+                    />
+                    <span class="text-sm">{"Knowledge"}</span>
+                </label>
+                <div tabindex="0" class="dropdown-content card card-compact bg-base-200 shadow z-20 w-72 mb-2">
+                    <div class="card-body text-xs gap-1">
+                        <div class="font-semibold">
+                            {move || if knowledge_enabled.get() {
+                                "Replies use your knowledge base"
+                            } else {
+                                "Turn on to ground replies in your knowledge base"
+                            }}
+                        </div>
+                        <Show
+                            when=move || knowledge_bases.with(|b| !b.is_empty())
+                            fallback=move || view! {
+                                <div class="flex justify-between" title="Set in the conversation's Local Prompt dialog">
+                                    <span class="opacity-70">"Searched"</span>
+                                    <span>{move || knowledge_scope.with(|s| s.summary())}</span>
+                                </div>
+                            }
+                        >
+                            <label class="flex items-center justify-between gap-2">
+                                <span class="opacity-70">"Knowledge base"</span>
+                                <select
+                                    class="select select-bordered select-xs max-w-40"
+                                    on:change=move |ev| {
+                                        let id = event_target_value(&ev);
+                                        on_select_collection.run(Some(id).filter(|id| !id.is_empty()));
+                                    }
+                                >
+                                    {move || {
+                                        let scope = knowledge_scope.get();
+                                        let current = scope.single_collection().map(str::to_string);
+                                        // Mixed scopes from the Local Prompt dialog stay until replaced
+                                        let mixed = (!scope.is_empty() && current.is_none()).then(|| {
+                                            view! {
+                                                <option value="custom" selected=true disabled=true>
+                                                    {scope.summary()}
+                                                </option>
+                                            }
+                                        });
+                                        let bases = knowledge_bases
+                                            .get()
+                                            .into_iter()
+                                            .map(|c| {
+                                                let selected = current.as_deref() == Some(c.id.as_str());
+                                                view! {
+                                                    <option value=c.id.clone() selected=selected>
+                                                        {c.name.clone()}
+                                                    </option>
+                                                }
+                                            })
+                                            .collect_view();
+                                        view! {
+                                            {mixed}
+                                            <option value="" selected=scope.is_empty()>"All documents"</option>
+                                            {bases}
+                                        }
+                                    }}
+                                </select>
+                            </label>
+                            {move || {
+                                let scope = knowledge_scope.get();
+                                let id = scope.single_collection()?;
+                                knowledge_bases.with(|b| {
+                                    b.iter()
+                                        .find(|c| c.id == id)
+                                        .filter(|c| !c.description.is_empty())
+                                        .map(|c| view! { <div class="opacity-70">{c.description.clone()}</div> })
+                                })
+                            }}
+                        </Show>
+                        <div class="flex justify-between">
+                            <span class="opacity-70">"Indexed documents"</span>
+                            <span>
+                                {move || impact.with(|i| {
+                                    if i.pending > 0 {
+                                        format!("{} ({} pending)", i.documents, i.pending)
+                                    } else {
+                                        i.documents.to_string()
+                                    }
+                                })}
+                            </span>
+                        </div>
+                        <div class="opacity-70">"Pipeline"</div>
+                        <ul class="pl-2">
+                            {move || impact.with(|i| {
+                                i.stages
+                                    .iter()
+                                    .map(|stage| {
+                                        let cost = if stage.measured {
+                                            format!("{} ms", stage.latency_ms)
+                                        } else {
+                                            format!("~{} ms", stage.latency_ms)
+                                        };
+                                        view! {
+                                            <li class="flex justify-between">
+                                                <span>{stage.name}</span>
+                                                <span class="opacity-70">{cost}</span>
+                                            </li>
+                                        }
+                                    })
+                                    .collect::<Vec<_>>()
+                            })}
+                        </ul>
+                        <div class="flex justify-between">
+                            <span class="opacity-70">"Added latency"</span>
+                            <span>{move || format!("~{} ms", impact.with(|i| i.latency_ms()))}</span>
+                        </div>
+                        <div class="flex justify-between">
+                            <span class="opacity-70">"Prompt overhead"</span>
+                            <span>{move || format!("up to ~{} tokens", impact.with(|i| i.prompt_tokens))}</span>
+                        </div>
+                        <Show when=move || impact.with(|i| i.documents == 0)>
+                            <div class="text-warning">"Nothing is indexed yet, so replies will not change."</div>
+                        </Show>
+                    </div>
+                </div>
+            </div>
 
             // Input expands to fill the row
             <div class="flex-1 min-w-0">
@@ -67,8 +496,109 @@ pub fn InputArea(
                     size=Signal::derive(|| "input-lg".to_string())
                     disabled=Signal::derive(move || is_loading.get())
                 />
+                <div class="flex justify-between gap-2 text-xs mt-1">
+                    <span class="text-base-content/50" title="Set in the conversation's Local Prompt dialog">
+                        {move || history_note.get().unwrap_or_default()}
+                    </span>
+                    <Show when=move || input_chars.get() > 0>
+                        <span class=counter_class>
+                            {move || format!("{} / {}", input_chars.get(), limits.with(|l| l.hard_chars))}
+                        </span>
+                    </Show>
+                </div>
             </div>
 
+            // Brainstorm mode: several replies per prompt, picked from a grid
+            <button
+                class=move || {
+                    if brainstorm.get() {
+                        "btn btn-circle btn-sm btn-warning"
+                    } else {
+                        "btn btn-circle btn-ghost btn-sm"
+                    }
+                }
+                title=format!("Brainstorm: answer with {} replies to pick from", BRAINSTORM_REPLIES)
+                aria-pressed=move || brainstorm.get().to_string()
+                on:click=move |_| {
+                    brainstorm.update(|b| *b = !*b);
+                    set_status_message.set(
+                        if brainstorm.get_untracked() { "Brainstorm mode on" } else { "Brainstorm mode off" }
+                            .to_string(),
+                    );
+                }
+            >
+                <i data-lucide="lightbulb" class="h-4 w-4"></i>
+            </button>
+
+            // Attach a file to this conversation
+            <button
+                class=move || {
+                    if attaching.get() {
+                        "btn btn-circle btn-ghost btn-sm loading"
+                    } else {
+                        "btn btn-circle btn-ghost btn-sm"
+                    }
+                }
+                title=move || {
+                    if conversation_id.with(|c| c.is_some()) {
+                        "Attach a file to this conversation"
+                    } else {
+                        "Start a conversation to attach files"
+                    }
+                }
+                disabled=move || attaching.get() || conversation_id.with(|c| c.is_none())
+                on:click=move |_| {
+                    if let Some(input) = attach_input.get() {
+                        input.click();
+                    }
+                }
+            >
+                <i data-lucide="paperclip" class="h-4 w-4"></i>
+            </button>
+            <input type="file" class="hidden" node_ref=attach_input on:change=attach_file />
+
+            // Dictation: microphone toggle with a recording indicator and language choice
+            <Show when=move || speech_supported>
+                <div class="flex items-center gap-1">
+                    <button
+                        class=move || {
+                            if listening.get() {
+                                "btn btn-circle btn-error btn-sm animate-pulse"
+                            } else {
+                                "btn btn-circle btn-ghost btn-sm"
+                            }
+                        }
+                        title=move || if listening.get() { "Stop dictation" } else { "Dictate" }
+                        aria-pressed=move || listening.get().to_string()
+                        disabled=move || is_loading.get()
+                        on:click=move |_| toggle_dictation()
+                    >
+                        <i data-lucide="mic" class="h-4 w-4"></i>
+                    </button>
+                    <select
+                        class="select select-ghost select-xs w-20"
+                        title="Dictation language"
+                        disabled=move || listening.get()
+                        on:change=move |ev| {
+                            let lang = event_target_value(&ev);
+                            let _ = TieredCache::set(SPEECH_LANGUAGE_KEY, &lang, CachePolicy::PREFERENCE);
+                            speech_lang.set(lang);
+                        }
+                    >
+                        {SPEECH_LANGUAGES
+                            .iter()
+                            .map(|(code, label)| {
+                                view! {
+                                    <option value=*code title=*label selected=move || speech_lang.get() == *code>
+                                        {*code}
+                                    </option>
+                                }
+                            })
+                            .collect_view()}
+                    </select>
+                </div>
+            </Show>
+
             // Icon-only send button
             <Button
                 // No text
@@ -76,7 +606,7 @@ pub fn InputArea(
                 on_click=Box::new({
                     let on_send = on_send.clone();
                     move || {
-                        if !is_loading.get() {
+                        if !is_loading.get() && length.get_untracked() != InputLength::OverHard {
                             let mouse_ev = ev::MouseEvent::new("click").unwrap();
                             on_send(mouse_ev);
                             set_status_message.set("Message sent".into());
@@ -89,5 +619,23 @@ pub fn InputArea(
                 disabled=disabled_sig
             />
         </div>
+        </div>
+    }
+}
+
+/// Title of a document created from chat input: its first line, or a timestamped name
+fn document_title(content: &str, now_ms: f64) -> String {
+    let first_line = content
+        .lines()
+        .map(str::trim)
+        .find(|l| !l.is_empty())
+        .unwrap_or_default()
+        .trim_start_matches('#')
+        .trim();
+    if first_line.is_empty() {
+        let date = js_sys::Date::new(&now_ms.into());
+        return format!("Pasted text {}.md", String::from(date.to_iso_string()));
     }
+    let title: String = first_line.chars().take(DOCUMENT_TITLE_CHARS).collect();
+    format!("{}.md", title.trim_end())
 }
@@ -0,0 +1,77 @@
+use crate::storage::encryption;
+use leptos::prelude::*;
+use leptos::task::spawn_local;
+
+/// Asks for the storage passphrase and opens the encrypted storage with it
+#[component]
+pub fn LockScreen(
+    /// Called once the storage is open and the app can be shown
+    on_unlocked: Callback<()>,
+) -> impl IntoView {
+    let passphrase = RwSignal::new(String::new());
+    let busy = RwSignal::new(false);
+    let error = RwSignal::new(None::<String>);
+
+    let unlock = move || {
+        if busy.get_untracked() || passphrase.with_untracked(|p| p.is_empty()) {
+            return;
+        }
+        busy.set(true);
+        error.set(None);
+        spawn_local(async move {
+            match encryption::unlock(&passphrase.get_untracked()).await {
+                Ok(()) => {
+                    passphrase.set(String::new());
+                    on_unlocked.run(());
+                }
+                Err(e) => {
+                    error.set(Some(e.to_string()));
+                    busy.set(false);
+                }
+            }
+        });
+    };
+
+    view! {
+        <div class="min-h-screen flex items-center justify-center bg-base-200 p-4">
+            <form
+                class="card bg-base-100 shadow-xl w-full max-w-sm"
+                on:submit=move |ev| {
+                    ev.prevent_default();
+                    unlock();
+                }
+            >
+                <div class="card-body gap-4">
+                    <div class="flex items-center gap-2">
+                        <i data-lucide="lock" class="w-5 h-5"></i>
+                        <h2 class="card-title text-lg">"Storage is locked"</h2>
+                    </div>
+                    <p class="text-sm text-base-content/70">
+                        "Conversations and documents are encrypted. Enter your passphrase to open them."
+                    </p>
+                    <input
+                        type="password"
+                        class="input input-bordered w-full"
+                        placeholder="Passphrase"
+                        autocomplete="current-password"
+                        autofocus=true
+                        prop:value=move || passphrase.get()
+                        on:input=move |ev| passphrase.set(event_target_value(&ev))
+                        disabled=move || busy.get()
+                    />
+                    {move || error.get().map(|e| view! { <div class="alert alert-error text-sm">{e}</div> })}
+                    <button
+                        type="submit"
+                        class="btn btn-primary"
+                        disabled=move || busy.get() || passphrase.with(|p| p.is_empty())
+                    >
+                        {move || if busy.get() { "Unlocking…" } else { "Unlock" }}
+                    </button>
+                    <p class="text-xs text-base-content/60">
+                        "A forgotten passphrase cannot be recovered, and neither can the data."
+                    </p>
+                </div>
+            </form>
+        </div>
+    }
+}
@@ -1,35 +1,105 @@
 use crate::components::ui_primitives::Button;
 use crate::components::{
-    chat_area::ChatArea, document_manager_simple::DocumentManagerSimple, sidebar::Sidebar,
-    sidebar_monitor::SidebarMonitorRight, status_bar::StatusBar,
+    activity_panel::ActivityPanel,
+    chat_area::ChatArea,
+    document_manager_simple::DocumentManagerSimple,
+    drop_zone::{DroppedFiles, GlobalDropZone},
+    graph_view::GraphView,
+    sidebar::Sidebar,
+    sidebar_monitor::SidebarMonitorRight,
+    status_bar::StatusBar,
+    tasks_panel::TasksPanel,
 };
+use crate::features::crm::CRMPanel;
+use crate::features::graphrag::GraphRAGPipeline;
+use crate::graphrag_config::create_graphrag_signals;
+use crate::models::{ActivityCategory, AppConfig, DocumentExpiry, LandingView};
+use crate::router::{Modal, RouterContext};
 use crate::state::webllm_state_simple::WebLLMStateProvider;
+use crate::state::ConversationStateContext;
+use crate::state::EventBusContext;
+use crate::state::GraphRAGStateContext;
 use crate::state::GraphRAGStateProvider;
 use crate::state::KnowledgeStorageContext;
-// use crate::features::crm::CRMPanel; // removed floating CRM panel
-use crate::graphrag_config::create_graphrag_signals;
-use crate::state::GraphRAGStateContext;
-use crate::storage::ConversationStorage;
+use crate::state::NarrationContext;
+use crate::state::TasksStateContext;
+use crate::storage::integrity;
+use crate::storage::persistent::PersistentStore;
+use crate::storage::{tab_sync, ConversationStorage, CONVERSATIONS_KEY};
+use crate::utils::compute_usage::TokenTotals;
 use crate::utils::icons::schedule_icon_render;
-use crate::utils::storage::StorageUtils;
 use leptos::prelude::*;
 
 #[component]
 pub fn MainInterface() -> impl IntoView {
+    // Conversation and open modal as linked from the URL, else the configured landing view
+    let router = RouterContext::new();
+    provide_context(router);
+    let startup = AppConfig::load().unwrap_or_default().startup;
+    let last_conversation = (startup.landing == LandingView::LastConversation)
+        .then(|| ConversationStorage::new().ok()?.list_conversations().ok())
+        .flatten()
+        .and_then(|list| list.into_iter().next())
+        .map(|c| c.id);
+    let linked = router
+        .route_now()
+        .or_landing(startup.landing, last_conversation);
+    if let Some(modal) = linked.modal {
+        router.open(modal);
+    }
+    let linked_modal = |modal: Modal| linked.modal == Some(modal);
+
     let (sidebar_collapsed, set_sidebar_collapsed) = signal(false);
-    let (monitor_collapsed, set_monitor_collapsed) = signal(true);
+    let (monitor_collapsed, set_monitor_collapsed) = signal(!linked_modal(Modal::GraphRagSettings));
     let (selected_llm, _set_selected_llm) = signal("Llama-3.2-1B-Instruct-q4f32_1-MLC".to_string());
     let (knowledge_enabled, set_knowledge_enabled) = signal(false);
     let (status_message, set_status_message) = signal("Ready".to_string());
+    let (conversation_tokens, set_conversation_tokens) = signal(TokenTotals::default());
 
     // Document manager modal state
-    let (show_document_manager, set_show_document_manager) = signal(false);
+    let (show_document_manager, set_show_document_manager) = signal(linked_modal(Modal::Documents));
+    // Tasks panel modal state
+    let (show_tasks, set_show_tasks) = signal(linked_modal(Modal::Tasks));
+    // Activity feed modal state
+    let (show_activity, set_show_activity) = signal(linked_modal(Modal::Activity));
+    // Knowledge graph modal state
+    let (show_graph, set_show_graph) = signal(linked_modal(Modal::Graph));
+    // CRM modal state; the board tab first when it is the landing view
+    let (show_crm, set_show_crm) = signal(linked_modal(Modal::Crm));
+    let crm_tab = (startup.landing == LandingView::CrmBoard).then(|| "board".to_string());
 
     // Global conversation state
     let (storage, set_storage) = signal::<Option<ConversationStorage>>(None);
-    let (current_conversation_id, set_current_conversation_id) = signal::<Option<String>>(None);
+    // Seeded from the link so no blank conversation is created first
+    let (current_conversation_id, set_current_conversation_id) =
+        signal::<Option<String>>(linked.conversation.clone());
     let (conversation_list_refresh, set_conversation_list_refresh) = signal(0u32);
 
+    router.bind_conversation(current_conversation_id, set_current_conversation_id);
+    router.bind_modal(
+        Modal::Documents,
+        show_document_manager,
+        set_show_document_manager,
+    );
+    router.bind_modal(Modal::Tasks, show_tasks, set_show_tasks);
+    router.bind_modal(Modal::Activity, show_activity, set_show_activity);
+    router.bind_modal(Modal::Graph, show_graph, set_show_graph);
+    router.bind_modal(Modal::Crm, show_crm, set_show_crm);
+    // The GraphRAG settings live in the monitor panel rather than a modal
+    Effect::new(move |_| {
+        if router
+            .route()
+            .with(|r| r.modal == Some(Modal::GraphRagSettings))
+        {
+            set_monitor_collapsed.set(false);
+        }
+    });
+    Effect::new(move |_| {
+        if monitor_collapsed.get() {
+            router.close(Modal::GraphRagSettings);
+        }
+    });
+
     // GraphRAG configuration and metrics
     let (graphrag_config, graphrag_metrics, graphrag_manager) = create_graphrag_signals();
 
@@ -44,6 +114,11 @@ pub fn MainInterface() -> impl IntoView {
         }
     });
 
+    // Conversations written in another tab show up in the list
+    tab_sync::watch(&[CONVERSATIONS_KEY], move || {
+        set_conversation_list_refresh.update(|n| *n += 1)
+    });
+
     // Effect to re-render Lucide icons when state changes
     Effect::new(move |_| {
         let _ = sidebar_collapsed.get();
@@ -56,33 +131,49 @@ pub fn MainInterface() -> impl IntoView {
         schedule_icon_render();
     });
 
+    // Event bus first so every context created below can emit into the activity feed
+    provide_context(EventBusContext::new());
     // Provide shared knowledge storage context at the app root
     provide_context(KnowledgeStorageContext::new());
+    // Tasks store shared by ChatArea (extraction) and the Tasks panel
+    let tasks = TasksStateContext::new();
+    tasks.sync_with_other_tabs();
+    provide_context(tasks);
+    // Read-aloud shared by message bubbles and the document reader
+    provide_context(NarrationContext::new());
+    // Recently opened conversations for the sidebar quick-switcher
+    provide_context(ConversationStateContext::new());
+    // Files dropped anywhere in the app, read by the Document Manager
+    provide_context(DroppedFiles::new());
 
-    // Startup coherence check: if buffer exists and index is empty, prompt to reindex
+    // Startup coherence check: if buffer exists and index is empty, prompt to reindex.
+    // Skipped when warm-loading the index at startup is turned off.
     let graphrag_ctx = use_context::<GraphRAGStateContext>();
+    let events = use_context::<EventBusContext>();
     Effect::new(move |_| {
         // Run once at mount
-        let buffer_exists = StorageUtils::retrieve_local::<String>("knowledge_upload_buffer_v1")
+        if !startup.warm_load_index {
+            return;
+        }
+        let buffer_exists = PersistentStore::read::<String>("knowledge_upload_buffer_v1")
             .ok()
             .flatten()
             .map(|s| !s.trim().is_empty())
             .unwrap_or(false);
-        // Prefer v1 key for index emptiness; fallback to legacy
-        let index_empty = if let Ok(Some(v)) = StorageUtils::retrieve_local::<
-            Vec<crate::models::graphrag::DocumentIndex>,
-        >("graphrag_document_index_v1")
-        {
-            v.is_empty()
-        } else {
-            StorageUtils::retrieve_local::<Vec<crate::models::graphrag::DocumentIndex>>(
-                "graphrag_document_index",
-            )
-            .ok()
-            .flatten()
-            .map(|v| v.is_empty())
-            .unwrap_or(true)
-        };
+        // The pipeline reads the v1 index and falls back to the legacy key
+        let indexed = GraphRAGPipeline::new()
+            .indexed_documents()
+            .map(|v| v.len())
+            .unwrap_or(0);
+        let index_empty = indexed == 0;
+        if !index_empty {
+            if let Some(events) = events.as_ref() {
+                events.record(
+                    ActivityCategory::Knowledge,
+                    format!("Knowledge index loaded: {} passage(s)", indexed),
+                );
+            }
+        }
         if buffer_exists && index_empty {
             if let Some(win) = web_sys::window() {
                 if let Ok(true) = win.confirm_with_message(
@@ -97,6 +188,50 @@ pub fn MainInterface() -> impl IntoView {
         }
     });
 
+    // Report records the startup integrity check set aside
+    Effect::new(move |_| {
+        let quarantined = integrity::quarantined_this_launch();
+        if quarantined.is_empty() {
+            return;
+        }
+        let labels: Vec<&str> = quarantined.iter().map(|r| r.label()).collect();
+        let summary = format!(
+            "Corrupt stored data set aside: {}. Repair or delete it under Storage.",
+            labels.join(", ")
+        );
+        if let Some(events) = events.as_ref() {
+            events.record(ActivityCategory::Error, summary.clone());
+        }
+        set_status_message.set(summary);
+    });
+
+    // Remind once per session about documents past their expiry date
+    Effect::new(move |_| {
+        let documents = GraphRAGPipeline::new()
+            .indexed_documents()
+            .unwrap_or_default();
+        let expired = DocumentExpiry::load().unwrap_or_default().expired_files(
+            documents.iter().map(|d| d.source_file()),
+            js_sys::Date::now(),
+        );
+        if !expired.is_empty() {
+            if let Some(events) = events.as_ref() {
+                events.record(
+                    ActivityCategory::Knowledge,
+                    format!(
+                        "Review stale documents: {} expired ({})",
+                        expired.len(),
+                        expired.join(", ")
+                    ),
+                );
+            }
+            set_status_message.set(format!(
+                "{} expired document(s) to review in the Document Manager",
+                expired.len()
+            ));
+        }
+    });
+
     view! {
         <GraphRAGStateProvider>
         <WebLLMStateProvider>
@@ -112,6 +247,10 @@ pub fn MainInterface() -> impl IntoView {
                     conversation_list_refresh=conversation_list_refresh
                     _set_conversation_list_refresh=set_conversation_list_refresh
                     set_show_document_manager=set_show_document_manager
+                    set_show_tasks=set_show_tasks
+                    set_show_activity=set_show_activity
+                    set_show_graph=set_show_graph
+                    set_show_crm=set_show_crm
                 />
 
                 // Chat area with floating monitor toggle
@@ -120,7 +259,6 @@ pub fn MainInterface() -> impl IntoView {
                     knowledge_enabled=knowledge_enabled
                     set_knowledge_enabled=set_knowledge_enabled
                     set_status_message=set_status_message
-                    selected_llm=selected_llm
                     graphrag_config=graphrag_config
                     graphrag_metrics=graphrag_metrics
                     graphrag_manager=graphrag_manager.clone()
@@ -128,6 +266,7 @@ pub fn MainInterface() -> impl IntoView {
                     current_conversation_id=current_conversation_id
                     set_current_conversation_id=set_current_conversation_id
                     set_conversation_list_refresh=set_conversation_list_refresh
+                    set_conversation_tokens=set_conversation_tokens
                     />
 
                     // Open button shown when monitor is collapsed
@@ -159,8 +298,11 @@ pub fn MainInterface() -> impl IntoView {
                 selected_llm=selected_llm
                 knowledge_enabled=knowledge_enabled
                 graphrag_metrics=graphrag_metrics
+                conversation_tokens=conversation_tokens
             />
 
+            <GlobalDropZone set_status_message=set_status_message />
+
 
 
 
@@ -183,6 +325,89 @@ pub fn MainInterface() -> impl IntoView {
                     </div>
                 </div>
             </Show>
+
+            // Tasks Modal
+            <Show when=move || show_tasks.get()>
+                <div class="fixed inset-0 bg-black bg-opacity-50 flex items-center justify-center z-50">
+                    <div class="bg-base-100 rounded-lg shadow-xl mx-4 max-h-[90vh] overflow-hidden w-full max-w-lg">
+                        <div class="flex justify-between items-center p-4 border-b border-base-300">
+                            <h2 class="text-lg font-semibold">"Tasks"</h2>
+                            <button
+                                class="btn btn-ghost btn-sm btn-circle"
+                                on:click=move |_| set_show_tasks.set(false)
+                            >
+                                "✕"
+                            </button>
+                        </div>
+                        <div class="p-4 overflow-y-auto max-h-[calc(90vh-80px)]">
+                            <TasksPanel on_open_conversation=Box::new(move |id: String| {
+                                set_current_conversation_id.set(Some(id));
+                                set_show_tasks.set(false);
+                            }) />
+                        </div>
+                    </div>
+                </div>
+            </Show>
+
+            // Activity Feed Modal
+            <Show when=move || show_activity.get()>
+                <div class="fixed inset-0 bg-black bg-opacity-50 flex items-center justify-center z-50">
+                    <div class="bg-base-100 rounded-lg shadow-xl mx-4 max-h-[90vh] overflow-hidden w-full max-w-2xl">
+                        <div class="flex justify-between items-center p-4 border-b border-base-300">
+                            <h2 class="text-lg font-semibold">"Activity"</h2>
+                            <button
+                                class="btn btn-ghost btn-sm btn-circle"
+                                on:click=move |_| set_show_activity.set(false)
+                            >
+                                "✕"
+                            </button>
+                        </div>
+                        <div class="p-4 overflow-y-auto max-h-[calc(90vh-80px)]">
+                            <ActivityPanel />
+                        </div>
+                    </div>
+                </div>
+            </Show>
+
+            // Knowledge Graph Modal
+            <Show when=move || show_graph.get()>
+                <div class="fixed inset-0 bg-black bg-opacity-50 flex items-center justify-center z-50">
+                    <div class="bg-base-100 rounded-lg shadow-xl mx-4 max-h-[90vh] overflow-hidden w-full max-w-5xl">
+                        <div class="flex justify-between items-center p-4 border-b border-base-300">
+                            <h2 class="text-lg font-semibold">"Knowledge Graph"</h2>
+                            <button
+                                class="btn btn-ghost btn-sm btn-circle"
+                                on:click=move |_| set_show_graph.set(false)
+                            >
+                                "✕"
+                            </button>
+                        </div>
+                        <div class="p-4 overflow-y-auto max-h-[calc(90vh-80px)]">
+                            <GraphView />
+                        </div>
+                    </div>
+                </div>
+            </Show>
+
+            // CRM Modal
+            <Show when=move || show_crm.get()>
+                <div class="fixed inset-0 bg-black bg-opacity-50 flex items-center justify-center z-50">
+                    <div class="bg-base-100 rounded-lg shadow-xl mx-4 max-h-[90vh] overflow-hidden w-full max-w-5xl">
+                        <div class="flex justify-between items-center p-4 border-b border-base-300">
+                            <h2 class="text-lg font-semibold">"CRM"</h2>
+                            <button
+                                class="btn btn-ghost btn-sm btn-circle"
+                                on:click=move |_| set_show_crm.set(false)
+                            >
+                                "✕"
+                            </button>
+                        </div>
+                        <div class="p-4 overflow-y-auto max-h-[calc(90vh-80px)]">
+                            <CRMPanel initial_tab=crm_tab.clone() />
+                        </div>
+                    </div>
+                </div>
+            </Show>
         </div>
         </WebLLMStateProvider>
         </GraphRAGStateProvider>
@@ -1,9 +1,84 @@
-use crate::models::{Message, MessageRole};
+use crate::components::charts::{split_chart_blocks, ContentSegment, SvgChart};
+use crate::models::graphrag::RetrievalTrace;
+use crate::models::{
+    AnswerConfidence, ConfidenceLevel, Message, MessageRole, QueryPlan, RegenerateMode,
+    RegenerateOptions, ReplyAttempt, SourceAttribution,
+};
+use crate::state::use_narration;
+use crate::utils::compute_usage::token_split;
+use crate::utils::format::FormatUtils;
+use crate::utils::json_output;
+use crate::utils::tts;
 use leptos::prelude::*;
+use serde_json::Value;
+
+// Temperature added by "Regenerate (more varied)"
+const VARIED_TEMPERATURE_DELTA: f32 = 0.3;
+
+/// Regeneration offered on an assistant reply
+#[derive(Clone, Copy)]
+pub struct RegenerateAction {
+    pub run: Callback<RegenerateOptions>,
+    /// Whether the action applies right now (last reply, model idle)
+    pub available: Signal<bool>,
+}
+
+/// Editing offered on a user message; `run` receives `(message_id, new_content)`
+#[derive(Clone, Copy)]
+pub struct EditAction {
+    pub run: Callback<(String, String)>,
+    /// Whether the message can be edited right now (model idle)
+    pub available: Signal<bool>,
+}
+
+/// Pinning offered on any stored message; `run` receives the message id and toggles it
+#[derive(Clone, Copy)]
+pub struct PinAction {
+    pub run: Callback<String>,
+    pub pinned: Signal<bool>,
+}
+
+/// Branching offered on any stored message; `run` receives the message id
+#[derive(Clone, Copy)]
+pub struct ForkAction {
+    pub run: Callback<String>,
+    pub available: Signal<bool>,
+}
+
+/// Side thread offered on any stored message; `run` receives the message id and
+/// opens the thread under it
+#[derive(Clone, Copy)]
+pub struct ThreadAction {
+    pub run: Callback<String>,
+    pub available: Signal<bool>,
+}
+
+/// Asking another model the same prompt, its reply shown beside this one; `run`
+/// receives the model id
+#[derive(Clone, Copy)]
+pub struct CompareAction {
+    pub run: Callback<String>,
+    /// Models to compare with, id and name
+    pub models: Signal<Vec<(String, String)>>,
+    pub available: Signal<bool>,
+}
 
 #[component]
-pub fn MessageBubble(message: Message) -> impl IntoView {
+pub fn MessageBubble(
+    message: Message,
+    #[prop(default = None)] regenerate: Option<RegenerateAction>,
+    #[prop(default = None)] edit: Option<EditAction>,
+    #[prop(default = None)] fork: Option<ForkAction>,
+    #[prop(default = None)] pin: Option<PinAction>,
+    #[prop(default = None)] thread: Option<ThreadAction>,
+    #[prop(default = None)] compare: Option<CompareAction>,
+    /// Opens a cited source; chips are plain labels without it
+    #[prop(default = None)]
+    open_source: Option<Callback<SourceAttribution>>,
+) -> impl IntoView {
     let is_user = matches!(message.role, MessageRole::User);
+    // Draft while the message is being edited
+    let draft = RwSignal::new(None::<String>);
     // Precompute provenance to avoid moving from `message` inside closures
     let provenance_items = message
         .metadata
@@ -12,22 +87,82 @@ pub fn MessageBubble(message: Message) -> impl IntoView {
         .cloned()
         .unwrap_or_default();
     let has_sources = !is_user && !provenance_items.is_empty();
-    let source_count = provenance_items.len();
-    let show_sources = RwSignal::new(false);
-    // Also precompute a sorted list for rendering
-    let mut sorted = provenance_items.clone();
-    sorted.sort_by(|a, b| {
+    // Citation chips, most relevant first
+    let mut sorted_sources = provenance_items;
+    sorted_sources.sort_by(|a, b| {
         b.confidence
             .partial_cmp(&a.confidence)
             .unwrap_or(std::cmp::Ordering::Equal)
     });
-    let sorted_items = sorted;
-    let sources_sig: RwSignal<Vec<_>> = RwSignal::new(sorted_items);
+    let retrieval_note = message
+        .metadata
+        .as_ref()
+        .and_then(|m| m.retrieval_note.clone())
+        .filter(|_| !is_user);
+    let confidence = message
+        .metadata
+        .as_ref()
+        .filter(|_| !is_user)
+        .and_then(|m| m.confidence.clone());
+    let query_plan = message
+        .metadata
+        .as_ref()
+        .filter(|_| !is_user)
+        .and_then(|m| m.query_plan.clone());
+    let retrieval_trace = message
+        .metadata
+        .as_ref()
+        .filter(|_| !is_user)
+        .and_then(|m| m.retrieval_trace.clone());
+    let retry_reason = message
+        .metadata
+        .as_ref()
+        .and_then(|m| m.retry_reason.clone());
+    let tools_used = message
+        .metadata
+        .as_ref()
+        .map(|m| m.tools_used.clone())
+        .unwrap_or_default();
+    let structured = message.metadata.as_ref().is_some_and(|m| m.structured);
+    let latency = message
+        .metadata
+        .as_ref()
+        .filter(|_| !is_user)
+        .and_then(|m| m.latency.clone())
+        .filter(|l| l.total_ms() > 0);
+    let token_split = message
+        .metadata
+        .as_ref()
+        .filter(|_| !is_user)
+        .and_then(token_split);
+    let earlier_replies = message
+        .metadata
+        .as_ref()
+        .map(|m| m.attempts.clone())
+        .unwrap_or_default();
+    let show_earlier = RwSignal::new(false);
+    let model_used = message.metadata.as_ref().and_then(|m| m.model_used.clone());
+    let compared = message
+        .metadata
+        .as_ref()
+        .filter(|_| !is_user)
+        .map(|m| m.compared.clone())
+        .unwrap_or_default();
+    let narration = use_narration();
+    let can_read_aloud = !is_user && tts::is_supported();
+
+    let content = message.content.clone();
+    let message_id = message.id.clone();
+    // Only entering or leaving the editor re-renders the bubble, not each keystroke
+    let editing = Memo::new(move |_| draft.with(|d| d.is_some()));
 
     view! {
-        <div class=move || {
-            format!("chat {} animate-fade-in", if is_user { "chat-end" } else { "chat-start" })
-        }>
+        <div
+            id=format!("message-{}", message.id)
+            class=move || {
+                format!("chat {} animate-fade-in rounded-box", if is_user { "chat-end" } else { "chat-start" })
+            }
+        >
             <div class="chat-image avatar">
                 <div class="w-10 h-10 rounded-full bg-base-300 p-2 flex items-center justify-center">
                     <i
@@ -41,47 +176,595 @@ pub fn MessageBubble(message: Message) -> impl IntoView {
                     "chat-bubble {} transition-all duration-200 hover:shadow-lg",
                     if is_user { "chat-bubble-primary" } else { "chat-bubble-neutral" },
                 )
-            }>{message.content}</div>
+            }>
+                {move || {
+                    if !editing.get() && !compared.is_empty() {
+                        return compared_replies(&content, model_used.as_deref(), structured, &compared);
+                    }
+                    if !editing.get() {
+                        return message_body(&content, is_user, structured);
+                    }
+                    let message_id = message_id.clone();
+                    let save = move || {
+                        if let (Some(action), Some(content)) = (edit, draft.get_untracked()) {
+                            action.run.run((message_id.clone(), content));
+                        }
+                        draft.set(None);
+                    };
+                    view! {
+                        <div class="flex flex-col gap-2 min-w-64">
+                            <textarea
+                                class="textarea textarea-bordered textarea-sm text-base-content w-full"
+                                rows="4"
+                                prop:value=draft.get_untracked().unwrap_or_default()
+                                on:input=move |ev| draft.set(Some(event_target_value(&ev)))
+                            ></textarea>
+                            <div class="text-xs opacity-80">"Saving removes the messages after this one and asks again."</div>
+                            <div class="flex justify-end gap-1">
+                                <button class="btn btn-ghost btn-xs" on:click=move |_| draft.set(None)>"Cancel"</button>
+                                <button
+                                    class="btn btn-xs"
+                                    disabled=move || draft.with(|d| d.as_deref().unwrap_or("").trim().is_empty())
+                                    on:click=move |_| save()
+                                >
+                                    "Save & resend"
+                                </button>
+                            </div>
+                        </div>
+                    }
+                    .into_any()
+                }}
+            </div>
             <div class="chat-footer opacity-50">
                 <time class="text-xs">{format_timestamp(message.timestamp)}</time>
+                {token_split.map(|(prompt, completion)| view! {
+                    <span
+                        class="ml-1 text-xs font-mono"
+                        title=format!("Prompt: {} tokens, reply: {} tokens", prompt, completion)
+                    >
+                        {format!("{} tokens", FormatUtils::format_number((prompt + completion) as i64))}
+                    </span>
+                })}
+                {retry_reason.map(|reason| view! {
+                    <span
+                        class="ml-1 px-1.5 py-0.5 rounded bg-base-300 text-[10px] tracking-wide"
+                        title=format!("Regenerated automatically: {}", reason)
+                    >
+                        "retried"
+                    </span>
+                })}
+                {(!tools_used.is_empty()).then(|| view! {
+                    <span
+                        class="ml-1 px-1.5 py-0.5 rounded bg-base-300 text-[10px] tracking-wide"
+                        title=format!("Tools called: {}", tools_used.join(", "))
+                    >
+                        {format!("{} tool call{}", tools_used.len(), if tools_used.len() == 1 { "" } else { "s" })}
+                    </span>
+                })}
+                {latency.map(|latency| {
+                    let total = latency.total_ms().max(1);
+                    let reused = latency.prefill_reused_tokens;
+                    let prefill_note = (reused > 0).then(|| match latency.prefill_saved_ms {
+                        Some(ms) => format!("{} tokens, ~{} saved", reused, format_latency(ms)),
+                        None => format!("{} tokens", reused),
+                    });
+                    view! {
+                        <details class="inline-block ml-1 align-middle">
+                            <summary
+                                class="list-none cursor-pointer px-1.5 py-0.5 rounded bg-base-300 text-[10px] font-mono"
+                                title=if reused > 0 {
+                                    "Latency breakdown (earlier turns reused from the model's cache)"
+                                } else {
+                                    "Latency breakdown"
+                                }
+                            >
+                                {format_latency(latency.total_ms())}
+                                {(reused > 0).then(|| view! { <span class="text-success">" ↺"</span> })}
+                            </summary>
+                            <div class="mt-1 p-2 rounded bg-base-200 text-[10px] font-mono space-y-1 w-56">
+                                {latency
+                                    .stages()
+                                    .into_iter()
+                                    .map(|(label, ms)| view! {
+                                        <div>
+                                            <div class="flex justify-between gap-2">
+                                                <span>{label}</span>
+                                                <span>{format_latency(ms)}</span>
+                                            </div>
+                                            <div class="h-1 rounded bg-base-300">
+                                                <div
+                                                    class="h-1 rounded bg-primary"
+                                                    style=format!("width: {}%", ms * 100 / total)
+                                                ></div>
+                                            </div>
+                                        </div>
+                                    })
+                                    .collect_view()}
+                                {prefill_note.map(|note| view! {
+                                    <div class="flex justify-between gap-2 pt-1 border-t border-base-300">
+                                        <span>"Prefill reused"</span>
+                                        <span>{note}</span>
+                                    </div>
+                                })}
+                            </div>
+                        </details>
+                    }
+                })}
+                {can_read_aloud.then(|| {
+                    let id = message.id.clone();
+                    let reading = Signal::derive({
+                        let id = id.clone();
+                        move || narration.is_reading(&id)
+                    });
+                    let content = message.content.clone();
+                    view! {
+                        <button
+                            class=move || {
+                                format!("btn btn-ghost btn-xs ml-1 {}", if reading.get() { "text-primary" } else { "" })
+                            }
+                            title=move || if reading.get() { "Stop reading" } else { "Read aloud" }
+                            aria-pressed=move || reading.get().to_string()
+                            on:click=move |_| {
+                                if reading.get_untracked() {
+                                    narration.stop();
+                                } else {
+                                    narration.play(&id, tts::paragraphs(&content), 0);
+                                }
+                            }
+                        >
+                            <i data-lucide="volume-2" class="h-3.5 w-3.5"></i>
+                        </button>
+                    }
+                })}
+                {pin.map(|action| {
+                    let id = message.id.clone();
+                    view! {
+                        <button
+                            class=move || {
+                                if action.pinned.get() {
+                                    "btn btn-ghost btn-xs ml-1 text-warning"
+                                } else {
+                                    "btn btn-ghost btn-xs ml-1"
+                                }
+                            }
+                            title=move || if action.pinned.get() { "Unpin" } else { "Pin message" }
+                            on:click=move |_| action.run.run(id.clone())
+                        >
+                            <i data-lucide="pin" class="h-3.5 w-3.5"></i>
+                        </button>
+                    }
+                })}
+                {fork.map(|action| {
+                    let id = message.id.clone();
+                    view! {
+                        <Show when=move || action.available.get() && !editing.get()>
+                            <button
+                                class="btn btn-ghost btn-xs ml-1"
+                                title="Branch from here"
+                                on:click={
+                                    let id = id.clone();
+                                    move |_| action.run.run(id.clone())
+                                }
+                            >
+                                <i data-lucide="git-branch" class="h-3.5 w-3.5"></i>
+                            </button>
+                        </Show>
+                    }
+                })}
+                {thread.map(|action| {
+                    let id = message.id.clone();
+                    view! {
+                        <Show when=move || action.available.get() && !editing.get()>
+                            <button
+                                class="btn btn-ghost btn-xs ml-1"
+                                title="Reply in thread"
+                                on:click={
+                                    let id = id.clone();
+                                    move |_| action.run.run(id.clone())
+                                }
+                            >
+                                <i data-lucide="message-square-reply" class="h-3.5 w-3.5"></i>
+                            </button>
+                        </Show>
+                    }
+                })}
+                {edit.map(|action| {
+                    let content = message.content.clone();
+                    view! {
+                        <Show when=move || action.available.get() && !editing.get()>
+                            <button
+                                class="btn btn-ghost btn-xs ml-1"
+                                title="Edit message"
+                                on:click={
+                                    let content = content.clone();
+                                    move |_| draft.set(Some(content.clone()))
+                                }
+                            >
+                                <i data-lucide="pencil" class="h-3.5 w-3.5"></i>
+                            </button>
+                        </Show>
+                    }
+                })}
+                {regenerate.map(|action| {
+                    let run = move |mode, temperature_delta| {
+                        action.run.run(RegenerateOptions { mode, temperature_delta })
+                    };
+                    view! {
+                        <Show when=move || action.available.get()>
+                            <div class="dropdown dropdown-top ml-1">
+                                <div tabindex="0" role="button" class="btn btn-ghost btn-xs" title="Regenerate">
+                                    <i data-lucide="refresh-cw" class="h-3.5 w-3.5"></i>
+                                </div>
+                                <ul tabindex="0" class="dropdown-content menu menu-sm bg-base-200 rounded-box z-10 w-56 p-1 shadow">
+                                    <li><a on:click=move |_| run(RegenerateMode::Replace, 0.0)>"Regenerate"</a></li>
+                                    <li>
+                                        <a on:click=move |_| run(RegenerateMode::Replace, VARIED_TEMPERATURE_DELTA)>
+                                            "Regenerate (more varied)"
+                                        </a>
+                                    </li>
+                                    <li><a on:click=move |_| run(RegenerateMode::Append, 0.0)>"Add another reply"</a></li>
+                                </ul>
+                            </div>
+                        </Show>
+                    }
+                })}
+                {compare.map(|action| view! {
+                    <Show when=move || action.available.get() && !action.models.get().is_empty()>
+                        <div class="dropdown dropdown-top ml-1">
+                            <div tabindex="0" role="button" class="btn btn-ghost btn-xs" title="Compare with another model">
+                                <i data-lucide="columns-2" class="h-3.5 w-3.5"></i>
+                            </div>
+                            <ul tabindex="0" class="dropdown-content menu menu-sm bg-base-200 rounded-box z-10 w-64 p-1 shadow">
+                                <li class="menu-title">"Ask the same question to"</li>
+                                {move || {
+                                    action
+                                        .models
+                                        .get()
+                                        .into_iter()
+                                        .map(|(id, name)| {
+                                            let title = id.clone();
+                                            view! {
+                                                <li><a title=title on:click=move |_| action.run.run(id.clone())>{name}</a></li>
+                                            }
+                                        })
+                                        .collect_view()
+                                }}
+                            </ul>
+                        </div>
+                    </Show>
+                })}
             </div>
-            <Show when=move || has_sources>
-                <div class="mt-1 text-xs text-base-content/70">
-                    <div class="flex items-center gap-2">
+            {(!is_user && !earlier_replies.is_empty()).then(|| {
+                let count = earlier_replies.len();
+                view! {
+                    <div class="mt-1 text-xs text-base-content/70">
                         <button
                             class="underline hover:text-base-content transition-colors"
-                            on:click=move |_| show_sources.update(|v| *v = !*v)
+                            on:click=move |_| show_earlier.update(|v| *v = !*v)
                         >
-                            {move || if show_sources.get() { "Hide sources" } else { "Sources" }}
-                            {format!(" ({})", source_count)}
+                            {move || if show_earlier.get() { "Hide earlier replies" } else { "Earlier replies" }}
+                            {format!(" ({})", count)}
                         </button>
-                        <span class="px-1.5 py-0.5 rounded bg-base-300 text-[10px] tracking-wide">RAG</span>
-                    </div>
-                    <Show when=move || show_sources.get()>
-                        {move || {
-                            let items = sources_sig.get();
-                            view! {
-                                <ul class="mt-1 space-y-0.5">
-                                    {items.into_iter().map(|a| {
-                                        let pct = (a.confidence * 100.0).round() as i32;
+                        <Show when=move || show_earlier.get()>
+                            <ol class="mt-1 space-y-1 list-decimal list-inside">
+                                {earlier_replies
+                                    .iter()
+                                    .map(|a| {
+                                        let label = match a.temperature {
+                                            Some(t) => format!("{} · temperature {:.1}", format_timestamp(a.timestamp), t),
+                                            None => format_timestamp(a.timestamp),
+                                        };
                                         view! {
-                                            <li class="flex items-center gap-2">
-                                                <i data-lucide="file-text" class="h-3.5 w-3.5 opacity-70"></i>
-                                                <span class="font-medium">{a.title}</span>
-                                                <span class="opacity-60">{format!("{}%", pct)}</span>
+                                            <li>
+                                                <span class="opacity-60">{label}</span>
+                                                <div class="whitespace-pre-wrap opacity-80">{a.content.clone()}</div>
                                             </li>
                                         }
-                                    }).collect::<Vec<_>>()}
-                                </ul>
-                            }.into_view()
-                        }}
-                    </Show>
+                                    })
+                                    .collect::<Vec<_>>()}
+                            </ol>
+                        </Show>
+                    </div>
+                }
+            })}
+            {retrieval_note.map(|note| view! {
+                <div class="mt-1 flex items-center gap-1 text-xs text-base-content/70">
+                    <i data-lucide="info" class="h-3.5 w-3.5 opacity-70"></i>
+                    <span>{note}</span>
+                </div>
+            })}
+            {query_plan.map(reasoning_details)}
+            {retrieval_trace.map(trace_details)}
+            {(has_sources || confidence.is_some()).then(|| view! {
+                <div class="mt-1 flex flex-wrap items-center gap-1 text-xs text-base-content/70">
+                    {confidence.map(confidence_details)}
+                    {has_sources.then(|| view! {
+                        <span class="px-1.5 py-0.5 rounded bg-base-300 text-[10px] tracking-wide">RAG</span>
+                    })}
+                    {sorted_sources
+                        .into_iter()
+                        .enumerate()
+                        .map(|(i, source)| {
+                            let pct = (source.confidence * 100.0).round() as i32;
+                            let label = format!("[{}] {}", i + 1, source.title);
+                            let title = format!("{} · {}% relevant", source.title, pct);
+                            view! {
+                                <button
+                                    class="inline-flex items-center gap-1 max-w-56 px-2 py-0.5 rounded-full border border-base-300 bg-base-200 hover:bg-base-300 transition-colors disabled:cursor-default"
+                                    title=title
+                                    disabled=open_source.is_none()
+                                    on:click=move |_| {
+                                        if let Some(open) = open_source {
+                                            open.run(source.clone());
+                                        }
+                                    }
+                                >
+                                    <i data-lucide="file-text" class="h-3 w-3 shrink-0 opacity-70"></i>
+                                    <span class="truncate">{label}</span>
+                                    <span class="opacity-60">{format!("{}%", pct)}</span>
+                                </button>
+                            }
+                        })
+                        .collect_view()}
                 </div>
-            </Show>
+            })}
         </div>
     }
 }
 
+/// Confidence badge that opens how the score was computed
+fn confidence_details(confidence: AnswerConfidence) -> impl IntoView {
+    let badge = match confidence.level {
+        ConfidenceLevel::High => "badge-success",
+        ConfidenceLevel::Medium => "badge-warning",
+        ConfidenceLevel::Low => "badge-error",
+    };
+    view! {
+        <details class="inline-block align-middle">
+            <summary
+                class=format!("list-none cursor-pointer badge badge-sm {}", badge)
+                title="How confident the answer is; click for the computation"
+            >
+                {format!("{} confidence", confidence.level.label())}
+            </summary>
+            <div class="mt-1 p-2 rounded bg-base-200 text-[10px] space-y-1 w-72">
+                {confidence
+                    .signals
+                    .iter()
+                    .map(|s| view! {
+                        <div>
+                            <div class="flex justify-between gap-2 font-mono">
+                                <span>{s.name.clone()}</span>
+                                <span>{format!("{:.2} × {:.2}", s.value, s.weight)}</span>
+                            </div>
+                            <div class="opacity-70">{s.note.clone()}</div>
+                        </div>
+                    })
+                    .collect_view()}
+                <div class="flex justify-between gap-2 font-mono border-t border-base-300 pt-1">
+                    <span>"Score"</span>
+                    <span>{format!("{:.2}", confidence.score)}</span>
+                </div>
+                <div class="opacity-70">
+                    "Signal × weight, summed. Weights and the level thresholds are in the generation settings."
+                </div>
+            </div>
+        </details>
+    }
+}
+
+/// Expandable list of the sub-queries a question was split into and what each found
+fn reasoning_details(plan: QueryPlan) -> impl IntoView {
+    view! {
+        <details class="mt-1 text-xs text-base-content/70">
+            <summary class="cursor-pointer" title="How the question was split before searching">
+                {format!("Reasoning · {} sub-queries", plan.sub_queries.len())}
+            </summary>
+            <ol class="mt-1 pl-4 list-decimal space-y-1">
+                {plan
+                    .sub_queries
+                    .into_iter()
+                    .map(|sub| {
+                        let found = if sub.sources.is_empty() {
+                            "nothing new".to_string()
+                        } else {
+                            sub.sources.join(", ")
+                        };
+                        view! {
+                            <li>
+                                <div class="font-medium">{sub.text}</div>
+                                <div class="opacity-70">{format!("Found: {}", found)}</div>
+                            </li>
+                        }
+                    })
+                    .collect_view()}
+            </ol>
+        </details>
+    }
+}
+
+/// "Why this answer?": the retrieval trace of a knowledge-enhanced reply
+fn trace_details(trace: RetrievalTrace) -> impl IntoView {
+    let score = |s: Option<f32>| {
+        s.map(|s| format!("{:.2}", s))
+            .unwrap_or_else(|| "–".to_string())
+    };
+    let total_ms: u32 = trace.stages.iter().map(|s| s.ms).sum();
+    let prompt_chunks = trace.prompt_chunks.clone();
+    view! {
+        <details class="mt-1 text-xs text-base-content/70">
+            <summary class="cursor-pointer" title="How the knowledge in the prompt was retrieved and ranked">
+                "Why this answer?"
+            </summary>
+            <div class="mt-1 p-2 rounded bg-base-200 space-y-2 overflow-x-auto">
+                <div class="flex flex-wrap items-center gap-1">
+                    <span class="opacity-60">"Query terms:"</span>
+                    {trace
+                        .query_tokens
+                        .into_iter()
+                        .map(|t| view! { <span class="badge badge-ghost badge-xs font-mono">{t}</span> })
+                        .collect_view()}
+                </div>
+                <div class="flex flex-wrap gap-x-3 gap-y-0.5 font-mono text-[10px]">
+                    {trace
+                        .stages
+                        .into_iter()
+                        .map(|s| view! { <span>{format!("{} {}", s.name, format_latency(s.ms))}</span> })
+                        .collect_view()}
+                    <span class="font-semibold">{format!("Total {}", format_latency(total_ms))}</span>
+                </div>
+                <table class="table table-xs">
+                    <thead>
+                        <tr>
+                            <th>"Source"</th>
+                            <th title="Lexical score, fused with embedding similarity">"Search"</th>
+                            <th title="After PageRank, community and graph fusion">"Graph"</th>
+                            <th title="Cross-encoder score">"Rerank"</th>
+                            <th title="Share of the query covered, compared with the threshold">"Relevance"</th>
+                            <th>"Outcome"</th>
+                        </tr>
+                    </thead>
+                    <tbody>
+                        {trace
+                            .candidates
+                            .into_iter()
+                            .map(|c| {
+                                let in_prompt = prompt_chunks.contains(&c.id);
+                                let outcome = if in_prompt {
+                                    "in prompt"
+                                } else {
+                                    c.outcome.label()
+                                };
+                                view! {
+                                    <tr class:font-semibold=in_prompt>
+                                        <td class="max-w-40 truncate" title=c.title.clone()>{c.title.clone()}</td>
+                                        <td class="font-mono">{format!("{:.2}", c.search_score)}</td>
+                                        <td class="font-mono">{score(c.graph_score)}</td>
+                                        <td class="font-mono">{score(c.rerank_score)}</td>
+                                        <td class="font-mono">{format!("{:.2}", c.relevance)}</td>
+                                        <td>{outcome}</td>
+                                    </tr>
+                                }
+                            })
+                            .collect_view()}
+                    </tbody>
+                </table>
+            </div>
+        </details>
+    }
+}
+
+/// "850 ms" below a second, "2.4 s" above
+fn format_latency(ms: u32) -> String {
+    if ms < 1000 {
+        format!("{} ms", ms)
+    } else {
+        format!("{:.1} s", ms as f64 / 1000.0)
+    }
+}
+
+/// Assistant replies may embed ```chart blocks rendered as inline SVG charts
+/// A reply and the other models' replies to the same prompt, side by side
+fn compared_replies(
+    content: &str,
+    model_used: Option<&str>,
+    structured: bool,
+    compared: &[ReplyAttempt],
+) -> AnyView {
+    let card = |model: String, body: AnyView| {
+        view! {
+            <div class="min-w-0 rounded-lg bg-base-100/10 p-2">
+                <div class="mb-1 text-[10px] font-mono uppercase tracking-wide opacity-70 truncate" title=model.clone()>
+                    {model}
+                </div>
+                {body}
+            </div>
+        }
+    };
+    view! {
+        <div class="grid grid-cols-1 md:grid-cols-2 gap-2">
+            {card(
+                model_used.unwrap_or("This reply").to_string(),
+                message_body(content, false, structured),
+            )}
+            {compared
+                .iter()
+                .map(|reply| {
+                    card(
+                        reply.model_used.clone().unwrap_or_else(|| "Other model".to_string()),
+                        message_body(&reply.content, false, false),
+                    )
+                })
+                .collect_view()}
+        </div>
+    }
+    .into_any()
+}
+
+fn message_body(content: &str, is_user: bool, structured: bool) -> AnyView {
+    if structured {
+        if let Ok(value) = serde_json::from_str::<Value>(json_output::extract_json(content)) {
+            return view! { <div class="font-mono text-xs">{json_tree(None, &value, 0)}</div> }
+                .into_any();
+        }
+    }
+    if !is_user && content.contains("```chart") {
+        split_chart_blocks(content)
+            .into_iter()
+            .map(|seg| match seg {
+                ContentSegment::Text(t) => view! { <div class="whitespace-pre-wrap">{t}</div> }.into_any(),
+                ContentSegment::Chart(spec) => view! { <SvgChart spec=spec /> }.into_any(),
+                ContentSegment::InvalidChart { raw, error } => view! {
+                    <div class="text-xs">
+                        <div class="opacity-70">{format!("Chart could not be rendered: {}", error)}</div>
+                        <pre class="whitespace-pre-wrap">{raw}</pre>
+                    </div>
+                }
+                .into_any(),
+            })
+            .collect_view()
+            .into_any()
+    } else {
+        content.to_string().into_any()
+    }
+}
+
+/// A JSON value as nested collapsible sections; the first two levels start open
+fn json_tree(key: Option<String>, value: &Value, depth: usize) -> AnyView {
+    let label = key.map(|k| format!("{}: ", k)).unwrap_or_default();
+    let children: Vec<(String, &Value)> = match value {
+        Value::Object(map) => map.iter().map(|(k, v)| (k.clone(), v)).collect(),
+        Value::Array(items) => items
+            .iter()
+            .enumerate()
+            .map(|(i, v)| (i.to_string(), v))
+            .collect(),
+        scalar => {
+            return view! {
+                <div class="pl-4 whitespace-pre-wrap">
+                    <span class="opacity-70">{label}</span>
+                    {scalar.to_string()}
+                </div>
+            }
+            .into_any()
+        }
+    };
+    let summary = match value {
+        Value::Array(_) => format!("{}[{}]", label, children.len()),
+        _ => format!("{}{{{}}}", label, children.len()),
+    };
+    view! {
+        <details class="pl-4" open=depth < 2>
+            <summary class="cursor-pointer opacity-70 -ml-4">{summary}</summary>
+            {children
+                .into_iter()
+                .map(|(k, v)| json_tree(Some(k), v, depth + 1))
+                .collect_view()}
+        </details>
+    }
+    .into_any()
+}
+
 fn format_timestamp(timestamp: f64) -> String {
     let date = js_sys::Date::new(&timestamp.into());
     let hours = date.get_hours();
@@ -0,0 +1,177 @@
+use crate::components::message_bubble::MessageBubble;
+use crate::models::{Message, MessageRole};
+use crate::storage::{ConversationStorage, ThreadInfo};
+use crate::utils::format::FormatUtils;
+use crate::utils::threads::thread_context;
+use crate::webllm_binding::{loaded_engine, send_message_to_llm};
+use leptos::prelude::*;
+use leptos::task::spawn_local;
+
+/// Side thread under a message: a reply count while collapsed, the thread and its
+/// input once opened. Replies only see `parent` and the thread's own history.
+#[component]
+pub fn MessageThread(
+    parent: Message,
+    storage: ReadSignal<Option<ConversationStorage>>,
+    conversation_id: ReadSignal<Option<String>>,
+    /// The stored thread, once it has replies
+    info: Signal<Option<ThreadInfo>>,
+    /// Message whose thread is expanded
+    open: RwSignal<Option<String>>,
+    model_ready: Signal<bool>,
+    /// Called after the thread was written to
+    on_changed: Callback<()>,
+) -> impl IntoView {
+    let parent_id = parent.id.clone();
+    let expanded = Signal::derive({
+        let id = parent_id.clone();
+        move || open.with(|o| o.as_ref() == Some(&id))
+    });
+    let replies = RwSignal::new(Vec::<Message>::new());
+    let draft = RwSignal::new(String::new());
+    let busy = RwSignal::new(false);
+    let error = RwSignal::new(None::<String>);
+
+    // Read the thread back when it is expanded
+    Effect::new(move |_| {
+        if !expanded.get() {
+            return;
+        }
+        let loaded = match (storage.get_untracked(), info.get()) {
+            (Some(storage), Some(info)) => storage
+                .load_conversation(&info.id)
+                .unwrap_or_else(|e| {
+                    log::error!("Failed to load thread: {:?}", e);
+                    None
+                })
+                .unwrap_or_default(),
+            _ => Vec::new(),
+        };
+        replies.set(loaded);
+    });
+
+    let parent = StoredValue::new(parent);
+    let send = move || {
+        let question = draft.get_untracked().trim().to_string();
+        if question.is_empty() || busy.get_untracked() {
+            return;
+        }
+        let (Some(storage), Some(conv_id)) =
+            (storage.get_untracked(), conversation_id.get_untracked())
+        else {
+            return;
+        };
+        let Some((_, engine)) = loaded_engine() else {
+            error.set(Some("Model not available".to_string()));
+            return;
+        };
+        let parent_id = parent.with_value(|p| p.id.clone());
+        let thread_id = match storage.open_thread(&conv_id, &parent_id) {
+            Ok(id) => id,
+            Err(e) => {
+                log::error!("Failed to open thread: {:?}", e);
+                error.set(Some("Failed to open thread".to_string()));
+                return;
+            }
+        };
+        let question = Message::new(MessageRole::User, question);
+        if let Err(e) = storage.save_message(&thread_id, &question) {
+            log::error!("Failed to save thread message: {:?}", e);
+            error.set(Some("Failed to save message".to_string()));
+            return;
+        }
+        replies.update(|r| r.push(question));
+        draft.set(String::new());
+        error.set(None);
+        busy.set(true);
+        on_changed.run(());
+
+        let request = parent.with_value(|p| thread_context(p, &replies.get_untracked()));
+        spawn_local(async move {
+            match send_message_to_llm(&engine, request).await {
+                Ok(text) => {
+                    let reply = Message::new(MessageRole::Assistant, text);
+                    if let Err(e) = storage.save_message(&thread_id, &reply) {
+                        log::error!("Failed to save thread reply: {:?}", e);
+                    }
+                    replies.update(|r| r.push(reply));
+                    on_changed.run(());
+                }
+                Err(e) => {
+                    log::error!("Thread reply failed: {:?}", e);
+                    error.set(Some("The model did not reply".to_string()));
+                }
+            }
+            busy.set(false);
+        });
+    };
+
+    let toggle = {
+        let id = parent_id.clone();
+        move |_| {
+            if expanded.get_untracked() {
+                open.set(None);
+            } else {
+                open.set(Some(id.clone()));
+            }
+        }
+    };
+
+    view! {
+        <Show when=move || expanded.get() || info.with(|i| i.is_some())>
+            <div class="ml-6 mb-3 border-l-2 border-base-300 pl-3" data-thread-of=parent_id.clone()>
+                <button class="btn btn-ghost btn-xs text-primary gap-1" on:click=toggle.clone()>
+                    <i data-lucide="message-square-reply" class="h-3.5 w-3.5"></i>
+                    {move || match info.get() {
+                        Some(i) => format!(
+                            "{} {} · {}",
+                            i.reply_count,
+                            if i.reply_count == 1 { "reply" } else { "replies" },
+                            FormatUtils::format_relative_time(i.updated_at)
+                        ),
+                        None => "New thread".to_string(),
+                    }}
+                    <i
+                        data-lucide=move || if expanded.get() { "chevron-up" } else { "chevron-down" }
+                        class="h-3 w-3"
+                    ></i>
+                </button>
+                <Show when=move || expanded.get()>
+                    <div class="mt-2 text-sm">
+                        <For
+                            each=move || replies.get()
+                            key=|m| m.id.clone()
+                            children=|m| view! { <MessageBubble message=m /> }
+                        />
+                        <Show when=move || busy.get()>
+                            <div class="text-xs text-base-content/60 mb-2">"Replying…"</div>
+                        </Show>
+                        {move || error.get().map(|e| view! { <div class="text-xs text-error mb-2">{e}</div> })}
+                        <div class="flex gap-2">
+                            <input
+                                type="text"
+                                class="input input-bordered input-sm flex-1"
+                                placeholder="Ask about this message…"
+                                prop:value=move || draft.get()
+                                on:input=move |ev| draft.set(event_target_value(&ev))
+                                on:keydown=move |ev| {
+                                    if ev.key() == "Enter" {
+                                        ev.prevent_default();
+                                        send();
+                                    }
+                                }
+                            />
+                            <button
+                                class="btn btn-primary btn-sm"
+                                disabled=move || busy.get() || !model_ready.get() || draft.with(|d| d.trim().is_empty())
+                                on:click=move |_| send()
+                            >
+                                "Reply"
+                            </button>
+                        </div>
+                    </div>
+                </Show>
+            </div>
+        </Show>
+    }
+}
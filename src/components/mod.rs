@@ -1,19 +1,43 @@
+pub mod activity_panel;
+pub mod backup_panel;
+pub mod charts;
 pub mod chat_area;
+pub mod collections_panel;
 pub mod conversation_history;
 pub mod conversation_list;
+pub mod conversation_search;
 pub mod counter_btn;
+pub mod device_report;
 pub mod input_area;
+pub mod lock_screen;
 // Components module
 pub mod atoms;
+pub mod document_inspector;
 pub mod document_manager_simple;
+pub mod document_reader;
+pub mod drop_zone;
+pub mod freshness_panel;
+pub mod generation_settings;
+pub mod graph_view;
 pub mod graphrag_settings;
 pub mod graphrag_settings_modal;
 pub mod main_interface;
 pub mod message_bubble;
+pub mod message_thread;
 pub mod molecules;
+pub mod notification_settings;
+pub mod original_files_panel;
+pub mod pwa_toasts;
+pub mod settings_page;
 pub mod sidebar;
 pub mod sidebar_action;
 pub mod sidebar_monitor;
+pub mod source_panel;
+pub mod startup_settings;
 pub mod status_bar;
+pub mod storage_encryption;
+pub mod storage_health;
+pub mod table_paste;
+pub mod tasks_panel;
 pub mod theme_toggle;
 pub mod ui_primitives;
@@ -0,0 +1,92 @@
+use crate::utils::notifications::{NotificationSettings, NotificationUtils, PermissionState};
+use leptos::prelude::*;
+use leptos::task::spawn_local;
+
+/// Opt-in browser notifications for model downloads, reindexes and coverage reports
+#[component]
+pub fn NotificationSettingsPanel(
+    /// Called after the settings were persisted
+    #[prop(optional)]
+    on_saved: Option<Box<dyn Fn() + 'static>>,
+) -> impl IntoView {
+    let initial = NotificationUtils::load_settings();
+    let enabled = RwSignal::new(initial.enabled);
+    let only_when_hidden = RwSignal::new(initial.only_when_hidden);
+    let permission = RwSignal::new(NotificationUtils::permission());
+    let error = RwSignal::new(None::<String>);
+
+    let request_permission = move |_| {
+        spawn_local(async move {
+            let state = NotificationUtils::request_permission().await;
+            permission.set(state);
+            if state == PermissionState::Granted {
+                enabled.set(true);
+            }
+        });
+    };
+
+    let save = move || {
+        let settings = NotificationSettings {
+            enabled: enabled.get_untracked(),
+            only_when_hidden: only_when_hidden.get_untracked(),
+        };
+        match NotificationUtils::save_settings(&settings) {
+            Ok(()) => {
+                error.set(None);
+                if let Some(cb) = on_saved.as_ref() {
+                    cb();
+                }
+            }
+            Err(e) => error.set(Some(e.to_string())),
+        }
+    };
+
+    view! {
+        <div class="flex flex-col gap-4" id="notification-settings">
+            <p class="text-sm text-base-content/70">
+                "Get a browser notification when a model download, a reindex or a coverage report finishes or fails."
+            </p>
+
+            <div class="flex items-center justify-between gap-2">
+                <div class="text-sm">
+                    <span class="font-medium">"Permission: "</span>
+                    <span class="text-base-content/70">{move || permission.get().label()}</span>
+                </div>
+                <Show when=move || permission.get() == PermissionState::Prompt>
+                    <button class="btn btn-sm btn-outline" on:click=request_permission>
+                        <i data-lucide="bell" class="h-4 w-4"></i>
+                        "Allow notifications"
+                    </button>
+                </Show>
+            </div>
+
+            <div class="flex flex-col gap-2">
+                <label class="label cursor-pointer justify-start gap-2">
+                    <input
+                        type="checkbox"
+                        class="checkbox checkbox-sm"
+                        disabled=move || permission.get() != PermissionState::Granted
+                        prop:checked=move || enabled.get()
+                        on:change=move |ev| enabled.set(event_target_checked(&ev))
+                    />
+                    <span class="label-text">"Notify when long-running tasks finish"</span>
+                </label>
+                <label class="label cursor-pointer justify-start gap-2">
+                    <input
+                        type="checkbox"
+                        class="checkbox checkbox-sm"
+                        prop:checked=move || only_when_hidden.get()
+                        on:change=move |ev| only_when_hidden.set(event_target_checked(&ev))
+                    />
+                    <span class="label-text">"Only while this tab is in the background"</span>
+                </label>
+            </div>
+
+            {move || error.get().map(|e| view! { <div class="alert alert-error text-sm">{e}</div> })}
+
+            <div class="flex justify-end">
+                <button class="btn btn-primary btn-sm" on:click=move |_| save()>"Save"</button>
+            </div>
+        </div>
+    }
+}
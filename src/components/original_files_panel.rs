@@ -0,0 +1,261 @@
+use crate::models::app::AppError;
+use crate::storage::originals::{self, OriginalFile, PREVIEW_BYTES};
+use crate::utils::download::DownloadUtils;
+use crate::utils::format::FormatUtils;
+use leptos::prelude::*;
+use leptos::task::spawn_local;
+
+/// What the preview pane shows
+#[derive(Clone, PartialEq)]
+enum Preview {
+    /// Start of a text file
+    Text {
+        name: String,
+        text: String,
+        cut: bool,
+    },
+    /// Object URL of a file the browser displays, e.g. a PDF
+    Embedded { name: String, url: String },
+}
+
+impl Preview {
+    fn name(&self) -> &str {
+        match self {
+            Preview::Text { name, .. } | Preview::Embedded { name, .. } => name,
+        }
+    }
+
+    fn release(&self) {
+        if let Preview::Embedded { url, .. } = self {
+            let _ = web_sys::Url::revoke_object_url(url);
+        }
+    }
+}
+
+/// Original files kept for uploaded documents, with a preview and download. Hidden
+/// where the browser has no origin private file system.
+#[component]
+pub fn OriginalFilesPanel(
+    /// Bumped when an upload kept a new original
+    #[prop(into)]
+    revision: Signal<u32>,
+) -> impl IntoView {
+    let files = RwSignal::new(Vec::<OriginalFile>::new());
+    let error = RwSignal::new(None::<String>);
+    let preview = RwSignal::new(None::<Preview>);
+
+    Effect::new(move |_| {
+        revision.track();
+        if !originals::is_supported() {
+            return;
+        }
+        spawn_local(async move {
+            match originals::list().await {
+                Ok(index) => files.set(index.files.into_iter().rev().collect()),
+                Err(e) => error.set(Some(e.to_string())),
+            }
+        });
+    });
+    let close_preview = move || {
+        if let Some(p) = preview.get_untracked() {
+            p.release();
+        }
+        preview.set(None);
+    };
+    on_cleanup(close_preview);
+
+    let show = move |original: OriginalFile| {
+        close_preview();
+        spawn_local(async move {
+            let opened = match originals::open(&original.name).await {
+                Ok(Some((_, file))) => file,
+                Ok(None) => {
+                    error.set(Some(format!("{} is no longer kept", original.name)));
+                    return;
+                }
+                Err(e) => {
+                    error.set(Some(e.to_string()));
+                    return;
+                }
+            };
+            let shown = if original.is_text() {
+                originals::preview_text(&opened)
+                    .await
+                    .map(|text| Preview::Text {
+                        name: original.name.clone(),
+                        text,
+                        cut: original.bytes > PREVIEW_BYTES,
+                    })
+            } else {
+                web_sys::Url::create_object_url_with_blob(&opened)
+                    .map(|url| Preview::Embedded {
+                        name: original.name.clone(),
+                        url,
+                    })
+                    .map_err(|e| AppError::storage(format!("Failed to open the file: {:?}", e)))
+            };
+            match shown {
+                Ok(p) => {
+                    error.set(None);
+                    preview.set(Some(p));
+                }
+                Err(e) => error.set(Some(e.to_string())),
+            }
+        });
+    };
+    let download = move |name: String| {
+        spawn_local(async move {
+            let outcome = match originals::open(&name).await {
+                Ok(Some((_, file))) => {
+                    DownloadUtils::download_blob(&name, &file).map_err(|e| e.to_string())
+                }
+                Ok(None) => Err(format!("{} is no longer kept", name)),
+                Err(e) => Err(e.to_string()),
+            };
+            error.set(outcome.err());
+        });
+    };
+    let remove = move |name: String| {
+        spawn_local(async move {
+            match originals::remove(&name).await {
+                Ok(()) => {
+                    if preview.with_untracked(|p| p.as_ref().is_some_and(|p| p.name() == name)) {
+                        close_preview();
+                    }
+                    files.update(|f| f.retain(|o| o.name != name));
+                }
+                Err(e) => error.set(Some(e.to_string())),
+            }
+        });
+    };
+
+    view! {
+        <Show when=originals::is_supported>
+            <div class="card bg-base-100 shadow-sm border border-base-300 rounded-xl">
+                <div class="card-body p-4 gap-3">
+                    <div class="flex items-center justify-between">
+                        <div>
+                            <h3 class="card-title text-lg">"Original files"</h3>
+                            <p class="text-xs text-base-content/60 mt-1">
+                                "Uploaded files as they were, kept in the browser's private file system."
+                            </p>
+                        </div>
+                        <span class="badge badge-ghost">
+                            {move || {
+                                let bytes: f64 = files.with(|f| f.iter().map(|o| o.bytes).sum());
+                                FormatUtils::format_file_size(bytes as u64)
+                            }}
+                        </span>
+                    </div>
+
+                    {move || error.get().map(|e| view! { <div class="alert alert-error text-sm">{e}</div> })}
+
+                    <Show
+                        when=move || !files.with(|f| f.is_empty())
+                        fallback=|| {
+                            view! {
+                                <p class="text-sm text-base-content/60">"No original files kept yet."</p>
+                            }
+                        }
+                    >
+                        <ul class="max-h-64 overflow-auto text-sm divide-y divide-base-200">
+                            {move || {
+                                files
+                                    .get()
+                                    .into_iter()
+                                    .map(|original| {
+                                        let name = original.name.clone();
+                                        let download_name = name.clone();
+                                        let remove_name = name.clone();
+                                        let previewable = original.is_text() || original.is_viewable();
+                                        let details = format!(
+                                            "{} · {}",
+                                            FormatUtils::format_file_size(original.bytes as u64),
+                                            FormatUtils::format_timestamp(original.stored_at)
+                                        );
+                                        view! {
+                                            <li class="flex items-center justify-between gap-2 py-1">
+                                                <div class="min-w-0">
+                                                    <div class="truncate" title=name.clone()>{name.clone()}</div>
+                                                    <div class="text-xs text-base-content/60">{details}</div>
+                                                </div>
+                                                <div class="flex gap-1 shrink-0">
+                                                    <Show when=move || previewable>
+                                                        <button
+                                                            class="btn btn-ghost btn-xs"
+                                                            title="Preview"
+                                                            on:click={
+                                                                let original = original.clone();
+                                                                move |_| show(original.clone())
+                                                            }
+                                                        >
+                                                            <i data-lucide="eye" class="h-3 w-3"></i>
+                                                        </button>
+                                                    </Show>
+                                                    <button
+                                                        class="btn btn-ghost btn-xs"
+                                                        title="Download"
+                                                        on:click=move |_| download(download_name.clone())
+                                                    >
+                                                        <i data-lucide="download" class="h-3 w-3"></i>
+                                                    </button>
+                                                    <button
+                                                        class="btn btn-ghost btn-xs"
+                                                        title="Remove the original; the indexed text stays"
+                                                        on:click=move |_| remove(remove_name.clone())
+                                                    >
+                                                        <i data-lucide="trash-2" class="h-3 w-3"></i>
+                                                    </button>
+                                                </div>
+                                            </li>
+                                        }
+                                    })
+                                    .collect_view()
+                            }}
+                        </ul>
+                    </Show>
+
+                    {move || {
+                        preview
+                            .get()
+                            .map(|p| {
+                                let title = p.name().to_string();
+                                let body = match p {
+                                    Preview::Text { text, cut, .. } => {
+                                        view! {
+                                            <pre class="max-h-96 overflow-auto whitespace-pre-wrap text-xs bg-base-200 rounded-lg p-3">
+                                                {text}
+                                                {cut.then_some("\n…")}
+                                            </pre>
+                                        }
+                                            .into_any()
+                                    }
+                                    Preview::Embedded { url, .. } => {
+                                        view! {
+                                            <iframe
+                                                class="w-full h-96 rounded-lg border border-base-300"
+                                                src=url
+                                                title=title.clone()
+                                            ></iframe>
+                                        }
+                                            .into_any()
+                                    }
+                                };
+                                view! {
+                                    <div class="space-y-2">
+                                        <div class="flex items-center justify-between">
+                                            <span class="font-medium text-sm truncate">{title}</span>
+                                            <button class="btn btn-ghost btn-xs" on:click=move |_| close_preview()>
+                                                "Close"
+                                            </button>
+                                        </div>
+                                        {body}
+                                    </div>
+                                }
+                            })
+                    }}
+                </div>
+            </div>
+        </Show>
+    }
+}
@@ -0,0 +1,63 @@
+use crate::utils::pwa;
+use leptos::prelude::*;
+
+/// Corner toasts of the installable app: a new release waiting to take over, the
+/// browser's offer to install, and working without a connection
+#[component]
+pub fn PwaToasts() -> impl IntoView {
+    let update_ready = RwSignal::new(false);
+    let installable = RwSignal::new(false);
+    let online = RwSignal::new(pwa::is_online());
+    let offline_dismissed = RwSignal::new(false);
+
+    pwa::register(move || update_ready.set(true));
+    pwa::watch_install(move |available| installable.set(available));
+    pwa::watch_connection(move |now_online| {
+        online.set(now_online);
+        offline_dismissed.set(false);
+    });
+
+    view! {
+        <div class="toast toast-end toast-bottom z-50">
+            <Show when=move || update_ready.get()>
+                <div class="alert alert-info shadow-lg rounded-lg">
+                    <i data-lucide="refresh-cw" class="w-5 h-5"></i>
+                    <span class="text-sm">"An update is available."</span>
+                    <button class="btn btn-sm btn-primary" on:click=move |_| pwa::apply_update()>
+                        "Reload"
+                    </button>
+                    <button class="btn btn-sm btn-ghost" on:click=move |_| update_ready.set(false)>
+                        "Later"
+                    </button>
+                </div>
+            </Show>
+            <Show when=move || installable.get()>
+                <div class="alert shadow-lg rounded-lg">
+                    <i data-lucide="download" class="w-5 h-5"></i>
+                    <span class="text-sm">"Install the chatbot to use it offline like an app."</span>
+                    <button
+                        class="btn btn-sm btn-primary"
+                        on:click=move |_| {
+                            pwa::install();
+                            installable.set(false);
+                        }
+                    >
+                        "Install"
+                    </button>
+                    <button class="btn btn-sm btn-ghost" on:click=move |_| installable.set(false)>
+                        <i data-lucide="x" class="w-4 h-4"></i>
+                    </button>
+                </div>
+            </Show>
+            <Show when=move || !online.get() && !offline_dismissed.get()>
+                <div class="alert alert-warning shadow-lg rounded-lg">
+                    <i data-lucide="wifi-off" class="w-5 h-5"></i>
+                    <span class="text-sm">"Offline. Cached models and your data still work."</span>
+                    <button class="btn btn-sm btn-ghost" on:click=move |_| offline_dismissed.set(true)>
+                        <i data-lucide="x" class="w-4 h-4"></i>
+                    </button>
+                </div>
+            </Show>
+        </div>
+    }
+}
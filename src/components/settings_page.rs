@@ -0,0 +1,180 @@
+use crate::utils::download::DownloadUtils;
+use crate::utils::settings_bundle::{self, ImportedSettings};
+use leptos::prelude::*;
+use leptos::task::spawn_local;
+use wasm_bindgen_futures::JsFuture;
+
+/// Every preference of the app exported to, or imported from, one JSON file
+#[component]
+pub fn SettingsPanel(
+    /// Called after an imported file was applied
+    #[prop(optional)]
+    on_applied: Option<Box<dyn Fn() + 'static>>,
+) -> impl IntoView {
+    let on_applied = StoredValue::new_local(on_applied);
+    let exported_sections = settings_bundle::collect().sections();
+    let pending_import = RwSignal::new(None::<ImportedSettings>);
+    let status = RwSignal::new(None::<Result<String, String>>);
+    let applied = RwSignal::new(false);
+
+    let export = move |_| {
+        let filename = format!("app-settings-{}.json", js_sys::Date::now() as u64);
+        let json = settings_bundle::collect().to_json();
+        match DownloadUtils::download_text(&filename, &json) {
+            Ok(()) => status.set(Some(Ok("Settings exported".to_string()))),
+            Err(e) => status.set(Some(Err(format!("Export failed: {}", e)))),
+        }
+    };
+    let load_import = move |ev: leptos::ev::Event| {
+        let target: web_sys::HtmlInputElement = event_target(&ev);
+        let Some(file) = target.files().and_then(|f| f.item(0)) else {
+            return;
+        };
+        target.set_value("");
+        spawn_local(async move {
+            let loaded = JsFuture::from(file.text())
+                .await
+                .map(|v| v.as_string().unwrap_or_default())
+                .map_err(|e| format!("{:?}", e))
+                .and_then(|json| settings_bundle::parse(&json));
+            match loaded {
+                Ok(imported) => {
+                    status.set(None);
+                    pending_import.set(Some(imported));
+                }
+                Err(e) => {
+                    pending_import.set(None);
+                    status.set(Some(Err(format!("Import refused: {}", e))));
+                }
+            }
+        });
+    };
+    let apply = move || {
+        let Some(imported) = pending_import.get_untracked() else {
+            return;
+        };
+        pending_import.set(None);
+        match settings_bundle::apply(imported) {
+            Ok(()) => {
+                status.set(Some(Ok("Settings imported".to_string())));
+                applied.set(true);
+                on_applied.with_value(|cb| {
+                    if let Some(cb) = cb {
+                        cb();
+                    }
+                });
+            }
+            Err(e) => status.set(Some(Err(format!("Some settings were not saved: {}", e)))),
+        }
+    };
+    let import_input = NodeRef::<leptos::html::Input>::new();
+
+    view! {
+        <div class="flex flex-col gap-4" id="app-settings">
+            <p class="text-sm text-base-content/70">
+                "Move your setup to another browser: export the settings here, then import the file there. Conversations and documents are not included."
+            </p>
+
+            <div class="text-sm">
+                <div class="font-medium mb-1">"Exported"</div>
+                <ul class="list-disc list-inside text-base-content/70">
+                    {exported_sections
+                        .into_iter()
+                        .map(|s| view! { <li>{s}</li> })
+                        .collect_view()}
+                </ul>
+                <p class="text-xs text-base-content/60 mt-1">
+                    "Keyboard shortcuts are fixed and need no transfer."
+                </p>
+            </div>
+
+            <div class="flex gap-2">
+                <button class="btn btn-primary btn-sm" on:click=export>
+                    <i data-lucide="download" class="w-4 h-4"></i>
+                    "Export"
+                </button>
+                <button
+                    class="btn btn-sm"
+                    on:click=move |_| {
+                        if let Some(input) = import_input.get() {
+                            input.click();
+                        }
+                    }
+                >
+                    <i data-lucide="upload" class="w-4 h-4"></i>
+                    "Import"
+                </button>
+                <input
+                    type="file"
+                    accept=".json,application/json"
+                    class="hidden"
+                    node_ref=import_input
+                    on:change=load_import
+                />
+            </div>
+
+            {move || {
+                status
+                    .get()
+                    .map(|s| {
+                        let (class, msg) = match s {
+                            Ok(msg) => ("alert alert-success text-sm", msg),
+                            Err(msg) => ("alert alert-error text-sm", msg),
+                        };
+                        view! { <div class=class>{msg}</div> }
+                    })
+            }}
+
+            // Preview of an imported file; nothing is saved before Apply
+            <Show when=move || pending_import.with(|p| p.is_some())>
+                <div class="p-3 rounded-xl border border-info bg-base-200 space-y-2 text-sm">
+                    <div class="font-medium">"Importing replaces these settings"</div>
+                    <ul class="list-disc list-inside">
+                        {move || {
+                            pending_import
+                                .with(|p| p.as_ref().map(|p| p.bundle.sections()).unwrap_or_default())
+                                .into_iter()
+                                .map(|s| view! { <li>{s}</li> })
+                                .collect_view()
+                        }}
+                    </ul>
+                    {move || {
+                        pending_import
+                            .with(|p| p.as_ref().map(|p| p.warnings.clone()).unwrap_or_default())
+                            .into_iter()
+                            .map(|w| view! { <p class="text-xs text-warning">{w}</p> })
+                            .collect_view()
+                    }}
+                    <div class="flex justify-end gap-2">
+                        <button class="btn btn-ghost btn-xs" on:click=move |_| pending_import.set(None)>
+                            "Cancel"
+                        </button>
+                        <button
+                            class="btn btn-primary btn-xs"
+                            on:click=move |_| apply()
+                        >
+                            "Apply"
+                        </button>
+                    </div>
+                </div>
+            </Show>
+
+            // The chat model and the panels that read settings once pick them up on reload
+            <Show when=move || applied.get()>
+                <div class="flex items-center justify-between gap-2 text-sm">
+                    <span class="text-base-content/70">"Reload to use the imported chat model everywhere."</span>
+                    <button
+                        class="btn btn-ghost btn-xs"
+                        on:click=move |_| {
+                            if let Some(win) = web_sys::window() {
+                                let _ = win.location().reload();
+                            }
+                        }
+                    >
+                        "Reload"
+                    </button>
+                </div>
+            </Show>
+        </div>
+    }
+}
@@ -1,10 +1,18 @@
 use crate::components::ui_primitives::Button;
 use crate::components::{
-    conversation_list::ConversationList, sidebar_action::SidebarAction, theme_toggle::ThemeToggle,
+    backup_panel::BackupPanel, conversation_list::ConversationList,
+    conversation_search::ConversationSearch, device_report::DeviceReport,
+    generation_settings::GenerationSettingsPanel, notification_settings::NotificationSettingsPanel,
+    settings_page::SettingsPanel, sidebar_action::SidebarAction,
+    startup_settings::StartupSettingsPanel, storage_health::StorageHealthPanel,
+    theme_toggle::ThemeToggle,
 };
 use crate::features::webllm::ui::WebLLMInitPanel;
-use crate::models::{webllm::ModelCapability, LLMModel};
-use crate::utils::storage::StorageUtils;
+use crate::models::{webllm::ModelCapability, ActivityCategory, LLMModel};
+use crate::router::{Modal, RouterContext};
+use crate::state::{ConversationStateContext, EventBusContext};
+use crate::storage::{CachePolicy, TieredCache};
+use crate::utils::titling;
 use leptos::prelude::*;
 
 #[component]
@@ -18,20 +26,63 @@ pub fn Sidebar(
     conversation_list_refresh: ReadSignal<u32>,
     _set_conversation_list_refresh: WriteSignal<u32>,
     set_show_document_manager: WriteSignal<bool>,
+    set_show_tasks: WriteSignal<bool>,
+    set_show_activity: WriteSignal<bool>,
+    set_show_graph: WriteSignal<bool>,
+    set_show_crm: WriteSignal<bool>,
 ) -> impl IntoView {
     // Global prompt modal state
     let (show_edit_global_prompt, set_show_edit_global_prompt) = signal(false);
+    // Generation settings modal state
+    let (show_generation_settings, set_show_generation_settings) = signal(false);
+    // Notification settings modal state
+    let (show_notification_settings, set_show_notification_settings) = signal(false);
+    // Startup settings modal state
+    let (show_startup_settings, set_show_startup_settings) = signal(false);
+    // Storage health modal state
+    let (show_storage_health, set_show_storage_health) = signal(false);
+    // Settings export/import modal state
+    let (show_app_settings, set_show_app_settings) = signal(false);
     let (global_prompt_input, set_global_prompt_input) = signal(String::new());
 
-    // Open global prompt editor
-    let open_global_prompt = move || {
-        if let Ok(Some(p)) = StorageUtils::retrieve_local::<String>("global_system_prompt") {
+    // Load the global prompt whenever its editor opens, including from a link
+    Effect::new(move |_| {
+        if show_edit_global_prompt.get() {
+            let p = TieredCache::get::<String>("global_system_prompt").unwrap_or_default();
             set_global_prompt_input.set(p);
-        } else {
-            set_global_prompt_input.set(String::new());
         }
-        set_show_edit_global_prompt.set(true);
-    };
+    });
+    let open_global_prompt = move || set_show_edit_global_prompt.set(true);
+
+    // Sidebar modals can be opened from the URL
+    if let Some(router) = use_context::<RouterContext>() {
+        router.bind_modal(
+            Modal::SystemPrompt,
+            show_edit_global_prompt,
+            set_show_edit_global_prompt,
+        );
+        router.bind_modal(
+            Modal::GenerationSettings,
+            show_generation_settings,
+            set_show_generation_settings,
+        );
+        router.bind_modal(
+            Modal::NotificationSettings,
+            show_notification_settings,
+            set_show_notification_settings,
+        );
+        router.bind_modal(
+            Modal::StartupSettings,
+            show_startup_settings,
+            set_show_startup_settings,
+        );
+        router.bind_modal(
+            Modal::StorageHealth,
+            show_storage_health,
+            set_show_storage_health,
+        );
+        router.bind_modal(Modal::Settings, show_app_settings, set_show_app_settings);
+    }
     let _llms = vec![
         // Llama 3.2 Models
         LLMModel::new(
@@ -219,13 +270,17 @@ pub fn Sidebar(
     ];
 
     // New chat handler
+    let events = use_context::<EventBusContext>();
     let create_new_chat = move |_| {
         if let Some(ref storage) = storage.get() {
-            match storage.create_conversation("New Chat".to_string()) {
+            match storage.create_conversation(titling::DEFAULT_TITLE.to_string()) {
                 Ok(conversation_id) => {
                     set_current_conversation_id.set(Some(conversation_id));
                     // Don't refresh conversation list yet - wait for first user message
                     log::info!("Created new conversation (not yet in history)");
+                    if let Some(bus) = events {
+                        bus.record(ActivityCategory::Conversation, "Conversation created");
+                    }
                 }
                 Err(e) => {
                     log::error!("Failed to create conversation: {:?}", e);
@@ -241,6 +296,33 @@ pub fn Sidebar(
         log::info!("Selected conversation");
     };
 
+    // Recency of opened conversations; Ctrl+` cycles through them (Shift goes back)
+    // and releasing Ctrl keeps the one landed on, like Ctrl+Tab between tabs
+    if let Some(conversations) = use_context::<ConversationStateContext>() {
+        Effect::new(move |_| {
+            if let Some(id) = current_conversation_id.get() {
+                conversations.touch_recent(&id);
+            }
+        });
+        let keydown = window_event_listener(leptos::ev::keydown, move |ev| {
+            if ev.ctrl_key() && ev.code() == "Backquote" {
+                ev.prevent_default();
+                if let Some(id) = conversations.cycle_recent(!ev.shift_key()) {
+                    set_current_conversation_id.set(Some(id));
+                }
+            }
+        });
+        let keyup = window_event_listener(leptos::ev::keyup, move |ev| {
+            if ev.key() == "Control" {
+                conversations.end_recent_cycle();
+            }
+        });
+        on_cleanup(move || {
+            keydown.remove();
+            keyup.remove();
+        });
+    }
+
     view! {
         <div class=move || {
             let width = if collapsed.get() { "w-16" } else { "w-80" };
@@ -302,12 +384,66 @@ pub fn Sidebar(
                     collapsed=collapsed
                     on_click=Box::new(open_global_prompt)
                 />
+                <SidebarAction
+                    icon="sliders-horizontal"
+                    label="Generation"
+                    collapsed=collapsed
+                    on_click=Box::new(move || set_show_generation_settings.set(true))
+                />
+                <SidebarAction
+                    icon="bell"
+                    label="Notifications"
+                    collapsed=collapsed
+                    on_click=Box::new(move || set_show_notification_settings.set(true))
+                />
+                <SidebarAction
+                    icon="power"
+                    label="Startup"
+                    collapsed=collapsed
+                    on_click=Box::new(move || set_show_startup_settings.set(true))
+                />
+                <SidebarAction
+                    icon="shield-check"
+                    label="Storage"
+                    collapsed=collapsed
+                    on_click=Box::new(move || set_show_storage_health.set(true))
+                />
+                <SidebarAction
+                    icon="settings-2"
+                    label="Settings"
+                    collapsed=collapsed
+                    on_click=Box::new(move || set_show_app_settings.set(true))
+                />
                 <SidebarAction
                     icon="file-text"
                     label="Load Markdown"
                     collapsed=collapsed
                     on_click=Box::new(move || set_show_document_manager.set(true))
                 />
+                <SidebarAction
+                    icon="list-todo"
+                    label="Tasks"
+                    collapsed=collapsed
+                    on_click=Box::new(move || set_show_tasks.set(true))
+                />
+                <SidebarAction
+                    icon="history"
+                    label="Activity"
+                    collapsed=collapsed
+                    on_click=Box::new(move || set_show_activity.set(true))
+                />
+                <SidebarAction
+                    icon="network"
+                    label="Knowledge Graph"
+                    collapsed=collapsed
+                    on_click=Box::new(move || set_show_graph.set(true))
+                />
+                <SidebarAction
+                    icon="briefcase"
+                    label="CRM"
+                    collapsed=collapsed
+                    on_click=Box::new(move || set_show_crm.set(true))
+                />
 
                 <Button
                     label=Signal::derive(move || {
@@ -324,6 +460,10 @@ pub fn Sidebar(
             <Show when=move || !collapsed.get()>
                 <div class="border-t border-base-300"></div>
                 <div class="flex-1 overflow-y-auto">
+                    <ConversationSearch
+                        storage=storage
+                        on_conversation_select=on_conversation_select
+                    />
                     <ConversationList
                         storage=storage
                         on_conversation_select=on_conversation_select
@@ -366,7 +506,7 @@ pub fn Sidebar(
                                             let set_show = set_show_edit_global_prompt;
                                             move || {
                                                 let text = global_prompt_input.get();
-                                                let _ = StorageUtils::store_local("global_system_prompt", &text);
+                                                let _ = TieredCache::set("global_system_prompt", &text, CachePolicy::PREFERENCE);
                                                 set_status_message.set("Global prompt saved".to_string());
                                                 set_show.set(false);
                                             }
@@ -379,6 +519,113 @@ pub fn Sidebar(
                 </div>
             </Show>
 
+            // Generation settings modal
+            <Show when=move || show_generation_settings.get()>
+                <div class="fixed inset-0 bg-black/50 flex items-center justify-center z-50">
+                    <div class="bg-base-100 rounded-lg p-6 max-w-2xl w-full mx-4 shadow-xl max-h-[90vh] overflow-y-auto">
+                        <div class="flex justify-between items-center mb-4">
+                            <h3 class="text-lg font-semibold">"Generation Settings"</h3>
+                            <button
+                                class="btn btn-ghost btn-sm btn-circle"
+                                on:click=move |_| set_show_generation_settings.set(false)
+                            >
+                                "✕"
+                            </button>
+                        </div>
+                        <GenerationSettingsPanel on_saved=Box::new(move || {
+                            set_status_message.set("Generation settings saved".to_string());
+                            set_show_generation_settings.set(false);
+                        }) />
+                    </div>
+                </div>
+            </Show>
+
+            // Notification settings modal
+            <Show when=move || show_notification_settings.get()>
+                <div class="fixed inset-0 bg-black/50 flex items-center justify-center z-50">
+                    <div class="bg-base-100 rounded-lg p-6 max-w-lg w-full mx-4 shadow-xl">
+                        <div class="flex justify-between items-center mb-4">
+                            <h3 class="text-lg font-semibold">"Notifications"</h3>
+                            <button
+                                class="btn btn-ghost btn-sm btn-circle"
+                                on:click=move |_| set_show_notification_settings.set(false)
+                            >
+                                "✕"
+                            </button>
+                        </div>
+                        <NotificationSettingsPanel on_saved=Box::new(move || {
+                            set_status_message.set("Notification settings saved".to_string());
+                            set_show_notification_settings.set(false);
+                        }) />
+                    </div>
+                </div>
+            </Show>
+
+            // Startup settings modal
+            <Show when=move || show_startup_settings.get()>
+                <div class="fixed inset-0 bg-black/50 flex items-center justify-center z-50">
+                    <div class="bg-base-100 rounded-lg p-6 max-w-lg w-full mx-4 shadow-xl">
+                        <div class="flex justify-between items-center mb-4">
+                            <h3 class="text-lg font-semibold">"Startup"</h3>
+                            <button
+                                class="btn btn-ghost btn-sm btn-circle"
+                                on:click=move |_| set_show_startup_settings.set(false)
+                            >
+                                "✕"
+                            </button>
+                        </div>
+                        <StartupSettingsPanel on_saved=Box::new(move || {
+                            set_status_message.set("Startup settings saved".to_string());
+                            set_show_startup_settings.set(false);
+                        }) />
+                    </div>
+                </div>
+            </Show>
+
+            // Storage health modal
+            <Show when=move || show_storage_health.get()>
+                <div class="fixed inset-0 bg-black/50 flex items-center justify-center z-50">
+                    <div class="bg-base-100 rounded-lg p-6 max-w-lg w-full mx-4 shadow-xl max-h-[85vh] overflow-auto">
+                        <div class="flex justify-between items-center mb-4">
+                            <h3 class="text-lg font-semibold">"Storage"</h3>
+                            <button
+                                class="btn btn-ghost btn-sm btn-circle"
+                                on:click=move |_| set_show_storage_health.set(false)
+                            >
+                                "✕"
+                            </button>
+                        </div>
+                        <StorageHealthPanel />
+                        <div class="divider my-2"></div>
+                        <StorageEncryptionPanel />
+                        <div class="divider my-2"></div>
+                        <BackupPanel />
+                    </div>
+                </div>
+            </Show>
+
+            // Settings export/import modal
+            <Show when=move || show_app_settings.get()>
+                <div class="fixed inset-0 bg-black/50 flex items-center justify-center z-50">
+                    <div class="bg-base-100 rounded-lg p-6 max-w-lg w-full mx-4 shadow-xl max-h-[85vh] overflow-auto">
+                        <div class="flex justify-between items-center mb-4">
+                            <h3 class="text-lg font-semibold">"Settings"</h3>
+                            <button
+                                class="btn btn-ghost btn-sm btn-circle"
+                                on:click=move |_| set_show_app_settings.set(false)
+                            >
+                                "✕"
+                            </button>
+                        </div>
+                        <SettingsPanel on_applied=Box::new(move || {
+                            set_status_message.set("Settings imported".to_string());
+                        }) />
+                        <div class="divider my-2"></div>
+                        <DeviceReport />
+                    </div>
+                </div>
+            </Show>
+
         </div>
     }
 }
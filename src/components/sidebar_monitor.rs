@@ -1,9 +1,20 @@
 use crate::components::graphrag_settings::GraphRAGSettings;
 use crate::components::ui_primitives::Button;
+use crate::features::graphrag::evaluation::{self, EvalReport, GoldenCase};
 use crate::graphrag_config::{
     GraphRAGConfig, GraphRAGConfigManager, GraphRAGMetrics, PerformanceMetrics,
 };
+use crate::metrics::PrometheusExporter;
+use crate::storage::{CachePolicy, ConversationStorage, TieredCache};
+use crate::utils::compute_usage::{
+    build_usage_report, format_energy, ComputeUsageReport, DEFAULT_DEVICE_WATTS, DEVICE_WATTS_KEY,
+};
+use crate::utils::download::DownloadUtils;
 use leptos::prelude::*;
+use wasm_bindgen_futures::JsFuture;
+
+// Runs kept for comparison in the evaluation card
+const MAX_EVAL_RUNS: usize = 6;
 
 #[component]
 pub fn SidebarMonitorRight(
@@ -20,6 +31,33 @@ pub fn SidebarMonitorRight(
     // Pre-clone manager for closures
     let mgr_for_perf = graphrag_manager.clone();
 
+    // Prometheus export of the recorded query history
+    // Offered only when the embedding page's origin is known
+    let embedded =
+        PrometheusExporter::is_embedded() && PrometheusExporter::parent_origin().is_some();
+    let prometheus_text = {
+        let mgr = graphrag_manager.clone();
+        move || {
+            PrometheusExporter::render(
+                &mgr.get_metrics_untracked(),
+                &mgr.get_metrics_history_untracked(),
+            )
+        }
+    };
+    let download_prometheus = {
+        let text = prometheus_text.clone();
+        move |_| {
+            if let Err(e) = PrometheusExporter::download(&text()) {
+                log::error!("Metrics export failed: {}", e);
+            }
+        }
+    };
+    let post_prometheus = move |_| {
+        if let Err(e) = PrometheusExporter::post_to_parent(&prometheus_text()) {
+            log::error!("Posting metrics to parent frame failed: {}", e);
+        }
+    };
+
     // Derived widths and classes
     let panel_class = Signal::derive(move || {
         if collapsed.get() {
@@ -107,7 +145,25 @@ pub fn SidebarMonitorRight(
                         <div class="card-body p-3">
                             <div class="flex items-center justify-between">
                                 <span class="text-xs font-semibold">"Performance Breakdown"</span>
-                                <i data-lucide="gauge" class="w-3.5 h-3.5 opacity-70"></i>
+                                <div class="flex items-center gap-1">
+                                    <Show when=move || embedded>
+                                        <button
+                                            class="btn btn-ghost btn-xs btn-square"
+                                            title="Send Prometheus metrics to the parent frame"
+                                            on:click=post_prometheus.clone()
+                                        >
+                                            <i data-lucide="send" class="w-3.5 h-3.5"></i>
+                                        </button>
+                                    </Show>
+                                    <button
+                                        class="btn btn-ghost btn-xs btn-square"
+                                        title="Download metrics (Prometheus format)"
+                                        on:click=download_prometheus
+                                    >
+                                        <i data-lucide="download" class="w-3.5 h-3.5"></i>
+                                    </button>
+                                    <i data-lucide="gauge" class="w-3.5 h-3.5 opacity-70"></i>
+                                </div>
                             </div>
                             {move || {
                                 let p: PerformanceMetrics = mgr_for_perf.get_performance_metrics();
@@ -153,6 +209,12 @@ pub fn SidebarMonitorRight(
                         </div>
                     </div>
 
+                    // Compute usage estimates from recorded generation stats
+                    <ComputeUsageCard collapsed=collapsed />
+
+                    // Golden-set evaluation of retrieval under the current settings
+                    <RetrievalEvalCard graphrag_config=graphrag_config />
+
                     // GraphRAG Settings (moved from left sidebar modal)
                     <div class="card bg-base-100 shadow-sm">
                         <div class="card-body p-3">
@@ -172,3 +234,292 @@ pub fn SidebarMonitorRight(
         </div>
     }
 }
+
+// Local calendar day of a timestamp, used to bucket usage per day
+fn local_day_key(ts: f64) -> String {
+    let d = js_sys::Date::new(&wasm_bindgen::JsValue::from_f64(ts));
+    format!(
+        "{:04}-{:02}-{:02}",
+        d.get_full_year(),
+        d.get_month() + 1,
+        d.get_date()
+    )
+}
+
+fn load_usage_report(watts: f32) -> ComputeUsageReport {
+    let conversations = ConversationStorage::new()
+        .and_then(|s| s.load_all_conversations())
+        .unwrap_or_default();
+    build_usage_report(&conversations, watts, local_day_key)
+}
+
+#[component]
+fn ComputeUsageCard(collapsed: ReadSignal<bool>) -> impl IntoView {
+    let watts = RwSignal::new(
+        TieredCache::get::<f32>(DEVICE_WATTS_KEY)
+            .filter(|w| *w > 0.0)
+            .unwrap_or(DEFAULT_DEVICE_WATTS),
+    );
+    let report = RwSignal::new(ComputeUsageReport::default());
+    let refresh = move || report.set(load_usage_report(watts.get_untracked()));
+
+    // Recompute whenever the panel is opened or the power assumption changes
+    Effect::new(move |_| {
+        if !collapsed.get() {
+            report.set(load_usage_report(watts.get()));
+        }
+    });
+
+    view! {
+        <div id="compute-usage" class="card bg-base-100 shadow-sm">
+            <div class="card-body p-3">
+                <div class="flex items-center justify-between">
+                    <span class="text-xs font-semibold">"Compute Usage"</span>
+                    <button class="btn btn-ghost btn-xs btn-square" title="Refresh" on:click=move |_| refresh()>
+                        <i data-lucide="refresh-cw" class="w-3.5 h-3.5 opacity-70"></i>
+                    </button>
+                </div>
+                {move || {
+                    let r = report.get();
+                    let o = r.overall.clone();
+                    view! {
+                        <div class="mt-2 grid grid-cols-2 gap-2 text-xs">
+                            <div class="flex items-center justify-between"><span class="opacity-70">"Replies"</span><span class="font-mono">{o.messages}</span></div>
+                            <div class="flex items-center justify-between"><span class="opacity-70">"Tokens"</span><span class="font-mono">{o.total_tokens()}</span></div>
+                            <div class="flex items-center justify-between" title="Time to first token plus generation; replies without a latency breakdown count their whole processing time"><span class="opacity-70">"Generation time"</span><span class="font-mono">{format!("{:.1}s", o.compute_ms / 1000.0)}</span></div>
+                            <div class="flex items-center justify-between"><span class="opacity-70">"Energy"</span><span class="font-mono">{format_energy(o.energy_wh)}</span></div>
+                            <div class="col-span-2 flex items-center justify-between"><span class="opacity-70">"Avg speed"</span><span class="font-mono">{o.avg_tokens_per_sec().map(|t| format!("{:.1} tok/s", t)).unwrap_or_else(|| "–".to_string())}</span></div>
+                        </div>
+                        <div class="mt-2 text-xs">
+                            <div class="opacity-70 mb-1">"Per day"</div>
+                            {r.per_day.iter().rev().take(7).map(|(day, t)| view! {
+                                <div class="flex items-center justify-between">
+                                    <span class="font-mono">{day.clone()}</span>
+                                    <span class="font-mono">{format!("{} · {:.1}s · {}", t.total_tokens(), t.compute_ms / 1000.0, format_energy(t.energy_wh))}</span>
+                                </div>
+                            }).collect_view()}
+                        </div>
+                        <div class="mt-2 text-xs">
+                            <div class="opacity-70 mb-1">"Per conversation"</div>
+                            {r.per_conversation.iter().take(5).map(|c| view! {
+                                <div class="flex items-center justify-between gap-2">
+                                    <span class="truncate" title=c.title.clone()>{c.title.clone()}</span>
+                                    <span class="font-mono shrink-0">{format!("{:.1}s · {}", c.totals.compute_ms / 1000.0, format_energy(c.totals.energy_wh))}</span>
+                                </div>
+                            }).collect_view()}
+                        </div>
+                    }
+                }}
+                <label class="mt-2 flex items-center justify-between gap-2 text-xs">
+                    <span class="opacity-70">"Assumed GPU power (W)"</span>
+                    <input
+                        type="number"
+                        min="1"
+                        step="1"
+                        class="input input-bordered input-xs w-20"
+                        prop:value=move || watts.get().to_string()
+                        on:change=move |ev| {
+                            if let Ok(w) = event_target_value(&ev).parse::<f32>() {
+                                if w > 0.0 {
+                                    watts.set(w);
+                                    let _ = TieredCache::set(DEVICE_WATTS_KEY, &w, CachePolicy::PREFERENCE);
+                                }
+                            }
+                        }
+                    />
+                </label>
+                <p class="text-[10px] opacity-60 mt-1">"Estimates: generation time × assumed power draw."</p>
+            </div>
+        </div>
+    }
+}
+
+/// Loads a golden question set, runs it through the retriever with the current
+/// settings and lists recent runs so configurations can be compared
+#[component]
+fn RetrievalEvalCard(graphrag_config: Signal<GraphRAGConfig>) -> impl IntoView {
+    let cases = RwSignal::new(Vec::<GoldenCase>::new());
+    let set_name = RwSignal::new(None::<String>);
+    let status = RwSignal::new(None::<String>);
+    let k = RwSignal::new(5usize);
+    // Questions done and total while a run is going
+    let progress = RwSignal::new(None::<(usize, usize)>);
+    // Latest run first
+    let runs = RwSignal::new(Vec::<EvalReport>::new());
+    let set_input = NodeRef::<leptos::html::Input>::new();
+
+    let load_set = move |ev: leptos::ev::Event| {
+        let target: web_sys::HtmlInputElement = event_target(&ev);
+        let Some(file) = target.files().and_then(|f| f.item(0)) else {
+            return;
+        };
+        target.set_value("");
+        let name = file.name();
+        wasm_bindgen_futures::spawn_local(async move {
+            let loaded = JsFuture::from(file.text())
+                .await
+                .map(|v| v.as_string().unwrap_or_default())
+                .map_err(|e| format!("{:?}", e))
+                .and_then(|json| evaluation::parse_golden_set(&json));
+            match loaded {
+                Ok(set) => {
+                    status.set(Some(format!("{} questions loaded", set.len())));
+                    cases.set(set);
+                    set_name.set(Some(name));
+                }
+                Err(e) => status.set(Some(format!("Load failed: {}", e))),
+            }
+        });
+    };
+
+    let run = move |_| {
+        let set = cases.get_untracked();
+        if set.is_empty() || progress.get_untracked().is_some() {
+            return;
+        }
+        let config = graphrag_config.get_untracked();
+        let k = k.get_untracked();
+        let total = set.len();
+        progress.set(Some((0, total)));
+        status.set(None);
+        wasm_bindgen_futures::spawn_local(async move {
+            let report =
+                evaluation::run(&set, &config, k, |done| progress.set(Some((done, total)))).await;
+            runs.update(|r| {
+                r.insert(0, report);
+                r.truncate(MAX_EVAL_RUNS);
+            });
+            progress.set(None);
+        });
+    };
+
+    let download = move |_| {
+        let json = runs.with_untracked(|r| serde_json::to_string_pretty(r).unwrap_or_default());
+        let filename = format!("retrieval-eval-{}.json", js_sys::Date::now() as u64);
+        if let Err(e) = DownloadUtils::download_text(&filename, &json) {
+            status.set(Some(format!("Download failed: {:?}", e)));
+        }
+    };
+
+    view! {
+        <div class="card bg-base-100 shadow-sm">
+            <div class="card-body p-3">
+                <div class="flex items-center justify-between">
+                    <span class="text-xs font-semibold">"Retrieval Evaluation"</span>
+                    <div class="flex items-center gap-1">
+                        <button
+                            class="btn btn-ghost btn-xs btn-square"
+                            title="Download runs (JSON)"
+                            disabled=move || runs.with(|r| r.is_empty())
+                            on:click=download
+                        >
+                            <i data-lucide="download" class="w-3.5 h-3.5"></i>
+                        </button>
+                        <i data-lucide="flask-conical" class="w-3.5 h-3.5 opacity-70"></i>
+                    </div>
+                </div>
+                <div class="mt-2 flex items-center gap-2 text-xs">
+                    <button
+                        class="btn btn-xs"
+                        on:click=move |_| {
+                            if let Some(input) = set_input.get() {
+                                input.click();
+                            }
+                        }
+                    >
+                        "Load set"
+                    </button>
+                    <span class="truncate opacity-70" title=move || set_name.get().unwrap_or_default()>
+                        {move || set_name.get().unwrap_or_else(|| "No golden set".to_string())}
+                    </span>
+                    <input
+                        node_ref=set_input
+                        type="file"
+                        accept=".json,application/json"
+                        style="display:none"
+                        on:change=load_set
+                    />
+                </div>
+                <div class="mt-2 flex items-center justify-between gap-2 text-xs">
+                    <label class="flex items-center gap-1">
+                        <span class="opacity-70">"k"</span>
+                        <input
+                            type="number"
+                            min="1"
+                            max="20"
+                            class="input input-bordered input-xs w-14"
+                            prop:value=move || k.get().to_string()
+                            on:change=move |ev| {
+                                if let Ok(n) = event_target_value(&ev).parse::<usize>() {
+                                    k.set(n.clamp(1, 20));
+                                }
+                            }
+                        />
+                    </label>
+                    <button
+                        class="btn btn-primary btn-xs"
+                        disabled=move || cases.with(|c| c.is_empty()) || progress.get().is_some()
+                        on:click=run
+                    >
+                        {move || match progress.get() {
+                            Some((done, total)) => format!("Running {}/{}", done, total),
+                            None => "Run".to_string(),
+                        }}
+                    </button>
+                </div>
+                {move || status.get().map(|s| view! { <p class="mt-1 text-[10px] opacity-70">{s}</p> })}
+                <Show when=move || runs.with(|r| !r.is_empty())>
+                    <table class="table table-xs mt-2">
+                        <thead>
+                            <tr>
+                                <th>"Run"</th>
+                                <th title="Share of expected sources in the top k">{move || format!("R@{}", k.get())}</th>
+                                <th title="Mean reciprocal rank of the first expected source">"MRR"</th>
+                                <th title="Median and 95th percentile latency">"p50/p95"</th>
+                            </tr>
+                        </thead>
+                        <tbody>
+                            {move || {
+                                runs.get()
+                                    .into_iter()
+                                    .map(|r| view! {
+                                        <tr>
+                                            <td class="max-w-28 truncate" title=r.label.clone()>{r.label.clone()}</td>
+                                            <td class="font-mono">{format!("{:.2}", r.recall_at_k)}</td>
+                                            <td class="font-mono">{format!("{:.2}", r.mrr)}</td>
+                                            <td class="font-mono">{format!("{:.0}/{:.0}ms", r.latency.p50_ms, r.latency.p95_ms)}</td>
+                                        </tr>
+                                    })
+                                    .collect_view()
+                            }}
+                        </tbody>
+                    </table>
+                    {move || {
+                        runs.with(|r| r.first().cloned()).map(|latest| {
+                            let missed: Vec<String> = latest
+                                .cases
+                                .iter()
+                                .filter(|c| c.first_hit.is_none())
+                                .map(|c| c.question.clone())
+                                .collect();
+                            (!missed.is_empty()).then(|| view! {
+                                <details class="mt-1 text-xs">
+                                    <summary class="cursor-pointer opacity-70">
+                                        {format!("{} missed in the latest run", missed.len())}
+                                    </summary>
+                                    <ul class="mt-1 space-y-0.5">
+                                        {missed.into_iter().map(|q| view! { <li class="truncate" title=q.clone()>{q}</li> }).collect_view()}
+                                    </ul>
+                                </details>
+                            })
+                        })
+                    }}
+                </Show>
+                <details class="mt-2 text-[10px]">
+                    <summary class="cursor-pointer opacity-60">"Golden set format"</summary>
+                    <pre class="mt-1 p-2 rounded bg-base-200 whitespace-pre-wrap break-all">{evaluation::EXAMPLE_SET}</pre>
+                </details>
+            </div>
+        </div>
+    }
+}
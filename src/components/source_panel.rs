@@ -0,0 +1,114 @@
+use crate::features::graphrag::index_generation::IndexSnapshot;
+use crate::models::graphrag::DocumentIndex;
+use crate::models::SourceAttribution;
+use crate::utils::web_page::is_web_source;
+use leptos::prelude::*;
+
+/// A cited passage as it is in the active index, with its siblings from the same file
+struct CitedPassage {
+    document: DocumentIndex,
+    parent: String,
+    /// Passages indexed from the parent file, this one included
+    parts: usize,
+}
+
+fn cited_passage(source_id: &str) -> Option<CitedPassage> {
+    let snapshot = IndexSnapshot::for_query(None).ok()?;
+    let document = snapshot
+        .documents
+        .iter()
+        .find(|d| d.id == source_id)?
+        .clone();
+    let parent = document.source_file().to_string();
+    let parts = snapshot
+        .documents
+        .iter()
+        .filter(|d| d.source_file() == parent)
+        .count();
+    Some(CitedPassage {
+        document,
+        parent,
+        parts,
+    })
+}
+
+/// Side panel opened from a citation chip: the full passage, the document it came from
+/// and how relevant retrieval found it
+#[component]
+pub fn SourcePanel(source: SourceAttribution, on_close: Callback<()>) -> impl IntoView {
+    let passage = cited_passage(&source.source_id);
+    let pct = (source.confidence * 100.0).round() as i32;
+
+    view! {
+        <div class="fixed inset-0 z-50 flex justify-end">
+            <div class="absolute inset-0 bg-black/40" on:click=move |_| on_close.run(())></div>
+            <aside
+                class="relative h-full w-[32rem] max-w-[95vw] bg-base-100 shadow-xl border-l border-base-300 flex flex-col"
+                aria-label="Cited source"
+            >
+                <div class="flex items-center justify-between gap-2 px-4 py-3 border-b border-base-300">
+                    <h3 class="font-semibold text-base truncate" title=source.title.clone()>
+                        {source.title.clone()}
+                    </h3>
+                    <button class="btn btn-ghost btn-sm" on:click=move |_| on_close.run(())>
+                        Close
+                    </button>
+                </div>
+                <div class="flex flex-wrap items-center gap-2 px-4 py-2 border-b border-base-300 text-xs">
+                    <span class="badge badge-primary badge-sm" title="Relevance found at retrieval time">
+                        {format!("{}% relevant", pct)}
+                    </span>
+                    {passage.as_ref().map(|p| {
+                        let parts = p.parts;
+                        view! {
+                            {if is_web_source(&p.parent) {
+                                view! {
+                                    <a
+                                        class="link flex items-center gap-1 opacity-80 truncate"
+                                        href=p.parent.clone()
+                                        target="_blank"
+                                        rel="noopener noreferrer"
+                                        title="Web page the passage was indexed from"
+                                    >
+                                        <i data-lucide="globe" class="h-3.5 w-3.5"></i>
+                                        {p.parent.clone()}
+                                    </a>
+                                }
+                                .into_any()
+                            } else {
+                                view! {
+                                    <span class="flex items-center gap-1 opacity-80" title="Document the passage was indexed from">
+                                        <i data-lucide="file-text" class="h-3.5 w-3.5"></i>
+                                        {p.parent.clone()}
+                                    </span>
+                                }
+                                .into_any()
+                            }}
+                            {(parts > 1).then(|| view! {
+                                <span class="opacity-60">{format!("one of {} passages", parts)}</span>
+                            })}
+                        }
+                    })}
+                </div>
+                <div class="flex-1 overflow-auto p-4">
+                    {match passage {
+                        Some(p) if !p.document.content.trim().is_empty() => view! {
+                            <p class="text-sm leading-relaxed whitespace-pre-wrap">{p.document.content}</p>
+                        }
+                        .into_any(),
+                        Some(_) => view! {
+                            <p class="text-sm opacity-70">"This passage has no readable text."</p>
+                        }
+                        .into_any(),
+                        None => view! {
+                            <p class="text-sm opacity-70">
+                                "This source is no longer in the knowledge base. It may have been removed or re-indexed since the reply was written."
+                            </p>
+                        }
+                        .into_any(),
+                    }}
+                </div>
+            </aside>
+        </div>
+    }
+}
@@ -0,0 +1,86 @@
+use crate::models::{AppConfig, LandingView};
+use leptos::prelude::*;
+
+/// What the app opens to and which tasks run on startup; applied on the next launch
+#[component]
+pub fn StartupSettingsPanel(
+    /// Called after the settings were persisted
+    #[prop(optional)]
+    on_saved: Option<Box<dyn Fn() + 'static>>,
+) -> impl IntoView {
+    let initial = AppConfig::load().unwrap_or_default().startup;
+    let landing = RwSignal::new(initial.landing);
+    let auto_init_model = RwSignal::new(initial.auto_init_model);
+    let warm_load_index = RwSignal::new(initial.warm_load_index);
+    let error = RwSignal::new(None::<String>);
+
+    let save = move || {
+        // Read back so settings saved elsewhere in the config are kept
+        let mut config = AppConfig::load().unwrap_or_default();
+        config.startup.landing = landing.get_untracked();
+        config.startup.auto_init_model = auto_init_model.get_untracked();
+        config.startup.warm_load_index = warm_load_index.get_untracked();
+        match config.save() {
+            Ok(()) => {
+                error.set(None);
+                if let Some(cb) = on_saved.as_ref() {
+                    cb();
+                }
+            }
+            Err(e) => error.set(Some(e.to_string())),
+        }
+    };
+
+    view! {
+        <div class="flex flex-col gap-4" id="startup-settings">
+            <p class="text-sm text-base-content/70">
+                "Applied the next time the app opens. A link to a conversation or panel always opens that instead."
+            </p>
+
+            <label class="form-control w-full">
+                <span class="label-text text-sm font-medium mb-1">"Open to"</span>
+                <select
+                    class="select select-bordered select-sm w-full"
+                    prop:value=move || landing.get().id()
+                    on:change=move |ev| {
+                        if let Some(view) = LandingView::from_id(&event_target_value(&ev)) {
+                            landing.set(view);
+                        }
+                    }
+                >
+                    {LandingView::ALL
+                        .into_iter()
+                        .map(|view| view! { <option value=view.id()>{view.label()}</option> })
+                        .collect_view()}
+                </select>
+            </label>
+
+            <div class="flex flex-col gap-2">
+                <label class="label cursor-pointer justify-start gap-2">
+                    <input
+                        type="checkbox"
+                        class="checkbox checkbox-sm"
+                        prop:checked=move || auto_init_model.get()
+                        on:change=move |ev| auto_init_model.set(event_target_checked(&ev))
+                    />
+                    <span class="label-text">"Load the chat model on startup"</span>
+                </label>
+                <label class="label cursor-pointer justify-start gap-2">
+                    <input
+                        type="checkbox"
+                        class="checkbox checkbox-sm"
+                        prop:checked=move || warm_load_index.get()
+                        on:change=move |ev| warm_load_index.set(event_target_checked(&ev))
+                    />
+                    <span class="label-text">"Check the knowledge index on startup"</span>
+                </label>
+            </div>
+
+            {move || error.get().map(|e| view! { <div class="alert alert-error text-sm">{e}</div> })}
+
+            <div class="flex justify-end">
+                <button class="btn btn-primary btn-sm" on:click=move |_| save()>"Save"</button>
+            </div>
+        </div>
+    }
+}
@@ -1,9 +1,16 @@
+use crate::components::document_inspector::DocumentInspector;
+use crate::components::document_reader::DocumentReader;
 use crate::features::graphrag::GraphRAGPipeline;
 use crate::graphrag_config::{with_graphrag_manager, GraphRAGMetrics};
+use crate::models::freshness::{DocumentExpiry, Freshness};
 use crate::models::graphrag::DocumentIndex;
 use crate::models::webllm::ModelStatus;
+use crate::router::{Modal, RouterContext};
 use crate::state::webllm_state_simple::use_webllm_state;
-use crate::utils::storage::StorageUtils;
+use crate::state::{EventBusContext, GraphRAGStateContext};
+use crate::utils::capabilities::GpuSupport;
+use crate::utils::compute_usage::TokenTotals;
+use crate::utils::format::FormatUtils;
 use gloo_timers::future::TimeoutFuture;
 use leptos::prelude::*;
 use leptos::task::spawn_local;
@@ -15,6 +22,9 @@ pub fn StatusBar(
     selected_llm: ReadSignal<String>,
     knowledge_enabled: ReadSignal<bool>,
     #[prop(optional)] graphrag_metrics: Option<Signal<GraphRAGMetrics>>,
+    /// Token totals of the open conversation
+    #[prop(optional)]
+    conversation_tokens: Option<ReadSignal<TokenTotals>>,
 ) -> impl IntoView {
     // Touch optional metrics to avoid unused warning without changing behavior
     let _ = &graphrag_metrics;
@@ -39,6 +49,10 @@ pub fn StatusBar(
         })
     });
 
+    // What this browser can run, once probed
+    let wl_ctx_for_device = wl_ctx.clone();
+    let device = Signal::derive(move || wl_ctx_for_device.get_capabilities());
+
     // Determine status from WebLLM state (single source of truth) using a memo to allow multiple reads
     let wl_ctx_for_status = wl_ctx.clone();
     let status_kind = Memo::new(move |_| -> (&'static str, &'static str) {
@@ -50,46 +64,51 @@ pub fn StatusBar(
         }
     });
 
+    // Index freshness (last build vs knowledge base changes)
+    let graphrag_ctx = use_context::<GraphRAGStateContext>();
+    let events = use_context::<EventBusContext>();
+    let ctx_for_staleness = graphrag_ctx.clone();
+    let staleness = Signal::derive(move || {
+        ctx_for_staleness
+            .as_ref()
+            .map(|c| c.staleness().get())
+            .unwrap_or_default()
+    });
+
     // Reactive document count with periodic refresh to capture same-tab localStorage updates
     let (doc_count_state, set_doc_count_state) = signal(0usize);
     // Docs modal state and data
     let (show_docs_modal, set_show_docs_modal) = signal(false);
     let (docs, set_docs) = signal::<Vec<DocumentIndex>>(Vec::new());
     let (doc_filter, set_doc_filter) = signal(String::new());
+    // Document open in the read-aloud reader
+    let reading_doc = RwSignal::new(None::<DocumentIndex>);
+    // File open in the inspector, with the passage it was opened from
+    let inspecting = RwSignal::new(None::<(String, String)>);
 
-    // Helper to compute count from storage
-    let read_doc_count = || -> usize {
-        if let Ok(Some(v)) =
-            StorageUtils::retrieve_local::<Vec<DocumentIndex>>("graphrag_document_index_v1")
-        {
-            v.len()
-        } else {
-            StorageUtils::retrieve_local::<Vec<DocumentIndex>>("graphrag_document_index")
-                .ok()
-                .flatten()
-                .map(|v| v.len())
-                .unwrap_or(0)
-        }
-    };
-
-    // Helper to load full docs list
+    // Helper to load full docs list (the pipeline falls back to the legacy key)
     let read_docs = || -> Vec<DocumentIndex> {
-        if let Ok(Some(v)) =
-            StorageUtils::retrieve_local::<Vec<DocumentIndex>>("graphrag_document_index_v1")
-        {
-            v
-        } else {
-            StorageUtils::retrieve_local::<Vec<DocumentIndex>>("graphrag_document_index")
-                .ok()
-                .flatten()
-                .unwrap_or_default()
-        }
+        GraphRAGPipeline::new()
+            .indexed_documents()
+            .unwrap_or_default()
+    };
+    let read_doc_count = move || -> usize { read_docs().len() };
+    // Uploaded files past their expiry date, for the review reminder
+    let (stale_count, set_stale_count) = signal(0usize);
+    let read_stale_count = move || -> usize {
+        let docs = read_docs();
+        DocumentExpiry::load()
+            .unwrap_or_default()
+            .expired_files(docs.iter().map(|d| d.source_file()), js_sys::Date::now())
+            .len()
     };
+    let router = use_context::<RouterContext>();
 
     // Initial read and tie to status message updates
     Effect::new(move |_| {
         let _ = message.get();
         set_doc_count_state.set(read_doc_count());
+        set_stale_count.set(read_stale_count());
     });
 
     // Lightweight polling loop (same-tab storage changes don't fire 'storage' event)
@@ -99,6 +118,7 @@ pub fn StatusBar(
             loop {
                 TimeoutFuture::new(1000).await;
                 set_doc_count_state.set(read_doc_count());
+                set_stale_count.set(read_stale_count());
             }
         });
     });
@@ -200,6 +220,24 @@ pub fn StatusBar(
                         }}</span>
                     </div>
 
+                    // Running token total of the open conversation
+                    {conversation_tokens.map(|tokens| view! {
+                        <div
+                            class="flex items-center gap-1"
+                            title=move || tokens.with(|t| format!(
+                                "{} replies: {} prompt + {} completion tokens",
+                                t.replies,
+                                FormatUtils::format_number(t.prompt_tokens as i64),
+                                FormatUtils::format_number(t.completion_tokens as i64),
+                            ))
+                        >
+                            <div class="w-2 h-2 bg-primary rounded-full"></div>
+                            <span class="font-mono">
+                                {move || format!("Tokens: {}", FormatUtils::format_number(tokens.with(|t| t.total()) as i64))}
+                            </span>
+                        </div>
+                    })}
+
                     // Document count (indexed) - clickable to open modal
                     <button
                         class="flex items-center gap-1 hover:underline cursor-pointer min-w-0"
@@ -215,6 +253,55 @@ pub fn StatusBar(
                         </span>
                     </button>
 
+                    // Expired documents: reminder opening the Document Manager for review
+                    <Show when=move || { stale_count.get() > 0 }>
+                        <button
+                            class="flex items-center gap-1 hover:underline cursor-pointer"
+                            title="Documents past their expiry date are left out of search - click to review"
+                            on:click=move |_| {
+                                if let Some(router) = router {
+                                    router.open(Modal::Documents);
+                                }
+                            }
+                        >
+                            <div class="w-2 h-2 bg-warning rounded-full"></div>
+                            <span class="font-mono">
+                                {move || format!("Review stale docs ({})", stale_count.get())}
+                            </span>
+                        </button>
+                    </Show>
+
+                    // Index freshness: stale badge triggers an incremental reindex
+                    <Show
+                        when=move || staleness.get().is_stale()
+                        fallback=move || view! {
+                            <span class="font-mono" title=move || staleness.get().summary()>
+                                {move || match staleness.get().built_at {
+                                    Some(t) => format!("Indexed {}", FormatUtils::format_relative_time(t).to_lowercase()),
+                                    None => "Not indexed".to_string(),
+                                }}
+                            </span>
+                        }
+                    >
+                        <button
+                            class="flex items-center gap-1 hover:underline cursor-pointer"
+                            title=move || format!("{} - click to update", staleness.get().summary())
+                            on:click={
+                                let ctx = graphrag_ctx.clone();
+                                move |_| {
+                                    if let Some(ctx) = ctx.as_ref() {
+                                        ctx.reindex_changed();
+                                    }
+                                }
+                            }
+                        >
+                            <div class="w-2 h-2 bg-warning rounded-full"></div>
+                            <span class="font-mono">
+                                {move || format!("Index stale ({})", staleness.get().pending())}
+                            </span>
+                        </button>
+                    </Show>
+
                     // Hybrid Fusion time badge (from global manager performance metrics)
                     <div class="flex items-center gap-1">
                         <div class="w-2 h-2 bg-success rounded-full"></div>
@@ -229,6 +316,31 @@ pub fn StatusBar(
                         </span>
                     </div>
 
+                    // Device capabilities (WebGPU, SIMD, memory), details on hover
+                    {move || {
+                        device
+                            .get()
+                            .map(|device| {
+                                let dot = match device.gpu {
+                                    GpuSupport::Available { shader_f16: true } => "bg-success",
+                                    GpuSupport::Available { shader_f16: false } => "bg-warning",
+                                    _ => "bg-error",
+                                };
+                                let details = device
+                                    .report()
+                                    .into_iter()
+                                    .map(|(label, finding)| format!("{}: {}", label, finding))
+                                    .collect::<Vec<_>>()
+                                    .join("\n");
+                                view! {
+                                    <div class="flex items-center gap-1" title=details>
+                                        <div class=format!("w-2 h-2 rounded-full {}", dot)></div>
+                                        <span class="font-mono">{device.summary()}</span>
+                                    </div>
+                                }
+                            })
+                    }}
+
                     // Status indicator
                     <div class="flex items-center gap-1">
                         <div class=move || {
@@ -284,10 +396,13 @@ pub fn StatusBar(
                         >
                             <ul class="menu w-full">
                                 {move || {
+                                    let expiry = DocumentExpiry::load().unwrap_or_default();
+                                    let now = js_sys::Date::now();
                                     filtered_docs
                                         .get()
                                         .into_iter()
                                         .map(|d| {
+                                            let freshness = expiry.freshness_of_file(d.source_file(), now);
                                             let title_attr = d.title.clone();
                                             let title_text = d.title.clone();
                                             let file_type = d.file_type.clone();
@@ -305,6 +420,8 @@ pub fn StatusBar(
                                                 .to_string();
                                             // Use a separate clone for display (badge/title) to avoid borrow-after-move when `id` is moved into the delete closure
                                             let id_for_badge = id.clone();
+                                            let doc_to_read = d.clone();
+                                            let to_inspect = (d.source_file().to_string(), d.id.clone());
                                             view! {
                                                 <li class="!px-0">
                                                     <div class="px-3 py-2 hover:bg-base-200">
@@ -314,6 +431,26 @@ pub fn StatusBar(
                                                                     <p class="font-medium truncate" title=title_attr>
                                                                         {title_text}
                                                                     </p>
+                                                                    <button
+                                                                        class="btn btn-ghost btn-xs shrink-0 ml-auto"
+                                                                        title="Read aloud"
+                                                                        on:click=move |_| {
+                                                                            set_show_docs_modal.set(false);
+                                                                            reading_doc.set(Some(doc_to_read.clone()));
+                                                                        }
+                                                                    >
+                                                                        Read
+                                                                    </button>
+                                                                    <button
+                                                                        class="btn btn-ghost btn-xs shrink-0"
+                                                                        title="Text, chunks, entities and citations of the file"
+                                                                        on:click=move |_| {
+                                                                            set_show_docs_modal.set(false);
+                                                                            inspecting.set(Some(to_inspect.clone()));
+                                                                        }
+                                                                    >
+                                                                        Inspect
+                                                                    </button>
                                                                     <button
                                                                         class="btn btn-ghost btn-xs text-error shrink-0"
                                                                         title="Delete document"
@@ -327,8 +464,16 @@ pub fn StatusBar(
                                                                             // Perform deletion and refresh UI state
                                                                             spawn_local(async move {
                                                                                 let pipeline = GraphRAGPipeline::new();
-                                                                                // Best-effort delete; ignore specific error to keep UI responsive
-                                                                                let _ = pipeline.delete_document_by_id(&id_to_delete);
+                                                                                // Also applies while a reindex runs; failures are shown, not dropped
+                                                                                if let Err(e) = pipeline.delete_document_by_id(&id_to_delete) {
+                                                                                    log::error!("Delete of {} failed: {}", id_to_delete, e);
+                                                                                    if let Some(bus) = events {
+                                                                                        bus.error(format!("Document \"{}\" not deleted", id_to_delete), e.to_string());
+                                                                                    }
+                                                                                    if let Some(w) = window() {
+                                                                                        let _ = w.alert_with_message(&format!("Document {} was not deleted: {}", id_to_delete, e));
+                                                                                    }
+                                                                                }
                                                                                 // Refresh docs and count
                                                                                 set_docs.set(read_docs());
                                                                                 set_doc_count_state.set(read_doc_count());
@@ -344,6 +489,19 @@ pub fn StatusBar(
                                                                     <span>{size_kb.clone()}</span>
                                                                     <span>"."</span>
                                                                     <span>{format!("nodes: {}", node_count)}</span>
+                                                                    {match freshness {
+                                                                        Freshness::Expired { .. } => Some(view! {
+                                                                            <span class="badge badge-error badge-sm" title="Left out of search until its expiry date is extended">
+                                                                                "Expired"
+                                                                            </span>
+                                                                        }),
+                                                                        Freshness::ExpiringSoon { .. } => Some(view! {
+                                                                            <span class="badge badge-warning badge-sm" title="Left out of search once its expiry date passes">
+                                                                                "Expires soon"
+                                                                            </span>
+                                                                        }),
+                                                                        _ => None,
+                                                                    }}
                                                                     <span
                                                                         class="badge badge-ghost badge-sm font-mono ml-1"
                                                                         title={id_for_badge.clone()}
@@ -366,5 +524,32 @@ pub fn StatusBar(
                 </div>
             </div>
         </Show>
+
+        {move || {
+            inspecting
+                .get()
+                .map(|(file, focus)| {
+                    view! {
+                        <DocumentInspector
+                            file=file
+                            focus=focus
+                            on_close=Callback::new(move |_| inspecting.set(None))
+                        />
+                    }
+                })
+        }}
+
+        {move || {
+            reading_doc
+                .get()
+                .map(|document| {
+                    view! {
+                        <DocumentReader
+                            document=document
+                            on_close=Callback::new(move |_| reading_doc.set(None))
+                        />
+                    }
+                })
+        }}
     }
 }
@@ -0,0 +1,184 @@
+use crate::storage::encryption::{self, MIN_PASSPHRASE_CHARS};
+use leptos::prelude::*;
+use leptos::task::spawn_local;
+
+/// What the encryption form does
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Action {
+    Enable,
+    ChangePassphrase,
+    Disable,
+}
+
+impl Action {
+    fn label(self) -> &'static str {
+        match self {
+            Action::Enable => "Encrypt",
+            Action::ChangePassphrase => "Change passphrase",
+            Action::Disable => "Decrypt",
+        }
+    }
+}
+
+/// Turning storage encryption on or off and changing its passphrase. Every stored
+/// record is rewritten, so this takes a while on a large knowledge base.
+#[component]
+pub fn StorageEncryptionPanel() -> impl IntoView {
+    let enabled = RwSignal::new(encryption::is_enabled());
+    let action = RwSignal::new(None::<Action>);
+    let current = RwSignal::new(String::new());
+    let passphrase = RwSignal::new(String::new());
+    let confirm = RwSignal::new(String::new());
+    let busy = RwSignal::new(false);
+    let message = RwSignal::new(None::<Result<String, String>>);
+
+    let reset_form = move || {
+        action.set(None);
+        current.set(String::new());
+        passphrase.set(String::new());
+        confirm.set(String::new());
+    };
+    let submit = move || {
+        let Some(act) = action.get_untracked() else {
+            return;
+        };
+        if act != Action::Disable {
+            if let Err(e) = encryption::validate_passphrase(
+                &passphrase.get_untracked(),
+                &confirm.get_untracked(),
+            ) {
+                message.set(Some(Err(e)));
+                return;
+            }
+        }
+        busy.set(true);
+        message.set(None);
+        spawn_local(async move {
+            let (current, new) = (current.get_untracked(), passphrase.get_untracked());
+            let outcome = match act {
+                Action::Enable => encryption::enable(&new).await,
+                Action::ChangePassphrase => encryption::change_passphrase(&current, &new).await,
+                Action::Disable => encryption::disable(&current).await,
+            };
+            match outcome {
+                Ok(n) => {
+                    let done = match act {
+                        Action::Enable => "Stored data is now encrypted",
+                        Action::ChangePassphrase => "Passphrase changed",
+                        Action::Disable => "Stored data is no longer encrypted",
+                    };
+                    message.set(Some(Ok(format!("{} ({} records rewritten)", done, n))));
+                    enabled.set(encryption::is_enabled());
+                    reset_form();
+                }
+                Err(e) => message.set(Some(Err(e.to_string()))),
+            }
+            busy.set(false);
+        });
+    };
+
+    let password_input = move |placeholder: &'static str, value: RwSignal<String>| {
+        view! {
+            <input
+                type="password"
+                class="input input-bordered input-sm w-full"
+                placeholder=placeholder
+                autocomplete="off"
+                prop:value=move || value.get()
+                on:input=move |ev| value.set(event_target_value(&ev))
+                disabled=move || busy.get()
+            />
+        }
+    };
+
+    view! {
+        <div class="flex flex-col gap-3 text-sm" id="storage-encryption">
+            <div class="flex items-center justify-between gap-2">
+                <div>
+                    <div class="font-medium">"Encryption"</div>
+                    <div class="text-xs text-base-content/70">
+                        {move || if enabled.get() {
+                            "Conversations and documents are encrypted with your passphrase."
+                        } else {
+                            "Conversations and documents are stored unencrypted."
+                        }}
+                    </div>
+                </div>
+                <Show when=move || action.get().is_none()>
+                    <div class="flex gap-1 shrink-0">
+                        <Show
+                            when=move || enabled.get()
+                            fallback=move || view! {
+                                <button class="btn btn-primary btn-xs" on:click=move |_| action.set(Some(Action::Enable))>
+                                    "Encrypt…"
+                                </button>
+                            }
+                        >
+                            <button class="btn btn-ghost btn-xs" on:click=move |_| action.set(Some(Action::ChangePassphrase))>
+                                "Change passphrase…"
+                            </button>
+                            <button class="btn btn-ghost btn-xs" on:click=move |_| action.set(Some(Action::Disable))>
+                                "Decrypt…"
+                            </button>
+                        </Show>
+                    </div>
+                </Show>
+            </div>
+
+            <Show when=move || action.get().is_some()>
+                <form
+                    class="flex flex-col gap-2 p-3 rounded-xl bg-base-200"
+                    on:submit=move |ev| {
+                        ev.prevent_default();
+                        submit();
+                    }
+                >
+                    <Show when=move || action.get() != Some(Action::Enable)>
+                        {password_input("Current passphrase", current)}
+                    </Show>
+                    <Show when=move || action.get() != Some(Action::Disable)>
+                        {password_input("New passphrase", passphrase)}
+                        {password_input("Repeat the new passphrase", confirm)}
+                        <p class="text-xs text-base-content/60">
+                            {format!(
+                                "At least {} characters. It cannot be recovered: without it the data is lost.",
+                                MIN_PASSPHRASE_CHARS
+                            )}
+                        </p>
+                    </Show>
+                    <div class="flex justify-end gap-2">
+                        <button
+                            type="button"
+                            class="btn btn-ghost btn-xs"
+                            disabled=move || busy.get()
+                            on:click=move |_| reset_form()
+                        >
+                            "Cancel"
+                        </button>
+                        <button type="submit" class="btn btn-primary btn-xs" disabled=move || busy.get()>
+                            {move || {
+                                if busy.get() {
+                                    "Rewriting records…"
+                                } else {
+                                    action.get().map(Action::label).unwrap_or_default()
+                                }
+                            }}
+                        </button>
+                    </div>
+                </form>
+            </Show>
+
+            {move || {
+                message
+                    .get()
+                    .map(|m| {
+                        let (class, text) = match m {
+                            Ok(text) => ("text-xs text-success", text),
+                            Err(text) => ("text-xs text-error", text),
+                        };
+                        view! { <p class=class>{text}</p> }
+                    })
+            }}
+        </div>
+    }
+}
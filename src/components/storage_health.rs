@@ -0,0 +1,140 @@
+use crate::storage::integrity::{self, IntegrityReport, KnownKey, QuarantinedRecord, KNOWN_KEYS};
+use crate::utils::format::FormatUtils;
+use crate::utils::storage::StorageInfo;
+use leptos::prelude::*;
+use leptos::task::spawn_local;
+
+fn confirm(message: &str) -> bool {
+    web_sys::window()
+        .and_then(|w| w.confirm_with_message(message).ok())
+        .unwrap_or(false)
+}
+
+/// Stored records the startup check set aside because they did not parse, with
+/// actions to restore what still parses or to delete them
+#[component]
+pub fn StorageHealthPanel() -> impl IntoView {
+    let records = RwSignal::new(IntegrityReport::load().quarantined);
+    let busy = RwSignal::new(None::<String>);
+    let message = RwSignal::new(None::<Result<String, String>>);
+    let reload = move || records.set(IntegrityReport::load().quarantined);
+
+    let repair = Callback::new(move |record: QuarantinedRecord| {
+        let label = record.label().to_string();
+        let current = label.to_lowercase();
+        if KnownKey::find(&record.key).is_some_and(integrity::has_record)
+            && !confirm(&format!(
+                "New {} were saved since the quarantine. Replace them with the repaired copy?",
+                current
+            ))
+        {
+            return;
+        }
+        busy.set(Some(record.key.clone()));
+        spawn_local(async move {
+            let outcome = match integrity::repair_quarantined(&record.key).await {
+                Ok(0) => Ok(format!("{} restored. Reload the app to use it.", label)),
+                Ok(dropped) => Ok(format!(
+                    "{} restored without {} broken entr{}. Reload the app to use it.",
+                    label,
+                    dropped,
+                    if dropped == 1 { "y" } else { "ies" }
+                )),
+                Err(e) => Err(format!("{} could not be repaired: {}", label, e)),
+            };
+            message.set(Some(outcome));
+            busy.set(None);
+            reload();
+        });
+    });
+
+    let delete = Callback::new(move |record: QuarantinedRecord| {
+        let label = record.label().to_string();
+        if !confirm(&format!(
+            "Delete the quarantined copy of {}? It cannot be recovered.",
+            label
+        )) {
+            return;
+        }
+        message.set(Some(
+            integrity::delete_quarantined(&record.key)
+                .map(|()| format!("Quarantined {} deleted", label))
+                .map_err(|e| e.to_string()),
+        ));
+        reload();
+    });
+
+    view! {
+        <div class="flex flex-col gap-3" id="storage-health">
+            <p class="text-sm text-base-content/70">
+                {format!(
+                    "Each launch checks {} stored records. Records that no longer read correctly are set aside here instead of being replaced by defaults.",
+                    KNOWN_KEYS.len(),
+                )}
+            </p>
+            <Show
+                when=move || records.with(|r| !r.is_empty())
+                fallback=|| view! {
+                    <div class="alert alert-success text-sm">
+                        <i data-lucide="shield-check" class="w-4 h-4"></i>
+                        "Every stored record passed the startup check."
+                    </div>
+                }
+            >
+                <For
+                    each=move || records.get()
+                    key=|r| (r.key.clone(), r.quarantined_at as u64)
+                    let:record
+                >
+                    {
+                        let for_repair = record.clone();
+                        let for_delete = record.clone();
+                        let key = record.key.clone();
+                        let working = move || busy.with(|b| b.as_deref() == Some(key.as_str()));
+                        view! {
+                            <div class="p-3 rounded-lg bg-base-200 space-y-1">
+                                <div class="flex items-center justify-between gap-2">
+                                    <span class="font-medium text-sm">{record.label().to_string()}</span>
+                                    <span class="text-xs text-base-content/60">
+                                        {format!(
+                                            "{} · {}",
+                                            StorageInfo::format_size(record.bytes),
+                                            FormatUtils::format_timestamp(record.quarantined_at),
+                                        )}
+                                    </span>
+                                </div>
+                                <p class="text-xs font-mono text-base-content/60">{record.key.clone()}</p>
+                                <p class="text-xs text-error break-words">{record.error.clone()}</p>
+                                <div class="flex gap-2 pt-1">
+                                    <button
+                                        class="btn btn-xs btn-primary"
+                                        title="Restore the entries that still read correctly"
+                                        disabled=working.clone()
+                                        on:click=move |_| repair.run(for_repair.clone())
+                                    >
+                                        "Repair"
+                                    </button>
+                                    <button
+                                        class="btn btn-xs btn-ghost"
+                                        disabled=working
+                                        on:click=move |_| delete.run(for_delete.clone())
+                                    >
+                                        "Delete"
+                                    </button>
+                                </div>
+                            </div>
+                        }
+                    }
+                </For>
+            </Show>
+            {move || {
+                message
+                    .get()
+                    .map(|m| match m {
+                        Ok(text) => view! { <p class="text-xs text-success">{text}</p> }.into_any(),
+                        Err(text) => view! { <p class="text-xs text-error">{text}</p> }.into_any(),
+                    })
+            }}
+        </div>
+    }
+}
@@ -0,0 +1,194 @@
+use crate::features::crm::table_import::{guess_mapping, to_customers, ColumnTarget};
+use crate::models::ActivityCategory;
+use crate::state::knowledge_storage_context::KnowledgeStorageContext;
+use crate::state::{CRMStateContext, EventBusContext, GraphRAGStateContext};
+use crate::utils::tables::{self, Table, TABLE_SUFFIX};
+use leptos::ev;
+use leptos::prelude::*;
+
+/// Table found in a paste event, if any
+pub fn pasted_table(ev: &ev::ClipboardEvent) -> Option<Table> {
+    let data = ev.clipboard_data()?;
+    let text = data.get_data("text").unwrap_or_default();
+    let html = data.get_data("text/html").ok().filter(|h| !h.is_empty());
+    tables::detect(&text, html.as_deref())
+}
+
+/// Destinations offered for a pasted table: customer records with a column mapping,
+/// or a table document indexed row by row. The paste itself goes through unchanged.
+#[component]
+pub fn TablePasteOffer(
+    /// Table of the last paste; cleared once handled or dismissed
+    table: RwSignal<Option<Table>>,
+    /// Told what was done, for the status line
+    on_done: Callback<String>,
+) -> impl IntoView {
+    let events = use_context::<EventBusContext>();
+    let graphrag_ctx = use_context::<GraphRAGStateContext>();
+    let crm = use_context::<CRMStateContext>().unwrap_or_default();
+    let mapping = RwSignal::new(Vec::<ColumnTarget>::new());
+    let mapping_open = RwSignal::new(false);
+    let name = RwSignal::new(String::new());
+
+    // A new paste starts over with guessed mappings
+    Effect::new(move |_| {
+        let headers = table.with(|t| t.as_ref().map(|t| t.headers.clone()));
+        mapping.set(headers.as_deref().map(guess_mapping).unwrap_or_default());
+        mapping_open.set(false);
+        name.set(String::new());
+    });
+
+    let index_table = Callback::new(move |_: ()| {
+        let Some(pasted) = table.get_untracked() else {
+            return;
+        };
+        let typed = name.get_untracked();
+        let base = typed.trim();
+        let base = if base.is_empty() {
+            format!(
+                "Pasted table {}",
+                js_sys::Date::new_0()
+                    .to_iso_string()
+                    .as_string()
+                    .unwrap_or_default()
+            )
+        } else {
+            base.to_string()
+        };
+        let title = format!("{}{}", base, TABLE_SUFFIX);
+        match KnowledgeStorageContext::new().upsert_buffer_document(&title, &pasted.to_markdown()) {
+            Ok(_) => {
+                table.set(None);
+                let message = format!("Indexed \"{}\" ({} rows)", title, pasted.rows.len());
+                if let Some(bus) = events {
+                    bus.record(ActivityCategory::Knowledge, message.clone());
+                }
+                if let Some(ctx) = graphrag_ctx.as_ref() {
+                    ctx.reindex_changed();
+                }
+                on_done.run(message);
+            }
+            Err(e) => {
+                if let Some(bus) = events {
+                    bus.error("Saving the pasted table failed", e.to_string());
+                }
+                on_done.run("Failed to save the table".to_string());
+            }
+        }
+    });
+
+    let import_customers = Callback::new(move |_: ()| {
+        let Some(pasted) = table.get_untracked() else {
+            return;
+        };
+        let import = to_customers(
+            &pasted,
+            &mapping.get_untracked(),
+            &crm.customers_now(),
+            js_sys::Date::now(),
+        );
+        let added = import.customers.len();
+        crm.add_customers(import.customers);
+        table.set(None);
+        let mut message = format!("Imported {} customer(s)", added);
+        if import.skipped > 0 {
+            message.push_str(&format!(", {} row(s) skipped", import.skipped));
+        }
+        if let Some(bus) = events {
+            bus.record(ActivityCategory::Crm, message.clone());
+        }
+        on_done.run(message);
+    });
+
+    let summary = move || {
+        table.with(|t| {
+            t.as_ref()
+                .map(|t| {
+                    format!(
+                        "Pasted a table: {} rows, {} columns.",
+                        t.rows.len(),
+                        t.headers.len()
+                    )
+                })
+                .unwrap_or_default()
+        })
+    };
+
+    view! {
+        <Show when=move || table.with(|t| t.is_some())>
+            <div class="alert alert-info text-sm py-2 flex-col items-stretch gap-2">
+                <div class="flex items-center gap-2 flex-wrap">
+                    <i data-lucide="table" class="h-4 w-4"></i>
+                    <span class="flex-1">{summary}</span>
+                    <button class="btn btn-sm btn-primary" on:click=move |_| mapping_open.update(|o| *o = !*o)>
+                        "Import as customers"
+                    </button>
+                    <input
+                        type="text"
+                        class="input input-bordered input-sm w-40"
+                        placeholder="Table name"
+                        prop:value=move || name.get()
+                        on:input=move |ev| name.set(event_target_value(&ev))
+                    />
+                    <button class="btn btn-sm" on:click=move |_| index_table.run(())>
+                        "Index as table"
+                    </button>
+                    <button class="btn btn-sm btn-ghost" on:click=move |_| table.set(None)>
+                        "Keep as text"
+                    </button>
+                </div>
+                <Show when=move || mapping_open.get()>
+                    <div class="bg-base-100 text-base-content rounded p-2 flex flex-col gap-1">
+                        {move || {
+                            let Some(t) = table.get() else {
+                                return Vec::new();
+                            };
+                            t.headers
+                                .iter()
+                                .enumerate()
+                                .map(|(col, header)| {
+                                    let sample = t.rows[0][col].clone();
+                                    view! {
+                                        <div class="flex items-center gap-2 text-xs">
+                                            <span class="w-32 truncate font-medium" title=header.clone()>{header.clone()}</span>
+                                            <span class="w-32 truncate opacity-60" title=sample.clone()>{sample.clone()}</span>
+                                            <select
+                                                class="select select-bordered select-xs"
+                                                prop:value=move || {
+                                                    mapping.with(|m| m.get(col).copied().unwrap_or(ColumnTarget::Skip).id())
+                                                }
+                                                on:change=move |ev| {
+                                                    if let Some(target) = ColumnTarget::from_id(&event_target_value(&ev)) {
+                                                        mapping.update(|m| {
+                                                            if let Some(slot) = m.get_mut(col) {
+                                                                *slot = target;
+                                                            }
+                                                        });
+                                                    }
+                                                }
+                                            >
+                                                {ColumnTarget::ALL
+                                                    .into_iter()
+                                                    .map(|t| view! { <option value=t.id()>{t.label()}</option> })
+                                                    .collect_view()}
+                                            </select>
+                                        </div>
+                                    }
+                                })
+                                .collect::<Vec<_>>()
+                        }}
+                        <div class="flex justify-end">
+                            <button
+                                class="btn btn-sm btn-primary"
+                                disabled=move || mapping.with(|m| !m.contains(&ColumnTarget::Name))
+                                on:click=move |_| import_customers.run(())
+                            >
+                                "Import"
+                            </button>
+                        </div>
+                    </div>
+                </Show>
+            </div>
+        </Show>
+    }
+}
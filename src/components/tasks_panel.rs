@@ -0,0 +1,210 @@
+use crate::components::ui_primitives::Button;
+use crate::features::crm::ics;
+use crate::models::tasks::{CrmRecordType, Task};
+use crate::state::use_tasks_state;
+use crate::utils::icons::schedule_icon_render;
+use leptos::prelude::*;
+
+fn format_due(ts: f64) -> String {
+    let date = js_sys::Date::new(&wasm_bindgen::JsValue::from(ts));
+    date.to_locale_date_string("en-US", &js_sys::Object::new())
+        .as_string()
+        .unwrap_or_default()
+}
+
+fn link_label(task: &Task) -> Option<String> {
+    task.crm_link.as_ref().map(|l| {
+        let kind = match l.record_type {
+            CrmRecordType::Customer => "Customer",
+            CrmRecordType::Lead => "Lead",
+            CrmRecordType::Deal => "Deal",
+        };
+        format!("{} · {}", kind, l.record_id)
+    })
+}
+
+/// Lightweight to-do list backed by `TasksStateContext`
+#[component]
+pub fn TasksPanel(
+    #[prop(optional)] on_open_conversation: Option<Box<dyn Fn(String) + 'static>>,
+) -> impl IntoView {
+    let ctx = use_tasks_state();
+    let tasks = ctx.tasks();
+    // "open" | "done" | "all"
+    let (filter, set_filter) = signal("open".to_string());
+    let on_open_conversation = std::rc::Rc::new(on_open_conversation);
+
+    let visible = Memo::new(move |_| {
+        let f = filter.get();
+        let mut v: Vec<Task> = tasks
+            .get()
+            .into_iter()
+            .filter(|t| match f.as_str() {
+                "open" => !t.done,
+                "done" => t.done,
+                _ => true,
+            })
+            .collect();
+        // Dated tasks first (soonest first), then newest undated
+        v.sort_by(|a, b| match (a.due_date, b.due_date) {
+            (Some(x), Some(y)) => x.partial_cmp(&y).unwrap_or(std::cmp::Ordering::Equal),
+            (Some(_), None) => std::cmp::Ordering::Less,
+            (None, Some(_)) => std::cmp::Ordering::Greater,
+            (None, None) => b
+                .created_at
+                .partial_cmp(&a.created_at)
+                .unwrap_or(std::cmp::Ordering::Equal),
+        });
+        v
+    });
+
+    let open_count = Memo::new(move |_| tasks.get().iter().filter(|t| !t.done).count());
+
+    Effect::new(move |_| {
+        let _ = visible.get();
+        schedule_icon_render();
+    });
+
+    let ctx_for_clear = ctx.clone();
+    let ctx_for_export = ctx.clone();
+
+    view! {
+        <div class="flex flex-col gap-3 min-w-[20rem]" id="tasks-panel">
+            <div class="flex items-center justify-between gap-2">
+                <div role="tablist" class="tabs tabs-boxed tabs-sm">
+                    {["open", "done", "all"]
+                        .into_iter()
+                        .map(|f| {
+                            view! {
+                                <a
+                                    role="tab"
+                                    class=move || if filter.get() == f { "tab tab-active" } else { "tab" }
+                                    on:click=move |_| set_filter.set(f.to_string())
+                                >
+                                    {match f {
+                                        "open" => "Open",
+                                        "done" => "Done",
+                                        _ => "All",
+                                    }}
+                                </a>
+                            }
+                        })
+                        .collect_view()}
+                </div>
+                <span class="text-xs text-base-content/60">
+                    {move || format!("{} open", open_count.get())}
+                </span>
+            </div>
+
+            <Show
+                when=move || !visible.get().is_empty()
+                fallback=|| view! {
+                    <div class="text-sm text-base-content/60 py-6 text-center">
+                        "No tasks. Use \"Extract Action Items\" in a conversation menu to create some."
+                    </div>
+                }
+            >
+                <ul class="flex flex-col gap-1 max-h-[60vh] overflow-y-auto">
+                    <For
+                        each=move || visible.get()
+                        key=|t| (t.id.clone(), t.done)
+                        children={
+                            let ctx = ctx.clone();
+                            let on_open_conversation = on_open_conversation.clone();
+                            move |t: Task| {
+                                let id_toggle = t.id.clone();
+                                let id_delete = t.id.clone();
+                                let cal_task = t.clone();
+                                let ctx_toggle = ctx.clone();
+                                let ctx_delete = ctx.clone();
+                                let overdue = t.is_overdue(js_sys::Date::now());
+                                let link = link_label(&t);
+                                let conv = t.conversation_id.clone();
+                                let open_conv = on_open_conversation.clone();
+                                view! {
+                                    <li class="flex items-start gap-2 p-2 rounded hover:bg-base-200">
+                                        <input
+                                            type="checkbox"
+                                            class="checkbox checkbox-sm mt-0.5"
+                                            prop:checked=t.done
+                                            on:change=move |_| ctx_toggle.toggle_done(&id_toggle)
+                                        />
+                                        <div class="flex-1 min-w-0">
+                                            <div class=if t.done { "text-sm line-through text-base-content/50" } else { "text-sm" }>
+                                                {t.title.clone()}
+                                            </div>
+                                            <div class="flex flex-wrap gap-1 mt-1">
+                                                {t.due_date.map(|d| view! {
+                                                    <span class=if overdue { "badge badge-xs badge-error" } else { "badge badge-xs badge-ghost" }>
+                                                        {format!("Due {}", format_due(d))}
+                                                    </span>
+                                                })}
+                                                {link.map(|l| view! { <span class="badge badge-xs badge-outline">{l}</span> })}
+                                                {conv.map(|cid| view! {
+                                                    <button
+                                                        class="badge badge-xs badge-ghost cursor-pointer"
+                                                        title="Open source conversation"
+                                                        on:click=move |_| {
+                                                            if let Some(cb) = open_conv.as_ref() {
+                                                                cb(cid.clone());
+                                                            }
+                                                        }
+                                                    >
+                                                        "Conversation"
+                                                    </button>
+                                                })}
+                                            </div>
+                                        </div>
+                                        {cal_task.due_date.map(|due| {
+                                            let ev = ics::task_event(&cal_task, due);
+                                            view! {
+                                                <button
+                                                    class="btn btn-ghost btn-xs btn-square"
+                                                    title="Add to calendar"
+                                                    on:click=move |_| {
+                                                        if let Err(e) = ics::download_calendar("task.ics", std::slice::from_ref(&ev)) {
+                                                            log::error!("Calendar export failed: {}", e);
+                                                        }
+                                                    }
+                                                >
+                                                    <i data-lucide="calendar-plus" class="w-3 h-3"></i>
+                                                </button>
+                                            }
+                                        })}
+                                        <button
+                                            class="btn btn-ghost btn-xs btn-square"
+                                            title="Delete task"
+                                            on:click=move |_| ctx_delete.delete_task(&id_delete)
+                                        >
+                                            <i data-lucide="trash-2" class="w-3 h-3"></i>
+                                        </button>
+                                    </li>
+                                }
+                            }
+                        }
+                    />
+                </ul>
+            </Show>
+
+            <div class="flex justify-end gap-2">
+                <Button
+                    label=Signal::derive(|| "Export .ics".to_string())
+                    variant=Signal::derive(|| "btn-ghost btn-sm".to_string())
+                    icon=Signal::derive(|| "calendar".to_string())
+                    on_click=Box::new(move || {
+                        let events = ics::events_from_tasks(&ctx_for_export.tasks_now());
+                        if let Err(e) = ics::download_calendar("tasks.ics", &events) {
+                            log::error!("Calendar export failed: {}", e);
+                        }
+                    })
+                />
+                <Button
+                    label=Signal::derive(|| "Clear completed".to_string())
+                    variant=Signal::derive(|| "btn-ghost btn-sm".to_string())
+                    icon=Signal::derive(|| "check-check".to_string())
+                    on_click=Box::new(move || ctx_for_clear.clear_completed())
+                />
+            </div>
+        </div>
+    }
+}
@@ -1,9 +1,11 @@
+use crate::models::{AppConfig, ThemeMode};
 use leptos::prelude::*;
 use leptos::web_sys::window;
 
 #[component]
 pub fn ThemeToggle() -> impl IntoView {
-    let (theme, set_theme) = signal("light");
+    // Saved in the app config so it survives reloads and travels with exported settings
+    let (theme, set_theme) = signal(AppConfig::load().unwrap_or_default().theme.data_theme());
 
     // Apply theme on startup and changes
     Effect::new(move |_| {
@@ -33,6 +35,13 @@ pub fn ThemeToggle() -> impl IntoView {
                                 }
                             }
                         });
+                        let mut config = AppConfig::load().unwrap_or_default();
+                        config.theme = if theme.get_untracked() == "business" {
+                            ThemeMode::Dark
+                        } else {
+                            ThemeMode::Light
+                        };
+                        let _ = config.save();
                     }
                 />
 
@@ -0,0 +1,282 @@
+//! Public API for embedding parts of the chatbot in another Leptos app instead of
+//! mounting the whole `App`.
+//!
+//! Configure the storage and model with a `ChatbotConfig`, initialize it once before
+//! mounting, then place any of `ChatWidget`, `KnowledgePanel` and `CrmPanel`:
+//!
+//! ```ignore
+//! use wasm_knowledge_chatbot_rs::{ChatWidget, ChatbotConfig, KnowledgePanel};
+//!
+//! let config = ChatbotConfig::new()
+//!     .with_storage(MyStore::default())
+//!     .with_llm_engine("my-model", engine)
+//!     .with_knowledge(true);
+//! wasm_bindgen_futures::spawn_local(async move {
+//!     config.init().await;
+//!     mount_to_body(move || view! {
+//!         <ChatWidget config=config.clone() />
+//!         <KnowledgePanel />
+//!     })
+//! });
+//! ```
+//!
+//! The components share the app's shared state (event bus, knowledge store, tasks,
+//! GraphRAG state) when an ancestor provides it, e.g. `ChatbotProvider`, and create
+//! their own otherwise.
+
+use crate::components::chat_area::ChatArea;
+use crate::components::document_manager_simple::DocumentManagerSimple;
+use crate::components::drop_zone::DroppedFiles;
+use crate::components::status_bar::StatusBar;
+use crate::features::crm::CRMPanel;
+use crate::features::webllm::service;
+use crate::graphrag_config::create_graphrag_signals;
+use crate::models::webllm::{LLMModel, ModelStatus};
+use crate::state::{
+    ConversationStateContext, EventBusContext, GraphRAGStateContext, KnowledgeStorageContext,
+    NarrationContext, TasksStateContext, WebLLMStateContext,
+};
+use crate::storage::persistent::{init_persistent_storage, init_persistent_storage_with};
+use crate::storage::{ConversationStorage, StorageBackend};
+use crate::utils::compute_usage::TokenTotals;
+use crate::webllm_binding::{loaded_engine, set_loaded_engine};
+use leptos::prelude::*;
+use std::rc::Rc;
+use wasm_bindgen::JsValue;
+
+/// WebLLM model loaded when no engine is injected
+pub const DEFAULT_MODEL: &str = "Llama-3.2-1B-Instruct-q4f32_1-MLC";
+
+/// Storage and model wiring of an embedded chatbot, built with the `with_*` methods
+#[derive(Clone)]
+pub struct ChatbotConfig {
+    storage: Option<Rc<dyn StorageBackend>>,
+    engine: Option<JsValue>,
+    model_id: String,
+    knowledge_enabled: bool,
+}
+
+impl Default for ChatbotConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ChatbotConfig {
+    /// IndexedDB storage (localStorage when unavailable) and the default WebLLM model
+    pub fn new() -> Self {
+        Self {
+            storage: None,
+            engine: None,
+            model_id: DEFAULT_MODEL.to_string(),
+            knowledge_enabled: false,
+        }
+    }
+
+    /// Keep conversations, documents, the index and the CRM in `backend`
+    pub fn with_storage(mut self, backend: impl StorageBackend + 'static) -> Self {
+        self.storage = Some(Rc::new(backend));
+        self
+    }
+
+    /// WebLLM model downloaded and loaded in the browser on first use
+    pub fn with_model(mut self, model_id: impl Into<String>) -> Self {
+        self.model_id = model_id.into();
+        self.engine = None;
+        self
+    }
+
+    /// Answer with an engine created by the host app instead of loading a model. Any
+    /// object exposing WebLLM's OpenAI-style `chat.completions.create` works, e.g. an
+    /// `MLCEngine`, a web-worker engine or a wrapper around a remote API.
+    pub fn with_llm_engine(mut self, model_id: impl Into<String>, engine: JsValue) -> Self {
+        self.model_id = model_id.into();
+        self.engine = Some(engine);
+        self
+    }
+
+    /// Search the knowledge base for every message from the start
+    pub fn with_knowledge(mut self, enabled: bool) -> Self {
+        self.knowledge_enabled = enabled;
+        self
+    }
+
+    pub fn model_id(&self) -> &str {
+        &self.model_id
+    }
+
+    pub fn knowledge_enabled(&self) -> bool {
+        self.knowledge_enabled
+    }
+
+    /// Open the storage and register the injected engine. Must complete before any
+    /// component is mounted, as the components read their datasets synchronously.
+    pub async fn init(&self) {
+        match &self.storage {
+            Some(backend) => init_persistent_storage_with(backend.clone()).await,
+            None => init_persistent_storage().await,
+        }
+        if let Some(engine) = &self.engine {
+            set_loaded_engine(&self.model_id, engine.clone());
+        }
+    }
+}
+
+/// Provide the shared state the components read, skipping what an ancestor already
+/// provides. The event bus comes first so the other contexts can report to it.
+pub fn provide_chatbot_contexts() {
+    if use_context::<EventBusContext>().is_none() {
+        provide_context(EventBusContext::new());
+    }
+    if use_context::<KnowledgeStorageContext>().is_none() {
+        provide_context(KnowledgeStorageContext::new());
+    }
+    if use_context::<TasksStateContext>().is_none() {
+        let tasks = TasksStateContext::new();
+        tasks.sync_with_other_tabs();
+        provide_context(tasks);
+    }
+    if use_context::<NarrationContext>().is_none() {
+        provide_context(NarrationContext::new());
+    }
+    if use_context::<ConversationStateContext>().is_none() {
+        provide_context(ConversationStateContext::new());
+    }
+    if use_context::<DroppedFiles>().is_none() {
+        provide_context(DroppedFiles::new());
+    }
+    if use_context::<GraphRAGStateContext>().is_none() {
+        let graphrag = GraphRAGStateContext::new();
+        graphrag.sync_with_other_tabs();
+        provide_context(graphrag);
+    }
+    if use_context::<WebLLMStateContext>().is_none() {
+        provide_context(WebLLMStateContext::new());
+    }
+}
+
+/// Shares one set of chatbot state between the embedded components it wraps, so e.g.
+/// documents added in a `KnowledgePanel` are searched by a `ChatWidget`
+#[component]
+pub fn ChatbotProvider(children: Children) -> impl IntoView {
+    provide_chatbot_contexts();
+    children()
+}
+
+/// Make `model_id` the model of the shared WebLLM state, which the chat reads. An
+/// engine injected by `ChatbotConfig::init` is used as it is; any other model is
+/// loaded through the model service.
+fn select_configured_model(ctx: WebLLMStateContext, model_id: &str) {
+    if ctx.get_current_model().is_some_and(|m| m.id == model_id) {
+        return;
+    }
+    let model = LLMModel::new(
+        model_id.to_string(),
+        model_id.to_string(),
+        "WebLLM".to_string(),
+        String::new(),
+    );
+    if loaded_engine().is_some_and(|(id, _)| id == model_id) {
+        ctx.set_current_model(Some(model));
+        ctx.set_model_status(ModelStatus::Ready);
+        ctx.set_initialization_progress(1.0);
+        ctx.refresh_engines();
+    } else {
+        service::init_model(ctx, model);
+    }
+}
+
+/// Standalone chat: message thread, input and a status line, answering with the
+/// configured model and searching the knowledge base when enabled
+#[component]
+pub fn ChatWidget(
+    /// Model and knowledge settings; defaults to `ChatbotConfig::new()`
+    #[prop(optional)]
+    config: Option<ChatbotConfig>,
+    /// Conversation opened first; a new one is started otherwise
+    #[prop(optional, into)]
+    conversation_id: Option<String>,
+) -> impl IntoView {
+    provide_chatbot_contexts();
+    let config = config.unwrap_or_default();
+    let (knowledge_enabled, set_knowledge_enabled) = signal(config.knowledge_enabled);
+    select_configured_model(
+        use_context::<WebLLMStateContext>().unwrap_or_default(),
+        &config.model_id,
+    );
+    let (selected_llm, _) = signal(config.model_id.clone());
+    let (status_message, set_status_message) = signal("Ready".to_string());
+    let (conversation_tokens, set_conversation_tokens) = signal(TokenTotals::default());
+    let (storage, _) = signal(ConversationStorage::new().ok());
+    let (current_conversation_id, set_current_conversation_id) = signal(conversation_id);
+    let (_, set_conversation_list_refresh) = signal(0u32);
+    let (graphrag_config, graphrag_metrics, graphrag_manager) = create_graphrag_signals();
+
+    view! {
+        <div class="flex flex-col h-full min-h-0 bg-base-100">
+            <div class="flex-1 min-h-0 relative">
+                <ChatArea
+                    knowledge_enabled=knowledge_enabled
+                    set_knowledge_enabled=set_knowledge_enabled
+                    set_status_message=set_status_message
+                    graphrag_config=graphrag_config
+                    graphrag_metrics=graphrag_metrics
+                    graphrag_manager=graphrag_manager
+                    storage=storage
+                    current_conversation_id=current_conversation_id
+                    set_current_conversation_id=set_current_conversation_id
+                    set_conversation_list_refresh=set_conversation_list_refresh
+                    set_conversation_tokens=set_conversation_tokens
+                />
+            </div>
+            <StatusBar
+                message=status_message
+                selected_llm=selected_llm
+                knowledge_enabled=knowledge_enabled
+                graphrag_metrics=graphrag_metrics
+                conversation_tokens=conversation_tokens
+            />
+        </div>
+    }
+}
+
+/// Standalone knowledge base manager: upload, index, collections and freshness
+#[component]
+pub fn KnowledgePanel() -> impl IntoView {
+    provide_chatbot_contexts();
+    view! { <DocumentManagerSimple /> }
+}
+
+/// Standalone CRM: customers, leads, deals and the pipeline board
+#[component]
+pub fn CrmPanel(
+    /// Tab shown first: "customers", "leads", "deals", "stages" or "board"
+    #[prop(optional, into)]
+    initial_tab: Option<String>,
+) -> impl IntoView {
+    provide_chatbot_contexts();
+    view! { <CRMPanel initial_tab=initial_tab /> }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_builder_keeps_the_last_model_choice() {
+        let config = ChatbotConfig::new();
+        assert_eq!(config.model_id(), DEFAULT_MODEL);
+        assert!(!config.knowledge_enabled());
+
+        let config = config
+            .with_llm_engine("host-model", JsValue::NULL)
+            .with_knowledge(true);
+        assert_eq!(config.model_id(), "host-model");
+        assert!(config.engine.is_some());
+        assert!(config.knowledge_enabled());
+
+        let config = config.with_model("Phi-3.5-mini-instruct-q4f16_1-MLC");
+        assert_eq!(config.model_id(), "Phi-3.5-mini-instruct-q4f16_1-MLC");
+        assert!(config.engine.is_none());
+    }
+}
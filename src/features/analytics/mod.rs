@@ -0,0 +1,4 @@
+pub mod topics;
+pub mod ui;
+
+pub use ui::TopicsPanel;
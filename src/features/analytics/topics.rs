@@ -0,0 +1,195 @@
+//! Topics of a conversation: keyphrases pulled from the transcript and ranked by how
+//! many messages mention them. Two-word phrases that recur are kept as one topic, and
+//! a word is dropped when it only ever appears inside such a phrase.
+
+use crate::features::graphrag::summarizer::content_terms;
+use crate::models::{Message, MessageRole};
+use std::collections::{HashMap, HashSet};
+
+/// Topics shown in the overview
+pub const MAX_TOPICS: usize = 30;
+
+// Words common in chat that say nothing about the subject
+const FILLER: &[&str] = &[
+    "please",
+    "thanks",
+    "thank",
+    "like",
+    "want",
+    "need",
+    "know",
+    "think",
+    "make",
+    "sure",
+    "yes",
+    "yeah",
+    "okay",
+    "let",
+    "get",
+    "use",
+    "using",
+    "here",
+    "there",
+    "well",
+    "good",
+    "great",
+    "really",
+    "something",
+    "anything",
+    "thing",
+    "things",
+    "way",
+    "does",
+    "did",
+    "doing",
+    "done",
+    "can't",
+    "don",
+    "i'm",
+    "it's",
+    "below",
+    "above",
+    "following",
+    "example",
+];
+
+/// A keyphrase and the messages it occurs in
+#[derive(Clone, Debug, PartialEq)]
+pub struct Topic {
+    pub phrase: String,
+    /// Occurrences across the transcript
+    pub count: usize,
+    /// Relative to the top topic, 0..=1, for sizing the cloud
+    pub weight: f32,
+    /// Ids of the messages mentioning the phrase, in transcript order
+    pub message_ids: Vec<String>,
+}
+
+fn is_content(word: &str) -> bool {
+    !word.chars().all(|c| c.is_ascii_digit())
+        && !FILLER.contains(&word)
+        && !content_terms(word).is_empty()
+}
+
+/// Candidate phrases of a message: content words and pairs of adjacent content words
+fn phrases(text: &str) -> Vec<String> {
+    let words: Vec<String> = text
+        .split(|c: char| !(c.is_alphanumeric() || c == '\''))
+        .map(|w| w.trim_matches('\'').to_lowercase())
+        .filter(|w| !w.is_empty())
+        .collect();
+    let mut out = Vec::new();
+    for (i, w) in words.iter().enumerate() {
+        if !is_content(w) {
+            continue;
+        }
+        out.push(w.clone());
+        if let Some(next) = words.get(i + 1).filter(|n| is_content(n)) {
+            out.push(format!("{} {}", w, next));
+        }
+    }
+    out
+}
+
+/// Whether `message` mentions `phrase`, matched on whole words
+pub fn mentions(message: &Message, phrase: &str) -> bool {
+    phrases(&message.content).iter().any(|p| p == phrase)
+}
+
+/// The `limit` most mentioned topics of a conversation, system messages excluded
+pub fn extract_topics(messages: &[Message], limit: usize) -> Vec<Topic> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    let mut ids: HashMap<String, Vec<String>> = HashMap::new();
+    for m in messages.iter().filter(|m| m.role != MessageRole::System) {
+        let mut seen = HashSet::new();
+        for p in phrases(&m.content) {
+            *counts.entry(p.clone()).or_insert(0) += 1;
+            if seen.insert(p.clone()) {
+                ids.entry(p).or_default().push(m.id.clone());
+            }
+        }
+    }
+
+    // A pair is a topic once it recurs; its words then only count on their own
+    let pairs: Vec<(&String, usize)> = counts
+        .iter()
+        .filter(|(p, n)| p.contains(' ') && **n >= 2)
+        .map(|(p, n)| (p, *n))
+        .collect();
+    let mut inside: HashMap<&str, usize> = HashMap::new();
+    for (pair, n) in &pairs {
+        for w in pair.split(' ') {
+            *inside.entry(w).or_insert(0) += n;
+        }
+    }
+    let mut topics: Vec<Topic> = counts
+        .iter()
+        .filter(|(p, n)| {
+            if p.contains(' ') {
+                **n >= 2
+            } else {
+                **n > inside.get(p.as_str()).copied().unwrap_or(0)
+            }
+        })
+        .map(|(p, n)| Topic {
+            phrase: p.clone(),
+            count: *n,
+            weight: 0.0,
+            message_ids: ids.get(p).cloned().unwrap_or_default(),
+        })
+        .collect();
+    topics.sort_by(|a, b| {
+        b.message_ids
+            .len()
+            .cmp(&a.message_ids.len())
+            .then(b.count.cmp(&a.count))
+            // The more specific phrase first
+            .then(b.phrase.contains(' ').cmp(&a.phrase.contains(' ')))
+            .then(a.phrase.cmp(&b.phrase))
+    });
+    topics.truncate(limit);
+    let top = topics.first().map_or(1, |t| t.count).max(1) as f32;
+    for t in &mut topics {
+        t.weight = t.count as f32 / top;
+    }
+    topics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_topics_rank_recurring_phrases() {
+        let messages = vec![
+            Message::new(MessageRole::System, "Pricing pricing pricing".to_string()),
+            Message::new(
+                MessageRole::User,
+                "What does the refund policy say about annual plans?".to_string(),
+            ),
+            Message::new(
+                MessageRole::Assistant,
+                "The refund policy allows refunds on annual plans within 30 days.".to_string(),
+            ),
+            Message::new(
+                MessageRole::User,
+                "Thanks! Does the refund policy cover monthly plans?".to_string(),
+            ),
+        ];
+        let topics = extract_topics(&messages, MAX_TOPICS);
+        let phrases: Vec<&str> = topics.iter().map(|t| t.phrase.as_str()).collect();
+
+        assert_eq!(phrases[0], "refund policy");
+        assert_eq!(topics[0].message_ids.len(), 3);
+        assert_eq!(topics[0].weight, 1.0);
+        assert!(phrases.contains(&"plans"));
+        assert!(phrases.contains(&"annual plans"));
+        // Words seen only inside a recurring pair, filler and system text are left out
+        assert!(!phrases.contains(&"policy"));
+        assert!(!phrases.contains(&"thanks"));
+        assert!(!phrases.contains(&"pricing"));
+
+        assert!(mentions(&messages[3], "refund policy"));
+        assert!(!mentions(&messages[3], "annual plans"));
+    }
+}
@@ -0,0 +1,78 @@
+use super::topics::{extract_topics, Topic, MAX_TOPICS};
+use crate::models::Message;
+use leptos::prelude::*;
+
+// Font size range of the cloud, in rem
+const MIN_FONT_REM: f32 = 0.75;
+const MAX_FONT_REM: f32 = 1.5;
+
+/// Word cloud of the conversation's topics, largest the most mentioned. Clicking a
+/// topic selects it so the transcript can be filtered to the messages mentioning it;
+/// clicking it again clears the selection.
+#[component]
+pub fn TopicsPanel(
+    #[prop(into)] messages: Signal<Vec<Message>>,
+    selected: RwSignal<Option<String>>,
+    on_close: Callback<()>,
+) -> impl IntoView {
+    let topics = Memo::new(move |_| messages.with(|m| extract_topics(m, MAX_TOPICS)));
+
+    view! {
+        <div class="px-4 py-3 border-b border-base-300 bg-base-100">
+            <div class="flex items-center justify-between gap-2 mb-2">
+                <div class="flex items-center gap-2">
+                    <i data-lucide="cloud" class="h-4 w-4"></i>
+                    <span class="text-sm font-semibold">"Topics"</span>
+                    <span class="text-xs opacity-60">"Click a topic to show the messages mentioning it"</span>
+                </div>
+                <button class="btn btn-ghost btn-xs" on:click=move |_| on_close.run(())>
+                    "Close"
+                </button>
+            </div>
+            <Show
+                when=move || topics.with(|t| !t.is_empty())
+                fallback=|| view! { <p class="text-xs opacity-60">"No topics yet: the conversation is too short."</p> }
+            >
+                <div class="flex flex-wrap items-baseline gap-x-3 gap-y-1 max-h-40 overflow-auto">
+                    <For each=move || topics.get() key=|t| t.phrase.clone() let:topic>
+                        {
+                            let Topic { phrase, count, weight, message_ids } = topic;
+                            let font = MIN_FONT_REM + (MAX_FONT_REM - MIN_FONT_REM) * weight;
+                            let phrase_for_class = phrase.clone();
+                            let phrase_for_click = phrase.clone();
+                            view! {
+                                <button
+                                    class=move || {
+                                        if selected.with(|s| s.as_deref() == Some(phrase_for_class.as_str())) {
+                                            "link link-primary font-semibold no-underline"
+                                        } else {
+                                            "link link-hover opacity-80 hover:opacity-100"
+                                        }
+                                    }
+                                    style=format!("font-size: {:.2}rem", font)
+                                    title=format!(
+                                        "Mentioned {} time(s) in {} message(s)",
+                                        count,
+                                        message_ids.len()
+                                    )
+                                    on:click=move |_| {
+                                        let phrase = phrase_for_click.clone();
+                                        selected.update(|s| {
+                                            *s = if s.as_deref() == Some(phrase.as_str()) {
+                                                None
+                                            } else {
+                                                Some(phrase)
+                                            };
+                                        });
+                                    }
+                                >
+                                    {phrase.clone()}
+                                </button>
+                            }
+                        }
+                    </For>
+                </div>
+            </Show>
+        </div>
+    }
+}
@@ -0,0 +1,245 @@
+//! Sales-call summaries written back to a deal. A pasted or dictated transcript goes
+//! through a fixed template in JSON mode; the summary is stored as a call activity on
+//! the deal, next steps become follow-ups, and a close date or value the call settled
+//! on updates the deal itself.
+
+use crate::models::crm::{Activity, ActivityType, Deal, Priority};
+use crate::models::{Message, MessageRole, OutputFormat};
+use crate::utils::datetime::{resolve_relative_date, CivilDate};
+use crate::utils::json_output;
+use serde::Deserialize;
+
+// Characters of the transcript sent to the model
+const TRANSCRIPT_CHARS: usize = 12_000;
+
+/// Schema of the template's reply
+pub const CALL_SUMMARY_SCHEMA: &str = r#"{"type": "object", "properties": {"summary": {"type": "string"}, "sentiment": {"type": "string", "enum": ["positive", "neutral", "negative"]}, "objections": {"type": "array", "items": {"type": "string"}}, "next_steps": {"type": "array", "items": {"type": "object", "properties": {"action": {"type": "string"}, "due": {"type": ["string", "null"]}, "owner": {"type": ["string", "null"]}}, "required": ["action"]}}, "close_date": {"type": ["string", "null"]}, "deal_value": {"type": ["number", "null"]}}, "required": ["summary", "sentiment", "objections", "next_steps"]}"#;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CallSentiment {
+    Positive,
+    #[default]
+    Neutral,
+    Negative,
+}
+
+impl CallSentiment {
+    pub fn label(&self) -> &'static str {
+        match self {
+            CallSentiment::Positive => "Positive",
+            CallSentiment::Neutral => "Neutral",
+            CallSentiment::Negative => "Negative",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct NextStep {
+    pub action: String,
+    /// As said on the call: a date or a phrase such as "next Friday"
+    #[serde(default)]
+    pub due: Option<String>,
+    #[serde(default)]
+    pub owner: Option<String>,
+}
+
+/// The template's fields for one call
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct CallSummary {
+    pub summary: String,
+    #[serde(default)]
+    pub sentiment: CallSentiment,
+    #[serde(default)]
+    pub objections: Vec<String>,
+    #[serde(default)]
+    pub next_steps: Vec<NextStep>,
+    #[serde(default)]
+    pub close_date: Option<String>,
+    #[serde(default)]
+    pub deal_value: Option<f64>,
+}
+
+/// Reply format of the template
+pub fn output_format() -> OutputFormat {
+    OutputFormat::Json {
+        schema: Some(CALL_SUMMARY_SCHEMA.to_string()),
+    }
+}
+
+/// Messages running the template over `transcript`
+pub fn request(deal: &Deal, customer: Option<&str>, transcript: &str, today: &str) -> Vec<Message> {
+    let mut system = format!(
+        "You summarize sales calls for a CRM. Today is {}. Report only what was said on \
+         the call. Sentiment is the prospect's attitude toward buying. Next steps are \
+         commitments made on the call, with the due date as said. Set close_date (YYYY-MM-DD) \
+         and deal_value only when the call agreed on them; otherwise null.",
+        today
+    );
+    if let Some(line) = json_output::instruction(&output_format()) {
+        system.push('\n');
+        system.push_str(&line);
+    }
+    let mut prompt = format!("Deal: {}", deal.title);
+    if let Some(customer) = customer {
+        prompt.push_str(&format!("\nCustomer: {}", customer));
+    }
+    let transcript: String = transcript.trim().chars().take(TRANSCRIPT_CHARS).collect();
+    prompt.push_str(&format!("\n\nCall transcript:\n{}", transcript));
+    vec![
+        Message::new(MessageRole::System, system),
+        Message::new(MessageRole::User, prompt),
+    ]
+}
+
+/// Read the template's reply, checked against `CALL_SUMMARY_SCHEMA`
+pub fn parse(reply: &str) -> Result<CallSummary, String> {
+    let value = json_output::check(reply, &output_format())?;
+    serde_json::from_value(value).map_err(|e| format!("Unexpected call summary: {}", e))
+}
+
+/// Text of the call activity
+pub fn describe(summary: &CallSummary) -> String {
+    let mut out = format!(
+        "{}\n\nSentiment: {}",
+        summary.summary.trim(),
+        summary.sentiment.label()
+    );
+    if !summary.objections.is_empty() {
+        out.push_str("\n\nObjections:");
+        for o in &summary.objections {
+            out.push_str(&format!("\n- {}", o.trim()));
+        }
+    }
+    if !summary.next_steps.is_empty() {
+        out.push_str("\n\nNext steps:");
+        for s in &summary.next_steps {
+            out.push_str(&format!("\n- {}", s.action.trim()));
+            if let Some(due) = s.due.as_deref().filter(|d| !d.trim().is_empty()) {
+                out.push_str(&format!(" (due {})", due.trim()));
+            }
+        }
+    }
+    out
+}
+
+/// `deal` with the call written back: a completed call activity, a follow-up per next
+/// step, and the close date and value when the call settled them
+pub fn write_back(deal: &Deal, summary: &CallSummary, today: CivilDate, now: f64) -> Deal {
+    let mut updated = deal.clone();
+    let activity = |n: usize, activity_type, title: String| Activity {
+        id: format!("act_{}_{}", now, n),
+        activity_type,
+        title,
+        description: None,
+        due_date: None,
+        due_all_day: false,
+        completed_at: None,
+        assigned_to: None,
+        priority: Priority::Medium,
+        created_at: now,
+    };
+    updated.activities.push(Activity {
+        description: Some(describe(summary)),
+        completed_at: Some(now),
+        ..activity(0, ActivityType::Call, "Call summary".to_string())
+    });
+    for (i, step) in summary.next_steps.iter().enumerate() {
+        if step.action.trim().is_empty() {
+            continue;
+        }
+        updated.activities.push(Activity {
+            due_date: step
+                .due
+                .as_deref()
+                .and_then(|d| resolve_relative_date(d, today))
+                .map(CivilDate::timestamp_ms),
+            // Next steps are due on a day, not at a time
+            due_all_day: true,
+            assigned_to: step.owner.clone().filter(|o| !o.trim().is_empty()),
+            priority: if summary.sentiment == CallSentiment::Positive {
+                Priority::High
+            } else {
+                Priority::Medium
+            },
+            ..activity(
+                i + 1,
+                ActivityType::FollowUp,
+                step.action.trim().to_string(),
+            )
+        });
+    }
+    if let Some(close) = summary.close_date.as_deref().and_then(CivilDate::parse_iso) {
+        updated.expected_close_date = Some(close.timestamp_ms());
+    }
+    if let Some(value) = summary.deal_value.filter(|v| *v > 0.0) {
+        updated.value = value;
+    }
+    updated.updated_at = now;
+    updated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::crm::DealStatus;
+
+    #[test]
+    fn test_call_summary_writes_back_to_the_deal() {
+        let deal = Deal {
+            id: "d1".to_string(),
+            title: "Acme renewal".to_string(),
+            customer_id: "c1".to_string(),
+            stage_id: "s1".to_string(),
+            value: 1000.0,
+            currency: "USD".to_string(),
+            probability: 0.5,
+            expected_close_date: None,
+            actual_close_date: None,
+            status: DealStatus::Open,
+            owner: None,
+            created_at: 0.0,
+            updated_at: 0.0,
+            activities: Vec::new(),
+        };
+        let reply = r#"{"summary": "Acme wants to renew with SSO.", "sentiment": "positive",
+            "objections": ["Price per seat"],
+            "next_steps": [{"action": "Send SSO quote", "due": "next Friday", "owner": "Sam"},
+                           {"action": "Book security review", "due": null}],
+            "close_date": "2026-11-30", "deal_value": 12000}"#;
+        let summary = parse(reply).unwrap();
+        assert!(parse(
+            r#"{"summary": "x", "sentiment": "angry", "objections": [], "next_steps": []}"#
+        )
+        .is_err());
+
+        // A Friday
+        let today = CivilDate::parse_iso("2026-10-16").unwrap();
+        let updated = write_back(&deal, &summary, today, 5.0);
+        assert_eq!(updated.activities.len(), 3);
+        let call = &updated.activities[0];
+        assert_eq!(call.activity_type, ActivityType::Call);
+        assert!(call
+            .description
+            .as_deref()
+            .unwrap()
+            .contains("- Price per seat"));
+        assert_eq!(call.completed_at, Some(5.0));
+
+        let quote = &updated.activities[1];
+        assert_eq!(
+            quote.due_date,
+            Some(CivilDate::parse_iso("2026-10-23").unwrap().timestamp_ms())
+        );
+        assert!(quote.due_all_day);
+        assert_eq!(quote.assigned_to.as_deref(), Some("Sam"));
+        assert_eq!(updated.activities[2].due_date, None);
+        assert_ne!(quote.id, updated.activities[2].id);
+
+        assert_eq!(
+            updated.expected_close_date,
+            Some(CivilDate::parse_iso("2026-11-30").unwrap().timestamp_ms())
+        );
+        assert_eq!(updated.value, 12000.0);
+    }
+}
@@ -0,0 +1,300 @@
+//! Customer enrichment from the knowledge base. Indexed passages that mention the
+//! customer's name, company or email domain are sent to the loaded model in JSON mode;
+//! what it finds becomes field proposals the user reviews before anything is saved.
+
+use crate::models::crm::Customer;
+use crate::models::graphrag::DocumentIndex;
+use crate::models::{Message, MessageRole, OutputFormat};
+use crate::utils::json_output;
+use serde::Deserialize;
+
+/// Custom fields enrichment fills in
+pub const INDUSTRY_FIELD: &str = "industry";
+pub const PRODUCTS_FIELD: &str = "products";
+pub const CONTACTS_FIELD: &str = "key_contacts";
+
+/// Passages sent to the model, most mentions first
+pub const MAX_PASSAGES: usize = 6;
+const PASSAGE_CHARS: usize = 1500;
+
+// Email domains that say nothing about the customer's company
+const FREE_MAIL: &[&str] = &[
+    "gmail.com",
+    "googlemail.com",
+    "outlook.com",
+    "hotmail.com",
+    "live.com",
+    "yahoo.com",
+    "icloud.com",
+    "proton.me",
+    "protonmail.com",
+    "aol.com",
+];
+
+/// Schema the model's reply must follow
+pub const ENRICHMENT_SCHEMA: &str = r#"{"type": "object", "properties": {"industry": {"type": ["string", "null"]}, "products": {"type": "array", "items": {"type": "string"}}, "contacts": {"type": "array", "items": {"type": "object", "properties": {"name": {"type": "string"}, "role": {"type": ["string", "null"]}, "email": {"type": ["string", "null"]}}, "required": ["name"]}}}, "required": ["industry", "products", "contacts"]}"#;
+
+/// A passage mentioning the customer
+#[derive(Clone, Debug, PartialEq)]
+pub struct Passage {
+    /// Uploaded file the passage comes from
+    pub source: String,
+    pub text: String,
+    pub mentions: usize,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+pub struct KeyContact {
+    pub name: String,
+    #[serde(default)]
+    pub role: Option<String>,
+    #[serde(default)]
+    pub email: Option<String>,
+}
+
+impl KeyContact {
+    fn label(&self) -> String {
+        let mut label = self.name.trim().to_string();
+        if let Some(role) = self.role.as_deref().filter(|r| !r.trim().is_empty()) {
+            label.push_str(&format!(" ({})", role.trim()));
+        }
+        if let Some(email) = self.email.as_deref().filter(|e| !e.trim().is_empty()) {
+            label.push_str(&format!(" <{}>", email.trim()));
+        }
+        label
+    }
+}
+
+/// What the model found about a customer
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+pub struct Enrichment {
+    #[serde(default)]
+    pub industry: Option<String>,
+    #[serde(default)]
+    pub products: Vec<String>,
+    #[serde(default)]
+    pub contacts: Vec<KeyContact>,
+}
+
+/// A custom field value proposed for review
+#[derive(Clone, Debug, PartialEq)]
+pub struct FieldProposal {
+    pub field: &'static str,
+    pub label: &'static str,
+    pub current: Option<String>,
+    pub proposed: String,
+}
+
+/// Lowercased terms a passage about the customer would contain: name, company and
+/// the company email domain
+pub fn search_terms(customer: &Customer) -> Vec<String> {
+    let domain = customer
+        .email
+        .as_deref()
+        .and_then(|e| e.rsplit_once('@'))
+        .map(|(_, d)| d.trim().to_lowercase())
+        .filter(|d| !d.is_empty() && !FREE_MAIL.contains(&d.as_str()));
+    let mut terms: Vec<String> = [Some(customer.name.clone()), customer.company.clone()]
+        .into_iter()
+        .flatten()
+        .map(|t| t.trim().to_lowercase())
+        .chain(domain)
+        .filter(|t| t.chars().count() >= 3)
+        .collect();
+    terms.sort();
+    terms.dedup();
+    terms
+}
+
+/// Indexed passages mentioning any of `terms`, most mentions first
+pub fn find_passages(docs: &[DocumentIndex], terms: &[String], limit: usize) -> Vec<Passage> {
+    let mut found: Vec<Passage> = docs
+        .iter()
+        .filter_map(|d| {
+            let text = d.content.to_lowercase();
+            let mentions: usize = terms.iter().map(|t| text.matches(t.as_str()).count()).sum();
+            (mentions > 0).then(|| Passage {
+                source: d.source_file().to_string(),
+                text: d.content.chars().take(PASSAGE_CHARS).collect(),
+                mentions,
+            })
+        })
+        .collect();
+    found.sort_by(|a, b| b.mentions.cmp(&a.mentions).then(a.source.cmp(&b.source)));
+    found.truncate(limit);
+    found
+}
+
+/// Reply format of enrichment requests
+pub fn output_format() -> OutputFormat {
+    OutputFormat::Json {
+        schema: Some(ENRICHMENT_SCHEMA.to_string()),
+    }
+}
+
+/// Messages asking the model what the passages say about the customer
+pub fn request(customer: &Customer, passages: &[Passage]) -> Vec<Message> {
+    let mut system = "You fill in CRM records from company documents. Use only facts stated \
+                      in the passages; leave a field null or empty when they do not say."
+        .to_string();
+    if let Some(line) = json_output::instruction(&output_format()) {
+        system.push('\n');
+        system.push_str(&line);
+    }
+    let mut prompt = format!("Customer: {}", customer.name);
+    if let Some(company) = &customer.company {
+        prompt.push_str(&format!("\nCompany: {}", company));
+    }
+    prompt.push_str(
+        "\n\nFrom the passages below, find the customer's industry, the products or \
+         services they are mentioned with, and their key contacts.",
+    );
+    for (i, p) in passages.iter().enumerate() {
+        prompt.push_str(&format!("\n\n[{}] {}\n{}", i + 1, p.source, p.text));
+    }
+    vec![
+        Message::new(MessageRole::System, system),
+        Message::new(MessageRole::User, prompt),
+    ]
+}
+
+/// Read the model's reply, checked against `ENRICHMENT_SCHEMA`
+pub fn parse(reply: &str) -> Result<Enrichment, String> {
+    let value = json_output::check(reply, &output_format())?;
+    serde_json::from_value(value).map_err(|e| format!("Unexpected enrichment reply: {}", e))
+}
+
+/// Field values the enrichment would change, leaving out empty and unchanged ones
+pub fn proposals(customer: &Customer, enrichment: &Enrichment) -> Vec<FieldProposal> {
+    let join = |items: Vec<String>| {
+        let mut seen: Vec<String> = Vec::new();
+        for item in items {
+            let item = item.trim().to_string();
+            if !item.is_empty() && !seen.iter().any(|s| s.eq_ignore_ascii_case(&item)) {
+                seen.push(item);
+            }
+        }
+        seen.join(", ")
+    };
+    let values = [
+        (
+            INDUSTRY_FIELD,
+            "Industry",
+            enrichment
+                .industry
+                .as_deref()
+                .map(str::trim)
+                .unwrap_or_default()
+                .to_string(),
+        ),
+        (
+            PRODUCTS_FIELD,
+            "Products",
+            join(enrichment.products.clone()),
+        ),
+        (
+            CONTACTS_FIELD,
+            "Key contacts",
+            join(enrichment.contacts.iter().map(KeyContact::label).collect()),
+        ),
+    ];
+    values
+        .into_iter()
+        .filter(|(field, _, proposed)| {
+            !proposed.is_empty() && customer.custom_fields.get(*field) != Some(proposed)
+        })
+        .map(|(field, label, proposed)| FieldProposal {
+            field,
+            label,
+            current: customer.custom_fields.get(field).cloned(),
+            proposed,
+        })
+        .collect()
+}
+
+/// `customer` with the accepted proposals saved to its custom fields
+pub fn apply(customer: &Customer, accepted: &[FieldProposal], now: f64) -> Customer {
+    let mut updated = customer.clone();
+    for p in accepted {
+        updated
+            .custom_fields
+            .insert(p.field.to_string(), p.proposed.clone());
+    }
+    if !accepted.is_empty() {
+        updated.updated_at = now;
+    }
+    updated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::crm::CustomerStatus;
+    use crate::models::graphrag::ProcessingStatus;
+
+    fn doc(id: &str, content: &str) -> DocumentIndex {
+        DocumentIndex {
+            id: id.to_string(),
+            title: id.to_string(),
+            content: content.to_string(),
+            file_type: "text".to_string(),
+            size_bytes: 0,
+            created_at: 0.0,
+            indexed_at: 0.0,
+            node_count: 0,
+            embedding_model: None,
+            processing_status: ProcessingStatus::Pending,
+            symbols: Vec::new(),
+            parent: None,
+        }
+    }
+
+    #[test]
+    fn test_enrichment_finds_passages_and_proposes_changes() {
+        let customer = Customer {
+            id: "c1".to_string(),
+            name: "Acme".to_string(),
+            email: Some("jane@acme.io".to_string()),
+            phone: None,
+            company: None,
+            status: CustomerStatus::Active,
+            created_at: 0.0,
+            updated_at: 0.0,
+            tags: Vec::new(),
+            // The industry is already on record
+            custom_fields: [(INDUSTRY_FIELD.to_string(), "Logistics".to_string())].into(),
+        };
+        assert_eq!(search_terms(&customer), vec!["acme", "acme.io"]);
+
+        let docs = vec![
+            doc(
+                "1:notes.md",
+                "Call with ACME about Routing Pro. See acme.io.",
+            ),
+            doc("1:faq.md", "Nothing relevant here."),
+            doc("1:deals.md#chunk=2", "Acme renewed."),
+        ];
+        let passages = find_passages(&docs, &search_terms(&customer), MAX_PASSAGES);
+        let sources: Vec<&str> = passages.iter().map(|p| p.source.as_str()).collect();
+        assert_eq!(sources, vec!["notes.md", "deals.md"]);
+
+        let reply = r#"```json
+        {"industry": "Logistics", "products": ["Routing Pro", "routing pro"],
+         "contacts": [{"name": "Jane Doe", "role": "CTO", "email": "jane@acme.io"}]}
+        ```"#;
+        let found = parse(reply).unwrap();
+        let proposed = proposals(&customer, &found);
+        assert_eq!(proposed.len(), 2);
+        assert_eq!(proposed[0].proposed, "Routing Pro");
+        assert_eq!(proposed[1].proposed, "Jane Doe (CTO) <jane@acme.io>");
+        assert!(parse(r#"{"industry": 3}"#).is_err());
+
+        let saved = apply(&customer, &proposed[1..], 42.0);
+        assert_eq!(
+            saved.custom_fields.get(CONTACTS_FIELD).map(String::as_str),
+            Some("Jane Doe (CTO) <jane@acme.io>")
+        );
+        assert!(!saved.custom_fields.contains_key(PRODUCTS_FIELD));
+        assert_eq!(saved.updated_at, 42.0);
+    }
+}
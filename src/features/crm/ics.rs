@@ -0,0 +1,276 @@
+//! iCalendar (RFC 5545) export for CRM activities, deal close dates and tasks.
+//!
+//! Timed events are written in UTC (`...Z`) so calendar clients convert them to the
+//! viewer's local zone. Date-only values (stored as UTC midnight: task due dates, close
+//! dates and activities flagged `due_all_day`) are written as all-day `VALUE=DATE`
+//! events so they never drift across a day boundary.
+
+use crate::models::crm::{ActivityType, Deal, DealStatus};
+use crate::models::tasks::Task;
+use crate::utils::datetime::CivilDate;
+
+const MS_PER_DAY: i64 = 86_400_000;
+const DEFAULT_EVENT_MINUTES: i64 = 30;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum IcsTime {
+    /// Calendar date (UTC day of the timestamp)
+    AllDay(f64),
+    /// Exact instant, exported in UTC
+    At(f64),
+}
+
+impl IcsTime {
+    /// A timestamp whose kind is known: a calendar day (UTC midnight) or an instant.
+    /// Never inferred from the time, a meeting can start at UTC midnight.
+    pub fn new(ms: f64, all_day: bool) -> Self {
+        if all_day {
+            IcsTime::AllDay(ms)
+        } else {
+            IcsTime::At(ms)
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct IcsEvent {
+    pub uid: String,
+    pub summary: String,
+    pub description: Option<String>,
+    pub start: IcsTime,
+}
+
+impl IcsEvent {
+    pub fn new(uid: impl Into<String>, summary: impl Into<String>, start: IcsTime) -> Self {
+        Self {
+            uid: uid.into(),
+            summary: summary.into(),
+            description: None,
+            start,
+        }
+    }
+
+    pub fn with_description(mut self, description: Option<String>) -> Self {
+        self.description = description.filter(|d| !d.trim().is_empty());
+        self
+    }
+}
+
+/// Expected close dates of open deals plus their pending activities
+pub fn events_from_deals(deals: &[Deal]) -> Vec<IcsEvent> {
+    let mut out = Vec::new();
+    for deal in deals {
+        if let Some(close) = deal.expected_close_date {
+            if deal.status == DealStatus::Open {
+                out.push(deal_close_event(deal, close));
+            }
+        }
+        for activity in &deal.activities {
+            let (Some(due), None) = (activity.due_date, activity.completed_at) else {
+                continue;
+            };
+            let kind = match activity.activity_type {
+                ActivityType::Call => "Call",
+                ActivityType::Email => "Email",
+                ActivityType::Meeting => "Meeting",
+                ActivityType::Task => "Task",
+                ActivityType::FollowUp => "Follow-up",
+            };
+            out.push(
+                IcsEvent::new(
+                    format!("{}@crm.activity", activity.id),
+                    format!("{}: {} ({})", kind, activity.title, deal.title),
+                    IcsTime::new(due, activity.due_all_day),
+                )
+                .with_description(activity.description.clone()),
+            );
+        }
+    }
+    out
+}
+
+/// Single "expected close" event for a deal
+pub fn deal_close_event(deal: &Deal, close: f64) -> IcsEvent {
+    IcsEvent::new(
+        format!("{}@crm.deal", deal.id),
+        format!("Expected close: {}", deal.title),
+        IcsTime::AllDay(close),
+    )
+    .with_description(Some(format!(
+        "{:.2} {} at {:.0}% probability",
+        deal.value,
+        deal.currency,
+        deal.probability * 100.0
+    )))
+}
+
+/// Open tasks that have a due date
+pub fn events_from_tasks(tasks: &[Task]) -> Vec<IcsEvent> {
+    tasks
+        .iter()
+        .filter(|t| !t.done)
+        .filter_map(|t| t.due_date.map(|due| task_event(t, due)))
+        .collect()
+}
+
+/// Task due dates are calendar days
+pub fn task_event(task: &Task, due: f64) -> IcsEvent {
+    IcsEvent::new(
+        format!("{}@tasks", task.id),
+        task.title.clone(),
+        IcsTime::AllDay(due),
+    )
+}
+
+/// Serialize events into a VCALENDAR document (CRLF line endings, folded lines)
+pub fn build_calendar(events: &[IcsEvent], dtstamp_ms: f64) -> String {
+    let mut lines: Vec<String> = vec![
+        "BEGIN:VCALENDAR".into(),
+        "VERSION:2.0".into(),
+        "PRODID:-//wasm-knowledge-chatbot-rs//CRM//EN".into(),
+        "CALSCALE:GREGORIAN".into(),
+        "METHOD:PUBLISH".into(),
+    ];
+    let stamp = format_utc_datetime(dtstamp_ms);
+    for ev in events {
+        lines.push("BEGIN:VEVENT".into());
+        lines.push(format!("UID:{}", escape_text(&ev.uid)));
+        lines.push(format!("DTSTAMP:{}", stamp));
+        match ev.start {
+            IcsTime::AllDay(ms) => {
+                lines.push(format!("DTSTART;VALUE=DATE:{}", format_date(ms)));
+                lines.push(format!(
+                    "DTEND;VALUE=DATE:{}",
+                    format_date(ms + MS_PER_DAY as f64)
+                ));
+            }
+            IcsTime::At(ms) => {
+                lines.push(format!("DTSTART:{}", format_utc_datetime(ms)));
+                lines.push(format!(
+                    "DTEND:{}",
+                    format_utc_datetime(ms + (DEFAULT_EVENT_MINUTES * 60_000) as f64)
+                ));
+            }
+        }
+        lines.push(format!("SUMMARY:{}", escape_text(&ev.summary)));
+        if let Some(desc) = &ev.description {
+            lines.push(format!("DESCRIPTION:{}", escape_text(desc)));
+        }
+        lines.push("END:VEVENT".into());
+    }
+    lines.push("END:VCALENDAR".into());
+
+    let mut out = String::new();
+    for line in lines {
+        out.push_str(&fold_line(&line));
+        out.push_str("\r\n");
+    }
+    out
+}
+
+/// Trigger a browser download of the calendar
+pub fn download_calendar(
+    filename: &str,
+    events: &[IcsEvent],
+) -> Result<(), crate::models::app::AppError> {
+    let ics = build_calendar(events, js_sys::Date::now());
+    crate::utils::download::DownloadUtils::download_text(filename, &ics)
+}
+
+fn escape_text(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            ';' => out.push_str("\\;"),
+            ',' => out.push_str("\\,"),
+            '\n' => out.push_str("\\n"),
+            '\r' => {}
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+// Lines longer than 75 octets continue on the next line prefixed by a space
+fn fold_line(line: &str) -> String {
+    let mut out = String::with_capacity(line.len() + 8);
+    let mut width = 0;
+    for c in line.chars() {
+        let len = c.len_utf8();
+        if width + len > 75 {
+            out.push_str("\r\n ");
+            width = 1;
+        }
+        out.push(c);
+        width += len;
+    }
+    out
+}
+
+fn split_utc(ms: f64) -> (i64, u32, u32, i64) {
+    let ms = ms as i64;
+    let days = ms.div_euclid(MS_PER_DAY);
+    let secs_of_day = ms.rem_euclid(MS_PER_DAY) / 1000;
+    let date = CivilDate::from_days(days);
+    (date.year, date.month, date.day, secs_of_day)
+}
+
+fn format_date(ms: f64) -> String {
+    let (y, m, d, _) = split_utc(ms);
+    format!("{:04}{:02}{:02}", y, m, d)
+}
+
+fn format_utc_datetime(ms: f64) -> String {
+    let (y, m, d, s) = split_utc(ms);
+    format!(
+        "{:04}{:02}{:02}T{:02}{:02}{:02}Z",
+        y,
+        m,
+        d,
+        s / 3600,
+        (s % 3600) / 60,
+        s % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_date_formatting() {
+        assert_eq!(format_date(0.0), "19700101");
+        assert_eq!(format_date(1_709_251_200_000.0), "20240301");
+        assert_eq!(format_utc_datetime(1_709_294_400_000.0), "20240301T120000Z");
+    }
+
+    #[test]
+    fn test_all_day_follows_the_flag() {
+        assert_eq!(
+            IcsTime::new(1_709_251_200_000.0, true),
+            IcsTime::AllDay(1_709_251_200_000.0)
+        );
+        // A meeting that happens to start at UTC midnight stays a timed event
+        assert_eq!(
+            IcsTime::new(1_709_251_200_000.0, false),
+            IcsTime::At(1_709_251_200_000.0)
+        );
+    }
+
+    #[test]
+    fn test_build_calendar_escapes_and_folds() {
+        let ev = IcsEvent::new(
+            "t1@tasks",
+            "Call Bob; bring notes, slides",
+            IcsTime::AllDay(0.0),
+        )
+        .with_description(Some("x".repeat(100)));
+        let ics = build_calendar(&[ev], 0.0);
+        assert!(ics.starts_with("BEGIN:VCALENDAR\r\n"));
+        assert!(ics.contains("SUMMARY:Call Bob\\; bring notes\\, slides\r\n"));
+        assert!(ics.contains("DTSTART;VALUE=DATE:19700101\r\nDTEND;VALUE=DATE:19700102\r\n"));
+        assert!(ics.lines().all(|l| l.len() <= 75));
+        assert!(ics.ends_with("END:VCALENDAR\r\n"));
+    }
+}
@@ -1,3 +1,9 @@
+pub mod call_summary;
+pub mod enrichment;
+pub mod ics;
+pub mod ownership;
+pub mod swimlanes;
+pub mod table_import;
 pub mod ui;
 
 pub use ui::CRMPanel;
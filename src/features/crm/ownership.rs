@@ -0,0 +1,135 @@
+//! Owner assignment helpers for leads and deals.
+
+use crate::models::crm::{Deal, DealStatus};
+
+pub const UNASSIGNED_LABEL: &str = "Unassigned";
+
+/// Owner filter shared by the CRM list views and the pipeline board
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum OwnerFilter {
+    #[default]
+    All,
+    Unassigned,
+    Owner(String),
+}
+
+impl OwnerFilter {
+    pub fn matches(&self, owner: Option<&str>) -> bool {
+        let owner = owner.map(str::trim).filter(|o| !o.is_empty());
+        match self {
+            OwnerFilter::All => true,
+            OwnerFilter::Unassigned => owner.is_none(),
+            OwnerFilter::Owner(name) => owner.is_some_and(|o| o.eq_ignore_ascii_case(name)),
+        }
+    }
+
+    /// Encode for a `<select>` value
+    pub fn to_key(&self) -> String {
+        match self {
+            OwnerFilter::All => String::new(),
+            OwnerFilter::Unassigned => "__unassigned".to_string(),
+            OwnerFilter::Owner(name) => name.clone(),
+        }
+    }
+
+    pub fn from_key(key: &str) -> Self {
+        match key {
+            "" => OwnerFilter::All,
+            "__unassigned" => OwnerFilter::Unassigned,
+            name => OwnerFilter::Owner(name.to_string()),
+        }
+    }
+}
+
+/// Normalise a free-text owner name; blank means unassigned
+pub fn normalize_owner(input: &str) -> Option<String> {
+    let name = input.split_whitespace().collect::<Vec<_>>().join(" ");
+    if name.is_empty() {
+        None
+    } else {
+        Some(name)
+    }
+}
+
+/// Open pipeline totals for one owner
+#[derive(Clone, Debug, PartialEq)]
+pub struct OwnerTotals {
+    pub owner: Option<String>,
+    pub deal_count: usize,
+    pub total_value: f64,
+    pub weighted_value: f64,
+}
+
+impl OwnerTotals {
+    pub fn label(&self) -> &str {
+        self.owner.as_deref().unwrap_or(UNASSIGNED_LABEL)
+    }
+}
+
+/// Per-owner totals over open deals, largest pipeline first, unassigned last
+pub fn pipeline_totals_by_owner(deals: &[Deal]) -> Vec<OwnerTotals> {
+    let mut totals: Vec<OwnerTotals> = Vec::new();
+    for deal in deals.iter().filter(|d| d.status == DealStatus::Open) {
+        let owner = deal.owner.as_deref().and_then(normalize_owner);
+        let idx = match totals.iter().position(|t| {
+            t.owner.as_deref().map(str::to_lowercase) == owner.as_deref().map(str::to_lowercase)
+        }) {
+            Some(i) => i,
+            None => {
+                totals.push(OwnerTotals {
+                    owner,
+                    deal_count: 0,
+                    total_value: 0.0,
+                    weighted_value: 0.0,
+                });
+                totals.len() - 1
+            }
+        };
+        let t = &mut totals[idx];
+        t.deal_count += 1;
+        t.total_value += deal.value;
+        t.weighted_value += deal.value * deal.probability as f64;
+    }
+    totals.sort_by(|a, b| {
+        a.owner.is_none().cmp(&b.owner.is_none()).then(
+            b.total_value
+                .partial_cmp(&a.total_value)
+                .unwrap_or(std::cmp::Ordering::Equal),
+        )
+    });
+    totals
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_owner_filter_matches() {
+        assert!(OwnerFilter::All.matches(None));
+        assert!(OwnerFilter::Unassigned.matches(Some("  ")));
+        assert!(!OwnerFilter::Unassigned.matches(Some("Ana")));
+        assert!(OwnerFilter::Owner("ana".into()).matches(Some("Ana")));
+        assert!(!OwnerFilter::Owner("ana".into()).matches(None));
+    }
+
+    #[test]
+    fn test_owner_filter_key_roundtrip() {
+        for f in [
+            OwnerFilter::All,
+            OwnerFilter::Unassigned,
+            OwnerFilter::Owner("Bo".into()),
+        ] {
+            assert_eq!(OwnerFilter::from_key(&f.to_key()), f);
+        }
+    }
+
+    #[test]
+    fn test_normalize_owner() {
+        assert_eq!(
+            normalize_owner("  Ana   Lima "),
+            Some("Ana Lima".to_string())
+        );
+        assert_eq!(normalize_owner("   "), None);
+    }
+}
@@ -0,0 +1,159 @@
+//! Swimlane grouping for the pipeline board.
+
+use crate::features::crm::ownership::{normalize_owner, UNASSIGNED_LABEL};
+use crate::models::crm::{Customer, Deal};
+
+/// Secondary grouping axis rendered as rows across the stage columns
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SwimlaneAxis {
+    #[default]
+    None,
+    Owner,
+    Customer,
+    DealSize,
+}
+
+impl SwimlaneAxis {
+    pub const ALL: [SwimlaneAxis; 4] = [
+        SwimlaneAxis::None,
+        SwimlaneAxis::Owner,
+        SwimlaneAxis::Customer,
+        SwimlaneAxis::DealSize,
+    ];
+
+    pub fn key(&self) -> &'static str {
+        match self {
+            SwimlaneAxis::None => "none",
+            SwimlaneAxis::Owner => "owner",
+            SwimlaneAxis::Customer => "customer",
+            SwimlaneAxis::DealSize => "size",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            SwimlaneAxis::None => "No swimlanes",
+            SwimlaneAxis::Owner => "By owner",
+            SwimlaneAxis::Customer => "By customer",
+            SwimlaneAxis::DealSize => "By deal size",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Self {
+        Self::ALL
+            .into_iter()
+            .find(|a| a.key() == key)
+            .unwrap_or_default()
+    }
+}
+
+// (upper bound exclusive, label); last bucket is open-ended
+const SIZE_BUCKETS: [(f64, &str); 4] = [
+    (1_000.0, "< 1k"),
+    (10_000.0, "1k – 10k"),
+    (100_000.0, "10k – 100k"),
+    (f64::INFINITY, "≥ 100k"),
+];
+
+/// Index and label of the size bucket a deal value falls into
+pub fn size_bucket(value: f64) -> (usize, &'static str) {
+    SIZE_BUCKETS
+        .iter()
+        .enumerate()
+        .find(|(_, (upper, _))| value < *upper)
+        .map(|(i, (_, label))| (i, *label))
+        .unwrap_or((
+            SIZE_BUCKETS.len() - 1,
+            SIZE_BUCKETS[SIZE_BUCKETS.len() - 1].1,
+        ))
+}
+
+#[derive(Clone, Debug)]
+pub struct Swimlane {
+    pub key: String,
+    pub label: String,
+    pub deals: Vec<Deal>,
+    pub total_value: f64,
+    pub weighted_value: f64,
+}
+
+/// Group deals into lanes along `axis`; `None` yields a single unlabeled lane
+pub fn group_deals(deals: &[Deal], axis: SwimlaneAxis, customers: &[Customer]) -> Vec<Swimlane> {
+    // (sort rank, key, label)
+    let lane_of = |d: &Deal| -> (usize, String, String) {
+        match axis {
+            SwimlaneAxis::None => (0, "all".to_string(), String::new()),
+            SwimlaneAxis::Owner => match d.owner.as_deref().and_then(normalize_owner) {
+                Some(o) => (0, format!("owner:{}", o.to_lowercase()), o),
+                None => (1, "owner:".to_string(), UNASSIGNED_LABEL.to_string()),
+            },
+            SwimlaneAxis::Customer => {
+                let name = customers
+                    .iter()
+                    .find(|c| c.id == d.customer_id)
+                    .map(|c| c.name.clone())
+                    .unwrap_or_else(|| "Unknown customer".to_string());
+                (0, format!("customer:{}", d.customer_id), name)
+            }
+            SwimlaneAxis::DealSize => {
+                let (idx, label) = size_bucket(d.value);
+                (idx, format!("size:{}", idx), label.to_string())
+            }
+        }
+    };
+
+    let mut lanes: Vec<(usize, Swimlane)> = Vec::new();
+    for d in deals {
+        let (rank, key, label) = lane_of(d);
+        let pos = match lanes.iter().position(|(_, l)| l.key == key) {
+            Some(p) => p,
+            None => {
+                lanes.push((
+                    rank,
+                    Swimlane {
+                        key,
+                        label,
+                        deals: Vec::new(),
+                        total_value: 0.0,
+                        weighted_value: 0.0,
+                    },
+                ));
+                lanes.len() - 1
+            }
+        };
+        let lane = &mut lanes[pos].1;
+        lane.total_value += d.value;
+        lane.weighted_value += d.value * d.probability as f64;
+        lane.deals.push(d.clone());
+    }
+
+    lanes.sort_by(|(ra, a), (rb, b)| {
+        ra.cmp(rb).then_with(|| match axis {
+            SwimlaneAxis::DealSize => std::cmp::Ordering::Equal,
+            _ => a.label.to_lowercase().cmp(&b.label.to_lowercase()),
+        })
+    });
+    lanes.into_iter().map(|(_, l)| l).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_size_bucket() {
+        assert_eq!(size_bucket(0.0).0, 0);
+        assert_eq!(size_bucket(999.0).0, 0);
+        assert_eq!(size_bucket(1_000.0).0, 1);
+        assert_eq!(size_bucket(50_000.0).0, 2);
+        assert_eq!(size_bucket(1e9).0, 3);
+    }
+
+    #[test]
+    fn test_axis_key_roundtrip() {
+        for axis in SwimlaneAxis::ALL {
+            assert_eq!(SwimlaneAxis::from_key(axis.key()), axis);
+        }
+        assert_eq!(SwimlaneAxis::from_key("bogus"), SwimlaneAxis::None);
+    }
+}
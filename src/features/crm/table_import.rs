@@ -0,0 +1,241 @@
+//! Customers imported from a pasted table. Each column is mapped to a customer field,
+//! guessed from its header and adjustable before import; columns mapped to nothing
+//! else are kept as custom fields named after the header.
+
+use crate::models::crm::{Customer, CustomerStatus};
+use crate::utils::tables::Table;
+use std::collections::HashMap;
+
+/// Where a column's cells go on the imported customer
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ColumnTarget {
+    Skip,
+    Name,
+    Email,
+    Phone,
+    Company,
+    /// Comma or semicolon separated
+    Tags,
+    /// A custom field named after the column
+    Custom,
+}
+
+impl ColumnTarget {
+    pub const ALL: [ColumnTarget; 7] = [
+        ColumnTarget::Name,
+        ColumnTarget::Email,
+        ColumnTarget::Phone,
+        ColumnTarget::Company,
+        ColumnTarget::Tags,
+        ColumnTarget::Custom,
+        ColumnTarget::Skip,
+    ];
+
+    pub fn id(self) -> &'static str {
+        match self {
+            ColumnTarget::Skip => "skip",
+            ColumnTarget::Name => "name",
+            ColumnTarget::Email => "email",
+            ColumnTarget::Phone => "phone",
+            ColumnTarget::Company => "company",
+            ColumnTarget::Tags => "tags",
+            ColumnTarget::Custom => "custom",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ColumnTarget::Skip => "Skip",
+            ColumnTarget::Name => "Name",
+            ColumnTarget::Email => "Email",
+            ColumnTarget::Phone => "Phone",
+            ColumnTarget::Company => "Company",
+            ColumnTarget::Tags => "Tags",
+            ColumnTarget::Custom => "Custom field",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|t| t.id() == id)
+    }
+
+    /// Target suggested by a column header
+    fn guess(header: &str) -> Self {
+        let h = header.trim().to_lowercase();
+        let has = |words: &[&str]| words.iter().any(|w| h.contains(w));
+        if has(&["e-mail", "email", "mail"]) {
+            ColumnTarget::Email
+        } else if has(&["phone", "mobile", "tel"]) {
+            ColumnTarget::Phone
+        } else if has(&[
+            "company",
+            "organization",
+            "organisation",
+            "account",
+            "employer",
+        ]) {
+            ColumnTarget::Company
+        } else if has(&["tag", "label", "segment"]) {
+            ColumnTarget::Tags
+        } else if has(&["name", "contact", "customer", "client"]) {
+            ColumnTarget::Name
+        } else if h.is_empty() {
+            ColumnTarget::Skip
+        } else {
+            ColumnTarget::Custom
+        }
+    }
+}
+
+/// Suggested target per column. Only the first column guessed for a single-valued
+/// field keeps it; with no name column, the first column is taken as the name.
+pub fn guess_mapping(headers: &[String]) -> Vec<ColumnTarget> {
+    let mut mapping: Vec<ColumnTarget> = Vec::new();
+    for header in headers {
+        let target = ColumnTarget::guess(header);
+        let single = !matches!(
+            target,
+            ColumnTarget::Tags | ColumnTarget::Custom | ColumnTarget::Skip
+        );
+        mapping.push(if single && mapping.contains(&target) {
+            ColumnTarget::Custom
+        } else {
+            target
+        });
+    }
+    if !mapping.contains(&ColumnTarget::Name) {
+        if let Some(first) = mapping.first_mut() {
+            *first = ColumnTarget::Name;
+        }
+    }
+    mapping
+}
+
+/// Result of mapping a table onto customers
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CustomerImport {
+    pub customers: Vec<Customer>,
+    /// Rows left out: no name, or the email or name of a known customer
+    pub skipped: usize,
+}
+
+/// Customers from the rows of `table`, mapped column by column
+pub fn to_customers(
+    table: &Table,
+    mapping: &[ColumnTarget],
+    existing: &[Customer],
+    now: f64,
+) -> CustomerImport {
+    let mut seen: Vec<String> = existing
+        .iter()
+        .flat_map(|c| [Some(c.name.clone()), c.email.clone()])
+        .flatten()
+        .map(|k| k.trim().to_lowercase())
+        .collect();
+    let mut out = CustomerImport::default();
+    for (i, row) in table.rows.iter().enumerate() {
+        let mut customer = Customer {
+            id: format!("cust_{}_{}", now, i),
+            name: String::new(),
+            email: None,
+            phone: None,
+            company: None,
+            status: CustomerStatus::Prospect,
+            created_at: now,
+            updated_at: now,
+            tags: Vec::new(),
+            custom_fields: HashMap::new(),
+        };
+        for ((cell, header), target) in row.iter().zip(&table.headers).zip(mapping) {
+            let cell = cell.trim();
+            if cell.is_empty() {
+                continue;
+            }
+            match target {
+                ColumnTarget::Skip => {}
+                ColumnTarget::Name => customer.name = cell.to_string(),
+                ColumnTarget::Email => customer.email = Some(cell.to_string()),
+                ColumnTarget::Phone => customer.phone = Some(cell.to_string()),
+                ColumnTarget::Company => customer.company = Some(cell.to_string()),
+                ColumnTarget::Tags => customer.tags.extend(
+                    cell.split([',', ';'])
+                        .map(str::trim)
+                        .filter(|t| !t.is_empty())
+                        .map(String::from),
+                ),
+                ColumnTarget::Custom => {
+                    customer.custom_fields.insert(
+                        header.trim().to_lowercase().replace(' ', "_"),
+                        cell.to_string(),
+                    );
+                }
+            }
+        }
+        let keys: Vec<String> = [Some(&customer.name), customer.email.as_ref()]
+            .into_iter()
+            .flatten()
+            .map(|k| k.trim().to_lowercase())
+            .filter(|k| !k.is_empty())
+            .collect();
+        if customer.name.is_empty() || keys.iter().any(|k| seen.contains(k)) {
+            out.skipped += 1;
+            continue;
+        }
+        seen.extend(keys);
+        out.customers.push(customer);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_table_rows_become_customers() {
+        let table = Table::from_markdown(
+            "| Contact | Work email | Organisation | Segment | Region |\n\
+             |---|---|---|---|---|\n\
+             | Jane Doe | jane@acme.io | Acme | smb; renewal | EU |\n\
+             | | nobody@x.io | | | |\n\
+             | Old Client | old@corp.com | Corp | | US |\n\
+             | Jane Doe | other@acme.io | Acme | | EU |",
+        )
+        .unwrap();
+        let mapping = guess_mapping(&table.headers);
+        assert_eq!(
+            mapping,
+            vec![
+                ColumnTarget::Name,
+                ColumnTarget::Email,
+                ColumnTarget::Company,
+                ColumnTarget::Tags,
+                ColumnTarget::Custom,
+            ]
+        );
+
+        let known = Customer {
+            email: Some("OLD@corp.com".to_string()),
+            ..to_customers(&table, &mapping, &[], 1.0).customers[1].clone()
+        };
+        let import = to_customers(&table, &mapping, &[known], 7.0);
+        // No name, known email, and a name already imported above
+        assert_eq!(import.skipped, 3);
+        assert_eq!(import.customers.len(), 1);
+        let jane = &import.customers[0];
+        assert_eq!(jane.email.as_deref(), Some("jane@acme.io"));
+        assert_eq!(jane.company.as_deref(), Some("Acme"));
+        assert_eq!(jane.tags, vec!["smb", "renewal"]);
+        assert_eq!(
+            jane.custom_fields.get("region").map(String::as_str),
+            Some("EU")
+        );
+        assert_eq!(jane.id, "cust_7_0");
+
+        // Without a name-like header the first column is the name
+        assert_eq!(
+            guess_mapping(&["Mail".to_string(), "Mail 2".to_string()]),
+            vec![ColumnTarget::Name, ColumnTarget::Custom]
+        );
+    }
+}
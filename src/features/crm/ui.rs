@@ -1,9 +1,26 @@
 #![allow(non_snake_case)]
+use crate::features::crm::call_summary::{self, CallSentiment, CallSummary};
+use crate::features::crm::enrichment::{self, FieldProposal, MAX_PASSAGES};
+use crate::features::crm::ics;
+use crate::features::crm::ownership::{pipeline_totals_by_owner, OwnerFilter};
+use crate::features::crm::swimlanes::{group_deals, SwimlaneAxis};
+use crate::features::graphrag::GraphRAGPipeline;
+use crate::models::activity::ActivityCategory;
 use crate::models::crm::{Customer, Deal, Lead, LeadSource, PipelineStage};
-use crate::state::{use_crm_state, CRMStateProvider};
+use crate::models::{Message, MessageRole, OutputFormat};
+use crate::state::{
+    use_crm_state, CRMStateContext, CRMStateProvider, EventBusContext, TasksStateContext,
+};
+use crate::utils::datetime::{CivilDate, LocalNow};
+use crate::utils::generation::GenerationUtils;
+use crate::utils::json_output;
+use crate::utils::speech::{self, SpeechInput};
+use crate::webllm_binding::{loaded_engine, send_message_to_llm_with_format};
 use leptos::prelude::*;
+use leptos::task::spawn_local;
+use std::collections::HashSet;
 use wasm_bindgen::closure::Closure;
-use wasm_bindgen::JsCast;
+use wasm_bindgen::{JsCast, JsValue};
 
 #[component]
 fn DetailAlert(hash: &'static str, text: String) -> impl IntoView {
@@ -21,11 +38,138 @@ fn DetailAlert(hash: &'static str, text: String) -> impl IntoView {
 }
 
 #[component]
-pub fn CRMPanel() -> impl IntoView {
+fn CalendarExportButton() -> impl IntoView {
+    let crm = use_crm_state();
+    let tasks = use_context::<TasksStateContext>();
+    let export = move |_| {
+        let mut events = ics::events_from_deals(&crm.deals_now());
+        if let Some(t) = tasks.as_ref() {
+            events.extend(ics::events_from_tasks(&t.tasks_now()));
+        }
+        if let Err(e) = ics::download_calendar("crm-calendar.ics", &events) {
+            log::error!("Calendar export failed: {}", e);
+        }
+    };
+    view! {
+        <button class="btn btn-sm btn-ghost" id="crm-export-ics" title="Export reminders, tasks and close dates" on:click=export>
+            "Export .ics"
+        </button>
+    }
+}
+
+fn add_deal_to_calendar(crm: &CRMStateContext, deal_id: &str) {
+    let Some(deal) = crm.deals_now().into_iter().find(|d| d.id == deal_id) else {
+        return;
+    };
+    // Close date alone when it exists, otherwise pending activities
+    let events = match deal.expected_close_date {
+        Some(close) => vec![ics::deal_close_event(&deal, close)],
+        None => ics::events_from_deals(std::slice::from_ref(&deal)),
+    };
+    if events.is_empty() {
+        return;
+    }
+    let filename = format!(
+        "{}.ics",
+        crate::utils::format::FormatUtils::to_safe_filename(&deal.title)
+    );
+    if let Err(e) = ics::download_calendar(&filename, &events) {
+        log::error!("Calendar export failed: {}", e);
+    }
+}
+
+const OWNER_OPTIONS_ID: &str = "crm-owner-options";
+
+/// Owner filter, local profile management and the shared owner `<datalist>`
+#[component]
+fn OwnerBar(
+    owner_filter: ReadSignal<OwnerFilter>,
+    set_owner_filter: WriteSignal<OwnerFilter>,
+) -> impl IntoView {
+    let crm = use_crm_state();
+    let (new_profile, set_new_profile) = signal(String::new());
+
+    let crm_owners = crm.clone();
+    let owners = Memo::new(move |_| crm_owners.known_owners());
+
+    let crm_add = crm.clone();
+    let add_profile = move |_| {
+        crm_add.add_owner_profile(&new_profile.get());
+        set_new_profile.set(String::new());
+    };
+
+    let crm_profiles = crm.clone();
+    view! {
+        <div id="crm-owner-bar" class="flex flex-wrap items-center gap-2 mb-3">
+            <datalist id=OWNER_OPTIONS_ID>
+                {move || owners.get().into_iter().map(|o| view! { <option value=o></option> }).collect_view()}
+            </datalist>
+            <select
+                class="select select-sm select-bordered"
+                id="crm-owner-filter"
+                prop:value=move || owner_filter.get().to_key()
+                on:change=move |e| set_owner_filter.set(OwnerFilter::from_key(&event_target_value(&e)))
+            >
+                <option value="">"All owners"</option>
+                <option value="__unassigned">"Unassigned"</option>
+                {move || owners.get().into_iter().map(|o| view! { <option value=o.clone()>{o.clone()}</option> }).collect_view()}
+            </select>
+            <input
+                class="input input-sm input-bordered w-36"
+                prop:value=new_profile
+                on:input=move |e| set_new_profile.set(event_target_value(&e))
+                placeholder="New owner profile"
+            />
+            <button class="btn btn-sm btn-ghost" on:click=add_profile>"Add owner"</button>
+            {move || {
+                let crm_ctx = crm_profiles.clone();
+                // Track profile changes via known_owners
+                let _ = owners.get();
+                crm_ctx
+                    .owner_profiles_now()
+                    .into_iter()
+                    .map(|name| {
+                        let crm_item = crm_ctx.clone();
+                        let remove = name.clone();
+                        view! {
+                            <span class="badge badge-outline gap-1">
+                                {name}
+                                <a class="cursor-pointer" on:click=move |_| crm_item.remove_owner_profile(&remove)>"✕"</a>
+                            </span>
+                        }
+                    })
+                    .collect_view()
+            }}
+        </div>
+    }
+}
+
+/// Free-text owner field backed by the shared owner datalist
+#[component]
+fn OwnerInput(owner: Option<String>, on_assign: impl Fn(String) + 'static) -> impl IntoView {
+    view! {
+        <input
+            class="input input-xs input-bordered w-28"
+            list=OWNER_OPTIONS_ID
+            placeholder="Owner"
+            title="Owner"
+            prop:value=owner.unwrap_or_default()
+            on:change=move |e| on_assign(event_target_value(&e))
+        />
+    }
+}
+
+#[component]
+pub fn CRMPanel(
+    /// Tab shown first unless the location hash names one
+    #[prop(default = None)]
+    initial_tab: Option<String>,
+) -> impl IntoView {
     // Provide local CRM state scope so panel can be dropped independently if desired
-    let (tab, set_tab) = signal("customers".to_string());
+    let (tab, set_tab) = signal(initial_tab.unwrap_or_else(|| "customers".to_string()));
     // Optional detail tuple: (kind, id) where kind is "customers" | "deals"
     let (detail, set_detail) = signal(None::<(String, String)>);
+    let (owner_filter, set_owner_filter) = signal(OwnerFilter::All);
     // Initialize from location.hash if present
     if let Some(win) = web_sys::window() {
         if let Ok(loc) = win.location().hash() {
@@ -107,21 +251,23 @@ pub fn CRMPanel() -> impl IntoView {
                     <button class=move || if tab.get() == "deals" { "tab tab-active" } else { "tab" } id="tab-deals" on:click=move |_| set_tab.set("deals".into())>"Deals"</button>
                     <button class=move || if tab.get() == "stages" { "tab tab-active" } else { "tab" } id="tab-stages" on:click=move |_| set_tab.set("stages".into())>"Stages"</button>
                     <button class=move || if tab.get() == "board" { "tab tab-active" } else { "tab" } id="tab-board" on:click=move |_| set_tab.set("board".into())>"Board"</button>
+                    <div class="ml-auto"><CalendarExportButton /></div>
                 </div>
+                <OwnerBar owner_filter=owner_filter set_owner_filter=set_owner_filter />
                 <Show when=move || tab.get() == "customers">
                     <CustomersView detail=detail />
                 </Show>
                 <Show when=move || tab.get() == "leads">
-                    <LeadsView />
+                    <LeadsView owner_filter=owner_filter />
                 </Show>
                 <Show when=move || tab.get() == "deals">
-                    <DealsView detail=detail />
+                    <DealsView detail=detail owner_filter=owner_filter />
                 </Show>
                 <Show when=move || tab.get() == "stages">
                     <StagesView />
                 </Show>
                 <Show when=move || tab.get() == "board">
-                    <PipelineBoardView />
+                    <PipelineBoardView owner_filter=owner_filter />
                 </Show>
             </div>
         </CRMStateProvider>
@@ -170,6 +316,12 @@ fn CustomersView(detail: ReadSignal<Option<(String, String)>>) -> impl IntoView
                     }
                 }}
             </Show>
+            {move || {
+                detail
+                    .get()
+                    .filter(|(k, _)| k == "customers")
+                    .map(|(_, id)| view! { <EnrichFromKb customer_id=id /> })
+            }}
             <div class="flex items-center gap-2 mb-2">
                 <input
                     class="input input-sm input-bordered w-full"
@@ -212,9 +364,194 @@ fn CustomersView(detail: ReadSignal<Option<(String, String)>>) -> impl IntoView
     }
 }
 
+/// Run a JSON-mode template and parse its reply, with one repair round as for JSON
+/// replies in chat
+async fn run_json_template<T>(
+    engine: &JsValue,
+    request: Vec<Message>,
+    format: &OutputFormat,
+    parse: impl Fn(&str) -> Result<T, String>,
+) -> Result<T, String> {
+    let reply = send_message_to_llm_with_format(engine, request.clone(), format)
+        .await
+        .map_err(|e| format!("{:?}", e))?
+        .0;
+    let error = match parse(&reply) {
+        Ok(parsed) => return Ok(parsed),
+        Err(error) => error,
+    };
+    let mut repair = request;
+    repair.push(Message::new(MessageRole::Assistant, reply));
+    repair.push(json_output::repair_message(&error));
+    match send_message_to_llm_with_format(engine, repair, format).await {
+        Ok((reply, _)) => parse(&reply),
+        Err(_) => Err(error),
+    }
+}
+
+/// "Enrich from KB" for the customer in the detail pane: passages mentioning the
+/// customer are read by the loaded model and the fields it fills in are listed for
+/// review; only the checked ones are saved.
+#[component]
+fn EnrichFromKb(customer_id: String) -> impl IntoView {
+    let crm = use_crm_state();
+    let events = use_context::<EventBusContext>();
+    let running = RwSignal::new(false);
+    let status = RwSignal::new(None::<String>);
+    // Proposals with whether each is checked for saving
+    let proposals = RwSignal::new(Vec::<(FieldProposal, bool)>::new());
+    let sources = RwSignal::new(Vec::<String>::new());
+
+    let crm_run = crm.clone();
+    let id_run = customer_id.clone();
+    let run = move |_| {
+        let Some(customer) = crm_run.customers_now().into_iter().find(|c| c.id == id_run) else {
+            return;
+        };
+        let Some((_, engine)) = loaded_engine() else {
+            status.set(Some("Load a model first".to_string()));
+            return;
+        };
+        let docs = GraphRAGPipeline::new()
+            .indexed_documents()
+            .unwrap_or_default();
+        let passages =
+            enrichment::find_passages(&docs, &enrichment::search_terms(&customer), MAX_PASSAGES);
+        proposals.set(Vec::new());
+        if passages.is_empty() {
+            status.set(Some(format!("No documents mention {}", customer.name)));
+            return;
+        }
+        let mut files: Vec<String> = Vec::new();
+        for p in &passages {
+            if !files.contains(&p.source) {
+                files.push(p.source.clone());
+            }
+        }
+        sources.set(files);
+        running.set(true);
+        status.set(Some(format!("Reading {} passage(s)...", passages.len())));
+        spawn_local(async move {
+            let result = run_json_template(
+                &engine,
+                enrichment::request(&customer, &passages),
+                &enrichment::output_format(),
+                enrichment::parse,
+            )
+            .await;
+            match result {
+                Ok(found) => {
+                    let found = enrichment::proposals(&customer, &found);
+                    status.set(
+                        found
+                            .is_empty()
+                            .then(|| "Nothing new found in the documents".to_string()),
+                    );
+                    proposals.set(found.into_iter().map(|p| (p, true)).collect());
+                }
+                Err(error) => {
+                    status.set(Some(format!("Enrichment failed: {}", error)));
+                    if let Some(bus) = events {
+                        bus.error("CRM enrichment failed", error);
+                    }
+                }
+            }
+            running.set(false);
+        });
+    };
+
+    let save = move |_| {
+        let accepted: Vec<FieldProposal> = proposals.with(|p| {
+            p.iter()
+                .filter(|(_, checked)| *checked)
+                .map(|(p, _)| p.clone())
+                .collect()
+        });
+        let Some(customer) = crm
+            .customers_now()
+            .into_iter()
+            .find(|c| c.id == customer_id)
+        else {
+            return;
+        };
+        if !accepted.is_empty() {
+            crm.upsert_customer(enrichment::apply(&customer, &accepted, js_sys::Date::now()));
+            if let Some(bus) = events {
+                bus.record(
+                    ActivityCategory::Crm,
+                    format!(
+                        "Enriched \"{}\" from the knowledge base ({} field(s))",
+                        customer.name,
+                        accepted.len()
+                    ),
+                );
+            }
+        }
+        status.set(Some(format!("Saved {} field(s)", accepted.len())));
+        proposals.set(Vec::new());
+    };
+
+    view! {
+        <div id="crm-enrich" class="mb-3 p-3 rounded-box bg-base-200 space-y-2">
+            <div class="flex items-center gap-2">
+                <button
+                    class="btn btn-sm btn-outline"
+                    title="Propose industry, products and key contacts from documents mentioning this customer"
+                    disabled=move || running.get()
+                    on:click=run
+                >
+                    {move || if running.get() { "Enriching..." } else { "Enrich from KB" }}
+                </button>
+                <span class="text-xs opacity-70">{move || status.get().unwrap_or_default()}</span>
+            </div>
+            <Show when=move || proposals.with(|p| !p.is_empty())>
+                <p class="text-xs opacity-70">
+                    {move || format!("From: {}", sources.get().join(", "))}
+                </p>
+                <table class="table table-xs">
+                    <thead>
+                        <tr><th></th><th>"Field"</th><th>"Current"</th><th>"Proposed"</th></tr>
+                    </thead>
+                    <tbody>
+                        <For each=move || proposals.get().into_iter().enumerate() key=|(i, (p, _))| (*i, p.field) let:row>
+                            {
+                                let (i, (proposal, checked)) = row;
+                                view! {
+                                    <tr>
+                                        <td>
+                                            <input
+                                                type="checkbox"
+                                                class="checkbox checkbox-xs"
+                                                prop:checked=checked
+                                                on:change=move |_| proposals.update(|p| {
+                                                    if let Some(row) = p.get_mut(i) {
+                                                        row.1 = !row.1;
+                                                    }
+                                                })
+                                            />
+                                        </td>
+                                        <td>{proposal.label}</td>
+                                        <td class="opacity-60">{proposal.current.clone().unwrap_or_else(|| "—".to_string())}</td>
+                                        <td>{proposal.proposed.clone()}</td>
+                                    </tr>
+                                }
+                            }
+                        </For>
+                    </tbody>
+                </table>
+                <div class="flex gap-2">
+                    <button class="btn btn-sm btn-primary" on:click=save.clone()>"Save checked"</button>
+                    <button class="btn btn-sm btn-ghost" on:click=move |_| proposals.set(Vec::new())>"Discard"</button>
+                </div>
+            </Show>
+        </div>
+    }
+}
+
 #[component]
-fn PipelineBoardView() -> impl IntoView {
+fn PipelineBoardView(owner_filter: ReadSignal<OwnerFilter>) -> impl IntoView {
     let crm = use_crm_state();
+    let events = use_context::<EventBusContext>();
 
     // Helpers to move a deal to adjacent stage
     let move_deal = {
@@ -240,6 +577,15 @@ fn PipelineBoardView() -> impl IntoView {
                     };
                     if new_idx != idx {
                         deal.stage_id = stages[new_idx].id.clone();
+                        if let Some(bus) = events {
+                            bus.record(
+                                ActivityCategory::Crm,
+                                format!(
+                                    "Deal \"{}\" moved to {}",
+                                    deal.title, stages[new_idx].name
+                                ),
+                            );
+                        }
                         crm_ctx.upsert_deal(deal);
                     }
                 }
@@ -291,82 +637,188 @@ fn PipelineBoardView() -> impl IntoView {
         }
     };
 
+    // Swimlane grouping and collapsed lane keys
+    let (lane_axis, set_lane_axis) = signal(SwimlaneAxis::None);
+    let collapsed_lanes = RwSignal::new(HashSet::<String>::new());
+
     // Render board
     view! {
         <div id="crm-board" class="overflow-x-auto">
             <div class="flex items-center gap-2 mb-3">
                 <input class="input input-sm input-bordered w-full" prop:value=new_stage on:input=move |e| set_new_stage.set(event_target_value(&e)) placeholder="Add new stage" />
                 <button class="btn btn-sm" on:click=add_stage>{"Add Stage"}</button>
+                <select
+                    class="select select-sm select-bordered"
+                    id="crm-lane-axis"
+                    prop:value=move || lane_axis.get().key()
+                    on:change=move |e| {
+                        collapsed_lanes.set(HashSet::new());
+                        set_lane_axis.set(SwimlaneAxis::from_key(&event_target_value(&e)));
+                    }
+                >
+                    {SwimlaneAxis::ALL.into_iter().map(|a| view! { <option value=a.key()>{a.label()}</option> }).collect_view()}
+                </select>
             </div>
-            <div class="grid grid-cols-1 sm:grid-cols-2 gap-3 min-w-[360px]">
+            // Per-owner open pipeline totals
+            <div id="crm-owner-totals" class="flex flex-wrap gap-2 mb-3">
+                {
+                    let crm_totals = crm.clone();
+                    move || {
+                        let filter = owner_filter.get();
+                        let deals: Vec<Deal> = crm_totals
+                            .deals_now()
+                            .into_iter()
+                            .filter(|d| filter.matches(d.owner.as_deref()))
+                            .collect();
+                        pipeline_totals_by_owner(&deals)
+                            .into_iter()
+                            .map(|t| view! {
+                                <div class="stat bg-base-200 rounded-box p-2 w-auto">
+                                    <div class="stat-title text-xs">{t.label().to_string()}</div>
+                                    <div class="stat-value text-base">{format!("{:.0}", t.total_value)}</div>
+                                    <div class="stat-desc">{format!("{} deals · weighted {:.0}", t.deal_count, t.weighted_value)}</div>
+                                </div>
+                            })
+                            .collect_view()
+                    }
+                }
+            </div>
+            <div id="crm-lanes" class="space-y-3">
                 {move || {
                     let mut stages = crm.stages_now();
                     stages.sort_by_key(|s| s.order);
-                    let deals = crm.deals_now();
-
-                    stages.into_iter().map(|stage| {
-                        let stage_id = stage.id.clone();
-                        let title = stage.name.clone();
-                        let stage_deals: Vec<_> = deals.iter().filter(|d| d.stage_id == stage_id).cloned().collect();
-                        let crm_move_left = move_deal.clone();
-                        let crm_move_right = move_deal.clone();
+                    let filter = owner_filter.get();
+                    let deals: Vec<Deal> = crm
+                        .deals_now()
+                        .into_iter()
+                        .filter(|d| filter.matches(d.owner.as_deref()))
+                        .collect();
+                    let axis = lane_axis.get();
+                    let lanes = group_deals(&deals, axis, &crm.customers_now());
+
+                    if axis == SwimlaneAxis::None {
+                        let lane_deals = lanes.into_iter().next().map(|l| l.deals).unwrap_or_default();
+                        return stage_columns(&stages, &lane_deals, move_deal.clone(), reorder_stage.clone()).into_any();
+                    }
+
+                    lanes.into_iter().map(|lane| {
+                        let key = lane.key.clone();
+                        let key_toggle = lane.key.clone();
+                        let is_collapsed = move || collapsed_lanes.with(|c| c.contains(&key));
+                        let grid = stage_columns(&stages, &lane.deals, move_deal.clone(), reorder_stage.clone());
                         view! {
-                            <div class="card bg-base-200">
-                                <div class="card-body p-3">
-                                    <div class="flex items-center justify-between mb-2">
-                                        <div class="font-semibold">{format!("{} ({})", title, stage_deals.len())}</div>
-                                        <div class="flex gap-1">
-                                            <button class="btn btn-xs" on:click={
-                                                let f = reorder_stage.clone(); let id = stage_id.clone(); move |_| f(id.clone(), -1)
-                                            }>{"↑"}</button>
-                                            <button class="btn btn-xs" on:click={
-                                                let f = reorder_stage.clone(); let id = stage_id.clone(); move |_| f(id.clone(), 1)
-                                            }>{"↓"}</button>
-                                        </div>
-                                    </div>
-                                    <div class="space-y-2">
-                                        {stage_deals.into_iter().map(|d| {
-                                            let id_left = d.id.clone();
-                                            let id_right = d.id.clone();
-                                            view! {
-                                                <div class="card bg-base-100 shadow-sm">
-                                                    <div class="card-body p-3">
-                                                        <div class="flex items-center justify-between gap-2">
-                                                            <div class="text-sm truncate">{d.title.clone()}</div>
-                                                            <div class="flex gap-1">
-                                                                <button class="btn btn-xs" on:click={
-                                                                    let f = crm_move_left.clone();
-                                                                    let id = id_left.clone();
-                                                                    move |_| f(id.clone(), -1)
-                                                                }>
-                                                                    "←"
-                                                                </button>
-                                                                <button class="btn btn-xs" on:click={
-                                                                    let f = crm_move_right.clone();
-                                                                    let id = id_right.clone();
-                                                                    move |_| f(id.clone(), 1)
-                                                                }>
-                                                                    "→"
-                                                                </button>
-                                                            </div>
-                                                        </div>
-                                                    </div>
-                                                </div>
-                                            }
-                                        }).collect_view()}
-                                    </div>
+                            <div class="crm-lane border border-base-300 rounded-box">
+                                <button
+                                    class="w-full flex items-center justify-between gap-2 px-3 py-2 bg-base-200 rounded-t-box text-left"
+                                    on:click=move |_| collapsed_lanes.update(|c| {
+                                        if !c.remove(&key_toggle) {
+                                            c.insert(key_toggle.clone());
+                                        }
+                                    })
+                                >
+                                    <span class="font-semibold text-sm">{lane.label.clone()}</span>
+                                    <span class="text-xs text-base-content/70">
+                                        {format!("{} deals · {:.0} · weighted {:.0}", lane.deals.len(), lane.total_value, lane.weighted_value)}
+                                    </span>
+                                </button>
+                                <div class=move || if is_collapsed() { "hidden" } else { "p-2" }>
+                                    {grid}
                                 </div>
                             </div>
                         }
-                    }).collect_view()
+                    }).collect_view().into_any()
                 }}
             </div>
         </div>
     }
 }
 
+/// Stage columns for one set of deals (a whole board or a single swimlane)
+fn stage_columns<M, R>(
+    stages: &[PipelineStage],
+    deals: &[Deal],
+    move_deal: M,
+    reorder_stage: R,
+) -> impl IntoView
+where
+    M: Fn(String, i32) + Clone + 'static,
+    R: Fn(String, i32) + Clone + 'static,
+{
+    let columns = stages
+        .iter()
+        .map(|stage| {
+            let stage_id = stage.id.clone();
+            let title = stage.name.clone();
+            let stage_deals: Vec<_> = deals.iter().filter(|d| d.stage_id == stage_id).cloned().collect();
+            let stage_total: f64 = stage_deals.iter().map(|d| d.value).sum();
+            let crm_move_left = move_deal.clone();
+            let crm_move_right = move_deal.clone();
+            let reorder_up = reorder_stage.clone();
+            let reorder_down = reorder_stage.clone();
+            view! {
+                <div class="card bg-base-200">
+                    <div class="card-body p-3">
+                        <div class="flex items-center justify-between mb-2">
+                            <div class="font-semibold" title=format!("Total {:.0}", stage_total)>{format!("{} ({})", title, stage_deals.len())}</div>
+                            <div class="flex gap-1">
+                                <button class="btn btn-xs" on:click={
+                                    let id = stage_id.clone(); move |_| reorder_up(id.clone(), -1)
+                                }>{"↑"}</button>
+                                <button class="btn btn-xs" on:click={
+                                    let id = stage_id.clone(); move |_| reorder_down(id.clone(), 1)
+                                }>{"↓"}</button>
+                            </div>
+                        </div>
+                        <div class="space-y-2">
+                            {stage_deals.into_iter().map(|d| {
+                                let id_left = d.id.clone();
+                                let id_right = d.id.clone();
+                                view! {
+                                    <div class="card bg-base-100 shadow-sm">
+                                        <div class="card-body p-3">
+                                            <div class="flex items-center justify-between gap-2">
+                                                <div class="min-w-0">
+                                                    <div class="text-sm truncate">{d.title.clone()}</div>
+                                                    {d.owner.clone().map(|o| view! {
+                                                        <span class="badge badge-xs badge-ghost crm-owner">{o}</span>
+                                                    })}
+                                                </div>
+                                                <div class="flex gap-1">
+                                                    <button class="btn btn-xs" on:click={
+                                                        let f = crm_move_left.clone();
+                                                        let id = id_left.clone();
+                                                        move |_| f(id.clone(), -1)
+                                                    }>
+                                                        "←"
+                                                    </button>
+                                                    <button class="btn btn-xs" on:click={
+                                                        let f = crm_move_right.clone();
+                                                        let id = id_right.clone();
+                                                        move |_| f(id.clone(), 1)
+                                                    }>
+                                                        "→"
+                                                    </button>
+                                                </div>
+                                            </div>
+                                        </div>
+                                    </div>
+                                }
+                            }).collect_view()}
+                        </div>
+                    </div>
+                </div>
+            }
+        })
+        .collect_view();
+    view! {
+        <div class="grid grid-cols-1 sm:grid-cols-2 gap-3 min-w-[360px]">
+            {columns}
+        </div>
+    }
+}
+
 #[component]
-fn LeadsView() -> impl IntoView {
+fn LeadsView(owner_filter: ReadSignal<OwnerFilter>) -> impl IntoView {
     let crm = use_crm_state();
     let (name, set_name) = signal(String::new());
 
@@ -396,15 +848,23 @@ fn LeadsView() -> impl IntoView {
             <ul class="menu bg-base-200 rounded-box">
                 {move || {
                     let crm_ctx = crm.clone();
+                    let filter = owner_filter.get();
                     crm_ctx
                         .leads_now()
                         .into_iter()
+                        .filter(|l| filter.matches(l.owner.as_deref()))
                         .map(|l| {
                             let id = l.id.clone();
                             let crm_item = crm_ctx.clone();
+                            let crm_owner = crm_ctx.clone();
+                            let id_owner = id.clone();
                             view! {
                                 <li class="flex items-center justify-between">
                                     <span>{l.name.clone()}</span>
+                                    <OwnerInput
+                                        owner=l.owner.clone()
+                                        on_assign=move |o: String| crm_owner.assign_lead_owner(&id_owner, &o)
+                                    />
                                     <button
                                         class="btn btn-ghost btn-xs"
                                         on:click=move |_| crm_item.delete_lead(&id)
@@ -422,7 +882,10 @@ fn LeadsView() -> impl IntoView {
 }
 
 #[component]
-fn DealsView(detail: ReadSignal<Option<(String, String)>>) -> impl IntoView {
+fn DealsView(
+    detail: ReadSignal<Option<(String, String)>>,
+    owner_filter: ReadSignal<OwnerFilter>,
+) -> impl IntoView {
     let crm = use_crm_state();
     let (title, set_title) = signal(String::new());
 
@@ -482,6 +945,12 @@ fn DealsView(detail: ReadSignal<Option<(String, String)>>) -> impl IntoView {
                     }
                 }}
             </Show>
+            {move || {
+                detail
+                    .get()
+                    .filter(|(k, _)| k == "deals")
+                    .map(|(_, id)| view! { <CallSummaryPanel deal_id=id /> })
+            }}
             <div class="flex items-center gap-2 mb-2">
                 <input
                     class="input input-sm input-bordered w-full"
@@ -503,18 +972,43 @@ fn DealsView(detail: ReadSignal<Option<(String, String)>>) -> impl IntoView {
             <ul class="menu bg-base-200 rounded-box">
                 {move || {
                     let crm_ctx = crm.clone();
+                    let filter = owner_filter.get();
                     crm_ctx
                         .deals_now()
                         .into_iter()
+                        .filter(|d| filter.matches(d.owner.as_deref()))
                         .map(|d| {
                             let id = d.id.clone();
                             let crm_item = crm_ctx.clone();
+                            let crm_owner = crm_ctx.clone();
+                            let id_owner = id.clone();
+                            let crm_cal = crm_ctx.clone();
+                            let id_cal = id.clone();
+                            let has_dates = d.expected_close_date.is_some()
+                                || d.activities.iter().any(|a| a.due_date.is_some() && a.completed_at.is_none());
                             view! {
                                 <li class="flex items-center justify-between">
                                     <button class="btn btn-ghost btn-xs" on:click={
                                         let id = id.clone();
                                         move |_| { let _ = web_sys::window().unwrap().location().set_hash(&format!("deals/{}", id)); }
                                     }>{d.title.clone()}</button>
+                                    <OwnerInput
+                                        owner=d.owner.clone()
+                                        on_assign=move |o: String| crm_owner.assign_deal_owner(&id_owner, &o)
+                                    />
+                                    <Show when=move || has_dates>
+                                        <button
+                                            class="btn btn-ghost btn-xs"
+                                            title="Add to calendar"
+                                            on:click={
+                                                let crm_cal = crm_cal.clone();
+                                                let id = id_cal.clone();
+                                                move |_| add_deal_to_calendar(&crm_cal, &id)
+                                            }
+                                        >
+                                            "📅"
+                                        </button>
+                                    </Show>
                                     <button
                                         class="btn btn-ghost btn-xs"
                                         on:click=move |_| crm_item.delete_deal(&id)
@@ -531,6 +1025,231 @@ fn DealsView(detail: ReadSignal<Option<(String, String)>>) -> impl IntoView {
     }
 }
 
+/// Sales-call summary for the deal in the detail pane: a pasted, loaded or dictated
+/// transcript runs through the call template, and the result is previewed before it is
+/// written to the deal as activities and field updates.
+#[component]
+fn CallSummaryPanel(deal_id: String) -> impl IntoView {
+    let crm = use_crm_state();
+    let events = use_context::<EventBusContext>();
+    let transcript = RwSignal::new(String::new());
+    let running = RwSignal::new(false);
+    let status = RwSignal::new(None::<String>);
+    let summary = RwSignal::new(None::<CallSummary>);
+
+    // Dictation appended to the transcript
+    let listening = RwSignal::new(false);
+    let dictation = StoredValue::new_local(None::<SpeechInput>);
+    let toggle_dictation = move |_| {
+        if listening.get_untracked() {
+            dictation.with_value(|d| {
+                if let Some(d) = d {
+                    d.stop();
+                }
+            });
+            return;
+        }
+        let typed = transcript.get_untracked();
+        let started = SpeechInput::start(
+            &speech::default_language(),
+            move |spoken| transcript.set(speech::merge_transcript(&typed, &spoken)),
+            move |error| {
+                listening.set(false);
+                if let Some(code) = error {
+                    status.set(Some(speech::describe_error(&code).to_string()));
+                }
+            },
+        );
+        match started {
+            Ok(input) => {
+                dictation.set_value(Some(input));
+                listening.set(true);
+            }
+            Err(e) => status.set(Some(e.to_string())),
+        }
+    };
+
+    let load_file = move |ev: leptos::ev::Event| {
+        let input = event_target::<web_sys::HtmlInputElement>(&ev);
+        let Some(file) = input.files().and_then(|f| f.item(0)) else {
+            return;
+        };
+        input.set_value("");
+        spawn_local(async move {
+            match wasm_bindgen_futures::JsFuture::from(file.text()).await {
+                Ok(text) => transcript.set(text.as_string().unwrap_or_default()),
+                Err(e) => status.set(Some(format!("Could not read file: {:?}", e))),
+            }
+        });
+    };
+
+    let crm_run = crm.clone();
+    let id_run = deal_id.clone();
+    let run = move |_| {
+        let text = transcript.get_untracked();
+        if text.trim().is_empty() {
+            status.set(Some("Paste or dictate the call first".to_string()));
+            return;
+        }
+        let Some(deal) = crm_run.deals_now().into_iter().find(|d| d.id == id_run) else {
+            return;
+        };
+        let Some((_, engine)) = loaded_engine() else {
+            status.set(Some("Load a model first".to_string()));
+            return;
+        };
+        let customer = crm_run
+            .customers_now()
+            .into_iter()
+            .find(|c| c.id == deal.customer_id)
+            .map(|c| c.name);
+        let settings = GenerationUtils::load_settings();
+        let today = LocalNow::current(settings.datetime.timezone.as_deref()).date;
+        let today_label = format!("{}, {}", today.weekday_name(), today.iso());
+        running.set(true);
+        summary.set(None);
+        status.set(Some("Summarizing the call...".to_string()));
+        spawn_local(async move {
+            let result = run_json_template(
+                &engine,
+                call_summary::request(&deal, customer.as_deref(), &text, &today_label),
+                &call_summary::output_format(),
+                call_summary::parse,
+            )
+            .await;
+            match result {
+                Ok(found) => {
+                    status.set(None);
+                    summary.set(Some(found));
+                }
+                Err(error) => {
+                    status.set(Some(format!("Summary failed: {}", error)));
+                    if let Some(bus) = events {
+                        bus.error("Call summary failed", error);
+                    }
+                }
+            }
+            running.set(false);
+        });
+    };
+
+    let save = move |_| {
+        let Some(found) = summary.get_untracked() else {
+            return;
+        };
+        let Some(deal) = crm.deals_now().into_iter().find(|d| d.id == deal_id) else {
+            return;
+        };
+        let settings = GenerationUtils::load_settings();
+        let today = LocalNow::current(settings.datetime.timezone.as_deref()).date;
+        crm.upsert_deal(call_summary::write_back(
+            &deal,
+            &found,
+            today,
+            js_sys::Date::now(),
+        ));
+        if let Some(bus) = events {
+            bus.record(
+                ActivityCategory::Crm,
+                format!(
+                    "Call summary saved to \"{}\" ({} next step(s))",
+                    deal.title,
+                    found.next_steps.len()
+                ),
+            );
+        }
+        summary.set(None);
+        transcript.set(String::new());
+        status.set(Some("Saved to the deal".to_string()));
+    };
+
+    view! {
+        <div id="crm-call-summary" class="mb-3 p-3 rounded-box bg-base-200 space-y-2">
+            <div class="flex items-center justify-between gap-2">
+                <span class="font-semibold text-sm">"Call summary"</span>
+                <div class="flex items-center gap-1">
+                    <Show when=move || speech::is_supported()>
+                        <button
+                            class=move || if listening.get() { "btn btn-xs btn-error" } else { "btn btn-xs btn-ghost" }
+                            on:click=toggle_dictation
+                        >
+                            {move || if listening.get() { "Stop" } else { "Dictate" }}
+                        </button>
+                    </Show>
+                    <label class="btn btn-xs btn-ghost" title="Load a transcript file">
+                        "Load file"
+                        <input type="file" accept=".txt,.vtt,.srt,.md" class="hidden" on:change=load_file />
+                    </label>
+                </div>
+            </div>
+            <textarea
+                class="textarea textarea-bordered textarea-sm w-full h-28"
+                placeholder="Paste the call transcript or notes"
+                prop:value=move || transcript.get()
+                on:input=move |e| transcript.set(event_target_value(&e))
+            ></textarea>
+            <div class="flex items-center gap-2">
+                <button class="btn btn-sm btn-outline" disabled=move || running.get() on:click=run>
+                    {move || if running.get() { "Summarizing..." } else { "Summarize call" }}
+                </button>
+                <span class="text-xs opacity-70">{move || status.get().unwrap_or_default()}</span>
+            </div>
+            {move || summary.get().map(|found| {
+                let sentiment_class = match found.sentiment {
+                    CallSentiment::Positive => "badge badge-success badge-sm",
+                    CallSentiment::Neutral => "badge badge-ghost badge-sm",
+                    CallSentiment::Negative => "badge badge-error badge-sm",
+                };
+                let close = found
+                    .close_date
+                    .as_deref()
+                    .and_then(CivilDate::parse_iso)
+                    .map(|d| d.iso());
+                let value = found.deal_value.filter(|v| *v > 0.0);
+                view! {
+                    <div class="space-y-2 text-sm">
+                        <div class="flex items-center gap-2">
+                            <span class=sentiment_class>{found.sentiment.label()}</span>
+                            <span>{found.summary.clone()}</span>
+                        </div>
+                        {(!found.objections.is_empty()).then(|| view! {
+                            <div>
+                                <div class="text-xs font-semibold opacity-70">"Objections"</div>
+                                <ul class="list-disc ml-5">
+                                    {found.objections.iter().map(|o| view! { <li>{o.clone()}</li> }).collect_view()}
+                                </ul>
+                            </div>
+                        })}
+                        {(!found.next_steps.is_empty()).then(|| view! {
+                            <div>
+                                <div class="text-xs font-semibold opacity-70">"Next steps (added as follow-ups)"</div>
+                                <ul class="list-disc ml-5">
+                                    {found.next_steps.iter().map(|s| {
+                                        let due = s.due.clone().map(|d| format!(" — due {}", d)).unwrap_or_default();
+                                        let owner = s.owner.clone().map(|o| format!(" ({})", o)).unwrap_or_default();
+                                        view! { <li>{format!("{}{}{}", s.action, owner, due)}</li> }
+                                    }).collect_view()}
+                                </ul>
+                            </div>
+                        })}
+                        {(close.is_some() || value.is_some()).then(|| view! {
+                            <div class="text-xs">
+                                <span class="font-semibold opacity-70">"Deal updates: "</span>
+                                {close.map(|d| format!("expected close {} ", d))}
+                                {value.map(|v| format!("value {:.0}", v))}
+                            </div>
+                        })}
+                        <div class="flex gap-2">
+                            <button class="btn btn-sm btn-primary" on:click=save.clone()>"Save to deal"</button>
+                            <button class="btn btn-sm btn-ghost" on:click=move |_| summary.set(None)>"Discard"</button>
+                        </div>
+                    </div>
+                }
+            })}
+        </div>
+    }
+}
+
 #[component]
 fn StagesView() -> impl IntoView {
     let crm = use_crm_state();
@@ -0,0 +1,213 @@
+//! Splitting prose documents into the passages that get indexed. Each passage becomes
+//! its own `DocumentIndex` entry pointing back at the uploaded file it came from.
+
+use super::embeddings::chunk_text;
+use serde::{Deserialize, Serialize};
+
+/// How prose documents are cut into indexed passages
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ChunkingStrategy {
+    /// Windows of `chunk_size` characters, broken at whitespace
+    FixedSize,
+    /// Whole sentences packed up to `chunk_size` characters
+    Sentence,
+    /// One passage per markdown section, long sections packed by sentence
+    #[default]
+    MarkdownHeadings,
+    /// Fixed-size windows repeating the last `chunk_overlap` characters of the previous one
+    OverlapWindow,
+}
+
+impl ChunkingStrategy {
+    pub const ALL: [ChunkingStrategy; 4] = [
+        ChunkingStrategy::FixedSize,
+        ChunkingStrategy::Sentence,
+        ChunkingStrategy::MarkdownHeadings,
+        ChunkingStrategy::OverlapWindow,
+    ];
+
+    pub fn id(&self) -> &'static str {
+        match self {
+            ChunkingStrategy::FixedSize => "fixed",
+            ChunkingStrategy::Sentence => "sentence",
+            ChunkingStrategy::MarkdownHeadings => "markdown",
+            ChunkingStrategy::OverlapWindow => "overlap",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ChunkingStrategy::FixedSize => "Fixed size",
+            ChunkingStrategy::Sentence => "Sentence boundaries",
+            ChunkingStrategy::MarkdownHeadings => "Markdown headings",
+            ChunkingStrategy::OverlapWindow => "Overlapping windows",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|s| s.id() == id)
+    }
+}
+
+/// One passage of a document
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextChunk {
+    /// Heading of the section the passage starts in, for markdown chunking
+    pub heading: Option<String>,
+    pub text: String,
+}
+
+/// Cut `text` into passages of at most about `size` characters
+pub fn chunk_document(
+    text: &str,
+    strategy: ChunkingStrategy,
+    size: usize,
+    overlap: usize,
+) -> Vec<TextChunk> {
+    let size = size.max(1);
+    let plain = |texts: Vec<String>| {
+        texts
+            .into_iter()
+            .map(|text| TextChunk {
+                heading: None,
+                text,
+            })
+            .collect()
+    };
+    match strategy {
+        ChunkingStrategy::FixedSize => plain(chunk_text(text, size, 0)),
+        ChunkingStrategy::OverlapWindow => plain(chunk_text(text, size, overlap)),
+        ChunkingStrategy::Sentence => plain(pack_sentences(text, size)),
+        ChunkingStrategy::MarkdownHeadings => markdown_sections(text)
+            .into_iter()
+            .flat_map(|(heading, body)| {
+                pack_sentences(&body, size)
+                    .into_iter()
+                    .map(move |text| TextChunk {
+                        heading: heading.clone(),
+                        text,
+                    })
+            })
+            .collect(),
+    }
+}
+
+/// Sentences and paragraphs of `text`, keeping their line breaks
+fn sentences(text: &str) -> Vec<&str> {
+    let mut out = Vec::new();
+    let mut start = 0;
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        let next = chars.peek().map(|&(_, n)| n);
+        let ends_sentence = matches!(c, '.' | '!' | '?') && next.is_none_or(char::is_whitespace);
+        let ends_paragraph = c == '\n' && next == Some('\n');
+        if ends_sentence || ends_paragraph {
+            let end = i + c.len_utf8();
+            if !text[start..end].trim().is_empty() {
+                out.push(&text[start..end]);
+            }
+            start = end;
+        }
+    }
+    if !text[start..].trim().is_empty() {
+        out.push(&text[start..]);
+    }
+    out
+}
+
+/// Whole sentences packed up to `size` characters; a longer sentence is split on its own
+fn pack_sentences(text: &str, size: usize) -> Vec<String> {
+    let mut out = Vec::new();
+    let mut current = String::new();
+    for sentence in sentences(text) {
+        if !current.is_empty() && current.chars().count() + sentence.chars().count() > size {
+            out.push(current.trim().to_string());
+            current.clear();
+        }
+        if sentence.chars().count() > size {
+            out.extend(chunk_text(sentence, size, 0));
+        } else {
+            current.push_str(sentence);
+        }
+    }
+    if !current.trim().is_empty() {
+        out.push(current.trim().to_string());
+    }
+    out
+}
+
+/// Markdown sections with their heading; text before the first heading has none.
+/// Headings inside fenced code blocks are ignored.
+fn markdown_sections(text: &str) -> Vec<(Option<String>, String)> {
+    let mut out: Vec<(Option<String>, String)> = Vec::new();
+    let mut heading = None;
+    let mut body = String::new();
+    let mut in_fence = false;
+    for line in text.lines() {
+        if line.trim_start().starts_with("```") {
+            in_fence = !in_fence;
+        }
+        let title = (!in_fence)
+            .then(|| line.strip_prefix('#'))
+            .flatten()
+            .map(|rest| rest.trim_start_matches('#'))
+            .filter(|rest| rest.starts_with(' '))
+            .map(|rest| rest.trim().to_string());
+        if let Some(title) = title {
+            if !body.trim().is_empty() {
+                out.push((heading.take(), std::mem::take(&mut body)));
+            }
+            body.clear();
+            body.push_str(line);
+            body.push('\n');
+            heading = Some(title);
+        } else {
+            body.push_str(line);
+            body.push('\n');
+        }
+    }
+    if !body.trim().is_empty() {
+        out.push((heading, body));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_markdown_chunks_follow_sections() {
+        let text = "Intro line.\n\n# Setup\nInstall it. Then run it.\n\n```\n# not a heading\n```\n## Usage\nCall it.";
+        let chunks = chunk_document(text, ChunkingStrategy::MarkdownHeadings, 500, 0);
+        let headings: Vec<Option<&str>> = chunks.iter().map(|c| c.heading.as_deref()).collect();
+        assert_eq!(headings, vec![None, Some("Setup"), Some("Usage")]);
+        assert!(chunks[1].text.starts_with("# Setup"));
+        assert!(chunks[1].text.contains("# not a heading"));
+
+        // A long section is packed by sentence under the same heading
+        let chunks = chunk_document(text, ChunkingStrategy::MarkdownHeadings, 20, 0);
+        assert!(
+            chunks
+                .iter()
+                .filter(|c| c.heading.as_deref() == Some("Setup"))
+                .count()
+                > 1
+        );
+        assert!(chunks.iter().all(|c| c.text.chars().count() <= 20));
+    }
+
+    #[test]
+    fn test_sentence_chunks_keep_sentences_whole() {
+        let text = "First one here. Second one here. Third one here.";
+        let chunks = chunk_document(text, ChunkingStrategy::Sentence, 35, 0);
+        let texts: Vec<&str> = chunks.iter().map(|c| c.text.as_str()).collect();
+        assert_eq!(
+            texts,
+            vec!["First one here. Second one here.", "Third one here."]
+        );
+
+        let windows = chunk_document(text, ChunkingStrategy::OverlapWindow, 20, 8);
+        assert!(windows.len() > chunk_document(text, ChunkingStrategy::FixedSize, 20, 0).len());
+    }
+}
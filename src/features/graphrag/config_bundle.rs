@@ -0,0 +1,243 @@
+//! Sharing tuned GraphRAG settings as a JSON file. A bundle carries the full
+//! `GraphRAGConfig`, the retrieval pipeline that config runs, the user's profiles and a
+//! format version; importing validates it and lists what would change before anything
+//! is applied.
+
+use super::profiles::ConfigProfile;
+use crate::graphrag_config::GraphRAGConfig;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+pub const CONFIG_BUNDLE_FORMAT: &str = "graphrag-config";
+/// Bumped when a bundle written now could not be read by older builds
+pub const CONFIG_BUNDLE_VERSION: u32 = 1;
+
+/// Stages a query runs through under a config, in order
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PipelineSpec {
+    pub stages: Vec<String>,
+    /// How prose files are cut into passages at index time
+    pub chunking: String,
+}
+
+impl PipelineSpec {
+    pub fn of(config: &GraphRAGConfig) -> Self {
+        let stages = [
+            (true, "lexical"),
+            (config.hyde_enabled, "hyde"),
+            (config.embeddings_enabled, "embeddings"),
+            (config.community_detection_enabled, "communities"),
+            (config.pagerank_enabled, "pagerank"),
+            (config.hybrid_enabled, "hybrid_fusion"),
+            (config.reranking_enabled, "rerank"),
+            (config.mmr_enabled, "mmr"),
+            (config.synthesis_enabled, "synthesis"),
+        ];
+        Self {
+            stages: stages
+                .into_iter()
+                .filter(|(on, _)| *on)
+                .map(|(_, s)| s.to_string())
+                .collect(),
+            chunking: format!(
+                "{:?} {}/{}",
+                config.chunking_strategy, config.chunk_size, config.chunk_overlap
+            ),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ConfigBundle {
+    pub format: String,
+    pub version: u32,
+    pub exported_at: f64,
+    pub config: GraphRAGConfig,
+    /// Informational; the pipeline is always derived from `config` on import
+    pub pipeline: PipelineSpec,
+    /// User profiles (see `profiles`); absent from bundles of earlier builds
+    #[serde(default)]
+    pub profiles: Vec<ConfigProfile>,
+}
+
+impl ConfigBundle {
+    pub fn new(config: GraphRAGConfig, exported_at: f64) -> Self {
+        Self {
+            format: CONFIG_BUNDLE_FORMAT.to_string(),
+            version: CONFIG_BUNDLE_VERSION,
+            exported_at,
+            pipeline: PipelineSpec::of(&config),
+            config,
+            profiles: Vec::new(),
+        }
+    }
+
+    pub fn with_profiles(mut self, profiles: Vec<ConfigProfile>) -> Self {
+        self.profiles = profiles;
+        self
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+}
+
+/// A bundle read from a file, with what to tell the user before applying it
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImportedConfig {
+    pub config: GraphRAGConfig,
+    pub profiles: Vec<ConfigProfile>,
+    pub warnings: Vec<String>,
+}
+
+/// Settings that cannot work; an imported config with any of these is refused
+pub fn validate(config: &GraphRAGConfig) -> Vec<String> {
+    let mut errors = Vec::new();
+    let unit = [
+        ("fusion_text_weight", config.fusion_text_weight),
+        ("fusion_graph_weight", config.fusion_graph_weight),
+        ("semantic_weight", config.semantic_weight),
+        ("min_relevance", config.min_relevance),
+        ("mmr_lambda", config.mmr_lambda),
+    ];
+    for (name, value) in unit {
+        if !(0.0..=1.0).contains(&value) {
+            errors.push(format!("{} must be between 0 and 1, got {}", name, value));
+        }
+    }
+    if config.chunk_size == 0 {
+        errors.push("chunk_size must be greater than 0".to_string());
+    } else if config.chunk_overlap >= config.chunk_size {
+        errors.push(format!(
+            "chunk_overlap ({}) must be smaller than chunk_size ({})",
+            config.chunk_overlap, config.chunk_size
+        ));
+    }
+    if config.batch_size == 0 {
+        errors.push("batch_size must be greater than 0".to_string());
+    }
+    if config.embedding_model.trim().is_empty() {
+        errors.push("embedding_model is empty".to_string());
+    }
+    if config.reranker_model.trim().is_empty() {
+        errors.push("reranker_model is empty".to_string());
+    }
+    errors
+}
+
+/// Read a bundle, or a bare config as exported by earlier builds
+pub fn parse(json: &str) -> Result<ImportedConfig, String> {
+    let value: Value = serde_json::from_str(json).map_err(|e| format!("Not valid JSON: {}", e))?;
+    let mut warnings = Vec::new();
+    let mut profiles = Vec::new();
+    let config = if value.get("format").is_some() {
+        let format = value["format"].as_str().unwrap_or_default();
+        if format != CONFIG_BUNDLE_FORMAT {
+            return Err(format!(
+                "Not a GraphRAG settings file (format \"{}\")",
+                format
+            ));
+        }
+        let version = value["version"].as_u64().unwrap_or(0);
+        if version > CONFIG_BUNDLE_VERSION as u64 {
+            return Err(format!(
+                "Written by a newer version (format version {}, this build reads up to {})",
+                version, CONFIG_BUNDLE_VERSION
+            ));
+        }
+        let bundle: ConfigBundle =
+            serde_json::from_value(value).map_err(|e| format!("Invalid settings file: {}", e))?;
+        if bundle.pipeline != PipelineSpec::of(&bundle.config) {
+            warnings.push(
+                "The file's pipeline description does not match its settings; the settings are used"
+                    .to_string(),
+            );
+        }
+        profiles = bundle.profiles;
+        bundle.config
+    } else {
+        warnings.push("No format version: read as a bare configuration".to_string());
+        serde_json::from_value(value).map_err(|e| format!("Invalid configuration: {}", e))?
+    };
+    let mut errors = validate(&config);
+    for profile in &profiles {
+        if let Err(e) = super::profiles::validate_name(&profile.name) {
+            errors.push(e);
+        }
+        errors.extend(
+            validate(&profile.config)
+                .into_iter()
+                .map(|e| format!("{}: {}", profile.name, e)),
+        );
+    }
+    if !errors.is_empty() {
+        return Err(errors.join("; "));
+    }
+    Ok(ImportedConfig {
+        config,
+        profiles,
+        warnings,
+    })
+}
+
+/// One setting that differs between two configs, values as JSON
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConfigChange {
+    pub field: String,
+    pub current: String,
+    pub incoming: String,
+}
+
+/// Settings `incoming` would change, sorted by field name
+pub fn diff(current: &GraphRAGConfig, incoming: &GraphRAGConfig) -> Vec<ConfigChange> {
+    let (Ok(Value::Object(current)), Ok(Value::Object(incoming))) = (
+        serde_json::to_value(current),
+        serde_json::to_value(incoming),
+    ) else {
+        return Vec::new();
+    };
+    incoming
+        .iter()
+        .filter(|(field, value)| current.get(field.as_str()) != Some(value))
+        .map(|(field, value)| ConfigChange {
+            field: field.clone(),
+            current: current
+                .get(field.as_str())
+                .map(Value::to_string)
+                .unwrap_or_default(),
+            incoming: value.to_string(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bundle_round_trip_validation_and_diff() {
+        let tuned = GraphRAGConfig {
+            mmr_lambda: 0.5,
+            reranking_enabled: true,
+            ..GraphRAGConfig::default()
+        };
+        let json = ConfigBundle::new(tuned.clone(), 0.0).to_json();
+
+        let imported = parse(&json).unwrap();
+        assert_eq!(imported.config, tuned);
+        assert!(imported.warnings.is_empty());
+        let changes = diff(&GraphRAGConfig::default(), &imported.config);
+        let fields: Vec<&str> = changes.iter().map(|c| c.field.as_str()).collect();
+        assert_eq!(fields.len(), 2);
+        assert!(fields.contains(&"mmr_lambda") && fields.contains(&"reranking_enabled"));
+
+        // Bare configs from older exports are still read
+        let bare = serde_json::to_string(&tuned).unwrap();
+        assert_eq!(parse(&bare).unwrap().warnings.len(), 1);
+
+        let newer = json.replace("\"version\": 1", "\"version\": 2");
+        assert!(parse(&newer).unwrap_err().contains("newer version"));
+        let broken = json.replace("\"chunk_overlap\": 200", "\"chunk_overlap\": 5000");
+        assert!(parse(&broken).unwrap_err().contains("chunk_overlap"));
+    }
+}
@@ -0,0 +1,298 @@
+//! Large knowledge base mode. The document index keeps only a sketch of each passage
+//! (its opening and most frequent terms), enough for lexical scoring; the full texts
+//! live in records of their own that are never preloaded, and the passages a query
+//! returns are streamed back in through an LRU cache capped at the configured memory
+//! ceiling.
+
+use super::index_stats::fingerprint;
+use super::summarizer::content_terms;
+use crate::models::app::AppResult;
+use crate::models::graphrag::DocumentIndex;
+use crate::storage::persistent::PersistentStore;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Record key of the stowed passage list (preloaded, small)
+pub const STOWED_CONTENT_KEY: &str = "graphrag_stowed_content_v1";
+/// Prefix of the full-text records (never preloaded)
+const CONTENT_KEY_PREFIX: &str = "graphrag_content_v1";
+/// Characters of the passage kept in its sketch
+const SKETCH_OPENING_CHARS: usize = 320;
+/// Most frequent terms kept in a sketch
+const SKETCH_TERMS: usize = 48;
+const SKETCH_MARKER: &str = "\n\n[…]\n";
+const BYTES_PER_MB: usize = 1024 * 1024;
+
+thread_local! {
+    // Full texts read back during this session, most recently used kept
+    static CACHE: RefCell<ContentCache> = RefCell::new(ContentCache::new(0));
+}
+
+/// A passage whose full text was moved out of the index
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct StowedContent {
+    pub chars: usize,
+    /// Fingerprint of the full text, part of its record key
+    pub fingerprint: u64,
+}
+
+/// Stowed passages by document id
+pub type StowedIndex = BTreeMap<String, StowedContent>;
+
+/// Memory the passage cache holds against its ceiling
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CacheUsage {
+    pub used_bytes: usize,
+    pub capacity_bytes: usize,
+    pub entries: usize,
+}
+
+impl CacheUsage {
+    pub fn used_mb(&self) -> f32 {
+        self.used_bytes as f32 / BYTES_PER_MB as f32
+    }
+
+    pub fn capacity_mb(&self) -> f32 {
+        self.capacity_bytes as f32 / BYTES_PER_MB as f32
+    }
+}
+
+/// Least-recently-used cache of full passage texts, bounded in bytes
+#[derive(Debug, Default)]
+pub struct ContentCache {
+    capacity_bytes: usize,
+    used_bytes: usize,
+    tick: u64,
+    entries: HashMap<String, (String, u64)>,
+}
+
+impl ContentCache {
+    pub fn new(capacity_bytes: usize) -> Self {
+        Self {
+            capacity_bytes,
+            ..Self::default()
+        }
+    }
+
+    pub fn get(&mut self, key: &str) -> Option<String> {
+        self.tick += 1;
+        let tick = self.tick;
+        self.entries.get_mut(key).map(|(body, used)| {
+            *used = tick;
+            body.clone()
+        })
+    }
+
+    /// Cache `body`, evicting the least recently used texts to stay under the
+    /// ceiling. A text larger than the whole ceiling is not cached.
+    pub fn insert(&mut self, key: String, body: String) {
+        if let Some((old, _)) = self.entries.remove(&key) {
+            self.used_bytes -= old.len();
+        }
+        if body.len() > self.capacity_bytes {
+            return;
+        }
+        self.tick += 1;
+        self.used_bytes += body.len();
+        self.entries.insert(key, (body, self.tick));
+        self.evict();
+    }
+
+    pub fn set_capacity(&mut self, capacity_bytes: usize) {
+        self.capacity_bytes = capacity_bytes;
+        self.evict();
+    }
+
+    pub fn usage(&self) -> CacheUsage {
+        CacheUsage {
+            used_bytes: self.used_bytes,
+            capacity_bytes: self.capacity_bytes,
+            entries: self.entries.len(),
+        }
+    }
+
+    fn evict(&mut self) {
+        while self.used_bytes > self.capacity_bytes {
+            let Some(oldest) = self
+                .entries
+                .iter()
+                .min_by_key(|(_, (_, used))| *used)
+                .map(|(k, _)| k.clone())
+            else {
+                break;
+            };
+            if let Some((body, _)) = self.entries.remove(&oldest) {
+                self.used_bytes -= body.len();
+            }
+        }
+    }
+}
+
+/// What the index keeps of a stowed passage: its opening and its most frequent terms
+pub fn sketch(content: &str) -> String {
+    let mut freq: HashMap<String, usize> = HashMap::new();
+    for t in content_terms(content) {
+        *freq.entry(t).or_insert(0) += 1;
+    }
+    let mut terms: Vec<(String, usize)> = freq.into_iter().collect();
+    terms.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    let terms: Vec<String> = terms
+        .into_iter()
+        .take(SKETCH_TERMS)
+        .map(|(t, _)| t)
+        .collect();
+    let mut out: String = content.chars().take(SKETCH_OPENING_CHARS).collect();
+    out.push_str(SKETCH_MARKER);
+    out.push_str(&terms.join(" "));
+    out
+}
+
+fn is_sketch(content: &str) -> bool {
+    content.contains(SKETCH_MARKER)
+}
+
+fn content_key(doc_id: &str, stowed: &StowedContent) -> String {
+    format!(
+        "{}:{}:{:016x}",
+        CONTENT_KEY_PREFIX, doc_id, stowed.fingerprint
+    )
+}
+
+/// Whether `key` names a full-text record
+pub fn is_record_key(key: &str) -> bool {
+    key.strip_prefix(CONTENT_KEY_PREFIX)
+        .is_some_and(|rest| rest.starts_with(':'))
+}
+
+/// Keys of the full-text records, which are never preloaded
+pub fn record_keys() -> Vec<String> {
+    load_index()
+        .iter()
+        .map(|(id, stowed)| content_key(id, stowed))
+        .collect()
+}
+
+pub fn load_index() -> StowedIndex {
+    PersistentStore::read(STOWED_CONTENT_KEY)
+        .ok()
+        .flatten()
+        .unwrap_or_default()
+}
+
+/// Bring the full-text records in line with the index about to be saved. With
+/// `stow_new`, passages still carrying their full text are moved out and replaced by
+/// their sketch. Records of passages that were removed, or reindexed with their full
+/// text while the mode was off, are deleted.
+pub fn sync(documents: &mut [DocumentIndex], stow_new: bool) -> AppResult<()> {
+    let mut index = load_index();
+    let before = index.clone();
+    let mut live = HashSet::new();
+    for d in documents.iter_mut() {
+        if index.contains_key(&d.id) && is_sketch(&d.content) {
+            live.insert(d.id.clone());
+            continue;
+        }
+        if !stow_new || d.content.is_empty() {
+            continue;
+        }
+        let stowed = StowedContent {
+            chars: d.content.chars().count(),
+            fingerprint: fingerprint(&d.content),
+        };
+        if let Some(old) = index.get(&d.id).filter(|old| *old != &stowed) {
+            PersistentStore::remove(&content_key(&d.id, old))?;
+        }
+        PersistentStore::write_cold(&content_key(&d.id, &stowed), &d.content)?;
+        d.content = sketch(&d.content);
+        index.insert(d.id.clone(), stowed);
+        live.insert(d.id.clone());
+    }
+    let stale: Vec<String> = index
+        .keys()
+        .filter(|k| !live.contains(*k))
+        .cloned()
+        .collect();
+    for id in stale {
+        if let Some(stowed) = index.remove(&id) {
+            PersistentStore::remove(&content_key(&id, &stowed))?;
+        }
+    }
+    if index != before {
+        PersistentStore::write(STOWED_CONTENT_KEY, &index)?;
+    }
+    Ok(())
+}
+
+/// Full texts of the stowed passages among `documents`, by document id, read from
+/// the cache or from disk. Passages kept in the index, and any whose record cannot
+/// be read, are left out so callers fall back to the indexed content.
+pub async fn load_bodies<'a>(
+    documents: impl IntoIterator<Item = &'a DocumentIndex>,
+    ceiling_mb: u32,
+) -> HashMap<String, String> {
+    CACHE.with(|c| {
+        c.borrow_mut()
+            .set_capacity(ceiling_mb as usize * BYTES_PER_MB)
+    });
+    let index = load_index();
+    let mut bodies = HashMap::new();
+    for d in documents {
+        let Some(stowed) = index.get(&d.id).filter(|_| is_sketch(&d.content)) else {
+            continue;
+        };
+        let key = content_key(&d.id, stowed);
+        if let Some(body) = CACHE.with(|c| c.borrow_mut().get(&key)) {
+            bodies.insert(d.id.clone(), body);
+            continue;
+        }
+        match PersistentStore::read_cold::<String>(&key).await {
+            Ok(Some(body)) => {
+                CACHE.with(|c| c.borrow_mut().insert(key, body.clone()));
+                bodies.insert(d.id.clone(), body);
+            }
+            Ok(None) => log::warn!("Full text of {} is missing", d.id),
+            Err(e) => log::warn!("Full text of {} not read: {}", d.id, e),
+        }
+    }
+    bodies
+}
+
+pub fn cache_usage() -> CacheUsage {
+    CACHE.with(|c| c.borrow().usage())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache_evicts_least_recently_used_past_the_ceiling() {
+        let mut cache = ContentCache::new(10);
+        cache.insert("a".into(), "aaaa".into());
+        cache.insert("b".into(), "bbbb".into());
+        // Touching "a" makes "b" the oldest
+        assert_eq!(cache.get("a").as_deref(), Some("aaaa"));
+        cache.insert("c".into(), "cccc".into());
+        assert_eq!(cache.get("b"), None);
+        assert!(cache.get("a").is_some() && cache.get("c").is_some());
+        assert_eq!(cache.usage().used_bytes, 8);
+
+        // Too large for the ceiling: not cached, nothing evicted
+        cache.insert("d".into(), "d".repeat(11));
+        assert_eq!(cache.usage().entries, 2);
+
+        cache.set_capacity(4);
+        assert_eq!(cache.usage().entries, 1);
+        assert!(cache.usage().used_bytes <= 4);
+
+        let text = "Refund policy. Refunds are issued within 30 days; refund requests \
+                    need the order number.";
+        let sketched = sketch(text);
+        assert!(is_sketch(&sketched));
+        assert!(!is_sketch(text));
+        // Most frequent first, then alphabetical; short words and stopwords left out
+        assert!(sketched
+            .ends_with("refund days issued need number order policy refunds requests within"));
+    }
+}
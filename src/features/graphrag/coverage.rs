@@ -0,0 +1,223 @@
+use super::retrieval::Retriever;
+use super::summarizer::content_terms;
+use crate::models::graphrag::{RAGQuery, SearchStrategy};
+use std::collections::HashSet;
+
+// Share of question terms the best document must contain
+const STRONG_COVERAGE: f32 = 0.6;
+const WEAK_COVERAGE: f32 = 0.25;
+
+/// How well the knowledge base supports a question
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CoverageLevel {
+    Strong,
+    Weak,
+    None,
+}
+
+impl CoverageLevel {
+    pub fn label(&self) -> &'static str {
+        match self {
+            CoverageLevel::Strong => "Strong",
+            CoverageLevel::Weak => "Weak",
+            CoverageLevel::None => "No support",
+        }
+    }
+
+    /// daisyUI badge class
+    pub fn badge_class(&self) -> &'static str {
+        match self {
+            CoverageLevel::Strong => "badge-success",
+            CoverageLevel::Weak => "badge-warning",
+            CoverageLevel::None => "badge-error",
+        }
+    }
+}
+
+/// Retrieval-only assessment of one question
+#[derive(Clone, Debug, PartialEq)]
+pub struct QuestionCoverage {
+    pub question: String,
+    pub level: CoverageLevel,
+    /// Share of question terms found in the best supporting document (0..1)
+    pub term_coverage: f32,
+    /// Titles of the documents that contain question terms, best first
+    pub sources: Vec<String>,
+    /// Question terms found in none of the retrieved documents
+    pub missing_terms: Vec<String>,
+}
+
+impl QuestionCoverage {
+    /// Grade a question against retrieved `(title, content)` documents
+    pub fn assess(question: &str, docs: &[(String, String)]) -> Self {
+        let mut terms = content_terms(question);
+        let mut seen = HashSet::new();
+        terms.retain(|t| seen.insert(t.clone()));
+
+        let mut found: HashSet<String> = HashSet::new();
+        let mut ranked: Vec<(f32, &str)> = Vec::new();
+        for (title, content) in docs {
+            let doc_terms: HashSet<String> = content_terms(&format!("{} {}", title, content))
+                .into_iter()
+                .collect();
+            let hits: Vec<&str> = terms
+                .iter()
+                .filter(|t| doc_terms.contains(*t))
+                .map(|t| t.as_str())
+                .collect();
+            if hits.is_empty() || terms.is_empty() {
+                continue;
+            }
+            found.extend(hits.iter().map(|t| t.to_string()));
+            ranked.push((hits.len() as f32 / terms.len() as f32, title.as_str()));
+        }
+        ranked.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+
+        let term_coverage = ranked.first().map(|(c, _)| *c).unwrap_or(0.0);
+        let level = if term_coverage >= STRONG_COVERAGE {
+            CoverageLevel::Strong
+        } else if term_coverage >= WEAK_COVERAGE {
+            CoverageLevel::Weak
+        } else {
+            CoverageLevel::None
+        };
+        Self {
+            question: question.trim().to_string(),
+            level,
+            term_coverage,
+            sources: ranked.into_iter().map(|(_, t)| t.to_string()).collect(),
+            missing_terms: terms.into_iter().filter(|t| !found.contains(t)).collect(),
+        }
+    }
+}
+
+/// Coverage of a whole question set
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CoverageReport {
+    pub questions: Vec<QuestionCoverage>,
+}
+
+impl CoverageReport {
+    /// Run retrieval (no generation) for every non-empty line of `questions`
+    pub async fn run(questions: &str) -> Self {
+        let retriever = Retriever::new();
+        let mut report = Self::default();
+        for question in questions.lines().map(str::trim).filter(|q| !q.is_empty()) {
+            let result = retriever
+                .search(
+                    &RAGQuery::new(question.to_string()),
+                    SearchStrategy::Combined,
+                )
+                .await;
+            let docs: Vec<(String, String)> = result
+                .nodes
+                .into_iter()
+                .map(|n| (n.metadata.source.unwrap_or_default(), n.content))
+                .collect();
+            report
+                .questions
+                .push(QuestionCoverage::assess(question, &docs));
+        }
+        report
+    }
+
+    pub fn count(&self, level: CoverageLevel) -> usize {
+        self.questions.iter().filter(|q| q.level == level).count()
+    }
+
+    /// Question terms missing from the knowledge base, most frequent first
+    pub fn gaps(&self) -> Vec<String> {
+        let mut counts: Vec<(String, usize)> = Vec::new();
+        for term in self.questions.iter().flat_map(|q| q.missing_terms.iter()) {
+            match counts.iter_mut().find(|(t, _)| t == term) {
+                Some((_, n)) => *n += 1,
+                None => counts.push((term.clone(), 1)),
+            }
+        }
+        counts.sort_by(|a, b| b.1.cmp(&a.1));
+        counts.into_iter().map(|(t, _)| t).collect()
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut out = format!(
+            "# Knowledge coverage report\n\n{} question(s): {} strong, {} weak, {} without support.\n",
+            self.questions.len(),
+            self.count(CoverageLevel::Strong),
+            self.count(CoverageLevel::Weak),
+            self.count(CoverageLevel::None)
+        );
+        let gaps = self.gaps();
+        if !gaps.is_empty() {
+            out.push_str(&format!("\nGaps: {}\n", gaps.join(", ")));
+        }
+        out.push_str(
+            "\n| Question | Coverage | Best sources | Missing terms |\n|---|---|---|---|\n",
+        );
+        for q in &self.questions {
+            out.push_str(&format!(
+                "| {} | {} ({:.0}%) | {} | {} |\n",
+                q.question.replace('|', "\\|"),
+                q.level.label(),
+                q.term_coverage * 100.0,
+                q.sources
+                    .iter()
+                    .take(3)
+                    .cloned()
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                q.missing_terms.join(", ")
+            ));
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn doc(title: &str, content: &str) -> (String, String) {
+        (title.to_string(), content.to_string())
+    }
+
+    #[test]
+    fn test_assess_grades_by_best_document() {
+        let docs = vec![
+            doc(
+                "pricing.md",
+                "Enterprise pricing includes support and onboarding.",
+            ),
+            doc("faq.md", "Support hours are listed here."),
+        ];
+        let strong = QuestionCoverage::assess("What enterprise pricing includes?", &docs);
+        assert_eq!(strong.level, CoverageLevel::Strong);
+        assert_eq!(strong.sources, vec!["pricing.md".to_string()]);
+
+        let weak = QuestionCoverage::assess("Is there support for refunds and chargebacks?", &docs);
+        assert_eq!(weak.level, CoverageLevel::Weak);
+        assert_eq!(weak.missing_terms, vec!["refunds", "chargebacks"]);
+
+        let none = QuestionCoverage::assess("Where is the office?", &docs);
+        assert_eq!(none.level, CoverageLevel::None);
+        assert!(none.sources.is_empty());
+    }
+
+    #[test]
+    fn test_report_counts_and_gaps() {
+        let docs = vec![doc("a.md", "Rust wasm engine")];
+        let report = CoverageReport {
+            questions: vec![
+                QuestionCoverage::assess("rust engine", &docs),
+                QuestionCoverage::assess("python engine", &docs),
+                QuestionCoverage::assess("python notebooks", &docs),
+            ],
+        };
+        assert_eq!(report.count(CoverageLevel::Strong), 1);
+        assert_eq!(report.count(CoverageLevel::Weak), 1);
+        assert_eq!(report.count(CoverageLevel::None), 1);
+        assert_eq!(report.gaps(), vec!["python", "notebooks"]);
+        assert!(report
+            .to_markdown()
+            .contains("3 question(s): 1 strong, 1 weak, 1 without support."));
+    }
+}
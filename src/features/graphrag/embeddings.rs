@@ -0,0 +1,365 @@
+use crate::models::app::{AppError, AppResult};
+use crate::models::graphrag::DocumentIndex;
+use crate::storage::persistent::PersistentStore;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use wasm_bindgen::JsValue;
+
+/// WebLLM embedding model used when none is configured
+pub const DEFAULT_EMBEDDING_MODEL: &str = "snowflake-arctic-embed-m-q0f32-MLC-b4";
+
+/// Embedding models suggested for collections, as (model id, label). Any other model
+/// id WebLLM can load (e.g. a multilingual build) may be typed in.
+pub const EMBEDDING_MODELS: [(&str, &str); 2] = [
+    (DEFAULT_EMBEDDING_MODEL, "Arctic Embed M (English)"),
+    (
+        "snowflake-arctic-embed-s-q0f32-MLC-b4",
+        "Arctic Embed S (English, faster)",
+    ),
+];
+
+pub const VECTOR_INDEX_KEY: &str = "graphrag_vector_index_v1";
+const CHUNK_CHARS: usize = 1200;
+const CHUNK_OVERLAP: usize = 200;
+// Inputs per `embeddings.create` call; matches the `-b4` model builds
+const EMBED_BATCH: usize = 4;
+
+thread_local! {
+    // Loaded embedding engine and its model id; reused across index and query calls
+    static EMBEDDER: RefCell<Option<(String, JsValue)>> = const { RefCell::new(None) };
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct VectorEntry {
+    pub doc_id: String,
+    pub chunk_index: usize,
+    pub vector: Vec<f32>,
+    /// Model that produced the vector; vectors of different models are never compared
+    #[serde(default)]
+    pub model: String,
+}
+
+/// Persisted chunk embeddings for all indexed documents, possibly from several models
+/// (one per knowledge collection)
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct VectorIndex {
+    /// Model of every entry in indexes saved before entries carried their own
+    #[serde(default, rename = "model", skip_serializing)]
+    legacy_model: String,
+    pub entries: Vec<VectorEntry>,
+}
+
+impl VectorIndex {
+    pub fn load() -> Self {
+        let mut index = PersistentStore::read::<VectorIndex>(VECTOR_INDEX_KEY)
+            .ok()
+            .flatten()
+            .unwrap_or_default();
+        index.migrate_legacy_model();
+        index
+    }
+
+    fn migrate_legacy_model(&mut self) {
+        let legacy = std::mem::take(&mut self.legacy_model);
+        for e in self.entries.iter_mut().filter(|e| e.model.is_empty()) {
+            e.model = legacy.clone();
+        }
+    }
+
+    pub fn save(&self) -> AppResult<()> {
+        PersistentStore::write(VECTOR_INDEX_KEY, self)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Vector length of `model`, once it has entries
+    pub fn dims(&self, model: &str) -> Option<usize> {
+        self.entries
+            .iter()
+            .find(|e| e.model == model)
+            .map(|e| e.vector.len())
+    }
+
+    /// Models with vectors in the index
+    pub fn models(&self) -> Vec<String> {
+        let mut models: Vec<String> = Vec::new();
+        for e in &self.entries {
+            if !models.contains(&e.model) {
+                models.push(e.model.clone());
+            }
+        }
+        models
+    }
+
+    /// Model a document's vectors were produced with, if it has any
+    pub fn model_of(&self, doc_id: &str) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|e| e.doc_id == doc_id)
+            .map(|e| e.model.as_str())
+    }
+
+    /// Replace all chunk vectors of a document with vectors from `model`
+    pub fn upsert_document(
+        &mut self,
+        doc_id: &str,
+        model: &str,
+        vectors: Vec<Vec<f32>>,
+    ) -> AppResult<()> {
+        if let Some(dims) = self.dims(model) {
+            if let Some(v) = vectors.iter().find(|v| v.len() != dims) {
+                return Err(AppError::validation(format!(
+                    "Embedding has {} dimensions, {} produces {}",
+                    v.len(),
+                    model,
+                    dims
+                )));
+            }
+        }
+        self.remove_document(doc_id);
+        self.entries.extend(
+            vectors
+                .into_iter()
+                .enumerate()
+                .map(|(chunk_index, vector)| VectorEntry {
+                    doc_id: doc_id.to_string(),
+                    chunk_index,
+                    vector,
+                    model: model.to_string(),
+                }),
+        );
+        Ok(())
+    }
+
+    pub fn remove_document(&mut self, doc_id: &str) {
+        self.entries.retain(|e| e.doc_id != doc_id);
+    }
+
+    /// Best chunk similarity per document, over the vectors of the model that
+    /// embedded `query`
+    pub fn search(&self, model: &str, query: &[f32]) -> HashMap<String, f32> {
+        let mut best: HashMap<String, f32> = HashMap::new();
+        for e in self.entries.iter().filter(|e| e.model == model) {
+            let sim = cosine_similarity(query, &e.vector);
+            let slot = best.entry(e.doc_id.clone()).or_insert(sim);
+            if sim > *slot {
+                *slot = sim;
+            }
+        }
+        best
+    }
+}
+
+/// Split text into overlapping chunks of at most `max_chars` characters,
+/// breaking at whitespace where possible
+pub fn chunk_text(text: &str, max_chars: usize, overlap: usize) -> Vec<String> {
+    let chars: Vec<char> = text.chars().collect();
+    let max_chars = max_chars.max(1);
+    let overlap = overlap.min(max_chars / 2);
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    while start < chars.len() {
+        let mut end = (start + max_chars).min(chars.len());
+        if end < chars.len() {
+            // Back off to the last whitespace in the second half of the window
+            if let Some(ws) = (start + max_chars / 2..end)
+                .rev()
+                .find(|&i| chars[i].is_whitespace())
+            {
+                end = ws;
+            }
+        }
+        let chunk: String = chars[start..end].iter().collect();
+        if !chunk.trim().is_empty() {
+            chunks.push(chunk.trim().to_string());
+        }
+        if end >= chars.len() {
+            break;
+        }
+        start = end.saturating_sub(overlap).max(start + 1);
+    }
+    chunks
+}
+
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() || a.is_empty() {
+        return 0.0;
+    }
+    let mut dot = 0.0f32;
+    let mut na = 0.0f32;
+    let mut nb = 0.0f32;
+    for (x, y) in a.iter().zip(b) {
+        dot += x * y;
+        na += x * x;
+        nb += y * y;
+    }
+    if na == 0.0 || nb == 0.0 {
+        return 0.0;
+    }
+    dot / (na.sqrt() * nb.sqrt())
+}
+
+/// Blend lexical and semantic scores per document. Lexical scores are
+/// max-normalized, cosine similarities are clamped to 0..=1.
+pub fn fuse_scores(lexical: &[f32], semantic: &[f32], semantic_weight: f32) -> Vec<f32> {
+    let w = semantic_weight.clamp(0.0, 1.0);
+    let max_lex = lexical.iter().cloned().fold(0.0f32, f32::max);
+    lexical
+        .iter()
+        .enumerate()
+        .map(|(i, l)| {
+            let l = if max_lex > 0.0 { l / max_lex } else { 0.0 };
+            let s = semantic.get(i).copied().unwrap_or(0.0).clamp(0.0, 1.0);
+            (1.0 - w) * l + w * s
+        })
+        .collect()
+}
+
+async fn embedder(model: &str) -> AppResult<JsValue> {
+    let cached = EMBEDDER.with(|e| {
+        e.borrow()
+            .as_ref()
+            .filter(|(m, _)| m == model)
+            .map(|(_, engine)| engine.clone())
+    });
+    if let Some(engine) = cached {
+        return Ok(engine);
+    }
+    let engine = crate::webllm_binding::init_webllm_with_progress(model, |_text, _progress| {})
+        .await
+        .map_err(|e| AppError::InternalError(format!("Failed to load embedding model: {:?}", e)))?;
+    EMBEDDER.with(|e| *e.borrow_mut() = Some((model.to_string(), engine.clone())));
+    Ok(engine)
+}
+
+/// Embed texts in batches with the given model
+pub async fn embed_texts(model: &str, texts: &[String]) -> AppResult<Vec<Vec<f32>>> {
+    let engine = embedder(model).await?;
+    let mut out = Vec::with_capacity(texts.len());
+    for batch in texts.chunks(EMBED_BATCH) {
+        let vectors = crate::webllm_binding::embed_texts(&engine, batch)
+            .await
+            .map_err(|e| AppError::InternalError(format!("Embedding failed: {:?}", e)))?;
+        out.extend(vectors);
+    }
+    Ok(out)
+}
+
+/// Passages of a document that get one vector each
+pub fn document_chunks(d: &DocumentIndex) -> Vec<String> {
+    let text = if d.content.is_empty() {
+        &d.title
+    } else {
+        &d.content
+    };
+    chunk_text(text, CHUNK_CHARS, CHUNK_OVERLAP)
+}
+
+/// Chunk and embed documents into `index`, each with the model `model_for` assigns it;
+/// a document's vectors from another model are replaced. Returns the number of chunks
+/// embedded.
+pub async fn embed_documents(
+    docs: &[DocumentIndex],
+    model_for: impl Fn(&DocumentIndex) -> String,
+    index: &mut VectorIndex,
+) -> AppResult<usize> {
+    let mut total = 0;
+    for d in docs {
+        let model = model_for(d);
+        let chunks = document_chunks(d);
+        if chunks.is_empty() {
+            index.remove_document(&d.id);
+            continue;
+        }
+        let vectors = embed_texts(&model, &chunks).await?;
+        total += vectors.len();
+        index.upsert_document(&d.id, &model, vectors)?;
+    }
+    Ok(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunk_text_overlaps_and_breaks_on_whitespace() {
+        let text = "alpha beta gamma delta epsilon zeta eta theta";
+        let chunks = chunk_text(text, 16, 6);
+        assert!(chunks.len() > 1);
+        assert!(chunks.iter().all(|c| c.chars().count() <= 16));
+        assert_eq!(chunks[0], "alpha beta");
+        assert!(chunks.last().unwrap().ends_with("theta"));
+        assert_eq!(chunk_text("short", 100, 10), vec!["short".to_string()]);
+        assert!(chunk_text("   ", 10, 2).is_empty());
+    }
+
+    #[test]
+    fn test_cosine_similarity() {
+        assert!((cosine_similarity(&[1.0, 0.0], &[2.0, 0.0]) - 1.0).abs() < 1e-6);
+        assert!(cosine_similarity(&[1.0, 0.0], &[0.0, 1.0]).abs() < 1e-6);
+        assert_eq!(cosine_similarity(&[1.0], &[1.0, 2.0]), 0.0);
+        assert_eq!(cosine_similarity(&[0.0, 0.0], &[1.0, 1.0]), 0.0);
+    }
+
+    #[test]
+    fn test_vector_index_search_takes_best_chunk() {
+        let mut index = VectorIndex::default();
+        index
+            .upsert_document("a", "m", vec![vec![0.0, 1.0], vec![1.0, 0.0]])
+            .unwrap();
+        index
+            .upsert_document("b", "m", vec![vec![0.5, 0.5]])
+            .unwrap();
+        assert!(index
+            .upsert_document("c", "m", vec![vec![1.0, 2.0, 3.0]])
+            .is_err());
+
+        let hits = index.search("m", &[1.0, 0.0]);
+        assert!((hits["a"] - 1.0).abs() < 1e-6);
+        assert!(hits["b"] < hits["a"]);
+
+        index
+            .upsert_document("a", "m", vec![vec![0.0, 1.0]])
+            .unwrap();
+        assert_eq!(index.entries.len(), 2);
+        index.remove_document("a");
+        assert!(!index.search("m", &[1.0, 0.0]).contains_key("a"));
+    }
+
+    #[test]
+    fn test_models_are_never_compared_across() {
+        let mut index: VectorIndex =
+            serde_json::from_str(r#"{"model": "old", "dims": 2, "entries": [{"doc_id": "a", "chunk_index": 0, "vector": [1.0, 0.0]}]}"#)
+                .unwrap();
+        index.migrate_legacy_model();
+        assert_eq!(index.model_of("a"), Some("old"));
+
+        // Another model may use another vector length
+        index
+            .upsert_document("b", "new", vec![vec![1.0, 0.0, 0.0]])
+            .unwrap();
+        assert_eq!(index.models(), vec!["old".to_string(), "new".to_string()]);
+        assert!(!index.search("new", &[1.0, 0.0, 0.0]).contains_key("a"));
+        assert!(!index.search("old", &[1.0, 0.0]).contains_key("b"));
+
+        // Re-embedding moves a document to the new model
+        index
+            .upsert_document("a", "new", vec![vec![0.0, 1.0, 0.0]])
+            .unwrap();
+        assert_eq!(index.models(), vec!["new".to_string()]);
+    }
+
+    #[test]
+    fn test_fuse_scores() {
+        let fused = fuse_scores(&[4.0, 2.0, 0.0], &[0.0, 1.0, 0.9], 0.5);
+        assert!((fused[0] - 0.5).abs() < 1e-6);
+        assert!((fused[1] - 0.75).abs() < 1e-6);
+        assert!((fused[2] - 0.45).abs() < 1e-6);
+        // Weight 0 keeps the lexical ranking
+        assert_eq!(fuse_scores(&[2.0, 1.0], &[0.0, 1.0], 0.0), vec![1.0, 0.5]);
+    }
+}
@@ -0,0 +1,265 @@
+//! Retrieval evaluation against golden question sets. Each question lists the sources
+//! a good answer should be built from; a run sends every question through `Retriever`
+//! with the current configuration and reports recall@k, MRR and latency, so runs under
+//! different settings can be compared side by side.
+
+use super::retrieval::Retriever;
+use crate::graphrag_config::GraphRAGConfig;
+use crate::models::graphrag::RAGQuery;
+use serde::{Deserialize, Serialize};
+
+/// A question and the sources expected among its results
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GoldenCase {
+    pub question: String,
+    /// Document ids, titles or uploaded file names
+    #[serde(alias = "expected")]
+    pub expected_sources: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum GoldenSet {
+    Cases(Vec<GoldenCase>),
+    Wrapped { cases: Vec<GoldenCase> },
+}
+
+/// Parse a golden set: a JSON array of cases, or an object with a `cases` array
+pub fn parse_golden_set(json: &str) -> Result<Vec<GoldenCase>, String> {
+    let set: GoldenSet = serde_json::from_str(json)
+        .map_err(|e| format!("Expected a list of question/expected_sources pairs: {}", e))?;
+    let cases = match set {
+        GoldenSet::Cases(cases) | GoldenSet::Wrapped { cases } => cases,
+    };
+    if cases.is_empty() {
+        return Err("The set has no questions".to_string());
+    }
+    if let Some(n) = cases
+        .iter()
+        .position(|c| c.question.trim().is_empty() || c.expected_sources.is_empty())
+    {
+        return Err(format!(
+            "Question {} needs text and at least one expected source",
+            n + 1
+        ));
+    }
+    Ok(cases)
+}
+
+/// Outcome of one question
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct CaseResult {
+    pub question: String,
+    /// Titles of the top results, best first
+    pub retrieved: Vec<String>,
+    /// 1-based rank of the first expected source in the top k
+    pub first_hit: Option<usize>,
+    /// Share of the expected sources found in the top k
+    pub recall: f32,
+    pub latency_ms: f64,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct LatencyStats {
+    pub mean_ms: f64,
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+    pub max_ms: f64,
+}
+
+/// Scores of one run
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct EvalReport {
+    /// Configuration the run used, see `config_label`
+    pub label: String,
+    pub k: usize,
+    pub recall_at_k: f32,
+    pub mrr: f32,
+    pub latency: LatencyStats,
+    pub cases: Vec<CaseResult>,
+}
+
+/// Whether a result (document id and title) is the `expected` source. Passages of an
+/// uploaded file match the file name, since their ids are `<indexed at>:<file>#<anchor>`.
+pub fn source_matches(id: &str, title: &str, expected: &str) -> bool {
+    let expected = expected.trim();
+    let file = id.split_once(':').map_or(id, |(_, rest)| rest);
+    let file = file.split_once('#').map_or(file, |(f, _)| f);
+    [id, title, file]
+        .iter()
+        .any(|s| s.eq_ignore_ascii_case(expected))
+}
+
+/// Score the ranked `(id, title)` results of `case`
+pub fn score_case(
+    case: &GoldenCase,
+    results: &[(String, String)],
+    k: usize,
+    latency_ms: f64,
+) -> CaseResult {
+    let top = &results[..results.len().min(k)];
+    let found = case
+        .expected_sources
+        .iter()
+        .filter(|e| top.iter().any(|(id, title)| source_matches(id, title, e)))
+        .count();
+    let first_hit = top
+        .iter()
+        .position(|(id, title)| {
+            case.expected_sources
+                .iter()
+                .any(|e| source_matches(id, title, e))
+        })
+        .map(|p| p + 1);
+    CaseResult {
+        question: case.question.clone(),
+        retrieved: top.iter().map(|(_, title)| title.clone()).collect(),
+        first_hit,
+        recall: found as f32 / case.expected_sources.len().max(1) as f32,
+        latency_ms,
+    }
+}
+
+/// Mean recall@k, MRR and latency percentiles of the scored cases
+pub fn summarize(label: String, k: usize, cases: Vec<CaseResult>) -> EvalReport {
+    let n = cases.len().max(1) as f32;
+    let recall_at_k = cases.iter().map(|c| c.recall).sum::<f32>() / n;
+    let mrr = cases
+        .iter()
+        .map(|c| c.first_hit.map_or(0.0, |r| 1.0 / r as f32))
+        .sum::<f32>()
+        / n;
+    let mut latencies: Vec<f64> = cases.iter().map(|c| c.latency_ms).collect();
+    latencies.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    // Nearest-rank percentile
+    let percentile = |p: f64| -> f64 {
+        if latencies.is_empty() {
+            return 0.0;
+        }
+        let rank = ((p * latencies.len() as f64).ceil() as usize).clamp(1, latencies.len());
+        latencies[rank - 1]
+    };
+    let latency = LatencyStats {
+        mean_ms: latencies.iter().sum::<f64>() / latencies.len().max(1) as f64,
+        p50_ms: percentile(0.5),
+        p95_ms: percentile(0.95),
+        max_ms: latencies.last().copied().unwrap_or(0.0),
+    };
+    EvalReport {
+        label,
+        k,
+        recall_at_k,
+        mrr,
+        latency,
+        cases,
+    }
+}
+
+/// Short description of the settings a run used, to tell runs apart
+pub fn config_label(config: &GraphRAGConfig, k: usize) -> String {
+    let mut parts = vec![format!("{:?}", config.search_strategy), format!("k={}", k)];
+    let features = [
+        (config.hyde_enabled, "HyDE".to_string()),
+        (
+            config.community_detection_enabled,
+            "communities".to_string(),
+        ),
+        (config.pagerank_enabled, "PageRank".to_string()),
+        (config.embeddings_enabled, "embeddings".to_string()),
+        (config.reranking_enabled, "rerank".to_string()),
+        (config.mmr_enabled, format!("MMR λ{:.1}", config.mmr_lambda)),
+    ];
+    parts.extend(features.into_iter().filter(|(on, _)| *on).map(|(_, f)| f));
+    parts.join(" · ")
+}
+
+/// Run every case through the retriever with `config`, bypassing the query cache so
+/// latencies are real. `on_progress` receives the number of questions done.
+pub async fn run(
+    cases: &[GoldenCase],
+    config: &GraphRAGConfig,
+    k: usize,
+    mut on_progress: impl FnMut(usize),
+) -> EvalReport {
+    let k = k.max(1);
+    let retriever = Retriever::new();
+    let mut scored = Vec::with_capacity(cases.len());
+    for (i, case) in cases.iter().enumerate() {
+        let mut q = RAGQuery::new(case.question.clone());
+        q.config.max_results = k;
+        q.config.use_hyde = config.hyde_enabled;
+        q.config.use_community_detection = config.community_detection_enabled;
+        q.config.use_reranking = config.reranking_enabled;
+        q.config.bypass_cache = true;
+        let started = js_sys::Date::now();
+        let result = retriever.search(&q, config.search_strategy.clone()).await;
+        let latency_ms = js_sys::Date::now() - started;
+        let results: Vec<(String, String)> = result
+            .nodes
+            .iter()
+            .map(|n| {
+                let title = n.metadata.source.clone().unwrap_or_else(|| n.id.clone());
+                (n.id.clone(), title)
+            })
+            .collect();
+        scored.push(score_case(case, &results, k, latency_ms));
+        on_progress(i + 1);
+    }
+    summarize(config_label(config, k), k, scored)
+}
+
+/// A golden set in the format `parse_golden_set` reads, for people starting one
+pub const EXAMPLE_SET: &str = r#"[
+  { "question": "How long do customers have to request a refund?", "expected_sources": ["refund-policy.md"] },
+  { "question": "Which plans include SSO?", "expected_sources": ["pricing.md", "security.md"] }
+]"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hit(id: &str, title: &str) -> (String, String) {
+        (id.to_string(), title.to_string())
+    }
+
+    #[test]
+    fn test_scores_recall_mrr_and_latency() {
+        let cases = parse_golden_set(EXAMPLE_SET).unwrap();
+        assert_eq!(cases.len(), 2);
+        assert!(parse_golden_set(r#"{"cases": []}"#).is_err());
+        assert!(parse_golden_set(r#"[{"question": "q", "expected": []}]"#).is_err());
+
+        // Passages of refund-policy.md match the file name
+        let first = score_case(
+            &cases[0],
+            &[
+                hit("1:faq.md", "faq.md"),
+                hit("2:refund-policy.md#chunk=1", "refund-policy.md (part 1)"),
+            ],
+            5,
+            40.0,
+        );
+        assert_eq!(first.first_hit, Some(2));
+        assert_eq!(first.recall, 1.0);
+
+        // Only one of two expected sources, and only within the top k
+        let second = score_case(
+            &cases[1],
+            &[
+                hit("3:pricing.md", "pricing.md"),
+                hit("4:security.md", "security.md"),
+            ],
+            1,
+            10.0,
+        );
+        assert_eq!(second.recall, 0.5);
+        assert_eq!(second.retrieved, vec!["pricing.md".to_string()]);
+
+        let report = summarize("test".to_string(), 5, vec![first, second]);
+        assert_eq!(report.recall_at_k, 0.75);
+        assert_eq!(report.mrr, 0.75);
+        assert_eq!(report.latency.mean_ms, 25.0);
+        assert_eq!(report.latency.p50_ms, 10.0);
+        assert_eq!(report.latency.p95_ms, 40.0);
+    }
+}
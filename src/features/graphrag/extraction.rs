@@ -1,7 +1,22 @@
+use crate::models::app::{AppError, AppResult};
 use crate::models::graph_store::{GraphEdge, GraphNode};
 use crate::models::graphrag::DocumentIndex;
-use serde_json::{json, Value};
+use crate::models::{Message, MessageRole};
+use crate::utils::tables::{row_cells, typed_properties};
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
 use std::collections::{HashMap, HashSet};
+use wasm_bindgen::JsValue;
+
+// Passage size shared by the heuristic and LLM extractors so passage indexes line up
+const PASSAGE_CHARS: usize = 500;
+
+const LLM_EXTRACTION_INSTRUCTIONS: &str = "You extract a knowledge graph from a passage. \
+Reply with a single JSON object and nothing else, shaped as \
+{\"entities\": [{\"name\": \"...\", \"type\": \"person|organization|location|product|concept|event|other\"}], \
+\"relations\": [{\"subject\": \"...\", \"predicate\": \"snake_case_verb\", \"object\": \"...\"}]}. \
+Write entity names exactly as they appear in the passage. \
+Only include relations stated in the passage. Use empty arrays when there is nothing to extract.";
 
 /// WASM-safe, heuristic NER/RE stub.
 /// - Creates a document node per `DocumentIndex`
@@ -32,23 +47,30 @@ pub fn extract_entities_relations(docs: &[DocumentIndex]) -> (Vec<GraphNode>, Ve
         }
     }
 
+    let mut rows = row_properties(docs);
     for d in docs {
-        // Document node
+        // Document node; rows of a dataset also carry their cells as typed properties
         let doc_id = unique_id(&format!("doc:{}", d.id), &mut existing_ids);
+        let mut metadata = json!({
+            "file_type": d.file_type,
+            "size_bytes": d.size_bytes,
+            "created_at": d.created_at,
+        });
+        if let Some((row, properties)) = rows.remove(d.id.as_str()) {
+            metadata["dataset"] = json!(d.source_file());
+            metadata["row"] = json!(row);
+            metadata["properties"] = Value::Object(properties);
+        }
         nodes.push(GraphNode {
             id: doc_id.clone(),
             label: Some(d.title.clone()),
             node_type: "document".to_string(),
             source_document_id: Some(d.id.clone()),
-            metadata: json!({
-                "file_type": d.file_type,
-                "size_bytes": d.size_bytes,
-                "created_at": d.created_at,
-            }),
+            metadata,
         });
 
         // 1) Chunk markdown into passages
-        let passages = chunk_markdown(&d.content, 500);
+        let passages = chunk_markdown(&d.content, PASSAGE_CHARS);
 
         // For collecting back-references per entity: entity -> array of {doc, passage_idx}
         let mut ent_backrefs: HashMap<String, Vec<Value>> = HashMap::new();
@@ -182,6 +204,262 @@ pub fn extract_entities_relations(docs: &[DocumentIndex]) -> (Vec<GraphNode>, Ve
     (nodes, edges)
 }
 
+/// Entities and typed relations the model found in one passage
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct LlmExtraction {
+    pub entities: Vec<LlmEntity>,
+    pub relations: Vec<LlmRelation>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct LlmEntity {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub entity_type: String,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct LlmRelation {
+    pub subject: String,
+    pub predicate: String,
+    pub object: String,
+}
+
+/// Heuristic extraction enriched with entities and typed relations from the loaded
+/// chat model, one request per passage. Passages the model fails on keep only the
+/// heuristic results. `on_progress` receives `(done, total)` passages.
+pub async fn extract_entities_relations_llm(
+    engine: &JsValue,
+    model_id: &str,
+    docs: &[DocumentIndex],
+    on_progress: impl Fn(usize, usize),
+) -> (Vec<GraphNode>, Vec<GraphEdge>) {
+    let (mut nodes, mut edges) = extract_entities_relations(docs);
+    let passages: Vec<(&DocumentIndex, usize, String)> = docs
+        .iter()
+        .flat_map(|d| {
+            chunk_markdown(&d.content, PASSAGE_CHARS)
+                .into_iter()
+                .enumerate()
+                .filter(|(_, p)| !p.trim().is_empty())
+                .map(move |(pidx, p)| (d, pidx, p))
+        })
+        .collect();
+    let total = passages.len();
+    for (done, (d, pidx, passage)) in passages.into_iter().enumerate() {
+        let request = build_llm_extraction_messages(&d.title, &passage);
+        match crate::webllm_binding::send_message_to_llm(engine, request).await {
+            Ok(response) => match parse_llm_extraction(&response) {
+                Ok(extraction) => {
+                    merge_llm_extraction(&mut nodes, &mut edges, d, pidx, &extraction, model_id)
+                }
+                Err(e) => log::warn!("LLM extraction of {} #{} skipped: {}", d.id, pidx, e),
+            },
+            Err(e) => log::warn!("LLM extraction of {} #{} failed: {:?}", d.id, pidx, e),
+        }
+        on_progress(done + 1, total);
+    }
+    (nodes, edges)
+}
+
+/// Row number and typed cells of every table row among `docs`, by document id. Column
+/// types are inferred over the rows of the same file.
+fn row_properties(docs: &[DocumentIndex]) -> HashMap<&str, (usize, Map<String, Value>)> {
+    let mut by_file: HashMap<&str, Vec<(&DocumentIndex, usize)>> = HashMap::new();
+    for d in docs {
+        let row =
+            d.id.rsplit_once("#row=")
+                .and_then(|(_, n)| n.parse::<usize>().ok());
+        if let Some(row) = row {
+            by_file.entry(d.source_file()).or_default().push((d, row));
+        }
+    }
+    let mut out = HashMap::new();
+    for rows in by_file.into_values() {
+        let cells: Vec<Vec<(String, String)>> =
+            rows.iter().map(|(d, _)| row_cells(&d.content)).collect();
+        for ((d, row), properties) in rows.into_iter().zip(typed_properties(&cells)) {
+            out.insert(d.id.as_str(), (row, properties));
+        }
+    }
+    out
+}
+
+/// Messages asking the model for the entities and relations of one passage
+pub fn build_llm_extraction_messages(title: &str, passage: &str) -> Vec<Message> {
+    vec![
+        Message::new(MessageRole::System, LLM_EXTRACTION_INSTRUCTIONS.to_string()),
+        Message::new(
+            MessageRole::User,
+            format!("Document: {}\nPassage:\n{}\n\nJSON:", title, passage.trim()),
+        ),
+    ]
+}
+
+/// Parse the model reply, tolerating code fences and surrounding prose. Blank names
+/// are dropped and predicates normalized to snake_case.
+pub fn parse_llm_extraction(response: &str) -> AppResult<LlmExtraction> {
+    let json = match (response.find('{'), response.rfind('}')) {
+        (Some(s), Some(e)) if e > s => &response[s..=e],
+        _ => {
+            return Err(AppError::SerializationError(
+                "No JSON object found in model output".to_string(),
+            ))
+        }
+    };
+    let mut out: LlmExtraction = serde_json::from_str(json)
+        .map_err(|e| AppError::SerializationError(format!("Invalid extraction: {}", e)))?;
+    out.entities.retain_mut(|e| {
+        e.name = e.name.trim().to_string();
+        e.entity_type = e.entity_type.trim().to_lowercase();
+        !e.name.is_empty()
+    });
+    out.relations.retain_mut(|r| {
+        r.subject = r.subject.trim().to_string();
+        r.object = r.object.trim().to_string();
+        r.predicate = snake_case(&r.predicate);
+        !r.subject.is_empty() && !r.object.is_empty() && !r.predicate.is_empty()
+    });
+    Ok(out)
+}
+
+/// Add one passage's LLM extraction to the graph. Entities are matched to existing
+/// nodes by case-insensitive label; every new node and edge records the model, the
+/// document and the passage it came from.
+pub fn merge_llm_extraction(
+    nodes: &mut Vec<GraphNode>,
+    edges: &mut Vec<GraphEdge>,
+    doc: &DocumentIndex,
+    passage_index: usize,
+    extraction: &LlmExtraction,
+    model_id: &str,
+) {
+    let mut existing_ids: HashSet<String> = nodes
+        .iter()
+        .map(|n| n.id.clone())
+        .chain(edges.iter().map(|e| e.id.clone()))
+        .collect();
+    let mut entity_map: HashMap<String, String> = nodes
+        .iter()
+        .filter(|n| n.node_type == "entity")
+        .filter_map(|n| Some((n.label.as_ref()?.to_lowercase(), n.id.clone())))
+        .collect();
+    let doc_node = nodes
+        .iter()
+        .find(|n| n.node_type == "document" && n.source_document_id.as_ref() == Some(&doc.id))
+        .map(|n| n.id.clone());
+    let backref = json!({"doc_id": doc.id, "passage_index": passage_index});
+    let provenance = json!({
+        "source": "llm",
+        "model": model_id,
+        "doc_id": doc.id,
+        "passage_index": passage_index,
+    });
+
+    let types: HashMap<String, &str> = extraction
+        .entities
+        .iter()
+        .map(|e| (e.name.to_lowercase(), e.entity_type.as_str()))
+        .collect();
+    let names = extraction.entities.iter().map(|e| e.name.as_str()).chain(
+        extraction
+            .relations
+            .iter()
+            .flat_map(|r| [r.subject.as_str(), r.object.as_str()]),
+    );
+    let mut seen: HashSet<String> = HashSet::new();
+    for name in names {
+        let key = name.to_lowercase();
+        if !seen.insert(key.clone()) {
+            continue;
+        }
+        let entity_type = types.get(&key).copied().filter(|t| !t.is_empty());
+        let ent_id = match entity_map.get(&key) {
+            Some(id) => id.clone(),
+            None => {
+                let id = unique_like("ent", name, &mut existing_ids);
+                entity_map.insert(key.clone(), id.clone());
+                nodes.push(GraphNode {
+                    id: id.clone(),
+                    label: Some(name.to_string()),
+                    node_type: "entity".to_string(),
+                    source_document_id: None,
+                    metadata: json!({
+                        "aliases": [name],
+                        "backrefs": [],
+                        "source": "llm",
+                        "model": model_id,
+                    }),
+                });
+                id
+            }
+        };
+        if let Some(node) = nodes.iter_mut().find(|n| n.id == ent_id) {
+            if !node.metadata.is_object() {
+                node.metadata = json!({});
+            }
+            if let Some(t) = entity_type {
+                if node.metadata.get("entity_type").is_none() {
+                    node.metadata["entity_type"] = json!(t);
+                }
+            }
+            match node.metadata.get_mut("backrefs") {
+                Some(Value::Array(a)) => {
+                    if !a.contains(&backref) {
+                        a.push(backref.clone());
+                    }
+                }
+                _ => node.metadata["backrefs"] = json!([backref.clone()]),
+            }
+        }
+        if let Some(doc_id) = &doc_node {
+            let base = format!("e:{}->{}#p{}", doc_id, ent_id, passage_index);
+            if !existing_ids.contains(&base) {
+                existing_ids.insert(base.clone());
+                edges.push(GraphEdge {
+                    id: base,
+                    from: doc_id.clone(),
+                    to: ent_id,
+                    relation: "mentions".to_string(),
+                    weight: 1.0,
+                    metadata: provenance.clone(),
+                });
+            }
+        }
+    }
+
+    for r in &extraction.relations {
+        let (Some(sid), Some(oid)) = (
+            entity_map.get(&r.subject.to_lowercase()).cloned(),
+            entity_map.get(&r.object.to_lowercase()).cloned(),
+        ) else {
+            continue;
+        };
+        let base = format!("e:{}:{}->{}#p{}", r.predicate, sid, oid, passage_index);
+        if existing_ids.contains(&base) {
+            continue;
+        }
+        existing_ids.insert(base.clone());
+        let mut metadata = provenance.clone();
+        metadata["triple"] = json!({
+            "subject": r.subject,
+            "predicate": r.predicate,
+            "object": r.object,
+        });
+        edges.push(GraphEdge {
+            id: base,
+            from: sid,
+            to: oid,
+            relation: r.predicate.clone(),
+            weight: 1.0,
+            metadata,
+        });
+    }
+}
+
 // --- helpers ---
 
 fn chunk_markdown(content: &str, max_len: usize) -> Vec<String> {
@@ -289,3 +567,13 @@ fn simple_relation_extraction(passage: &str) -> Vec<(String, String, String)> {
     }
     triples
 }
+
+fn snake_case(predicate: &str) -> String {
+    predicate
+        .trim()
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .collect::<Vec<_>>()
+        .join("_")
+}
@@ -1,5 +1,7 @@
 use crate::models::graphrag::{GraphEdge, GraphNode};
 
+pub mod resolution;
+
 /// Placeholder for community detection and pagerank functions.
 pub struct GraphAnalytics;
 
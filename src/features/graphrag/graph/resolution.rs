@@ -0,0 +1,472 @@
+//! Entity resolution: entity nodes that name the same thing ("Acme", "ACME Inc.",
+//! "the Acme") are merged into one. Names are compared by a key that ignores case,
+//! punctuation, a leading "the", possessives, plurals and company suffixes, recorded
+//! aliases are compared too, and an all-caps acronym joins the one entity whose
+//! initials it spells. Edges are moved to the kept node and the merged nodes are
+//! listed in its `merged_from` metadata.
+
+use crate::models::graph_store::GraphStore;
+use serde_json::{json, Map, Value};
+use std::collections::{HashMap, HashSet};
+
+const COMPANY_SUFFIXES: &[&str] = &[
+    "inc",
+    "ltd",
+    "llc",
+    "corp",
+    "corporation",
+    "co",
+    "gmbh",
+    "plc",
+    "sa",
+    "ag",
+];
+const ACRONYM_FILLERS: &[&str] = &["of", "and", "the", "for", "de"];
+
+/// Entities folded into another one
+#[derive(Clone, Debug, PartialEq)]
+pub struct MergedEntity {
+    pub kept_id: String,
+    pub label: String,
+    pub merged_labels: Vec<String>,
+}
+
+/// Outcome of a resolution pass
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ResolutionReport {
+    pub merged: Vec<MergedEntity>,
+    /// Edges moved to a kept node
+    pub rewired_edges: usize,
+    /// Edges dropped as copies of another edge, or as loops between two merged names
+    pub dropped_edges: usize,
+}
+
+impl ResolutionReport {
+    /// Entity nodes removed by the pass
+    pub fn merged_nodes(&self) -> usize {
+        self.merged.iter().map(|m| m.merged_labels.len()).sum()
+    }
+}
+
+/// Name with the variations that do not change the entity removed
+pub fn resolution_key(name: &str) -> String {
+    let mut words: Vec<String> = name
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+    if words.len() > 1 && words[0] == "the" {
+        words.remove(0);
+    }
+    // "Acme's" splits into "acme" and "s"
+    if words.len() > 1 && words.last().is_some_and(|w| w == "s") {
+        words.pop();
+    }
+    while words.len() > 1
+        && words
+            .last()
+            .is_some_and(|w| COMPANY_SUFFIXES.contains(&w.as_str()))
+    {
+        words.pop();
+    }
+    if let Some(last) = words.last_mut() {
+        if last.len() > 3 && last.ends_with('s') && !last.ends_with("ss") {
+            last.pop();
+        }
+    }
+    words.join(" ")
+}
+
+/// Merge the entity nodes of `store` that refer to the same entity
+pub fn resolve_entities(store: &mut GraphStore) -> ResolutionReport {
+    let entities: Vec<usize> = (0..store.nodes.len())
+        .filter(|&i| store.nodes[i].node_type == "entity")
+        .collect();
+    let mut groups = UnionFind::new(store.nodes.len());
+
+    // Same key, from the label or a recorded alias
+    let mut holders: HashMap<String, usize> = HashMap::new();
+    for &i in &entities {
+        for key in names(&store.nodes[i].metadata, store.nodes[i].label.as_deref())
+            .iter()
+            .map(|n| resolution_key(n))
+            .filter(|k| !k.is_empty())
+        {
+            match holders.get(&key) {
+                Some(&j) if compatible(store, i, j) => groups.union(i, j),
+                Some(_) => {}
+                None => {
+                    holders.insert(key, i);
+                }
+            }
+        }
+    }
+
+    // Acronyms spelling the initials of exactly one entity
+    let mut by_initials: HashMap<String, Vec<usize>> = HashMap::new();
+    for &i in &entities {
+        if let Some(initials) = store.nodes[i].label.as_deref().and_then(initials) {
+            by_initials.entry(initials).or_default().push(i);
+        }
+    }
+    for &i in &entities {
+        let Some(label) = store.nodes[i].label.as_deref() else {
+            continue;
+        };
+        if !is_acronym(label) {
+            continue;
+        }
+        if let Some([j]) = by_initials.get(&label.to_lowercase()).map(Vec::as_slice) {
+            if compatible(store, i, *j) {
+                groups.union(i, *j);
+            }
+        }
+    }
+
+    let mut members: HashMap<usize, Vec<usize>> = HashMap::new();
+    for &i in &entities {
+        members.entry(groups.find(i)).or_default().push(i);
+    }
+    let mut degree: HashMap<&str, usize> = HashMap::new();
+    for e in &store.edges {
+        *degree.entry(e.from.as_str()).or_default() += 1;
+        *degree.entry(e.to.as_str()).or_default() += 1;
+    }
+
+    // The best connected node of each group is kept, the longest name on ties
+    let mut groups: Vec<Vec<usize>> = members.into_values().filter(|g| g.len() > 1).collect();
+    for group in groups.iter_mut() {
+        group.sort_by(|&a, &b| {
+            let (na, nb) = (&store.nodes[a], &store.nodes[b]);
+            let da = degree.get(na.id.as_str()).copied().unwrap_or(0);
+            let db = degree.get(nb.id.as_str()).copied().unwrap_or(0);
+            db.cmp(&da)
+                .then_with(|| label_len(nb).cmp(&label_len(na)))
+                .then_with(|| na.id.cmp(&nb.id))
+        });
+    }
+    groups.sort_by(|a, b| store.nodes[a[0]].id.cmp(&store.nodes[b[0]].id));
+
+    let mut report = ResolutionReport::default();
+    let mut redirect: HashMap<String, String> = HashMap::new();
+    let mut removed: HashSet<usize> = HashSet::new();
+    for group in &groups {
+        let (kept, others) = (group[0], &group[1..]);
+        let absorbed: Vec<_> = others.iter().map(|&i| store.nodes[i].clone()).collect();
+        let node = &mut store.nodes[kept];
+        let mut metadata = match node.metadata.take() {
+            Value::Object(map) => map,
+            _ => Map::new(),
+        };
+        let mut aliases = names(&Value::Object(metadata.clone()), node.label.as_deref());
+        let mut backrefs = array(&metadata, "backrefs");
+        let mut merged_from = array(&metadata, "merged_from");
+        for other in &absorbed {
+            for alias in names(&other.metadata, other.label.as_deref()) {
+                if !aliases.contains(&alias) {
+                    aliases.push(alias);
+                }
+            }
+            if let Value::Object(fields) = &other.metadata {
+                for backref in fields
+                    .get("backrefs")
+                    .and_then(Value::as_array)
+                    .into_iter()
+                    .flatten()
+                {
+                    if !backrefs.contains(backref) {
+                        backrefs.push(backref.clone());
+                    }
+                }
+                for (key, value) in fields {
+                    if !metadata.contains_key(key) && key != "community" {
+                        metadata.insert(key.clone(), value.clone());
+                    }
+                }
+            }
+            merged_from.push(json!({"id": other.id, "label": other.label}));
+            redirect.insert(other.id.clone(), node.id.clone());
+        }
+        metadata.insert("aliases".to_string(), json!(aliases));
+        metadata.insert("backrefs".to_string(), Value::Array(backrefs));
+        metadata.insert("merged_from".to_string(), Value::Array(merged_from));
+        node.metadata = Value::Object(metadata);
+        removed.extend(others);
+        report.merged.push(MergedEntity {
+            kept_id: node.id.clone(),
+            label: node.label.clone().unwrap_or_else(|| node.id.clone()),
+            merged_labels: absorbed
+                .iter()
+                .map(|n| n.label.clone().unwrap_or_else(|| n.id.clone()))
+                .collect(),
+        });
+    }
+    if removed.is_empty() {
+        return report;
+    }
+    store.nodes = std::mem::take(&mut store.nodes)
+        .into_iter()
+        .enumerate()
+        .filter(|(i, _)| !removed.contains(i))
+        .map(|(_, node)| node)
+        .collect();
+
+    // Edges follow their endpoints; copies of an edge and new self-loops are dropped
+    let mut seen: HashSet<(String, String, String, String, String)> = HashSet::new();
+    let before = store.edges.len();
+    let mut rewired = 0;
+    store.edges.retain_mut(|e| {
+        let from = redirect.get(&e.from).cloned();
+        let to = redirect.get(&e.to).cloned();
+        let moved = from.is_some() || to.is_some();
+        if moved {
+            if from.as_ref().unwrap_or(&e.from) == to.as_ref().unwrap_or(&e.to) {
+                return false;
+            }
+            let original = json!({"from": e.from, "to": e.to});
+            match e.metadata.as_object_mut() {
+                Some(fields) => {
+                    fields.insert("resolved_from".to_string(), original);
+                }
+                None => e.metadata = json!({ "resolved_from": original }),
+            }
+            e.from = from.unwrap_or_else(|| e.from.clone());
+            e.to = to.unwrap_or_else(|| e.to.clone());
+        }
+        let kept = seen.insert((
+            e.from.clone(),
+            e.to.clone(),
+            e.relation.clone(),
+            e.metadata
+                .get("doc_id")
+                .map(Value::to_string)
+                .unwrap_or_default(),
+            e.metadata
+                .get("passage_index")
+                .map(Value::to_string)
+                .unwrap_or_default(),
+        ));
+        if kept && moved {
+            rewired += 1;
+        }
+        kept
+    });
+    report.dropped_edges = before - store.edges.len();
+    report.rewired_edges = rewired;
+    report
+}
+
+// Label and recorded aliases of a node
+fn names(metadata: &Value, label: Option<&str>) -> Vec<String> {
+    let mut names: Vec<String> = label.map(str::to_string).into_iter().collect();
+    for alias in metadata
+        .get("aliases")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+    {
+        if !names.iter().any(|n| n == alias) {
+            names.push(alias.to_string());
+        }
+    }
+    names
+}
+
+fn array(metadata: &Map<String, Value>, key: &str) -> Vec<Value> {
+    metadata
+        .get(key)
+        .and_then(Value::as_array)
+        .cloned()
+        .unwrap_or_default()
+}
+
+fn label_len(node: &crate::models::graph_store::GraphNode) -> usize {
+    node.label.as_deref().map_or(0, str::len)
+}
+
+// Entities typed differently by the extractor are kept apart
+fn compatible(store: &GraphStore, a: usize, b: usize) -> bool {
+    let type_of = |i: usize| {
+        store.nodes[i]
+            .metadata
+            .get("entity_type")
+            .and_then(Value::as_str)
+            .map(str::to_string)
+    };
+    match (type_of(a), type_of(b)) {
+        (Some(ta), Some(tb)) => ta == tb,
+        _ => true,
+    }
+}
+
+fn is_acronym(label: &str) -> bool {
+    (2..=6).contains(&label.len()) && label.chars().all(|c| c.is_ascii_uppercase())
+}
+
+// Lowercase initials of a name of several words, e.g. "International Business Machines"
+fn initials(label: &str) -> Option<String> {
+    let words: Vec<&str> = label
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty() && !ACRONYM_FILLERS.contains(&w.to_lowercase().as_str()))
+        .collect();
+    (words.len() >= 2).then(|| {
+        words
+            .iter()
+            .filter_map(|w| w.chars().next())
+            .flat_map(char::to_lowercase)
+            .collect()
+    })
+}
+
+struct UnionFind {
+    parent: Vec<usize>,
+}
+
+impl UnionFind {
+    fn new(size: usize) -> Self {
+        Self {
+            parent: (0..size).collect(),
+        }
+    }
+
+    fn find(&mut self, i: usize) -> usize {
+        let mut root = i;
+        while self.parent[root] != root {
+            root = self.parent[root];
+        }
+        let mut i = i;
+        while self.parent[i] != root {
+            let next = self.parent[i];
+            self.parent[i] = root;
+            i = next;
+        }
+        root
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let (ra, rb) = (self.find(a), self.find(b));
+        if ra != rb {
+            self.parent[ra.max(rb)] = ra.min(rb);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::graph_store::{GraphEdge, GraphNode};
+
+    fn entity(id: &str, label: &str, metadata: Value) -> GraphNode {
+        GraphNode {
+            id: id.to_string(),
+            label: Some(label.to_string()),
+            node_type: "entity".to_string(),
+            source_document_id: None,
+            metadata,
+        }
+    }
+
+    fn edge(id: &str, from: &str, to: &str, relation: &str, passage: usize) -> GraphEdge {
+        GraphEdge {
+            id: id.to_string(),
+            from: from.to_string(),
+            to: to.to_string(),
+            relation: relation.to_string(),
+            weight: 1.0,
+            metadata: json!({"doc_id": "d1", "passage_index": passage}),
+        }
+    }
+
+    #[test]
+    fn test_resolution_key_ignores_surface_variations() {
+        assert_eq!(resolution_key("Acme"), "acme");
+        assert_eq!(resolution_key("ACME Inc."), "acme");
+        assert_eq!(resolution_key("the Acme Corp"), "acme");
+        assert_eq!(resolution_key("Acme's"), "acme");
+        assert_eq!(resolution_key("Refunds"), "refund");
+        assert_eq!(resolution_key("Business"), "business");
+        assert_eq!(resolution_key("The"), "the");
+    }
+
+    #[test]
+    fn test_resolve_merges_variants_and_rewires_edges() {
+        let mut store = GraphStore::new();
+        store.add_node(GraphNode {
+            id: "doc:d1".to_string(),
+            label: Some("notes.md".to_string()),
+            node_type: "document".to_string(),
+            source_document_id: Some("d1".to_string()),
+            metadata: json!({}),
+        });
+        store.add_node(entity(
+            "ent:Acme",
+            "Acme",
+            json!({"aliases": ["Acme"], "backrefs": [{"doc_id": "d1", "passage_index": 0}]}),
+        ));
+        store.add_node(entity(
+            "ent:ACME Inc",
+            "ACME Inc",
+            json!({"aliases": ["ACME Inc"], "backrefs": [{"doc_id": "d1", "passage_index": 1}]}),
+        ));
+        store.add_node(entity("ent:IBM", "IBM", json!({})));
+        store.add_node(entity(
+            "ent:International Business Machines",
+            "International Business Machines",
+            json!({"entity_type": "organization"}),
+        ));
+        store.add_node(entity("ent:Paris", "Paris", json!({"entity_type": "city"})));
+        store.add_node(entity(
+            "ent:paris",
+            "paris",
+            json!({"entity_type": "person"}),
+        ));
+        store.add_edge(edge("e1", "doc:d1", "ent:Acme", "mentions", 0));
+        store.add_edge(edge("e2", "doc:d1", "ent:ACME Inc", "mentions", 1));
+        store.add_edge(edge("e3", "doc:d1", "ent:Acme", "mentions", 1));
+        store.add_edge(edge("e4", "ent:Acme", "ent:ACME Inc", "is_a", 0));
+        store.add_edge(edge("e5", "ent:IBM", "ent:Acme", "partner_of", 2));
+
+        let report = resolve_entities(&mut store);
+        assert_eq!(report.merged_nodes(), 2);
+        assert_eq!(
+            report.merged,
+            vec![
+                MergedEntity {
+                    kept_id: "ent:Acme".to_string(),
+                    label: "Acme".to_string(),
+                    merged_labels: vec!["ACME Inc".to_string()],
+                },
+                MergedEntity {
+                    kept_id: "ent:IBM".to_string(),
+                    label: "IBM".to_string(),
+                    merged_labels: vec!["International Business Machines".to_string()],
+                },
+            ]
+        );
+        // Differently typed entities stay apart
+        assert!(store.nodes.iter().any(|n| n.id == "ent:paris"));
+        assert_eq!(store.nodes.len(), 5);
+
+        let acme = store.nodes.iter().find(|n| n.id == "ent:Acme").unwrap();
+        assert_eq!(acme.metadata["aliases"], json!(["Acme", "ACME Inc"]));
+        assert_eq!(acme.metadata["backrefs"].as_array().unwrap().len(), 2);
+        assert_eq!(acme.metadata["merged_from"][0]["id"], "ent:ACME Inc");
+        let ibm = store.nodes.iter().find(|n| n.id == "ent:IBM").unwrap();
+        assert_eq!(ibm.metadata["entity_type"], "organization");
+
+        // e2 moves to Acme, e3 then repeats it and e4 became a loop
+        let ids: Vec<&str> = store.edges.iter().map(|e| e.id.as_str()).collect();
+        assert_eq!(ids, vec!["e1", "e2", "e5"]);
+        assert_eq!(store.edges[1].to, "ent:Acme");
+        assert_eq!(
+            store.edges[1].metadata["resolved_from"]["to"],
+            "ent:ACME Inc"
+        );
+        assert_eq!(report.rewired_edges, 1);
+        assert_eq!(report.dropped_edges, 2);
+
+        // A second pass finds nothing left to merge
+        assert_eq!(resolve_entities(&mut store), ResolutionReport::default());
+    }
+}
@@ -0,0 +1,487 @@
+use super::content_store;
+use super::embeddings::VectorIndex;
+use super::pipeline::{GraphRAGPipeline, DOCUMENT_INDEX_KEY_V1};
+use crate::models::app::{AppError, AppResult};
+use crate::models::graph_store::GraphStore;
+use crate::models::graphrag::{DocumentIndex, ProcessingStatus};
+use crate::pagerank_reranking::NodeImportance;
+use crate::storage::persistent::PersistentStore;
+use crate::utils::storage::StorageUtils;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::HashSet;
+
+/// localStorage key of the active index generation
+pub const INDEX_GENERATION_KEY: &str = "graphrag_index_generation_v1";
+
+thread_local! {
+    // Generation bookkeeping shared by every build and query of this tab
+    static GENERATIONS: RefCell<Option<IndexGenerations>> = const { RefCell::new(None) };
+}
+
+/// Which index generation queries read and which one is being built
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct IndexGenerations {
+    pub active: u64,
+    /// Not persisted, so a build interrupted by a reload does not block later ones
+    #[serde(skip)]
+    pub building: Option<u64>,
+    /// Documents deleted from the active index while the build runs; its commit drops
+    /// them too, so they do not come back with the shadow copy
+    #[serde(skip)]
+    pub removed_during_build: Vec<String>,
+}
+
+impl IndexGenerations {
+    /// Read from localStorage each time rather than through the tiered cache, whose
+    /// memory tier would hide a generation committed by another tab
+    pub fn load() -> Self {
+        StorageUtils::retrieve_local(INDEX_GENERATION_KEY)
+            .ok()
+            .flatten()
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> AppResult<()> {
+        StorageUtils::store_local(INDEX_GENERATION_KEY, self)
+    }
+
+    /// Reserve the next generation; only one build may run at a time
+    pub fn begin(&mut self) -> AppResult<u64> {
+        if let Some(generation) = self.building {
+            return Err(AppError::validation(format!(
+                "Index generation {} is still being built",
+                generation
+            )));
+        }
+        let next = self.active + 1;
+        self.building = Some(next);
+        self.removed_during_build.clear();
+        Ok(next)
+    }
+
+    /// Pick up the generation `stored` by another tab. A build of this tab keeps its
+    /// reservation only while no other tab committed that generation or a later one.
+    pub fn sync(&mut self, stored: &IndexGenerations) {
+        self.active = stored.active;
+        if self.building.is_some_and(|b| b <= self.active) {
+            self.building = None;
+            self.removed_during_build.clear();
+        }
+    }
+
+    /// Note documents deleted from the active index; false when no build is running,
+    /// so the delete can run as a build of its own
+    pub fn remove_during_build(&mut self, ids: &[String]) -> bool {
+        if self.building.is_none() {
+            return false;
+        }
+        self.removed_during_build.extend(ids.iter().cloned());
+        true
+    }
+
+    /// Commit a finished build against the generations `stored` now. `write` persists
+    /// its data, after dropping the documents given to it (deleted during the build);
+    /// it is not called when another tab committed first, so a stale build never
+    /// overwrites a newer index.
+    pub fn commit_build(
+        &mut self,
+        stored: &IndexGenerations,
+        generation: u64,
+        write: impl FnOnce(&[String]) -> AppResult<()>,
+    ) -> AppResult<()> {
+        self.sync(stored);
+        if self.building != Some(generation) {
+            return Err(AppError::validation(format!(
+                "Index generation {} was superseded by generation {} built in another tab",
+                generation, self.active
+            )));
+        }
+        if let Err(e) = write(&self.removed_during_build) {
+            self.abort(generation);
+            return Err(e);
+        }
+        self.commit(generation)
+    }
+
+    /// Make a finished build the generation queries read
+    pub fn commit(&mut self, generation: u64) -> AppResult<()> {
+        if self.building != Some(generation) {
+            return Err(AppError::validation(format!(
+                "Index generation {} is not being built",
+                generation
+            )));
+        }
+        self.active = generation;
+        self.building = None;
+        self.removed_during_build.clear();
+        Ok(())
+    }
+
+    pub fn abort(&mut self, generation: u64) {
+        if self.building == Some(generation) {
+            self.building = None;
+            self.removed_during_build.clear();
+        }
+    }
+
+    /// Generation a query may read: the active one unless a specific one is requested.
+    /// A generation still being built, or one that was superseded, is refused.
+    pub fn resolve(&self, requested: Option<u64>) -> AppResult<u64> {
+        match requested {
+            None => Ok(self.active),
+            Some(g) if g == self.active => Ok(g),
+            Some(g) if self.building == Some(g) => Err(AppError::validation(format!(
+                "Index generation {} is still being built",
+                g
+            ))),
+            Some(g) => Err(AppError::validation(format!(
+                "Index generation {} is no longer available (active: {})",
+                g, self.active
+            ))),
+        }
+    }
+}
+
+fn with_generations<R>(f: impl FnOnce(&mut IndexGenerations) -> R) -> R {
+    GENERATIONS.with(|g| f(g.borrow_mut().get_or_insert_with(IndexGenerations::load)))
+}
+
+/// Generation state of this tab
+pub fn index_generations() -> IndexGenerations {
+    with_generations(|g| g.clone())
+}
+
+/// Pick up a generation another tab committed. A build running in this tab keeps its
+/// reservation; its commit is refused if the other tab got there first.
+pub fn reload_generations() {
+    let stored = IndexGenerations::load();
+    with_generations(|g| g.sync(&stored));
+}
+
+/// Documents, graph, vectors and node importance of one index generation.
+/// Reads and writes happen without awaiting in between, so a commit never lands
+/// halfway through either.
+#[derive(Clone, Debug, Default)]
+pub struct IndexSnapshot {
+    pub generation: u64,
+    pub documents: Vec<DocumentIndex>,
+    pub graph: GraphStore,
+    pub vectors: VectorIndex,
+    pub importance: Option<NodeImportance>,
+}
+
+impl IndexSnapshot {
+    /// Snapshot for a query targeting `requested` (the active generation when `None`)
+    pub fn for_query(requested: Option<u64>) -> AppResult<Self> {
+        let generation = with_generations(|g| g.resolve(requested))?;
+        Ok(Self::read(generation))
+    }
+
+    /// Start building the next generation in a shadow copy of the active one.
+    /// Fails while another build is running.
+    pub fn begin_build() -> AppResult<ShadowIndex> {
+        // Start from what another tab may have committed meanwhile
+        reload_generations();
+        let generation = with_generations(|g| g.begin())?;
+        Ok(ShadowIndex {
+            index: Self::read(generation),
+            finished: false,
+        })
+    }
+
+    fn read(generation: u64) -> Self {
+        Self {
+            generation,
+            documents: GraphRAGPipeline::new()
+                .indexed_documents()
+                .unwrap_or_default(),
+            graph: GraphStore::load().unwrap_or_default(),
+            vectors: VectorIndex::load(),
+            importance: NodeImportance::load(),
+        }
+    }
+
+    fn write(&mut self) -> AppResult<()> {
+        // Large knowledge base mode moves full texts out of the index record
+        let large_kb = GraphRAGPipeline::new().config().large_kb_mode;
+        content_store::sync(&mut self.documents, large_kb)?;
+        PersistentStore::write(DOCUMENT_INDEX_KEY_V1, &self.documents)?;
+        self.graph.save()?;
+        self.vectors.save()?;
+        match &self.importance {
+            Some(importance) => importance.save(),
+            None => Ok(()),
+        }
+    }
+
+    /// Insert or replace documents by id, marking them indexed at `now`
+    pub fn upsert_documents(&mut self, docs: &[DocumentIndex], batch_size: usize, now: f64) {
+        for chunk in docs.chunks(batch_size.max(1)) {
+            for d in chunk.iter().cloned() {
+                let mut updated = d;
+                updated.indexed_at = now;
+                updated.processing_status = ProcessingStatus::Completed;
+                if let Some(slot) = self.documents.iter_mut().find(|x| x.id == updated.id) {
+                    *slot = updated;
+                } else {
+                    self.documents.push(updated);
+                }
+            }
+        }
+    }
+
+    /// Drop documents with their graph nodes and vectors. Returns how many were indexed.
+    pub fn remove_documents(&mut self, ids: &[String]) -> usize {
+        let idset: HashSet<&String> = ids.iter().collect();
+        let before = self.documents.len();
+        self.documents.retain(|d| !idset.contains(&d.id));
+        for id in ids {
+            self.graph.remove_document_cascade(id);
+            self.vectors.remove_document(id);
+        }
+        before - self.documents.len()
+    }
+
+    /// Drop every document whose title is in `titles`. Returns how many were indexed.
+    pub fn remove_titles(&mut self, titles: &[String]) -> usize {
+        let ids = self.ids_of_titles(titles);
+        self.remove_documents(&ids)
+    }
+
+    fn ids_of_titles(&self, titles: &[String]) -> Vec<String> {
+        self.documents
+            .iter()
+            .filter(|d| titles.contains(&d.title))
+            .map(|d| d.id.clone())
+            .collect()
+    }
+
+    /// Delete documents by id, also while a build runs: they leave the active index at
+    /// once and the build drops them when it commits. Returns how many were indexed.
+    pub fn delete_documents(ids: &[String]) -> AppResult<usize> {
+        if ids.is_empty() {
+            return Ok(0);
+        }
+        reload_generations();
+        if with_generations(|g| g.building.is_none()) {
+            let mut shadow = Self::begin_build()?;
+            let removed = shadow.index.remove_documents(ids);
+            shadow.commit()?;
+            return Ok(removed);
+        }
+        let mut active = Self::read(index_generations().active);
+        let removed = active.remove_documents(ids);
+        active.write()?;
+        with_generations(|g| g.remove_during_build(ids));
+        Ok(removed)
+    }
+
+    /// Like `delete_documents`, for every document whose title is in `titles`
+    pub fn delete_titles(titles: &[String]) -> AppResult<usize> {
+        let ids = Self::read(index_generations().active).ids_of_titles(titles);
+        Self::delete_documents(&ids)
+    }
+}
+
+/// A generation under construction. Queries keep reading the active generation until
+/// `commit`; dropping it uncommitted abandons the build.
+pub struct ShadowIndex {
+    pub index: IndexSnapshot,
+    finished: bool,
+}
+
+impl ShadowIndex {
+    pub fn generation(&self) -> u64 {
+        self.index.generation
+    }
+
+    /// Persist the shadow copy and make it the active generation. Refused, with
+    /// nothing written, when another tab committed a build first.
+    pub fn commit(mut self) -> AppResult<u64> {
+        self.finished = true;
+        let generation = self.index.generation;
+        let stored = IndexGenerations::load();
+        let index = &mut self.index;
+        with_generations(|g| {
+            g.commit_build(&stored, generation, |removed| {
+                index.remove_documents(removed);
+                index.write()
+            })?;
+            g.save()
+        })?;
+        Ok(generation)
+    }
+}
+
+impl Drop for ShadowIndex {
+    fn drop(&mut self) {
+        if !self.finished {
+            let generation = self.index.generation;
+            with_generations(|g| g.abort(generation));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn doc(id: &str) -> DocumentIndex {
+        DocumentIndex {
+            id: id.to_string(),
+            title: format!("{}.md", id),
+            content: format!("content of {}", id),
+            file_type: "markdown".to_string(),
+            size_bytes: 10,
+            created_at: 0.0,
+            indexed_at: 0.0,
+            node_count: 0,
+            embedding_model: None,
+            processing_status: ProcessingStatus::Pending,
+            symbols: Vec::new(),
+            parent: None,
+        }
+    }
+
+    #[test]
+    fn test_queries_during_build_read_the_active_generation() {
+        let mut gens = IndexGenerations {
+            active: 3,
+            ..Default::default()
+        };
+        let building = gens.begin().unwrap();
+        assert_eq!(building, 4);
+
+        // A search starting mid-build reads generation 3 and cannot target 4
+        assert_eq!(gens.resolve(None).unwrap(), 3);
+        assert!(gens.resolve(Some(4)).is_err());
+        // A second reindex interleaving with the first is refused
+        assert!(gens.begin().is_err());
+
+        gens.commit(building).unwrap();
+        assert_eq!(gens.resolve(None).unwrap(), 4);
+        assert!(gens.resolve(Some(3)).is_err());
+        assert!(gens.commit(building).is_err());
+    }
+
+    #[test]
+    fn test_aborted_build_keeps_active_generation() {
+        let mut gens = IndexGenerations::default();
+        let building = gens.begin().unwrap();
+        gens.abort(building);
+        assert_eq!(gens.resolve(None).unwrap(), 0);
+        assert_eq!(gens.begin().unwrap(), 1);
+
+        let restored: IndexGenerations =
+            serde_json::from_str(&serde_json::to_string(&gens).unwrap()).unwrap();
+        assert_eq!(restored.building, None);
+    }
+
+    #[test]
+    fn test_shadow_edits_do_not_reach_reader_snapshot() {
+        let reader = IndexSnapshot {
+            generation: 1,
+            documents: vec![doc("a"), doc("b")],
+            ..Default::default()
+        };
+        let mut shadow = reader.clone();
+        shadow.generation = 2;
+        assert_eq!(shadow.remove_titles(&["a.md".to_string()]), 1);
+        let mut edited = doc("b");
+        edited.content = "edited".to_string();
+        shadow.upsert_documents(&[edited, doc("c")], 1, 5.0);
+
+        assert_eq!(reader.documents.len(), 2);
+        assert_eq!(reader.documents[1].content, "content of b");
+        let ids: Vec<&str> = shadow.documents.iter().map(|d| d.id.as_str()).collect();
+        assert_eq!(ids, vec!["b", "c"]);
+        assert_eq!(shadow.documents[0].content, "edited");
+        assert_eq!(
+            shadow.documents[1].processing_status,
+            ProcessingStatus::Completed
+        );
+    }
+
+    #[test]
+    fn test_stale_shadow_from_a_racing_tab_writes_nothing() {
+        // Two tabs reserve generation 4 from the same stored state
+        let stored = IndexGenerations {
+            active: 3,
+            ..Default::default()
+        };
+        let mut tab_a = stored.clone();
+        let mut tab_b = stored.clone();
+        let a = tab_a.begin().unwrap();
+        let b = tab_b.begin().unwrap();
+        assert_eq!(a, b);
+
+        let mut disk = IndexSnapshot {
+            generation: 3,
+            documents: vec![doc("a"), doc("b")],
+            ..Default::default()
+        };
+        let mut shadow_a = disk.clone();
+        shadow_a.upsert_documents(&[doc("c")], 1, 5.0);
+        let mut shadow_b = disk.clone();
+        shadow_b.remove_titles(&["a.md".to_string()]);
+
+        tab_a
+            .commit_build(&stored, a, |_| {
+                disk = shadow_a.clone();
+                Ok(())
+            })
+            .unwrap();
+        let stored: IndexGenerations =
+            serde_json::from_str(&serde_json::to_string(&tab_a).unwrap()).unwrap();
+
+        // Tab B finishes later: refused before its shadow reaches storage
+        let refused = tab_b.commit_build(&stored, b, |_| {
+            disk = shadow_b.clone();
+            Ok(())
+        });
+        assert!(refused.is_err());
+        let ids: Vec<&str> = disk.documents.iter().map(|d| d.id.as_str()).collect();
+        assert_eq!(ids, vec!["a", "b", "c"]);
+        // Its next build starts after tab A's generation
+        assert_eq!(tab_b.resolve(None).unwrap(), 4);
+        assert_eq!(tab_b.begin().unwrap(), 5);
+    }
+
+    #[test]
+    fn test_delete_during_build_reaches_active_index_and_commit() {
+        let stored = IndexGenerations {
+            active: 1,
+            ..Default::default()
+        };
+        let mut gens = stored.clone();
+        let mut active = IndexSnapshot {
+            generation: 1,
+            documents: vec![doc("a"), doc("b")],
+            ..Default::default()
+        };
+        let building = gens.begin().unwrap();
+        let mut shadow = active.clone();
+        shadow.generation = building;
+        shadow.upsert_documents(&[doc("c")], 1, 5.0);
+
+        // Deleted mid-build: gone from what queries read at once...
+        let ids = vec!["a".to_string()];
+        assert_eq!(active.remove_documents(&ids), 1);
+        assert!(gens.remove_during_build(&ids));
+        assert_eq!(active.documents.len(), 1);
+
+        // ...and not brought back by the build's commit
+        gens.commit_build(&stored, building, |removed| {
+            shadow.remove_documents(removed);
+            Ok(())
+        })
+        .unwrap();
+        let ids_after: Vec<&str> = shadow.documents.iter().map(|d| d.id.as_str()).collect();
+        assert_eq!(ids_after, vec!["b", "c"]);
+        assert!(gens.removed_during_build.is_empty());
+
+        // Without a build running, a delete is a build of its own
+        assert!(!gens.remove_during_build(&ids));
+    }
+}
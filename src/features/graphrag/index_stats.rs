@@ -0,0 +1,177 @@
+use crate::models::app::AppResult;
+use crate::models::graphrag::DocumentIndex;
+use crate::utils::hash::fnv1a;
+use crate::utils::storage::StorageUtils;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// localStorage key of the manifest written after each index build
+pub const INDEX_MANIFEST_KEY: &str = "graphrag_index_manifest_v1";
+
+/// Snapshot of what the index was built from: content fingerprints by document title
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct IndexManifest {
+    pub built_at: f64,
+    pub fingerprints: HashMap<String, u64>,
+}
+
+impl IndexManifest {
+    pub fn from_docs(docs: &[DocumentIndex], built_at: f64) -> Self {
+        Self {
+            built_at,
+            fingerprints: docs
+                .iter()
+                .map(|d| (d.title.clone(), fingerprint(&d.content)))
+                .collect(),
+        }
+    }
+
+    pub fn load() -> Option<Self> {
+        StorageUtils::retrieve_local(INDEX_MANIFEST_KEY)
+            .ok()
+            .flatten()
+    }
+
+    pub fn save(&self) -> AppResult<()> {
+        StorageUtils::store_local(INDEX_MANIFEST_KEY, self)
+    }
+}
+
+/// Difference between the knowledge base and the last index build
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct IndexStaleness {
+    /// When the index was last built, `None` if it never was
+    pub built_at: Option<f64>,
+    pub indexed_docs: usize,
+    pub added: Vec<String>,
+    pub changed: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+impl IndexStaleness {
+    pub fn compute(manifest: Option<&IndexManifest>, docs: &[DocumentIndex]) -> Self {
+        let empty = HashMap::new();
+        let indexed = manifest.map(|m| &m.fingerprints).unwrap_or(&empty);
+        let mut out = Self {
+            built_at: manifest.map(|m| m.built_at),
+            indexed_docs: indexed.len(),
+            ..Self::default()
+        };
+        for d in docs {
+            match indexed.get(&d.title) {
+                None => out.added.push(d.title.clone()),
+                Some(fp) if *fp != fingerprint(&d.content) => out.changed.push(d.title.clone()),
+                Some(_) => {}
+            }
+        }
+        let mut removed: Vec<String> = indexed
+            .keys()
+            .filter(|t| !docs.iter().any(|d| &d.title == *t))
+            .cloned()
+            .collect();
+        removed.sort();
+        out.removed = removed;
+        out
+    }
+
+    /// Documents added, changed or removed since the last build
+    pub fn pending(&self) -> usize {
+        self.added.len() + self.changed.len() + self.removed.len()
+    }
+
+    pub fn is_stale(&self) -> bool {
+        self.pending() > 0
+    }
+
+    /// Titles whose index entries must be dropped before an incremental build
+    pub fn outdated_titles(&self) -> Vec<String> {
+        self.changed
+            .iter()
+            .chain(self.removed.iter())
+            .cloned()
+            .collect()
+    }
+
+    pub fn summary(&self) -> String {
+        match (self.built_at, self.pending()) {
+            (None, 0) => "Nothing indexed yet".to_string(),
+            (None, n) => format!("Index not built ({} doc{} waiting)", n, plural(n)),
+            (Some(_), 0) => format!("Index up to date ({} docs)", self.indexed_docs),
+            (Some(_), n) => format!(
+                "Index is stale ({} doc{} changed since last index)",
+                n,
+                plural(n)
+            ),
+        }
+    }
+}
+
+fn plural(n: usize) -> &'static str {
+    if n == 1 {
+        ""
+    } else {
+        "s"
+    }
+}
+
+/// FNV-1a hash of the document content; stable across sessions and builds
+pub fn fingerprint(content: &str) -> u64 {
+    fnv1a(content.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::graphrag::ProcessingStatus;
+
+    fn doc(title: &str, content: &str) -> DocumentIndex {
+        DocumentIndex {
+            id: title.to_string(),
+            title: title.to_string(),
+            content: content.to_string(),
+            file_type: "markdown".to_string(),
+            size_bytes: content.len() as u64,
+            created_at: 0.0,
+            indexed_at: 0.0,
+            node_count: 0,
+            embedding_model: None,
+            processing_status: ProcessingStatus::Pending,
+            symbols: Vec::new(),
+            parent: None,
+        }
+    }
+
+    #[test]
+    fn test_staleness_tracks_added_changed_removed() {
+        let manifest = IndexManifest::from_docs(&[doc("a.md", "one"), doc("b.md", "two")], 1.0);
+        let fresh =
+            IndexStaleness::compute(Some(&manifest), &[doc("a.md", "one"), doc("b.md", "two")]);
+        assert!(!fresh.is_stale());
+        assert_eq!(fresh.summary(), "Index up to date (2 docs)");
+
+        let stale = IndexStaleness::compute(
+            Some(&manifest),
+            &[doc("a.md", "one, edited"), doc("c.md", "three")],
+        );
+        assert_eq!(stale.changed, vec!["a.md"]);
+        assert_eq!(stale.added, vec!["c.md"]);
+        assert_eq!(stale.removed, vec!["b.md"]);
+        assert_eq!(stale.outdated_titles(), vec!["a.md", "b.md"]);
+        assert_eq!(
+            stale.summary(),
+            "Index is stale (3 docs changed since last index)"
+        );
+    }
+
+    #[test]
+    fn test_never_built_index() {
+        let s = IndexStaleness::compute(None, &[doc("a.md", "one")]);
+        assert_eq!(s.added, vec!["a.md"]);
+        assert_eq!(s.summary(), "Index not built (1 doc waiting)");
+        assert_eq!(
+            IndexStaleness::compute(None, &[]).summary(),
+            "Nothing indexed yet"
+        );
+        assert_ne!(fingerprint("a"), fingerprint("b"));
+    }
+}
@@ -0,0 +1,183 @@
+//! What enabling knowledge does to a reply: which retrieval stages run, how much
+//! latency they add and how many prompt tokens the injected preamble can take.
+
+use super::index_stats::IndexStaleness;
+use super::preamble::{render, PreambleParts};
+use crate::graphrag_config::{GraphRAGConfig, PerformanceMetrics};
+
+/// Snippets of the top results quoted in the knowledge preamble
+pub const PREAMBLE_SNIPPETS: usize = 3;
+/// Characters kept of each quoted snippet
+pub const PREAMBLE_SNIPPET_CHARS: usize = 300;
+/// Longest summary the retriever synthesizes
+const SUMMARY_CHARS: usize = 512;
+/// Typical length of a cited source title
+const CITATION_CHARS: usize = 40;
+/// Rough characters per token of English text
+const CHARS_PER_TOKEN: usize = 4;
+/// Scoring cost per indexed document, in milliseconds
+const PER_DOCUMENT_MS: f32 = 0.05;
+
+/// One retrieval stage and its expected cost
+#[derive(Clone, Debug, PartialEq)]
+pub struct StageEstimate {
+    pub name: &'static str,
+    pub latency_ms: u32,
+    /// Taken from the last query rather than the built-in baseline
+    pub measured: bool,
+}
+
+/// Live explanation of the knowledge toggle
+#[derive(Clone, Debug, PartialEq)]
+pub struct KnowledgeImpact {
+    pub documents: usize,
+    /// Documents added, changed or removed since the last index build
+    pub pending: usize,
+    pub stages: Vec<StageEstimate>,
+    /// Upper bound of the tokens the preamble adds to the prompt
+    pub prompt_tokens: usize,
+}
+
+impl KnowledgeImpact {
+    pub fn estimate(
+        config: &GraphRAGConfig,
+        staleness: &IndexStaleness,
+        last_query: &PerformanceMetrics,
+    ) -> Self {
+        let documents = staleness.indexed_docs;
+        let scan_ms = 2 + (documents as f32 * PER_DOCUMENT_MS).round() as u32;
+        let candidates = [
+            ("Keyword search", true, scan_ms, 0),
+            (
+                "HyDE expansion",
+                config.hyde_enabled,
+                15,
+                last_query.hyde_time_ms,
+            ),
+            ("Semantic search", config.embeddings_enabled, 40, 0),
+            (
+                "Graph fusion",
+                config.hybrid_enabled,
+                5,
+                last_query.hybrid_fusion_time_ms,
+            ),
+            (
+                "Community detection",
+                config.community_detection_enabled,
+                10,
+                last_query.community_detection_time_ms,
+            ),
+            (
+                "PageRank",
+                config.pagerank_enabled,
+                10,
+                last_query.pagerank_time_ms,
+            ),
+            (
+                "Cross-encoder rerank",
+                config.reranking_enabled,
+                80,
+                last_query.reranking_time_ms,
+            ),
+            (
+                "Summary",
+                config.synthesis_enabled,
+                5,
+                last_query.synthesis_time_ms,
+            ),
+            (
+                "Abstractive summary",
+                config.synthesis_enabled && config.abstractive_synthesis_enabled,
+                1500,
+                0,
+            ),
+        ];
+        let stages = candidates
+            .into_iter()
+            .filter(|(_, enabled, _, _)| *enabled)
+            .map(|(name, _, baseline, measured)| StageEstimate {
+                name,
+                latency_ms: if measured > 0 { measured } else { baseline },
+                measured: measured > 0,
+            })
+            .collect();
+        Self {
+            documents,
+            pending: staleness.pending(),
+            stages,
+            prompt_tokens: preamble_tokens(config, documents),
+        }
+    }
+
+    pub fn latency_ms(&self) -> u32 {
+        self.stages.iter().map(|s| s.latency_ms).sum()
+    }
+}
+
+/// Upper bound of the preamble the chat builds from a retrieval result
+fn preamble_tokens(config: &GraphRAGConfig, documents: usize) -> usize {
+    if documents == 0 {
+        return 0;
+    }
+    let quoted = documents.min(PREAMBLE_SNIPPETS);
+    let parts = PreambleParts {
+        summary: if config.synthesis_enabled {
+            "x".repeat(SUMMARY_CHARS)
+        } else {
+            String::new()
+        },
+        snippets: vec!["x".repeat(PREAMBLE_SNIPPET_CHARS); quoted],
+        citations: vec!["x".repeat(CITATION_CHARS); quoted],
+        date: "2000-01-01".to_string(),
+    };
+    render(&config.preamble_template, &parts)
+        .len()
+        .div_ceil(CHARS_PER_TOKEN)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn staleness(indexed_docs: usize) -> IndexStaleness {
+        IndexStaleness {
+            indexed_docs,
+            ..IndexStaleness::default()
+        }
+    }
+
+    #[test]
+    fn test_estimate_lists_enabled_stages_and_prefers_measurements() {
+        let config = GraphRAGConfig::default();
+        let measured = PerformanceMetrics {
+            pagerank_time_ms: 33,
+            ..PerformanceMetrics::default()
+        };
+        let impact = KnowledgeImpact::estimate(&config, &staleness(100), &measured);
+        let names: Vec<&str> = impact.stages.iter().map(|s| s.name).collect();
+        assert!(names.contains(&"PageRank"));
+        assert!(!names.contains(&"Cross-encoder rerank"));
+        let pagerank = impact.stages.iter().find(|s| s.name == "PageRank").unwrap();
+        assert_eq!(pagerank.latency_ms, 33);
+        assert!(pagerank.measured);
+        assert_eq!(
+            impact.latency_ms(),
+            impact.stages.iter().map(|s| s.latency_ms).sum::<u32>()
+        );
+    }
+
+    #[test]
+    fn test_preamble_tokens_scale_with_snippets() {
+        let mut config = GraphRAGConfig::default();
+        assert_eq!(preamble_tokens(&config, 0), 0);
+        let one = preamble_tokens(&config, 1);
+        let many = preamble_tokens(&config, 50);
+        assert!(many > one);
+        assert_eq!(many, preamble_tokens(&config, PREAMBLE_SNIPPETS));
+        config.synthesis_enabled = false;
+        assert!(preamble_tokens(&config, 50) < many);
+        // The estimate follows the template
+        config.preamble_template = "Sources:\n{citations}".to_string();
+        assert!(preamble_tokens(&config, 50) < one);
+    }
+}
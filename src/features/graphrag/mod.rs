@@ -1,6 +1,20 @@
+pub mod chunking;
+pub mod config_bundle;
+pub mod content_store;
+pub mod coverage;
+pub mod embeddings;
+pub mod evaluation;
 pub mod extraction;
 pub mod graph;
+pub mod index_generation;
+pub mod index_stats;
+pub mod knowledge_impact;
 pub mod pipeline;
+pub mod preamble;
+pub mod profiles;
+pub mod query_planning;
+pub mod reembed;
+pub mod reranker;
 pub mod retrieval;
 pub mod summarizer;
 pub mod traversal;
@@ -1,8 +1,13 @@
+use super::index_generation::IndexSnapshot;
 use crate::graphrag_config::GraphRAGConfig;
 use crate::models::app::AppResult;
-use crate::models::graph_store::GraphStore;
-use crate::models::graphrag::{DocumentIndex, ProcessingStatus, RAGQuery, RAGResult};
-use crate::utils::storage::StorageUtils;
+use crate::models::graphrag::{DocumentIndex, RAGQuery, RAGResult};
+use crate::storage::originals;
+use crate::storage::persistent::PersistentStore;
+use wasm_bindgen_futures::spawn_local;
+
+/// Record key of the persisted document index
+pub const DOCUMENT_INDEX_KEY_V1: &str = "graphrag_document_index_v1";
 
 /// Pipeline entrypoints for GraphRAG. Honors configuration when indexing/querying.
 pub struct GraphRAGPipeline {
@@ -11,107 +16,90 @@ pub struct GraphRAGPipeline {
 
 impl GraphRAGPipeline {
     pub fn new() -> Self {
-        // Load GraphRAGConfig from localStorage; prefer v1 key with legacy fallback
-        let config = if let Ok(Some(c)) =
-            StorageUtils::retrieve_local::<GraphRAGConfig>("graphrag_config_v1")
-        {
-            c
-        } else {
-            match StorageUtils::retrieve_local::<GraphRAGConfig>("graphrag_config") {
-                Ok(Some(c)) => c,
-                _ => GraphRAGConfig::default(),
-            }
-        };
+        // Load GraphRAGConfig from localStorage (the legacy key is migrated at startup)
+        let config = GraphRAGConfig::load();
         Self { config }
     }
 
-    /// Storage keys for persisted document index (versioned)
-    const INDEX_KEY_V1: &'static str = "graphrag_document_index_v1";
-    const INDEX_KEY_LEGACY: &'static str = "graphrag_document_index";
+    pub fn config(&self) -> &GraphRAGConfig {
+        &self.config
+    }
+
+    /// Storage key for persisted document index (versioned)
+    const INDEX_KEY_V1: &'static str = DOCUMENT_INDEX_KEY_V1;
 
-    /// Load the current document index from localStorage.
+    /// Load the current document index from persistent storage (IndexedDB).
     fn load_index(&self) -> AppResult<Vec<DocumentIndex>> {
-        // Prefer v1 key; if missing, fallback to legacy key (migration read)
-        if let Ok(Some(v)) = StorageUtils::retrieve_local::<Vec<DocumentIndex>>(Self::INDEX_KEY_V1)
-        {
-            return Ok(v);
-        }
-        let legacy = StorageUtils::retrieve_local::<Vec<DocumentIndex>>(Self::INDEX_KEY_LEGACY)?;
-        Ok(legacy.unwrap_or_default())
+        let index = PersistentStore::read::<Vec<DocumentIndex>>(Self::INDEX_KEY_V1)?;
+        Ok(index.unwrap_or_default())
+    }
+
+    /// Documents currently in the persisted index
+    pub fn indexed_documents(&self) -> AppResult<Vec<DocumentIndex>> {
+        self.load_index()
     }
 
-    /// Save the document index to localStorage.
-    fn save_index(&self, docs: &[DocumentIndex]) -> AppResult<()> {
-        // Write to versioned key
-        StorageUtils::store_local(Self::INDEX_KEY_V1, &docs)
+    /// Delete every indexed document whose title is in `titles`, with its graph nodes,
+    /// vectors and kept original file. Returns how many entries were removed.
+    pub fn delete_documents_by_titles(&self, titles: &[String]) -> AppResult<usize> {
+        let removed = IndexSnapshot::delete_titles(titles)?;
+        self.drop_originals(titles.to_vec());
+        Ok(removed)
     }
 
     /// Index documents into the knowledge graph.
-    /// Current behavior: upsert provided DocumentIndex entries by id and persist.
+    /// Current behavior: upsert provided DocumentIndex entries by id and persist
+    /// as a new index generation.
     pub fn index_documents(&self, docs: &[DocumentIndex]) -> AppResult<()> {
-        // Load existing
-        let mut existing = self.load_index()?;
-
-        // Honor batch_size: process in chunks and annotate processing_status/indexed_at
-        let now = js_sys::Date::now();
-        let batch = self.config.batch_size.max(1);
-        for chunk in docs.chunks(batch) {
-            for d in chunk.iter().cloned() {
-                // Mark completed with updated timestamp
-                let mut updated = d;
-                updated.indexed_at = now;
-                updated.processing_status = ProcessingStatus::Completed;
-                if let Some(slot) = existing.iter_mut().find(|x| x.id == updated.id) {
-                    *slot = updated;
-                } else {
-                    existing.push(updated);
-                }
-            }
-        }
-
-        // Persist
-        self.save_index(&existing)
+        let mut shadow = IndexSnapshot::begin_build()?;
+        // Honor batch_size: entries are annotated with processing_status/indexed_at
+        shadow
+            .index
+            .upsert_documents(docs, self.config.batch_size, js_sys::Date::now());
+        shadow.commit()?;
+        Ok(())
     }
 
     /// Delete a single document by id from the persisted index and cascade-remove
     /// associated nodes/edges from the GraphStore.
     pub fn delete_document_by_id(&self, id: &str) -> AppResult<()> {
-        // Load existing index
-        let mut existing = self.load_index()?;
-        // Filter out the document
-        let before = existing.len();
-        existing.retain(|d| d.id != id);
-        // Persist index only if changed
-        if existing.len() != before {
-            self.save_index(&existing)?;
-        }
-        // Remove from graph store (best-effort)
-        if let Ok(mut store) = GraphStore::load() {
-            store.remove_document_cascade(id);
-            let _ = store.save();
-        }
-        Ok(())
+        self.delete_documents_by_ids(&[id.to_string()])
     }
 
     /// Delete multiple documents by ids. Returns Ok even if some ids were not present.
+    /// While a reindex runs, they leave the active index at once and the new generation
+    /// without them.
     pub fn delete_documents_by_ids(&self, ids: &[String]) -> AppResult<()> {
-        if ids.is_empty() {
-            return Ok(());
+        let titles = self
+            .load_index()?
+            .into_iter()
+            .filter(|d| ids.contains(&d.id))
+            .map(|d| d.title)
+            .collect();
+        IndexSnapshot::delete_documents(ids)?;
+        self.drop_originals(titles);
+        Ok(())
+    }
+
+    /// Remove, in the background, the kept original files of deleted documents whose
+    /// title no remaining document has
+    fn drop_originals(&self, mut titles: Vec<String>) {
+        if !originals::is_supported() {
+            return;
         }
-        let mut existing = self.load_index()?;
-        let idset: std::collections::HashSet<&String> = ids.iter().collect();
-        let before = existing.len();
-        existing.retain(|d| !idset.contains(&d.id));
-        if existing.len() != before {
-            self.save_index(&existing)?;
+        if let Ok(remaining) = self.load_index() {
+            titles.retain(|t| !remaining.iter().any(|d| &d.title == t));
         }
-        if let Ok(mut store) = GraphStore::load() {
-            for id in ids {
-                store.remove_document_cascade(id);
-            }
-            let _ = store.save();
+        if titles.is_empty() {
+            return;
         }
-        Ok(())
+        spawn_local(async move {
+            for title in titles {
+                if let Err(e) = originals::remove(&title).await {
+                    log::warn!("Original of {} not removed: {}", title, e);
+                }
+            }
+        });
     }
 
     /// Run a GraphRAG query against the current index. Stub: returns empty result.
@@ -130,6 +118,9 @@ impl GraphRAGPipeline {
                 community_filtered: false,
                 algorithms_used: vec![],
                 summary: None,
+                below_threshold: None,
+                index_generation: 0,
+                trace: None,
             },
         }
     }
@@ -0,0 +1,128 @@
+//! The system message that injects retrieved knowledge into a prompt, rendered from
+//! the user-editable template in `GraphRAGConfig`. A template is split into blocks at
+//! blank lines and a block is left out when a placeholder in it has nothing to show,
+//! so "Top snippets:\n{snippets}" disappears with the snippets instead of leaving a
+//! dangling heading.
+
+/// Template used until the user edits it, and when the edited one is blank
+pub const DEFAULT_PREAMBLE_TEMPLATE: &str =
+    "Knowledge summary: {summary}\n\nTop snippets:\n{snippets}\n\nSources:\n{citations}";
+
+/// Placeholders a template can use, with what they expand to
+pub const PLACEHOLDERS: &[(&str, &str)] = &[
+    ("{summary}", "summary of the retrieved passages"),
+    ("{snippets}", "quoted top passages, one per line"),
+    (
+        "{citations}",
+        "numbered titles of the sources, matching [n] markers",
+    ),
+    ("{date}", "today's date"),
+];
+
+/// Values substituted into a template
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PreambleParts {
+    pub summary: String,
+    pub snippets: Vec<String>,
+    /// Source titles; `[n]` in the summary and snippets refers to the n-th
+    pub citations: Vec<String>,
+    pub date: String,
+}
+
+impl PreambleParts {
+    fn value(&self, placeholder: &str) -> String {
+        match placeholder {
+            "{summary}" => self.summary.trim().to_string(),
+            "{snippets}" => numbered(&self.snippets),
+            "{citations}" => numbered(&self.citations),
+            "{date}" => self.date.clone(),
+            _ => String::new(),
+        }
+    }
+}
+
+fn numbered(lines: &[String]) -> String {
+    lines
+        .iter()
+        .enumerate()
+        .map(|(i, line)| format!("[{}] {}", i + 1, line.trim()))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// `template` with its placeholders filled in. Empty when every block was left out.
+pub fn render(template: &str, parts: &PreambleParts) -> String {
+    let template = if template.trim().is_empty() {
+        DEFAULT_PREAMBLE_TEMPLATE
+    } else {
+        template
+    };
+    let values: Vec<(&str, String)> = PLACEHOLDERS
+        .iter()
+        .map(|(placeholder, _)| (*placeholder, parts.value(placeholder)))
+        .collect();
+    template
+        .split("\n\n")
+        .filter(|block| {
+            values
+                .iter()
+                .all(|(placeholder, value)| !value.is_empty() || !block.contains(placeholder))
+        })
+        .map(|block| {
+            values
+                .iter()
+                .fold(block.to_string(), |block, (placeholder, value)| {
+                    block.replace(placeholder, value)
+                })
+        })
+        .map(|block| block.trim().to_string())
+        .filter(|block| !block.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parts() -> PreambleParts {
+        PreambleParts {
+            summary: "Refunds take 30 days.".to_string(),
+            snippets: vec!["Refunds are issued within thirty days.".to_string()],
+            citations: vec!["policy.md".to_string()],
+            date: "2026-10-16".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_default_template_renders_every_block() {
+        assert_eq!(
+            render(DEFAULT_PREAMBLE_TEMPLATE, &parts()),
+            "Knowledge summary: Refunds take 30 days.\n\nTop snippets:\n\
+             [1] Refunds are issued within thirty days.\n\nSources:\n[1] policy.md"
+        );
+        // A blank template falls back to the default
+        assert_eq!(
+            render("  \n", &parts()),
+            render(DEFAULT_PREAMBLE_TEMPLATE, &parts())
+        );
+    }
+
+    #[test]
+    fn test_blocks_with_empty_placeholders_are_dropped() {
+        let template =
+            "As of {date}:\n\nSummary: {summary}\n\nQuotes:\n{snippets}\n\nCite {citations}";
+        let without_snippets = PreambleParts {
+            snippets: Vec::new(),
+            summary: String::new(),
+            ..parts()
+        };
+        assert_eq!(
+            render(template, &without_snippets),
+            "As of 2026-10-16:\n\nCite [1] policy.md"
+        );
+        assert_eq!(render(template, &PreambleParts::default()), "");
+        // Unknown placeholders are left as written
+        assert_eq!(render("Use {tone}.", &parts()), "Use {tone}.");
+    }
+}
@@ -0,0 +1,244 @@
+//! Named GraphRAG presets. A profile holds the retrieval tuning of a config (feature
+//! toggles and weights); switching to one leaves the models, chunking, preamble
+//! template and resource limits alone, so a profile made on one machine can be used
+//! on another. "Fast", "Balanced" and "Max quality" are built in; user profiles are
+//! kept next to the config and can be shared as a JSON file.
+
+use crate::graphrag_config::GraphRAGConfig;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+pub const PROFILES_FORMAT: &str = "graphrag-profiles";
+pub const PROFILES_VERSION: u32 = 1;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ConfigProfile {
+    pub name: String,
+    pub config: GraphRAGConfig,
+}
+
+impl ConfigProfile {
+    pub fn new(name: &str, config: GraphRAGConfig) -> Self {
+        Self {
+            name: name.trim().to_string(),
+            config,
+        }
+    }
+
+    /// `target` with this profile's toggles and weights
+    pub fn apply_to(&self, target: &GraphRAGConfig) -> GraphRAGConfig {
+        let p = &self.config;
+        GraphRAGConfig {
+            hyde_enabled: p.hyde_enabled,
+            community_detection_enabled: p.community_detection_enabled,
+            pagerank_enabled: p.pagerank_enabled,
+            reranking_enabled: p.reranking_enabled,
+            synthesis_enabled: p.synthesis_enabled,
+            hybrid_enabled: p.hybrid_enabled,
+            fusion_text_weight: p.fusion_text_weight,
+            fusion_graph_weight: p.fusion_graph_weight,
+            embeddings_enabled: p.embeddings_enabled,
+            semantic_weight: p.semantic_weight,
+            llm_extraction_enabled: p.llm_extraction_enabled,
+            entity_resolution_enabled: p.entity_resolution_enabled,
+            query_decomposition_enabled: p.query_decomposition_enabled,
+            abstractive_synthesis_enabled: p.abstractive_synthesis_enabled,
+            min_relevance: p.min_relevance,
+            mmr_enabled: p.mmr_enabled,
+            mmr_lambda: p.mmr_lambda,
+            search_strategy: p.search_strategy.clone(),
+            ..target.clone()
+        }
+    }
+
+    /// Whether `config` is tuned exactly as this profile
+    pub fn matches(&self, config: &GraphRAGConfig) -> bool {
+        self.apply_to(config) == *config
+    }
+}
+
+/// Built-in presets, cheapest first
+pub fn builtin_profiles() -> Vec<ConfigProfile> {
+    let balanced = GraphRAGConfig::default();
+    let fast = GraphRAGConfig {
+        hyde_enabled: false,
+        community_detection_enabled: false,
+        pagerank_enabled: false,
+        reranking_enabled: false,
+        hybrid_enabled: false,
+        embeddings_enabled: false,
+        mmr_enabled: false,
+        query_decomposition_enabled: false,
+        abstractive_synthesis_enabled: false,
+        ..balanced.clone()
+    };
+    let max_quality = GraphRAGConfig {
+        reranking_enabled: true,
+        embeddings_enabled: true,
+        query_decomposition_enabled: true,
+        abstractive_synthesis_enabled: true,
+        ..balanced.clone()
+    };
+    vec![
+        ConfigProfile::new("Fast", fast),
+        ConfigProfile::new("Balanced", balanced),
+        ConfigProfile::new("Max quality", max_quality),
+    ]
+}
+
+pub fn is_builtin(name: &str) -> bool {
+    builtin_profiles()
+        .iter()
+        .any(|p| p.name.eq_ignore_ascii_case(name.trim()))
+}
+
+/// Name of the first profile `config` is tuned as, built-in ones first
+pub fn matching_profile<'a>(
+    config: &GraphRAGConfig,
+    builtin: &'a [ConfigProfile],
+    custom: &'a [ConfigProfile],
+) -> Option<&'a str> {
+    builtin
+        .iter()
+        .chain(custom)
+        .find(|p| p.matches(config))
+        .map(|p| p.name.as_str())
+}
+
+/// `profiles` with `profile` added, replacing one of the same name
+pub fn upsert(profiles: &mut Vec<ConfigProfile>, profile: ConfigProfile) {
+    match profiles
+        .iter_mut()
+        .find(|p| p.name.eq_ignore_ascii_case(&profile.name))
+    {
+        Some(existing) => *existing = profile,
+        None => profiles.push(profile),
+    }
+}
+
+/// Why `name` cannot be used for a user profile
+pub fn validate_name(name: &str) -> Result<(), String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("A profile needs a name".to_string());
+    }
+    if is_builtin(name) {
+        return Err(format!("\"{}\" is a built-in profile", name));
+    }
+    Ok(())
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct ProfilesFile {
+    format: String,
+    version: u32,
+    profiles: Vec<ConfigProfile>,
+}
+
+pub fn to_json(profiles: &[ConfigProfile]) -> String {
+    serde_json::to_string_pretty(&ProfilesFile {
+        format: PROFILES_FORMAT.to_string(),
+        version: PROFILES_VERSION,
+        profiles: profiles.to_vec(),
+    })
+    .unwrap_or_default()
+}
+
+/// User profiles of a profiles file or of a settings bundle. Profiles named like a
+/// built-in one or with invalid settings are refused.
+pub fn parse(json: &str) -> Result<Vec<ConfigProfile>, String> {
+    let value: Value = serde_json::from_str(json).map_err(|e| format!("Not valid JSON: {}", e))?;
+    let profiles = match value["format"].as_str() {
+        Some(PROFILES_FORMAT) => {
+            let version = value["version"].as_u64().unwrap_or(0);
+            if version > PROFILES_VERSION as u64 {
+                return Err(format!(
+                    "Written by a newer version (format version {}, this build reads up to {})",
+                    version, PROFILES_VERSION
+                ));
+            }
+            serde_json::from_value::<ProfilesFile>(value)
+                .map_err(|e| format!("Invalid profiles file: {}", e))?
+                .profiles
+        }
+        Some(super::config_bundle::CONFIG_BUNDLE_FORMAT) => {
+            super::config_bundle::parse(json)?.profiles
+        }
+        _ => return Err("Not a GraphRAG profiles file".to_string()),
+    };
+    let mut errors = Vec::new();
+    for p in &profiles {
+        if let Err(e) = validate_name(&p.name) {
+            errors.push(e);
+        }
+        errors.extend(
+            super::config_bundle::validate(&p.config)
+                .into_iter()
+                .map(|e| format!("{}: {}", p.name, e)),
+        );
+    }
+    if !errors.is_empty() {
+        return Err(errors.join("; "));
+    }
+    if profiles.is_empty() {
+        return Err("The file has no profiles".to_string());
+    }
+    Ok(profiles)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profiles_switch_tuning_only() {
+        let builtin = builtin_profiles();
+        let mine = GraphRAGConfig {
+            chunk_size: 900,
+            embedding_model: "my-model".to_string(),
+            ..GraphRAGConfig::default()
+        };
+        assert_eq!(matching_profile(&mine, &builtin, &[]), Some("Balanced"));
+
+        let fast = builtin[0].apply_to(&mine);
+        assert!(!fast.hyde_enabled && !fast.hybrid_enabled);
+        assert_eq!(fast.chunk_size, 900);
+        assert_eq!(fast.embedding_model, "my-model");
+        assert_eq!(matching_profile(&fast, &builtin, &[]), Some("Fast"));
+
+        let tuned = GraphRAGConfig {
+            mmr_lambda: 0.4,
+            ..fast
+        };
+        assert_eq!(matching_profile(&tuned, &builtin, &[]), None);
+        let mut custom = vec![ConfigProfile::new(" Legal ", tuned.clone())];
+        upsert(
+            &mut custom,
+            ConfigProfile::new("legal", GraphRAGConfig::default()),
+        );
+        assert_eq!(custom.len(), 1);
+        assert_eq!(custom[0].name, "legal");
+        assert!(validate_name("max QUALITY").is_err());
+    }
+
+    #[test]
+    fn test_profiles_file_round_trip() {
+        let custom = vec![ConfigProfile::new(
+            "Legal",
+            GraphRAGConfig {
+                mmr_lambda: 0.4,
+                ..GraphRAGConfig::default()
+            },
+        )];
+        assert_eq!(parse(&to_json(&custom)).unwrap(), custom);
+
+        let bundle = super::super::config_bundle::ConfigBundle::new(GraphRAGConfig::default(), 0.0)
+            .with_profiles(custom.clone());
+        assert_eq!(parse(&bundle.to_json()).unwrap(), custom);
+
+        let builtin_name = to_json(&[ConfigProfile::new("Fast", GraphRAGConfig::default())]);
+        assert!(parse(&builtin_name).unwrap_err().contains("built-in"));
+        assert!(parse(&to_json(&[])).is_err());
+        assert!(parse("{\"format\": \"other\"}").is_err());
+    }
+}
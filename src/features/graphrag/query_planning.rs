@@ -0,0 +1,350 @@
+//! Query decomposition for multi-part questions. The loaded chat model splits a
+//! question such as "Who leads the Berlin office and what is its refund policy?" into
+//! self-contained sub-queries, each one is retrieved on its own, and the evidence is
+//! merged so every part of the question is represented in the prompt.
+
+use crate::models::graphrag::{CandidateOutcome, RAGResult};
+use crate::models::{Message, MessageRole, QueryPlan, SubQuery};
+use std::collections::HashSet;
+use wasm_bindgen::JsValue;
+
+/// Sub-queries kept from a plan; more would crowd each other out of the prompt
+pub const MAX_SUB_QUERIES: usize = 4;
+
+const PLANNING_INSTRUCTIONS: &str = "You split questions for a search engine. \
+If the question asks several things, rewrite each part as a short, self-contained \
+search query that repeats the names it refers to. If it asks one thing, return it \
+unchanged as the only query. Reply with a JSON array of strings and nothing else.";
+
+// Words and marks that suggest a question has several parts
+const CONJUNCTIONS: &[&str] = &[
+    " and ",
+    " as well as ",
+    " also ",
+    " versus ",
+    " vs ",
+    " then ",
+];
+const COMPARISONS: &[&str] = &["compare", "difference between", "differences between"];
+
+/// Whether a question is worth a planning call: several question marks, a comparison,
+/// or parts joined by a conjunction in a longer question
+pub fn needs_planning(question: &str) -> bool {
+    let q = format!(" {} ", question.to_lowercase());
+    let words = q.split_whitespace().count();
+    q.matches('?').count() > 1
+        || COMPARISONS.iter().any(|c| q.contains(c))
+        || (words >= 8 && CONJUNCTIONS.iter().any(|c| q.contains(c)))
+        || (words >= 8 && q.contains(';'))
+}
+
+/// Messages asking the model to split `question`
+pub fn build_planning_messages(question: &str) -> Vec<Message> {
+    vec![
+        Message::new(MessageRole::System, PLANNING_INSTRUCTIONS.to_string()),
+        Message::new(
+            MessageRole::User,
+            format!("Question: {}\n\nJSON:", question.trim()),
+        ),
+    ]
+}
+
+/// Sub-queries of the model reply: a JSON array, or one query per line when the model
+/// ignored the format. Blank and repeated queries are dropped.
+pub fn parse_sub_queries(response: &str) -> Vec<String> {
+    let from_json = match (response.find('['), response.rfind(']')) {
+        (Some(s), Some(e)) if e > s => serde_json::from_str::<Vec<String>>(&response[s..=e]).ok(),
+        _ => None,
+    };
+    let candidates = from_json.unwrap_or_else(|| {
+        response
+            .lines()
+            .map(|l| {
+                l.trim()
+                    .trim_start_matches(|c: char| c.is_ascii_digit() || "-*.) ".contains(c))
+                    .trim_matches('"')
+                    .to_string()
+            })
+            .collect()
+    });
+    let mut seen = HashSet::new();
+    candidates
+        .into_iter()
+        .map(|q| q.trim().to_string())
+        .filter(|q| !q.is_empty() && seen.insert(q.to_lowercase()))
+        .take(MAX_SUB_QUERIES)
+        .collect()
+}
+
+/// Ask the model for the sub-queries of `question`. `None` when it is a single
+/// question or the model could not be asked.
+pub async fn plan_query(engine: &JsValue, question: &str) -> Option<Vec<String>> {
+    let request = build_planning_messages(question);
+    match crate::webllm_binding::send_message_to_llm(engine, request).await {
+        Ok(response) => Some(parse_sub_queries(&response)).filter(|q| q.len() > 1),
+        Err(e) => {
+            log::warn!("Query planning failed: {:?}", e);
+            None
+        }
+    }
+}
+
+/// One result from the results of every sub-query. Passages are taken in turns, each
+/// sub-query's best first, so every part of the question gets evidence; a passage
+/// found by several sub-queries is kept once. Returns the plan with the sources each
+/// sub-query contributed.
+pub fn merge_results(
+    results: Vec<(String, RAGResult)>,
+    max_results: usize,
+) -> (RAGResult, QueryPlan) {
+    let mut plan = QueryPlan {
+        sub_queries: results
+            .iter()
+            .map(|(text, _)| SubQuery {
+                text: text.clone(),
+                sources: Vec::new(),
+            })
+            .collect(),
+    };
+    let mut iters: Vec<_> = results
+        .iter()
+        .map(|(_, r)| r.nodes.iter().zip(r.scores.iter()))
+        .collect();
+    let mut seen = HashSet::new();
+    let (mut nodes, mut scores) = (Vec::new(), Vec::new());
+    while nodes.len() < max_results {
+        let mut progressed = false;
+        for (i, iter) in iters.iter_mut().enumerate() {
+            if nodes.len() >= max_results {
+                break;
+            }
+            for (node, score) in iter.by_ref() {
+                progressed = true;
+                if seen.insert(node.id.clone()) {
+                    plan.sub_queries[i].sources.push(
+                        node.metadata
+                            .source
+                            .clone()
+                            .unwrap_or_else(|| node.id.clone()),
+                    );
+                    nodes.push(node.clone());
+                    scores.push(*score);
+                    break;
+                }
+            }
+        }
+        if !progressed {
+            break;
+        }
+    }
+
+    let mut results = results.into_iter().map(|(_, r)| r);
+    let mut merged = results.next().unwrap_or_else(empty_result);
+    let mut edge_ids: HashSet<String> = merged.edges.iter().map(|e| e.id.clone()).collect();
+    let mut summaries: Vec<String> = merged.metadata.summary.take().into_iter().collect();
+    let mut below_threshold = merged.metadata.below_threshold.take();
+    for r in results {
+        merged.edges.extend(
+            r.edges
+                .into_iter()
+                .filter(|e| edge_ids.insert(e.id.clone())),
+        );
+        summaries.extend(r.metadata.summary);
+        if let Some(other) = r.metadata.trace {
+            match &mut merged.metadata.trace {
+                Some(trace) => trace.merge(other),
+                none => *none = Some(other),
+            }
+        }
+        below_threshold = below_threshold.or(r.metadata.below_threshold);
+        merged.metadata.processing_time_ms += r.metadata.processing_time_ms;
+        merged.metadata.reranked |= r.metadata.reranked;
+        merged.metadata.hyde_enhanced |= r.metadata.hyde_enhanced;
+        merged.metadata.community_filtered |= r.metadata.community_filtered;
+        for algorithm in r.metadata.algorithms_used {
+            if !merged.metadata.algorithms_used.contains(&algorithm) {
+                merged.metadata.algorithms_used.push(algorithm);
+            }
+        }
+    }
+    merged
+        .metadata
+        .algorithms_used
+        .push("query_decomposition".to_string());
+    merged.metadata.summary = Some(summaries.join(" ")).filter(|s| !s.trim().is_empty());
+    // Withheld only when no sub-query found anything usable
+    merged.metadata.below_threshold = below_threshold.filter(|_| nodes.is_empty());
+    // A candidate counts as selected only if it made the merged cut
+    if let Some(trace) = &mut merged.metadata.trace {
+        for c in trace.candidates.iter_mut() {
+            if nodes.iter().any(|n| n.id == c.id) {
+                c.outcome = CandidateOutcome::Selected;
+            } else if c.outcome == CandidateOutcome::Selected {
+                c.outcome = CandidateOutcome::RankedOut;
+            }
+        }
+    }
+    merged.nodes = nodes;
+    merged.scores = scores;
+    (merged, plan)
+}
+
+fn empty_result() -> RAGResult {
+    RAGResult {
+        id: String::new(),
+        query_id: String::new(),
+        nodes: vec![],
+        edges: vec![],
+        scores: vec![],
+        metadata: crate::models::graphrag::ResultMetadata {
+            processing_time_ms: 0,
+            total_nodes_searched: 0,
+            reranked: false,
+            hyde_enhanced: false,
+            community_filtered: false,
+            algorithms_used: vec![],
+            summary: None,
+            below_threshold: None,
+            index_generation: 0,
+            trace: None,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::graphrag::{
+        GraphNode, NodeMetadata, NodeType, RetrievalTrace, TraceCandidate, TraceStage,
+    };
+    use std::collections::HashMap;
+
+    fn result(ids: &[(&str, f32)], summary: &str) -> RAGResult {
+        let mut r = empty_result();
+        for (id, score) in ids {
+            r.nodes.push(GraphNode {
+                id: id.to_string(),
+                content: format!("passage {}", id),
+                node_type: NodeType::Document,
+                metadata: NodeMetadata {
+                    created_at: 0.0,
+                    updated_at: 0.0,
+                    source: Some(format!("{}.md", id)),
+                    confidence: *score,
+                    tags: vec![],
+                    properties: HashMap::new(),
+                },
+                embeddings: None,
+                connections: vec![],
+            });
+            r.scores.push(*score);
+        }
+        r.metadata.summary = Some(summary.to_string()).filter(|s| !s.is_empty());
+        r.metadata.trace = Some(RetrievalTrace {
+            query_tokens: vec![summary.to_lowercase()],
+            stages: vec![TraceStage {
+                name: "Search".to_string(),
+                ms: 3,
+            }],
+            candidates: ids
+                .iter()
+                .map(|(id, score)| TraceCandidate {
+                    id: id.to_string(),
+                    title: format!("{}.md", id),
+                    search_score: *score,
+                    graph_score: None,
+                    rerank_score: None,
+                    relevance: *score,
+                    outcome: CandidateOutcome::Selected,
+                })
+                .collect(),
+            prompt_chunks: vec![],
+        });
+        r
+    }
+
+    #[test]
+    fn test_complex_questions_are_planned() {
+        assert!(needs_planning(
+            "Who leads the Berlin office and what is the refund policy there?"
+        ));
+        assert!(needs_planning("Who is Ada? What did she write?"));
+        assert!(needs_planning("Compare the basic and pro plans"));
+        assert!(!needs_planning("What is the refund policy?"));
+        assert!(!needs_planning("Salt and pepper?"));
+    }
+
+    #[test]
+    fn test_sub_queries_are_parsed_from_json_or_lines() {
+        assert_eq!(
+            parse_sub_queries("Sure:\n```json\n[\"Berlin office lead\", \"refund policy\", \"Refund policy\", \" \"]\n```"),
+            vec!["Berlin office lead", "refund policy"]
+        );
+        assert_eq!(
+            parse_sub_queries("1. Berlin office lead\n2) \"refund policy\"\n- pricing"),
+            vec!["Berlin office lead", "refund policy", "pricing"]
+        );
+        let many = serde_json::to_string(&["a", "b", "c", "d", "e"]).unwrap();
+        assert_eq!(parse_sub_queries(&many).len(), MAX_SUB_QUERIES);
+    }
+
+    #[test]
+    fn test_merge_takes_turns_and_drops_repeats() {
+        let (merged, plan) = merge_results(
+            vec![
+                (
+                    "office lead".to_string(),
+                    result(&[("a", 0.9), ("b", 0.8), ("c", 0.7)], "Ada leads Berlin."),
+                ),
+                (
+                    "refund policy".to_string(),
+                    result(&[("a", 0.6), ("d", 0.5)], "Refunds take 30 days."),
+                ),
+            ],
+            4,
+        );
+        let ids: Vec<&str> = merged.nodes.iter().map(|n| n.id.as_str()).collect();
+        assert_eq!(ids, vec!["a", "d", "b", "c"]);
+        assert_eq!(merged.scores, vec![0.9, 0.5, 0.8, 0.7]);
+        assert_eq!(
+            merged.metadata.summary.as_deref(),
+            Some("Ada leads Berlin. Refunds take 30 days.")
+        );
+        assert!(merged
+            .metadata
+            .algorithms_used
+            .contains(&"query_decomposition".to_string()));
+        assert_eq!(plan.sub_queries[0].sources, vec!["a.md", "b.md", "c.md"]);
+        assert_eq!(plan.sub_queries[1].sources, vec!["d.md"]);
+
+        // Traces are merged and only the passages kept count as selected
+        let (merged, _) = merge_results(
+            vec![
+                (
+                    "office lead".to_string(),
+                    result(&[("a", 0.9), ("b", 0.8)], ""),
+                ),
+                (
+                    "refund policy".to_string(),
+                    result(&[("a", 0.6), ("d", 0.5)], ""),
+                ),
+            ],
+            2,
+        );
+        let trace = merged.metadata.trace.unwrap();
+        assert_eq!(trace.stages[0].ms, 6);
+        let outcomes: Vec<(&str, CandidateOutcome)> = trace
+            .candidates
+            .iter()
+            .map(|c| (c.id.as_str(), c.outcome))
+            .collect();
+        assert_eq!(
+            outcomes,
+            vec![
+                ("a", CandidateOutcome::Selected),
+                ("b", CandidateOutcome::RankedOut),
+                ("d", CandidateOutcome::Selected)
+            ]
+        );
+    }
+}
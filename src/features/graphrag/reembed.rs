@@ -0,0 +1,161 @@
+//! Moving vectors to another embedding model without a search outage. Documents are
+//! embedded into a staging index in the background while queries keep using the active
+//! vectors; the finished vectors replace the old ones in a single index generation.
+
+use super::embeddings::{document_chunks, VectorIndex};
+use crate::models::graphrag::DocumentIndex;
+use std::collections::HashSet;
+
+/// Embedding cost per chunk assumed until the first document has been timed
+const ESTIMATED_MS_PER_CHUNK: f64 = 150.0;
+
+/// A document to re-embed, with the model it is moving to
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReembedItem {
+    pub doc_id: String,
+    pub model: String,
+    pub chunks: Vec<String>,
+}
+
+/// Where a running migration is
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReembedProgress {
+    pub done_docs: usize,
+    pub total_docs: usize,
+    pub done_chunks: usize,
+    pub total_chunks: usize,
+    pub paused: bool,
+    /// Milliseconds left at the rate measured so far
+    pub eta_ms: f64,
+}
+
+impl ReembedProgress {
+    pub fn new(items: &[ReembedItem]) -> Self {
+        let total_chunks = items.iter().map(|i| i.chunks.len()).sum();
+        Self {
+            done_docs: 0,
+            total_docs: items.len(),
+            done_chunks: 0,
+            total_chunks,
+            paused: false,
+            eta_ms: estimate_ms(total_chunks, 0, 0.0),
+        }
+    }
+
+    pub fn fraction(&self) -> f32 {
+        if self.total_chunks == 0 {
+            return 1.0;
+        }
+        self.done_chunks as f32 / self.total_chunks as f32
+    }
+}
+
+/// Documents whose vectors come from a model other than the one `model_for` assigns.
+/// Documents without vectors are left to indexing.
+pub fn plan(
+    docs: &[DocumentIndex],
+    vectors: &VectorIndex,
+    model_for: impl Fn(&DocumentIndex) -> String,
+) -> Vec<ReembedItem> {
+    docs.iter()
+        .filter_map(|d| {
+            let current = vectors.model_of(&d.id)?;
+            let model = model_for(d);
+            (current != model).then(|| ReembedItem {
+                doc_id: d.id.clone(),
+                model,
+                chunks: document_chunks(d),
+            })
+        })
+        .collect()
+}
+
+/// Milliseconds needed for `remaining` chunks, at the rate of `done` chunks taking
+/// `spent_ms` once anything has been embedded
+pub fn estimate_ms(remaining: usize, done: usize, spent_ms: f64) -> f64 {
+    let per_chunk = if done > 0 {
+        spent_ms / done as f64
+    } else {
+        ESTIMATED_MS_PER_CHUNK
+    };
+    remaining as f64 * per_chunk
+}
+
+/// Replace the vectors of every staged document in `active`, skipping documents that
+/// left the index while the migration ran. Returns the ids swapped.
+pub fn apply(
+    active: &mut VectorIndex,
+    staged: &VectorIndex,
+    indexed: &[DocumentIndex],
+) -> Vec<String> {
+    let present: HashSet<&str> = indexed.iter().map(|d| d.id.as_str()).collect();
+    let mut swapped: Vec<String> = Vec::new();
+    for e in &staged.entries {
+        if !present.contains(e.doc_id.as_str()) {
+            continue;
+        }
+        if !swapped.contains(&e.doc_id) {
+            active.remove_document(&e.doc_id);
+            swapped.push(e.doc_id.clone());
+        }
+        active.entries.push(e.clone());
+    }
+    swapped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::graphrag::ProcessingStatus;
+
+    fn doc(id: &str) -> DocumentIndex {
+        DocumentIndex {
+            id: id.to_string(),
+            title: id.to_string(),
+            content: "some text".to_string(),
+            file_type: "text".to_string(),
+            size_bytes: 9,
+            created_at: 0.0,
+            indexed_at: 0.0,
+            node_count: 0,
+            embedding_model: None,
+            processing_status: ProcessingStatus::Completed,
+            symbols: Vec::new(),
+            parent: None,
+        }
+    }
+
+    #[test]
+    fn test_plan_and_apply_move_documents_to_their_model() {
+        let mut active = VectorIndex::default();
+        active
+            .upsert_document("a", "old", vec![vec![1.0, 0.0]])
+            .unwrap();
+        active
+            .upsert_document("b", "new", vec![vec![0.0, 1.0, 0.0]])
+            .unwrap();
+        let docs = vec![doc("a"), doc("b"), doc("c")];
+
+        let items = plan(&docs, &active, |_| "new".to_string());
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].doc_id, "a");
+        assert_eq!(ReembedProgress::new(&items).total_chunks, 1);
+
+        let mut staged = VectorIndex::default();
+        staged
+            .upsert_document("a", "new", vec![vec![1.0, 0.0, 0.0]])
+            .unwrap();
+        staged
+            .upsert_document("gone", "new", vec![vec![1.0, 1.0, 0.0]])
+            .unwrap();
+        assert_eq!(apply(&mut active, &staged, &docs), vec!["a".to_string()]);
+        assert_eq!(active.models(), vec!["new".to_string()]);
+        assert!(active.model_of("gone").is_none());
+    }
+
+    #[test]
+    fn test_estimate_uses_measured_rate() {
+        assert_eq!(estimate_ms(10, 0, 0.0), 10.0 * ESTIMATED_MS_PER_CHUNK);
+        assert_eq!(estimate_ms(10, 4, 200.0), 500.0);
+    }
+}
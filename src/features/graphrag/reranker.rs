@@ -0,0 +1,115 @@
+use super::embeddings::chunk_text;
+use super::summarizer::content_terms;
+use crate::models::app::{AppError, AppResult};
+use std::collections::HashSet;
+use wasm_bindgen::JsValue;
+use wasm_bindgen_futures::JsFuture;
+
+/// Cross-encoder (transformers.js) used when none is configured
+pub const DEFAULT_RERANKER_MODEL: &str = "Xenova/ms-marco-MiniLM-L-6-v2";
+
+// Passage sent per document; the MiniLM cross-encoders truncate at 512 tokens
+const PASSAGE_CHARS: usize = 1500;
+
+/// Score `(query, passage)` pairs with a cross-encoder through `window.crossEncoder`
+/// (index.html). Returns one relevance logit per passage.
+pub async fn cross_encoder_scores(
+    model: &str,
+    query: &str,
+    passages: &[String],
+) -> AppResult<Vec<f32>> {
+    let api = js_sys::Reflect::get(&js_sys::global(), &"crossEncoder".into())
+        .ok()
+        .filter(|v| !v.is_undefined())
+        .ok_or_else(|| AppError::InternalError("Cross-encoder is not loaded".to_string()))?;
+    let score: js_sys::Function = js_sys::Reflect::get(&api, &"score".into())
+        .map_err(|e| AppError::InternalError(format!("Cross-encoder API missing: {:?}", e)))?
+        .into();
+    let batch = passages
+        .iter()
+        .map(|p| JsValue::from_str(p))
+        .collect::<js_sys::Array>();
+    let promise = score
+        .call3(&api, &model.into(), &query.into(), &batch)
+        .map_err(|e| AppError::InternalError(format!("Cross-encoder call failed: {:?}", e)))?;
+    let result = JsFuture::from(js_sys::Promise::from(promise))
+        .await
+        .map_err(|e| AppError::InternalError(format!("Cross-encoder scoring failed: {:?}", e)))?;
+    let scores: Vec<f32> = js_sys::Array::from(&result)
+        .iter()
+        .map(|v| v.as_f64().unwrap_or(f64::NEG_INFINITY) as f32)
+        .collect();
+    if scores.len() != passages.len() {
+        return Err(AppError::InternalError(format!(
+            "Cross-encoder returned {} scores for {} passages",
+            scores.len(),
+            passages.len()
+        )));
+    }
+    Ok(scores)
+}
+
+/// The chunk of `content` sharing the most terms with the query, so long documents
+/// are judged on their most relevant part instead of their opening
+pub fn select_passage(query: &str, content: &str) -> String {
+    let query_terms: HashSet<String> = content_terms(query).into_iter().collect();
+    // Earliest chunk wins ties
+    chunk_text(content, PASSAGE_CHARS, 0)
+        .into_iter()
+        .enumerate()
+        .max_by_key(|(i, chunk)| {
+            let terms: HashSet<String> = content_terms(chunk).into_iter().collect();
+            (
+                terms.intersection(&query_terms).count(),
+                std::cmp::Reverse(*i),
+            )
+        })
+        .map(|(_, chunk)| chunk)
+        .unwrap_or_default()
+}
+
+/// Reorder candidates by cross-encoder logit, best first. Scores become the logit's
+/// sigmoid so later stages see a 0..1 relevance.
+pub fn apply_scores(top: &mut Vec<(usize, f32)>, logits: &[f32]) {
+    let mut ranked: Vec<((usize, f32), f32)> = top
+        .iter()
+        .zip(logits)
+        .map(|(&(idx, _), &logit)| ((idx, sigmoid(logit)), logit))
+        .collect();
+    ranked.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+    *top = ranked.into_iter().map(|(candidate, _)| candidate).collect();
+}
+
+fn sigmoid(x: f32) -> f32 {
+    1.0 / (1.0 + (-x).exp())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_scores_reorders_by_logit() {
+        let mut top = vec![(0, 0.9), (1, 0.8), (2, 0.7)];
+        apply_scores(&mut top, &[-2.0, 3.0, 0.0]);
+        assert_eq!(
+            top.iter().map(|(i, _)| *i).collect::<Vec<_>>(),
+            vec![1, 2, 0]
+        );
+        assert!((top[1].1 - 0.5).abs() < 1e-6);
+        assert!(top[0].1 > 0.9 && top[2].1 < 0.2);
+    }
+
+    #[test]
+    fn test_select_passage_prefers_matching_chunk() {
+        let filler = "Unrelated introduction text. ".repeat(80);
+        let content = format!(
+            "{}Refund policy: refunds are issued within 30 days.",
+            filler
+        );
+        let passage = select_passage("How do refunds work?", &content);
+        assert!(passage.contains("Refund policy"));
+        assert!(passage.chars().count() <= PASSAGE_CHARS);
+        assert_eq!(select_passage("anything", "short doc"), "short doc");
+    }
+}
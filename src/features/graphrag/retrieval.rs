@@ -1,13 +1,30 @@
+use super::content_store;
+use super::embeddings::{embed_texts, fuse_scores};
+use super::index_generation::IndexSnapshot;
+use super::index_stats::fingerprint;
+use super::reranker::{apply_scores, cross_encoder_scores, select_passage};
+use super::summarizer::content_terms;
 use crate::graphrag_config::{with_graphrag_manager, GraphRAGConfig, PerformanceMetrics};
-use crate::models::graph_store::GraphStore;
+use crate::models::attachment::{AttachmentScope, Attachments};
+use crate::models::collection::Collections;
+use crate::models::freshness::DocumentExpiry;
 use crate::models::graphrag::{
-    DocumentIndex, EdgeMetadata, EdgeType, GraphEdge, GraphNode, NodeType, RAGQuery, RAGResult,
-    ResultMetadata, SearchStrategy,
+    BelowThreshold, CandidateOutcome, DocumentIndex, EdgeMetadata, EdgeType, GraphEdge, GraphNode,
+    NodeType, RAGQuery, RAGResult, ResultMetadata, RetrievalTrace, SearchStrategy, TraceCandidate,
+    TraceStage,
 };
-use crate::utils::storage::StorageUtils;
+use crate::storage::{CachePolicy, TieredCache};
 use std::collections::{HashMap, HashSet};
 
-/// GraphRAG retrieval entrypoints. Stubs returning empty results.
+// Minutes a query result is reused for an identical query
+const QUERY_CACHE_MINUTES: f64 = 10.0;
+// Candidates considered per result slot when diversifying by maximal marginal relevance
+const MMR_POOL_FACTOR: usize = 3;
+
+/// GraphRAG retrieval over one pinned index generation: HyDE expansion, lexical search
+/// fused with embeddings, community, PageRank and reranking stages, then an extractive
+/// summary of the top results. Each stage is timed into the manager's performance
+/// metrics; an identical query reuses the cached result.
 pub struct Retriever;
 
 impl Retriever {
@@ -27,31 +44,97 @@ impl Retriever {
         let mut synthesis_time_ms: u32 = 0;
         let mut algorithms = vec![format!("strategy:{:?}", strategy)];
 
-        // Load GraphRAGConfig from localStorage (prefer v1 key, fallback to legacy, else defaults)
-        let config: GraphRAGConfig = if let Ok(Some(c)) =
-            StorageUtils::retrieve_local::<GraphRAGConfig>("graphrag_config_v1")
-        {
-            c
-        } else {
-            match StorageUtils::retrieve_local::<GraphRAGConfig>("graphrag_config") {
-                Ok(Some(c)) => c,
-                _ => GraphRAGConfig::default(),
+        // Settings saved by the GraphRAG panel, else defaults
+        let config = GraphRAGConfig::load();
+
+        // Pin one index generation for the whole query; a reindex committing while this
+        // query awaits does not change what it reads
+        let snapshot = match IndexSnapshot::for_query(q.config.index_generation) {
+            Ok(snapshot) => snapshot,
+            Err(e) => {
+                log::warn!("Query {} refused: {}", q.id, e);
+                return refused_result(q, e.to_string());
             }
         };
 
-        // Load persisted index (versioned key with legacy fallback)
-        let docs: Vec<DocumentIndex> = if let Ok(Some(v)) =
-            StorageUtils::retrieve_local::<Vec<DocumentIndex>>("graphrag_document_index_v1")
-        {
-            v
+        // Documents past their expiry date are left out unless the settings include them
+        let expired_files = if config.include_expired {
+            Vec::new()
         } else {
-            match StorageUtils::retrieve_local::<Vec<DocumentIndex>>("graphrag_document_index") {
-                Ok(Some(v)) => v,
-                _ => Vec::new(),
+            DocumentExpiry::load().unwrap_or_default().expired_files(
+                snapshot.documents.iter().map(|d| d.source_file()),
+                js_sys::Date::now(),
+            )
+        };
+        // Embedding model of each collection, and the collections a scope names
+        let collections = Collections::load().unwrap_or_default();
+        // Files attached to a message are only retrieved in that conversation, and a
+        // conversation limited to some documents searches those and its attachments
+        let attachments = Attachments::load().unwrap_or_default();
+        let conversation = q.filters.conversation_id.as_deref();
+        let visible = |d: &DocumentIndex| {
+            !expired_files.iter().any(|f| f == d.source_file())
+                && match attachments.scope(d, conversation) {
+                    AttachmentScope::Here => true,
+                    AttachmentScope::Elsewhere => false,
+                    AttachmentScope::Global => q.filters.scope.includes(d, &collections),
+                }
+        };
+        let fresh_docs: Vec<DocumentIndex>;
+        let docs: &[DocumentIndex] = if snapshot.documents.iter().all(visible) {
+            &snapshot.documents
+        } else {
+            if !expired_files.is_empty() {
+                algorithms.push("expiry_filter".into());
             }
+            if !q.filters.scope.is_empty() {
+                algorithms.push("conversation_scope".into());
+            }
+            fresh_docs = snapshot
+                .documents
+                .iter()
+                .filter(|d| visible(d))
+                .cloned()
+                .collect();
+            &fresh_docs
         };
 
+        // An identical query against the same generation and settings reuses the result
+        let cache_key = query_cache_key(
+            q,
+            &strategy,
+            &config,
+            &collections,
+            &attachments,
+            &expired_files,
+            snapshot.generation,
+        );
+        let cached = (!q.config.bypass_cache)
+            .then(|| TieredCache::get::<RAGResult>(&cache_key))
+            .flatten();
+        if let Some(mut cached) = cached {
+            cached.id = q.id.clone();
+            cached.query_id = q.id.clone();
+            cached.metadata.processing_time_ms = (js_sys::Date::now() - t0) as u32;
+            cached.metadata.algorithms_used.push("query_cache".into());
+            if let Some(trace) = &mut cached.metadata.trace {
+                trace.stages.push(TraceStage {
+                    name: "Query cache".into(),
+                    ms: cached.metadata.processing_time_ms,
+                });
+            }
+            with_graphrag_manager(|m| {
+                m.record_cache_lookup(true);
+                m.update_query_metrics(
+                    cached.metadata.processing_time_ms,
+                    content_store::cache_usage().used_mb(),
+                );
+            });
+            return cached;
+        }
+
         // Tokenize query for TF-IDF style scoring
+        let t_s0 = js_sys::Date::now();
         let mut q_tokens: Vec<String> = q
             .text
             .to_lowercase()
@@ -60,6 +143,12 @@ impl Retriever {
             .filter(|s| !s.is_empty())
             .collect();
 
+        // Content terms of the original query, used for the absolute relevance score
+        let mut query_terms: HashSet<String> = content_terms(&q.text).into_iter().collect();
+        if query_terms.is_empty() {
+            query_terms = q_tokens.iter().cloned().collect();
+        }
+
         // HyDE expansion (very light heuristic): duplicate tokens to upweight terms if enabled
         let hyde_on = q.config.use_hyde || config.hyde_enabled;
         if hyde_on {
@@ -78,7 +167,7 @@ impl Retriever {
         let mut doc_sets: Vec<HashSet<String>> = Vec::with_capacity(docs.len());
         let mut df: HashMap<String, usize> = HashMap::new();
 
-        for d in &docs {
+        for d in docs {
             let content = if d.content.is_empty() {
                 d.title.clone()
             } else {
@@ -121,62 +210,151 @@ impl Retriever {
             scored.push((i, score));
         }
 
+        // Dense retrieval: blend cosine similarity of the best chunk with the lexical score.
+        // The query is embedded once per collection model and each document is compared
+        // only with vectors of its own collection's model; documents still embedded with
+        // another model stay lexical-only until re-embedded. Falls back to lexical-only
+        // when the index is empty or a model fails.
+        let mut semantic_hits: HashMap<String, f32> = HashMap::new();
+        if config.embeddings_enabled && !docs.is_empty() && !snapshot.vectors.is_empty() {
+            let vindex = &snapshot.vectors;
+            let assigned: Vec<String> = docs
+                .iter()
+                .map(|d| collections.embedding_model_for(d, &config.embedding_model))
+                .collect();
+            let indexed_models = vindex.models();
+            for model in indexed_models.iter().filter(|m| assigned.contains(m)) {
+                match embed_texts(model, &[q.text.clone()]).await {
+                    Ok(mut vectors) if !vectors.is_empty() => {
+                        let hits = vindex.search(model, &vectors.remove(0));
+                        for (d, _) in docs.iter().zip(&assigned).filter(|(_, m)| *m == model) {
+                            if let Some(sim) = hits.get(&d.id) {
+                                semantic_hits.insert(d.id.clone(), *sim);
+                            }
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(e) => log::warn!("Embedding retrieval with {} skipped: {}", model, e),
+                }
+            }
+            if !semantic_hits.is_empty() {
+                let hits = &semantic_hits;
+                let lexical: Vec<f32> = scored.iter().map(|(_, s)| *s).collect();
+                let semantic: Vec<f32> = scored
+                    .iter()
+                    .map(|(i, _)| hits.get(&docs[*i].id).copied().unwrap_or(0.0))
+                    .collect();
+                let fused = fuse_scores(&lexical, &semantic, config.semantic_weight);
+                for ((_, s), f) in scored.iter_mut().zip(fused) {
+                    *s = f;
+                }
+                algorithms.push("embeddings".into());
+            }
+        }
+
+        // Chunks defining a symbol the query names ("where is parse_config implemented")
+        // rank above every text match
+        let symbol_hits: HashSet<usize> = docs
+            .iter()
+            .enumerate()
+            .filter(|(_, d)| names_symbol(&q.text, &d.symbols))
+            .map(|(i, _)| i)
+            .collect();
+        if !symbol_hits.is_empty() {
+            algorithms.push("symbol_match".into());
+            let boost = scored.iter().map(|(_, s)| *s).fold(1.0f32, f32::max);
+            for (i, s) in scored.iter_mut() {
+                if symbol_hits.contains(i) {
+                    *s += boost;
+                }
+            }
+        }
+
+        // Files attached in this conversation rank above the rest of the knowledge base
+        let attached_here: HashSet<usize> = docs
+            .iter()
+            .enumerate()
+            .filter(|(_, d)| attachments.scope(d, conversation) == AttachmentScope::Here)
+            .map(|(i, _)| i)
+            .collect();
+        if !attached_here.is_empty() {
+            algorithms.push("conversation_attachments".into());
+            let boost = scored.iter().map(|(_, s)| *s).fold(1.0f32, f32::max);
+            for (i, s) in scored.iter_mut() {
+                if attached_here.contains(i) {
+                    *s += boost;
+                }
+            }
+        }
+
         // Sort by score desc and take top K according to config
         scored.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+        let scoring_time_ms = ((js_sys::Date::now() - t_s0) as u32).saturating_sub(hyde_time_ms);
         let k = q.config.max_results.max(1);
-        let mut top = scored.into_iter().take(k).collect::<Vec<_>>();
+        // Keep a wider pool when MMR picks the final K from it
+        let pool = if config.mmr_enabled {
+            k * MMR_POOL_FACTOR
+        } else {
+            k
+        };
 
-        // Optional PageRank-like centrality weighting over top docs
-        // Uses Jaccard similarities among top docs as edge weights; boosts central/important docs.
+        // Community scoping over the Louvain assignments persisted in the GraphStore.
+        // Without assignments the token-overlap boost below is used instead.
+        let use_community = q.config.use_community_detection || config.community_detection_enabled;
+        let mut community_scoped = false;
+        let mut top = if use_community {
+            let t_c0 = js_sys::Date::now();
+            let communities = snapshot.graph.document_communities();
+            let top = if communities.is_empty() {
+                scored.into_iter().take(pool).collect::<Vec<_>>()
+            } else {
+                community_scoped = true;
+                algorithms.push("louvain_communities".into());
+                scope_by_community(
+                    &scored,
+                    |i| communities.get(&docs[i].id).copied(),
+                    &strategy,
+                    pool,
+                )
+            };
+            community_time_ms = (js_sys::Date::now() - t_c0) as u32;
+            top
+        } else {
+            scored.into_iter().take(pool).collect::<Vec<_>>()
+        };
+
+        // Every candidate of the pool is traced through the remaining stages
+        let mut candidates: Vec<TraceCandidate> = top
+            .iter()
+            .map(|(idx, sc)| TraceCandidate {
+                id: docs[*idx].id.clone(),
+                title: docs[*idx].title.clone(),
+                search_score: *sc,
+                graph_score: None,
+                rerank_score: None,
+                relevance: 0.0,
+                outcome: CandidateOutcome::RankedOut,
+            })
+            .collect();
+
+        // PageRank weighting: boost documents that are central in the knowledge graph.
+        // Scores are computed over the GraphStore at index time (see NodeImportance).
         let use_pr = config.pagerank_enabled;
         if use_pr && top.len() > 1 {
             let t_pr0 = js_sys::Date::now();
-            algorithms.push("pagerank_weighting".into());
-            // Build a simple centrality score: sum of Jaccard weights to others
-            let mut centrality: Vec<f32> = vec![0.0; top.len()];
-            for (i, (di, _)) in top.iter().enumerate() {
-                let di = *di;
-                for (j, (dj, _)) in top.iter().enumerate() {
-                    if i == j {
-                        continue;
-                    }
-                    let dj = *dj;
-                    let set_i = &doc_sets[di];
-                    let set_j = &doc_sets[dj];
-                    let inter = set_i.intersection(set_j).count() as f32;
-                    let uni = set_i.union(set_j).count() as f32;
-                    if uni > 0.0 {
-                        let w = inter / uni; // Jaccard weight
-                        centrality[i] += w;
-                    }
+            if let Some(importance) = &snapshot.importance {
+                algorithms.push("pagerank".into());
+                let alpha = 0.2f32;
+                for (idx, s) in top.iter_mut() {
+                    *s *= 1.0 + alpha * importance.document(&docs[*idx].id);
                 }
+                top.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
             }
-            // Normalize centrality to 0..1
-            if let Some(max_c) = centrality
-                .iter()
-                .cloned()
-                .fold(None, |acc: Option<f32>, x| {
-                    Some(acc.map_or(x, |m| if x > m { x } else { m }))
-                })
-            {
-                if max_c > 0.0 {
-                    for c in &mut centrality {
-                        *c /= max_c;
-                    }
-                }
-            }
-            // Apply boost to scores and re-sort
-            let alpha = 0.2f32;
-            for (i, (_idx, s)) in top.iter_mut().enumerate() {
-                *s *= 1.0 + alpha * centrality[i];
-            }
-            top.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
             pagerank_time_ms = (js_sys::Date::now() - t_pr0) as u32;
         }
 
-        // Optional community boosting: lightweight cluster-based boost using token overlap
-        let use_community = q.config.use_community_detection || config.community_detection_enabled;
-        if use_community && top.len() > 1 {
+        // Fallback community boosting: lightweight cluster-based boost using token overlap
+        if use_community && !community_scoped && top.len() > 1 {
             let t_c0 = js_sys::Date::now();
             algorithms.push("community_boost".into());
             // Build neighbor counts based on Jaccard >= threshold within top-K
@@ -212,30 +390,15 @@ impl Retriever {
                     top.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
                 }
             }
-            community_time_ms = (js_sys::Date::now() - t_c0) as u32;
-        }
-
-        // Optional improved reranking: apply small deterministic tiebreak and resort
-        let mut was_reranked = false;
-        let do_rerank = q.config.use_reranking || config.reranking_enabled;
-        if do_rerank {
-            let t_r0 = js_sys::Date::now();
-            algorithms.push("advanced_rerank".into());
-            was_reranked = true;
-            for (i, (_idx, s)) in top.iter_mut().enumerate() {
-                // tiny index-based perturbation to stabilize ordering and break ties
-                *s += (i as f32) * 1e-6;
-            }
-            top.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
-            reranking_time_ms = (js_sys::Date::now() - t_r0) as u32;
+            community_time_ms += (js_sys::Date::now() - t_c0) as u32;
         }
 
         // Hybrid fusion: combine text scores with simple graph scores (mentions degree)
         if config.hybrid_enabled && !top.is_empty() {
             let t_hf0 = js_sys::Date::now();
             algorithms.push("hybrid_fusion".into());
-            // Load graph store and compute a simple graph score per document id: mentions degree
-            let store = GraphStore::load().unwrap_or_default();
+            // Compute a simple graph score per document id from the pinned graph: mentions degree
+            let store = &snapshot.graph;
             let doc_id_set: std::collections::HashSet<String> =
                 docs.iter().map(|d| d.id.clone()).collect();
             let mut degree: std::collections::HashMap<String, f32> =
@@ -286,21 +449,124 @@ impl Retriever {
             hybrid_fusion_time_ms = (js_sys::Date::now() - t_hf0) as u32;
         }
 
+        let graph_stages = [
+            "louvain_communities",
+            "pagerank",
+            "community_boost",
+            "hybrid_fusion",
+        ];
+        if algorithms
+            .iter()
+            .any(|a| graph_stages.contains(&a.as_str()))
+        {
+            trace_scores(&mut candidates, &top, docs, |c, s| c.graph_score = Some(s));
+        }
+
+        // In large knowledge base mode the index only holds sketches; the full texts of
+        // the candidates are streamed in from disk
+        let bodies = content_store::load_bodies(
+            top.iter().map(|(idx, _)| &docs[*idx]),
+            config.max_memory_mb,
+        )
+        .await;
+        let content_of = |idx: usize| -> String {
+            let d = &docs[idx];
+            match bodies.get(&d.id) {
+                Some(body) => body.clone(),
+                None if d.content.is_empty() => d.title.clone(),
+                None => d.content.clone(),
+            }
+        };
+
+        // Cross-encoder reranking of the fused top-K: scores (query, passage) pairs and
+        // reorders by relevance. Keeps the fused order if the model is unavailable.
+        let mut was_reranked = false;
+        let do_rerank = q.config.use_reranking || config.reranking_enabled;
+        if do_rerank && top.len() > 1 {
+            let t_r0 = js_sys::Date::now();
+            let passages: Vec<String> = top
+                .iter()
+                .map(|(idx, _)| select_passage(&q.text, &content_of(*idx)))
+                .collect();
+            match cross_encoder_scores(&config.reranker_model, &q.text, &passages).await {
+                Ok(logits) => {
+                    apply_scores(&mut top, &logits);
+                    trace_scores(&mut candidates, &top, docs, |c, s| c.rerank_score = Some(s));
+                    algorithms.push("cross_encoder_rerank".into());
+                    was_reranked = true;
+                }
+                Err(e) => log::warn!("Reranking skipped: {}", e),
+            }
+            reranking_time_ms = (js_sys::Date::now() - t_r0) as u32;
+        }
+
+        // Maximal marginal relevance: pick the final K from the pool so passages that
+        // repeat an already chosen one give way to ones adding new content
+        if config.mmr_enabled && top.len() > 1 {
+            top = mmr_select(
+                &top,
+                |a, b| jaccard(&doc_sets[a], &doc_sets[b]),
+                config.mmr_lambda,
+                k,
+            );
+            algorithms.push("mmr".into());
+        }
+        top.truncate(k);
+
+        // Drop candidates below the relevance threshold so unrelated snippets are never
+        // injected; when nothing passes, report the best rejected candidate instead.
+        // Relevance is the share of query terms a document contains, or its embedding
+        // similarity when higher, so unlike the rank-normalized scores it is comparable
+        // across queries.
+        let relevance = |idx: usize| -> f32 {
+            if symbol_hits.contains(&idx) {
+                return 1.0;
+            }
+            let lexical = term_coverage(&query_terms, &doc_sets[idx]);
+            let semantic = semantic_hits.get(&docs[idx].id).copied().unwrap_or(0.0);
+            lexical.max(semantic).clamp(0.0, 1.0)
+        };
+        let mut below_threshold = None;
+        let best_candidate = top
+            .iter()
+            .map(|(idx, _)| (*idx, relevance(*idx)))
+            .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
+        top.retain(|(idx, _)| relevance(*idx) >= config.min_relevance);
+        for c in candidates.iter_mut() {
+            let idx = docs.iter().position(|d| d.id == c.id).unwrap_or_default();
+            c.relevance = relevance(idx);
+            c.outcome = if top.iter().any(|(i, _)| *i == idx) {
+                CandidateOutcome::Selected
+            } else if c.relevance < config.min_relevance {
+                CandidateOutcome::BelowThreshold
+            } else {
+                CandidateOutcome::RankedOut
+            };
+        }
+        if top.is_empty() {
+            if let Some((idx, score)) = best_candidate {
+                algorithms.push("relevance_threshold".into());
+                below_threshold = Some(BelowThreshold {
+                    best_title: docs[idx].title.clone(),
+                    best_score: score,
+                    threshold: config.min_relevance,
+                });
+            }
+        }
+
         // Build nodes with stable IDs from DocumentIndex and annotate source/confidence
         let mut nodes: Vec<GraphNode> = Vec::with_capacity(top.len());
         let mut scores: Vec<f32> = Vec::with_capacity(top.len());
         for (idx, sc) in &top {
             let d = &docs[*idx];
-            let content = if d.content.is_empty() {
-                d.title.clone()
-            } else {
-                d.content.clone()
-            };
-            let mut node = GraphNode::new(content, NodeType::Document);
+            let mut node = GraphNode::new(content_of(*idx), NodeType::Document);
             // Use stable id and enrich metadata
             node.id = d.id.clone();
             node.metadata.source = Some(d.title.clone());
-            node.metadata.confidence = (*sc).clamp(0.0, 1e9); // raw score stored as confidence proxy
+            node.metadata.confidence = relevance(*idx);
+            node.metadata
+                .properties
+                .insert("rank_score".to_string(), sc.to_string());
             nodes.push(node);
             scores.push(*sc);
         }
@@ -375,12 +641,7 @@ impl Retriever {
             // Take up to first 3 sentences from the highest-scoring documents
             let mut parts: Vec<String> = Vec::new();
             for (idx, _sc) in top.iter().take(3) {
-                let d = &docs[*idx];
-                let content = if d.content.is_empty() {
-                    d.title.clone()
-                } else {
-                    d.content.clone()
-                };
+                let content = content_of(*idx);
                 // naive sentence split on '.', '!' or '?' and filter empties
                 let sentences: Vec<String> = content
                     .split(['.', '!', '?'])
@@ -410,6 +671,38 @@ impl Retriever {
 
         // Finalize processing time and update metrics after all stages (including synthesis)
         let processing_time_ms = (js_sys::Date::now() - t0) as u32;
+        let trace = RetrievalTrace {
+            query_tokens: q_tokens
+                .iter()
+                .fold(Vec::new(), |mut unique: Vec<String>, t| {
+                    if !unique.contains(t) {
+                        unique.push(t.clone());
+                    }
+                    unique
+                }),
+            stages: [
+                ("HyDE expansion", hyde_on, hyde_time_ms),
+                ("Search", true, scoring_time_ms),
+                ("Community", use_community, community_time_ms),
+                ("PageRank", use_pr, pagerank_time_ms),
+                (
+                    "Hybrid fusion",
+                    config.hybrid_enabled,
+                    hybrid_fusion_time_ms,
+                ),
+                ("Reranking", do_rerank, reranking_time_ms),
+                ("Synthesis", config.synthesis_enabled, synthesis_time_ms),
+            ]
+            .into_iter()
+            .filter(|(_, ran, _)| *ran)
+            .map(|(name, _, ms)| TraceStage {
+                name: name.into(),
+                ms,
+            })
+            .collect(),
+            candidates,
+            prompt_chunks: Vec::new(),
+        };
         let perf = PerformanceMetrics {
             hyde_time_ms,
             community_detection_time_ms: community_time_ms,
@@ -420,11 +713,12 @@ impl Retriever {
             total_time_ms: processing_time_ms,
         };
         with_graphrag_manager(|m| {
+            m.record_cache_lookup(false);
+            m.update_query_metrics(processing_time_ms, content_store::cache_usage().used_mb());
             m.update_performance_metrics(perf.clone());
-            m.update_query_metrics(processing_time_ms, 0.0);
         });
 
-        RAGResult {
+        let result = RAGResult {
             id: q.id.clone(),
             query_id: q.id.clone(),
             nodes,
@@ -438,8 +732,85 @@ impl Retriever {
                 community_filtered: community_on,
                 algorithms_used: algorithms,
                 summary,
+                below_threshold,
+                index_generation: snapshot.generation,
+                trace: Some(trace),
             },
+        };
+        if let Err(e) = TieredCache::set(
+            &cache_key,
+            &result,
+            CachePolicy::memory(QUERY_CACHE_MINUTES),
+        ) {
+            log::warn!("Query result not cached: {}", e);
         }
+        result
+    }
+}
+
+/// Record the current score of each traced candidate still in `top`
+fn trace_scores(
+    candidates: &mut [TraceCandidate],
+    top: &[(usize, f32)],
+    docs: &[DocumentIndex],
+    set: impl Fn(&mut TraceCandidate, f32),
+) {
+    for (idx, sc) in top {
+        if let Some(c) = candidates.iter_mut().find(|c| c.id == docs[*idx].id) {
+            set(c, *sc);
+        }
+    }
+}
+
+/// Cache key of a query: its text and settings plus the generation it reads
+fn query_cache_key(
+    q: &RAGQuery,
+    strategy: &SearchStrategy,
+    config: &GraphRAGConfig,
+    collections: &Collections,
+    attachments: &Attachments,
+    expired_files: &[String],
+    generation: u64,
+) -> String {
+    let settings = format!(
+        "{:?}|{}|{}|{}|{}|{}|{:?}|{}|{}",
+        strategy,
+        generation,
+        serde_json::to_string(&q.config).unwrap_or_default(),
+        serde_json::to_string(config).unwrap_or_default(),
+        serde_json::to_string(collections).unwrap_or_default(),
+        serde_json::to_string(attachments).unwrap_or_default(),
+        q.filters.conversation_id,
+        serde_json::to_string(&q.filters.scope).unwrap_or_default(),
+        expired_files.join("\n")
+    );
+    format!(
+        "rag_query:{:016x}:{:016x}",
+        fingerprint(&settings),
+        fingerprint(&q.text)
+    )
+}
+
+/// Empty result for a query whose index generation cannot be read
+fn refused_result(q: &RAGQuery, reason: String) -> RAGResult {
+    RAGResult {
+        id: q.id.clone(),
+        query_id: q.id.clone(),
+        nodes: vec![],
+        edges: vec![],
+        scores: vec![],
+        metadata: ResultMetadata {
+            processing_time_ms: 0,
+            total_nodes_searched: 0,
+            reranked: false,
+            hyde_enhanced: false,
+            community_filtered: false,
+            algorithms_used: vec!["generation_refused".into()],
+            summary: Some(reason),
+            below_threshold: None,
+            index_generation: q.config.index_generation.unwrap_or_default(),
+            trace: None,
+        },
     }
 }
 
@@ -448,3 +819,250 @@ impl Default for Retriever {
         Self::new()
     }
 }
+
+/// Pick up to `k` candidates from `scored` (best first) using document communities.
+/// Local keeps only the best document's community; Global takes the best remaining
+/// match of each community in turn; other strategies keep the ranking but boost
+/// documents that share the best document's community.
+pub fn scope_by_community(
+    scored: &[(usize, f32)],
+    community: impl Fn(usize) -> Option<usize>,
+    strategy: &SearchStrategy,
+    k: usize,
+) -> Vec<(usize, f32)> {
+    let Some(&(best_idx, _)) = scored.first() else {
+        return Vec::new();
+    };
+    let Some(best) = community(best_idx) else {
+        return scored.iter().take(k).copied().collect();
+    };
+    match strategy {
+        SearchStrategy::Local => scored
+            .iter()
+            .filter(|(i, _)| community(*i) == Some(best))
+            .take(k)
+            .copied()
+            .collect(),
+        SearchStrategy::Global => {
+            // Round-robin over communities of the matching documents; documents
+            // without a community count as their own group
+            let mut groups: Vec<(Option<usize>, Vec<(usize, f32)>)> = Vec::new();
+            for &(i, s) in scored.iter().filter(|(_, s)| *s > 0.0) {
+                let c = community(i);
+                match groups.iter_mut().find(|(g, _)| c.is_some() && *g == c) {
+                    Some((_, members)) => members.push((i, s)),
+                    None => groups.push((c, vec![(i, s)])),
+                }
+            }
+            let mut out = Vec::new();
+            let mut round = 0;
+            while out.len() < k && groups.iter().any(|(_, m)| m.len() > round) {
+                out.extend(groups.iter().filter_map(|(_, m)| m.get(round).copied()));
+                round += 1;
+            }
+            out.truncate(k);
+            for &(i, s) in scored.iter().filter(|(_, s)| *s <= 0.0) {
+                if out.len() >= k {
+                    break;
+                }
+                out.push((i, s));
+            }
+            out
+        }
+        SearchStrategy::Automatic | SearchStrategy::Combined => {
+            let mut boosted: Vec<(usize, f32)> = scored
+                .iter()
+                .map(|&(i, s)| {
+                    if community(i) == Some(best) {
+                        (i, s * 1.15)
+                    } else {
+                        (i, s)
+                    }
+                })
+                .collect();
+            boosted.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+            boosted.truncate(k);
+            boosted
+        }
+    }
+}
+
+/// Pick up to `k` candidates from `scored` by maximal marginal relevance: each step takes
+/// the candidate maximizing `lambda * relevance - (1 - lambda) * similarity` to the
+/// closest one already picked. Relevance is the score normalized by the best one;
+/// picked candidates keep their original scores.
+pub fn mmr_select(
+    scored: &[(usize, f32)],
+    similarity: impl Fn(usize, usize) -> f32,
+    lambda: f32,
+    k: usize,
+) -> Vec<(usize, f32)> {
+    let lambda = lambda.clamp(0.0, 1.0);
+    let max = scored.iter().map(|(_, s)| *s).fold(0.0f32, f32::max);
+    let relevance = |s: f32| if max > 0.0 { s / max } else { 0.0 };
+    let mut remaining: Vec<(usize, f32)> = scored.to_vec();
+    let mut picked: Vec<(usize, f32)> = Vec::with_capacity(k.min(scored.len()));
+    while picked.len() < k && !remaining.is_empty() {
+        let mmr = |&(i, s): &(usize, f32)| {
+            let redundancy = picked
+                .iter()
+                .map(|(j, _)| similarity(i, *j))
+                .fold(0.0f32, f32::max);
+            lambda * relevance(s) - (1.0 - lambda) * redundancy
+        };
+        // Ties go to the earlier, better ranked candidate
+        let (best, _) = remaining
+            .iter()
+            .enumerate()
+            .fold((0, f32::NEG_INFINITY), |acc, (n, c)| {
+                let value = mmr(c);
+                if value > acc.1 {
+                    (n, value)
+                } else {
+                    acc
+                }
+            });
+        picked.push(remaining.remove(best));
+    }
+    picked
+}
+
+/// Overlap of two term sets (0..1)
+pub fn jaccard(a: &HashSet<String>, b: &HashSet<String>) -> f32 {
+    let union = a.union(b).count();
+    if union == 0 {
+        return 0.0;
+    }
+    a.intersection(b).count() as f32 / union as f32
+}
+
+/// Share of `query_terms` present in `doc_terms` (0..1)
+pub fn term_coverage(query_terms: &HashSet<String>, doc_terms: &HashSet<String>) -> f32 {
+    if query_terms.is_empty() {
+        return 0.0;
+    }
+    query_terms.intersection(doc_terms).count() as f32 / query_terms.len() as f32
+}
+
+/// Whether `query` mentions one of `symbols` as a whole identifier, e.g. `load` in
+/// "where is Config::load implemented?". Case only matters for plain words, so "config"
+/// in a question does not match a `Config` type.
+pub fn names_symbol(query: &str, symbols: &[String]) -> bool {
+    if symbols.is_empty() {
+        return false;
+    }
+    query
+        .split(|c: char| !(c.is_alphanumeric() || c == '_' || c == '$'))
+        .filter(|w| w.len() >= 3)
+        .any(|w| {
+            symbols
+                .iter()
+                .any(|s| s == w || (w.contains('_') && s.eq_ignore_ascii_case(w)))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(words: &[&str]) -> HashSet<String> {
+        words.iter().map(|w| w.to_string()).collect()
+    }
+
+    #[test]
+    fn test_term_coverage() {
+        let q = set(&["pricing", "enterprise"]);
+        assert_eq!(
+            term_coverage(&q, &set(&["enterprise", "pricing", "plan"])),
+            1.0
+        );
+        assert_eq!(term_coverage(&q, &set(&["pricing"])), 0.5);
+        assert_eq!(term_coverage(&q, &set(&["weather"])), 0.0);
+        assert_eq!(term_coverage(&HashSet::new(), &set(&["pricing"])), 0.0);
+    }
+
+    #[test]
+    fn test_names_symbol() {
+        let symbols = vec!["parse_config".to_string(), "Config".to_string()];
+        assert!(names_symbol(
+            "where is parse_config() implemented?",
+            &symbols
+        ));
+        assert!(names_symbol("what does Config::load do", &symbols));
+        assert!(names_symbol("where is PARSE_CONFIG set", &symbols));
+        assert!(!names_symbol("how do I parse the config file", &symbols));
+        assert!(!names_symbol("where is parse_config", &[]));
+    }
+
+    #[test]
+    fn test_mmr_select_skips_near_duplicates() {
+        // docs 0 and 1 are the same passage; doc 2 is different but scores lower
+        let docs = [
+            set(&["refund", "policy", "days"]),
+            set(&["refund", "policy", "days"]),
+            set(&["refund", "shipping", "costs"]),
+        ];
+        let scored = vec![(0, 1.0), (1, 0.95), (2, 0.7)];
+        let ids = |v: Vec<(usize, f32)>| v.into_iter().map(|(i, _)| i).collect::<Vec<_>>();
+
+        let picked = mmr_select(&scored, |a, b| jaccard(&docs[a], &docs[b]), 0.7, 2);
+        assert_eq!(ids(picked.clone()), vec![0, 2]);
+        assert_eq!(picked[1].1, 0.7);
+        // Relevance only keeps the original ranking
+        assert_eq!(
+            ids(mmr_select(
+                &scored,
+                |a, b| jaccard(&docs[a], &docs[b]),
+                1.0,
+                2
+            )),
+            vec![0, 1]
+        );
+        assert!(mmr_select(&[], |_, _| 0.0, 0.7, 3).is_empty());
+    }
+
+    #[test]
+    fn test_scope_by_community() {
+        // docs 0, 1 and 3 share community 0; doc 2 is in community 1
+        let scored = vec![(0, 0.9), (2, 0.8), (1, 0.5), (3, 0.0)];
+        let community = |i: usize| Some(if i == 2 { 1 } else { 0 });
+        let ids = |v: Vec<(usize, f32)>| v.into_iter().map(|(i, _)| i).collect::<Vec<_>>();
+
+        assert_eq!(
+            ids(scope_by_community(
+                &scored,
+                community,
+                &SearchStrategy::Local,
+                3
+            )),
+            vec![0, 1, 3]
+        );
+        assert_eq!(
+            ids(scope_by_community(
+                &scored,
+                community,
+                &SearchStrategy::Global,
+                4
+            )),
+            vec![0, 2, 1, 3]
+        );
+        assert_eq!(
+            ids(scope_by_community(
+                &scored,
+                community,
+                &SearchStrategy::Combined,
+                2
+            )),
+            vec![0, 2]
+        );
+        assert_eq!(
+            ids(scope_by_community(
+                &scored,
+                |_| None,
+                &SearchStrategy::Local,
+                2
+            )),
+            vec![0, 2]
+        );
+    }
+}
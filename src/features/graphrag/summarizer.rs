@@ -1,3 +1,30 @@
+use super::index_stats::fingerprint;
+use crate::models::graphrag::DocumentIndex;
+use crate::storage::{CachePolicy, TieredCache};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::rc::Rc;
+
+/// Title of the synthesized overview document stored back into the knowledge base
+pub const COLLECTION_OVERVIEW_TITLE: &str = "Collection overview.md";
+
+// Words ignored when scoring sentences and picking themes
+const STOPWORDS: &[&str] = &[
+    "the", "and", "for", "are", "but", "not", "you", "all", "any", "can", "had", "her", "was",
+    "one", "our", "out", "has", "have", "this", "that", "with", "from", "they", "will", "would",
+    "there", "their", "what", "about", "which", "when", "were", "been", "into", "than", "then",
+    "them", "these", "those", "also", "such", "its", "his", "she", "him", "who", "how", "may",
+    "more", "most", "other", "some", "only", "over", "each", "very", "just", "should", "could",
+];
+
+/// Summary of a single knowledge base document
+#[derive(Clone, Debug, PartialEq)]
+pub struct DocumentSummary {
+    pub doc_id: String,
+    pub title: String,
+    pub summary: String,
+}
+
 /// Simple placeholder summarizer for communities and results.
 pub struct Summarizer;
 
@@ -15,6 +42,80 @@ impl Summarizer {
         s.push('…');
         s
     }
+
+    /// Extractive summary: the `max_sentences` sentences with the highest average
+    /// term frequency, kept in their original order
+    pub fn summarize_extractive(&self, text: &str, max_sentences: usize) -> String {
+        let sentences = split_sentences(text);
+        if sentences.len() <= max_sentences {
+            return sentences.join(" ");
+        }
+        let freq = term_frequencies(text);
+        let mut scored: Vec<(usize, f32)> = sentences
+            .iter()
+            .enumerate()
+            .map(|(i, s)| {
+                let terms = content_terms(s);
+                let score = if terms.is_empty() {
+                    0.0
+                } else {
+                    terms
+                        .iter()
+                        .map(|t| *freq.get(t).unwrap_or(&0) as f32)
+                        .sum::<f32>()
+                        / terms.len() as f32
+                };
+                (i, score)
+            })
+            .collect();
+        scored.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+        let mut keep: Vec<usize> = scored
+            .into_iter()
+            .take(max_sentences)
+            .map(|(i, _)| i)
+            .collect();
+        keep.sort_unstable();
+        keep.into_iter()
+            .map(|i| sentences[i].as_str())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Markdown overview of a collection: shared themes plus one linked section per source
+    pub fn synthesize_overview(&self, summaries: &[DocumentSummary]) -> String {
+        let all_text = summaries
+            .iter()
+            .map(|s| s.summary.as_str())
+            .collect::<Vec<_>>()
+            .join(" ");
+        let mut themes: Vec<(String, usize)> = term_frequencies(&all_text).into_iter().collect();
+        themes.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+        let mut out = format!(
+            "# Collection overview\n\n{} document(s) summarized.\n",
+            summaries.len()
+        );
+        if !themes.is_empty() {
+            let top = themes
+                .iter()
+                .take(8)
+                .map(|(t, _)| t.as_str())
+                .collect::<Vec<_>>()
+                .join(", ");
+            out.push_str(&format!("\nKey themes: {}\n", top));
+        }
+        out.push_str("\n## Sources\n");
+        for s in summaries {
+            out.push_str(&format!(
+                "\n### [{}]({})\n\n",
+                s.title,
+                source_link(&s.title)
+            ));
+            out.push_str(&s.summary);
+            out.push('\n');
+        }
+        out
+    }
 }
 
 impl Default for Summarizer {
@@ -22,3 +123,192 @@ impl Default for Summarizer {
         Self::new()
     }
 }
+
+/// Link target for a knowledge base document (`kb://` + percent-encoded title)
+pub fn source_link(title: &str) -> String {
+    format!("kb://{}", percent_encode(title))
+}
+
+/// Summarize documents with `workers` cooperative tasks sharing one queue. Each task
+/// yields to the event loop after every document so the UI stays responsive.
+/// `on_progress(done, total)` fires per document; `on_done` receives summaries in input order.
+pub fn summarize_in_background<P, D>(
+    docs: Vec<DocumentIndex>,
+    workers: usize,
+    max_sentences: usize,
+    on_progress: P,
+    on_done: D,
+) where
+    P: Fn(usize, usize) + 'static,
+    D: FnOnce(Vec<DocumentSummary>) + 'static,
+{
+    struct Job {
+        queue: VecDeque<(usize, DocumentIndex)>,
+        results: Vec<Option<DocumentSummary>>,
+        done: usize,
+        on_done: Option<Box<dyn FnOnce(Vec<DocumentSummary>)>>,
+    }
+
+    let total = docs.len();
+    if total == 0 {
+        on_done(Vec::new());
+        return;
+    }
+    let job = Rc::new(RefCell::new(Job {
+        queue: docs.into_iter().enumerate().collect(),
+        results: vec![None; total],
+        done: 0,
+        on_done: Some(Box::new(on_done)),
+    }));
+    let on_progress: Rc<dyn Fn(usize, usize)> = Rc::new(on_progress);
+
+    for _ in 0..workers.clamp(1, total) {
+        let job = job.clone();
+        let on_progress = on_progress.clone();
+        wasm_bindgen_futures::spawn_local(async move {
+            let summarizer = Summarizer::new();
+            loop {
+                let next = job.borrow_mut().queue.pop_front();
+                let Some((i, doc)) = next else {
+                    break;
+                };
+                let summary = DocumentSummary {
+                    summary: cached_summary(&summarizer, &doc.content, max_sentences),
+                    doc_id: doc.id,
+                    title: doc.title,
+                };
+                let (done, finished) = {
+                    let mut j = job.borrow_mut();
+                    j.results[i] = Some(summary);
+                    j.done += 1;
+                    let finished = if j.done == total {
+                        let results = j.results.drain(..).flatten().collect::<Vec<_>>();
+                        j.on_done.take().map(|f| (f, results))
+                    } else {
+                        None
+                    };
+                    (j.done, finished)
+                };
+                on_progress(done, total);
+                if let Some((f, results)) = finished {
+                    f(results);
+                }
+                gloo_timers::future::TimeoutFuture::new(0).await;
+            }
+        });
+    }
+}
+
+/// Extractive summaries are kept for a week, keyed by content so edits miss the cache
+const SUMMARY_CACHE_MINUTES: f64 = 7.0 * 24.0 * 60.0;
+const SUMMARY_CACHE_BYTES: usize = 16 * 1024;
+
+fn cached_summary(summarizer: &Summarizer, content: &str, max_sentences: usize) -> String {
+    let key = format!(
+        "doc_summary:{:016x}:{}",
+        fingerprint(content),
+        max_sentences
+    );
+    if let Some(summary) = TieredCache::get::<String>(&key) {
+        return summary;
+    }
+    let summary = summarizer.summarize_extractive(content, max_sentences);
+    let policy = CachePolicy::expiring(SUMMARY_CACHE_MINUTES, SUMMARY_CACHE_BYTES);
+    if let Err(e) = TieredCache::set(&key, &summary, policy) {
+        log::warn!("Failed to cache summary: {}", e);
+    }
+    summary
+}
+
+fn split_sentences(text: &str) -> Vec<String> {
+    let mut out = Vec::new();
+    let mut current = String::new();
+    for c in text.chars() {
+        if c == '\n' {
+            current.push(' ');
+        } else {
+            current.push(c);
+        }
+        if matches!(c, '.' | '!' | '?' | '\n') {
+            let s = current.trim();
+            if !s.is_empty() {
+                out.push(s.to_string());
+            }
+            current.clear();
+        }
+    }
+    let s = current.trim();
+    if !s.is_empty() {
+        out.push(s.to_string());
+    }
+    out
+}
+
+pub(crate) fn content_terms(text: &str) -> Vec<String> {
+    let stop: HashSet<&str> = STOPWORDS.iter().copied().collect();
+    text.split(|c: char| !c.is_alphanumeric())
+        .map(|w| w.to_lowercase())
+        .filter(|w| w.chars().count() > 2 && !stop.contains(w.as_str()))
+        .collect()
+}
+
+fn term_frequencies(text: &str) -> HashMap<String, usize> {
+    let mut freq = HashMap::new();
+    for t in content_terms(text) {
+        *freq.entry(t).or_insert(0) += 1;
+    }
+    freq
+}
+
+fn percent_encode(s: &str) -> String {
+    let mut out = String::new();
+    for b in s.bytes() {
+        if b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.' | b'~') {
+            out.push(b as char);
+        } else {
+            out.push_str(&format!("%{:02X}", b));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summarize_extractive_keeps_order_and_key_sentences() {
+        let text = "Rust powers the engine. The weather was nice. \
+                    The engine compiles Rust to wasm. Lunch was late.";
+        let s = Summarizer::new().summarize_extractive(text, 2);
+        assert_eq!(
+            s,
+            "Rust powers the engine. The engine compiles Rust to wasm."
+        );
+        assert_eq!(
+            Summarizer::new().summarize_extractive("One line only", 3),
+            "One line only"
+        );
+    }
+
+    #[test]
+    fn test_synthesize_overview_links_sources() {
+        let summaries = vec![
+            DocumentSummary {
+                doc_id: "1".into(),
+                title: "notes.md".into(),
+                summary: "Graph retrieval improves recall.".into(),
+            },
+            DocumentSummary {
+                doc_id: "2".into(),
+                title: "report.pdf (p. 2)".into(),
+                summary: "Graph traversal finds related entities.".into(),
+            },
+        ];
+        let md = Summarizer::new().synthesize_overview(&summaries);
+        assert!(md.starts_with("# Collection overview\n\n2 document(s) summarized."));
+        assert!(md.contains("Key themes: graph"));
+        assert!(md.contains("### [notes.md](kb://notes.md)"));
+        assert!(md.contains("### [report.pdf (p. 2)](kb://report.pdf%20%28p.%202%29)"));
+    }
+}
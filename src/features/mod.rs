@@ -1,3 +1,4 @@
+pub mod analytics;
 pub mod crm;
 pub mod graphrag;
 pub mod webllm;
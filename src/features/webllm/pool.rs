@@ -0,0 +1,190 @@
+//! Loaded chat engines: the active one the chat uses, and warm ones kept loaded so
+//! switching to them is instant. Generic over the engine handle so the bookkeeping is
+//! testable without a browser.
+
+use serde::{Deserialize, Serialize};
+
+/// Warm engines kept next to the active one
+pub const MAX_WARM: usize = 1;
+
+/// A loaded engine and the model it runs
+#[derive(Clone, Debug, PartialEq)]
+pub struct PooledEngine<E> {
+    pub model_id: String,
+    pub engine: E,
+    /// GPU memory the model needs, when known
+    pub vram_mb: Option<u32>,
+}
+
+/// Memory report of one loaded engine
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct EngineUsage {
+    pub model_id: String,
+    pub active: bool,
+    pub vram_mb: Option<u32>,
+}
+
+#[derive(Clone, Debug)]
+pub struct EnginePool<E> {
+    active: Option<PooledEngine<E>>,
+    /// Most recently used first
+    warm: Vec<PooledEngine<E>>,
+}
+
+impl<E> Default for EnginePool<E> {
+    fn default() -> Self {
+        Self {
+            active: None,
+            warm: Vec::new(),
+        }
+    }
+}
+
+impl<E: Clone> EnginePool<E> {
+    pub fn active(&self) -> Option<&PooledEngine<E>> {
+        self.active.as_ref()
+    }
+
+    /// Engine of `model_id`, active or warm
+    pub fn get(&self, model_id: &str) -> Option<&PooledEngine<E>> {
+        self.active
+            .iter()
+            .chain(self.warm.iter())
+            .find(|e| e.model_id == model_id)
+    }
+
+    pub fn is_loaded(&self, model_id: &str) -> bool {
+        self.get(model_id).is_some()
+    }
+
+    /// Make `engine` the active one. The previous one stays warm when `keep_previous`
+    /// is set. Returns the engines to unload.
+    pub fn activate(
+        &mut self,
+        engine: PooledEngine<E>,
+        keep_previous: bool,
+    ) -> Vec<PooledEngine<E>> {
+        // A warm copy of the same model is replaced by the new one
+        let mut evicted = self
+            .take_warm(&engine.model_id)
+            .into_iter()
+            .collect::<Vec<_>>();
+        if let Some(previous) = self.active.replace(engine) {
+            if keep_previous {
+                evicted.extend(self.push_warm(previous));
+            } else {
+                evicted.push(previous);
+            }
+        }
+        evicted
+    }
+
+    /// Switch to the warm engine of `model_id`; the active one takes its place, so
+    /// switching back is instant too. None when that model is not warm.
+    pub fn promote(&mut self, model_id: &str) -> Option<Vec<PooledEngine<E>>> {
+        let engine = self.take_warm(model_id)?;
+        Some(self.activate(engine, true))
+    }
+
+    /// Keep `engine` loaded without using it. Returns the engines to unload.
+    pub fn add_warm(&mut self, engine: PooledEngine<E>) -> Vec<PooledEngine<E>> {
+        if self
+            .active
+            .as_ref()
+            .is_some_and(|a| a.model_id == engine.model_id)
+        {
+            return vec![engine];
+        }
+        let mut evicted: Vec<_> = self.take_warm(&engine.model_id).into_iter().collect();
+        evicted.extend(self.push_warm(engine));
+        evicted
+    }
+
+    /// Remove the engine of `model_id`, active or warm, to unload it
+    pub fn remove(&mut self, model_id: &str) -> Option<PooledEngine<E>> {
+        if self.active.as_ref().is_some_and(|a| a.model_id == model_id) {
+            return self.active.take();
+        }
+        self.take_warm(model_id)
+    }
+
+    pub fn usage(&self) -> Vec<EngineUsage> {
+        let report = |e: &PooledEngine<E>, active| EngineUsage {
+            model_id: e.model_id.clone(),
+            active,
+            vram_mb: e.vram_mb,
+        };
+        self.active
+            .iter()
+            .map(|a| report(a, true))
+            .chain(self.warm.iter().map(|w| report(w, false)))
+            .collect()
+    }
+
+    fn take_warm(&mut self, model_id: &str) -> Option<PooledEngine<E>> {
+        let at = self.warm.iter().position(|w| w.model_id == model_id)?;
+        Some(self.warm.remove(at))
+    }
+
+    fn push_warm(&mut self, engine: PooledEngine<E>) -> Vec<PooledEngine<E>> {
+        self.warm.insert(0, engine);
+        self.warm.split_off(MAX_WARM.min(self.warm.len()))
+    }
+}
+
+/// Total memory of `usage`, counting the engines of unknown size apart
+pub fn total_vram_mb(usage: &[EngineUsage]) -> (u32, usize) {
+    let known: u32 = usage.iter().filter_map(|u| u.vram_mb).sum();
+    let unknown = usage.iter().filter(|u| u.vram_mb.is_none()).count();
+    (known, unknown)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn engine(id: &str, handle: u32) -> PooledEngine<u32> {
+        PooledEngine {
+            model_id: id.to_string(),
+            engine: handle,
+            vram_mb: Some(1000),
+        }
+    }
+
+    fn ids(engines: Vec<PooledEngine<u32>>) -> Vec<String> {
+        engines.into_iter().map(|e| e.model_id).collect()
+    }
+
+    #[test]
+    fn test_switching_keeps_one_engine_warm() {
+        let mut pool = EnginePool::default();
+        assert!(pool.activate(engine("a", 1), true).is_empty());
+
+        // The previous engine stays warm, so switching back needs no load
+        assert!(pool.activate(engine("b", 2), true).is_empty());
+        assert!(pool.is_loaded("a"));
+        assert!(pool.promote("a").unwrap().is_empty());
+        assert_eq!(pool.active().unwrap().engine, 1);
+        assert!(pool.promote("c").is_none());
+        assert_eq!(pool.get("b").map(|e| e.engine), Some(2));
+
+        // Only one warm engine: the oldest goes
+        assert_eq!(ids(pool.activate(engine("c", 3), true)), ["b"]);
+        assert_eq!(
+            pool.usage()
+                .iter()
+                .map(|u| (u.model_id.as_str(), u.active))
+                .collect::<Vec<_>>(),
+            [("c", true), ("a", false)]
+        );
+        assert_eq!(total_vram_mb(&pool.usage()), (2000, 0));
+
+        // Without keeping the previous engine, it is unloaded at once
+        assert_eq!(ids(pool.activate(engine("d", 4), false)), ["c"]);
+        // Pre-warming the active model loads nothing more
+        assert_eq!(ids(pool.add_warm(engine("d", 5))), ["d"]);
+        assert_eq!(ids(pool.add_warm(engine("e", 6))), ["a"]);
+        assert_eq!(pool.remove("e").map(|e| e.engine), Some(6));
+        assert_eq!(pool.usage().len(), 1);
+    }
+}
@@ -7,9 +7,70 @@ use wasm_bindgen_futures::spawn_local;
 
 use crate::models::webllm::{LLMModel, ModelStatus};
 use crate::state::webllm_state_simple::WebLLMStateContext;
+use crate::storage::TieredCache;
+use crate::utils::notifications::{LongTask, NotificationUtils};
+use crate::webllm_binding;
 
-/// Initialize a WebLLM model with progress updates wired into WebLLMState
+/// Cache key of whether the previous chat engine stays loaded after a switch
+pub const KEEP_PREVIOUS_KEY: &str = "webllm_keep_previous_engine";
+/// Cache key of the model pre-warmed once the chat model is ready
+pub const PREWARM_MODEL_KEY: &str = "webllm_prewarm_model_id";
+
+/// Whether switching models keeps the previous engine warm; off by default as it
+/// holds two models in GPU memory
+pub fn keep_previous_engine() -> bool {
+    TieredCache::get::<bool>(KEEP_PREVIOUS_KEY).unwrap_or(false)
+}
+
+/// Whether the device has room for `previous` and `next` at once, by the same rule as
+/// the capability check; true when either size or the memory is unknown
+fn fits_together(ctx: &WebLLMStateContext, previous: &LLMModel, next: &LLMModel) -> bool {
+    let budget_mb = ctx.get_capabilities().and_then(|d| d.memory_budget_mb());
+    match (budget_mb, previous.size_mb, next.size_mb) {
+        (Some(budget_mb), Some(a), Some(b)) => (a + b).saturating_mul(2) <= budget_mb,
+        _ => true,
+    }
+}
+
+/// Initialize a WebLLM model with progress updates wired into WebLLMState. The chat
+/// keeps using the previous engine until the new one is ready; a warm engine of the
+/// model takes over at once.
 pub fn init_model(ctx: WebLLMStateContext, model: LLMModel) {
+    // A model the device cannot run fails here, before its download starts
+    if let Some(reason) = ctx
+        .get_capabilities()
+        .and_then(|device| device.check(&model).err())
+    {
+        let message = format!("{} cannot run on this device: {}", model.name, reason);
+        log::warn!("{}", message);
+        ctx.set_current_model(Some(model));
+        ctx.set_model_status(ModelStatus::Error { message });
+        return;
+    }
+
+    if webllm_binding::switch_to_warm(&model.id) {
+        info!("Switched to the warm engine of {}", model.id);
+        ctx.set_current_model(Some(model));
+        ctx.set_model_status(ModelStatus::Ready);
+        ctx.set_initialization_progress(1.0);
+        ctx.refresh_engines();
+        return;
+    }
+
+    // Model the chat keeps using while the new one loads
+    let previous = ctx
+        .get_current_model()
+        .filter(|m| m.id != model.id && webllm_binding::is_model_loaded(&m.id));
+    let previous = match previous {
+        Some(p) if !fits_together(&ctx, &p, &model) => {
+            info!("Unloading {} first: no room for two models", p.id);
+            webllm_binding::unload_model(&p.id);
+            ctx.refresh_engines();
+            None
+        }
+        p => p,
+    };
+
     // Set initial state
     ctx.set_current_model(Some(model.clone()));
     ctx.set_model_status(ModelStatus::Loading { progress: 0.0 });
@@ -21,7 +82,15 @@ pub fn init_model(ctx: WebLLMStateContext, model: LLMModel) {
         info!("Starting WebLLM init for {}", model_id);
 
         let state_for_progress = ctx.clone();
+        let progress_id = model_id.clone();
         let progress_cb = move |text: String, p: f64| {
+            // Another switch started meanwhile and reports its own progress
+            if state_for_progress
+                .get_current_model()
+                .is_some_and(|m| m.id != progress_id)
+            {
+                return;
+            }
             // Map the text to statuses if desired; we just store progress
             let progress = p.clamp(0.0, 1.0) as f32;
             state_for_progress.set_model_status(ModelStatus::Loading { progress });
@@ -31,25 +100,137 @@ pub fn init_model(ctx: WebLLMStateContext, model: LLMModel) {
 
         // Attempt to call into JS binding
         let res: Result<JsValue, JsValue> =
-            crate::webllm_binding::init_webllm_with_progress(&model_id, progress_cb).await;
+            webllm_binding::init_webllm_with_progress(&model_id, progress_cb).await;
+        let superseded = ctx.get_current_model().is_some_and(|m| m.id != model_id);
 
         match res {
-            Ok(_engine) => {
+            Ok(engine) if superseded => {
+                // The user switched again while this loaded: keep it to switch back to
+                webllm_binding::prewarm_engine(&model_id, engine);
+                ctx.refresh_engines();
+            }
+            Ok(engine) => {
+                webllm_binding::activate_engine(&model_id, engine, keep_previous_engine());
                 ctx.set_model_status(ModelStatus::Ready);
                 ctx.set_initialization_progress(1.0);
+                ctx.refresh_engines();
                 info!("WebLLM init finished for {}", model_id);
+                NotificationUtils::task_finished(
+                    LongTask::ModelInit,
+                    Ok(&format!("{} is ready", model_id)),
+                );
+            }
+            Err(e) if superseded => {
+                error!("WebLLM init failed for {}: {:?}", model_id, e);
             }
             Err(e) => {
-                error!(
-                    "WebLLM init failed for {}: {:?}. Falling back to simulated init.",
-                    model_id, e
+                NotificationUtils::task_finished(
+                    LongTask::ModelInit,
+                    Err(&format!("{} failed to load", model_id)),
                 );
-                simulate_progress(ctx.clone());
+                match previous.filter(|p| webllm_binding::is_model_loaded(&p.id)) {
+                    // The chat never stopped using the previous model
+                    Some(previous) => {
+                        error!(
+                            "WebLLM init failed for {}: {:?}. Staying on {}.",
+                            model_id, e, previous.id
+                        );
+                        ctx.set_current_model(Some(previous));
+                        ctx.set_model_status(ModelStatus::Ready);
+                        ctx.set_initialization_progress(1.0);
+                    }
+                    None => {
+                        error!(
+                            "WebLLM init failed for {}: {:?}. Falling back to simulated init.",
+                            model_id, e
+                        );
+                        simulate_progress(ctx.clone());
+                    }
+                }
             }
         }
     });
 }
 
+/// Load `model` in the background so switching to it later is instant. The chat
+/// model is left alone; only one model is kept warm.
+pub fn prewarm_model(ctx: WebLLMStateContext, model: LLMModel) {
+    if webllm_binding::is_model_loaded(&model.id) || ctx.get_prewarming().is_some() {
+        return;
+    }
+    if let Some(reason) = ctx
+        .get_capabilities()
+        .and_then(|device| device.check(&model).err())
+    {
+        log::warn!("Not pre-warming {}: {}", model.id, reason);
+        return;
+    }
+    if let Some(current) = ctx.get_current_model() {
+        if !fits_together(&ctx, &current, &model) {
+            log::warn!(
+                "Not pre-warming {}: no room next to {}",
+                model.id,
+                current.id
+            );
+            return;
+        }
+    }
+    ctx.set_prewarming(Some(model.id.clone()));
+    spawn_local(async move {
+        info!("Pre-warming {}", model.id);
+        let progress_id = model.id.clone();
+        let loaded = webllm_binding::init_webllm_with_progress(&model.id, move |text, p| {
+            log::debug!("pre-warm {}: {} => {:.0}%", progress_id, text, p * 100.0);
+        })
+        .await;
+        match loaded {
+            Ok(engine) => webllm_binding::prewarm_engine(&model.id, engine),
+            Err(e) => error!("Pre-warming {} failed: {:?}", model.id, e),
+        }
+        ctx.set_prewarming(None);
+        ctx.refresh_engines();
+    });
+}
+
+/// Load `model` next to the chat model for a one-off use, e.g. comparing replies,
+/// reporting progress to `on_progress`. Refused, like a pre-warm, when the device
+/// cannot run it or has no room for it next to the chat model.
+pub async fn load_alongside<F>(
+    ctx: &WebLLMStateContext,
+    model: &LLMModel,
+    on_progress: F,
+) -> Result<(), String>
+where
+    F: Fn(String, f64) + 'static,
+{
+    if webllm_binding::is_model_loaded(&model.id) {
+        return Ok(());
+    }
+    if let Some(reason) = ctx
+        .get_capabilities()
+        .and_then(|device| device.check(model).err())
+    {
+        return Err(format!(
+            "{} cannot run on this device: {}",
+            model.name, reason
+        ));
+    }
+    if let Some(current) = ctx.get_current_model() {
+        if !fits_together(ctx, &current, model) {
+            return Err(format!(
+                "No room for {} next to {}",
+                model.name, current.name
+            ));
+        }
+    }
+    let engine = webllm_binding::init_webllm_with_progress(&model.id, on_progress)
+        .await
+        .map_err(|e| format!("{:?}", e))?;
+    webllm_binding::prewarm_engine(&model.id, engine);
+    ctx.refresh_engines();
+    Ok(())
+}
+
 /// Simulate initialization progress for environments without WebLLM JS available
 pub fn simulate_progress(ctx: WebLLMStateContext) {
     ctx.set_model_status(ModelStatus::Loading { progress: 0.0 });
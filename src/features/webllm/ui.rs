@@ -1,14 +1,32 @@
-use crate::features::webllm::service::{init_model, simulate_progress};
+use crate::features::webllm::pool::total_vram_mb;
+use crate::features::webllm::service::{
+    init_model, keep_previous_engine, prewarm_model, simulate_progress, KEEP_PREVIOUS_KEY,
+    PREWARM_MODEL_KEY,
+};
 use crate::models::webllm::{LLMModel, ModelCapability, ModelStatus};
+use crate::models::AppConfig;
 use crate::state::webllm_state_simple::use_webllm_state;
-use crate::utils::storage::StorageUtils;
+use crate::storage::model_metadata::{self, CustomModelEntry};
+use crate::storage::{CachePolicy, TieredCache};
+use crate::utils::capabilities::GpuSupport;
+use crate::utils::webllm::WebLLMUtils;
 use js_sys::{Array, Object, Reflect};
 use leptos::prelude::*;
-use serde::{Deserialize, Serialize};
-use serde_json;
+use leptos::task::spawn_local;
 use wasm_bindgen::{JsCast, JsValue};
 use web_sys::window;
 
+/// Cache key and lifetime of the prebuilt WebLLM model list
+const MODEL_LIST_KEY: &str = "webllm_model_list";
+const MODEL_LIST_POLICY: CachePolicy = CachePolicy {
+    ttl_ms: Some(24.0 * 60.0 * 60_000.0),
+    max_bytes: 256 * 1024,
+    memory_only: false,
+};
+
+/// Cache key of the last-used chat model id
+pub const LAST_MODEL_KEY: &str = "webllm_last_model_id";
+
 #[component]
 pub fn WebLLMInitPanel() -> impl IntoView {
     let ctx = use_webllm_state();
@@ -68,7 +86,13 @@ pub fn WebLLMInitPanel() -> impl IntoView {
         let ctx = ctx.clone();
         move |_| {
             if ctx.get_available_models().is_empty() {
-                if let Some(models) = fetch_prebuilt_models_from_js() {
+                // The JS list may not be loaded yet; the last one seen is kept for a day
+                let models = fetch_prebuilt_models_from_js()
+                    .inspect(|models| {
+                        let _ = TieredCache::set(MODEL_LIST_KEY, models, MODEL_LIST_POLICY);
+                    })
+                    .or_else(|| TieredCache::get::<Vec<LLMModel>>(MODEL_LIST_KEY));
+                if let Some(models) = models {
                     ctx.set_available_models(models);
                 } else {
                     let models = vec![
@@ -113,13 +137,10 @@ pub fn WebLLMInitPanel() -> impl IntoView {
 
     let (selected, set_selected) = signal(String::new());
 
-    // Persist key for last-used model id
-    const LAST_MODEL_KEY: &str = "webllm_last_model_id";
-
     // Load last-used model id on mount
     Effect::new({
         move |_| {
-            if let Ok(Some(id)) = StorageUtils::retrieve_local::<String>(LAST_MODEL_KEY) {
+            if let Some(id) = TieredCache::get::<String>(LAST_MODEL_KEY) {
                 if !id.trim().is_empty() {
                     set_selected.set(id);
                 }
@@ -139,17 +160,7 @@ pub fn WebLLMInitPanel() -> impl IntoView {
         }
     });
 
-    // Advanced: custom models persistence
-    #[derive(Clone, Debug, Serialize, Deserialize)]
-    struct CustomModelEntry {
-        model: String,
-        model_lib: String,
-        model_id: String,
-        name: Option<String>,
-        family: Option<String>,
-        size_mb: Option<u32>,
-    }
-
+    // Advanced: custom models, kept with the other model metadata
     let (adv_open, set_adv_open) = signal(false);
     let (cm_model, set_cm_model) = signal(String::new());
     let (cm_model_lib, set_cm_model_lib) = signal(String::new());
@@ -162,43 +173,27 @@ pub fn WebLLMInitPanel() -> impl IntoView {
     Effect::new({
         let ctx = ctx.clone();
         move |_| {
-            if let Some(win) = window() {
-                if let Ok(Some(storage)) = win.local_storage() {
-                    if let Ok(Some(raw)) = storage.get_item("webllm_custom_models") {
-                        if !raw.is_empty() {
-                            if let Ok(entries) = serde_json::from_str::<Vec<CustomModelEntry>>(&raw)
-                            {
-                                if !entries.is_empty() {
-                                    let mut avail = ctx.get_available_models();
-                                    for e in entries {
-                                        let name =
-                                            e.name.clone().unwrap_or_else(|| e.model_id.clone());
-                                        let family = e
-                                            .family
-                                            .clone()
-                                            .unwrap_or_else(|| "custom".to_string());
-                                        let mut m = LLMModel::new(
-                                            e.model_id.clone(),
-                                            name,
-                                            "WebLLM".to_string(),
-                                            family,
-                                        )
-                                        .with_capabilities(vec![ModelCapability::TextGeneration]);
-                                        if let Some(sz) = e.size_mb {
-                                            m = m.with_size(sz);
-                                        }
-                                        // Avoid duplicates by id
-                                        if !avail.iter().any(|am| am.id == m.id) {
-                                            avail.push(m);
-                                        }
-                                    }
-                                    ctx.set_available_models(avail);
-                                }
-                            }
-                        }
+            let ctx = ctx.clone();
+            spawn_local(async move {
+                let entries = match model_metadata::custom_models().await {
+                    Ok(entries) => entries,
+                    Err(e) => {
+                        log::warn!("Custom models not loaded: {}", e);
+                        return;
                     }
+                };
+                if entries.is_empty() {
+                    return;
                 }
-            }
+                let mut avail = ctx.get_available_models();
+                for m in entries.iter().map(CustomModelEntry::to_model) {
+                    // Avoid duplicates by id
+                    if !avail.iter().any(|am| am.id == m.id) {
+                        avail.push(m);
+                    }
+                }
+                ctx.set_available_models(avail);
+            });
         }
     });
 
@@ -248,12 +243,94 @@ pub fn WebLLMInitPanel() -> impl IntoView {
         let ctx = ctx.clone();
         move || ctx.get_available_models()
     });
+    let device = Signal::derive({
+        let ctx = ctx.clone();
+        move || ctx.get_capabilities()
+    });
+    // Models this device can run; all of them until the probe is done
+    let runnable = Signal::derive(move || match device.get() {
+        Some(device) => device.compatible(&available.get()),
+        None => available.get(),
+    });
+    // Remembered model that cannot run here, with the reason, replaced on startup
+    let replaced = RwSignal::new(None::<(LLMModel, String)>);
     // Store commonly used values to prevent moving them into event handlers
     let ctx_sv = StoredValue::new(ctx.clone());
-    let available_sv = StoredValue::new(available);
+    let available_sv = StoredValue::new(runnable);
 
-    // One-time auto-init guard
-    let (auto_init_done, set_auto_init_done) = signal(false);
+    // Why models are missing from the list
+    let device_notice = Signal::derive(move || -> Option<String> {
+        match device.get()?.gpu {
+            GpuSupport::Unsupported => Some(
+                "This browser has no WebGPU, so models cannot run here. Recent Chrome and Edge support it."
+                    .to_string(),
+            ),
+            GpuSupport::NoAdapter => Some(
+                "WebGPU is blocked for this GPU or its driver, so models cannot run here."
+                    .to_string(),
+            ),
+            GpuSupport::Available { .. } => {
+                let hidden = available.get().len() - runnable.get().len();
+                (hidden > 0).then(|| format!("{} models hidden: this device cannot run them.", hidden))
+            }
+        }
+    });
+    // Loaded engines, and the one the chat keeps using while another loads
+    let engines = Signal::derive({
+        let ctx = ctx.clone();
+        move || ctx.get_engines()
+    });
+    let prewarming = Signal::derive({
+        let ctx = ctx.clone();
+        move || ctx.get_prewarming()
+    });
+    let still_chatting_with = Signal::derive({
+        let ctx = ctx.clone();
+        move || {
+            if !is_initializing.get() {
+                return None;
+            }
+            let loading = ctx.get_current_model().map(|m| m.id);
+            engines
+                .get()
+                .into_iter()
+                .find(|e| e.active && Some(&e.model_id) != loading.as_ref())
+                .map(|e| e.model_id)
+        }
+    });
+    let keep_previous = RwSignal::new(keep_previous_engine());
+    let prewarm_id =
+        RwSignal::new(TieredCache::get::<String>(PREWARM_MODEL_KEY).unwrap_or_default());
+    // The model of `id`, also when it is no longer listed
+    let model_by_id = move |id: &str| {
+        available
+            .get_untracked()
+            .into_iter()
+            .find(|m| m.id == id)
+            .unwrap_or_else(|| {
+                LLMModel::new(
+                    id.to_string(),
+                    id.to_string(),
+                    "WebLLM".to_string(),
+                    "webllm".to_string(),
+                )
+            })
+    };
+
+    let load = move |model: LLMModel| {
+        replaced.set(None);
+        set_selected.set(model.id.clone());
+        let _ = TieredCache::set(LAST_MODEL_KEY, &model.id, CachePolicy::PREFERENCE);
+        init_model(ctx_sv.get_value().clone(), model);
+    };
+
+    // One-time auto-init guard, already spent when startup model loading is turned off
+    let (auto_init_done, set_auto_init_done) = signal(
+        !AppConfig::load()
+            .unwrap_or_default()
+            .startup
+            .auto_init_model,
+    );
 
     // Auto-initialize the selected (or first) model once models are available and not initialized yet
     Effect::new({
@@ -262,21 +339,30 @@ pub fn WebLLMInitPanel() -> impl IntoView {
             if auto_init_done.get() {
                 return;
             }
-            let models = available_sv.get_value().get();
+            // Wait for the probe rather than start a model that cannot run
+            let Some(device) = device.get() else {
+                return;
+            };
+            let models = available.get();
             if models.is_empty() {
                 return;
             }
             if matches!(ctx.get_model_status(), ModelStatus::NotInitialized) {
-                // Prefer explicitly selected; else first available
-                let chosen = models
-                    .iter()
-                    .find(|m| m.id == selected.get())
-                    .cloned()
-                    .or_else(|| models.first().cloned());
+                set_auto_init_done.set(true);
+                // Prefer explicitly selected, or a fallback when it cannot run; else first runnable
+                let chosen = match models.iter().find(|m| m.id == selected.get()) {
+                    Some(wanted) => match device.check(wanted) {
+                        Ok(()) => Some(wanted.clone()),
+                        Err(reason) => {
+                            replaced.set(Some((wanted.clone(), reason.to_string())));
+                            device.fallbacks(wanted, &models).into_iter().next()
+                        }
+                    },
+                    None => runnable.get().into_iter().next(),
+                };
                 if let Some(m) = chosen {
-                    set_auto_init_done.set(true);
                     // Persist auto-chosen model id
-                    let _ = StorageUtils::store_local(LAST_MODEL_KEY, &m.id);
+                    let _ = TieredCache::set(LAST_MODEL_KEY, &m.id, CachePolicy::PREFERENCE);
                     // Reflect in the UI select as well
                     set_selected.set(m.id.clone());
                     init_model(ctx.clone(), m);
@@ -285,6 +371,23 @@ pub fn WebLLMInitPanel() -> impl IntoView {
         }
     });
 
+    // Pre-warm the chosen secondary model once the chat model is ready
+    let prewarm_started = RwSignal::new(false);
+    Effect::new({
+        let ctx = ctx_sv.get_value().clone();
+        move |_| {
+            if prewarm_started.get() || !matches!(status.get(), ModelStatus::Ready) {
+                return;
+            }
+            let id = prewarm_id.get_untracked();
+            if id.is_empty() || ctx.get_current_model().is_some_and(|m| m.id == id) {
+                return;
+            }
+            prewarm_started.set(true);
+            prewarm_model(ctx.clone(), model_by_id(&id));
+        }
+    });
+
     // Actions handled inline to avoid moving closures that make the view FnOnce
 
     view! {
@@ -296,8 +399,9 @@ pub fn WebLLMInitPanel() -> impl IntoView {
                         prop:value=move || selected.get()
                         on:change=move |ev| {
                             let v = event_target_value(&ev);
+                            replaced.set(None);
                             set_selected.set(v.clone());
-                            let _ = StorageUtils::store_local(LAST_MODEL_KEY, &v);
+                            let _ = TieredCache::set(LAST_MODEL_KEY, &v, CachePolicy::PREFERENCE);
                             // Immediately initialize the chosen model so StatusBar reflects it
                             let available_sig = available_sv.get_value();
                             if let Some(model) = available_sig
@@ -311,7 +415,7 @@ pub fn WebLLMInitPanel() -> impl IntoView {
                     >
                         <option value="">{"Select model"}</option>
                         {move || {
-                            available
+                            runnable
                                 .get()
                                 .into_iter()
                                 .map(|m| view! { <option value=m.id.clone()>{m.name}</option> })
@@ -408,9 +512,175 @@ pub fn WebLLMInitPanel() -> impl IntoView {
                 <span class=move || status_badge_class.get()>{move || status_text.get()}</span>
             </div>
 
+            {move || {
+                still_chatting_with
+                    .get()
+                    .map(|id| {
+                        view! {
+                            <p class="mt-2 text-xs text-base-content/70">
+                                {format!("The chat keeps using {} until the new model is ready.", id)}
+                            </p>
+                        }
+                    })
+            }}
+            {move || {
+                device_notice
+                    .get()
+                    .map(|notice| view! { <p class="mt-2 text-xs text-warning">{notice}</p> })
+            }}
+            {move || {
+                replaced
+                    .get()
+                    .and_then(|(wanted, reason)| {
+                        let options = device
+                            .get()
+                            .map(|d| d.fallbacks(&wanted, &available.get()))
+                            .unwrap_or_default();
+                        // Without WebGPU nothing can run; the notice above says so
+                        if options.is_empty() {
+                            return None;
+                        }
+                        Some(view! {
+                            <div class="mt-2 text-xs space-y-1">
+                                <p>{format!("{} cannot run on this device: {}. Models that can:", wanted.name, reason)}</p>
+                                <div class="flex flex-wrap gap-1">
+                                    {options
+                                        .into_iter()
+                                        .map(|m| {
+                                            let label = m.name.clone();
+                                            view! {
+                                                <button class="btn btn-outline btn-xs" on:click=move |_| load(m.clone())>
+                                                    {label}
+                                                </button>
+                                            }
+                                        })
+                                        .collect_view()}
+                                </div>
+                            </div>
+                        })
+                    })
+            }}
+
             <div class="mt-4">
                 <Show when=move || adv_open.get()>
                     <div class="mt-3 p-3 rounded-lg border border-base-300 bg-base-200/40 space-y-2 max-w-full min-w-0 overflow-x-clip">
+                        <div class="space-y-2 text-xs">
+                            <label class="flex items-center gap-2 cursor-pointer">
+                                <input
+                                    type="checkbox"
+                                    class="checkbox checkbox-xs"
+                                    prop:checked=move || keep_previous.get()
+                                    on:change=move |ev| {
+                                        let keep = event_target_checked(&ev);
+                                        keep_previous.set(keep);
+                                        let _ = TieredCache::set(KEEP_PREVIOUS_KEY, &keep, CachePolicy::PREFERENCE);
+                                    }
+                                />
+                                <span>"Keep the previous model loaded after switching (instant switch back, more GPU memory)"</span>
+                            </label>
+                            <div class="flex items-center gap-2 min-w-0">
+                                <span class="shrink-0">"Pre-warm"</span>
+                                <select
+                                    class="select select-bordered select-xs rounded-lg flex-1 min-w-0"
+                                    prop:value=move || prewarm_id.get()
+                                    on:change=move |ev| {
+                                        let id = event_target_value(&ev);
+                                        prewarm_id.set(id.clone());
+                                        let _ = TieredCache::set(PREWARM_MODEL_KEY, &id, CachePolicy::PREFERENCE);
+                                        if !id.is_empty() {
+                                            prewarm_model(ctx_sv.get_value().clone(), model_by_id(&id));
+                                        }
+                                    }
+                                >
+                                    <option value="">{"No second model"}</option>
+                                    {move || {
+                                        runnable
+                                            .get()
+                                            .into_iter()
+                                            .map(|m| view! { <option value=m.id.clone()>{m.name}</option> })
+                                            .collect_view()
+                                    }}
+                                </select>
+                            </div>
+                            {move || {
+                                prewarming
+                                    .get()
+                                    .map(|id| view! { <p class="text-base-content/70">{format!("Pre-warming {}…", id)}</p> })
+                            }}
+                            <Show when=move || !engines.with(|e| e.is_empty())>
+                                <div class="space-y-1">
+                                    <div class="flex items-center justify-between">
+                                        <span class="font-medium">"Loaded models"</span>
+                                        <span class="text-base-content/70">
+                                            {move || {
+                                                let (known, unknown) = total_vram_mb(&engines.get());
+                                                let total = WebLLMUtils::format_model_size(Some(known));
+                                                if unknown > 0 {
+                                                    format!("{} + {} of unknown size", total, unknown)
+                                                } else {
+                                                    total
+                                                }
+                                            }}
+                                        </span>
+                                    </div>
+                                    <ul class="divide-y divide-base-300">
+                                        {move || {
+                                            engines
+                                                .get()
+                                                .into_iter()
+                                                .map(|engine| {
+                                                    let switch_id = engine.model_id.clone();
+                                                    let unload_id = engine.model_id.clone();
+                                                    let active = engine.active;
+                                                    let (badge_class, role) = if active {
+                                                        ("badge badge-success badge-xs", "Chat")
+                                                    } else {
+                                                        ("badge badge-ghost badge-xs", "Warm")
+                                                    };
+                                                    view! {
+                                                        <li class="flex items-center justify-between gap-2 py-1 min-w-0">
+                                                            <span class="truncate" title=engine.model_id.clone()>
+                                                                {engine.model_id.clone()}
+                                                            </span>
+                                                            <div class="flex items-center gap-1 shrink-0">
+                                                                <span class=badge_class>{role}</span>
+                                                                <span class="font-mono">{WebLLMUtils::format_model_size(engine.vram_mb)}</span>
+                                                                <Show when=move || !active>
+                                                                    <button
+                                                                        class="btn btn-ghost btn-xs"
+                                                                        title="Switch the chat to this model"
+                                                                        on:click={
+                                                                            let id = switch_id.clone();
+                                                                            move |_| load(model_by_id(&id))
+                                                                        }
+                                                                    >
+                                                                        "Switch"
+                                                                    </button>
+                                                                </Show>
+                                                                <button
+                                                                    class="btn btn-ghost btn-xs"
+                                                                    title="Unload to free GPU memory"
+                                                                    on:click=move |_| {
+                                                                        crate::webllm_binding::unload_model(&unload_id);
+                                                                        let ctx = ctx_sv.get_value();
+                                                                        if ctx.get_current_model().is_some_and(|m| m.id == unload_id) {
+                                                                            ctx.set_model_status(ModelStatus::NotInitialized);
+                                                                        }
+                                                                        ctx.refresh_engines();
+                                                                    }
+                                                                >
+                                                                    "Unload"
+                                                                </button>
+                                                            </div>
+                                                        </li>
+                                                    }
+                                                })
+                                                .collect_view()
+                                        }}
+                                    </ul>
+                                </div>
+                            </Show>
+                        </div>
                         <div class="grid grid-cols-1 md:grid-cols-2 gap-2 max-w-full min-w-0">
                             <input
                                 class="input input-bordered input-sm rounded-lg"
@@ -468,46 +738,25 @@ pub fn WebLLMInitPanel() -> impl IntoView {
                                         let f = cm_family.get();
                                         if f.trim().is_empty() { "custom".to_string() } else { f }
                                     };
-                                    let mut m = LLMModel::new(
-                                            id.clone(),
-                                            name.clone(),
-                                            "WebLLM".to_string(),
-                                            family.clone(),
-                                        )
-                                        .with_capabilities(vec![ModelCapability::TextGeneration]);
-                                    if let Ok(sz) = cm_size.get().trim().parse::<u32>() {
-                                        m = m.with_size(sz);
-                                    }
-                                    let mut avail = available_sv.get_value().get();
-                                    if !avail.iter().any(|am| am.id == id) {
-                                        avail.push(m);
-                                    }
-                                    let ctx2 = ctx_sv.get_value().clone();
-                                    ctx2.set_available_models(avail.clone());
                                     let entry = CustomModelEntry {
                                         model,
                                         model_lib,
-                                        model_id: id,
+                                        model_id: id.clone(),
                                         name: Some(name),
                                         family: Some(family),
                                         size_mb: cm_size.get().trim().parse().ok(),
                                     };
-                                    if let Some(win) = window() {
-                                        if let Ok(Some(storage)) = win.local_storage() {
-                                            let mut list: Vec<CustomModelEntry> = storage
-                                                .get_item("webllm_custom_models")
-                                                .ok()
-                                                .flatten()
-                                                .and_then(|raw| serde_json::from_str(&raw).ok())
-                                                .unwrap_or_default();
-                                            if !list.iter().any(|e| e.model_id == entry.model_id) {
-                                                list.push(entry);
-                                            }
-                                            if let Ok(raw) = serde_json::to_string(&list) {
-                                                let _ = storage.set_item("webllm_custom_models", &raw);
-                                            }
-                                        }
+                                    let mut avail = available_sv.get_value().get();
+                                    if !avail.iter().any(|am| am.id == id) {
+                                        avail.push(entry.to_model());
                                     }
+                                    let ctx2 = ctx_sv.get_value().clone();
+                                    ctx2.set_available_models(avail.clone());
+                                    spawn_local(async move {
+                                        if let Err(e) = model_metadata::add_custom_model(entry).await {
+                                            log::error!("Custom model {} not saved: {}", id, e);
+                                        }
+                                    });
                                 }
                             >
                                 {"Add custom model"}
@@ -1,8 +1,16 @@
+use crate::features::graphrag::chunking::ChunkingStrategy;
+use crate::features::graphrag::config_bundle::{self, ConfigBundle, ImportedConfig};
+use crate::features::graphrag::profiles::{self, ConfigProfile};
+use crate::models::app::AppResult;
 use crate::models::graphrag::SearchStrategy;
+use crate::utils::storage::StorageUtils;
 use leptos::prelude::*;
 use serde::{Deserialize, Serialize};
 use std::sync::OnceLock;
 
+/// localStorage key of the GraphRAG settings
+pub const GRAPHRAG_CONFIG_KEY: &str = "graphrag_config_v1";
+
 // Core GraphRAG Configuration with Feature Toggles
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 #[serde(default)]
@@ -17,8 +25,43 @@ pub struct GraphRAGConfig {
     pub hybrid_enabled: bool,
     pub fusion_text_weight: f32,
     pub fusion_graph_weight: f32,
+    // Dense embedding retrieval fused with the lexical score
+    pub embeddings_enabled: bool,
+    pub embedding_model: String,
+    pub semantic_weight: f32,
+    // Cross-encoder used when reranking is enabled
+    pub reranker_model: String,
+    // Ask the loaded chat model for entities and typed relations at index time
+    pub llm_extraction_enabled: bool,
+    // Merge entity nodes naming the same entity after every index build
+    pub entity_resolution_enabled: bool,
+    // Ask the loaded chat model to split multi-part questions into sub-queries that are
+    // retrieved separately; one extra model call per complex question
+    pub query_decomposition_enabled: bool,
+    // With synthesis on, have the loaded chat model summarize the top chunks with
+    // citation markers and inject that instead of the quoted snippets
+    pub abstractive_synthesis_enabled: bool,
+    // Format of the system message that injects retrieved knowledge, with placeholders
+    // from `features::graphrag::preamble`
+    pub preamble_template: String,
+    // Minimum relevance (0..1) a document needs before it is injected into prompts
+    pub min_relevance: f32,
+    // Re-select the top passages by maximal marginal relevance so near-duplicates are
+    // not injected together; lambda 1.0 ranks by relevance only, 0.0 by novelty only
+    pub mmr_enabled: bool,
+    pub mmr_lambda: f32,
+    // Keep documents past their expiry date in search results
+    pub include_expired: bool,
     // Search strategy for chat-integrated retrieval
     pub search_strategy: SearchStrategy,
+    // How prose files are cut into indexed passages (sizes in characters)
+    pub chunking_strategy: ChunkingStrategy,
+    pub chunk_size: usize,
+    pub chunk_overlap: usize,
+
+    // Keep only passage sketches in memory and stream full texts from disk; the
+    // passage cache is capped at max_memory_mb
+    pub large_kb_mode: bool,
 
     // Performance settings
     pub max_query_time_ms: u32,
@@ -51,12 +94,31 @@ impl Default for GraphRAGConfig {
             hyde_enabled: true,
             community_detection_enabled: true,
             pagerank_enabled: true,
-            reranking_enabled: false, // Computationally expensive
+            reranking_enabled: false, // Downloads a cross-encoder model
             synthesis_enabled: true,
             hybrid_enabled: true,
             fusion_text_weight: 0.7,
             fusion_graph_weight: 0.3,
+            embeddings_enabled: false, // Downloads an embedding model
+            embedding_model: crate::features::graphrag::embeddings::DEFAULT_EMBEDDING_MODEL
+                .to_string(),
+            semantic_weight: 0.5,
+            reranker_model: crate::features::graphrag::reranker::DEFAULT_RERANKER_MODEL.to_string(),
+            llm_extraction_enabled: false, // One model call per passage
+            entity_resolution_enabled: true,
+            query_decomposition_enabled: false,
+            abstractive_synthesis_enabled: false, // One model call per question
+            preamble_template: crate::features::graphrag::preamble::DEFAULT_PREAMBLE_TEMPLATE
+                .to_string(),
+            min_relevance: 0.2,
+            mmr_enabled: true,
+            mmr_lambda: 0.7,
+            include_expired: false,
             search_strategy: SearchStrategy::Automatic,
+            chunking_strategy: ChunkingStrategy::MarkdownHeadings,
+            chunk_size: 1500,
+            chunk_overlap: 200,
+            large_kb_mode: false,
             max_query_time_ms: 5000,
             max_memory_mb: 100,
             batch_size: 10,
@@ -64,6 +126,20 @@ impl Default for GraphRAGConfig {
     }
 }
 
+impl GraphRAGConfig {
+    /// Saved settings; defaults when none are saved or they cannot be read
+    pub fn load() -> Self {
+        StorageUtils::retrieve_local(GRAPHRAG_CONFIG_KEY)
+            .ok()
+            .flatten()
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> AppResult<()> {
+        StorageUtils::store_local(GRAPHRAG_CONFIG_KEY, self)
+    }
+}
+
 // Real-time metrics for StatusBar
 #[derive(Clone, Debug, Serialize, Deserialize, Default)]
 pub struct GraphRAGMetrics {
@@ -87,14 +163,41 @@ pub struct PerformanceMetrics {
     pub total_time_ms: u32,
 }
 
+impl PerformanceMetrics {
+    /// Stage timings measured by the retriever, keeping this query's own total
+    pub fn with_retrieval_stages(self, retrieval: &PerformanceMetrics) -> Self {
+        Self {
+            total_time_ms: self.total_time_ms,
+            ..retrieval.clone()
+        }
+    }
+}
+
+// One recorded query: metrics and stage timings at `timestamp_ms`
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+pub struct MetricsSample {
+    pub timestamp_ms: f64,
+    pub metrics: GraphRAGMetrics,
+    pub performance: PerformanceMetrics,
+}
+
+// Samples kept in memory for time series export
+pub const MAX_METRICS_HISTORY: usize = 240;
+
 // Configuration Manager with localStorage persistence
 #[derive(Clone, Debug)]
 pub struct GraphRAGConfigManager {
     config: RwSignal<GraphRAGConfig>,
     metrics: RwSignal<GraphRAGMetrics>,
     performance: RwSignal<PerformanceMetrics>,
+    history: RwSignal<Vec<MetricsSample>>,
+    // User profiles; the built-in ones come from `profiles::builtin_profiles`
+    profiles: RwSignal<Vec<ConfigProfile>>,
 }
 
+// localStorage key of the user profiles
+const PROFILES_KEY: &str = "graphrag_profiles_v1";
+
 impl GraphRAGConfigManager {
     pub fn new() -> Self {
         let config = Self::load_config();
@@ -102,6 +205,8 @@ impl GraphRAGConfigManager {
             config: RwSignal::new(config),
             metrics: RwSignal::new(GraphRAGMetrics::default()),
             performance: RwSignal::new(PerformanceMetrics::default()),
+            history: RwSignal::new(Vec::new()),
+            profiles: RwSignal::new(Self::load_profiles()),
         };
         manager.save_config(); // Ensure localStorage is initialized
         manager
@@ -142,10 +247,42 @@ impl GraphRAGConfigManager {
         self.update_config(|c| c.reranking_enabled = !c.reranking_enabled);
     }
 
+    pub fn toggle_mmr(&self) {
+        self.update_config(|c| c.mmr_enabled = !c.mmr_enabled);
+    }
+
+    pub fn toggle_include_expired(&self) {
+        self.update_config(|c| c.include_expired = !c.include_expired);
+    }
+
+    pub fn toggle_large_kb_mode(&self) {
+        self.update_config(|c| c.large_kb_mode = !c.large_kb_mode);
+    }
+
     pub fn toggle_synthesis(&self) {
         self.update_config(|c| c.synthesis_enabled = !c.synthesis_enabled);
     }
 
+    pub fn toggle_embeddings(&self) {
+        self.update_config(|c| c.embeddings_enabled = !c.embeddings_enabled);
+    }
+
+    pub fn toggle_llm_extraction(&self) {
+        self.update_config(|c| c.llm_extraction_enabled = !c.llm_extraction_enabled);
+    }
+
+    pub fn toggle_entity_resolution(&self) {
+        self.update_config(|c| c.entity_resolution_enabled = !c.entity_resolution_enabled);
+    }
+
+    pub fn toggle_query_decomposition(&self) {
+        self.update_config(|c| c.query_decomposition_enabled = !c.query_decomposition_enabled);
+    }
+
+    pub fn toggle_abstractive_synthesis(&self) {
+        self.update_config(|c| c.abstractive_synthesis_enabled = !c.abstractive_synthesis_enabled);
+    }
+
     // Metrics management
     pub fn get_metrics(&self) -> GraphRAGMetrics {
         self.metrics.get()
@@ -155,6 +292,14 @@ impl GraphRAGConfigManager {
         self.metrics.get_untracked()
     }
 
+    /// Fold one query cache lookup into the hit rate; call before `update_query_metrics`
+    pub fn record_cache_lookup(&self, hit: bool) {
+        self.metrics.update(|m| {
+            let n = m.queries_processed as f32;
+            m.cache_hit_rate = (m.cache_hit_rate * n + if hit { 1.0 } else { 0.0 }) / (n + 1.0);
+        });
+    }
+
     pub fn update_query_metrics(&self, time_ms: u32, memory_mb: f32) {
         self.metrics.update(|m| {
             m.last_query_time_ms = time_ms;
@@ -164,10 +309,28 @@ impl GraphRAGConfigManager {
         });
     }
 
+    /// Store the stage timings of the latest query and append a history sample.
+    /// Call after `update_query_metrics` so the sample carries the same query.
     pub fn update_performance_metrics(&self, perf: PerformanceMetrics) {
+        let sample = MetricsSample {
+            timestamp_ms: js_sys::Date::now(),
+            metrics: self.metrics.get_untracked(),
+            performance: perf.clone(),
+        };
+        self.history.update(|h| {
+            h.push(sample);
+            if h.len() > MAX_METRICS_HISTORY {
+                let excess = h.len() - MAX_METRICS_HISTORY;
+                h.drain(..excess);
+            }
+        });
         self.performance.set(perf);
     }
 
+    pub fn get_metrics_history_untracked(&self) -> Vec<MetricsSample> {
+        self.history.get_untracked()
+    }
+
     pub fn get_performance_metrics(&self) -> PerformanceMetrics {
         self.performance.get()
     }
@@ -206,57 +369,139 @@ impl GraphRAGConfigManager {
         if config.synthesis_enabled {
             features.push("Synthesis".to_string());
         }
+        if config.embeddings_enabled {
+            features.push("Embeddings".to_string());
+        }
+        if config.large_kb_mode {
+            features.push("Large KB".to_string());
+        }
+        if config.query_decomposition_enabled {
+            features.push("Decomposition".to_string());
+        }
 
         self.metrics.update(|m| m.active_features = features);
     }
 
     // Persistence
     fn load_config() -> GraphRAGConfig {
-        if let Some(window) = web_sys::window() {
-            if let Ok(Some(storage)) = window.local_storage() {
-                // Prefer versioned key
-                if let Ok(Some(config_str)) = storage.get_item("graphrag_config_v1") {
-                    if let Ok(config) = serde_json::from_str::<GraphRAGConfig>(&config_str) {
-                        return config;
-                    }
-                }
-                // Fallback to legacy key
-                if let Ok(Some(config_str)) = storage.get_item("graphrag_config") {
-                    if let Ok(config) = serde_json::from_str::<GraphRAGConfig>(&config_str) {
-                        return config;
-                    }
-                }
-            }
-        }
-        GraphRAGConfig::default()
+        GraphRAGConfig::load()
     }
 
     fn save_config(&self) {
-        let config = self.config.get_untracked();
-        if let Some(window) = web_sys::window() {
-            if let Ok(Some(storage)) = window.local_storage() {
-                if let Ok(config_str) = serde_json::to_string(&config) {
-                    let _ = storage.set_item("graphrag_config_v1", &config_str);
-                }
+        if let Err(e) = self.config.get_untracked().save() {
+            log::warn!("Failed to save GraphRAG settings: {}", e);
+        }
+    }
+
+    fn load_profiles() -> Vec<ConfigProfile> {
+        web_sys::window()
+            .and_then(|w| w.local_storage().ok().flatten())
+            .and_then(|s| s.get_item(PROFILES_KEY).ok().flatten())
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default()
+    }
+
+    fn save_profiles(&self) {
+        let profiles = self.profiles.get_untracked();
+        if let Some(storage) = web_sys::window().and_then(|w| w.local_storage().ok().flatten()) {
+            if let Ok(json) = serde_json::to_string(&profiles) {
+                let _ = storage.set_item(PROFILES_KEY, &json);
             }
         }
     }
 
-    // Export/Import functionality
+    // Profiles (presets)
+    /// User profiles, in the order they were saved
+    pub fn custom_profiles(&self) -> Vec<ConfigProfile> {
+        self.profiles.get()
+    }
+
+    /// Name of the profile the current config is tuned as, if any
+    pub fn active_profile(&self) -> Option<String> {
+        let config = self.config.get();
+        self.profiles.with(|custom| {
+            profiles::matching_profile(&config, &profiles::builtin_profiles(), custom)
+                .map(str::to_string)
+        })
+    }
+
+    /// Switch to the tuning of a built-in or user profile
+    pub fn apply_profile(&self, name: &str) {
+        let profile = profiles::builtin_profiles()
+            .into_iter()
+            .chain(self.profiles.get_untracked())
+            .find(|p| p.name == name);
+        if let Some(profile) = profile {
+            self.apply_config(profile.apply_to(&self.config.get_untracked()));
+        }
+    }
+
+    /// Save the current tuning as a user profile, replacing one of the same name
+    pub fn save_profile(&self, name: &str) -> Result<(), String> {
+        profiles::validate_name(name)?;
+        let profile = ConfigProfile::new(name, self.config.get_untracked());
+        self.profiles.update(|p| profiles::upsert(p, profile));
+        self.save_profiles();
+        Ok(())
+    }
+
+    pub fn delete_profile(&self, name: &str) {
+        self.profiles.update(|p| p.retain(|p| p.name != name));
+        self.save_profiles();
+    }
+
+    pub fn export_profiles(&self) -> String {
+        profiles::to_json(&self.profiles.get_untracked())
+    }
+
+    /// Add the profiles of a profiles file or settings bundle; returns how many
+    pub fn import_profiles(&self, json: &str) -> Result<usize, String> {
+        let imported = profiles::parse(json)?;
+        let count = imported.len();
+        self.merge_profiles(imported);
+        Ok(count)
+    }
+
+    fn merge_profiles(&self, imported: Vec<ConfigProfile>) {
+        if imported.is_empty() {
+            return;
+        }
+        self.profiles.update(|p| {
+            for profile in imported {
+                profiles::upsert(p, profile);
+            }
+        });
+        self.save_profiles();
+    }
+
+    // Export/Import functionality, as a versioned bundle (see `config_bundle`)
     pub fn export_config(&self) -> String {
-        serde_json::to_string_pretty(&self.config.get_untracked()).unwrap_or_default()
+        self.config_bundle().to_json()
     }
 
+    /// The current config and user profiles, as exported
+    pub fn config_bundle(&self) -> ConfigBundle {
+        ConfigBundle::new(self.config.get_untracked(), js_sys::Date::now())
+            .with_profiles(self.profiles.get_untracked())
+    }
+
+    /// Apply a bundle or bare config after validating it
     pub fn import_config(&self, config_json: &str) -> Result<(), String> {
-        match serde_json::from_str::<GraphRAGConfig>(config_json) {
-            Ok(config) => {
-                self.config.set(config);
-                self.save_config();
-                self.update_active_features();
-                Ok(())
-            }
-            Err(e) => Err(format!("Invalid configuration: {}", e)),
-        }
+        let imported = config_bundle::parse(config_json)?;
+        self.apply_import(imported);
+        Ok(())
+    }
+
+    /// Apply a validated bundle: its config, and its profiles added to the user's
+    pub fn apply_import(&self, imported: ImportedConfig) {
+        self.merge_profiles(imported.profiles);
+        self.apply_config(imported.config);
+    }
+
+    pub fn apply_config(&self, config: GraphRAGConfig) {
+        self.config.set(config);
+        self.save_config();
+        self.update_active_features();
     }
 
     pub fn reset_to_defaults(&self) {
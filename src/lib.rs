@@ -4,31 +4,47 @@ use leptos_meta::*;
 // Modules
 pub mod advanced_graphrag;
 pub mod components;
+pub mod embed;
 pub mod error_handling;
 pub mod features;
 pub mod graphrag_config;
+pub mod metrics;
 pub mod models;
 pub mod pagerank_reranking;
+pub mod router;
 pub mod state;
 pub mod storage;
 pub mod ui;
 pub mod utils;
 pub mod webllm_binding;
 
+// Public API for embedding the chatbot in other Leptos apps
+pub use embed::{ChatWidget, ChatbotConfig, ChatbotProvider, CrmPanel, KnowledgePanel};
+
 // Components
+use crate::components::lock_screen::LockScreen;
 use crate::components::main_interface::MainInterface;
+use crate::components::pwa_toasts::PwaToasts;
 
 /// Main Wasm Knowledge Chatbot application
 #[component]
 pub fn App() -> impl IntoView {
     // Provides context that manages stylesheets, titles, meta tags, etc.
     provide_meta_context();
+    // With encrypted storage nothing can be read before the passphrase is given
+    let unlocked = RwSignal::new(!crate::storage::encryption::is_enabled());
 
     view! {
         <Html attr:lang="en" attr:dir="ltr" attr:data-theme="business" />
         <Title text="Wasm Knowledge Chatbot" />
         <Meta charset="UTF-8" />
         <Meta name="viewport" content="width=device-width, initial-scale=1.0" />
-        <MainInterface />
+        <Show
+            when=move || unlocked.get()
+            fallback=move || view! { <LockScreen on_unlocked=Callback::new(move |_| unlocked.set(true)) /> }
+        >
+            <MainInterface />
+        </Show>
+        <PwaToasts />
     }
 }
@@ -1,4 +1,5 @@
 use leptos::prelude::*;
+use wasm_knowledge_chatbot_rs::storage::{encryption, init_persistent_storage};
 use wasm_knowledge_chatbot_rs::App;
 
 fn main() {
@@ -6,9 +7,16 @@ fn main() {
     _ = console_log::init_with_level(log::Level::Debug);
     console_error_panic_hook::set_once();
 
-    mount_to_body(|| {
-        view! {
-            <App />
+    // Datasets are preloaded from IndexedDB before any component reads them; encrypted
+    // ones once the lock screen has the passphrase
+    wasm_bindgen_futures::spawn_local(async {
+        if !encryption::is_enabled() {
+            init_persistent_storage().await;
         }
-    })
+        mount_to_body(|| {
+            view! {
+                <App />
+            }
+        })
+    });
 }
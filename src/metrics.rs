@@ -0,0 +1,288 @@
+use crate::graphrag_config::{GraphRAGMetrics, MetricsSample, PerformanceMetrics};
+use crate::models::app::{AppError, AppResult};
+use crate::storage::TieredCache;
+use crate::utils::download::DownloadUtils;
+
+/// Default file name for downloaded exports
+pub const PROMETHEUS_FILENAME: &str = "graphrag_metrics.prom";
+/// `type` field of the message posted to a parent frame
+pub const METRICS_MESSAGE_TYPE: &str = "graphrag-metrics";
+/// Cache key of the origin metrics are posted to, for hosts the referrer does not name
+pub const METRICS_PARENT_ORIGIN_KEY: &str = "metrics_parent_origin";
+
+/// Serializes GraphRAG metrics in the Prometheus text exposition format (v0.0.4)
+pub struct PrometheusExporter;
+
+impl PrometheusExporter {
+    /// Render the recorded query history as timestamped samples. Without history the
+    /// current metrics are rendered once, untimestamped.
+    pub fn render(current: &GraphRAGMetrics, history: &[MetricsSample]) -> String {
+        let mut out = String::new();
+        let latest = [MetricsSample {
+            timestamp_ms: 0.0,
+            metrics: current.clone(),
+            performance: PerformanceMetrics::default(),
+        }];
+        let (samples, timed) = if history.is_empty() {
+            (&latest[..], false)
+        } else {
+            (history, true)
+        };
+        let ts = |s: &MetricsSample| timed.then_some(s.timestamp_ms);
+
+        family(
+            &mut out,
+            "graphrag_queries_total",
+            "counter",
+            "GraphRAG queries processed",
+        );
+        for s in samples {
+            sample(
+                &mut out,
+                "graphrag_queries_total",
+                &[],
+                s.metrics.queries_processed as f64,
+                ts(s),
+            );
+        }
+
+        let gauges: [(&str, &str, fn(&GraphRAGMetrics) -> f64); 4] = [
+            (
+                "graphrag_query_duration_seconds",
+                "Duration of the last GraphRAG query",
+                |m| m.last_query_time_ms as f64 / 1000.0,
+            ),
+            (
+                "graphrag_memory_usage_megabytes",
+                "Estimated memory used by the last query",
+                |m| m.memory_usage_mb as f64,
+            ),
+            (
+                "graphrag_cache_hit_ratio",
+                "Share of queries served from cache",
+                |m| m.cache_hit_rate as f64,
+            ),
+            (
+                "graphrag_performance_score",
+                "Performance score from 0 to 100",
+                |m| m.performance_score as f64,
+            ),
+        ];
+        for (name, help, value) in gauges {
+            family(&mut out, name, "gauge", help);
+            for s in samples {
+                sample(&mut out, name, &[], value(&s.metrics), ts(s));
+            }
+        }
+
+        if timed {
+            let name = "graphrag_stage_duration_seconds";
+            family(
+                &mut out,
+                name,
+                "gauge",
+                "Time spent in each retrieval stage",
+            );
+            for s in samples {
+                for (stage, ms) in stages(&s.performance) {
+                    sample(
+                        &mut out,
+                        name,
+                        &[("stage", stage)],
+                        ms as f64 / 1000.0,
+                        ts(s),
+                    );
+                }
+            }
+        }
+
+        if !current.active_features.is_empty() {
+            let name = "graphrag_feature_enabled";
+            family(
+                &mut out,
+                name,
+                "gauge",
+                "GraphRAG features currently enabled",
+            );
+            for feature in &current.active_features {
+                sample(&mut out, name, &[("feature", feature)], 1.0, None);
+            }
+        }
+        out
+    }
+
+    pub fn download(text: &str) -> AppResult<()> {
+        DownloadUtils::download_text(PROMETHEUS_FILENAME, text)
+    }
+
+    /// Whether the app runs inside another page's frame
+    pub fn is_embedded() -> bool {
+        web_sys::window()
+            .and_then(|w| {
+                let parent = w.parent().ok().flatten()?;
+                Some(!js_sys::Object::is(&parent, &w))
+            })
+            .unwrap_or(false)
+    }
+
+    /// Origin of the embedding page: the configured one, else the referrer's. `None`
+    /// when neither is known, and then nothing is posted.
+    pub fn parent_origin() -> Option<String> {
+        TieredCache::get::<String>(METRICS_PARENT_ORIGIN_KEY)
+            .and_then(|configured| origin_of(&configured))
+            .or_else(|| {
+                let referrer = web_sys::window()?.document()?.referrer();
+                origin_of(&referrer)
+            })
+    }
+
+    /// Post `{ type: "graphrag-metrics", format: "prometheus", body }` to the parent
+    /// frame, only if it is at `parent_origin`
+    pub fn post_to_parent(text: &str) -> AppResult<()> {
+        let origin = Self::parent_origin().ok_or_else(|| {
+            AppError::InternalError("The embedding page's origin is not known".to_string())
+        })?;
+        let window = web_sys::window()
+            .ok_or_else(|| AppError::InternalError("Window not available".to_string()))?;
+        let parent = window
+            .parent()
+            .ok()
+            .flatten()
+            .filter(|p| !js_sys::Object::is(p, &window))
+            .ok_or_else(|| AppError::InternalError("Not embedded in a frame".to_string()))?;
+        let message = js_sys::Object::new();
+        for (key, value) in [
+            ("type", METRICS_MESSAGE_TYPE),
+            ("format", "prometheus"),
+            ("body", text),
+        ] {
+            let _ = js_sys::Reflect::set(&message, &key.into(), &value.into());
+        }
+        parent
+            .post_message(&message, &origin)
+            .map_err(|e| AppError::InternalError(format!("postMessage failed: {:?}", e)))
+    }
+}
+
+/// `scheme://host[:port]` of an http(s) URL
+fn origin_of(url: &str) -> Option<String> {
+    let (scheme, rest) = url.trim().split_once("://")?;
+    let scheme = scheme.to_ascii_lowercase();
+    if scheme != "http" && scheme != "https" {
+        return None;
+    }
+    let authority = rest.split(['/', '?', '#']).next()?;
+    let host = authority
+        .rsplit_once('@')
+        .map_or(authority, |(_, host)| host);
+    if host.is_empty() {
+        return None;
+    }
+    Some(format!("{}://{}", scheme, host.to_ascii_lowercase()))
+}
+
+fn stages(p: &PerformanceMetrics) -> [(&'static str, u32); 7] {
+    [
+        ("hyde", p.hyde_time_ms),
+        ("community_detection", p.community_detection_time_ms),
+        ("pagerank", p.pagerank_time_ms),
+        ("reranking", p.reranking_time_ms),
+        ("hybrid_fusion", p.hybrid_fusion_time_ms),
+        ("synthesis", p.synthesis_time_ms),
+        ("total", p.total_time_ms),
+    ]
+}
+
+fn family(out: &mut String, name: &str, kind: &str, help: &str) {
+    out.push_str(&format!(
+        "# HELP {} {}\n# TYPE {} {}\n",
+        name, help, name, kind
+    ));
+}
+
+fn sample(out: &mut String, name: &str, labels: &[(&str, &str)], value: f64, ts: Option<f64>) {
+    out.push_str(name);
+    if !labels.is_empty() {
+        let labels: Vec<String> = labels
+            .iter()
+            .map(|(k, v)| format!("{}=\"{}\"", k, escape_label(v)))
+            .collect();
+        out.push_str(&format!("{{{}}}", labels.join(",")));
+    }
+    out.push_str(&format!(" {}", value));
+    if let Some(ts) = ts {
+        out.push_str(&format!(" {}", ts.round() as i64));
+    }
+    out.push('\n');
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metrics(queries: u32, time_ms: u32) -> GraphRAGMetrics {
+        GraphRAGMetrics {
+            last_query_time_ms: time_ms,
+            queries_processed: queries,
+            active_features: vec!["HyDE".to_string()],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_render_history_as_timestamped_samples() {
+        let history = vec![
+            MetricsSample {
+                timestamp_ms: 1000.0,
+                metrics: metrics(1, 250),
+                performance: PerformanceMetrics {
+                    hyde_time_ms: 12,
+                    total_time_ms: 250,
+                    ..Default::default()
+                },
+            },
+            MetricsSample {
+                timestamp_ms: 2000.0,
+                metrics: metrics(2, 100),
+                performance: PerformanceMetrics::default(),
+            },
+        ];
+        let text = PrometheusExporter::render(&metrics(2, 100), &history);
+        assert!(text.contains("# TYPE graphrag_queries_total counter\n"));
+        assert!(text.contains("graphrag_queries_total 1 1000\ngraphrag_queries_total 2 2000\n"));
+        assert!(text.contains("graphrag_query_duration_seconds 0.25 1000\n"));
+        assert!(text.contains("graphrag_stage_duration_seconds{stage=\"hyde\"} 0.012 1000\n"));
+        assert!(text.contains("graphrag_feature_enabled{feature=\"HyDE\"} 1\n"));
+    }
+
+    #[test]
+    fn test_render_without_history() {
+        let text = PrometheusExporter::render(&metrics(0, 0), &[]);
+        assert!(text.contains("graphrag_queries_total 0\n"));
+        assert!(!text.contains("graphrag_stage_duration_seconds"));
+        assert_eq!(escape_label("a\"b\\c"), "a\\\"b\\\\c");
+    }
+
+    #[test]
+    fn test_parent_origin_of_urls() {
+        assert_eq!(
+            origin_of("https://Host.example:8443/app/page?x=1#top").as_deref(),
+            Some("https://host.example:8443")
+        );
+        assert_eq!(
+            origin_of("http://user:pw@intranet.local/").as_deref(),
+            Some("http://intranet.local")
+        );
+        assert_eq!(origin_of(""), None);
+        assert_eq!(origin_of("null"), None);
+        assert_eq!(origin_of("file:///tmp/page.html"), None);
+        assert_eq!(origin_of("https:///path"), None);
+    }
+}
@@ -0,0 +1,74 @@
+use serde::{Deserialize, Serialize};
+
+/// Area of the app an activity event comes from
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ActivityCategory {
+    Model,
+    Knowledge,
+    Conversation,
+    Crm,
+    Tasks,
+    Error,
+}
+
+impl ActivityCategory {
+    pub const ALL: [ActivityCategory; 6] = [
+        ActivityCategory::Model,
+        ActivityCategory::Knowledge,
+        ActivityCategory::Conversation,
+        ActivityCategory::Crm,
+        ActivityCategory::Tasks,
+        ActivityCategory::Error,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ActivityCategory::Model => "Model",
+            ActivityCategory::Knowledge => "Knowledge",
+            ActivityCategory::Conversation => "Conversations",
+            ActivityCategory::Crm => "CRM",
+            ActivityCategory::Tasks => "Tasks",
+            ActivityCategory::Error => "Errors",
+        }
+    }
+
+    /// Lucide icon name
+    pub fn icon(&self) -> &'static str {
+        match self {
+            ActivityCategory::Model => "cpu",
+            ActivityCategory::Knowledge => "database",
+            ActivityCategory::Conversation => "message-square",
+            ActivityCategory::Crm => "briefcase",
+            ActivityCategory::Tasks => "list-todo",
+            ActivityCategory::Error => "triangle-alert",
+        }
+    }
+}
+
+/// Entry of the session activity feed
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ActivityEvent {
+    pub id: String,
+    pub category: ActivityCategory,
+    pub message: String,
+    pub detail: Option<String>,
+    pub timestamp: f64,
+}
+
+impl ActivityEvent {
+    pub fn new(category: ActivityCategory, message: impl Into<String>) -> Self {
+        let timestamp = js_sys::Date::now();
+        Self {
+            id: format!("evt_{}_{}", timestamp as u64, uuid::Uuid::new_v4().simple()),
+            category,
+            message: message.into(),
+            detail: None,
+            timestamp,
+        }
+    }
+
+    pub fn with_detail(mut self, detail: impl Into<String>) -> Self {
+        self.detail = Some(detail.into());
+        self
+    }
+}
@@ -1,6 +1,9 @@
+use crate::storage::persistent::PersistentStore;
 use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+pub const APP_CONFIG_KEY_V1: &str = "app_config_v1";
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct AppConfig {
     pub theme: ThemeMode,
     pub language: String,
@@ -8,6 +11,18 @@ pub struct AppConfig {
     pub performance_mode: PerformanceMode,
     pub accessibility: AccessibilityConfig,
     pub ui_preferences: UIPreferences,
+    #[serde(default)]
+    pub startup: StartupPreferences,
+}
+
+impl AppConfig {
+    pub fn save(&self) -> AppResult<()> {
+        PersistentStore::write(APP_CONFIG_KEY_V1, self)
+    }
+
+    pub fn load() -> AppResult<Self> {
+        Ok(PersistentStore::read(APP_CONFIG_KEY_V1)?.unwrap_or_default())
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
@@ -17,6 +32,16 @@ pub enum ThemeMode {
     Auto,
 }
 
+impl ThemeMode {
+    /// daisyUI theme set on `<html data-theme>`; `Auto` keeps the light theme
+    pub fn data_theme(&self) -> &'static str {
+        match self {
+            ThemeMode::Dark => "business",
+            ThemeMode::Light | ThemeMode::Auto => "light",
+        }
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum PerformanceMode {
     High,
@@ -24,7 +49,7 @@ pub enum PerformanceMode {
     Battery,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct AccessibilityConfig {
     pub high_contrast: bool,
     pub reduced_motion: bool,
@@ -33,7 +58,7 @@ pub struct AccessibilityConfig {
     pub screen_reader_mode: bool,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct UIPreferences {
     pub sidebar_collapsed: bool,
     pub show_timestamps: bool,
@@ -49,6 +74,69 @@ pub enum AnimationSpeed {
     Fast,
 }
 
+/// What the app opens to when the URL does not link anywhere
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LandingView {
+    /// The most recently updated conversation
+    LastConversation,
+    #[default]
+    NewChat,
+    Documents,
+    CrmBoard,
+}
+
+impl LandingView {
+    pub const ALL: [LandingView; 4] = [
+        LandingView::LastConversation,
+        LandingView::NewChat,
+        LandingView::Documents,
+        LandingView::CrmBoard,
+    ];
+
+    pub fn id(&self) -> &'static str {
+        match self {
+            LandingView::LastConversation => "last_conversation",
+            LandingView::NewChat => "new_chat",
+            LandingView::Documents => "documents",
+            LandingView::CrmBoard => "crm_board",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            LandingView::LastConversation => "Last conversation",
+            LandingView::NewChat => "New chat",
+            LandingView::Documents => "Documents",
+            LandingView::CrmBoard => "CRM board",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|v| v.id() == id)
+    }
+}
+
+/// Landing view and the tasks run when the app starts
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StartupPreferences {
+    pub landing: LandingView,
+    /// Load the last used chat model on startup
+    pub auto_init_model: bool,
+    /// Read the knowledge index on startup and offer to rebuild it when missing
+    pub warm_load_index: bool,
+}
+
+impl Default for StartupPreferences {
+    fn default() -> Self {
+        Self {
+            landing: LandingView::default(),
+            auto_init_model: true,
+            warm_load_index: true,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum AppError {
     // Network and connectivity
@@ -92,6 +180,7 @@ impl Default for AppConfig {
             performance_mode: PerformanceMode::Balanced,
             accessibility: AccessibilityConfig::default(),
             ui_preferences: UIPreferences::default(),
+            startup: StartupPreferences::default(),
         }
     }
 }
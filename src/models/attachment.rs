@@ -0,0 +1,139 @@
+use crate::models::app::AppError;
+use crate::models::graphrag::DocumentIndex;
+use crate::storage::persistent::PersistentStore;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+pub const ATTACHMENTS_KEY_V1: &str = "conversation_attachments_v1";
+
+/// Where an indexed document may be retrieved, given the conversation asking
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AttachmentScope {
+    /// Uploaded to the knowledge base: retrieved everywhere
+    Global,
+    /// Attached in the asking conversation: retrieved first
+    Here,
+    /// Attached in another conversation: not retrieved
+    Elsewhere,
+}
+
+/// Files attached to a chat message. They are indexed like any upload but only
+/// retrieved in the conversation they were attached in, where they rank first.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct Attachments {
+    /// Uploaded file name to the id of its conversation
+    pub files: BTreeMap<String, String>,
+}
+
+impl Attachments {
+    pub fn save(&self) -> Result<(), AppError> {
+        PersistentStore::write(ATTACHMENTS_KEY_V1, self)
+    }
+
+    pub fn load() -> Result<Self, AppError> {
+        Ok(PersistentStore::read(ATTACHMENTS_KEY_V1)?.unwrap_or_default())
+    }
+
+    pub fn attach(&mut self, file: &str, conversation_id: &str) {
+        self.files
+            .insert(file.to_string(), conversation_id.to_string());
+    }
+
+    pub fn detach(&mut self, file: &str) {
+        self.files.remove(file);
+    }
+
+    /// Files attached in `conversation_id`, by name
+    pub fn files_of(&self, conversation_id: &str) -> Vec<String> {
+        self.files
+            .iter()
+            .filter(|(_, c)| c.as_str() == conversation_id)
+            .map(|(f, _)| f.clone())
+            .collect()
+    }
+
+    pub fn scope(&self, doc: &DocumentIndex, conversation_id: Option<&str>) -> AttachmentScope {
+        match self.files.get(doc.source_file()) {
+            None => AttachmentScope::Global,
+            Some(c) if Some(c.as_str()) == conversation_id => AttachmentScope::Here,
+            Some(_) => AttachmentScope::Elsewhere,
+        }
+    }
+}
+
+/// `name`, or `name` numbered before its extension while `taken`, so an attachment
+/// does not replace a knowledge base file of the same name
+pub fn unique_file_name(name: &str, taken: impl Fn(&str) -> bool) -> String {
+    if !taken(name) {
+        return name.to_string();
+    }
+    let (stem, ext) = match name.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() => (stem, format!(".{}", ext)),
+        _ => (name, String::new()),
+    };
+    (2..)
+        .map(|n| format!("{} ({}){}", stem, n, ext))
+        .find(|candidate| !taken(candidate))
+        .unwrap_or_else(|| name.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::graphrag::ProcessingStatus;
+
+    fn doc(id: &str, parent: Option<&str>) -> DocumentIndex {
+        DocumentIndex {
+            id: id.to_string(),
+            title: id.to_string(),
+            content: String::new(),
+            file_type: "text".to_string(),
+            size_bytes: 0,
+            created_at: 0.0,
+            indexed_at: 0.0,
+            node_count: 0,
+            embedding_model: None,
+            processing_status: ProcessingStatus::Pending,
+            symbols: Vec::new(),
+            parent: parent.map(String::from),
+        }
+    }
+
+    #[test]
+    fn test_attached_files_are_scoped_to_their_conversation() {
+        let mut attachments = Attachments::default();
+        attachments.attach("quote.pdf", "c1");
+        attachments.attach("notes.md", "c2");
+
+        let page = doc("1:quote.pdf#page=2", Some("quote.pdf"));
+        let handbook = doc("1:handbook.md", None);
+        assert_eq!(attachments.scope(&page, Some("c1")), AttachmentScope::Here);
+        assert_eq!(
+            attachments.scope(&page, Some("c2")),
+            AttachmentScope::Elsewhere
+        );
+        // Outside any conversation an attachment is hidden too
+        assert_eq!(attachments.scope(&page, None), AttachmentScope::Elsewhere);
+        assert_eq!(
+            attachments.scope(&handbook, Some("c1")),
+            AttachmentScope::Global
+        );
+
+        assert_eq!(attachments.files_of("c2"), vec!["notes.md"]);
+        let taken = ["notes.md", "notes (2).md", "README"];
+        assert_eq!(
+            unique_file_name("notes.md", |n| taken.contains(&n)),
+            "notes (3).md"
+        );
+        assert_eq!(
+            unique_file_name("README", |n| taken.contains(&n)),
+            "README (2)"
+        );
+        assert_eq!(
+            unique_file_name("quote.pdf", |n| taken.contains(&n)),
+            "quote.pdf"
+        );
+        attachments.detach("notes.md");
+        assert!(attachments.files_of("c2").is_empty());
+    }
+}
@@ -1,3 +1,4 @@
+use super::graphrag::RetrievalTrace;
 use serde::{Deserialize, Serialize};
 
 #[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
@@ -14,6 +15,9 @@ pub struct Message {
     pub content: String,
     pub timestamp: f64, // Using f64 for js_sys::Date compatibility
     pub metadata: Option<MessageMetadata>,
+    /// Kept in the conversation's pinned list for quick access
+    #[serde(default)]
+    pub pinned: bool,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -25,14 +29,184 @@ pub struct MessageMetadata {
     pub error: Option<String>,
     // Optional multi-document provenance for transparency
     pub provenance: Option<Vec<SourceAttribution>>,
+    /// Token split reported by the engine (tokens_used is the sum)
+    #[serde(default)]
+    pub prompt_tokens: Option<u32>,
+    #[serde(default)]
+    pub completion_tokens: Option<u32>,
+    /// Decode throughput reported by the engine
+    #[serde(default)]
+    pub decode_tokens_per_sec: Option<f32>,
+    /// Why the first completion was rejected and regenerated, if it was
+    #[serde(default)]
+    pub retry_reason: Option<String>,
+    /// Why retrieved knowledge was withheld from the prompt, if it was
+    #[serde(default)]
+    pub retrieval_note: Option<String>,
+    /// Sampling temperature the reply was generated with
+    #[serde(default)]
+    pub temperature: Option<f32>,
+    /// Earlier replies to the same prompt, oldest first, kept when the reply was regenerated
+    #[serde(default)]
+    pub attempts: Vec<ReplyAttempt>,
+    /// Tools the model called while producing the reply, in call order
+    #[serde(default)]
+    pub tools_used: Vec<String>,
+    /// Reply was requested as JSON and passed the schema check
+    #[serde(default)]
+    pub structured: bool,
+    /// Where the reply's time went, from the spans traced for its request
+    #[serde(default)]
+    pub latency: Option<LatencyBreakdown>,
+    /// Calibrated confidence in the reply, from `utils::confidence`
+    #[serde(default)]
+    pub confidence: Option<AnswerConfidence>,
+    /// Sub-queries a multi-part question was split into before retrieval
+    #[serde(default)]
+    pub query_plan: Option<QueryPlan>,
+    /// How the knowledge injected into the prompt was retrieved and ranked
+    #[serde(default)]
+    pub retrieval_trace: Option<RetrievalTrace>,
+    /// Replies of other models to the same prompt, shown side by side with this one
+    #[serde(default)]
+    pub compared: Vec<ReplyAttempt>,
 }
 
+/// How a multi-part question was decomposed for retrieval
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct QueryPlan {
+    pub sub_queries: Vec<SubQuery>,
+}
+
+/// One part of a decomposed question
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SubQuery {
+    pub text: String,
+    /// Sources of the passages this sub-query contributed to the prompt
+    pub sources: Vec<String>,
+}
+
+/// Coarse confidence shown on a reply
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ConfidenceLevel {
+    High,
+    Medium,
+    Low,
+}
+
+impl ConfidenceLevel {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ConfidenceLevel::High => "High",
+            ConfidenceLevel::Medium => "Medium",
+            ConfidenceLevel::Low => "Low",
+        }
+    }
+}
+
+/// One input to a reply's confidence
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ConfidenceSignal {
+    pub name: String,
+    /// 0.0..1.0
+    pub value: f32,
+    /// Share of the score, after renormalizing over the signals available
+    pub weight: f32,
+    /// What the value was read from
+    pub note: String,
+}
+
+/// Weighted combination of the signals available for a reply
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AnswerConfidence {
+    pub level: ConfidenceLevel,
+    /// 0.0..1.0
+    pub score: f32,
+    pub signals: Vec<ConfidenceSignal>,
+}
+
+/// Milliseconds a reply spent in each stage of its request
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct LatencyBreakdown {
+    pub retrieval_ms: u32,
+    pub queue_ms: u32,
+    pub first_token_ms: u32,
+    pub generation_ms: u32,
+    /// Prompt tokens the model kept from the previous turn instead of prefilling them
+    #[serde(default)]
+    pub prefill_reused_tokens: u32,
+    /// Prefill time those tokens would have cost, at the measured prefill speed
+    #[serde(default)]
+    pub prefill_saved_ms: Option<u32>,
+}
+
+impl LatencyBreakdown {
+    pub fn total_ms(&self) -> u32 {
+        self.retrieval_ms + self.queue_ms + self.first_token_ms + self.generation_ms
+    }
+
+    /// Stages with their labels, in request order
+    pub fn stages(&self) -> [(&'static str, u32); 4] {
+        [
+            ("Retrieval", self.retrieval_ms),
+            ("Queueing", self.queue_ms),
+            ("Time to first token", self.first_token_ms),
+            ("Generation", self.generation_ms),
+        ]
+    }
+}
+
+/// A reply superseded by a regeneration
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ReplyAttempt {
+    pub content: String,
+    pub timestamp: f64,
+    pub model_used: Option<String>,
+    pub temperature: Option<f32>,
+}
+
+/// Where a regenerated reply goes
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RegenerateMode {
+    /// Replace the reply in place
+    Replace,
+    /// Keep the reply and add the new one after it
+    Append,
+    /// Keep the reply and show the new one, from another model, beside it
+    Compare,
+}
+
+/// A request to regenerate the last assistant reply
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RegenerateOptions {
+    pub mode: RegenerateMode,
+    /// Added to the configured sampling temperature
+    pub temperature_delta: f32,
+}
+
+/// Characters of a cited passage kept with the reply that cites it
+pub const CITED_SNIPPET_CHARS: usize = 600;
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct SourceAttribution {
     pub source_id: String,
     pub title: String,
     /// Confidence in range 0.0..1.0
     pub confidence: f32,
+    /// Start of the cited passage as it was when the reply was written
+    #[serde(default)]
+    pub snippet: Option<String>,
+}
+
+impl SourceAttribution {
+    /// Stable reference to the cited passage: its file and chunk, e.g.
+    /// `guide.pdf#page=2`, without the index generation prefix of its id. `None` for
+    /// sources that are not indexed passages.
+    pub fn anchor(&self) -> Option<&str> {
+        let (generation, passage) = self.source_id.split_once(':')?;
+        (!generation.is_empty() && generation.bytes().all(|b| b.is_ascii_digit()))
+            .then_some(passage)
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -74,6 +248,7 @@ impl Message {
             content,
             timestamp,
             metadata: None,
+            pinned: false,
         }
     }
 
@@ -81,6 +256,31 @@ impl Message {
         self.metadata = Some(metadata);
         self
     }
+
+    /// Keep `reply` of another model beside this one, replacing an earlier reply of
+    /// the same model. Messages without metadata, e.g. error notes, keep none.
+    pub fn add_compared(&mut self, reply: ReplyAttempt) {
+        let Some(metadata) = self.metadata.as_mut() else {
+            return;
+        };
+        metadata
+            .compared
+            .retain(|c| c.model_used.is_none() || c.model_used != reply.model_used);
+        metadata.compared.push(reply);
+    }
+
+    /// Every reply to this message's prompt so far, this one last
+    pub fn reply_attempts(&self) -> Vec<ReplyAttempt> {
+        let metadata = self.metadata.as_ref();
+        let mut attempts = metadata.map(|m| m.attempts.clone()).unwrap_or_default();
+        attempts.push(ReplyAttempt {
+            content: self.content.clone(),
+            timestamp: self.timestamp,
+            model_used: metadata.and_then(|m| m.model_used.clone()),
+            temperature: metadata.and_then(|m| m.temperature),
+        });
+        attempts
+    }
 }
 
 impl Conversation {
@@ -120,3 +320,70 @@ impl Conversation {
             .find(|msg| matches!(msg.role, MessageRole::Assistant))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reply(content: &str, model: &str) -> ReplyAttempt {
+        ReplyAttempt {
+            content: content.to_string(),
+            timestamp: 0.0,
+            model_used: Some(model.to_string()),
+            temperature: None,
+        }
+    }
+
+    #[test]
+    fn test_compared_replies_keep_one_per_model() {
+        let mut message = Message {
+            id: "1".to_string(),
+            role: MessageRole::Assistant,
+            content: "first".to_string(),
+            timestamp: 0.0,
+            metadata: Some(MessageMetadata {
+                tokens_used: None,
+                processing_time_ms: None,
+                model_used: Some("llama".to_string()),
+                graphrag_enhanced: false,
+                error: None,
+                provenance: None,
+                prompt_tokens: None,
+                completion_tokens: None,
+                decode_tokens_per_sec: None,
+                retry_reason: None,
+                retrieval_note: None,
+                temperature: None,
+                attempts: Vec::new(),
+                tools_used: Vec::new(),
+                structured: false,
+                latency: None,
+                confidence: None,
+                query_plan: None,
+                retrieval_trace: None,
+                compared: Vec::new(),
+            }),
+            pinned: false,
+        };
+        message.add_compared(reply("second", "qwen"));
+        message.add_compared(reply("third", "phi"));
+        message.add_compared(reply("again", "qwen"));
+        let compared = &message.metadata.as_ref().unwrap().compared;
+        assert_eq!(
+            compared
+                .iter()
+                .map(|c| c.content.as_str())
+                .collect::<Vec<_>>(),
+            ["third", "again"]
+        );
+        // The reply itself is untouched
+        assert_eq!(message.reply_attempts().len(), 1);
+
+        let mut note = Message {
+            metadata: None,
+            ..message.clone()
+        };
+        note.add_compared(reply("lost", "qwen"));
+        assert!(note.metadata.is_none());
+    }
+}
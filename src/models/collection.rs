@@ -0,0 +1,244 @@
+use crate::models::app::AppError;
+use crate::models::graphrag::DocumentIndex;
+use crate::storage::persistent::PersistentStore;
+use serde::{Deserialize, Serialize};
+
+pub const COLLECTIONS_KEY_V1: &str = "knowledge_collections_v1";
+
+/// A named group of uploaded files with its own embedding model, such as "Work docs"
+/// or "Recipes", that a conversation can search on its own. Files outside every
+/// collection use the embedding model from the GraphRAG settings.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Collection {
+    pub id: String,
+    pub name: String,
+    /// What the collection holds, shown when choosing one
+    #[serde(default)]
+    pub description: String,
+    /// Uploaded file names, as in the knowledge buffer
+    pub documents: Vec<String>,
+    /// Embedding model id; `None` follows the GraphRAG settings
+    pub embedding_model: Option<String>,
+}
+
+impl Collection {
+    pub fn new(name: String) -> Self {
+        Self {
+            id: format!("collection_{}", uuid::Uuid::new_v4().simple()),
+            name,
+            description: String::new(),
+            documents: Vec::new(),
+            embedding_model: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct Collections {
+    pub collections: Vec<Collection>,
+}
+
+impl Collections {
+    pub fn save(&self) -> Result<(), AppError> {
+        PersistentStore::write(COLLECTIONS_KEY_V1, self)
+    }
+
+    pub fn load() -> Result<Self, AppError> {
+        Ok(PersistentStore::read(COLLECTIONS_KEY_V1)?.unwrap_or_default())
+    }
+
+    /// Collection an uploaded file belongs to; a file is in at most one
+    pub fn of_file(&self, file: &str) -> Option<&Collection> {
+        self.collections
+            .iter()
+            .find(|c| c.documents.iter().any(|d| d == file))
+    }
+
+    /// Move `file` into `collection_id`, or out of every collection when `None`
+    pub fn assign(&mut self, file: &str, collection_id: Option<&str>) {
+        for c in &mut self.collections {
+            c.documents.retain(|d| d != file);
+            if Some(c.id.as_str()) == collection_id {
+                c.documents.push(file.to_string());
+            }
+        }
+    }
+
+    /// Embedding model an indexed document must be embedded and searched with
+    pub fn embedding_model_for(&self, doc: &DocumentIndex, default_model: &str) -> String {
+        self.of_file(doc.source_file())
+            .and_then(|c| c.embedding_model.clone())
+            .unwrap_or_else(|| default_model.to_string())
+    }
+}
+
+/// Part of the knowledge base a conversation searches: whole collections and single
+/// files. An empty scope searches everything.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct KnowledgeScope {
+    /// Collection ids
+    #[serde(default)]
+    pub collections: Vec<String>,
+    /// Uploaded file names, as in the knowledge buffer
+    #[serde(default)]
+    pub documents: Vec<String>,
+}
+
+impl KnowledgeScope {
+    pub fn is_empty(&self) -> bool {
+        self.collections.is_empty() && self.documents.is_empty()
+    }
+
+    /// Scope searching one collection alone
+    pub fn only(collection_id: &str) -> Self {
+        Self {
+            collections: vec![collection_id.to_string()],
+            documents: Vec::new(),
+        }
+    }
+
+    /// The collection searched, when the scope is exactly one collection
+    pub fn single_collection(&self) -> Option<&str> {
+        match (self.collections.as_slice(), self.documents.is_empty()) {
+            ([id], true) => Some(id.as_str()),
+            _ => None,
+        }
+    }
+
+    /// Whether `doc` may be retrieved under this scope
+    pub fn includes(&self, doc: &DocumentIndex, collections: &Collections) -> bool {
+        if self.is_empty() {
+            return true;
+        }
+        let file = doc.source_file();
+        self.documents.iter().any(|d| d == file)
+            || collections
+                .of_file(file)
+                .is_some_and(|c| self.collections.contains(&c.id))
+    }
+
+    pub fn toggle_collection(&mut self, collection_id: &str) {
+        toggle(&mut self.collections, collection_id);
+    }
+
+    pub fn toggle_document(&mut self, file: &str) {
+        toggle(&mut self.documents, file);
+    }
+
+    /// Short description, e.g. "2 collections, 1 file"
+    pub fn summary(&self) -> String {
+        if self.is_empty() {
+            return "All documents".to_string();
+        }
+        let count = |n: usize, one: &str, many: &str| match n {
+            0 => None,
+            1 => Some(format!("1 {}", one)),
+            n => Some(format!("{} {}", n, many)),
+        };
+        [
+            count(self.collections.len(), "collection", "collections"),
+            count(self.documents.len(), "file", "files"),
+        ]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>()
+        .join(", ")
+    }
+}
+
+fn toggle(items: &mut Vec<String>, item: &str) {
+    if items.iter().any(|i| i == item) {
+        items.retain(|i| i != item);
+    } else {
+        items.push(item.to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::graphrag::ProcessingStatus;
+
+    fn doc(id: &str) -> DocumentIndex {
+        DocumentIndex {
+            id: id.to_string(),
+            title: id.to_string(),
+            content: String::new(),
+            file_type: "text".to_string(),
+            size_bytes: 0,
+            created_at: 0.0,
+            indexed_at: 0.0,
+            node_count: 0,
+            embedding_model: None,
+            processing_status: ProcessingStatus::Pending,
+            symbols: Vec::new(),
+            parent: None,
+        }
+    }
+
+    #[test]
+    fn test_embedding_model_follows_collection_of_source_file() {
+        let mut set = Collections {
+            collections: vec![
+                Collection {
+                    id: "fr".to_string(),
+                    name: "French".to_string(),
+                    description: String::new(),
+                    documents: Vec::new(),
+                    embedding_model: Some("multilingual".to_string()),
+                },
+                Collection {
+                    id: "misc".to_string(),
+                    name: "Misc".to_string(),
+                    description: String::new(),
+                    documents: Vec::new(),
+                    embedding_model: None,
+                },
+            ],
+        };
+        set.assign("guide.pdf", Some("fr"));
+        let page = doc("1700000000000:guide.pdf#page=2");
+        assert_eq!(set.embedding_model_for(&page, "english"), "multilingual");
+
+        set.assign("guide.pdf", Some("misc"));
+        assert!(set.collections[0].documents.is_empty());
+        assert_eq!(set.embedding_model_for(&page, "english"), "english");
+        set.assign("guide.pdf", None);
+        assert!(set.of_file("guide.pdf").is_none());
+    }
+
+    #[test]
+    fn test_scope_limits_documents_to_its_collections_and_files() {
+        let mut set = Collections {
+            collections: vec![Collection {
+                id: "hr".to_string(),
+                name: "HR".to_string(),
+                description: "Policies".to_string(),
+                documents: vec!["leave.md".to_string()],
+                embedding_model: None,
+            }],
+        };
+        let leave = doc("1:leave.md");
+        let page = doc("1:quote.pdf#page=3");
+        let other = doc("1:notes.md");
+
+        let mut scope = KnowledgeScope::default();
+        assert!(scope.includes(&other, &set));
+        assert_eq!(scope.summary(), "All documents");
+
+        scope.toggle_collection("hr");
+        scope.toggle_document("quote.pdf");
+        assert!(scope.includes(&leave, &set));
+        assert!(scope.includes(&page, &set));
+        assert!(!scope.includes(&other, &set));
+        assert_eq!(scope.summary(), "1 collection, 1 file");
+        assert_eq!(scope.single_collection(), None);
+        assert_eq!(KnowledgeScope::only("hr").single_collection(), Some("hr"));
+
+        // Moving a file out of the collection takes it out of the scope
+        set.assign("leave.md", None);
+        assert!(!scope.includes(&leave, &set));
+        scope.toggle_document("quote.pdf");
+        assert_eq!(scope.documents, Vec::<String>::new());
+    }
+}
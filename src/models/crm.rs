@@ -33,7 +33,9 @@ pub struct Lead {
     pub source: LeadSource,
     pub status: LeadStatus,
     pub score: Option<u32>, // Lead scoring 0-100
-    pub assigned_to: Option<String>,
+    /// Local profile or free-text name responsible for the lead
+    #[serde(default, alias = "assigned_to")]
+    pub owner: Option<String>,
     pub created_at: f64,
     pub updated_at: f64,
     pub notes: Vec<Note>,
@@ -107,7 +109,9 @@ pub struct Deal {
     pub expected_close_date: Option<f64>,
     pub actual_close_date: Option<f64>,
     pub status: DealStatus,
-    pub assigned_to: Option<String>,
+    /// Local profile or free-text name responsible for the deal
+    #[serde(default, alias = "assigned_to")]
+    pub owner: Option<String>,
     pub created_at: f64,
     pub updated_at: f64,
     pub activities: Vec<Activity>,
@@ -138,6 +142,9 @@ pub struct Activity {
     pub title: String,
     pub description: Option<String>,
     pub due_date: Option<f64>,
+    /// The due date is a calendar day (UTC midnight), not a time
+    #[serde(default)]
+    pub due_all_day: bool,
     pub completed_at: Option<f64>,
     pub assigned_to: Option<String>,
     pub priority: Priority,
@@ -200,12 +207,17 @@ impl Lead {
             source,
             status: LeadStatus::New,
             score: None,
-            assigned_to: None,
+            owner: None,
             created_at: timestamp,
             updated_at: timestamp,
             notes: Vec::new(),
         }
     }
+
+    pub fn with_owner(mut self, owner: Option<String>) -> Self {
+        self.owner = owner;
+        self
+    }
 }
 
 impl Contact {
@@ -245,10 +257,15 @@ impl Deal {
             expected_close_date: None,
             actual_close_date: None,
             status: DealStatus::Open,
-            assigned_to: None,
+            owner: None,
             created_at: timestamp,
             updated_at: timestamp,
             activities: Vec::new(),
         }
     }
+
+    pub fn with_owner(mut self, owner: Option<String>) -> Self {
+        self.owner = owner;
+        self
+    }
 }
@@ -0,0 +1,151 @@
+use crate::models::app::AppError;
+use crate::models::graphrag::DocumentIndex;
+use crate::storage::persistent::PersistentStore;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+pub const DOCUMENT_EXPIRY_KEY_V1: &str = "document_expiry_v1";
+
+pub const MS_PER_DAY: f64 = 86_400_000.0;
+/// Documents expiring within this many days are flagged as expiring soon
+pub const EXPIRING_SOON_DAYS: f64 = 7.0;
+
+/// Where a document stands against its expiry date
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Freshness {
+    /// No expiry date set
+    Evergreen,
+    Fresh {
+        expires_at: f64,
+    },
+    ExpiringSoon {
+        expires_at: f64,
+    },
+    Expired {
+        expires_at: f64,
+    },
+}
+
+impl Freshness {
+    pub fn is_expired(&self) -> bool {
+        matches!(self, Freshness::Expired { .. })
+    }
+}
+
+/// Optional expiry dates of uploaded files. Dates are kept per file rather than on
+/// the indexed passages so they survive reindexing and re-chunking.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct DocumentExpiry {
+    /// Uploaded file name to expiry time (ms since epoch)
+    pub expires: BTreeMap<String, f64>,
+}
+
+impl DocumentExpiry {
+    pub fn save(&self) -> Result<(), AppError> {
+        PersistentStore::write(DOCUMENT_EXPIRY_KEY_V1, self)
+    }
+
+    pub fn load() -> Result<Self, AppError> {
+        Ok(PersistentStore::read(DOCUMENT_EXPIRY_KEY_V1)?.unwrap_or_default())
+    }
+
+    pub fn expires_at(&self, file: &str) -> Option<f64> {
+        self.expires.get(file).copied()
+    }
+
+    /// Set the expiry of `file`, or clear it when `None`
+    pub fn set(&mut self, file: &str, expires_at: Option<f64>) {
+        match expires_at {
+            Some(t) => self.expires.insert(file.to_string(), t),
+            None => self.expires.remove(file),
+        };
+    }
+
+    pub fn freshness_of_file(&self, file: &str, now: f64) -> Freshness {
+        match self.expires_at(file) {
+            None => Freshness::Evergreen,
+            Some(expires_at) if expires_at <= now => Freshness::Expired { expires_at },
+            Some(expires_at) if expires_at - now <= EXPIRING_SOON_DAYS * MS_PER_DAY => {
+                Freshness::ExpiringSoon { expires_at }
+            }
+            Some(expires_at) => Freshness::Fresh { expires_at },
+        }
+    }
+
+    /// Whether an indexed passage comes from an expired file
+    pub fn is_expired(&self, doc: &DocumentIndex, now: f64) -> bool {
+        self.freshness_of_file(doc.source_file(), now).is_expired()
+    }
+
+    /// Expired files among `files`, the ones still in the knowledge base
+    pub fn expired_files<'a>(
+        &self,
+        files: impl IntoIterator<Item = &'a str>,
+        now: f64,
+    ) -> Vec<String> {
+        let mut expired: Vec<String> = files
+            .into_iter()
+            .filter(|f| self.freshness_of_file(f, now).is_expired())
+            .map(str::to_string)
+            .collect();
+        expired.sort();
+        expired.dedup();
+        expired
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::graphrag::ProcessingStatus;
+
+    fn doc(id: &str) -> DocumentIndex {
+        DocumentIndex {
+            id: id.to_string(),
+            title: id.to_string(),
+            content: String::new(),
+            file_type: "text".to_string(),
+            size_bytes: 0,
+            created_at: 0.0,
+            indexed_at: 0.0,
+            node_count: 0,
+            embedding_model: None,
+            processing_status: ProcessingStatus::Pending,
+            symbols: Vec::new(),
+            parent: None,
+        }
+    }
+
+    #[test]
+    fn test_expiry_applies_to_every_passage_of_a_file() {
+        let now = 100.0 * MS_PER_DAY;
+        let mut expiry = DocumentExpiry::default();
+        expiry.set("pricing.md", Some(now - 1.0));
+        expiry.set("schedule.md", Some(now + 3.0 * MS_PER_DAY));
+        expiry.set("handbook.md", Some(now + 30.0 * MS_PER_DAY));
+
+        assert!(expiry.is_expired(&doc("1700000000000:pricing.md#chunk=2"), now));
+        assert!(!expiry.is_expired(&doc("1700000000000:faq.md"), now));
+        assert_eq!(
+            expiry.freshness_of_file("schedule.md", now),
+            Freshness::ExpiringSoon {
+                expires_at: now + 3.0 * MS_PER_DAY
+            }
+        );
+        assert!(matches!(
+            expiry.freshness_of_file("handbook.md", now),
+            Freshness::Fresh { .. }
+        ));
+        assert_eq!(
+            expiry.expired_files(["schedule.md", "pricing.md", "pricing.md"], now),
+            vec!["pricing.md".to_string()]
+        );
+
+        // Clearing the date makes the file evergreen again
+        expiry.set("pricing.md", None);
+        assert_eq!(
+            expiry.freshness_of_file("pricing.md", now),
+            Freshness::Evergreen
+        );
+    }
+}
@@ -0,0 +1,482 @@
+use serde::{Deserialize, Serialize};
+
+/// Transformation applied to a completion before it is stored
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum PostProcessor {
+    /// Drop filler openers ("Sure!") and closers ("I hope this helps!")
+    TrimBoilerplate,
+    /// Remove a leading "Assistant:" style role label
+    StripRolePrefixes,
+    /// User-defined regex replacement (`$1` style capture references allowed)
+    RegexReplace {
+        pattern: String,
+        replacement: String,
+    },
+}
+
+impl PostProcessor {
+    pub fn label(&self) -> String {
+        match self {
+            PostProcessor::TrimBoilerplate => "Trim boilerplate".to_string(),
+            PostProcessor::StripRolePrefixes => "Strip role prefixes".to_string(),
+            PostProcessor::RegexReplace {
+                pattern,
+                replacement,
+            } => format!("/{}/ → {}", pattern, replacement),
+        }
+    }
+}
+
+/// Sampling parameters sent with each completion request
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SamplingParams {
+    pub temperature: f32,
+    pub max_tokens: u32,
+    pub frequency_penalty: f32,
+    /// Fixed seed for reproducible sampling; random when `None`
+    pub seed: Option<u64>,
+}
+
+impl Default for SamplingParams {
+    fn default() -> Self {
+        Self {
+            temperature: 0.7,
+            max_tokens: 512,
+            frequency_penalty: 0.0,
+            seed: None,
+        }
+    }
+}
+
+impl SamplingParams {
+    /// Cooler sampling with a repetition penalty, used when a completion was rejected
+    pub fn for_retry(&self) -> Self {
+        Self {
+            temperature: (self.temperature * 0.6).max(0.1),
+            frequency_penalty: (self.frequency_penalty + 0.5).min(2.0),
+            ..self.clone()
+        }
+    }
+}
+
+/// Checks a finished completion must pass before it is accepted without a retry
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CompletionValidation {
+    pub enabled: bool,
+    /// Minimum number of non-whitespace characters
+    pub min_chars: usize,
+    /// Share of repeated word trigrams above which a reply counts as a loop
+    pub max_repetition: f32,
+    /// Reject replies written in a different script than the prompt
+    pub check_language: bool,
+}
+
+impl Default for CompletionValidation {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            min_chars: 2,
+            max_repetition: 0.6,
+            check_language: true,
+        }
+    }
+}
+
+/// Length limits of the chat input, in characters
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InputLimits {
+    /// Above this the input warns and offers to save the text as a document instead
+    pub soft_chars: usize,
+    /// Above this the message cannot be sent
+    pub hard_chars: usize,
+}
+
+impl Default for InputLimits {
+    fn default() -> Self {
+        Self {
+            soft_chars: 8_000,
+            hard_chars: 32_000,
+        }
+    }
+}
+
+/// How much conversation the model sees, in estimated tokens
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ContextSettings {
+    /// Fold older turns into a summary when the prompt nears the window
+    pub summarize: bool,
+    /// Context window of the loaded model
+    pub window_tokens: u32,
+    /// Share of the window (after the reply reserve) at which summarization starts
+    pub summarize_at: f32,
+    /// Most recent messages that are always sent verbatim
+    pub keep_recent: usize,
+}
+
+impl Default for ContextSettings {
+    fn default() -> Self {
+        Self {
+            summarize: true,
+            window_tokens: 4096,
+            summarize_at: 0.8,
+            keep_recent: 6,
+        }
+    }
+}
+
+/// Which part of a conversation's history is sent with each prompt
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum HistoryPolicy {
+    /// Everything that fits, summarizing older turns per the global `ContextSettings`
+    #[default]
+    Auto,
+    /// Only the last `turns` exchanges
+    LastTurns { turns: usize },
+    /// The most recent messages that fit in `tokens`
+    TokenBudget { tokens: u32 },
+    /// A summary of everything before the last `turns` exchanges, then those verbatim
+    SummaryPlusRecent { turns: usize },
+}
+
+impl HistoryPolicy {
+    pub fn label(&self) -> String {
+        match self {
+            HistoryPolicy::Auto => "Automatic".to_string(),
+            HistoryPolicy::LastTurns { turns } => format!("Last {} turns", turns),
+            HistoryPolicy::TokenBudget { tokens } => format!("Up to {} tokens", tokens),
+            HistoryPolicy::SummaryPlusRecent { turns } => {
+                format!("Summary + last {} turns", turns)
+            }
+        }
+    }
+}
+
+/// Current date and time given to the model as a system message
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DateTimeSettings {
+    pub inject: bool,
+    pub include_time: bool,
+    /// IANA timezone, e.g. `America/New_York`; `None` uses the browser's
+    pub timezone: Option<String>,
+}
+
+impl Default for DateTimeSettings {
+    fn default() -> Self {
+        Self {
+            inject: true,
+            include_time: true,
+            timezone: None,
+        }
+    }
+}
+
+/// Hints shown while composing a message
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SuggestionSettings {
+    /// Point to past conversations resembling the first message of a new chat
+    pub similar_conversations: bool,
+}
+
+impl Default for SuggestionSettings {
+    fn default() -> Self {
+        Self {
+            similar_conversations: true,
+        }
+    }
+}
+
+/// How a reply's confidence is combined from its signals; weights are relative and
+/// renormalized over the signals a reply has
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConfidenceSettings {
+    /// Relevance of the passages the reply was grounded on
+    pub retrieval_weight: f32,
+    /// Completion checks: validation retries and JSON schema repairs
+    pub verification_weight: f32,
+    /// Hedging or refusal in the reply itself
+    pub self_assessment_weight: f32,
+    /// Score at or above which a reply is high confidence
+    pub high_at: f32,
+    /// Score at or above which a reply is medium confidence
+    pub medium_at: f32,
+}
+
+impl Default for ConfidenceSettings {
+    fn default() -> Self {
+        Self {
+            retrieval_weight: 0.5,
+            verification_weight: 0.2,
+            self_assessment_weight: 0.3,
+            high_at: 0.7,
+            medium_at: 0.4,
+        }
+    }
+}
+
+/// Shape requested for the model's replies in a conversation
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    /// A JSON value, matching `schema` (JSON Schema source) when one is given
+    Json { schema: Option<String> },
+}
+
+impl OutputFormat {
+    pub fn is_json(&self) -> bool {
+        matches!(self, OutputFormat::Json { .. })
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            OutputFormat::Text => "Text",
+            OutputFormat::Json { schema: None } => "JSON",
+            OutputFormat::Json { schema: Some(_) } => "JSON (schema)",
+        }
+    }
+}
+
+/// A way of answering the user can ask for in a conversation; cues come in opposing
+/// pairs and only one of a pair is in effect at a time
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StyleCue {
+    Shorter,
+    Longer,
+    Bullets,
+    Prose,
+    Simpler,
+    Technical,
+}
+
+impl StyleCue {
+    pub const ALL: [StyleCue; 6] = [
+        StyleCue::Shorter,
+        StyleCue::Longer,
+        StyleCue::Bullets,
+        StyleCue::Prose,
+        StyleCue::Simpler,
+        StyleCue::Technical,
+    ];
+
+    pub fn opposite(self) -> Self {
+        match self {
+            StyleCue::Shorter => StyleCue::Longer,
+            StyleCue::Longer => StyleCue::Shorter,
+            StyleCue::Bullets => StyleCue::Prose,
+            StyleCue::Prose => StyleCue::Bullets,
+            StyleCue::Simpler => StyleCue::Technical,
+            StyleCue::Technical => StyleCue::Simpler,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            StyleCue::Shorter => "Short answers",
+            StyleCue::Longer => "Detailed answers",
+            StyleCue::Bullets => "Bullet points",
+            StyleCue::Prose => "Full sentences",
+            StyleCue::Simpler => "Plain language",
+            StyleCue::Technical => "Technical depth",
+        }
+    }
+
+    /// Sentence added to the system prompt while the cue is a preference
+    pub fn instruction(&self) -> &'static str {
+        match self {
+            StyleCue::Shorter => "Keep answers short and to the point.",
+            StyleCue::Longer => "Give thorough, detailed answers.",
+            StyleCue::Bullets => "Format answers as bullet points where possible.",
+            StyleCue::Prose => "Write answers as prose paragraphs, not bullet lists.",
+            StyleCue::Simpler => "Use plain, non-technical language.",
+            StyleCue::Technical => "Include technical details and precise terminology.",
+        }
+    }
+}
+
+/// Answer preferences of one conversation: learned from what the user keeps asking for
+/// (see `utils::answer_style`) and editable in the conversation settings
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AnswerStyle {
+    /// Cues in effect, at most one of each opposing pair
+    pub preferences: Vec<StyleCue>,
+    /// Times a cue was asked for before becoming a preference
+    pub pending: Vec<(StyleCue, u32)>,
+    /// Instructions written by the user
+    pub notes: String,
+}
+
+impl AnswerStyle {
+    pub fn is_empty(&self) -> bool {
+        self.preferences.is_empty() && self.notes.trim().is_empty()
+    }
+
+    pub fn has(&self, cue: StyleCue) -> bool {
+        self.preferences.contains(&cue)
+    }
+
+    /// Put `cue` in effect, replacing its opposite
+    pub fn prefer(&mut self, cue: StyleCue) {
+        self.preferences.retain(|c| *c != cue.opposite());
+        if !self.preferences.contains(&cue) {
+            self.preferences.push(cue);
+        }
+        self.pending
+            .retain(|(c, _)| *c != cue && *c != cue.opposite());
+    }
+
+    pub fn forget(&mut self, cue: StyleCue) {
+        self.preferences.retain(|c| *c != cue);
+        self.pending.retain(|(c, _)| *c != cue);
+    }
+}
+
+/// Tools the model may call while answering (see `utils::tools`)
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ToolSettings {
+    pub enabled: bool,
+    /// Tool call rounds allowed before the model must answer
+    pub max_rounds: u8,
+    /// Names of tools withheld from the model
+    pub disabled: Vec<String>,
+}
+
+impl Default for ToolSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_rounds: 3,
+            disabled: Vec::new(),
+        }
+    }
+}
+
+/// Where an input length falls relative to `InputLimits`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InputLength {
+    Ok,
+    /// Within 10% of the soft limit
+    NearSoft,
+    OverSoft,
+    OverHard,
+}
+
+impl InputLimits {
+    pub fn classify(&self, chars: usize) -> InputLength {
+        let hard = self.hard_chars.max(self.soft_chars);
+        if chars > hard {
+            InputLength::OverHard
+        } else if chars > self.soft_chars {
+            InputLength::OverSoft
+        } else if chars * 10 >= self.soft_chars * 9 {
+            InputLength::NearSoft
+        } else {
+            InputLength::Ok
+        }
+    }
+}
+
+/// Reason a completion was rejected by `CompletionValidation`
+#[derive(Clone, Debug, PartialEq)]
+pub enum CompletionIssue {
+    Empty,
+    TooShort { chars: usize },
+    Repetitive { ratio: f32 },
+    LanguageMismatch,
+}
+
+impl CompletionIssue {
+    pub fn describe(&self) -> String {
+        match self {
+            CompletionIssue::Empty => "empty reply".to_string(),
+            CompletionIssue::TooShort { chars } => format!("reply too short ({} chars)", chars),
+            CompletionIssue::Repetitive { ratio } => {
+                format!("repetitive reply ({:.0}% repeated)", ratio * 100.0)
+            }
+            CompletionIssue::LanguageMismatch => "reply not in the prompt's language".to_string(),
+        }
+    }
+}
+
+/// Global generation settings; stop sequences are merged with per-conversation ones
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct GenerationSettings {
+    #[serde(default)]
+    pub stop_sequences: Vec<String>,
+    #[serde(default)]
+    pub post_processors: Vec<PostProcessor>,
+    #[serde(default)]
+    pub sampling: SamplingParams,
+    #[serde(default)]
+    pub validation: CompletionValidation,
+    #[serde(default)]
+    pub input_limits: InputLimits,
+    #[serde(default)]
+    pub context: ContextSettings,
+    #[serde(default)]
+    pub datetime: DateTimeSettings,
+    #[serde(default)]
+    pub tools: ToolSettings,
+    #[serde(default)]
+    pub suggestions: SuggestionSettings,
+    #[serde(default)]
+    pub confidence: ConfidenceSettings,
+    /// Reply format of the current request, taken from the conversation; never saved
+    #[serde(skip)]
+    pub output_format: OutputFormat,
+}
+
+impl GenerationSettings {
+    pub fn has_processor(&self, processor: &PostProcessor) -> bool {
+        self.post_processors.contains(processor)
+    }
+
+    /// Global stops followed by the conversation's own, without empties or duplicates
+    pub fn with_conversation_stops(&self, conversation_stops: &[String]) -> Self {
+        let mut stops: Vec<String> = Vec::new();
+        for s in self.stop_sequences.iter().chain(conversation_stops) {
+            if !s.is_empty() && !stops.contains(s) {
+                stops.push(s.clone());
+            }
+        }
+        Self {
+            stop_sequences: stops,
+            ..self.clone()
+        }
+    }
+
+    /// Same settings producing `format`. JSON replies skip post-processing, tools and
+    /// the completion checks; they are checked against their schema instead.
+    pub fn with_output_format(&self, format: &OutputFormat) -> Self {
+        let mut settings = Self {
+            output_format: format.clone(),
+            ..self.clone()
+        };
+        if format.is_json() {
+            settings.post_processors.clear();
+            settings.tools.enabled = false;
+            settings.validation.enabled = false;
+        }
+        settings
+    }
+
+    /// Same settings with retry sampling
+    pub fn for_retry(&self) -> Self {
+        Self {
+            sampling: self.sampling.for_retry(),
+            ..self.clone()
+        }
+    }
+}
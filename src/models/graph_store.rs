@@ -1,5 +1,5 @@
 use crate::models::app::AppError;
-use crate::utils::storage::StorageUtils;
+use crate::storage::persistent::PersistentStore;
 use serde::{Deserialize, Serialize};
 
 pub const GRAPH_STORE_KEY_V1: &str = "graphrag_graph_store_v1";
@@ -13,6 +13,16 @@ pub struct GraphNode {
     pub metadata: serde_json::Value,
 }
 
+impl GraphNode {
+    /// Community recorded by the last community detection run
+    pub fn community(&self) -> Option<usize> {
+        self.metadata
+            .get("community")
+            .and_then(|c| c.as_u64())
+            .map(|c| c as usize)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct GraphEdge {
     pub id: String,
@@ -45,10 +55,27 @@ impl GraphStore {
         self.edges.push(edge);
     }
     pub fn save(&self) -> Result<(), AppError> {
-        StorageUtils::store_local(GRAPH_STORE_KEY_V1, self)
+        PersistentStore::write(GRAPH_STORE_KEY_V1, self)
     }
     pub fn load() -> Result<Self, AppError> {
-        Ok(StorageUtils::retrieve_local(GRAPH_STORE_KEY_V1)?.unwrap_or_default())
+        Ok(PersistentStore::read(GRAPH_STORE_KEY_V1)?.unwrap_or_default())
+    }
+
+    /// Community recorded on a node by the last community detection run
+    pub fn community_of(&self, node_id: &str) -> Option<usize> {
+        self.nodes
+            .iter()
+            .find(|n| n.id == node_id)
+            .and_then(|n| n.community())
+    }
+
+    /// Community of each document node, keyed by source document id
+    pub fn document_communities(&self) -> std::collections::HashMap<String, usize> {
+        self.nodes
+            .iter()
+            .filter(|n| n.node_type == "document")
+            .filter_map(|n| Some((n.source_document_id.clone()?, n.community()?)))
+            .collect()
     }
 
     /// Remove all nodes and edges associated with a given document id.
@@ -86,3 +113,839 @@ impl GraphStore {
         });
     }
 }
+
+/// Standard formats the graph can be downloaded in
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GraphFormat {
+    /// XML graph format read by Gephi, yEd, NetworkX and Cytoscape
+    GraphMl,
+    /// Gephi's native XML format
+    Gexf,
+    /// Neo4j Cypher statements recreating the graph
+    Cypher,
+    /// The store as it is persisted
+    Json,
+}
+
+impl GraphFormat {
+    pub const ALL: [GraphFormat; 4] = [
+        GraphFormat::GraphMl,
+        GraphFormat::Gexf,
+        GraphFormat::Cypher,
+        GraphFormat::Json,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            GraphFormat::GraphMl => "GraphML",
+            GraphFormat::Gexf => "GEXF (Gephi)",
+            GraphFormat::Cypher => "Cypher (Neo4j)",
+            GraphFormat::Json => "JSON",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            GraphFormat::GraphMl => "graphml",
+            GraphFormat::Gexf => "gexf",
+            GraphFormat::Cypher => "cypher",
+            GraphFormat::Json => "json",
+        }
+    }
+}
+
+impl GraphStore {
+    /// The whole graph in `format`
+    pub fn export(&self, format: GraphFormat) -> String {
+        match format {
+            GraphFormat::GraphMl => self.to_graphml(),
+            GraphFormat::Gexf => self.to_gexf(),
+            GraphFormat::Cypher => self.to_cypher(),
+            GraphFormat::Json => serde_json::to_string_pretty(self).unwrap_or_default(),
+        }
+    }
+
+    /// GraphML with the node type, source document, community and the remaining
+    /// metadata (as JSON) as attributes
+    pub fn to_graphml(&self) -> String {
+        let mut out = String::from(concat!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+            "<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n",
+            "  <key id=\"label\" for=\"node\" attr.name=\"label\" attr.type=\"string\"/>\n",
+            "  <key id=\"type\" for=\"node\" attr.name=\"type\" attr.type=\"string\"/>\n",
+            "  <key id=\"source\" for=\"node\" attr.name=\"source_document\" attr.type=\"string\"/>\n",
+            "  <key id=\"community\" for=\"node\" attr.name=\"community\" attr.type=\"int\"/>\n",
+            "  <key id=\"nmeta\" for=\"node\" attr.name=\"metadata\" attr.type=\"string\"/>\n",
+            "  <key id=\"relation\" for=\"edge\" attr.name=\"relation\" attr.type=\"string\"/>\n",
+            "  <key id=\"weight\" for=\"edge\" attr.name=\"weight\" attr.type=\"double\"/>\n",
+            "  <key id=\"emeta\" for=\"edge\" attr.name=\"metadata\" attr.type=\"string\"/>\n",
+            "  <graph id=\"knowledge\" edgedefault=\"directed\">\n",
+        ));
+        for n in &self.nodes {
+            out.push_str(&format!("    <node id=\"{}\">\n", xml_escape(&n.id)));
+            if let Some(label) = &n.label {
+                out.push_str(&graphml_data("label", label));
+            }
+            out.push_str(&graphml_data("type", &n.node_type));
+            if let Some(source) = &n.source_document_id {
+                out.push_str(&graphml_data("source", source));
+            }
+            if let Some(community) = n.community() {
+                out.push_str(&graphml_data("community", &community.to_string()));
+            }
+            if let Some(metadata) = remaining_metadata(&n.metadata) {
+                out.push_str(&graphml_data("nmeta", &metadata));
+            }
+            out.push_str("    </node>\n");
+        }
+        for e in &self.edges {
+            out.push_str(&format!(
+                "    <edge id=\"{}\" source=\"{}\" target=\"{}\">\n",
+                xml_escape(&e.id),
+                xml_escape(&e.from),
+                xml_escape(&e.to)
+            ));
+            out.push_str(&graphml_data("relation", &e.relation));
+            out.push_str(&graphml_data("weight", &e.weight.to_string()));
+            if let Some(metadata) = remaining_metadata(&e.metadata) {
+                out.push_str(&graphml_data("emeta", &metadata));
+            }
+            out.push_str("    </edge>\n");
+        }
+        out.push_str("  </graph>\n</graphml>\n");
+        out
+    }
+
+    /// GEXF 1.3; relations become edge labels and the node type, source document,
+    /// community and metadata become attributes
+    pub fn to_gexf(&self) -> String {
+        let mut out = String::from(concat!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+            "<gexf xmlns=\"http://gexf.net/1.3\" version=\"1.3\">\n",
+            "  <graph defaultedgetype=\"directed\" mode=\"static\">\n",
+            "    <attributes class=\"node\">\n",
+            "      <attribute id=\"type\" title=\"type\" type=\"string\"/>\n",
+            "      <attribute id=\"source\" title=\"source_document\" type=\"string\"/>\n",
+            "      <attribute id=\"community\" title=\"community\" type=\"integer\"/>\n",
+            "      <attribute id=\"metadata\" title=\"metadata\" type=\"string\"/>\n",
+            "    </attributes>\n",
+            "    <attributes class=\"edge\">\n",
+            "      <attribute id=\"metadata\" title=\"metadata\" type=\"string\"/>\n",
+            "    </attributes>\n",
+            "    <nodes>\n",
+        ));
+        for n in &self.nodes {
+            out.push_str(&format!(
+                "      <node id=\"{}\" label=\"{}\">\n        <attvalues>\n",
+                xml_escape(&n.id),
+                xml_escape(n.label.as_deref().unwrap_or(&n.id))
+            ));
+            out.push_str(&gexf_value("type", &n.node_type));
+            if let Some(source) = &n.source_document_id {
+                out.push_str(&gexf_value("source", source));
+            }
+            if let Some(community) = n.community() {
+                out.push_str(&gexf_value("community", &community.to_string()));
+            }
+            if let Some(metadata) = remaining_metadata(&n.metadata) {
+                out.push_str(&gexf_value("metadata", &metadata));
+            }
+            out.push_str("        </attvalues>\n      </node>\n");
+        }
+        out.push_str("    </nodes>\n    <edges>\n");
+        for e in &self.edges {
+            out.push_str(&format!(
+                "      <edge id=\"{}\" source=\"{}\" target=\"{}\" label=\"{}\" weight=\"{}\"",
+                xml_escape(&e.id),
+                xml_escape(&e.from),
+                xml_escape(&e.to),
+                xml_escape(&e.relation),
+                e.weight
+            ));
+            match remaining_metadata(&e.metadata) {
+                Some(metadata) => out.push_str(&format!(
+                    ">\n        <attvalues>\n{}        </attvalues>\n      </edge>\n",
+                    gexf_value("metadata", &metadata)
+                )),
+                None => out.push_str("/>\n"),
+            }
+        }
+        out.push_str("    </edges>\n  </graph>\n</gexf>\n");
+        out
+    }
+
+    /// Cypher statements for Neo4j. Every node gets the `Node` label, indexed on `id`,
+    /// plus one for its type; relations become relationship types.
+    pub fn to_cypher(&self) -> String {
+        let mut out =
+            String::from("CREATE INDEX node_id IF NOT EXISTS FOR (n:Node) ON (n.id);\n\n");
+        for n in &self.nodes {
+            let mut props = vec![format!("id: {}", cypher_string(&n.id))];
+            if let Some(label) = &n.label {
+                props.push(format!("label: {}", cypher_string(label)));
+            }
+            if let Some(source) = &n.source_document_id {
+                props.push(format!("source_document: {}", cypher_string(source)));
+            }
+            if let Some(community) = n.community() {
+                props.push(format!("community: {}", community));
+            }
+            if let Some(metadata) = remaining_metadata(&n.metadata) {
+                props.push(format!("metadata: {}", cypher_string(&metadata)));
+            }
+            out.push_str(&format!(
+                "CREATE (:Node:{} {{{}}});\n",
+                cypher_name(&n.node_type, false),
+                props.join(", ")
+            ));
+        }
+        if !self.edges.is_empty() {
+            out.push('\n');
+        }
+        for e in &self.edges {
+            let mut props = vec![
+                format!("id: {}", cypher_string(&e.id)),
+                format!("weight: {}", e.weight),
+            ];
+            if let Some(metadata) = remaining_metadata(&e.metadata) {
+                props.push(format!("metadata: {}", cypher_string(&metadata)));
+            }
+            out.push_str(&format!(
+                "MATCH (a:Node {{id: {}}}), (b:Node {{id: {}}}) CREATE (a)-[:{} {{{}}}]->(b);\n",
+                cypher_string(&e.from),
+                cypher_string(&e.to),
+                cypher_name(&e.relation, true),
+                props.join(", ")
+            ));
+        }
+        out
+    }
+}
+
+// Metadata other than the community, which is exported as its own attribute
+fn remaining_metadata(metadata: &serde_json::Value) -> Option<String> {
+    let mut metadata = metadata.clone();
+    if let Some(fields) = metadata.as_object_mut() {
+        fields.remove("community");
+        if fields.is_empty() {
+            return None;
+        }
+    }
+    (!metadata.is_null()).then(|| metadata.to_string())
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn graphml_data(key: &str, value: &str) -> String {
+    format!("      <data key=\"{}\">{}</data>\n", key, xml_escape(value))
+}
+
+fn gexf_value(attribute: &str, value: &str) -> String {
+    format!(
+        "          <attvalue for=\"{}\" value=\"{}\"/>\n",
+        attribute,
+        xml_escape(value)
+    )
+}
+
+fn cypher_string(text: &str) -> String {
+    format!("'{}'", text.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+// Node label (`Document`) or relationship type (`WORKS_AT`); other characters are
+// dropped, so the name never needs quoting
+fn cypher_name(name: &str, relationship: bool) -> String {
+    let words: Vec<&str> = name
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .collect();
+    let name = if relationship {
+        words
+            .iter()
+            .map(|w| w.to_uppercase())
+            .collect::<Vec<_>>()
+            .join("_")
+    } else {
+        words
+            .iter()
+            .map(|w| {
+                let mut chars = w.chars();
+                chars.next().map_or(String::new(), |c| {
+                    c.to_ascii_uppercase().to_string() + &chars.as_str().to_lowercase()
+                })
+            })
+            .collect()
+    };
+    match name.chars().next() {
+        Some(c) if c.is_ascii_alphabetic() => name,
+        Some(_) => format!("T{}", name),
+        None if relationship => "RELATED_TO".to_string(),
+        None => "Node".to_string(),
+    }
+}
+
+/// How an imported graph is combined with the current one
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GraphImportMode {
+    /// Add new nodes and edges and update those with the same id
+    Merge,
+    /// Drop the current graph first
+    Replace,
+}
+
+/// What an import changed
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GraphImportSummary {
+    pub nodes_added: usize,
+    pub nodes_updated: usize,
+    pub edges_added: usize,
+    pub edges_updated: usize,
+}
+
+impl GraphStore {
+    /// Read a graph from GraphML or JSON: this store's own format, or node-link JSON
+    /// with `nodes` and `edges`/`links` as written by NetworkX or D3. Every problem
+    /// found is returned, not only the first.
+    pub fn import(text: &str) -> Result<GraphStore, Vec<String>> {
+        let text = text.trim_start_matches('\u{feff}').trim();
+        let store = if text.starts_with('<') {
+            parse_graphml(text)?
+        } else {
+            let value: serde_json::Value = serde_json::from_str(text)
+                .map_err(|e| vec![format!("not GraphML and not valid JSON: {}", e)])?;
+            parse_node_link(&value)?
+        };
+        let problems = store.validate();
+        if problems.is_empty() {
+            Ok(store)
+        } else {
+            Err(problems)
+        }
+    }
+
+    /// Problems that make the graph unusable: missing or repeated ids, edges whose
+    /// endpoints are not nodes and weights that are not finite
+    pub fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        let mut node_ids = std::collections::HashSet::new();
+        for (i, n) in self.nodes.iter().enumerate() {
+            if n.id.trim().is_empty() {
+                problems.push(format!("node {} has no id", i + 1));
+            } else if !node_ids.insert(n.id.as_str()) {
+                problems.push(format!("node id \"{}\" is used twice", n.id));
+            }
+        }
+        let mut edge_ids = std::collections::HashSet::new();
+        for e in &self.edges {
+            if !edge_ids.insert(e.id.as_str()) {
+                problems.push(format!("edge id \"{}\" is used twice", e.id));
+            }
+            for end in [&e.from, &e.to] {
+                if !node_ids.contains(end.as_str()) {
+                    problems.push(format!(
+                        "edge \"{}\" points to unknown node \"{}\"",
+                        e.id, end
+                    ));
+                }
+            }
+            if !e.weight.is_finite() {
+                problems.push(format!("edge \"{}\" has an invalid weight", e.id));
+            }
+        }
+        problems
+    }
+
+    /// Add the nodes and edges of `other`; those with an id already present replace
+    /// it, keeping metadata fields the import does not set
+    pub fn merge_from(&mut self, other: GraphStore) -> GraphImportSummary {
+        let mut summary = GraphImportSummary::default();
+        for node in other.nodes {
+            match self.nodes.iter_mut().find(|n| n.id == node.id) {
+                Some(existing) => {
+                    let mut metadata = std::mem::take(&mut existing.metadata);
+                    merge_metadata(&mut metadata, node.metadata.clone());
+                    *existing = GraphNode {
+                        label: node.label.or(existing.label.take()),
+                        source_document_id: node
+                            .source_document_id
+                            .or(existing.source_document_id.take()),
+                        metadata,
+                        ..node
+                    };
+                    summary.nodes_updated += 1;
+                }
+                None => {
+                    self.nodes.push(node);
+                    summary.nodes_added += 1;
+                }
+            }
+        }
+        for edge in other.edges {
+            match self.edges.iter_mut().find(|e| e.id == edge.id) {
+                Some(existing) => {
+                    *existing = edge;
+                    summary.edges_updated += 1;
+                }
+                None => {
+                    self.edges.push(edge);
+                    summary.edges_added += 1;
+                }
+            }
+        }
+        summary
+    }
+}
+
+fn merge_metadata(into: &mut serde_json::Value, from: serde_json::Value) {
+    match (into.as_object_mut(), from) {
+        (Some(fields), serde_json::Value::Object(new)) => fields.extend(new),
+        (_, serde_json::Value::Null) => {}
+        (_, from) => *into = from,
+    }
+}
+
+// Node-link JSON; this store's own fields are read first, common alternatives after
+fn parse_node_link(value: &serde_json::Value) -> Result<GraphStore, Vec<String>> {
+    use serde_json::Value;
+    let Some(nodes) = value.get("nodes").and_then(Value::as_array) else {
+        return Err(vec!["the JSON has no \"nodes\" list".to_string()]);
+    };
+    let edges = ["edges", "links"]
+        .iter()
+        .find_map(|k| value.get(*k).and_then(Value::as_array))
+        .cloned()
+        .unwrap_or_default();
+    let text = |v: &Value| match v {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    };
+    let field = |v: &Value, names: &[&str]| names.iter().find_map(|k| v.get(*k).and_then(text));
+
+    let mut problems = Vec::new();
+    let mut store = GraphStore::new();
+    for (i, n) in nodes.iter().enumerate() {
+        let Some(fields) = n.as_object() else {
+            problems.push(format!("node {} is not an object", i + 1));
+            continue;
+        };
+        let mut metadata = serde_json::Map::new();
+        for (key, value) in fields {
+            let known = [
+                "id",
+                "label",
+                "name",
+                "node_type",
+                "type",
+                "source_document_id",
+                "source_document",
+                "metadata",
+            ];
+            if !known.contains(&key.as_str()) {
+                metadata.insert(key.clone(), value.clone());
+            }
+        }
+        if let Some(Value::Object(extra)) = n.get("metadata") {
+            metadata.extend(extra.clone());
+        }
+        store.add_node(GraphNode {
+            id: field(n, &["id"]).unwrap_or_default(),
+            label: field(n, &["label", "name"]),
+            node_type: field(n, &["node_type", "type"]).unwrap_or_else(|| "entity".to_string()),
+            source_document_id: field(n, &["source_document_id", "source_document"]),
+            metadata: Value::Object(metadata),
+        });
+    }
+    for (i, e) in edges.iter().enumerate() {
+        let Some(fields) = e.as_object() else {
+            problems.push(format!("edge {} is not an object", i + 1));
+            continue;
+        };
+        let (Some(from), Some(to)) = (field(e, &["from", "source"]), field(e, &["to", "target"]))
+        else {
+            problems.push(format!("edge {} has no source or target", i + 1));
+            continue;
+        };
+        let weight = match e.get("weight") {
+            None | Some(Value::Null) => 1.0,
+            Some(w) => match w.as_f64() {
+                Some(w) => w as f32,
+                None => {
+                    problems.push(format!("edge {} has a non-numeric weight", i + 1));
+                    continue;
+                }
+            },
+        };
+        let mut metadata = serde_json::Map::new();
+        for (key, value) in fields {
+            let known = [
+                "id", "from", "source", "to", "target", "relation", "label", "type", "weight",
+                "metadata", "key",
+            ];
+            if !known.contains(&key.as_str()) {
+                metadata.insert(key.clone(), value.clone());
+            }
+        }
+        if let Some(Value::Object(extra)) = e.get("metadata") {
+            metadata.extend(extra.clone());
+        }
+        let relation =
+            field(e, &["relation", "label", "type"]).unwrap_or_else(|| "related_to".to_string());
+        store.add_edge(GraphEdge {
+            id: field(e, &["id"])
+                .unwrap_or_else(|| format!("e:{}:{}->{}#{}", relation, from, to, i)),
+            from,
+            to,
+            relation,
+            weight,
+            metadata: Value::Object(metadata),
+        });
+    }
+    if problems.is_empty() {
+        Ok(store)
+    } else {
+        Err(problems)
+    }
+}
+
+// GraphML read with patterns rather than a full XML parser: keys, nodes, edges and
+// their data values, which covers what graph tools write
+fn parse_graphml(xml: &str) -> Result<GraphStore, Vec<String>> {
+    use regex::Regex;
+    use serde_json::{json, Value};
+    if !xml.contains("<graphml") {
+        return Err(vec!["the XML is not a GraphML document".to_string()]);
+    }
+    let attributes_re =
+        Regex::new(r#"([\w.:-]+)\s*=\s*(?:"([^"]*)"|'([^']*)')"#).expect("valid attribute regex");
+    let attributes = |tag: &str| -> std::collections::HashMap<String, String> {
+        attributes_re
+            .captures_iter(tag)
+            .map(|c| {
+                let value = c.get(2).or(c.get(3)).map_or("", |m| m.as_str());
+                (c[1].to_string(), xml_unescape(value))
+            })
+            .collect()
+    };
+    // Key id -> (attribute name, type)
+    let keys: std::collections::HashMap<String, (String, String)> = Regex::new(r"<key\b([^>]*)>")
+        .expect("valid key regex")
+        .captures_iter(xml)
+        .filter_map(|c| {
+            let attrs = attributes(&c[1]);
+            let id = attrs.get("id")?.clone();
+            let name = attrs
+                .get("attr.name")
+                .cloned()
+                .unwrap_or_else(|| id.clone());
+            let kind = attrs.get("attr.type").cloned().unwrap_or_default();
+            Some((id, (name, kind)))
+        })
+        .collect();
+    let data_re = Regex::new(r"(?s)<data\b([^>]*)>(.*?)</data>").expect("valid data regex");
+    // Data values of an element as (attribute name, typed value)
+    let data = |body: &str| -> Vec<(String, Value)> {
+        data_re
+            .captures_iter(body)
+            .filter_map(|c| {
+                let key = attributes(&c[1]).get("key")?.clone();
+                let (name, kind) = keys.get(&key).cloned().unwrap_or((key, String::new()));
+                let raw = xml_unescape(c[2].trim());
+                let value = match kind.as_str() {
+                    "int" | "long" => raw.parse::<i64>().map(Value::from).ok(),
+                    "float" | "double" => raw.parse::<f64>().map(Value::from).ok(),
+                    "boolean" => raw.parse::<bool>().map(Value::from).ok(),
+                    _ => None,
+                }
+                .unwrap_or(Value::String(raw));
+                Some((name, value))
+            })
+            .collect()
+    };
+    let as_text = |v: &Value| match v {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    };
+
+    let mut problems = Vec::new();
+    let mut store = GraphStore::new();
+    let node_re = Regex::new(r"(?s)<node\b([^>]*?)(?:/>|>(.*?)</node>)").expect("valid node regex");
+    for (i, c) in node_re.captures_iter(xml).enumerate() {
+        let attrs = attributes(&c[1]);
+        let mut node = GraphNode {
+            id: attrs.get("id").cloned().unwrap_or_default(),
+            label: None,
+            node_type: "entity".to_string(),
+            source_document_id: None,
+            metadata: json!({}),
+        };
+        for (name, value) in data(c.get(2).map_or("", |m| m.as_str())) {
+            match name.as_str() {
+                "label" | "name" => node.label = Some(as_text(&value)),
+                "type" | "node_type" => node.node_type = as_text(&value),
+                "source_document" | "source_document_id" => {
+                    node.source_document_id = Some(as_text(&value))
+                }
+                "metadata" => match serde_json::from_str::<Value>(&as_text(&value)) {
+                    Ok(extra @ Value::Object(_)) => merge_metadata(&mut node.metadata, extra),
+                    _ => problems.push(format!(
+                        "node {} has metadata that is not a JSON object",
+                        i + 1
+                    )),
+                },
+                _ => {
+                    node.metadata[name] = value;
+                }
+            }
+        }
+        store.add_node(node);
+    }
+    let edge_re = Regex::new(r"(?s)<edge\b([^>]*?)(?:/>|>(.*?)</edge>)").expect("valid edge regex");
+    for (i, c) in edge_re.captures_iter(xml).enumerate() {
+        let attrs = attributes(&c[1]);
+        let (Some(from), Some(to)) = (attrs.get("source"), attrs.get("target")) else {
+            problems.push(format!("edge {} has no source or target", i + 1));
+            continue;
+        };
+        let mut edge = GraphEdge {
+            id: attrs.get("id").cloned().unwrap_or_default(),
+            from: from.clone(),
+            to: to.clone(),
+            relation: "related_to".to_string(),
+            weight: 1.0,
+            metadata: json!({}),
+        };
+        for (name, value) in data(c.get(2).map_or("", |m| m.as_str())) {
+            match name.as_str() {
+                "relation" | "label" | "type" => edge.relation = as_text(&value),
+                "weight" => match as_text(&value).parse::<f32>() {
+                    Ok(w) => edge.weight = w,
+                    Err(_) => problems.push(format!("edge {} has a non-numeric weight", i + 1)),
+                },
+                "metadata" => match serde_json::from_str::<Value>(&as_text(&value)) {
+                    Ok(extra @ Value::Object(_)) => merge_metadata(&mut edge.metadata, extra),
+                    _ => problems.push(format!(
+                        "edge {} has metadata that is not a JSON object",
+                        i + 1
+                    )),
+                },
+                _ => {
+                    edge.metadata[name] = value;
+                }
+            }
+        }
+        if edge.id.is_empty() {
+            edge.id = format!("e:{}:{}->{}#{}", edge.relation, edge.from, edge.to, i);
+        }
+        store.add_edge(edge);
+    }
+    if problems.is_empty() {
+        Ok(store)
+    } else {
+        Err(problems)
+    }
+}
+
+fn xml_unescape(text: &str) -> String {
+    if let Some(raw) = text
+        .strip_prefix("<![CDATA[")
+        .and_then(|t| t.strip_suffix("]]>"))
+    {
+        return raw.to_string();
+    }
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        out.push_str(&rest[..start]);
+        let tail = &rest[start..];
+        let decoded = tail.find(';').filter(|&end| end <= 10).and_then(|end| {
+            let entity = &tail[1..end];
+            let ch = match entity {
+                "amp" => '&',
+                "lt" => '<',
+                "gt" => '>',
+                "quot" => '"',
+                "apos" => '\'',
+                _ => {
+                    let code = if let Some(hex) = entity.strip_prefix("#x") {
+                        u32::from_str_radix(hex, 16).ok()
+                    } else {
+                        entity.strip_prefix('#').and_then(|d| d.parse().ok())
+                    };
+                    char::from_u32(code?)?
+                }
+            };
+            Some((ch, end + 1))
+        });
+        match decoded {
+            Some((ch, len)) => {
+                out.push(ch);
+                rest = &tail[len..];
+            }
+            None => {
+                out.push('&');
+                rest = &tail[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn sample() -> GraphStore {
+        let mut store = GraphStore::new();
+        store.add_node(GraphNode {
+            id: "doc:d1".to_string(),
+            label: Some("R&D <notes>.md".to_string()),
+            node_type: "document".to_string(),
+            source_document_id: Some("d1".to_string()),
+            metadata: json!({"community": 2}),
+        });
+        store.add_node(GraphNode {
+            id: "ent:O'Brien".to_string(),
+            label: Some("O'Brien".to_string()),
+            node_type: "entity".to_string(),
+            source_document_id: None,
+            metadata: json!({"aliases": ["O'Brien"]}),
+        });
+        store.add_edge(GraphEdge {
+            id: "e1".to_string(),
+            from: "doc:d1".to_string(),
+            to: "ent:O'Brien".to_string(),
+            relation: "works at".to_string(),
+            weight: 0.5,
+            metadata: json!({}),
+        });
+        store
+    }
+
+    #[test]
+    fn test_xml_exports_escape_and_keep_attributes() {
+        let graphml = sample().to_graphml();
+        assert!(graphml.contains("<node id=\"doc:d1\">"));
+        assert!(graphml.contains("<data key=\"label\">R&amp;D &lt;notes&gt;.md</data>"));
+        assert!(graphml.contains("<data key=\"community\">2</data>"));
+        assert!(graphml
+            .contains("<data key=\"nmeta\">{&quot;aliases&quot;:[&quot;O'Brien&quot;]}</data>"));
+        assert!(graphml.contains("<edge id=\"e1\" source=\"doc:d1\" target=\"ent:O'Brien\">"));
+        assert!(graphml.contains("<data key=\"weight\">0.5</data>"));
+        assert!(!graphml.contains("emeta\">"));
+
+        let gexf = sample().to_gexf();
+        assert!(gexf.contains("<node id=\"doc:d1\" label=\"R&amp;D &lt;notes&gt;.md\">"));
+        assert!(gexf.contains("<attvalue for=\"community\" value=\"2\"/>"));
+        assert!(gexf.contains(
+            "<edge id=\"e1\" source=\"doc:d1\" target=\"ent:O'Brien\" label=\"works at\" weight=\"0.5\"/>"
+        ));
+    }
+
+    #[test]
+    fn test_cypher_export_quotes_values_and_names_types() {
+        let cypher = sample().to_cypher();
+        assert!(cypher.contains(
+            "CREATE (:Node:Document {id: 'doc:d1', label: 'R&D <notes>.md', source_document: 'd1', community: 2});"
+        ));
+        assert!(cypher.contains("CREATE (:Node:Entity {id: 'ent:O\\'Brien', label: 'O\\'Brien', metadata: '{\"aliases\":[\"O\\'Brien\"]}'});"));
+        assert!(cypher.contains(
+            "MATCH (a:Node {id: 'doc:d1'}), (b:Node {id: 'ent:O\\'Brien'}) CREATE (a)-[:WORKS_AT {id: 'e1', weight: 0.5}]->(b);"
+        ));
+        assert_eq!(cypher_name("is_a", true), "IS_A");
+        assert_eq!(cypher_name("3d model", false), "T3dModel");
+        assert_eq!(cypher_name("—", true), "RELATED_TO");
+    }
+
+    #[test]
+    fn test_import_reads_back_exports_and_node_link_json() {
+        let original = sample();
+        for format in [GraphFormat::GraphMl, GraphFormat::Json] {
+            let imported = GraphStore::import(&original.export(format)).expect("valid graph");
+            assert_eq!(imported.nodes, original.nodes, "{:?}", format);
+            assert_eq!(imported.edges, original.edges, "{:?}", format);
+        }
+
+        let networkx = r#"{"directed": true, "nodes": [{"id": 1, "name": "Ada", "born": 1815},
+            {"id": 2, "type": "document"}], "links": [{"source": 1, "target": 2, "label": "wrote"}]}"#;
+        let imported = GraphStore::import(networkx).expect("valid node-link graph");
+        assert_eq!(imported.nodes[0].id, "1");
+        assert_eq!(imported.nodes[0].label.as_deref(), Some("Ada"));
+        assert_eq!(imported.nodes[0].node_type, "entity");
+        assert_eq!(imported.nodes[0].metadata, json!({"born": 1815}));
+        assert_eq!(imported.nodes[1].node_type, "document");
+        assert_eq!(imported.edges[0].relation, "wrote");
+        assert_eq!(imported.edges[0].weight, 1.0);
+    }
+
+    #[test]
+    fn test_import_lists_every_problem() {
+        let broken = r#"{"nodes": [{"id": "a"}, {"id": "a"}, {"label": "no id"}],
+            "edges": [{"source": "a", "target": "b"}, {"source": "a"}, {"from": "a", "to": "a", "weight": "heavy"}]}"#;
+        assert_eq!(
+            GraphStore::import(broken),
+            Err(vec![
+                "edge 2 has no source or target".to_string(),
+                "edge 3 has a non-numeric weight".to_string(),
+            ])
+        );
+        let dangling = r#"{"nodes": [{"id": "a"}, {"id": "a"}, {"label": "no id"}],
+            "edges": [{"id": "x", "source": "a", "target": "b"}]}"#;
+        assert_eq!(
+            GraphStore::import(dangling),
+            Err(vec![
+                "node id \"a\" is used twice".to_string(),
+                "node 3 has no id".to_string(),
+                "edge \"x\" points to unknown node \"b\"".to_string(),
+            ])
+        );
+        assert!(GraphStore::import("<html></html>").is_err());
+        assert!(GraphStore::import("nodes: []").is_err());
+    }
+
+    #[test]
+    fn test_merge_updates_by_id_and_keeps_metadata() {
+        let mut store = sample();
+        let mut incoming = GraphStore::new();
+        incoming.add_node(GraphNode {
+            id: "doc:d1".to_string(),
+            label: None,
+            node_type: "document".to_string(),
+            source_document_id: None,
+            metadata: json!({"reviewed": true}),
+        });
+        incoming.add_node(GraphNode {
+            id: "ent:Ada".to_string(),
+            label: Some("Ada".to_string()),
+            node_type: "entity".to_string(),
+            source_document_id: None,
+            metadata: json!({}),
+        });
+        let summary = store.merge_from(incoming);
+        assert_eq!(
+            summary,
+            GraphImportSummary {
+                nodes_added: 1,
+                nodes_updated: 1,
+                edges_added: 0,
+                edges_updated: 0,
+            }
+        );
+        let doc = &store.nodes[0];
+        assert_eq!(doc.label.as_deref(), Some("R&D <notes>.md"));
+        assert_eq!(doc.source_document_id.as_deref(), Some("d1"));
+        assert_eq!(doc.metadata, json!({"community": 2, "reviewed": true}));
+        assert_eq!(store.nodes.len(), 3);
+        assert_eq!(
+            xml_unescape("a &amp; &#x41;&#66; &bogus; &"),
+            "a & AB &bogus; &"
+        );
+    }
+}
@@ -1,3 +1,4 @@
+use crate::models::collection::KnowledgeScope;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
@@ -80,6 +81,13 @@ pub struct QueryFilters {
     pub tags: Vec<String>,
     pub date_range: Option<(f64, f64)>,
     pub confidence_threshold: Option<f32>,
+    /// Conversation the query is asked in; files attached there rank first and files
+    /// attached in other conversations are left out
+    #[serde(default)]
+    pub conversation_id: Option<String>,
+    /// Documents the asking conversation is limited to; empty searches everything
+    #[serde(default)]
+    pub scope: KnowledgeScope,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -89,6 +97,12 @@ pub struct QueryConfig {
     pub use_reranking: bool,
     pub use_hyde: bool,
     pub use_community_detection: bool,
+    /// Index generation to search; the active one when `None`
+    #[serde(default)]
+    pub index_generation: Option<u64>,
+    /// Search even when an identical query has a cached result, e.g. to time retrieval
+    #[serde(default)]
+    pub bypass_cache: bool,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -110,6 +124,117 @@ pub struct ResultMetadata {
     pub community_filtered: bool,
     pub algorithms_used: Vec<String>,
     pub summary: Option<String>,
+    /// Set when candidates were found but none reached the relevance threshold
+    #[serde(default)]
+    pub below_threshold: Option<BelowThreshold>,
+    /// Index generation the result was read from
+    #[serde(default)]
+    pub index_generation: u64,
+    /// How the result was ranked, for explaining an answer
+    #[serde(default)]
+    pub trace: Option<RetrievalTrace>,
+}
+
+/// Step-by-step record of one retrieval: what was searched for, how long each stage
+/// took and how every candidate of the ranking pool was scored
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct RetrievalTrace {
+    /// Terms scored against the index, including HyDE expansions
+    pub query_tokens: Vec<String>,
+    /// Stages that ran, in order
+    pub stages: Vec<TraceStage>,
+    /// Ranking pool, in the order it was first ranked
+    pub candidates: Vec<TraceCandidate>,
+    /// Ids of the candidates quoted in the prompt, filled in by the chat
+    #[serde(default)]
+    pub prompt_chunks: Vec<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TraceStage {
+    pub name: String,
+    pub ms: u32,
+}
+
+/// Scores of one candidate after each ranking stage; `None` when the stage did not run
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TraceCandidate {
+    pub id: String,
+    pub title: String,
+    /// Lexical score, fused with embedding similarity when embeddings are on
+    pub search_score: f32,
+    /// After PageRank, community and hybrid graph fusion
+    pub graph_score: Option<f32>,
+    /// Cross-encoder score
+    pub rerank_score: Option<f32>,
+    /// Absolute relevance compared with the threshold
+    pub relevance: f32,
+    pub outcome: CandidateOutcome,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CandidateOutcome {
+    Selected,
+    /// Ranked below the results kept, or dropped as a near-duplicate
+    RankedOut,
+    BelowThreshold,
+}
+
+impl CandidateOutcome {
+    pub fn label(&self) -> &'static str {
+        match self {
+            CandidateOutcome::Selected => "selected",
+            CandidateOutcome::RankedOut => "ranked out",
+            CandidateOutcome::BelowThreshold => "below threshold",
+        }
+    }
+}
+
+impl RetrievalTrace {
+    /// Stage timings summed by name, candidates kept once with their best outcome;
+    /// used when one answer was retrieved with several queries
+    pub fn merge(&mut self, other: RetrievalTrace) {
+        for token in other.query_tokens {
+            if !self.query_tokens.contains(&token) {
+                self.query_tokens.push(token);
+            }
+        }
+        for stage in other.stages {
+            match self.stages.iter_mut().find(|s| s.name == stage.name) {
+                Some(existing) => existing.ms += stage.ms,
+                None => self.stages.push(stage),
+            }
+        }
+        for candidate in other.candidates {
+            match self.candidates.iter_mut().find(|c| c.id == candidate.id) {
+                Some(existing) => {
+                    if candidate.outcome == CandidateOutcome::Selected {
+                        existing.outcome = CandidateOutcome::Selected;
+                    }
+                }
+                None => self.candidates.push(candidate),
+            }
+        }
+    }
+}
+
+/// Best rejected candidate of a query whose results were all below the threshold
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BelowThreshold {
+    pub best_title: String,
+    pub best_score: f32,
+    pub threshold: f32,
+}
+
+impl BelowThreshold {
+    pub fn explanation(&self) -> String {
+        format!(
+            "No knowledge used: the best match \"{}\" scored {:.0}%, below the {:.0}% relevance threshold.",
+            self.best_title,
+            self.best_score * 100.0,
+            self.threshold * 100.0
+        )
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -124,6 +249,25 @@ pub struct DocumentIndex {
     pub node_count: usize,
     pub embedding_model: Option<String>,
     pub processing_status: ProcessingStatus,
+    /// Functions and types defined in the chunk, for source files
+    #[serde(default)]
+    pub symbols: Vec<String>,
+    /// Uploaded file a passage, page, field or definition was cut from; `None` for
+    /// entries covering a whole file
+    #[serde(default)]
+    pub parent: Option<String>,
+}
+
+impl DocumentIndex {
+    /// Uploaded file the entry was made from; pages, fields and definitions of one file
+    /// share it. Ids are `<indexed at>:<file>`, optionally followed by `#<anchor>`.
+    pub fn source_file(&self) -> &str {
+        if let Some(parent) = &self.parent {
+            return parent;
+        }
+        let rest = self.id.split_once(':').map_or(self.id.as_str(), |(_, r)| r);
+        rest.split_once('#').map_or(rest, |(file, _)| file)
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
@@ -156,6 +300,8 @@ impl Default for QueryFilters {
             tags: Vec::new(),
             date_range: None,
             confidence_threshold: Some(0.3),
+            conversation_id: None,
+            scope: KnowledgeScope::default(),
         }
     }
 }
@@ -168,6 +314,8 @@ impl Default for QueryConfig {
             use_reranking: false,
             use_hyde: true,
             use_community_detection: true,
+            index_generation: None,
+            bypass_cache: false,
         }
     }
 }
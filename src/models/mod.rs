@@ -1,16 +1,36 @@
 // Re-export all model modules
+pub mod activity;
 pub mod app;
+pub mod attachment;
 pub mod chat;
+pub mod collection;
 pub mod crm;
+pub mod freshness;
+pub mod generation;
 pub mod graph_store;
 pub mod graphrag;
+pub mod tasks;
 pub mod webllm;
 
 // Re-export commonly used types
-pub use app::{AppConfig, AppError, AppResult, ThemeMode};
-pub use chat::{Conversation, Message, MessageMetadata, MessageRole, SourceAttribution};
+pub use activity::{ActivityCategory, ActivityEvent};
+pub use app::{AppConfig, AppError, AppResult, LandingView, StartupPreferences, ThemeMode};
+pub use attachment::{AttachmentScope, Attachments};
+pub use chat::{
+    AnswerConfidence, ConfidenceLevel, ConfidenceSignal, Conversation, LatencyBreakdown, Message,
+    MessageMetadata, MessageRole, QueryPlan, RegenerateMode, RegenerateOptions, ReplyAttempt,
+    SourceAttribution, SubQuery, CITED_SNIPPET_CHARS,
+};
+pub use collection::{Collection, Collections, KnowledgeScope};
 pub use crm::{Contact, Customer, Deal, Lead, PipelineStage};
+pub use freshness::{DocumentExpiry, Freshness};
+pub use generation::{
+    AnswerStyle, CompletionIssue, CompletionValidation, ConfidenceSettings, ContextSettings,
+    DateTimeSettings, GenerationSettings, HistoryPolicy, OutputFormat, PostProcessor,
+    SamplingParams, StyleCue, SuggestionSettings, ToolSettings,
+};
 pub use graphrag::{
     DocumentIndex, GraphEdge, GraphNode, PerformanceMode, RAGQuery, RAGResult, SearchStrategy,
 };
+pub use tasks::{CrmLink, CrmRecordType, Task};
 pub use webllm::{ChatSession, LLMModel, ModelConfig, ModelStatus};
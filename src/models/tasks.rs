@@ -0,0 +1,63 @@
+use serde::{Deserialize, Serialize};
+
+/// Action item extracted from a conversation (or created by hand)
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Task {
+    pub id: String,
+    pub title: String,
+    pub due_date: Option<f64>,
+    pub done: bool,
+    pub conversation_id: Option<String>,
+    pub crm_link: Option<CrmLink>,
+    pub created_at: f64,
+    pub completed_at: Option<f64>,
+}
+
+/// Reference to the CRM record a task belongs to
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CrmLink {
+    pub record_type: CrmRecordType,
+    pub record_id: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum CrmRecordType {
+    Customer,
+    Lead,
+    Deal,
+}
+
+impl Task {
+    pub fn new(title: String) -> Self {
+        let now = js_sys::Date::now();
+        Self {
+            id: format!("task_{}_{}", now as u64, uuid::Uuid::new_v4().simple()),
+            title,
+            due_date: None,
+            done: false,
+            conversation_id: None,
+            crm_link: None,
+            created_at: now,
+            completed_at: None,
+        }
+    }
+
+    pub fn with_due_date(mut self, due_date: Option<f64>) -> Self {
+        self.due_date = due_date;
+        self
+    }
+
+    pub fn with_conversation(mut self, conversation_id: Option<String>) -> Self {
+        self.conversation_id = conversation_id;
+        self
+    }
+
+    pub fn with_crm_link(mut self, crm_link: Option<CrmLink>) -> Self {
+        self.crm_link = crm_link;
+        self
+    }
+
+    pub fn is_overdue(&self, now: f64) -> bool {
+        !self.done && self.due_date.map(|d| d < now).unwrap_or(false)
+    }
+}
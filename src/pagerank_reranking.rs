@@ -1,4 +1,13 @@
+use crate::advanced_graphrag::WeightedGraph;
+use crate::models::app::AppResult;
+use crate::models::graph_store::GraphStore;
+use crate::storage::persistent::PersistentStore;
+use crate::utils::hash;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Persistent key of the PageRank scores computed at index time
+pub const NODE_IMPORTANCE_KEY: &str = "graphrag_node_importance_v1";
 
 /// Minimal graph access trait for PageRank
 pub trait GraphAccess {
@@ -122,6 +131,83 @@ impl PageRankEngine {
     }
 }
 
+/// PageRank importance of every GraphStore node, computed at index time over the
+/// undirected edge graph and cached until the graph changes
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct NodeImportance {
+    /// `graph_version` of the store the scores were computed from
+    pub graph_version: u64,
+    /// Score per node id, scaled so the most important node is 1.0
+    pub nodes: HashMap<String, f32>,
+    /// Score of each document node, keyed by source document id
+    pub documents: HashMap<String, f32>,
+}
+
+impl NodeImportance {
+    pub fn compute(store: &GraphStore, config: PageRankConfig) -> Self {
+        let scores =
+            PageRankEngine::new(config).score_nodes(&WeightedGraph::from_graph_store(store));
+        let max = scores.iter().cloned().fold(0.0f32, f32::max);
+        let scale = if max > 0.0 { 1.0 / max } else { 0.0 };
+        let mut out = Self {
+            graph_version: graph_version(store),
+            ..Self::default()
+        };
+        for (node, score) in store.nodes.iter().zip(scores) {
+            let score = score * scale;
+            if node.node_type == "document" {
+                if let Some(doc_id) = &node.source_document_id {
+                    out.documents.insert(doc_id.clone(), score);
+                }
+            }
+            out.nodes.insert(node.id.clone(), score);
+        }
+        out
+    }
+
+    /// Cached scores for `store`, recomputed and saved when the graph changed
+    pub fn ensure(store: &GraphStore) -> Self {
+        if let Some(cached) = Self::load().filter(|c| c.graph_version == graph_version(store)) {
+            return cached;
+        }
+        let fresh = Self::compute(store, PageRankConfig::default());
+        if let Err(e) = fresh.save() {
+            log::error!("Failed to save PageRank scores: {}", e);
+        }
+        fresh
+    }
+
+    pub fn load() -> Option<Self> {
+        PersistentStore::read(NODE_IMPORTANCE_KEY).ok().flatten()
+    }
+
+    pub fn save(&self) -> AppResult<()> {
+        PersistentStore::write(NODE_IMPORTANCE_KEY, self)
+    }
+
+    pub fn document(&self, doc_id: &str) -> f32 {
+        self.documents.get(doc_id).copied().unwrap_or(0.0)
+    }
+}
+
+/// Structural hash of a graph (node ids, edge endpoints and weights). Changes whenever
+/// nodes or edges are added, removed or reweighted.
+pub fn graph_version(store: &GraphStore) -> u64 {
+    let mut h = hash::OFFSET_BASIS;
+    let mut feed = |bytes: &[u8]| {
+        h = hash::fnv1a_extend(hash::fnv1a_extend(h, bytes), &[0xff]);
+    };
+    for n in &store.nodes {
+        feed(n.id.as_bytes());
+    }
+    for e in &store.edges {
+        feed(e.from.as_bytes());
+        feed(e.to.as_bytes());
+        feed(&e.weight.to_bits().to_le_bytes());
+    }
+    h
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct RerankingConfig {
     pub weight_mono_t5: f32,
@@ -164,14 +250,40 @@ impl AdvancedReranker {
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct SynthesisConfig {
     pub max_chars: usize,
+    /// Ask the chat model for a grounded summary of the top chunks instead of quoting them
+    #[serde(default)]
+    pub abstractive: bool,
+    /// Chunks given to the model for an abstractive summary
+    #[serde(default = "default_max_sources")]
+    pub max_sources: usize,
+}
+
+fn default_max_sources() -> usize {
+    5
 }
 
 impl Default for SynthesisConfig {
     fn default() -> Self {
-        Self { max_chars: 2048 }
+        Self {
+            max_chars: 2048,
+            abstractive: false,
+            max_sources: default_max_sources(),
+        }
     }
 }
 
+/// A retrieved chunk handed to the synthesizer, cited as `[n]` by its position
+#[derive(Clone, Debug, PartialEq)]
+pub struct SynthesisSource {
+    pub title: String,
+    pub content: String,
+}
+
+const ABSTRACTIVE_INSTRUCTIONS: &str = "Summarize what the numbered sources say about \
+the question in a few sentences. Use only facts stated in the sources and put the \
+number of the source after each fact, like [1] or [2][3]. If the sources do not \
+answer the question, say so. Do not answer from memory.";
+
 #[derive(Clone, Debug)]
 pub struct ResultSynthesizer {
     pub config: SynthesisConfig,
@@ -190,4 +302,132 @@ impl ResultSynthesizer {
         }
         combined
     }
+
+    /// Messages asking the model to summarize `sources` for `question`
+    pub fn abstractive_messages(
+        &self,
+        question: &str,
+        sources: &[SynthesisSource],
+    ) -> Vec<crate::models::Message> {
+        use crate::models::{Message, MessageRole};
+        let numbered = sources
+            .iter()
+            .take(self.config.max_sources)
+            .enumerate()
+            .map(|(i, s)| format!("[{}] {}\n{}", i + 1, s.title, s.content.trim()))
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        vec![
+            Message::new(MessageRole::System, ABSTRACTIVE_INSTRUCTIONS.to_string()),
+            Message::new(
+                MessageRole::User,
+                format!("Question: {}\n\nSources:\n{}", question.trim(), numbered),
+            ),
+        ]
+    }
+
+    /// The model's summary with citation markers of sources it was not given removed.
+    /// `None` when nothing is left that cites a source, so the caller falls back to
+    /// quoting the chunks rather than injecting an ungrounded summary.
+    pub fn grounded_summary(&self, response: &str, sources: usize) -> Option<String> {
+        let sources = sources.min(self.config.max_sources);
+        // A run of markers such as "[7][2]" keeps its leading space only if one survives
+        let runs = regex::Regex::new(r"(\s*)((?:\[\d+\])+)").expect("valid citation pattern");
+        let marker = regex::Regex::new(r"\[(\d+)\]").expect("valid citation pattern");
+        let mut cited = false;
+        let cleaned = runs.replace_all(response.trim(), |run: &regex::Captures| {
+            let kept: String = marker
+                .captures_iter(&run[2])
+                .filter(|c| {
+                    c[1].parse::<usize>()
+                        .is_ok_and(|n| (1..=sources).contains(&n))
+                })
+                .map(|c| c[0].to_string())
+                .collect();
+            if kept.is_empty() {
+                return String::new();
+            }
+            cited = true;
+            format!("{}{}", &run[1], kept)
+        });
+        let mut summary = cleaned.trim().to_string();
+        if !cited {
+            return None;
+        }
+        if summary.len() > self.config.max_chars {
+            let mut end = self.config.max_chars;
+            while !summary.is_char_boundary(end) {
+                end -= 1;
+            }
+            summary.truncate(end);
+        }
+        Some(summary)
+    }
+
+    /// Grounded summary of `sources` written by the loaded chat model
+    pub async fn synthesize_abstractive(
+        &self,
+        engine: &wasm_bindgen::JsValue,
+        question: &str,
+        sources: &[SynthesisSource],
+    ) -> Option<String> {
+        if sources.is_empty() {
+            return None;
+        }
+        let request = self.abstractive_messages(question, sources);
+        match crate::webllm_binding::send_message_to_llm(engine, request).await {
+            Ok(response) => self.grounded_summary(&response, sources.len()),
+            Err(e) => {
+                log::warn!("Abstractive synthesis failed: {:?}", e);
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_grounded_summary_keeps_only_known_citations() {
+        let synthesizer = ResultSynthesizer::new(SynthesisConfig::default());
+        assert_eq!(
+            synthesizer.grounded_summary(
+                " Refunds take 30 days [1]. Gift cards are final [7][2]. ",
+                2
+            ),
+            Some("Refunds take 30 days [1]. Gift cards are final [2].".to_string())
+        );
+        // Nothing tied to a source is not grounded
+        assert_eq!(
+            synthesizer.grounded_summary("Refunds take 30 days [9].", 2),
+            None
+        );
+        assert_eq!(
+            synthesizer.grounded_summary("Refunds take 30 days.", 2),
+            None
+        );
+    }
+
+    #[test]
+    fn test_prompt_numbers_sources_for_citation() {
+        let synthesizer = ResultSynthesizer::new(SynthesisConfig {
+            max_sources: 1,
+            ..SynthesisConfig::default()
+        });
+        let sources = vec![
+            SynthesisSource {
+                title: "policy.md".to_string(),
+                content: "Refunds take 30 days.".to_string(),
+            },
+            SynthesisSource {
+                title: "faq.md".to_string(),
+                content: "Gift cards are final.".to_string(),
+            },
+        ];
+        let prompt = &synthesizer.abstractive_messages("refunds?", &sources)[1].content;
+        assert!(prompt.contains("[1] policy.md\nRefunds take 30 days."));
+        assert!(!prompt.contains("faq.md"));
+    }
 }
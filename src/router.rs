@@ -0,0 +1,376 @@
+//! Hash routing for the single-page app. The open conversation and modal live in the
+//! URL (`#chat/<conversation>?modal=docs&doc=<title>`), so reloading or sharing a link
+//! restores them. Hashes that are not ours (e.g. `#customers`) are left alone.
+
+use crate::models::LandingView;
+use leptos::prelude::*;
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::JsCast;
+
+/// Modals and panels that can be opened from a link
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Modal {
+    Documents,
+    Tasks,
+    Activity,
+    Graph,
+    GraphRagSettings,
+    GenerationSettings,
+    NotificationSettings,
+    SystemPrompt,
+    StartupSettings,
+    StorageHealth,
+    Crm,
+    Settings,
+}
+
+impl Modal {
+    pub const ALL: [Modal; 12] = [
+        Modal::Documents,
+        Modal::Tasks,
+        Modal::Activity,
+        Modal::Graph,
+        Modal::GraphRagSettings,
+        Modal::GenerationSettings,
+        Modal::NotificationSettings,
+        Modal::SystemPrompt,
+        Modal::StartupSettings,
+        Modal::StorageHealth,
+        Modal::Crm,
+        Modal::Settings,
+    ];
+
+    /// Value of the `modal` query parameter
+    pub fn key(self) -> &'static str {
+        match self {
+            Modal::Documents => "docs",
+            Modal::Tasks => "tasks",
+            Modal::Activity => "activity",
+            Modal::Graph => "graph",
+            Modal::GraphRagSettings => "graphrag",
+            Modal::GenerationSettings => "generation",
+            Modal::NotificationSettings => "notifications",
+            Modal::SystemPrompt => "prompt",
+            Modal::StartupSettings => "startup",
+            Modal::StorageHealth => "storage",
+            Modal::Crm => "crm",
+            Modal::Settings => "settings",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|m| m.key() == key)
+    }
+}
+
+/// What the URL hash says is on screen
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Route {
+    pub conversation: Option<String>,
+    pub modal: Option<Modal>,
+    /// Document shown by the document modal
+    pub document: Option<String>,
+}
+
+impl Route {
+    /// Route of `hash` (with or without the leading `#`), `None` for hashes owned by
+    /// something else
+    pub fn parse(hash: &str) -> Option<Self> {
+        let hash = hash.trim_start_matches('#');
+        let (path, query) = hash.split_once('?').unwrap_or((hash, ""));
+        let conversation = match path.split_once('/') {
+            _ if path.is_empty() => None,
+            None if path == "chat" => None,
+            Some(("chat", id)) if !id.is_empty() => Some(percent_decode(id)),
+            _ => return None,
+        };
+        let mut route = Route {
+            conversation,
+            ..Route::default()
+        };
+        for pair in query.split('&').filter(|p| !p.is_empty()) {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            match key {
+                "modal" => route.modal = Modal::from_key(value),
+                "doc" if !value.is_empty() => route.document = Some(percent_decode(value)),
+                _ => {}
+            }
+        }
+        Some(route)
+    }
+
+    /// Hash for this route, without the leading `#`
+    pub fn to_hash(&self) -> String {
+        let mut out = match &self.conversation {
+            Some(id) => format!("chat/{}", percent_encode(id)),
+            None => String::new(),
+        };
+        let mut params = Vec::new();
+        if let Some(modal) = self.modal {
+            params.push(format!("modal={}", modal.key()));
+        }
+        if let Some(doc) = &self.document {
+            params.push(format!("doc={}", percent_encode(doc)));
+        }
+        if !params.is_empty() {
+            if out.is_empty() {
+                out.push_str("chat");
+            }
+            out.push('?');
+            out.push_str(&params.join("&"));
+        }
+        out
+    }
+
+    /// Route to open at startup: this one when the URL links somewhere, else the
+    /// `landing` view, `last_conversation` being the most recently updated one
+    pub fn or_landing(self, landing: LandingView, last_conversation: Option<String>) -> Self {
+        if self != Route::default() {
+            return self;
+        }
+        match landing {
+            LandingView::LastConversation => Route {
+                conversation: last_conversation,
+                ..self
+            },
+            LandingView::NewChat => self,
+            LandingView::Documents => Route {
+                modal: Some(Modal::Documents),
+                ..self
+            },
+            LandingView::CrmBoard => Route {
+                modal: Some(Modal::Crm),
+                ..self
+            },
+        }
+    }
+}
+
+/// Current route, kept in sync with `location.hash` in both directions
+#[derive(Clone, Copy)]
+pub struct RouterContext {
+    route: RwSignal<Route>,
+}
+
+impl Default for RouterContext {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RouterContext {
+    pub fn new() -> Self {
+        let route = RwSignal::new(
+            current_hash()
+                .and_then(|h| Route::parse(&h))
+                .unwrap_or_default(),
+        );
+        // Back/forward and edited URLs
+        if let Some(win) = web_sys::window() {
+            let cb = Closure::wrap(Box::new(move |_e: web_sys::Event| {
+                if let Some(parsed) = current_hash().and_then(|h| Route::parse(&h)) {
+                    if route.with_untracked(|r| *r != parsed) {
+                        route.set(parsed);
+                    }
+                }
+            }) as Box<dyn FnMut(_)>);
+            let _ = win.add_event_listener_with_callback("hashchange", cb.as_ref().unchecked_ref());
+            cb.forget(); // Lives as long as the app
+        }
+        Effect::new(move |_| {
+            let hash = route.with(|r| r.to_hash());
+            // A foreign hash stays until the route actually changes
+            let current = current_hash().unwrap_or_default();
+            let ours = Route::parse(&current).is_some();
+            if current.trim_start_matches('#') != hash && (ours || !hash.is_empty()) {
+                if let Some(win) = web_sys::window() {
+                    let _ = win.location().set_hash(&hash);
+                }
+            }
+        });
+        Self { route }
+    }
+
+    pub fn route(&self) -> Signal<Route> {
+        self.route.into()
+    }
+
+    pub fn route_now(&self) -> Route {
+        self.route.get_untracked()
+    }
+
+    pub fn open(&self, modal: Modal) {
+        self.route.update(|r| {
+            if r.modal != Some(modal) {
+                r.modal = Some(modal);
+                r.document = None;
+            }
+        });
+    }
+
+    /// Close `modal` if it is the open one
+    pub fn close(&self, modal: Modal) {
+        if self.route.with_untracked(|r| r.modal == Some(modal)) {
+            self.route.update(|r| {
+                r.modal = None;
+                r.document = None;
+            });
+        }
+    }
+
+    pub fn set_conversation(&self, id: Option<String>) {
+        if self.route.with_untracked(|r| r.conversation != id) {
+            self.route.update(|r| r.conversation = id);
+        }
+    }
+
+    /// Keep a component's open/closed signal in sync with `modal` in the route
+    pub fn bind_modal(&self, modal: Modal, show: ReadSignal<bool>, set_show: WriteSignal<bool>) {
+        let router = *self;
+        Effect::new(move |_| {
+            let open = router.route.with(|r| r.modal == Some(modal));
+            if show.get_untracked() != open {
+                set_show.set(open);
+            }
+        });
+        // The first run would close a linked modal before the effect above opens it
+        Effect::new(move |first: Option<()>| {
+            let open = show.get();
+            if first.is_some() {
+                if open {
+                    router.open(modal);
+                } else {
+                    router.close(modal);
+                }
+            }
+        });
+    }
+
+    /// Keep the open conversation in sync with the route
+    pub fn bind_conversation(
+        &self,
+        current: ReadSignal<Option<String>>,
+        set_current: WriteSignal<Option<String>>,
+    ) {
+        let router = *self;
+        Effect::new(move |_| {
+            let linked = router.route.with(|r| r.conversation.clone());
+            if linked.is_some() && current.with_untracked(|c| *c != linked) {
+                set_current.set(linked);
+            }
+        });
+        Effect::new(move |first: Option<()>| {
+            let id = current.get();
+            if first.is_some() || id.is_some() {
+                router.set_conversation(id);
+            }
+        });
+    }
+}
+
+fn current_hash() -> Option<String> {
+    web_sys::window().and_then(|w| w.location().hash().ok())
+}
+
+fn percent_encode(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for b in s.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                out.push(b as char)
+            }
+            _ => out.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    out
+}
+
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = (bytes[i] == b'%')
+            .then(|| s.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match hex {
+            Some(b) => {
+                out.push(b);
+                i += 3;
+            }
+            None => {
+                out.push(if bytes[i] == b'+' { b' ' } else { bytes[i] });
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_route_round_trips() {
+        let route = Route {
+            conversation: Some("abc-123".into()),
+            modal: Some(Modal::Documents),
+            document: Some("Q3 plan & notes.pdf".into()),
+        };
+        let hash = route.to_hash();
+        assert_eq!(
+            hash,
+            "chat/abc-123?modal=docs&doc=Q3%20plan%20%26%20notes.pdf"
+        );
+        assert_eq!(Route::parse(&format!("#{}", hash)), Some(route));
+        assert_eq!(Route::parse(""), Some(Route::default()));
+        assert_eq!(Route::default().to_hash(), "");
+    }
+
+    #[test]
+    fn test_parse_leaves_foreign_hashes_alone() {
+        assert_eq!(Route::parse("#customers"), None);
+        assert_eq!(Route::parse("#deals/42"), None);
+        let modal_only = Route::parse("#chat?modal=graph").unwrap();
+        assert_eq!(modal_only.conversation, None);
+        assert_eq!(modal_only.modal, Some(Modal::Graph));
+        assert_eq!(Route::parse("#chat?modal=unknown").unwrap().modal, None);
+    }
+
+    #[test]
+    fn test_landing_applies_only_without_a_link() {
+        let last = || Some("c9".to_string());
+        assert_eq!(
+            Route::default()
+                .or_landing(LandingView::LastConversation, last())
+                .conversation,
+            last()
+        );
+        assert_eq!(
+            Route::default().or_landing(LandingView::NewChat, last()),
+            Route::default()
+        );
+        assert_eq!(
+            Route::default()
+                .or_landing(LandingView::CrmBoard, last())
+                .to_hash(),
+            "chat?modal=crm"
+        );
+        // A link wins over the landing view
+        let linked = Route::parse("#chat/abc").unwrap();
+        assert_eq!(
+            linked.clone().or_landing(LandingView::Documents, last()),
+            linked
+        );
+        assert_eq!(
+            Route::parse("#chat?modal=startup").unwrap().modal,
+            Some(Modal::StartupSettings)
+        );
+        assert_eq!(
+            Route::parse("#chat?modal=storage").unwrap().modal,
+            Some(Modal::StorageHealth)
+        );
+    }
+}
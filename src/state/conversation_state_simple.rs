@@ -2,9 +2,14 @@ use crate::models::{
     app::AppError,
     chat::{Conversation, Message},
 };
+use crate::utils::storage::StorageUtils;
 use leptos::prelude::*;
 use serde::{Deserialize, Serialize};
 
+const RECENT_CONVERSATIONS_KEY: &str = "recent_conversations_v1";
+/// Conversations kept in the sidebar quick-switcher
+pub const MAX_RECENT_CONVERSATIONS: usize = 5;
+
 /// Simplified conversation state for chat management
 #[derive(Clone, Debug, Serialize, Deserialize, Default)]
 pub struct ConversationState {
@@ -16,6 +21,79 @@ pub struct ConversationState {
     pub error: Option<AppError>,
 }
 
+/// Conversations in the order they were last opened, most recent first. While the
+/// user cycles through them the order is frozen, so repeated presses walk the list
+/// instead of bouncing between the two newest entries.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct RecentConversations {
+    pub ids: Vec<String>,
+    /// Position reached by the cycle in progress
+    #[serde(skip)]
+    cycle: Option<usize>,
+}
+
+impl RecentConversations {
+    pub fn load() -> Self {
+        StorageUtils::retrieve_local::<RecentConversations>(RECENT_CONVERSATIONS_KEY)
+            .ok()
+            .flatten()
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<(), AppError> {
+        StorageUtils::store_local(RECENT_CONVERSATIONS_KEY, self)
+    }
+
+    /// Move `id` to the front; ignored while cycling
+    pub fn touch(&mut self, id: &str) -> bool {
+        if self.cycle.is_some() || self.ids.first().is_some_and(|f| f == id) {
+            return false;
+        }
+        self.ids.retain(|i| i != id);
+        self.ids.insert(0, id.to_string());
+        self.ids.truncate(MAX_RECENT_CONVERSATIONS);
+        true
+    }
+
+    /// Drop ids `keep` rejects, e.g. conversations that were deleted
+    pub fn retain(&mut self, keep: impl Fn(&str) -> bool) -> bool {
+        let before = self.ids.len();
+        self.ids.retain(|i| keep(i));
+        if self.cycle.is_some_and(|c| c >= self.ids.len()) {
+            self.cycle = None;
+        }
+        self.ids.len() != before
+    }
+
+    /// Next (or previous) conversation of the cycle, starting one after the newest
+    pub fn cycle(&mut self, forward: bool) -> Option<String> {
+        let len = self.ids.len();
+        if len < 2 {
+            return None;
+        }
+        let at = self.cycle.unwrap_or(0);
+        let next = if forward {
+            (at + 1) % len
+        } else {
+            (at + len - 1) % len
+        };
+        self.cycle = Some(next);
+        Some(self.ids[next].clone())
+    }
+
+    pub fn is_cycling(&self) -> bool {
+        self.cycle.is_some()
+    }
+
+    /// Finish cycling and move the conversation landed on to the front
+    pub fn end_cycle(&mut self) -> Option<String> {
+        let at = self.cycle.take()?;
+        let id = self.ids.get(at)?.clone();
+        self.touch(&id);
+        Some(id)
+    }
+}
+
 impl Default for ConversationStateContext {
     fn default() -> Self {
         Self::new()
@@ -23,15 +101,56 @@ impl Default for ConversationStateContext {
 }
 
 /// Conversation state context for chat management
-#[derive(Clone)]
+#[derive(Clone, Copy)]
 pub struct ConversationStateContext {
     pub state: RwSignal<ConversationState>,
+    /// Opening order of conversations, persisted across sessions
+    pub recent: RwSignal<RecentConversations>,
 }
 
 impl ConversationStateContext {
     pub fn new() -> Self {
         Self {
             state: RwSignal::new(ConversationState::default()),
+            recent: RwSignal::new(RecentConversations::load()),
+        }
+    }
+
+    // Recency methods
+    pub fn recent_ids(&self) -> Vec<String> {
+        self.recent.with(|r| r.ids.clone())
+    }
+
+    pub fn touch_recent(&self, id: &str) {
+        if self.recent.try_update(|r| r.touch(id)).unwrap_or(false) {
+            self.save_recent();
+        }
+    }
+
+    pub fn prune_recent(&self, keep: impl Fn(&str) -> bool) {
+        if self.recent.try_update(|r| r.retain(keep)).unwrap_or(false) {
+            self.save_recent();
+        }
+    }
+
+    pub fn cycle_recent(&self, forward: bool) -> Option<String> {
+        self.recent.try_update(|r| r.cycle(forward)).flatten()
+    }
+
+    pub fn end_recent_cycle(&self) {
+        if self
+            .recent
+            .try_update(|r| r.end_cycle())
+            .flatten()
+            .is_some()
+        {
+            self.save_recent();
+        }
+    }
+
+    fn save_recent(&self) {
+        if let Err(e) = self.recent.with_untracked(|r| r.save()) {
+            log::warn!("Failed to save recent conversations: {:?}", e);
         }
     }
 
@@ -128,6 +247,7 @@ impl ConversationStateContext {
     }
 
     pub fn delete_conversation(&self, id: &str) {
+        self.prune_recent(|i| i != id);
         self.state.update(|s| {
             s.conversations.retain(|c| c.id != id);
             if s.current_conversation_id.as_ref() == Some(&id.to_string()) {
@@ -166,6 +286,30 @@ mod tests {
         assert!(!ctx.is_streaming());
     }
 
+    #[test]
+    fn test_recent_conversations_cycle() {
+        let mut recent = RecentConversations::default();
+        for id in ["a", "b", "c", "d", "e", "f"] {
+            recent.touch(id);
+        }
+        recent.touch("c");
+        assert_eq!(recent.ids, vec!["c", "f", "e", "d", "b"]);
+
+        // Cycling walks the frozen order, then commits where it stopped
+        assert_eq!(recent.cycle(true).as_deref(), Some("f"));
+        recent.touch("f");
+        assert_eq!(recent.cycle(true).as_deref(), Some("e"));
+        assert_eq!(recent.cycle(false).as_deref(), Some("f"));
+        assert_eq!(recent.cycle(false).as_deref(), Some("c"));
+        assert_eq!(recent.cycle(false).as_deref(), Some("b"));
+        assert_eq!(recent.end_cycle().as_deref(), Some("b"));
+        assert!(!recent.is_cycling());
+        assert_eq!(recent.ids, vec!["b", "c", "f", "e", "d"]);
+
+        recent.retain(|id| id != "c");
+        assert_eq!(recent.ids, vec!["b", "f", "e", "d"]);
+    }
+
     #[test]
     fn test_conversation_management() {
         let ctx = ConversationStateContext::new();
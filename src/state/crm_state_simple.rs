@@ -1,12 +1,15 @@
+use crate::features::crm::ownership::normalize_owner;
 use crate::models::app::AppError;
 use crate::models::crm::{Customer, Deal, Lead, PipelineStage};
+use crate::storage::tab_sync;
 use crate::utils::storage::StorageUtils;
 use leptos::prelude::*;
 
-const CUSTOMERS_KEY: &str = "crm_customers";
-const LEADS_KEY: &str = "crm_leads";
-const DEALS_KEY: &str = "crm_deals";
-const STAGES_KEY: &str = "crm_stages";
+pub const CUSTOMERS_KEY: &str = "crm_customers";
+pub const LEADS_KEY: &str = "crm_leads";
+pub const DEALS_KEY: &str = "crm_deals";
+pub const STAGES_KEY: &str = "crm_stages";
+pub const OWNERS_KEY: &str = "crm_owner_profiles";
 
 #[derive(Clone)]
 pub struct CRMStateContext {
@@ -14,6 +17,7 @@ pub struct CRMStateContext {
     leads: RwSignal<Vec<Lead>>,
     deals: RwSignal<Vec<Deal>>,
     stages: RwSignal<Vec<PipelineStage>>,
+    owner_profiles: RwSignal<Vec<String>>,
     last_error: RwSignal<Option<AppError>>,
 }
 
@@ -24,6 +28,7 @@ impl CRMStateContext {
             leads: RwSignal::new(Vec::new()),
             deals: RwSignal::new(Vec::new()),
             stages: RwSignal::new(Vec::new()),
+            owner_profiles: RwSignal::new(Vec::new()),
             last_error: RwSignal::new(None),
         };
         ctx.load_from_storage();
@@ -42,6 +47,26 @@ impl CRMStateContext {
     pub fn stages_now(&self) -> Vec<PipelineStage> {
         self.stages.get_untracked()
     }
+    pub fn owner_profiles_now(&self) -> Vec<String> {
+        self.owner_profiles.get_untracked()
+    }
+    /// Saved profiles plus any owner names already used on leads/deals, sorted
+    pub fn known_owners(&self) -> Vec<String> {
+        let mut names = self.owner_profiles.get();
+        let in_use = self
+            .leads
+            .get()
+            .into_iter()
+            .filter_map(|l| l.owner)
+            .chain(self.deals.get().into_iter().filter_map(|d| d.owner));
+        for name in in_use {
+            if !names.iter().any(|n| n.eq_ignore_ascii_case(&name)) {
+                names.push(name);
+            }
+        }
+        names.sort_by_key(|n| n.to_lowercase());
+        names
+    }
     pub fn last_error_now(&self) -> Option<AppError> {
         self.last_error.get_untracked()
     }
@@ -68,6 +93,20 @@ impl CRMStateContext {
             Ok(None) => {}
             Err(e) => self.last_error.set(Some(e)),
         }
+        match StorageUtils::retrieve_local::<Vec<String>>(OWNERS_KEY) {
+            Ok(Some(v)) => self.owner_profiles.set(v),
+            Ok(None) => {}
+            Err(e) => self.last_error.set(Some(e)),
+        }
+    }
+
+    /// Reload the CRM lists whenever another tab changes them
+    pub fn sync_with_other_tabs(&self) {
+        let ctx = self.clone();
+        tab_sync::watch(
+            &[CUSTOMERS_KEY, LEADS_KEY, DEALS_KEY, STAGES_KEY, OWNERS_KEY],
+            move || ctx.load_from_storage(),
+        );
     }
 
     fn persist_all(&self) {
@@ -84,6 +123,10 @@ impl CRMStateContext {
         if let Err(e) = StorageUtils::store_local(STAGES_KEY, &self.stages.get_untracked()) {
             self.last_error.set(Some(e));
         }
+        if let Err(e) = StorageUtils::store_local(OWNERS_KEY, &self.owner_profiles.get_untracked())
+        {
+            self.last_error.set(Some(e));
+        }
     }
 
     // Customers CRUD
@@ -98,6 +141,15 @@ impl CRMStateContext {
         self.persist_all();
     }
 
+    /// Add customers in one write, e.g. the rows of an imported table
+    pub fn add_customers(&self, customers: Vec<Customer>) {
+        if customers.is_empty() {
+            return;
+        }
+        self.customers.update(|v| v.extend(customers));
+        self.persist_all();
+    }
+
     pub fn delete_customer(&self, id: &str) {
         self.customers.update(|v| v.retain(|c| c.id != id));
         self.persist_all();
@@ -153,11 +205,55 @@ impl CRMStateContext {
         self.stages.update(|v| v.retain(|c| c.id != id));
         self.persist_all();
     }
+
+    // Owner profiles
+    pub fn add_owner_profile(&self, name: &str) {
+        let Some(name) = normalize_owner(name) else {
+            return;
+        };
+        self.owner_profiles.update(|v| {
+            if !v.iter().any(|n| n.eq_ignore_ascii_case(&name)) {
+                v.push(name);
+            }
+        });
+        self.persist_all();
+    }
+
+    pub fn remove_owner_profile(&self, name: &str) {
+        self.owner_profiles
+            .update(|v| v.retain(|n| !n.eq_ignore_ascii_case(name)));
+        self.persist_all();
+    }
+
+    /// Assign (or clear, with a blank name) the owner of a lead
+    pub fn assign_lead_owner(&self, id: &str, owner: &str) {
+        let owner = normalize_owner(owner);
+        self.leads.update(|v| {
+            if let Some(l) = v.iter_mut().find(|l| l.id == id) {
+                l.owner = owner;
+                l.updated_at = js_sys::Date::now();
+            }
+        });
+        self.persist_all();
+    }
+
+    /// Assign (or clear, with a blank name) the owner of a deal
+    pub fn assign_deal_owner(&self, id: &str, owner: &str) {
+        let owner = normalize_owner(owner);
+        self.deals.update(|v| {
+            if let Some(d) = v.iter_mut().find(|d| d.id == id) {
+                d.owner = owner;
+                d.updated_at = js_sys::Date::now();
+            }
+        });
+        self.persist_all();
+    }
 }
 
 #[component]
 pub fn CRMStateProvider(children: Children) -> impl IntoView {
     let ctx = CRMStateContext::new();
+    ctx.sync_with_other_tabs();
     provide_context(ctx);
     view! { {children()} }
 }
@@ -0,0 +1,80 @@
+use crate::models::activity::{ActivityCategory, ActivityEvent};
+use crate::utils::storage::StorageUtils;
+use leptos::prelude::*;
+
+// Session storage so the feed survives reloads but not the browser session
+const ACTIVITY_KEY: &str = "activity_feed_v1";
+const MAX_EVENTS: usize = 500;
+
+/// App-wide event bus; every emitted event is appended to the session activity feed
+#[derive(Clone, Copy)]
+pub struct EventBusContext {
+    events: RwSignal<Vec<ActivityEvent>>,
+}
+
+impl Default for EventBusContext {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EventBusContext {
+    pub fn new() -> Self {
+        let events = StorageUtils::retrieve_session::<Vec<ActivityEvent>>(ACTIVITY_KEY)
+            .ok()
+            .flatten()
+            .unwrap_or_default();
+        Self {
+            events: RwSignal::new(events),
+        }
+    }
+
+    /// Reactive feed, oldest first
+    pub fn events(&self) -> Signal<Vec<ActivityEvent>> {
+        let events = self.events;
+        Signal::derive(move || events.get())
+    }
+    pub fn events_now(&self) -> Vec<ActivityEvent> {
+        self.events.get_untracked()
+    }
+
+    pub fn emit(&self, event: ActivityEvent) {
+        log::debug!("[{:?}] {}", event.category, event.message);
+        self.events.update(|v| {
+            v.push(event);
+            if v.len() > MAX_EVENTS {
+                let excess = v.len() - MAX_EVENTS;
+                v.drain(..excess);
+            }
+        });
+        self.persist();
+    }
+
+    /// Shorthand for `emit(ActivityEvent::new(category, message))`
+    pub fn record(&self, category: ActivityCategory, message: impl Into<String>) {
+        self.emit(ActivityEvent::new(category, message));
+    }
+
+    pub fn error(&self, message: impl Into<String>, detail: impl Into<String>) {
+        self.emit(ActivityEvent::new(ActivityCategory::Error, message).with_detail(detail));
+    }
+
+    pub fn clear(&self) {
+        self.events.set(Vec::new());
+        let _ = StorageUtils::remove_session(ACTIVITY_KEY);
+    }
+
+    fn persist(&self) {
+        let _ = StorageUtils::store_session(ACTIVITY_KEY, &self.events.get_untracked());
+    }
+}
+
+#[component]
+pub fn EventBusProvider(children: Children) -> impl IntoView {
+    provide_context(EventBusContext::new());
+    children()
+}
+
+pub fn use_event_bus() -> EventBusContext {
+    expect_context::<EventBusContext>()
+}
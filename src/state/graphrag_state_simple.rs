@@ -1,10 +1,27 @@
-use crate::features::graphrag::extraction::extract_entities_relations;
+use crate::advanced_graphrag::{CommunityDetectionConfig, CommunityDetectionEngine};
+use crate::features::graphrag::embeddings::{embed_documents, embed_texts, VectorIndex};
+use crate::features::graphrag::extraction::{
+    extract_entities_relations, extract_entities_relations_llm,
+};
+use crate::features::graphrag::graph::resolution::resolve_entities;
+use crate::features::graphrag::index_generation::{self, IndexSnapshot};
+use crate::features::graphrag::index_stats::{IndexManifest, IndexStaleness};
+use crate::features::graphrag::reembed::{self, ReembedProgress};
 use crate::features::graphrag::{GraphRAGPipeline, Retriever};
+use crate::graphrag_config::GraphRAGConfig;
+use crate::models::graph_store::{GraphImportMode, GraphImportSummary, GraphStore};
 use crate::models::{
+    activity::{ActivityCategory, ActivityEvent},
     app::AppError,
-    graphrag::{RAGQuery, RAGResult, SearchStrategy},
+    collection::Collections,
+    graphrag::{DocumentIndex, RAGQuery, RAGResult, SearchStrategy},
 };
+use crate::pagerank_reranking::{graph_version, NodeImportance, PageRankConfig};
+use crate::state::event_bus_simple::EventBusContext;
 use crate::state::knowledge_storage_context::KnowledgeStorageContext;
+use crate::storage::tab_sync;
+use crate::utils::notifications::{LongTask, NotificationUtils};
+use crate::webllm_binding::loaded_engine;
 use js_sys::Promise;
 use leptos::prelude::*;
 use std::collections::HashSet;
@@ -12,6 +29,15 @@ use wasm_bindgen_futures::spawn_local;
 use wasm_bindgen_futures::JsFuture;
 use web_sys::window;
 
+/// Records another tab may write that change the index state shown here
+const SYNCED_KEYS: &[&str] = &[
+    KnowledgeStorageContext::BUFFER_KEY,
+    crate::features::graphrag::pipeline::DOCUMENT_INDEX_KEY_V1,
+    crate::features::graphrag::embeddings::VECTOR_INDEX_KEY,
+    crate::features::graphrag::index_stats::INDEX_MANIFEST_KEY,
+    index_generation::INDEX_GENERATION_KEY,
+];
+
 #[derive(Clone)]
 pub struct GraphRAGStateContext {
     indexing: RwSignal<bool>,
@@ -19,6 +45,11 @@ pub struct GraphRAGStateContext {
     last_error: RwSignal<Option<AppError>>,
     last_result: RwSignal<Option<RAGResult>>,
     index_progress: RwSignal<Option<f32>>, // 0.0..=1.0 when indexing
+    staleness: RwSignal<IndexStaleness>,
+    /// Re-embedding migration in progress, if any
+    reembed: RwSignal<Option<ReembedProgress>>,
+    reembed_cancelled: RwSignal<bool>,
+    events: Option<EventBusContext>,
 }
 
 impl Default for GraphRAGStateContext {
@@ -35,6 +66,11 @@ impl GraphRAGStateContext {
             last_error: RwSignal::new(None),
             last_result: RwSignal::new(None),
             index_progress: RwSignal::new(None),
+            staleness: RwSignal::new(current_staleness()),
+            reembed: RwSignal::new(None),
+            reembed_cancelled: RwSignal::new(false),
+            // Captured at construction; contexts are not reachable from async tasks
+            events: use_context::<EventBusContext>(),
         }
     }
 
@@ -53,6 +89,28 @@ impl GraphRAGStateContext {
     pub fn index_progress(&self) -> ReadSignal<Option<f32>> {
         self.index_progress.read_only()
     }
+    /// Knowledge base changes not yet reflected in the index
+    pub fn staleness(&self) -> ReadSignal<IndexStaleness> {
+        self.staleness.read_only()
+    }
+
+    pub fn reembed_progress(&self) -> ReadSignal<Option<ReembedProgress>> {
+        self.reembed.read_only()
+    }
+
+    /// Recompute staleness after the knowledge base buffer was written
+    pub fn refresh_staleness(&self) {
+        self.staleness.set(current_staleness());
+    }
+
+    /// Follow documents uploaded and index builds committed in another tab
+    pub fn sync_with_other_tabs(&self) {
+        let this = self.clone();
+        tab_sync::watch(SYNCED_KEYS, move || {
+            index_generation::reload_generations();
+            this.refresh_staleness();
+        });
+    }
 
     // Convenience getters for tests and non-reactive checks
     pub fn indexing_now(&self) -> bool {
@@ -75,6 +133,11 @@ impl GraphRAGStateContext {
         self.last_error.set(err);
     }
 
+    /// Record a result retrieved outside `run_query` (e.g. for a chat answer)
+    pub fn record_result(&self, result: RAGResult) {
+        self.last_result.set(Some(result));
+    }
+
     pub fn run_query(&self, q: RAGQuery, strategy: SearchStrategy) {
         let this = self.clone();
         // clear previous error and mark as busy
@@ -88,52 +151,398 @@ impl GraphRAGStateContext {
         });
     }
 
+    /// Rebuild the index from every document in the knowledge base
     pub fn reindex(&self) {
-        // Placeholder for kicking indexing pipeline.
+        self.run_index(false);
+    }
+
+    /// Index only documents added or changed since the last build and drop removed ones
+    pub fn reindex_changed(&self) {
+        self.run_index(true);
+    }
+
+    /// Move documents embedded with another model than their collection's to that model.
+    /// Batches run in the background while searches keep using the old vectors; the new
+    /// ones replace them in one index generation when every document is done.
+    pub fn start_reembed(&self) {
+        if self.indexing.get_untracked() || self.reembed.with_untracked(|r| r.is_some()) {
+            return;
+        }
+        let pipeline = GraphRAGPipeline::new();
+        let default_model = pipeline.config().embedding_model.clone();
+        let collections = Collections::load().unwrap_or_default();
+        let active = match IndexSnapshot::for_query(None) {
+            Ok(snapshot) => snapshot,
+            Err(e) => {
+                log::error!("Re-embedding not started: {}", e);
+                return;
+            }
+        };
+        let items = reembed::plan(&active.documents, &active.vectors, |d| {
+            collections.embedding_model_for(d, &default_model)
+        });
+        if items.is_empty() {
+            return;
+        }
+        self.reembed.set(Some(ReembedProgress::new(&items)));
+        self.reembed_cancelled.set(false);
+        if let Some(bus) = self.events {
+            bus.record(
+                ActivityCategory::Knowledge,
+                format!("Re-embedding {} document(s)", items.len()),
+            );
+        }
+        let this = self.clone();
+        spawn_local(async move {
+            let mut staged = VectorIndex::default();
+            let mut done_chunks = 0usize;
+            let mut spent_ms = 0.0f64;
+            for (i, item) in items.iter().enumerate() {
+                // A paused migration waits between documents
+                while this
+                    .reembed
+                    .with_untracked(|r| r.as_ref().is_some_and(|r| r.paused))
+                    && !this.reembed_cancelled.get_untracked()
+                {
+                    sleep_ms(250).await;
+                }
+                if this.reembed_cancelled.get_untracked() {
+                    this.reembed.set(None);
+                    if let Some(bus) = this.events {
+                        bus.record(ActivityCategory::Knowledge, "Re-embedding cancelled");
+                    }
+                    return;
+                }
+                let t0 = js_sys::Date::now();
+                let embedded = match embed_texts(&item.model, &item.chunks).await {
+                    Ok(vectors) => staged.upsert_document(&item.doc_id, &item.model, vectors),
+                    Err(e) => Err(e),
+                };
+                if let Err(e) = embedded {
+                    log::error!("Re-embedding failed: {}", e);
+                    if let Some(bus) = this.events {
+                        bus.error("Re-embedding failed", e.to_string());
+                    }
+                    NotificationUtils::task_finished(LongTask::Reembed, Err(&e.to_string()));
+                    this.reembed.set(None);
+                    return;
+                }
+                spent_ms += js_sys::Date::now() - t0;
+                done_chunks += item.chunks.len();
+                this.reembed.update(|r| {
+                    if let Some(r) = r {
+                        r.done_docs = i + 1;
+                        r.done_chunks = done_chunks;
+                        r.eta_ms = reembed::estimate_ms(
+                            r.total_chunks.saturating_sub(done_chunks),
+                            done_chunks,
+                            spent_ms,
+                        );
+                    }
+                });
+            }
+
+            // Switch atomically; wait for an indexing run to finish first
+            while this.indexing.get_untracked() {
+                sleep_ms(500).await;
+            }
+            let result = IndexSnapshot::begin_build().and_then(|mut shadow| {
+                let swapped =
+                    reembed::apply(&mut shadow.index.vectors, &staged, &shadow.index.documents);
+                for d in shadow.index.documents.iter_mut() {
+                    if swapped.contains(&d.id) {
+                        d.embedding_model = staged.model_of(&d.id).map(str::to_string);
+                    }
+                }
+                shadow.commit().map(|_| swapped.len())
+            });
+            match result {
+                Ok(count) => {
+                    let summary = format!("Re-embedded {} document(s)", count);
+                    NotificationUtils::task_finished(LongTask::Reembed, Ok(&summary));
+                    if let Some(bus) = this.events {
+                        bus.record(ActivityCategory::Knowledge, summary);
+                    }
+                }
+                Err(e) => {
+                    log::error!("Re-embedding not applied: {}", e);
+                    if let Some(bus) = this.events {
+                        bus.error("Re-embedding not applied", e.to_string());
+                    }
+                    NotificationUtils::task_finished(LongTask::Reembed, Err(&e.to_string()));
+                }
+            }
+            this.reembed.set(None);
+        });
+    }
+
+    /// Merge entity nodes naming the same entity in the active index, e.g. after
+    /// turning resolution on. Runs as its own index generation.
+    pub fn resolve_entities(&self) {
+        if self.indexing.get_untracked() {
+            return;
+        }
+        let config = GraphRAGPipeline::new().config().clone();
+        let result = IndexSnapshot::begin_build().and_then(|mut shadow| {
+            let report = resolve_entities(&mut shadow.index.graph);
+            if report.merged.is_empty() {
+                // Nothing changed: the build is dropped uncommitted
+                return Ok(report);
+            }
+            analyse_graph(&mut shadow.index, &config);
+            shadow.commit().map(|_| report)
+        });
+        match result {
+            Ok(report) if report.merged.is_empty() => {
+                if let Some(bus) = self.events {
+                    bus.record(ActivityCategory::Knowledge, "No duplicate entities found");
+                }
+            }
+            Ok(report) => {
+                if let Some(bus) = self.events {
+                    let detail = report
+                        .merged
+                        .iter()
+                        .map(|m| format!("{} ← {}", m.label, m.merged_labels.join(", ")))
+                        .collect::<Vec<_>>()
+                        .join("\n");
+                    bus.emit(
+                        ActivityEvent::new(
+                            ActivityCategory::Knowledge,
+                            format!("Merged {} duplicate entities", report.merged_nodes()),
+                        )
+                        .with_detail(detail),
+                    );
+                }
+            }
+            Err(e) => {
+                log::error!("Entity resolution failed: {}", e);
+                if let Some(bus) = self.events {
+                    bus.error("Entity resolution failed", e.to_string());
+                }
+            }
+        }
+    }
+
+    /// Load an external graph into the index, merged with the current graph or in its
+    /// place. Edges left pointing at missing nodes refuse the import.
+    pub fn import_graph(
+        &self,
+        imported: GraphStore,
+        mode: GraphImportMode,
+    ) -> Result<GraphImportSummary, AppError> {
+        if self.indexing.get_untracked() {
+            return Err(AppError::IndexingError(
+                "wait for indexing to finish before importing a graph".to_string(),
+            ));
+        }
+        let config = GraphRAGPipeline::new().config().clone();
+        let mut shadow = IndexSnapshot::begin_build()?;
+        if mode == GraphImportMode::Replace {
+            shadow.index.graph = GraphStore::new();
+        }
+        let summary = shadow.index.graph.merge_from(imported);
+        let problems = shadow.index.graph.validate();
+        if !problems.is_empty() {
+            return Err(AppError::validation(problems.join("; ")));
+        }
+        analyse_graph(&mut shadow.index, &config);
+        shadow.commit()?;
+        if let Some(bus) = self.events {
+            bus.record(
+                ActivityCategory::Knowledge,
+                format!(
+                    "Imported a graph: {} node(s) added, {} updated, {} edge(s) added",
+                    summary.nodes_added, summary.nodes_updated, summary.edges_added
+                ),
+            );
+        }
+        Ok(summary)
+    }
+
+    pub fn toggle_reembed_pause(&self) {
+        self.reembed.update(|r| {
+            if let Some(r) = r {
+                r.paused = !r.paused;
+            }
+        });
+    }
+
+    /// Stop the migration; the staged vectors are discarded and the old ones stay
+    pub fn cancel_reembed(&self) {
+        self.reembed_cancelled.set(true);
+    }
+
+    fn run_index(&self, incremental: bool) {
+        if self.indexing.get_untracked() || self.reembed.with_untracked(|r| r.is_some()) {
+            return;
+        }
         let this = self.clone();
         self.indexing.set(true);
         this.index_progress.set(Some(0.0));
+        // Load real documents for indexing from shared storage context via Leptos context
+        let kctx: KnowledgeStorageContext = use_context().unwrap_or_default();
         spawn_local(async move {
             let pipeline = GraphRAGPipeline::new();
-            // Load real documents for indexing from shared storage context via Leptos context
-            let kctx: KnowledgeStorageContext = use_context().unwrap_or_default();
-            let docs = kctx.get_documents_for_indexing();
+            let all_docs = kctx.get_documents_for_indexing();
+            let staleness = IndexStaleness::compute(IndexManifest::load().as_ref(), &all_docs);
+            // Previous entries of re-indexed or deleted documents are replaced, not duplicated
+            let (mut docs, outdated) = if incremental {
+                let pending: Vec<&String> = staleness
+                    .added
+                    .iter()
+                    .chain(staleness.changed.iter())
+                    .collect();
+                let docs: Vec<_> = all_docs
+                    .iter()
+                    .filter(|d| pending.contains(&&d.title))
+                    .cloned()
+                    .collect();
+                (docs, staleness.outdated_titles())
+            } else {
+                let mut titles: Vec<String> = all_docs.iter().map(|d| d.title.clone()).collect();
+                titles.extend(staleness.removed.iter().cloned());
+                (all_docs.clone(), titles)
+            };
+            // Build the next generation in a shadow copy; searches keep reading the
+            // active one until the commit below
+            let mut shadow = match IndexSnapshot::begin_build() {
+                Ok(shadow) => shadow,
+                Err(e) => {
+                    log::error!("Indexing not started: {}", e);
+                    if let Some(bus) = this.events {
+                        bus.error("Indexing not started", e.to_string());
+                    }
+                    this.index_progress.set(None);
+                    this.indexing.set(false);
+                    return;
+                }
+            };
+            shadow.index.remove_titles(&outdated);
             // Simulate progress in a few steps
-            async fn sleep_ms(ms: i32) {
-                let p = Promise::new(&mut |resolve, _reject| {
-                    let _ = window()
-                        .unwrap()
-                        .set_timeout_with_callback_and_timeout_and_arguments_0(&resolve, ms);
-                });
-                let _ = JsFuture::from(p).await;
-            }
-
             sleep_ms(150).await;
             this.index_progress.set(Some(0.3));
             sleep_ms(200).await;
             this.index_progress.set(Some(0.7));
 
-            // Index the collected documents
-            let _ = pipeline.index_documents(&docs);
-
-            // Extract simple entities/relations and persist to GraphStore (basic migration if empty)
-            let (nodes, edges) = extract_entities_relations(&docs);
-            let _ = kctx.update_graph_store(|store| {
-                let mut existing_node_ids: HashSet<String> =
-                    store.nodes.iter().map(|n| n.id.clone()).collect();
-                let mut existing_edge_ids: HashSet<String> =
-                    store.edges.iter().map(|e| e.id.clone()).collect();
-                for n in &nodes {
-                    if existing_node_ids.insert(n.id.clone()) {
-                        store.nodes.push(n.clone());
+            // Embed chunks for dense retrieval; lexical search keeps working if this fails
+            let config = pipeline.config().clone();
+            if config.embeddings_enabled && !docs.is_empty() {
+                let mut vectors = shadow.index.vectors.clone();
+                // Each document is embedded with its collection's model
+                let collections = Collections::load().unwrap_or_default();
+                let model_for =
+                    |d: &DocumentIndex| collections.embedding_model_for(d, &config.embedding_model);
+                match embed_documents(&docs, model_for, &mut vectors).await {
+                    Ok(chunks) => {
+                        shadow.index.vectors = vectors;
+                        for d in docs.iter_mut() {
+                            d.embedding_model = Some(model_for(d));
+                        }
+                        log::info!("Embedded {} chunk(s)", chunks);
+                    }
+                    Err(e) => {
+                        log::error!("Embedding documents failed: {}", e);
+                        if let Some(bus) = this.events {
+                            bus.error("Embedding documents failed", e.to_string());
+                        }
                     }
                 }
-                for e in &edges {
-                    if existing_edge_ids.insert(e.id.clone()) {
-                        store.edges.push(e.clone());
+            }
+
+            // Index the collected documents
+            shadow
+                .index
+                .upsert_documents(&docs, config.batch_size, js_sys::Date::now());
+
+            // Extract entities/relations into the shadow GraphStore, with the loaded chat
+            // model when LLM extraction is enabled
+            let (nodes, edges, extractor) = match (config.llm_extraction_enabled, loaded_engine()) {
+                (true, Some((model_id, engine))) => {
+                    let progress = this.index_progress;
+                    let (nodes, edges) = extract_entities_relations_llm(
+                        &engine,
+                        &model_id,
+                        &docs,
+                        move |done, total| {
+                            progress.set(Some(0.7 + 0.25 * done as f32 / total.max(1) as f32));
+                        },
+                    )
+                    .await;
+                    (nodes, edges, model_id)
+                }
+                (llm, _) => {
+                    if llm {
+                        log::warn!("LLM extraction skipped: no chat model is loaded");
                     }
+                    let (nodes, edges) = extract_entities_relations(&docs);
+                    (nodes, edges, "heuristic".to_string())
                 }
-            });
+            };
+            let store = &mut shadow.index.graph;
+            let mut existing_node_ids: HashSet<String> =
+                store.nodes.iter().map(|n| n.id.clone()).collect();
+            let mut existing_edge_ids: HashSet<String> =
+                store.edges.iter().map(|e| e.id.clone()).collect();
+            for n in &nodes {
+                if existing_node_ids.insert(n.id.clone()) {
+                    store.nodes.push(n.clone());
+                }
+            }
+            for e in &edges {
+                if existing_edge_ids.insert(e.id.clone()) {
+                    store.edges.push(e.clone());
+                }
+            }
+            // Entities named differently in the new documents join the existing ones
+            let resolved = if config.entity_resolution_enabled {
+                resolve_entities(store).merged_nodes()
+            } else {
+                0
+            };
+            let communities = analyse_graph(&mut shadow.index, &config);
+
+            // Swap the new generation in: every record is written before it becomes active
+            if let Err(e) = shadow.commit() {
+                log::error!("Indexing failed: {}", e);
+                if let Some(bus) = this.events {
+                    bus.error("Indexing failed", e.to_string());
+                }
+                NotificationUtils::task_finished(LongTask::Reindex, Err(&e.to_string()));
+                this.index_progress.set(None);
+                this.indexing.set(false);
+                return;
+            }
+
+            let summary = if incremental {
+                format!(
+                    "Incrementally indexed {} document(s), dropped {}",
+                    docs.len(),
+                    staleness.removed.len()
+                )
+            } else {
+                format!("Indexed {} document(s)", docs.len())
+            };
+            NotificationUtils::task_finished(LongTask::Reindex, Ok(&summary));
+            if let Some(bus) = this.events {
+                bus.emit(
+                    ActivityEvent::new(ActivityCategory::Knowledge, summary).with_detail(format!(
+                        "{} entities, {} relations extracted by {}, {} merged, {} communities",
+                        nodes.len(),
+                        edges.len(),
+                        extractor,
+                        resolved,
+                        communities
+                    )),
+                );
+            }
+
+            let manifest = IndexManifest::from_docs(&all_docs, js_sys::Date::now());
+            if let Err(e) = manifest.save() {
+                log::error!("Failed to save index manifest: {}", e);
+            }
+            this.staleness
+                .set(IndexStaleness::compute(Some(&manifest), &all_docs));
 
             this.index_progress.set(Some(1.0));
             sleep_ms(120).await;
@@ -143,9 +552,49 @@ impl GraphRAGStateContext {
     }
 }
 
+/// Recompute communities and node importance after the graph changed. Returns the
+/// number of communities, 0 when detection is off.
+fn analyse_graph(index: &mut IndexSnapshot, config: &GraphRAGConfig) -> usize {
+    // Community assignments are recomputed over the whole graph on every build
+    let mut communities = 0;
+    if config.community_detection_enabled {
+        communities = CommunityDetectionEngine::new(CommunityDetectionConfig::default())
+            .assign_communities(&mut index.graph);
+    }
+    // Node importance is cached per graph version, so unchanged graphs are not rescored
+    if config.pagerank_enabled {
+        let version = graph_version(&index.graph);
+        let cached = index.importance.as_ref().map(|i| i.graph_version);
+        if cached != Some(version) {
+            index.importance = Some(NodeImportance::compute(
+                &index.graph,
+                PageRankConfig::default(),
+            ));
+        }
+    }
+    communities
+}
+
+async fn sleep_ms(ms: i32) {
+    let p = Promise::new(&mut |resolve, _reject| {
+        let _ = window()
+            .unwrap()
+            .set_timeout_with_callback_and_timeout_and_arguments_0(&resolve, ms);
+    });
+    let _ = JsFuture::from(p).await;
+}
+
+fn current_staleness() -> IndexStaleness {
+    IndexStaleness::compute(
+        IndexManifest::load().as_ref(),
+        &KnowledgeStorageContext::new().get_documents_for_indexing(),
+    )
+}
+
 #[component]
 pub fn GraphRAGStateProvider(children: Children) -> impl IntoView {
     let ctx = GraphRAGStateContext::new();
+    ctx.sync_with_other_tabs();
     provide_context(ctx);
     children()
 }
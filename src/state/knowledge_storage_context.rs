@@ -1,11 +1,18 @@
+use crate::features::graphrag::chunking::chunk_document;
 use crate::features::graphrag::traversal::{bfs, dfs, TraversalFilters, TraversalResult};
+use crate::features::graphrag::GraphRAGPipeline;
 use crate::models::app::AppError;
 use crate::models::graph_store::GraphStore;
 use crate::models::graphrag::{DocumentIndex, ProcessingStatus};
-use crate::utils::storage::StorageUtils;
+use crate::storage::persistent::PersistentStore;
+use crate::utils::code::{code_chunks, CodeLanguage};
+use crate::utils::pdf::PdfUtils;
+use crate::utils::structured::{field_chunks, StructuredFormat};
+use crate::utils::tables::{is_csv_document, is_table_document, Table};
+use crate::utils::web_page::is_web_source;
 
 /// Minimal shared storage context that exposes documents for GraphRAG indexing.
-/// It reads a plain text buffer saved by the Document Manager from localStorage
+/// It reads a plain text buffer saved by the Document Manager from persistent storage
 /// and converts it into `DocumentIndex` entries.
 #[derive(Clone, Default)]
 pub struct KnowledgeStorageContext;
@@ -16,22 +23,55 @@ impl KnowledgeStorageContext {
     }
 
     /// Storage key where Document Manager persists the aggregated uploaded content.
-    const BUFFER_KEY: &'static str = "knowledge_upload_buffer_v1";
+    pub const BUFFER_KEY: &'static str = "knowledge_upload_buffer_v1";
 
-    /// Load the raw buffer from localStorage.
+    /// Load the raw buffer from persistent storage.
     fn load_buffer(&self) -> Option<String> {
-        match StorageUtils::retrieve_local::<String>(Self::BUFFER_KEY) {
+        match PersistentStore::read::<String>(Self::BUFFER_KEY) {
             Ok(Some(s)) => Some(s),
             _ => None,
         }
     }
 
+    /// Replace the buffer segment for file `name` (or append one) and persist the buffer.
+    /// Returns the updated buffer.
+    pub fn upsert_buffer_document(&self, name: &str, content: &str) -> Result<String, AppError> {
+        let buf = upsert_buffer_segment(&self.load_buffer().unwrap_or_default(), name, content);
+        PersistentStore::write(Self::BUFFER_KEY, &buf)?;
+        Ok(buf)
+    }
+
+    /// Whether the buffer has a segment for file `name`
+    pub fn has_buffer_document(&self, name: &str) -> bool {
+        self.load_buffer()
+            .is_some_and(|buf| buf.split("\n\n---\n\n").any(|s| is_segment_of(s, name)))
+    }
+
+    /// Text of file `name` as stored in the buffer, before it was cut into passages
+    pub fn buffer_document(&self, name: &str) -> Option<String> {
+        buffer_segment_content(&self.load_buffer()?, name)
+    }
+
+    /// Name and text of every file in the buffer
+    pub fn buffer_documents(&self) -> Vec<(String, String)> {
+        buffer_segments(&self.load_buffer().unwrap_or_default())
+    }
+
+    /// Drop the buffer segment for file `name` and persist the buffer. Returns the
+    /// updated buffer.
+    pub fn remove_buffer_document(&self, name: &str) -> Result<String, AppError> {
+        let buf = remove_buffer_segment(&self.load_buffer().unwrap_or_default(), name);
+        PersistentStore::write(Self::BUFFER_KEY, &buf)?;
+        Ok(buf)
+    }
+
     /// Parse the raw buffer into `DocumentIndex` entries.
     /// The buffer format is a simple concatenation of segments:
     ///   "# File: <name>\n\n<content>\n\n---\n\n# File: ..."
     pub fn get_documents_for_indexing(&self) -> Vec<DocumentIndex> {
         let mut out = Vec::new();
         let now = js_sys::Date::now();
+        let config = GraphRAGPipeline::new().config().clone();
         let Some(buf) = self.load_buffer() else {
             return out;
         };
@@ -56,28 +96,171 @@ impl KnowledgeStorageContext {
                 if title.is_empty() && content.is_empty() {
                     continue;
                 }
+                // Pages added by URL hold extracted text whatever the URL ends with
+                let web = is_web_source(&title);
+
+                // PDFs become one document per page so sources cite the page
+                if !web && title.to_lowercase().ends_with(".pdf") {
+                    for (page, text) in PdfUtils::split_pages(&content) {
+                        out.push(DocumentIndex {
+                            id: format!("{}:{}#page={}", now, title, page),
+                            title: format!("{} (p. {})", title, page),
+                            size_bytes: text.len() as u64,
+                            content: text,
+                            file_type: "pdf".to_string(),
+                            created_at: now,
+                            indexed_at: now,
+                            node_count: 0,
+                            embedding_model: None,
+                            processing_status: ProcessingStatus::Pending,
+                            symbols: Vec::new(),
+                            parent: Some(title.clone()),
+                        });
+                    }
+                    continue;
+                }
 
-                let file_type = if title.ends_with(".md") || title.ends_with(".markdown") {
+                // Pasted tables and CSV datasets become one document per row, each cell
+                // named by its column
+                let table = if web {
+                    None
+                } else if is_table_document(&title) {
+                    Table::from_markdown(&content).map(|t| (t, "table"))
+                } else if is_csv_document(&title) {
+                    Table::from_csv(&content).map(|t| (t, "csv"))
+                } else {
+                    None
+                };
+                if let Some((table, file_type)) = table {
+                    out.extend(row_documents(&table, &title, file_type, now));
+                    continue;
+                }
+
+                // JSON/YAML become one document per field path so sources cite the field;
+                // unparseable files are indexed as plain text below
+                if let Some(format) = StructuredFormat::of(&title).filter(|_| !web) {
+                    if let Ok(value) = format.parse(&content) {
+                        // Lists of records are datasets, indexed a row at a time
+                        if let Some(table) = Table::from_records(&value) {
+                            out.extend(row_documents(&table, &title, format.file_type(), now));
+                            continue;
+                        }
+                        for chunk in field_chunks(&value) {
+                            let (id, chunk_title) = if chunk.path.is_empty() {
+                                (format!("{}:{}", now, title), title.clone())
+                            } else {
+                                (
+                                    format!("{}:{}#path={}", now, title, chunk.path),
+                                    format!("{} ({})", title, chunk.path),
+                                )
+                            };
+                            out.push(DocumentIndex {
+                                id,
+                                title: chunk_title,
+                                size_bytes: chunk.text.len() as u64,
+                                content: chunk.text,
+                                file_type: format.file_type().to_string(),
+                                created_at: now,
+                                indexed_at: now,
+                                node_count: 0,
+                                embedding_model: None,
+                                processing_status: ProcessingStatus::Pending,
+                                symbols: Vec::new(),
+                                parent: (!chunk.path.is_empty()).then(|| title.clone()),
+                            });
+                        }
+                        continue;
+                    }
+                }
+
+                // Source files become one document per definition, carrying its symbols
+                if let Some(language) = CodeLanguage::of(&title).filter(|_| !web) {
+                    for chunk in code_chunks(language, &content) {
+                        let chunk_title = match chunk.symbols.first() {
+                            Some(symbol) => format!("{} ({})", title, symbol),
+                            None => format!("{} (line {})", title, chunk.start_line),
+                        };
+                        out.push(DocumentIndex {
+                            id: format!("{}:{}#line={}", now, title, chunk.start_line),
+                            title: chunk_title,
+                            size_bytes: chunk.text.len() as u64,
+                            content: chunk.text,
+                            file_type: language.file_type().to_string(),
+                            created_at: now,
+                            indexed_at: now,
+                            node_count: 0,
+                            embedding_model: None,
+                            processing_status: ProcessingStatus::Pending,
+                            symbols: chunk.symbols,
+                            parent: Some(title.clone()),
+                        });
+                    }
+                    continue;
+                }
+
+                let file_type = if web {
+                    "web"
+                } else if title.ends_with(".md") || title.ends_with(".markdown") {
                     "markdown"
                 } else if title.ends_with(".txt") {
                     "text"
+                } else if title.ends_with(".html") || title.ends_with(".htm") {
+                    "html"
+                } else if title.ends_with(".epub") {
+                    "epub"
+                } else if let Some(format) = StructuredFormat::of(&title) {
+                    format.file_type()
                 } else {
                     "unknown"
                 };
-                let size_bytes = content.len() as u64;
 
-                out.push(DocumentIndex {
-                    id: format!("{}:{}", now, title),
-                    title,
-                    content,
-                    file_type: file_type.to_string(),
-                    size_bytes,
-                    created_at: now,
-                    indexed_at: now,
-                    node_count: 0,
-                    embedding_model: None,
-                    processing_status: ProcessingStatus::Pending,
-                });
+                // Prose is cut into passages by the configured strategy; a file that fits
+                // in one passage stays a single entry
+                let chunks = chunk_document(
+                    &content,
+                    config.chunking_strategy,
+                    config.chunk_size,
+                    config.chunk_overlap,
+                );
+                if chunks.len() <= 1 {
+                    let size_bytes = content.len() as u64;
+                    out.push(DocumentIndex {
+                        id: format!("{}:{}", now, title),
+                        title,
+                        content,
+                        file_type: file_type.to_string(),
+                        size_bytes,
+                        created_at: now,
+                        indexed_at: now,
+                        node_count: 0,
+                        embedding_model: None,
+                        processing_status: ProcessingStatus::Pending,
+                        symbols: Vec::new(),
+                        parent: None,
+                    });
+                    continue;
+                }
+                for (i, chunk) in chunks.into_iter().enumerate() {
+                    let part = i + 1;
+                    let chunk_title = match &chunk.heading {
+                        Some(heading) => format!("{} (part {}: {})", title, part, heading),
+                        None => format!("{} (part {})", title, part),
+                    };
+                    out.push(DocumentIndex {
+                        id: format!("{}:{}#chunk={}", now, title, part),
+                        title: chunk_title,
+                        size_bytes: chunk.text.len() as u64,
+                        content: chunk.text,
+                        file_type: file_type.to_string(),
+                        created_at: now,
+                        indexed_at: now,
+                        node_count: 0,
+                        embedding_model: None,
+                        processing_status: ProcessingStatus::Pending,
+                        symbols: Vec::new(),
+                        parent: Some(title.clone()),
+                    });
+                }
             } else {
                 // Fallback: treat whole segment as a single unnamed document
                 let content = seg.to_string();
@@ -95,18 +278,20 @@ impl KnowledgeStorageContext {
                     node_count: 0,
                     embedding_model: None,
                     processing_status: ProcessingStatus::Pending,
+                    symbols: Vec::new(),
+                    parent: None,
                 });
             }
         }
         out
     }
 
-    /// Load the current GraphStore from versioned localStorage or return an empty default store.
+    /// Load the current GraphStore from persistent storage or return an empty default store.
     pub fn load_graph_store(&self) -> Result<GraphStore, AppError> {
         GraphStore::load()
     }
 
-    /// Persist a GraphStore to versioned localStorage.
+    /// Persist a GraphStore to persistent storage.
     pub fn save_graph_store(&self, store: &GraphStore) -> Result<(), AppError> {
         store.save()
     }
@@ -165,3 +350,115 @@ impl KnowledgeStorageContext {
         Ok(dfs(&store, start_id, &filters))
     }
 }
+
+/// One document per row of `table`, cited as `<file>#row=<n>`
+fn row_documents<'a>(
+    table: &'a Table,
+    title: &'a str,
+    file_type: &'a str,
+    now: f64,
+) -> impl Iterator<Item = DocumentIndex> + 'a {
+    (0..table.rows.len()).map(move |row| {
+        let text = table.row_text(row);
+        DocumentIndex {
+            id: format!("{}:{}#row={}", now, title, row + 1),
+            title: format!("{} (row {})", title, row + 1),
+            size_bytes: text.len() as u64,
+            content: text,
+            file_type: file_type.to_string(),
+            created_at: now,
+            indexed_at: now,
+            node_count: 0,
+            embedding_model: None,
+            processing_status: ProcessingStatus::Pending,
+            symbols: Vec::new(),
+            parent: Some(title.to_string()),
+        }
+    })
+}
+
+// Whether a buffer segment holds file `name`
+fn is_segment_of(segment: &str, name: &str) -> bool {
+    segment
+        .trim_start()
+        .lines()
+        .next()
+        .and_then(|l| l.trim().strip_prefix("# File:"))
+        .is_some_and(|n| n.trim() == name)
+}
+
+/// Buffer segments are "# File: <name>\n\n<content>" joined by "\n\n---\n\n"
+fn upsert_buffer_segment(buf: &str, name: &str, content: &str) -> String {
+    let segment = format!("# File: {}\n\n{}", name, content.trim());
+    let mut segments: Vec<String> = buf
+        .split("\n\n---\n\n")
+        .filter(|s| !s.trim().is_empty())
+        .map(|s| s.to_string())
+        .collect();
+    match segments.iter_mut().find(|s| is_segment_of(s, name)) {
+        Some(slot) => *slot = segment,
+        None => segments.push(segment),
+    }
+    segments.join("\n\n---\n\n")
+}
+
+fn buffer_segment_content(buf: &str, name: &str) -> Option<String> {
+    let segment = buf.split("\n\n---\n\n").find(|s| is_segment_of(s, name))?;
+    let (_, content) = segment
+        .trim_start()
+        .split_once('\n')
+        .unwrap_or((segment, ""));
+    Some(content.trim().to_string())
+}
+
+fn buffer_segments(buf: &str) -> Vec<(String, String)> {
+    buf.split("\n\n---\n\n")
+        .filter_map(|segment| {
+            let segment = segment.trim_start();
+            let (header, content) = segment.split_once('\n').unwrap_or((segment, ""));
+            let name = header.trim().strip_prefix("# File:")?.trim();
+            Some((name.to_string(), content.trim().to_string()))
+        })
+        .collect()
+}
+
+fn remove_buffer_segment(buf: &str, name: &str) -> String {
+    buf.split("\n\n---\n\n")
+        .filter(|s| !s.trim().is_empty() && !is_segment_of(s, name))
+        .collect::<Vec<_>>()
+        .join("\n\n---\n\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_upsert_buffer_segment() {
+        let buf = upsert_buffer_segment("", "a.md", "first");
+        assert_eq!(buf, "# File: a.md\n\nfirst");
+        let buf = upsert_buffer_segment(&buf, "b.md", "second");
+        let buf = upsert_buffer_segment(&buf, "a.md", "replaced");
+        assert_eq!(
+            buf,
+            "# File: a.md\n\nreplaced\n\n---\n\n# File: b.md\n\nsecond"
+        );
+        assert_eq!(
+            remove_buffer_segment(&buf, "a.md"),
+            "# File: b.md\n\nsecond"
+        );
+        assert_eq!(remove_buffer_segment(&buf, "a"), buf);
+        assert_eq!(
+            buffer_segment_content(&buf, "b.md").as_deref(),
+            Some("second")
+        );
+        assert_eq!(buffer_segment_content(&buf, "c.md"), None);
+        assert_eq!(
+            buffer_segments(&buf),
+            vec![
+                ("a.md".to_string(), "replaced".to_string()),
+                ("b.md".to_string(), "second".to_string())
+            ]
+        );
+    }
+}
@@ -4,10 +4,13 @@ pub mod actions_simple;
 pub mod app_state_simple;
 pub mod conversation_state_simple;
 pub mod crm_state_simple;
+pub mod event_bus_simple;
 pub mod graphrag_state_simple;
 pub mod integration_test;
 pub mod knowledge_storage_context;
 pub mod mod_simple;
+pub mod narration_simple;
+pub mod tasks_state_simple;
 pub mod webllm_state_simple;
 
 // Re-export all state management functionality
@@ -17,7 +20,10 @@ pub use conversation_state_simple::{
     use_conversation_state, ConversationStateContext, ConversationStateProvider,
 };
 pub use crm_state_simple::{use_crm_state, CRMStateContext, CRMStateProvider};
+pub use event_bus_simple::{use_event_bus, EventBusContext, EventBusProvider};
 pub use graphrag_state_simple::{use_graphrag_state, GraphRAGStateContext, GraphRAGStateProvider};
 pub use knowledge_storage_context::KnowledgeStorageContext;
 pub use mod_simple::*;
+pub use narration_simple::{use_narration, NarrationContext, Playback};
+pub use tasks_state_simple::{use_tasks_state, TasksStateContext, TasksStateProvider};
 pub use webllm_state_simple::{use_webllm_state, WebLLMStateContext, WebLLMStateProvider};
@@ -0,0 +1,143 @@
+use crate::storage::{CachePolicy, TieredCache};
+use crate::utils::tts::{self, Narrator};
+use leptos::prelude::*;
+
+const NARRATION_RATE_KEY: &str = "narration_rate";
+
+/// What is being read aloud right now
+#[derive(Clone, Debug, PartialEq)]
+pub struct Playback {
+    /// Message or document id the text belongs to
+    pub owner: String,
+    pub paragraph: usize,
+    pub paragraphs: usize,
+    pub paused: bool,
+}
+
+/// Read-aloud shared by message replies and the document reader; starting one stops
+/// the other, since the browser has a single speech queue
+#[derive(Clone, Copy)]
+pub struct NarrationContext {
+    playback: RwSignal<Option<Playback>>,
+    rate: RwSignal<f32>,
+    narrator: StoredValue<Narrator, LocalStorage>,
+}
+
+impl Default for NarrationContext {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NarrationContext {
+    pub fn new() -> Self {
+        let rate = TieredCache::get::<f32>(NARRATION_RATE_KEY)
+            .unwrap_or(1.0)
+            .clamp(tts::MIN_RATE, tts::MAX_RATE);
+        Self {
+            playback: RwSignal::new(None),
+            rate: RwSignal::new(rate),
+            narrator: StoredValue::new_local(Narrator::new()),
+        }
+    }
+
+    pub fn playback(&self) -> Signal<Option<Playback>> {
+        let playback = self.playback;
+        Signal::derive(move || playback.get())
+    }
+
+    pub fn rate(&self) -> Signal<f32> {
+        let rate = self.rate;
+        Signal::derive(move || rate.get())
+    }
+
+    /// Whether `owner` is being read (paused or not)
+    pub fn is_reading(&self, owner: &str) -> bool {
+        self.playback
+            .with(|p| p.as_ref().is_some_and(|p| p.owner == owner))
+    }
+
+    /// Read `paragraphs` of `owner` from `start`, replacing any other playback
+    pub fn play(&self, owner: &str, paragraphs: Vec<String>, start: usize) {
+        if paragraphs.is_empty() {
+            return;
+        }
+        let playback = self.playback;
+        playback.set(Some(Playback {
+            owner: owner.to_string(),
+            paragraph: start,
+            paragraphs: paragraphs.len(),
+            paused: false,
+        }));
+        let rate = self.rate.get_untracked();
+        self.narrator.with_value(|n| {
+            n.play(
+                &paragraphs,
+                start,
+                rate,
+                move |i| {
+                    playback.update(|p| {
+                        if let Some(p) = p {
+                            p.paragraph = i;
+                        }
+                    })
+                },
+                move || playback.set(None),
+            )
+        });
+    }
+
+    pub fn toggle_pause(&self) {
+        let Some(paused) = self
+            .playback
+            .with_untracked(|p| p.as_ref().map(|p| p.paused))
+        else {
+            return;
+        };
+        self.narrator
+            .with_value(|n| if paused { n.resume() } else { n.pause() });
+        self.playback.update(|p| {
+            if let Some(p) = p {
+                p.paused = !paused;
+            }
+        });
+    }
+
+    /// Jump to `paragraph` of the current playback; a paused playback resumes there
+    pub fn seek(&self, paragraph: usize) {
+        let Some(playback) = self.playback.get_untracked() else {
+            return;
+        };
+        let paragraph = paragraph.min(playback.paragraphs.saturating_sub(1));
+        self.narrator.with_value(|n| {
+            n.seek(paragraph);
+            if playback.paused {
+                n.resume();
+            }
+        });
+        self.playback.update(|p| {
+            if let Some(p) = p {
+                p.paragraph = paragraph;
+                p.paused = false;
+            }
+        });
+    }
+
+    pub fn set_rate(&self, rate: f32) {
+        let rate = rate.clamp(tts::MIN_RATE, tts::MAX_RATE);
+        self.rate.set(rate);
+        let _ = TieredCache::set(NARRATION_RATE_KEY, &rate, CachePolicy::PREFERENCE);
+        if self.playback.with_untracked(|p| p.is_some()) {
+            self.narrator.with_value(|n| n.set_rate(rate));
+        }
+    }
+
+    pub fn stop(&self) {
+        self.narrator.with_value(|n| n.stop());
+        self.playback.set(None);
+    }
+}
+
+pub fn use_narration() -> NarrationContext {
+    expect_context::<NarrationContext>()
+}
@@ -0,0 +1,129 @@
+use crate::models::app::AppError;
+use crate::models::tasks::Task;
+use crate::storage::tab_sync;
+use crate::utils::storage::StorageUtils;
+use leptos::prelude::*;
+
+pub const TASKS_KEY: &str = "tasks_v1";
+
+#[derive(Clone)]
+pub struct TasksStateContext {
+    tasks: RwSignal<Vec<Task>>,
+    last_error: RwSignal<Option<AppError>>,
+}
+
+impl TasksStateContext {
+    pub fn new() -> Self {
+        let ctx = Self {
+            tasks: RwSignal::new(Vec::new()),
+            last_error: RwSignal::new(None),
+        };
+        ctx.load_from_storage();
+        ctx
+    }
+
+    /// Reactive task list for views
+    pub fn tasks(&self) -> Signal<Vec<Task>> {
+        let tasks = self.tasks;
+        Signal::derive(move || tasks.get())
+    }
+    pub fn tasks_now(&self) -> Vec<Task> {
+        self.tasks.get_untracked()
+    }
+    pub fn last_error_now(&self) -> Option<AppError> {
+        self.last_error.get_untracked()
+    }
+
+    pub fn load_from_storage(&self) {
+        match StorageUtils::retrieve_local::<Vec<Task>>(TASKS_KEY) {
+            Ok(Some(v)) => self.tasks.set(v),
+            Ok(None) => {}
+            Err(e) => self.last_error.set(Some(e)),
+        }
+    }
+
+    /// Reload the tasks whenever another tab changes them
+    pub fn sync_with_other_tabs(&self) {
+        let ctx = self.clone();
+        tab_sync::watch(&[TASKS_KEY], move || ctx.load_from_storage());
+    }
+
+    fn persist(&self) {
+        if let Err(e) = StorageUtils::store_local(TASKS_KEY, &self.tasks.get_untracked()) {
+            self.last_error.set(Some(e));
+        }
+    }
+
+    /// Append tasks, skipping titles already open for the same conversation
+    pub fn add_tasks(&self, new_tasks: Vec<Task>) -> usize {
+        let mut added = 0;
+        self.tasks.update(|v| {
+            for t in new_tasks {
+                let dup = v.iter().any(|e| {
+                    !e.done
+                        && e.conversation_id == t.conversation_id
+                        && e.title.eq_ignore_ascii_case(&t.title)
+                });
+                if !dup {
+                    v.push(t);
+                    added += 1;
+                }
+            }
+        });
+        self.persist();
+        added
+    }
+
+    pub fn upsert_task(&self, task: Task) {
+        self.tasks.update(|v| {
+            if let Some(idx) = v.iter().position(|t| t.id == task.id) {
+                v[idx] = task;
+            } else {
+                v.push(task);
+            }
+        });
+        self.persist();
+    }
+
+    pub fn toggle_done(&self, id: &str) {
+        self.tasks.update(|v| {
+            if let Some(t) = v.iter_mut().find(|t| t.id == id) {
+                t.done = !t.done;
+                t.completed_at = if t.done {
+                    Some(js_sys::Date::now())
+                } else {
+                    None
+                };
+            }
+        });
+        self.persist();
+    }
+
+    pub fn delete_task(&self, id: &str) {
+        self.tasks.update(|v| v.retain(|t| t.id != id));
+        self.persist();
+    }
+
+    pub fn clear_completed(&self) {
+        self.tasks.update(|v| v.retain(|t| !t.done));
+        self.persist();
+    }
+}
+
+#[component]
+pub fn TasksStateProvider(children: Children) -> impl IntoView {
+    let ctx = TasksStateContext::new();
+    ctx.sync_with_other_tabs();
+    provide_context(ctx);
+    view! { {children()} }
+}
+
+pub fn use_tasks_state() -> TasksStateContext {
+    expect_context::<TasksStateContext>()
+}
+
+impl Default for TasksStateContext {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,8 +1,11 @@
+use crate::features::webllm::pool::EngineUsage;
 use crate::models::{
     app::AppError,
     webllm::{ChatSession, LLMModel, ModelStatus},
 };
+use crate::utils::capabilities::{self, DeviceCapabilities};
 use leptos::prelude::*;
+use leptos::task::spawn_local;
 use serde::{Deserialize, Serialize};
 
 /// Simplified WebLLM state for model management
@@ -15,6 +18,15 @@ pub struct WebLLMState {
     pub chat_session: Option<ChatSession>,
     pub is_generating: bool,
     pub error: Option<AppError>,
+    /// What this browser can run, once probed
+    #[serde(default)]
+    pub capabilities: Option<DeviceCapabilities>,
+    /// Loaded engines, the chat's and the warm ones
+    #[serde(default)]
+    pub engines: Vec<EngineUsage>,
+    /// Model being loaded in the background to switch to later
+    #[serde(default)]
+    pub prewarming: Option<String>,
 }
 
 impl Default for WebLLMStateContext {
@@ -33,6 +45,9 @@ impl Default for WebLLMState {
             chat_session: None,
             is_generating: false,
             error: None,
+            capabilities: None,
+            engines: Vec::new(),
+            prewarming: None,
         }
     }
 }
@@ -111,6 +126,34 @@ impl WebLLMStateContext {
         self.state.update(|s| s.error = None);
     }
 
+    // Device methods
+    pub fn get_capabilities(&self) -> Option<DeviceCapabilities> {
+        self.state.get().capabilities
+    }
+
+    pub fn set_capabilities(&self, capabilities: Option<DeviceCapabilities>) {
+        self.state.update(|s| s.capabilities = capabilities);
+    }
+
+    // Engine methods
+    pub fn get_engines(&self) -> Vec<EngineUsage> {
+        self.state.get().engines
+    }
+
+    /// Refresh the engine report after engines were loaded or unloaded
+    pub fn refresh_engines(&self) {
+        let engines = crate::webllm_binding::engine_usage();
+        self.state.update(|s| s.engines = engines);
+    }
+
+    pub fn get_prewarming(&self) -> Option<String> {
+        self.state.get().prewarming
+    }
+
+    pub fn set_prewarming(&self, model_id: Option<String>) {
+        self.state.update(|s| s.prewarming = model_id);
+    }
+
     // Chat session methods
     pub fn get_chat_session(&self) -> Option<ChatSession> {
         self.state.get().chat_session
@@ -125,6 +168,11 @@ impl WebLLMStateContext {
 #[component]
 pub fn WebLLMStateProvider(children: Children) -> impl IntoView {
     let webllm_state = WebLLMStateContext::new();
+    // Probed before the model list is shown, so incompatible models never appear
+    let probed = webllm_state.clone();
+    spawn_local(async move {
+        probed.set_capabilities(Some(capabilities::probe().await));
+    });
     provide_context(webllm_state);
     children()
 }
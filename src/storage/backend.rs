@@ -0,0 +1,58 @@
+use crate::models::app::{AppError, AppResult};
+use std::future::Future;
+use std::pin::Pin;
+
+/// Boxed future returned by `StorageBackend` methods (single-threaded wasm, not `Send`)
+pub type StorageFuture<'a, T> = Pin<Box<dyn Future<Output = AppResult<T>> + 'a>>;
+
+/// Async key/value persistence shared by conversations, documents and the graph store.
+/// Values are serialized JSON strings.
+pub trait StorageBackend {
+    fn name(&self) -> &'static str;
+    fn get<'a>(&'a self, key: &'a str) -> StorageFuture<'a, Option<String>>;
+    fn put<'a>(&'a self, key: &'a str, value: String) -> StorageFuture<'a, ()>;
+    fn delete<'a>(&'a self, key: &'a str) -> StorageFuture<'a, ()>;
+}
+
+/// localStorage-backed fallback for browsers without IndexedDB (e.g. some private modes)
+pub struct LocalStorageBackend;
+
+impl LocalStorageBackend {
+    fn storage() -> AppResult<web_sys::Storage> {
+        web_sys::window()
+            .ok_or_else(|| AppError::storage("Window not available".to_string()))?
+            .local_storage()
+            .map_err(|_| AppError::storage("LocalStorage not available".to_string()))?
+            .ok_or_else(|| AppError::storage("LocalStorage not supported".to_string()))
+    }
+}
+
+impl StorageBackend for LocalStorageBackend {
+    fn name(&self) -> &'static str {
+        "localStorage"
+    }
+
+    fn get<'a>(&'a self, key: &'a str) -> StorageFuture<'a, Option<String>> {
+        Box::pin(async move {
+            Self::storage()?
+                .get_item(key)
+                .map_err(|_| AppError::storage(format!("Failed to read key: {}", key)))
+        })
+    }
+
+    fn put<'a>(&'a self, key: &'a str, value: String) -> StorageFuture<'a, ()> {
+        Box::pin(async move {
+            Self::storage()?
+                .set_item(key, &value)
+                .map_err(|_| AppError::storage(format!("Failed to store data for key: {}", key)))
+        })
+    }
+
+    fn delete<'a>(&'a self, key: &'a str) -> StorageFuture<'a, ()> {
+        Box::pin(async move {
+            Self::storage()?
+                .remove_item(key)
+                .map_err(|_| AppError::storage(format!("Failed to remove key: {}", key)))
+        })
+    }
+}
@@ -0,0 +1,805 @@
+//! "Backup everything": conversations, documents, the knowledge graph, CRM, tasks and
+//! settings in one zip, and its restorer. Conversations go through
+//! `ConversationStorage::export_json` and `import_json`, settings through the settings
+//! bundle, and every other dataset is kept as its stored record. Caches and the long
+//! message chunks (rebuilt when conversations are imported) stay behind. What happens to
+//! a record that also exists here is up to the chosen `ConflictPolicy`.
+
+use super::conversation_storage::{ConversationStorage, CONVERSATIONS_KEY};
+use super::integrity::{KnownKey, StorageTier, KNOWN_KEYS};
+use super::persistent::PersistentStore;
+use crate::features::graphrag::content_store;
+use crate::models::app::AppResult;
+use crate::utils::settings_bundle::{self, ImportedSettings};
+use crate::utils::storage::StorageUtils;
+use crate::utils::zip::{ZipArchive, ZipWriter};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+
+pub const BACKUP_FORMAT: &str = "knowledge-chatbot-backup";
+/// Bumped when a backup written now could not be read by older builds
+pub const BACKUP_VERSION: u32 = 1;
+
+const MANIFEST_ENTRY: &str = "manifest.json";
+const CONVERSATIONS_ENTRY: &str = "conversations.json";
+const SETTINGS_ENTRY: &str = "settings.json";
+/// Full texts of stowed passages, by record key
+const PASSAGES_ENTRY: &str = "passages.json";
+
+/// Part of the app a backup covers; restoring can be limited to some of them
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackupSection {
+    Conversations,
+    Documents,
+    Graph,
+    Crm,
+    Tasks,
+    Settings,
+}
+
+impl BackupSection {
+    pub const ALL: [BackupSection; 6] = [
+        BackupSection::Conversations,
+        BackupSection::Documents,
+        BackupSection::Graph,
+        BackupSection::Crm,
+        BackupSection::Tasks,
+        BackupSection::Settings,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            BackupSection::Conversations => "Conversations",
+            BackupSection::Documents => "Documents",
+            BackupSection::Graph => "Knowledge graph and embeddings",
+            BackupSection::Crm => "CRM",
+            BackupSection::Tasks => "Tasks",
+            BackupSection::Settings => "Settings",
+        }
+    }
+
+    /// Section a stored record is backed up in. `None` for the records a backup leaves
+    /// out: caches, long message chunks, and settings the settings file carries.
+    pub fn of_key(key: &str) -> Option<Self> {
+        use crate::features::graphrag as rag;
+        use crate::models;
+        use crate::state::crm_state_simple as crm;
+        match key {
+            models::attachment::ATTACHMENTS_KEY_V1 => Some(BackupSection::Conversations),
+            "knowledge_upload_buffer_v1"
+            | rag::pipeline::DOCUMENT_INDEX_KEY_V1
+            | rag::content_store::STOWED_CONTENT_KEY
+            | rag::index_stats::INDEX_MANIFEST_KEY
+            | rag::index_generation::INDEX_GENERATION_KEY
+            | models::collection::COLLECTIONS_KEY_V1
+            | models::freshness::DOCUMENT_EXPIRY_KEY_V1 => Some(BackupSection::Documents),
+            models::graph_store::GRAPH_STORE_KEY_V1
+            | rag::embeddings::VECTOR_INDEX_KEY
+            | crate::pagerank_reranking::NODE_IMPORTANCE_KEY => Some(BackupSection::Graph),
+            crm::CUSTOMERS_KEY
+            | crm::LEADS_KEY
+            | crm::DEALS_KEY
+            | crm::STAGES_KEY
+            | crm::OWNERS_KEY => Some(BackupSection::Crm),
+            crate::state::tasks_state_simple::TASKS_KEY => Some(BackupSection::Tasks),
+            _ => None,
+        }
+    }
+}
+
+/// What to do with data that exists both here and in the backup
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ConflictPolicy {
+    /// Add what is missing; of an entry in both, keep the most recently updated
+    #[default]
+    Merge,
+    /// Add what is missing and leave everything here as it is
+    KeepCurrent,
+    /// The backup overwrites what is here
+    Replace,
+}
+
+impl ConflictPolicy {
+    pub const ALL: [ConflictPolicy; 3] = [
+        ConflictPolicy::Merge,
+        ConflictPolicy::KeepCurrent,
+        ConflictPolicy::Replace,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ConflictPolicy::Merge => "Merge, newest wins",
+            ConflictPolicy::KeepCurrent => "Keep what is here",
+            ConflictPolicy::Replace => "Replace with the backup",
+        }
+    }
+}
+
+/// The value to store for a record given what is here and what the backup has;
+/// `None` when the record stays as it is
+pub fn resolve(policy: ConflictPolicy, current: Option<Value>, backup: Value) -> Option<Value> {
+    let Some(current) = current else {
+        return Some(backup);
+    };
+    if current == backup {
+        return None;
+    }
+    let resolved = match policy {
+        ConflictPolicy::Replace => return Some(backup),
+        ConflictPolicy::Merge => combine(current.clone(), backup, true),
+        ConflictPolicy::KeepCurrent => combine(current.clone(), backup, false),
+    };
+    (resolved != current).then_some(resolved)
+}
+
+/// Lists are combined entry by entry, matched by `id` when they have one. Objects get
+/// the fields they lack, and their list fields are combined the same way. Anything else
+/// here is kept, unless `prefer_newer` and the backup's `updated_at` is at least as recent.
+fn combine(current: Value, backup: Value, prefer_newer: bool) -> Value {
+    match (current, backup) {
+        (Value::Array(current), Value::Array(backup)) => {
+            Value::Array(combine_lists(current, backup, prefer_newer))
+        }
+        (Value::Object(mut current), Value::Object(backup)) => {
+            for (field, incoming) in backup {
+                let merged = match current.remove(&field) {
+                    None => incoming,
+                    Some(Value::Array(here)) => match incoming {
+                        Value::Array(incoming) => {
+                            Value::Array(combine_lists(here, incoming, prefer_newer))
+                        }
+                        _ => Value::Array(here),
+                    },
+                    Some(here) if prefer_newer && is_newer(&incoming, &here) => incoming,
+                    Some(here) => here,
+                };
+                current.insert(field, merged);
+            }
+            Value::Object(current)
+        }
+        (current, _) => current,
+    }
+}
+
+fn combine_lists(mut current: Vec<Value>, backup: Vec<Value>, prefer_newer: bool) -> Vec<Value> {
+    let mut by_id: HashMap<String, usize> = current
+        .iter()
+        .enumerate()
+        .filter_map(|(i, v)| v["id"].as_str().map(|id| (id.to_string(), i)))
+        .collect();
+    for incoming in backup {
+        let slot = match incoming["id"].as_str() {
+            Some(id) => by_id.get(id).copied(),
+            None => current.iter().position(|v| *v == incoming),
+        };
+        match slot {
+            Some(i) => {
+                if prefer_newer && is_newer(&incoming, &current[i]) {
+                    current[i] = incoming;
+                }
+            }
+            None => {
+                if let Some(id) = incoming["id"].as_str() {
+                    by_id.insert(id.to_string(), current.len());
+                }
+                current.push(incoming);
+            }
+        }
+    }
+    current
+}
+
+/// Same rule as `import_json`: the backup wins ties
+fn is_newer(incoming: &Value, here: &Value) -> bool {
+    match (incoming["updated_at"].as_f64(), here["updated_at"].as_f64()) {
+        (Some(incoming), Some(here)) => incoming >= here,
+        _ => false,
+    }
+}
+
+/// How a record of the backup compares with what is here
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RecordStatus {
+    New,
+    Identical,
+    Differs,
+}
+
+impl RecordStatus {
+    pub fn of(current: Option<&Value>, backup: &Value) -> Self {
+        match current {
+            None | Some(Value::Null) => RecordStatus::New,
+            Some(Value::Array(items)) if items.is_empty() => RecordStatus::New,
+            Some(current) if current == backup => RecordStatus::Identical,
+            Some(_) => RecordStatus::Differs,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            RecordStatus::New => "new",
+            RecordStatus::Identical => "same as here",
+            RecordStatus::Differs => "differs from here",
+        }
+    }
+}
+
+/// One entry of the restore preview
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlannedItem {
+    pub section: BackupSection,
+    pub label: String,
+    pub status: RecordStatus,
+}
+
+/// A stored record in the archive
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BackupRecord {
+    pub key: String,
+    pub path: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BackupManifest {
+    pub format: String,
+    pub version: u32,
+    pub created_at: f64,
+    #[serde(default)]
+    pub conversations: usize,
+    #[serde(default)]
+    pub records: Vec<BackupRecord>,
+    #[serde(default)]
+    pub passages: usize,
+    #[serde(default)]
+    pub settings: bool,
+}
+
+impl BackupManifest {
+    pub fn new(created_at: f64) -> Self {
+        Self {
+            format: BACKUP_FORMAT.to_string(),
+            version: BACKUP_VERSION,
+            created_at,
+            conversations: 0,
+            records: Vec::new(),
+            passages: 0,
+            settings: false,
+        }
+    }
+
+    /// One line of what the backup holds
+    pub fn summary(&self) -> String {
+        let mut parts = vec![format!("{} conversation(s)", self.conversations)];
+        parts.push(format!("{} dataset(s)", self.records.len()));
+        if self.passages > 0 {
+            parts.push(format!("{} passage text(s)", self.passages));
+        }
+        if self.settings {
+            parts.push("settings".to_string());
+        }
+        parts.join(", ")
+    }
+}
+
+fn record_path(key: &str) -> String {
+    format!("records/{}.json", key)
+}
+
+fn read_current(known: &KnownKey) -> Option<Value> {
+    match known.tier {
+        StorageTier::Persistent => PersistentStore::read::<Value>(known.key),
+        StorageTier::Local => StorageUtils::retrieve_local::<Value>(known.key),
+    }
+    .ok()
+    .flatten()
+}
+
+fn write_current(known: &KnownKey, value: &Value) -> AppResult<()> {
+    match known.tier {
+        StorageTier::Persistent => PersistentStore::write(known.key, value),
+        StorageTier::Local => StorageUtils::store_local(known.key, value),
+    }
+}
+
+fn current_conversations() -> Result<Value, String> {
+    let json = ConversationStorage::new()
+        .and_then(|s| s.export_json())
+        .map_err(|e| e.to_string())?;
+    serde_json::from_str(&json).map_err(|e| e.to_string())
+}
+
+/// Everything stored in this browser, as a zip archive
+pub async fn create() -> Result<(Vec<u8>, BackupManifest), String> {
+    let mut manifest = BackupManifest::new(js_sys::Date::now());
+    let mut entries = Vec::new();
+
+    let conversations = current_conversations().map_err(|e| format!("Conversations: {}", e))?;
+    manifest.conversations = conversations["conversations"]
+        .as_array()
+        .map_or(0, Vec::len);
+    entries.push((CONVERSATIONS_ENTRY.to_string(), conversations.to_string()));
+
+    for known in KNOWN_KEYS {
+        if BackupSection::of_key(known.key).is_none() {
+            continue;
+        }
+        if let Some(value) = read_current(known) {
+            let path = record_path(known.key);
+            entries.push((path.clone(), value.to_string()));
+            manifest.records.push(BackupRecord {
+                key: known.key.to_string(),
+                path,
+            });
+        }
+    }
+
+    // Full texts of stowed passages are kept out of memory; read them one by one
+    let mut passages = Map::new();
+    for key in content_store::record_keys() {
+        match PersistentStore::read_cold::<String>(&key).await {
+            Ok(Some(text)) => {
+                passages.insert(key, Value::String(text));
+            }
+            Ok(None) => {}
+            Err(e) => return Err(format!("Passage text {}: {}", key, e)),
+        }
+    }
+    if !passages.is_empty() {
+        manifest.passages = passages.len();
+        entries.push((
+            PASSAGES_ENTRY.to_string(),
+            Value::Object(passages).to_string(),
+        ));
+    }
+
+    entries.push((
+        SETTINGS_ENTRY.to_string(),
+        settings_bundle::collect().to_json(),
+    ));
+    manifest.settings = true;
+
+    let mut zip = ZipWriter::new();
+    let manifest_json = serde_json::to_string_pretty(&manifest).map_err(|e| e.to_string())?;
+    zip.add(MANIFEST_ENTRY, manifest_json.as_bytes())?;
+    for (path, content) in &entries {
+        zip.add(path, content.as_bytes())?;
+    }
+    Ok((zip.finish(), manifest))
+}
+
+/// A backup read from an archive and checked, ready to preview and restore
+#[derive(Clone, Debug)]
+pub struct LoadedBackup {
+    pub manifest: BackupManifest,
+    /// `export_json` bundle of the conversations
+    conversations: Option<Value>,
+    records: Vec<(&'static KnownKey, Value)>,
+    passages: Map<String, Value>,
+    settings: Option<ImportedSettings>,
+    /// Parts of the archive left out, and why
+    pub warnings: Vec<String>,
+}
+
+/// Read a backup archive. Each entry is checked against its CRC-32 as it is read, so a
+/// damaged or truncated archive is refused before anything is restored.
+pub async fn open(bytes: &[u8]) -> Result<LoadedBackup, String> {
+    let archive = ZipArchive::parse(bytes).map_err(|_| "Not a backup archive".to_string())?;
+    if archive.find(MANIFEST_ENTRY).is_none() {
+        return Err("Not a backup archive: it has no manifest.json".to_string());
+    }
+    let mut entries = HashMap::new();
+    for entry in &archive.entries {
+        let text = archive
+            .read_text(&entry.name)
+            .await
+            .map_err(|e| format!("Damaged backup: {}", e))?;
+        entries.insert(entry.name.clone(), text);
+    }
+    LoadedBackup::from_entries(&entries)
+}
+
+impl LoadedBackup {
+    /// Check the entries of an archive, by name. Only records this app knows are
+    /// taken, each must parse as the type the app reads it as.
+    pub fn from_entries(entries: &HashMap<String, String>) -> Result<Self, String> {
+        let manifest: Value = entries
+            .get(MANIFEST_ENTRY)
+            .and_then(|json| serde_json::from_str(json).ok())
+            .ok_or_else(|| "Not a backup archive: unreadable manifest.json".to_string())?;
+        if manifest["format"].as_str() != Some(BACKUP_FORMAT) {
+            return Err("Not a backup archive".to_string());
+        }
+        let version = manifest["version"].as_u64().unwrap_or(0);
+        if version > BACKUP_VERSION as u64 {
+            return Err(format!(
+                "Written by a newer version (format version {}, this build reads up to {})",
+                version, BACKUP_VERSION
+            ));
+        }
+        let manifest: BackupManifest =
+            serde_json::from_value(manifest).map_err(|e| format!("Invalid manifest: {}", e))?;
+        let mut warnings = Vec::new();
+        let mut parse_entry = |path: &str, label: &str| match entries.get(path) {
+            Some(text) => serde_json::from_str(text)
+                .map_err(|e| warnings.push(format!("{}: not valid JSON ({})", label, e)))
+                .ok(),
+            None => None,
+        };
+
+        let mut conversations = parse_entry(CONVERSATIONS_ENTRY, "Conversations");
+        let mut records = Vec::new();
+        let mut missing = Vec::new();
+        for record in &manifest.records {
+            let known = BackupSection::of_key(&record.key).and(KnownKey::find(&record.key));
+            let Some(known) = known else {
+                missing.push(format!("{}: not a record this app restores", record.key));
+                continue;
+            };
+            if !entries.contains_key(&record.path) {
+                missing.push(format!("{}: missing from the archive", known.label));
+            } else if let Some(value) = parse_entry(&record.path, known.label) {
+                records.push((known, value));
+            }
+        }
+        let mut passages = match parse_entry(PASSAGES_ENTRY, "Passage texts") {
+            Some(Value::Object(passages)) => passages,
+            _ => Map::new(),
+        };
+        warnings.extend(missing);
+        let settings = entries.get(SETTINGS_ENTRY).and_then(|json| {
+            settings_bundle::parse(json)
+                .map_err(|e| warnings.push(format!("Settings: {}", e)))
+                .ok()
+        });
+
+        if let Some(bundle) = &conversations {
+            let known = KnownKey::find(CONVERSATIONS_KEY).expect("conversations are a known key");
+            let problem = (bundle["version"].as_u64() != Some(1))
+                .then(|| "unsupported export version".to_string())
+                .or_else(|| known.problem(&bundle["conversations"].to_string()));
+            if let Some(problem) = problem {
+                warnings.push(format!("Conversations: {}", problem));
+                conversations = None;
+            }
+        }
+        records.retain(|(known, value)| match known.problem(&value.to_string()) {
+            Some(problem) => {
+                warnings.push(format!("{}: {}", known.label, problem));
+                false
+            }
+            None => true,
+        });
+        passages.retain(|key, text| content_store::is_record_key(key) && text.is_string());
+
+        if conversations.is_none() && records.is_empty() && settings.is_none() {
+            return Err("The backup has nothing to restore".to_string());
+        }
+        Ok(Self {
+            manifest,
+            conversations,
+            records,
+            passages,
+            settings,
+            warnings,
+        })
+    }
+
+    /// Sections the backup has data for, in display order
+    pub fn sections(&self) -> Vec<BackupSection> {
+        let has_records = |section: BackupSection| {
+            self.records
+                .iter()
+                .any(|(known, _)| BackupSection::of_key(known.key) == Some(section))
+        };
+        BackupSection::ALL
+            .into_iter()
+            .filter(|&section| match section {
+                BackupSection::Conversations => {
+                    self.conversations.is_some() || has_records(section)
+                }
+                BackupSection::Documents => !self.passages.is_empty() || has_records(section),
+                BackupSection::Settings => self.settings.is_some(),
+                _ => has_records(section),
+            })
+            .collect()
+    }
+
+    /// How each part of the backup compares with what is stored here
+    pub fn plan(&self) -> Vec<PlannedItem> {
+        let mut items = Vec::new();
+        if let Some(bundle) = &self.conversations {
+            let current = current_conversations().ok();
+            let count = bundle["conversations"].as_array().map_or(0, Vec::len);
+            items.push(PlannedItem {
+                section: BackupSection::Conversations,
+                label: format!("{} conversation(s)", count),
+                status: RecordStatus::of(
+                    current.as_ref().map(|c| &c["conversations"]),
+                    &bundle["conversations"],
+                ),
+            });
+        }
+        for (known, value) in &self.records {
+            items.push(PlannedItem {
+                section: BackupSection::of_key(known.key).unwrap_or(BackupSection::Documents),
+                label: known.label.to_string(),
+                status: RecordStatus::of(read_current(known).as_ref(), value),
+            });
+        }
+        if !self.passages.is_empty() {
+            items.push(PlannedItem {
+                section: BackupSection::Documents,
+                label: format!("{} passage text(s)", self.passages.len()),
+                status: RecordStatus::New,
+            });
+        }
+        if let Some(settings) = &self.settings {
+            let here = settings_bundle::collect();
+            let mut backup = settings.bundle.clone();
+            backup.exported_at = here.exported_at;
+            let status = if here == backup {
+                RecordStatus::Identical
+            } else {
+                RecordStatus::Differs
+            };
+            items.push(PlannedItem {
+                section: BackupSection::Settings,
+                label: settings.bundle.sections().join(", "),
+                status,
+            });
+        }
+        items.sort_by_key(|item| BackupSection::ALL.iter().position(|s| *s == item.section));
+        items
+    }
+
+    /// Restore the chosen sections. Settings are applied unless the current data is
+    /// kept. The app must reload afterwards: open views still hold the old data.
+    pub fn restore(&self, sections: &[BackupSection], policy: ConflictPolicy) -> RestoreReport {
+        let mut report = RestoreReport::default();
+        let wanted = |section: BackupSection| sections.contains(&section);
+
+        // Passage texts go first so the index restored with them can read them
+        if wanted(BackupSection::Documents) {
+            for (key, text) in &self.passages {
+                match PersistentStore::write_cold(key, text) {
+                    Ok(()) => report.restored += 1,
+                    Err(e) => report.errors.push(format!("Passage text {}: {}", key, e)),
+                }
+            }
+        }
+        for (known, value) in &self.records {
+            if !BackupSection::of_key(known.key).is_some_and(wanted) {
+                continue;
+            }
+            let Some(value) = resolve(policy, read_current(known), value.clone()) else {
+                report.kept += 1;
+                continue;
+            };
+            if let Some(problem) = known.problem(&value.to_string()) {
+                report.errors.push(format!(
+                    "{}: could not be merged ({})",
+                    known.label, problem
+                ));
+                continue;
+            }
+            match write_current(known, &value) {
+                Ok(()) => report.restored += 1,
+                Err(e) => report.errors.push(format!("{}: {}", known.label, e)),
+            }
+        }
+        if let Some(bundle) = self
+            .conversations
+            .as_ref()
+            .filter(|_| wanted(BackupSection::Conversations))
+        {
+            let outcome = current_conversations().and_then(|current| {
+                match resolve(policy, Some(current), bundle.clone()) {
+                    Some(value) => ConversationStorage::new()
+                        .and_then(|s| s.import_json(&value.to_string(), false))
+                        .map(|_| true)
+                        .map_err(|e| e.to_string()),
+                    None => Ok(false),
+                }
+            });
+            match outcome {
+                Ok(true) => report.restored += 1,
+                Ok(false) => report.kept += 1,
+                Err(e) => report.errors.push(format!("Conversations: {}", e)),
+            }
+        }
+        if let Some(settings) = self
+            .settings
+            .as_ref()
+            .filter(|_| wanted(BackupSection::Settings))
+        {
+            if policy == ConflictPolicy::KeepCurrent {
+                report.kept += 1;
+            } else {
+                match settings_bundle::apply(settings.clone()) {
+                    Ok(()) => report.restored += 1,
+                    Err(e) => report.errors.push(format!("Settings: {}", e)),
+                }
+            }
+        }
+        report
+    }
+}
+
+/// What a restore did
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RestoreReport {
+    /// Records written from the backup, whole or merged
+    pub restored: usize,
+    /// Records left as they were
+    pub kept: usize,
+    pub errors: Vec<String>,
+}
+
+impl RestoreReport {
+    pub fn summary(&self) -> String {
+        format!(
+            "{} record(s) restored, {} left as they were",
+            self.restored, self.kept
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_conflict_policies() {
+        let here = json!([
+            {"id": "a", "title": "Here", "updated_at": 20.0},
+            {"id": "b", "title": "Only here", "updated_at": 5.0}
+        ]);
+        let backup = json!([
+            {"id": "a", "title": "Backup", "updated_at": 30.0},
+            {"id": "c", "title": "Only in backup", "updated_at": 1.0}
+        ]);
+        let titles = |value: &Value| -> Vec<String> {
+            value
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|v| v["title"].as_str().unwrap().to_string())
+                .collect()
+        };
+
+        let merged = resolve(ConflictPolicy::Merge, Some(here.clone()), backup.clone()).unwrap();
+        assert_eq!(
+            titles(&merged),
+            vec!["Backup", "Only here", "Only in backup"]
+        );
+        let kept = resolve(
+            ConflictPolicy::KeepCurrent,
+            Some(here.clone()),
+            backup.clone(),
+        )
+        .unwrap();
+        assert_eq!(titles(&kept), vec!["Here", "Only here", "Only in backup"]);
+        let replaced =
+            resolve(ConflictPolicy::Replace, Some(here.clone()), backup.clone()).unwrap();
+        assert_eq!(replaced, backup);
+        assert_eq!(
+            resolve(ConflictPolicy::Merge, None, backup.clone()),
+            Some(backup.clone())
+        );
+        assert_eq!(
+            resolve(ConflictPolicy::Replace, Some(here.clone()), here.clone()),
+            None
+        );
+
+        // Objects gain missing fields and combine their lists; plain values stay
+        let graph = json!({"version": 2, "nodes": [{"id": "n1"}], "edges": []});
+        let older = json!({"version": 1, "nodes": [{"id": "n2"}], "edges": [], "extra": true});
+        let merged = resolve(ConflictPolicy::Merge, Some(graph), older).unwrap();
+        assert_eq!(merged["version"], 2);
+        assert_eq!(merged["nodes"].as_array().unwrap().len(), 2);
+        assert_eq!(merged["extra"], true);
+        // Lists without ids are combined by value
+        let owners = resolve(
+            ConflictPolicy::KeepCurrent,
+            Some(json!(["Ana"])),
+            json!(["Ana", "Bo"]),
+        );
+        assert_eq!(owners, Some(json!(["Ana", "Bo"])));
+        assert_eq!(
+            resolve(
+                ConflictPolicy::KeepCurrent,
+                Some(json!(["Ana", "Bo"])),
+                json!(["Bo"])
+            ),
+            None
+        );
+
+        assert_eq!(
+            RecordStatus::of(Some(&json!([])), &backup),
+            RecordStatus::New
+        );
+        assert_eq!(
+            RecordStatus::of(Some(&here), &here),
+            RecordStatus::Identical
+        );
+        assert_eq!(
+            RecordStatus::of(Some(&here), &backup),
+            RecordStatus::Differs
+        );
+    }
+
+    #[test]
+    fn test_archive_entries_are_checked() {
+        use crate::state::crm_state_simple::OWNERS_KEY;
+        use crate::state::tasks_state_simple::TASKS_KEY;
+
+        let mut manifest = BackupManifest::new(0.0);
+        for key in [OWNERS_KEY, TASKS_KEY, "storage_encryption_v1"] {
+            manifest.records.push(BackupRecord {
+                key: key.to_string(),
+                path: record_path(key),
+            });
+        }
+        let mut entries: HashMap<String, String> = [
+            (MANIFEST_ENTRY, serde_json::to_string(&manifest).unwrap()),
+            (
+                CONVERSATIONS_ENTRY,
+                json!({"version": 1, "conversations": []}).to_string(),
+            ),
+            (
+                "records/crm_owner_profiles.json",
+                json!(["Ana"]).to_string(),
+            ),
+            (
+                "records/tasks_v1.json",
+                json!({"not": "a task list"}).to_string(),
+            ),
+            ("records/storage_encryption_v1.json", json!({}).to_string()),
+            (
+                PASSAGES_ENTRY,
+                json!({"graphrag_content_v1:doc:00ff": "Full text", "crm_leads": "[]"}).to_string(),
+            ),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v))
+        .collect();
+
+        let loaded = LoadedBackup::from_entries(&entries).unwrap();
+        assert!(loaded.conversations.is_some());
+        assert_eq!(loaded.records.len(), 1);
+        assert_eq!(loaded.records[0].0.key, OWNERS_KEY);
+        assert_eq!(loaded.passages.len(), 1);
+        assert_eq!(loaded.warnings.len(), 2);
+        assert!(loaded.warnings.iter().any(|w| w.starts_with("Tasks:")));
+        assert!(loaded
+            .warnings
+            .iter()
+            .any(|w| w.contains("not a record this app restores")));
+        assert_eq!(
+            loaded.sections(),
+            vec![
+                BackupSection::Conversations,
+                BackupSection::Documents,
+                BackupSection::Crm
+            ]
+        );
+
+        manifest.version = BACKUP_VERSION + 1;
+        let newer = serde_json::to_string(&manifest).unwrap();
+        entries.insert(MANIFEST_ENTRY.to_string(), newer);
+        assert!(LoadedBackup::from_entries(&entries)
+            .unwrap_err()
+            .contains("newer version"));
+        entries.insert(
+            MANIFEST_ENTRY.to_string(),
+            json!({"format": "app-settings"}).to_string(),
+        );
+        assert_eq!(
+            LoadedBackup::from_entries(&entries).unwrap_err(),
+            "Not a backup archive"
+        );
+    }
+}
@@ -0,0 +1,294 @@
+//! Tiered key/value cache for preferences, model lists, query results and summaries.
+//!
+//! Reads go memory → persistent store (IndexedDB, or localStorage when IndexedDB is
+//! unavailable) → values written directly to localStorage by older builds, which are
+//! promoted on first read. Entries carry an optional expiry; values larger than their
+//! policy allows stay in memory only, and the persistent tier is kept under a byte
+//! budget by evicting the oldest entries.
+//!
+//! Settings and bookkeeping with a type of their own (e.g. `GraphRAGConfig`,
+//! `IndexGenerations`, `IndexManifest`) are not cached values: they keep `load`/`save`
+//! methods over localStorage and are listed in `integrity`, so other tabs see them at
+//! once.
+
+use super::persistent::PersistentStore;
+use crate::models::app::{AppError, AppResult};
+use crate::utils::storage::StorageUtils;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+
+/// Record key of the persistent tier's index
+pub const CACHE_INDEX_KEY: &str = "tiered_cache_index_v1";
+/// Prefix of the persistent tier's records
+const RECORD_KEY_PREFIX: &str = "cache_v1:";
+/// Bytes the persistent tier may hold before the oldest entries are evicted
+const PERSISTENT_BUDGET_BYTES: usize = 8 * 1024 * 1024;
+
+const MINUTE_MS: f64 = 60_000.0;
+
+/// How long an entry lives and where it may be stored
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CachePolicy {
+    /// Lifetime in milliseconds; `None` keeps the entry until it is replaced
+    pub ttl_ms: Option<f64>,
+    /// Largest serialized value written to the persistent tier
+    pub max_bytes: usize,
+    /// Never write the entry to the persistent tier
+    pub memory_only: bool,
+}
+
+impl CachePolicy {
+    /// User preferences: persisted without expiry
+    pub const PREFERENCE: Self = Self {
+        ttl_ms: None,
+        max_bytes: 64 * 1024,
+        memory_only: false,
+    };
+
+    /// Persisted for `minutes`, up to `max_bytes`
+    pub fn expiring(minutes: f64, max_bytes: usize) -> Self {
+        Self {
+            ttl_ms: Some(minutes * MINUTE_MS),
+            max_bytes,
+            memory_only: false,
+        }
+    }
+
+    /// Kept in this tab's memory for `minutes`
+    pub fn memory(minutes: f64) -> Self {
+        Self {
+            ttl_ms: Some(minutes * MINUTE_MS),
+            max_bytes: 0,
+            memory_only: true,
+        }
+    }
+}
+
+/// Bookkeeping of one cached entry
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CacheMeta {
+    pub stored_at: f64,
+    pub expires_at: Option<f64>,
+    pub bytes: usize,
+}
+
+impl CacheMeta {
+    pub fn is_expired(&self, now: f64) -> bool {
+        self.expires_at.map(|t| t <= now).unwrap_or(false)
+    }
+}
+
+/// Persistent tier entries by cache key
+pub type CacheIndex = BTreeMap<String, CacheMeta>;
+
+thread_local! {
+    static MEMORY: RefCell<HashMap<String, (CacheMeta, String)>> = RefCell::new(HashMap::new());
+}
+
+/// General-purpose cached storage
+pub struct TieredCache;
+
+impl TieredCache {
+    /// Cached value of `key`, if present, unexpired and readable as `T`
+    pub fn get<T: Serialize + DeserializeOwned>(key: &str) -> Option<T> {
+        let now = js_sys::Date::now();
+        let in_memory = MEMORY.with(|m| {
+            let mut m = m.borrow_mut();
+            match m.get(key) {
+                Some((meta, _)) if meta.is_expired(now) => {
+                    m.remove(key);
+                    None
+                }
+                Some((_, raw)) => Some(raw.clone()),
+                None => None,
+            }
+        });
+        if let Some(raw) = in_memory {
+            return serde_json::from_str(&raw).ok();
+        }
+
+        let index = Self::load_index();
+        if let Some(meta) = index.get(key) {
+            if meta.is_expired(now) {
+                if let Err(e) = Self::remove(key) {
+                    log::warn!("Failed to drop expired cache entry {}: {}", key, e);
+                }
+                return None;
+            }
+            let raw = PersistentStore::read::<String>(&record_key(key))
+                .ok()
+                .flatten()?;
+            let value = serde_json::from_str(&raw).ok()?;
+            MEMORY.with(|m| m.borrow_mut().insert(key.to_string(), (meta.clone(), raw)));
+            return Some(value);
+        }
+
+        // Written by an older build straight to localStorage
+        let legacy: T = StorageUtils::retrieve_local(key).ok().flatten()?;
+        match Self::set(key, &legacy, CachePolicy::PREFERENCE) {
+            Ok(()) => {
+                let _ = StorageUtils::remove_local(key);
+            }
+            Err(e) => log::warn!("Failed to promote {} into the cache: {}", key, e),
+        }
+        Some(legacy)
+    }
+
+    pub fn set<T: Serialize>(key: &str, value: &T, policy: CachePolicy) -> AppResult<()> {
+        let raw = serde_json::to_string(value)
+            .map_err(|e| AppError::storage(format!("Serialization failed: {}", e)))?;
+        let now = js_sys::Date::now();
+        let meta = CacheMeta {
+            stored_at: now,
+            expires_at: policy.ttl_ms.map(|ttl| now + ttl),
+            bytes: raw.len(),
+        };
+        MEMORY.with(|m| {
+            m.borrow_mut()
+                .insert(key.to_string(), (meta.clone(), raw.clone()))
+        });
+
+        let mut index = Self::load_index();
+        if policy.memory_only || raw.len() > policy.max_bytes {
+            // A persisted older value must not resurface after a reload
+            if index.remove(key).is_some() {
+                PersistentStore::remove(&record_key(key))?;
+                Self::save_index(&index)?;
+            }
+            return Ok(());
+        }
+        PersistentStore::write(&record_key(key), &raw)?;
+        index.insert(key.to_string(), meta);
+        for evicted in eviction_candidates(&index, now, PERSISTENT_BUDGET_BYTES) {
+            index.remove(&evicted);
+            PersistentStore::remove(&record_key(&evicted))?;
+        }
+        Self::save_index(&index)
+    }
+
+    /// Drop `key` from every tier
+    pub fn remove(key: &str) -> AppResult<()> {
+        MEMORY.with(|m| m.borrow_mut().remove(key));
+        let mut index = Self::load_index();
+        if index.remove(key).is_some() {
+            PersistentStore::remove(&record_key(key))?;
+            Self::save_index(&index)?;
+        }
+        let _ = StorageUtils::remove_local(key);
+        Ok(())
+    }
+
+    /// Drop expired entries from every tier. Returns how many persisted entries went.
+    pub fn purge_expired() -> AppResult<usize> {
+        let now = js_sys::Date::now();
+        MEMORY.with(|m| m.borrow_mut().retain(|_, (meta, _)| !meta.is_expired(now)));
+        let mut index = Self::load_index();
+        let expired = eviction_candidates(&index, now, usize::MAX);
+        for key in &expired {
+            index.remove(key);
+            PersistentStore::remove(&record_key(key))?;
+        }
+        if !expired.is_empty() {
+            Self::save_index(&index)?;
+        }
+        Ok(expired.len())
+    }
+
+    fn load_index() -> CacheIndex {
+        PersistentStore::read(CACHE_INDEX_KEY)
+            .ok()
+            .flatten()
+            .unwrap_or_default()
+    }
+
+    fn save_index(index: &CacheIndex) -> AppResult<()> {
+        PersistentStore::write(CACHE_INDEX_KEY, index)
+    }
+}
+
+/// Record keys of the unexpired entries in `index`, for preloading
+pub fn preload_keys(index: &CacheIndex, now: f64) -> Vec<String> {
+    index
+        .iter()
+        .filter(|(_, meta)| !meta.is_expired(now))
+        .map(|(key, _)| record_key(key))
+        .collect()
+}
+
+/// Keys to drop so `index` holds no expired entry and fits in `budget` bytes,
+/// expired entries first, then oldest first
+pub fn eviction_candidates(index: &CacheIndex, now: f64, budget: usize) -> Vec<String> {
+    let mut out: Vec<String> = Vec::new();
+    let mut live: Vec<(&String, &CacheMeta)> = Vec::new();
+    for (key, meta) in index {
+        if meta.is_expired(now) {
+            out.push(key.clone());
+        } else {
+            live.push((key, meta));
+        }
+    }
+    let mut total: usize = live.iter().map(|(_, m)| m.bytes).sum();
+    live.sort_by(|a, b| {
+        a.1.stored_at
+            .partial_cmp(&b.1.stored_at)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    for (key, meta) in live {
+        if total <= budget {
+            break;
+        }
+        total -= meta.bytes;
+        out.push(key.clone());
+    }
+    out
+}
+
+fn record_key(key: &str) -> String {
+    format!("{}{}", RECORD_KEY_PREFIX, key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn meta(stored_at: f64, expires_at: Option<f64>, bytes: usize) -> CacheMeta {
+        CacheMeta {
+            stored_at,
+            expires_at,
+            bytes,
+        }
+    }
+
+    #[test]
+    fn test_eviction_drops_expired_then_oldest() {
+        let mut index = CacheIndex::new();
+        index.insert("old".into(), meta(1.0, None, 40));
+        index.insert("expired".into(), meta(5.0, Some(10.0), 40));
+        index.insert("new".into(), meta(3.0, Some(100.0), 40));
+        index.insert("newest".into(), meta(4.0, None, 40));
+
+        assert_eq!(eviction_candidates(&index, 20.0, 1000), vec!["expired"]);
+        assert_eq!(
+            eviction_candidates(&index, 20.0, 80),
+            vec!["expired".to_string(), "old".to_string()]
+        );
+        // Both timed entries have expired by now and the rest fits
+        assert_eq!(eviction_candidates(&index, 200.0, 80).len(), 2);
+    }
+
+    #[test]
+    fn test_preload_keys_skip_expired() {
+        let mut index = CacheIndex::new();
+        index.insert("models".into(), meta(0.0, Some(50.0), 1));
+        index.insert("prompt".into(), meta(0.0, None, 1));
+        assert_eq!(
+            preload_keys(&index, 10.0),
+            vec!["cache_v1:models".to_string(), "cache_v1:prompt".to_string()]
+        );
+        assert_eq!(preload_keys(&index, 60.0), vec!["cache_v1:prompt"]);
+        assert!(CachePolicy::memory(1.0).memory_only);
+        assert_eq!(CachePolicy::expiring(2.0, 10).ttl_ms, Some(120_000.0));
+    }
+}
@@ -1,7 +1,16 @@
-use crate::models::Message;
+use crate::features::graphrag::summarizer::content_terms;
+use crate::models::{
+    AnswerStyle, HistoryPolicy, KnowledgeScope, Message, MessageRole, OutputFormat,
+};
+use crate::storage::long_messages::LongMessageStore;
+use crate::storage::persistent::PersistentStore;
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 use uuid::Uuid;
 
+/// Record key of the conversation list
+pub const CONVERSATIONS_KEY: &str = "wasm_llm_conversations";
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Conversation {
     pub id: String,
@@ -12,6 +21,48 @@ pub struct Conversation {
     /// Optional per-conversation system prompt
     #[serde(default)]
     pub system_prompt: Option<String>,
+    /// Stop sequences added to the global generation settings for this conversation
+    #[serde(default)]
+    pub stop_sequences: Vec<String>,
+    /// History sent to the model with each prompt
+    #[serde(default)]
+    pub history_policy: HistoryPolicy,
+    /// Format requested for the model's replies
+    #[serde(default)]
+    pub output_format: OutputFormat,
+    /// Answer preferences learned from the user's requests or set by hand
+    #[serde(default)]
+    pub answer_style: AnswerStyle,
+    /// Collections and files retrieval is limited to; empty searches everything
+    #[serde(default)]
+    pub knowledge_scope: KnowledgeScope,
+    /// First conversation of the branch tree this one belongs to, `None` for the root
+    #[serde(default)]
+    pub root_id: Option<String>,
+    /// Conversation this branch was forked from
+    #[serde(default)]
+    pub parent_id: Option<String>,
+    /// Last message copied from the parent
+    #[serde(default)]
+    pub forked_at: Option<String>,
+    /// Message this conversation is a side thread of; threads are kept out of the
+    /// conversation list
+    #[serde(default)]
+    pub thread_of: Option<ThreadLink>,
+}
+
+/// The message a side thread replies to
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ThreadLink {
+    pub conversation_id: String,
+    pub message_id: String,
+}
+
+impl Conversation {
+    /// Id of the root of this conversation's branch tree
+    pub fn tree_id(&self) -> &str {
+        self.root_id.as_deref().unwrap_or(&self.id)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -19,8 +70,64 @@ pub struct ConversationInfo {
     pub id: String,
     pub title: String,
     pub updated_at: f64,
+    /// Root of the branch tree, `None` for roots
+    #[serde(default)]
+    pub root_id: Option<String>,
+}
+
+/// A side thread shown under the message it replies to
+#[derive(Debug, Clone, PartialEq)]
+pub struct ThreadInfo {
+    /// Id of the thread's own conversation
+    pub id: String,
+    pub message_id: String,
+    pub reply_count: usize,
+    pub updated_at: f64,
+}
+
+/// One conversation of a branch tree, in depth-first order
+#[derive(Debug, Clone, PartialEq)]
+pub struct BranchInfo {
+    pub id: String,
+    pub title: String,
+    /// 0 for the root
+    pub depth: usize,
+    pub message_count: usize,
+    pub updated_at: f64,
+}
+
+/// A message matching a conversation search
+#[derive(Debug, Clone, PartialEq)]
+pub struct MessageSearchHit {
+    pub conversation_id: String,
+    pub conversation_title: String,
+    pub message_id: String,
+    pub role: MessageRole,
+    pub timestamp: f64,
+    /// Snippet around the first match as (text, is_match) segments
+    pub snippet: Vec<(String, bool)>,
+}
+
+/// A past conversation resembling the first message of a new chat
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimilarConversation {
+    pub conversation_id: String,
+    pub title: String,
+    /// Share of the message's terms that occur in the conversation
+    pub score: f32,
+    pub updated_at: f64,
 }
 
+/// Maximum number of hits returned by `search_messages`
+const MAX_SEARCH_HITS: usize = 50;
+/// Characters of context kept on each side of the first match
+const SNIPPET_RADIUS: usize = 60;
+/// Suggestions returned by `similar_conversations`
+const MAX_SIMILAR: usize = 3;
+/// Terms a draft needs, and must share with a conversation, before it is suggested
+const MIN_SIMILAR_TERMS: usize = 2;
+const MIN_SIMILAR_SCORE: f32 = 0.5;
+
 // ---- Export / Import schema and validators (module scope) ----
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct ExportBundleV1 {
@@ -55,41 +162,25 @@ pub struct ConversationStorage {
 impl ConversationStorage {
     pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
         Ok(Self {
-            storage_key: "wasm_llm_conversations".to_string(),
+            storage_key: CONVERSATIONS_KEY.to_string(),
         })
     }
 
-    fn get_local_storage(&self) -> Result<web_sys::Storage, Box<dyn std::error::Error>> {
-        let window = web_sys::window().ok_or("No window object")?;
-        let storage = window
-            .local_storage()
-            .map_err(|_| "Failed to get localStorage")?
-            .ok_or("localStorage not available")?;
-        Ok(storage)
-    }
-
     fn load_conversations(&self) -> Result<Vec<Conversation>, Box<dyn std::error::Error>> {
-        let storage = self.get_local_storage()?;
-
-        match storage.get_item(&self.storage_key) {
-            Ok(Some(data)) => {
-                let conversations: Vec<Conversation> = serde_json::from_str(&data)?;
-                Ok(conversations)
-            }
-            Ok(None) => Ok(vec![]),
-            Err(_) => Ok(vec![]),
-        }
+        let mut conversations = PersistentStore::read::<Vec<Conversation>>(&self.storage_key)
+            .map_err(|e| e.to_string())?
+            .unwrap_or_default();
+        LongMessageStore::restore(&mut conversations);
+        Ok(conversations)
     }
 
     fn save_conversations(
         &self,
         conversations: &[Conversation],
     ) -> Result<(), Box<dyn std::error::Error>> {
-        let storage = self.get_local_storage()?;
-        let data = serde_json::to_string(conversations)?;
-        storage
-            .set_item(&self.storage_key, &data)
-            .map_err(|_| "Failed to save to localStorage")?;
+        let mut stowed = conversations.to_vec();
+        LongMessageStore::stow(&mut stowed).map_err(|e| e.to_string())?;
+        PersistentStore::write(&self.storage_key, &stowed).map_err(|e| e.to_string())?;
         Ok(())
     }
 
@@ -105,6 +196,15 @@ impl ConversationStorage {
             updated_at: now,
             messages: vec![],
             system_prompt: None,
+            stop_sequences: Vec::new(),
+            history_policy: HistoryPolicy::default(),
+            output_format: OutputFormat::default(),
+            answer_style: AnswerStyle::default(),
+            knowledge_scope: KnowledgeScope::default(),
+            root_id: None,
+            parent_id: None,
+            forked_at: None,
+            thread_of: None,
         };
 
         conversations.push(conversation);
@@ -130,6 +230,80 @@ impl ConversationStorage {
         Ok(())
     }
 
+    /// Pin or unpin a stored message. Pinning is not activity, so the conversation keeps
+    /// its place in the list.
+    pub fn set_message_pinned(
+        &self,
+        conversation_id: &str,
+        message_id: &str,
+        pinned: bool,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut conversations = self.load_conversations()?;
+        let message = conversations
+            .iter_mut()
+            .find(|c| c.id == conversation_id)
+            .and_then(|c| c.messages.iter_mut().find(|m| m.id == message_id));
+        if let Some(message) = message {
+            message.pinned = pinned;
+            self.save_conversations(&conversations)?;
+        }
+        Ok(())
+    }
+
+    /// Replace the message `message_id` with `message` (which may carry a new id)
+    pub fn replace_message(
+        &self,
+        conversation_id: &str,
+        message_id: &str,
+        message: &Message,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut conversations = self.load_conversations()?;
+        let now = js_sys::Date::now();
+
+        if let Some(conversation) = conversations.iter_mut().find(|c| c.id == conversation_id) {
+            match conversation
+                .messages
+                .iter_mut()
+                .find(|m| m.id == message_id)
+            {
+                Some(slot) => *slot = message.clone(),
+                None => conversation.messages.push(message.clone()),
+            }
+            conversation.updated_at = now;
+            self.save_conversations(&conversations)?;
+        }
+
+        Ok(())
+    }
+
+    /// Replace the message `message_id` with `message` and drop every message after it,
+    /// so the conversation continues from the edited one
+    pub fn truncate_at(
+        &self,
+        conversation_id: &str,
+        message_id: &str,
+        message: &Message,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut conversations = self.load_conversations()?;
+        let now = js_sys::Date::now();
+
+        if let Some(conversation) = conversations.iter_mut().find(|c| c.id == conversation_id) {
+            let Some(pos) = conversation
+                .messages
+                .iter()
+                .position(|m| m.id == message_id)
+            else {
+                return Err(format!("Message {} not found", message_id).into());
+            };
+            conversation.messages.truncate(pos);
+            conversation.messages.push(message.clone());
+            conversation.updated_at = now;
+            self.save_conversations(&conversations)?;
+        }
+
+        Ok(())
+    }
+
     pub fn load_conversation(
         &self,
         conversation_id: &str,
@@ -147,15 +321,22 @@ impl ConversationStorage {
         }
     }
 
+    /// All stored conversations including their messages
+    pub fn load_all_conversations(&self) -> Result<Vec<Conversation>, Box<dyn std::error::Error>> {
+        self.load_conversations()
+    }
+
     pub fn list_conversations(&self) -> Result<Vec<ConversationInfo>, Box<dyn std::error::Error>> {
         let conversations = self.load_conversations()?;
 
         let mut result: Vec<ConversationInfo> = conversations
             .into_iter()
+            .filter(|c| c.thread_of.is_none())
             .map(|c| ConversationInfo {
                 id: c.id,
                 title: c.title,
                 updated_at: c.updated_at,
+                root_id: c.root_id,
             })
             .collect();
 
@@ -200,18 +381,277 @@ impl ConversationStorage {
         Ok(())
     }
 
+    /// Load the per-conversation stop sequences
+    pub fn load_conversation_stop_sequences(
+        &self,
+        conversation_id: &str,
+    ) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        let conversations = self.load_conversations()?;
+        Ok(conversations
+            .iter()
+            .find(|c| c.id == conversation_id)
+            .map(|c| c.stop_sequences.clone())
+            .unwrap_or_default())
+    }
+
+    /// Replace the per-conversation stop sequences
+    pub fn update_conversation_stop_sequences(
+        &self,
+        conversation_id: &str,
+        stop_sequences: Vec<String>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut conversations = self.load_conversations()?;
+        let now = js_sys::Date::now();
+        if let Some(conversation) = conversations.iter_mut().find(|c| c.id == conversation_id) {
+            conversation.stop_sequences = stop_sequences;
+            conversation.updated_at = now;
+            self.save_conversations(&conversations)?;
+        }
+        Ok(())
+    }
+
+    /// Load the conversation's history policy
+    pub fn load_conversation_history_policy(
+        &self,
+        conversation_id: &str,
+    ) -> Result<HistoryPolicy, Box<dyn std::error::Error>> {
+        let conversations = self.load_conversations()?;
+        Ok(conversations
+            .iter()
+            .find(|c| c.id == conversation_id)
+            .map(|c| c.history_policy)
+            .unwrap_or_default())
+    }
+
+    pub fn update_conversation_history_policy(
+        &self,
+        conversation_id: &str,
+        policy: HistoryPolicy,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut conversations = self.load_conversations()?;
+        let now = js_sys::Date::now();
+        if let Some(conversation) = conversations.iter_mut().find(|c| c.id == conversation_id) {
+            conversation.history_policy = policy;
+            conversation.updated_at = now;
+            self.save_conversations(&conversations)?;
+        }
+        Ok(())
+    }
+
+    pub fn load_conversation_output_format(
+        &self,
+        conversation_id: &str,
+    ) -> Result<OutputFormat, Box<dyn std::error::Error>> {
+        let conversations = self.load_conversations()?;
+        Ok(conversations
+            .iter()
+            .find(|c| c.id == conversation_id)
+            .map(|c| c.output_format.clone())
+            .unwrap_or_default())
+    }
+
+    pub fn update_conversation_output_format(
+        &self,
+        conversation_id: &str,
+        format: OutputFormat,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut conversations = self.load_conversations()?;
+        let now = js_sys::Date::now();
+        if let Some(conversation) = conversations.iter_mut().find(|c| c.id == conversation_id) {
+            conversation.output_format = format;
+            conversation.updated_at = now;
+            self.save_conversations(&conversations)?;
+        }
+        Ok(())
+    }
+
+    pub fn load_conversation_answer_style(
+        &self,
+        conversation_id: &str,
+    ) -> Result<AnswerStyle, Box<dyn std::error::Error>> {
+        let conversations = self.load_conversations()?;
+        Ok(conversations
+            .iter()
+            .find(|c| c.id == conversation_id)
+            .map(|c| c.answer_style.clone())
+            .unwrap_or_default())
+    }
+
+    pub fn update_conversation_answer_style(
+        &self,
+        conversation_id: &str,
+        style: AnswerStyle,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut conversations = self.load_conversations()?;
+        let now = js_sys::Date::now();
+        if let Some(conversation) = conversations.iter_mut().find(|c| c.id == conversation_id) {
+            conversation.answer_style = style;
+            conversation.updated_at = now;
+            self.save_conversations(&conversations)?;
+        }
+        Ok(())
+    }
+
+    pub fn load_conversation_knowledge_scope(
+        &self,
+        conversation_id: &str,
+    ) -> Result<KnowledgeScope, Box<dyn std::error::Error>> {
+        let conversations = self.load_conversations()?;
+        Ok(conversations
+            .iter()
+            .find(|c| c.id == conversation_id)
+            .map(|c| c.knowledge_scope.clone())
+            .unwrap_or_default())
+    }
+
+    pub fn update_conversation_knowledge_scope(
+        &self,
+        conversation_id: &str,
+        scope: KnowledgeScope,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut conversations = self.load_conversations()?;
+        if let Some(conversation) = conversations.iter_mut().find(|c| c.id == conversation_id) {
+            conversation.knowledge_scope = scope;
+            self.save_conversations(&conversations)?;
+        }
+        Ok(())
+    }
+
     #[allow(dead_code)]
     pub fn delete_conversation(
         &self,
         conversation_id: &str,
     ) -> Result<(), Box<dyn std::error::Error>> {
         let mut conversations = self.load_conversations()?;
-        conversations.retain(|c| c.id != conversation_id);
+        if let Some(pos) = conversations.iter().position(|c| c.id == conversation_id) {
+            let removed = conversations.remove(pos);
+            reattach_branches(&mut conversations, &removed);
+        }
+        conversations.retain(|c| {
+            c.thread_of
+                .as_ref()
+                .is_none_or(|t| t.conversation_id != conversation_id)
+        });
         self.save_conversations(&conversations)?;
         Ok(())
     }
 
-    #[allow(dead_code)]
+    /// Copy `conversation_id` up to and including `message_id` into a new branch of the
+    /// same tree. Returns the id of the branch.
+    pub fn fork_conversation(
+        &self,
+        conversation_id: &str,
+        message_id: &str,
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        let mut conversations = self.load_conversations()?;
+        let source = conversations
+            .iter()
+            .find(|c| c.id == conversation_id)
+            .ok_or_else(|| format!("Conversation {} not found", conversation_id))?;
+        let end = source
+            .messages
+            .iter()
+            .position(|m| m.id == message_id)
+            .ok_or_else(|| format!("Message {} not found", message_id))?;
+        let tree_id = source.tree_id().to_string();
+        let siblings = conversations
+            .iter()
+            .filter(|c| c.tree_id() == tree_id)
+            .count();
+        let now = js_sys::Date::now();
+        let branch = Conversation {
+            id: Uuid::new_v4().to_string(),
+            title: format!("{} (branch {})", base_title(&source.title), siblings),
+            created_at: now,
+            updated_at: now,
+            messages: source.messages[..=end].to_vec(),
+            system_prompt: source.system_prompt.clone(),
+            stop_sequences: source.stop_sequences.clone(),
+            history_policy: source.history_policy,
+            output_format: source.output_format.clone(),
+            answer_style: source.answer_style.clone(),
+            knowledge_scope: source.knowledge_scope.clone(),
+            root_id: Some(tree_id),
+            parent_id: Some(source.id.clone()),
+            forked_at: Some(message_id.to_string()),
+            thread_of: None,
+        };
+        let branch_id = branch.id.clone();
+        conversations.push(branch);
+        self.save_conversations(&conversations)?;
+        Ok(branch_id)
+    }
+
+    /// Every conversation in the branch tree of `conversation_id`
+    pub fn list_branches(
+        &self,
+        conversation_id: &str,
+    ) -> Result<Vec<BranchInfo>, Box<dyn std::error::Error>> {
+        let conversations = self.load_conversations()?;
+        let Some(tree_id) = conversations
+            .iter()
+            .find(|c| c.id == conversation_id)
+            .map(|c| c.tree_id().to_string())
+        else {
+            return Ok(Vec::new());
+        };
+        Ok(branch_tree(&conversations, &tree_id))
+    }
+
+    /// Id of the side thread on `message_id`, created on first use. The thread starts
+    /// empty and inherits the conversation's prompt and reply settings.
+    pub fn open_thread(
+        &self,
+        conversation_id: &str,
+        message_id: &str,
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        let mut conversations = self.load_conversations()?;
+        let link = ThreadLink {
+            conversation_id: conversation_id.to_string(),
+            message_id: message_id.to_string(),
+        };
+        if let Some(existing) = conversations
+            .iter()
+            .find(|c| c.thread_of.as_ref() == Some(&link))
+        {
+            return Ok(existing.id.clone());
+        }
+        let source = conversations
+            .iter()
+            .find(|c| c.id == conversation_id)
+            .ok_or_else(|| format!("Conversation {} not found", conversation_id))?;
+        let now = js_sys::Date::now();
+        let thread = Conversation {
+            id: Uuid::new_v4().to_string(),
+            title: format!("Thread in {}", source.title),
+            created_at: now,
+            updated_at: now,
+            messages: Vec::new(),
+            system_prompt: source.system_prompt.clone(),
+            stop_sequences: source.stop_sequences.clone(),
+            history_policy: source.history_policy,
+            output_format: source.output_format.clone(),
+            answer_style: source.answer_style.clone(),
+            knowledge_scope: source.knowledge_scope.clone(),
+            root_id: None,
+            parent_id: None,
+            forked_at: None,
+            thread_of: Some(link),
+        };
+        let thread_id = thread.id.clone();
+        conversations.push(thread);
+        self.save_conversations(&conversations)?;
+        Ok(thread_id)
+    }
+
+    /// Side threads on the messages of `conversation_id`
+    pub fn list_threads(
+        &self,
+        conversation_id: &str,
+    ) -> Result<Vec<ThreadInfo>, Box<dyn std::error::Error>> {
+        Ok(threads_of(&self.load_conversations()?, conversation_id))
+    }
+
     pub fn update_conversation_title(
         &self,
         conversation_id: &str,
@@ -229,6 +669,50 @@ impl ConversationStorage {
         Ok(())
     }
 
+    /// Full-text search over every stored message. All whitespace-separated terms must
+    /// occur (case-insensitive); hits are newest first.
+    pub fn search_messages(
+        &self,
+        query: &str,
+    ) -> Result<Vec<MessageSearchHit>, Box<dyn std::error::Error>> {
+        let terms: Vec<&str> = query.split_whitespace().collect();
+        if terms.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut hits = Vec::new();
+        for c in self.load_conversations()? {
+            for m in &c.messages {
+                if let Some(snippet) = highlight_snippet(&m.content, &terms, SNIPPET_RADIUS) {
+                    hits.push(MessageSearchHit {
+                        conversation_id: c.id.clone(),
+                        conversation_title: c.title.clone(),
+                        message_id: m.id.clone(),
+                        role: m.role.clone(),
+                        timestamp: m.timestamp,
+                        snippet,
+                    });
+                }
+            }
+        }
+        hits.sort_by(|a, b| {
+            b.timestamp
+                .partial_cmp(&a.timestamp)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        hits.truncate(MAX_SEARCH_HITS);
+        Ok(hits)
+    }
+
+    /// Past conversations sharing most terms with `draft`, best first, at most one per
+    /// branch tree. `exclude` is the conversation being written in.
+    pub fn similar_conversations(
+        &self,
+        draft: &str,
+        exclude: Option<&str>,
+    ) -> Result<Vec<SimilarConversation>, Box<dyn std::error::Error>> {
+        Ok(rank_similar(&self.load_conversations()?, draft, exclude))
+    }
+
     // ---- Export / Import utilities ----
 
     /// Export all conversations as a JSON bundle (schema v1).
@@ -279,3 +763,367 @@ impl ConversationStorage {
         self.save_conversations(&existing)
     }
 }
+
+pub fn rank_similar(
+    conversations: &[Conversation],
+    draft: &str,
+    exclude: Option<&str>,
+) -> Vec<SimilarConversation> {
+    let mut terms = content_terms(draft);
+    terms.sort();
+    terms.dedup();
+    if terms.len() < MIN_SIMILAR_TERMS {
+        return Vec::new();
+    }
+    let mut ranked: Vec<(&str, SimilarConversation)> = conversations
+        .iter()
+        .filter(|c| Some(c.id.as_str()) != exclude && !c.messages.is_empty())
+        .filter(|c| c.thread_of.is_none())
+        .filter_map(|c| {
+            let words: HashSet<String> = content_terms(&c.title)
+                .into_iter()
+                .chain(c.messages.iter().flat_map(|m| content_terms(&m.content)))
+                .collect();
+            let shared = terms.iter().filter(|t| words.contains(*t)).count();
+            let score = shared as f32 / terms.len() as f32;
+            (shared >= MIN_SIMILAR_TERMS && score >= MIN_SIMILAR_SCORE).then(|| {
+                (
+                    c.tree_id(),
+                    SimilarConversation {
+                        conversation_id: c.id.clone(),
+                        title: c.title.clone(),
+                        score,
+                        updated_at: c.updated_at,
+                    },
+                )
+            })
+        })
+        .collect();
+    ranked.sort_by(|(_, a), (_, b)| {
+        b.score
+            .partial_cmp(&a.score)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then(
+                b.updated_at
+                    .partial_cmp(&a.updated_at)
+                    .unwrap_or(std::cmp::Ordering::Equal),
+            )
+    });
+    let mut trees = HashSet::new();
+    ranked
+        .into_iter()
+        .filter(|(tree, _)| trees.insert(*tree))
+        .map(|(_, s)| s)
+        .take(MAX_SIMILAR)
+        .collect()
+}
+
+/// Title without the " (branch N)" suffix of a forked conversation
+fn base_title(title: &str) -> &str {
+    match title.rfind(" (branch ") {
+        Some(i) if title.ends_with(')') => &title[..i],
+        _ => title,
+    }
+}
+
+/// Keep the branches of a deleted conversation in their tree: children move up to its
+/// parent, and when a root goes its oldest child becomes the new root
+fn reattach_branches(conversations: &mut [Conversation], removed: &Conversation) {
+    let new_root = match removed.root_id {
+        Some(_) => None,
+        None => conversations
+            .iter()
+            .filter(|c| c.parent_id.as_deref() == Some(removed.id.as_str()))
+            .min_by(|a, b| {
+                a.created_at
+                    .partial_cmp(&b.created_at)
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            })
+            .map(|c| c.id.clone()),
+    };
+    for c in conversations.iter_mut() {
+        if c.parent_id.as_deref() == Some(removed.id.as_str()) {
+            c.parent_id = removed.parent_id.clone();
+            c.forked_at = if removed.parent_id.is_some() {
+                removed.forked_at.clone()
+            } else {
+                None
+            };
+        }
+        if let Some(new_root) = &new_root {
+            if c.root_id.as_deref() == Some(removed.id.as_str()) {
+                c.root_id = (c.id != *new_root).then(|| new_root.clone());
+            }
+            if c.id != *new_root && c.parent_id.is_none() && c.root_id.is_some() {
+                c.parent_id = Some(new_root.clone());
+            }
+        }
+    }
+}
+
+/// Threads on messages of `conversation_id` that have replies, oldest first
+pub fn threads_of(conversations: &[Conversation], conversation_id: &str) -> Vec<ThreadInfo> {
+    let mut threads: Vec<ThreadInfo> = conversations
+        .iter()
+        .filter_map(|c| {
+            let link = c.thread_of.as_ref()?;
+            (link.conversation_id == conversation_id && !c.messages.is_empty()).then(|| {
+                ThreadInfo {
+                    id: c.id.clone(),
+                    message_id: link.message_id.clone(),
+                    reply_count: c.messages.len(),
+                    updated_at: c.updated_at,
+                }
+            })
+        })
+        .collect();
+    threads.sort_by(|a, b| {
+        a.updated_at
+            .partial_cmp(&b.updated_at)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    threads
+}
+
+/// Conversations of the tree rooted at `tree_id`, depth first, older branches first
+pub fn branch_tree(conversations: &[Conversation], tree_id: &str) -> Vec<BranchInfo> {
+    fn visit(
+        conversations: &[Conversation],
+        tree_id: &str,
+        parent: Option<&str>,
+        depth: usize,
+        out: &mut Vec<BranchInfo>,
+    ) {
+        let mut children: Vec<&Conversation> = conversations
+            .iter()
+            .filter(|c| c.tree_id() == tree_id)
+            .filter(|c| match parent {
+                None => c.id == tree_id,
+                Some(p) => c.id != tree_id && c.parent_id.as_deref() == Some(p),
+            })
+            .collect();
+        children.sort_by(|a, b| {
+            a.created_at
+                .partial_cmp(&b.created_at)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        for c in children {
+            // Guards against cycles in hand-edited imports
+            if out.iter().any(|b| b.id == c.id) {
+                continue;
+            }
+            out.push(BranchInfo {
+                id: c.id.clone(),
+                title: c.title.clone(),
+                depth,
+                message_count: c.messages.len(),
+                updated_at: c.updated_at,
+            });
+            visit(conversations, tree_id, Some(&c.id), depth + 1, out);
+        }
+    }
+    let mut out = Vec::new();
+    visit(conversations, tree_id, None, 0, &mut out);
+    out
+}
+
+/// Char ranges of every case-insensitive occurrence of `term` in `chars`
+fn find_term(chars: &[char], term: &str) -> Vec<(usize, usize)> {
+    let term: Vec<char> = term
+        .chars()
+        .map(|c| c.to_lowercase().next().unwrap_or(c))
+        .collect();
+    let lower: Vec<char> = chars
+        .iter()
+        .map(|c| c.to_lowercase().next().unwrap_or(*c))
+        .collect();
+    if term.is_empty() || term.len() > lower.len() {
+        return Vec::new();
+    }
+    (0..=lower.len() - term.len())
+        .filter(|&i| lower[i..i + term.len()] == term[..])
+        .map(|i| (i, i + term.len()))
+        .collect()
+}
+
+/// Snippet of `content` around the first match when every term occurs, split into
+/// (text, is_match) segments; `None` when a term is missing
+pub fn highlight_snippet(
+    content: &str,
+    terms: &[&str],
+    radius: usize,
+) -> Option<Vec<(String, bool)>> {
+    let chars: Vec<char> = content.chars().collect();
+    let mut ranges = Vec::new();
+    for term in terms {
+        let found = find_term(&chars, term);
+        if found.is_empty() {
+            return None;
+        }
+        ranges.extend(found);
+    }
+    ranges.sort_unstable();
+    let first = ranges.first()?.0;
+    let start = first.saturating_sub(radius);
+    let end = (first + radius).min(chars.len());
+
+    let mut segments: Vec<(String, bool)> = Vec::new();
+    let mut push = |text: String, is_match: bool| {
+        if text.is_empty() {
+            return;
+        }
+        match segments.last_mut() {
+            Some((last, m)) if *m == is_match => last.push_str(&text),
+            _ => segments.push((text, is_match)),
+        }
+    };
+    if start > 0 {
+        push("…".to_string(), false);
+    }
+    let mut pos = start;
+    for (s, e) in ranges {
+        let (s, e) = (s.max(pos), e.min(end));
+        if s >= e {
+            continue;
+        }
+        push(chars[pos..s].iter().collect(), false);
+        push(chars[s..e].iter().collect(), true);
+        pos = e;
+    }
+    push(chars[pos..end].iter().collect(), false);
+    if end < chars.len() {
+        push("…".to_string(), false);
+    }
+    Some(segments)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn conv(id: &str, parent: Option<&str>, root: Option<&str>, created_at: f64) -> Conversation {
+        Conversation {
+            id: id.to_string(),
+            title: id.to_string(),
+            created_at,
+            updated_at: created_at,
+            messages: Vec::new(),
+            system_prompt: None,
+            stop_sequences: Vec::new(),
+            history_policy: HistoryPolicy::default(),
+            output_format: OutputFormat::default(),
+            answer_style: AnswerStyle::default(),
+            knowledge_scope: KnowledgeScope::default(),
+            root_id: root.map(str::to_string),
+            parent_id: parent.map(str::to_string),
+            forked_at: None,
+            thread_of: None,
+        }
+    }
+
+    fn message(content: &str) -> Message {
+        Message {
+            id: content.to_string(),
+            role: MessageRole::User,
+            content: content.to_string(),
+            timestamp: 0.0,
+            metadata: None,
+            pinned: false,
+        }
+    }
+
+    fn ids(branches: &[BranchInfo]) -> Vec<(&str, usize)> {
+        branches.iter().map(|b| (b.id.as_str(), b.depth)).collect()
+    }
+
+    #[test]
+    fn test_branch_tree_is_depth_first() {
+        let conversations = vec![
+            conv("b", Some("r"), Some("r"), 3.0),
+            conv("r", None, None, 1.0),
+            conv("a", Some("r"), Some("r"), 2.0),
+            conv("a1", Some("a"), Some("r"), 4.0),
+            conv("other", None, None, 0.0),
+        ];
+        assert_eq!(
+            ids(&branch_tree(&conversations, "r")),
+            vec![("r", 0), ("a", 1), ("a1", 2), ("b", 1)]
+        );
+        assert_eq!(base_title("Plan (branch 2)"), "Plan");
+        assert_eq!(base_title("Plan (draft"), "Plan (draft");
+    }
+
+    #[test]
+    fn test_deleting_a_root_promotes_its_oldest_branch() {
+        let mut conversations = vec![
+            conv("a", Some("r"), Some("r"), 2.0),
+            conv("b", Some("r"), Some("r"), 3.0),
+            conv("a1", Some("a"), Some("r"), 4.0),
+        ];
+        let root = conv("r", None, None, 1.0);
+        reattach_branches(&mut conversations, &root);
+        assert_eq!(
+            ids(&branch_tree(&conversations, "a")),
+            vec![("a", 0), ("a1", 1), ("b", 1)]
+        );
+    }
+
+    #[test]
+    fn test_similar_conversations_share_most_draft_terms() {
+        let mut rust = conv("rust", None, None, 1.0);
+        rust.messages = vec![message("How do I borrow a vector mutably in Rust?")];
+        let mut branch = conv("rust-b", Some("rust"), Some("rust"), 2.0);
+        branch.messages = rust.messages.clone();
+        let mut cooking = conv("cooking", None, None, 3.0);
+        cooking.messages = vec![message("A recipe for vector soup")];
+        let empty = conv("new", None, None, 4.0);
+        let conversations = vec![rust, branch, cooking, empty];
+
+        let similar = rank_similar(
+            &conversations,
+            "borrow checker errors with a mutable vector in rust",
+            Some("new"),
+        );
+        // One suggestion per branch tree; one shared term is not enough
+        assert_eq!(similar.len(), 1);
+        assert_eq!(similar[0].conversation_id, "rust-b");
+        assert!(rank_similar(&conversations, "rust", None).is_empty());
+    }
+
+    #[test]
+    fn test_threads_are_listed_under_their_conversation_only() {
+        let thread = |id: &str, on: &str, message_id: &str, updated_at: f64| {
+            let mut c = conv(id, None, None, updated_at);
+            c.thread_of = Some(ThreadLink {
+                conversation_id: on.to_string(),
+                message_id: message_id.to_string(),
+            });
+            c.messages = vec![message("Why is the borrow checker complaining?")];
+            c
+        };
+        let mut main = conv("main", None, None, 1.0);
+        main.messages = vec![message("How do I borrow a vector mutably in Rust?")];
+        let mut opened = thread("t0", "main", "m1", 2.0);
+        opened.messages.clear();
+        let conversations = vec![
+            main,
+            thread("t2", "main", "m2", 5.0),
+            thread("t1", "main", "m1", 3.0),
+            thread("other", "elsewhere", "m1", 4.0),
+            opened,
+        ];
+
+        let threads = threads_of(&conversations, "main");
+        // Oldest first; a thread opened without replies is not shown
+        let listed: Vec<(&str, &str)> = threads
+            .iter()
+            .map(|t| (t.id.as_str(), t.message_id.as_str()))
+            .collect();
+        assert_eq!(listed, vec![("t1", "m1"), ("t2", "m2")]);
+        assert_eq!(threads[0].reply_count, 1);
+
+        // Side threads are never suggested as past conversations
+        let similar = rank_similar(&conversations, "borrow checker complaining", None);
+        assert!(similar.is_empty());
+    }
+}
@@ -0,0 +1,394 @@
+//! Optional encryption of the persistent datasets. When it is on, every record
+//! `PersistentStore` writes (conversations, long message bodies, uploaded documents,
+//! the document index, graph store and vectors, stowed passages, cached values) is
+//! sealed with AES-GCM under a key derived from the user's passphrase with PBKDF2,
+//! and the app opens on a lock screen until the passphrase is given.
+//!
+//! Record keys stay readable, values do not. The salt and a sealed check value are
+//! kept in localStorage so a passphrase can be verified before anything is read; the
+//! passphrase and the key are never stored. Original files kept in the private file
+//! system are not records and cannot be sealed; they are removed instead.
+
+use super::backend::{StorageBackend, StorageFuture};
+use crate::models::app::{AppError, AppResult};
+use crate::utils::storage::StorageUtils;
+use js_sys::{Array, Object, Reflect, Uint8Array};
+use serde::{Deserialize, Serialize};
+use std::rc::Rc;
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{CryptoKey, SubtleCrypto};
+
+/// localStorage key of the encryption settings; present while encryption is on
+pub const ENCRYPTION_SETTINGS_KEY: &str = "storage_encryption_v1";
+/// Start of a sealed record: `aesgcm1:` then base64 of the IV and ciphertext
+pub const SEALED_PREFIX: &str = "aesgcm1:";
+pub const MIN_PASSPHRASE_CHARS: usize = 8;
+const PBKDF2_ITERATIONS: u32 = 600_000;
+const SALT_BYTES: usize = 16;
+const IV_BYTES: usize = 12;
+// Sealed into the settings to tell a wrong passphrase from a damaged record
+const CHECK_PLAINTEXT: &str = "wasm-knowledge-chatbot";
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct EncryptionSettings {
+    /// Base64 PBKDF2 salt
+    pub salt: String,
+    pub iterations: u32,
+    /// `CHECK_PLAINTEXT` sealed with the key
+    pub check: String,
+}
+
+impl EncryptionSettings {
+    pub fn load() -> Option<Self> {
+        StorageUtils::retrieve_local(ENCRYPTION_SETTINGS_KEY)
+            .ok()
+            .flatten()
+    }
+
+    fn save(&self) -> AppResult<()> {
+        StorageUtils::store_local(ENCRYPTION_SETTINGS_KEY, self)
+    }
+}
+
+/// Whether stored data is encrypted, so the app has to be unlocked first
+pub fn is_enabled() -> bool {
+    EncryptionSettings::load().is_some()
+}
+
+/// Whether `raw` is a sealed record rather than plain JSON
+pub fn is_sealed(raw: &str) -> bool {
+    raw.starts_with(SEALED_PREFIX)
+}
+
+/// Why `passphrase` (typed twice as `confirm`) cannot be used
+pub fn validate_passphrase(passphrase: &str, confirm: &str) -> Result<(), String> {
+    if passphrase.chars().count() < MIN_PASSPHRASE_CHARS {
+        return Err(format!("Use at least {} characters", MIN_PASSPHRASE_CHARS));
+    }
+    if passphrase != confirm {
+        return Err("The passphrases do not match".to_string());
+    }
+    Ok(())
+}
+
+/// AES-GCM key of one passphrase
+#[derive(Clone)]
+pub struct StorageCipher {
+    key: CryptoKey,
+}
+
+impl StorageCipher {
+    pub async fn derive(passphrase: &str, salt: &[u8], iterations: u32) -> AppResult<Self> {
+        let subtle = subtle()?;
+        let usages = Array::of1(&JsValue::from_str("deriveKey"));
+        let material = subtle
+            .import_key_with_str(
+                "raw",
+                &Uint8Array::from(passphrase.as_bytes()),
+                "PBKDF2",
+                false,
+                &usages,
+            )
+            .map_err(|e| crypto_error("Key import failed", e))?;
+        let material: CryptoKey = JsFuture::from(material)
+            .await
+            .map_err(|e| crypto_error("Key import failed", e))?
+            .unchecked_into();
+        let algorithm = object(&[
+            ("name", JsValue::from_str("PBKDF2")),
+            ("salt", Uint8Array::from(salt).into()),
+            ("iterations", JsValue::from(iterations)),
+            ("hash", JsValue::from_str("SHA-256")),
+        ]);
+        let key_type = object(&[
+            ("name", JsValue::from_str("AES-GCM")),
+            ("length", JsValue::from(256)),
+        ]);
+        let usages = Array::of2(&JsValue::from_str("encrypt"), &JsValue::from_str("decrypt"));
+        let key = subtle
+            .derive_key_with_object_and_object(&algorithm, &material, &key_type, false, &usages)
+            .map_err(|e| crypto_error("Key derivation failed", e))?;
+        let key = JsFuture::from(key)
+            .await
+            .map_err(|e| crypto_error("Key derivation failed", e))?;
+        Ok(Self {
+            key: key.unchecked_into(),
+        })
+    }
+
+    pub async fn encrypt(&self, plaintext: &str) -> AppResult<String> {
+        let iv = random_bytes(IV_BYTES)?;
+        let params = gcm_params(&iv);
+        let sealed = subtle()?
+            .encrypt_with_object_and_buffer_source(
+                &params,
+                &self.key,
+                &Uint8Array::from(plaintext.as_bytes()),
+            )
+            .map_err(|e| crypto_error("Encryption failed", e))?;
+        let sealed = JsFuture::from(sealed)
+            .await
+            .map_err(|e| crypto_error("Encryption failed", e))?;
+        Ok(seal(&iv, &Uint8Array::new(&sealed).to_vec()))
+    }
+
+    /// Plaintext of a sealed record; fails on a wrong key or a damaged record
+    pub async fn decrypt(&self, raw: &str) -> AppResult<String> {
+        let (iv, ciphertext) =
+            unseal(raw).ok_or_else(|| AppError::storage("Not an encrypted record".to_string()))?;
+        let params = gcm_params(&iv);
+        let plain = subtle()?
+            .decrypt_with_object_and_buffer_source(
+                &params,
+                &self.key,
+                &Uint8Array::from(ciphertext.as_slice()),
+            )
+            .map_err(|e| crypto_error("Decryption failed", e))?;
+        let plain = JsFuture::from(plain)
+            .await
+            .map_err(|_| AppError::storage("Wrong passphrase or damaged record".to_string()))?;
+        String::from_utf8(Uint8Array::new(&plain).to_vec())
+            .map_err(|e| AppError::storage(format!("Decrypted record is not text: {}", e)))
+    }
+}
+
+/// A backend sealing every value it stores in `inner`. Plain values, e.g. records
+/// not yet rewritten when encryption was turned on, are read as they are.
+/// Sealing awaits WebCrypto before the value is stored; `PersistentStore` applies its
+/// writes one at a time, so a slower older write never lands after a newer one.
+pub struct EncryptedBackend {
+    inner: Rc<dyn StorageBackend>,
+    cipher: StorageCipher,
+}
+
+impl EncryptedBackend {
+    pub fn new(inner: Rc<dyn StorageBackend>, cipher: StorageCipher) -> Self {
+        Self { inner, cipher }
+    }
+}
+
+impl StorageBackend for EncryptedBackend {
+    // Named after the store holding the data, which decides e.g. the legacy migration
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    fn get<'a>(&'a self, key: &'a str) -> StorageFuture<'a, Option<String>> {
+        Box::pin(async move {
+            match self.inner.get(key).await? {
+                Some(raw) if is_sealed(&raw) => self.cipher.decrypt(&raw).await.map(Some),
+                other => Ok(other),
+            }
+        })
+    }
+
+    fn put<'a>(&'a self, key: &'a str, value: String) -> StorageFuture<'a, ()> {
+        Box::pin(async move {
+            let sealed = self.cipher.encrypt(&value).await?;
+            self.inner.put(key, sealed).await
+        })
+    }
+
+    fn delete<'a>(&'a self, key: &'a str) -> StorageFuture<'a, ()> {
+        self.inner.delete(key)
+    }
+}
+
+/// Cipher of `passphrase` if it is the one encryption was set up with
+async fn verify(settings: &EncryptionSettings, passphrase: &str) -> AppResult<StorageCipher> {
+    let salt = base64_decode(&settings.salt)
+        .ok_or_else(|| AppError::storage("Encryption settings are damaged".to_string()))?;
+    let cipher = StorageCipher::derive(passphrase, &salt, settings.iterations).await?;
+    match cipher.decrypt(&settings.check).await {
+        Ok(check) if check == CHECK_PLAINTEXT => Ok(cipher),
+        _ => Err(AppError::storage("Wrong passphrase".to_string())),
+    }
+}
+
+/// A new salt and check value for `passphrase`, with its cipher
+async fn setup(passphrase: &str) -> AppResult<(EncryptionSettings, StorageCipher)> {
+    let salt = random_bytes(SALT_BYTES)?;
+    let cipher = StorageCipher::derive(passphrase, &salt, PBKDF2_ITERATIONS).await?;
+    let settings = EncryptionSettings {
+        salt: base64_encode(&salt),
+        iterations: PBKDF2_ITERATIONS,
+        check: cipher.encrypt(CHECK_PLAINTEXT).await?,
+    };
+    Ok((settings, cipher))
+}
+
+/// Open the persistent storage with the key of `passphrase`. Replaces
+/// `init_persistent_storage` while encryption is on.
+pub async fn unlock(passphrase: &str) -> AppResult<()> {
+    let settings = EncryptionSettings::load()
+        .ok_or_else(|| AppError::storage("Storage is not encrypted".to_string()))?;
+    let cipher = verify(&settings, passphrase).await?;
+    let inner = super::persistent::open_backend().await;
+    super::persistent::init_persistent_storage_with(Rc::new(EncryptedBackend::new(inner, cipher)))
+        .await;
+    Ok(())
+}
+
+/// Encrypt every stored record with a new passphrase; returns the records rewritten
+pub async fn enable(passphrase: &str) -> AppResult<usize> {
+    if is_enabled() {
+        return Err(AppError::storage(
+            "Storage is already encrypted".to_string(),
+        ));
+    }
+    let (settings, cipher) = setup(passphrase).await?;
+    let inner = super::persistent::open_backend().await;
+    let next = Rc::new(EncryptedBackend::new(inner, cipher));
+    let rewritten = super::persistent::rewrite_with(next, || settings.save()).await?;
+    // Kept original files cannot be sealed, so they go
+    if super::originals::is_supported() {
+        if let Err(e) = super::originals::clear().await {
+            log::warn!("Original files not removed after encrypting: {}", e);
+        }
+    }
+    Ok(rewritten)
+}
+
+/// Re-encrypt every stored record under a new passphrase
+pub async fn change_passphrase(current: &str, passphrase: &str) -> AppResult<usize> {
+    let settings = EncryptionSettings::load()
+        .ok_or_else(|| AppError::storage("Storage is not encrypted".to_string()))?;
+    verify(&settings, current).await?;
+    let (settings, cipher) = setup(passphrase).await?;
+    let inner = super::persistent::open_backend().await;
+    let next = Rc::new(EncryptedBackend::new(inner, cipher));
+    super::persistent::rewrite_with(next, || settings.save()).await
+}
+
+/// Store every record in plain text again
+pub async fn disable(current: &str) -> AppResult<usize> {
+    let settings = EncryptionSettings::load()
+        .ok_or_else(|| AppError::storage("Storage is not encrypted".to_string()))?;
+    verify(&settings, current).await?;
+    let next = super::persistent::open_backend().await;
+    super::persistent::rewrite_with(next, || StorageUtils::remove_local(ENCRYPTION_SETTINGS_KEY))
+        .await
+}
+
+fn subtle() -> AppResult<SubtleCrypto> {
+    Ok(web_sys::window()
+        .ok_or_else(|| AppError::storage("Window not available".to_string()))?
+        .crypto()
+        .map_err(|e| crypto_error("WebCrypto not available", e))?
+        .subtle())
+}
+
+fn random_bytes(n: usize) -> AppResult<Vec<u8>> {
+    let mut bytes = vec![0u8; n];
+    web_sys::window()
+        .ok_or_else(|| AppError::storage("Window not available".to_string()))?
+        .crypto()
+        .and_then(|c| c.get_random_values_with_u8_array(&mut bytes))
+        .map_err(|e| crypto_error("No random source", e))?;
+    Ok(bytes)
+}
+
+fn gcm_params(iv: &[u8]) -> Object {
+    object(&[
+        ("name", JsValue::from_str("AES-GCM")),
+        ("iv", Uint8Array::from(iv).into()),
+    ])
+}
+
+fn object(entries: &[(&str, JsValue)]) -> Object {
+    let obj = Object::new();
+    for (name, value) in entries {
+        let _ = Reflect::set(&obj, &JsValue::from_str(name), value);
+    }
+    obj
+}
+
+fn crypto_error(context: &str, e: JsValue) -> AppError {
+    AppError::storage(format!("{}: {:?}", context, e))
+}
+
+fn seal(iv: &[u8], ciphertext: &[u8]) -> String {
+    let mut bytes = iv.to_vec();
+    bytes.extend_from_slice(ciphertext);
+    format!("{}{}", SEALED_PREFIX, base64_encode(&bytes))
+}
+
+/// IV and ciphertext of a sealed record
+fn unseal(raw: &str) -> Option<(Vec<u8>, Vec<u8>)> {
+    let bytes = base64_decode(raw.strip_prefix(SEALED_PREFIX)?)?;
+    if bytes.len() <= IV_BYTES {
+        return None;
+    }
+    let (iv, ciphertext) = bytes.split_at(IV_BYTES);
+    Some((iv.to_vec(), ciphertext.to_vec()))
+}
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn base64_encode(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, b)| n | (*b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(BASE64_ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+fn base64_decode(text: &str) -> Option<Vec<u8>> {
+    let text = text.trim_end_matches('=');
+    let mut out = Vec::with_capacity(text.len() * 3 / 4);
+    let (mut n, mut bits) = (0u32, 0);
+    for c in text.bytes() {
+        let v = BASE64_ALPHABET.iter().position(|a| *a == c)? as u32;
+        n = n << 6 | v;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((n >> bits & 0xff) as u8);
+        }
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sealed_records_round_trip() {
+        let iv: Vec<u8> = (0..IV_BYTES as u8).collect();
+        let sealed = seal(&iv, b"ciphertext!");
+        assert!(is_sealed(&sealed));
+        assert!(!is_sealed("[{\"id\":\"c1\"}]"));
+        assert_eq!(unseal(&sealed), Some((iv, b"ciphertext!".to_vec())));
+        assert_eq!(unseal("aesgcm1:AAAA"), None);
+        assert_eq!(unseal("aesgcm1:not base64!"), None);
+
+        for text in ["", "f", "fo", "foo", "foob", "fooba", "foobar"] {
+            let encoded = base64_encode(text.as_bytes());
+            assert_eq!(base64_decode(&encoded).unwrap(), text.as_bytes());
+        }
+        assert_eq!(base64_encode(b"foobar"), "Zm9vYmFy");
+        assert_eq!(base64_encode(b"fo"), "Zm8=");
+    }
+
+    #[test]
+    fn test_passphrase_rules() {
+        assert!(validate_passphrase("short", "short").is_err());
+        assert!(validate_passphrase("long enough", "long enougH")
+            .unwrap_err()
+            .contains("match"));
+        assert!(validate_passphrase("long enough", "long enough").is_ok());
+    }
+}
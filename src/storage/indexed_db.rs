@@ -0,0 +1,126 @@
+use super::backend::{StorageBackend, StorageFuture};
+use crate::models::app::{AppError, AppResult};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{IdbDatabase, IdbObjectStore, IdbRequest, IdbTransactionMode};
+
+const DB_NAME: &str = "wasm_knowledge_chatbot";
+const DB_VERSION: u32 = 1;
+const STORE_NAME: &str = "records";
+
+/// IndexedDB backend: a single object store of JSON strings keyed by record key
+pub struct IndexedDbBackend {
+    db: IdbDatabase,
+}
+
+impl IndexedDbBackend {
+    /// Open (and on first use create) the application database
+    pub async fn open() -> AppResult<Self> {
+        let factory = web_sys::window()
+            .ok_or_else(|| AppError::storage("Window not available".to_string()))?
+            .indexed_db()
+            .map_err(|e| js_error("IndexedDB not available", e))?
+            .ok_or_else(|| AppError::storage("IndexedDB not supported".to_string()))?;
+        let request = factory
+            .open_with_u32(DB_NAME, DB_VERSION)
+            .map_err(|e| js_error("Failed to open IndexedDB", e))?;
+
+        let upgrade_request = request.clone();
+        let on_upgrade = Closure::once(move |_event: JsValue| {
+            if let Ok(db) = upgrade_request.result() {
+                let db: IdbDatabase = db.unchecked_into();
+                if let Err(e) = db.create_object_store(STORE_NAME) {
+                    log::error!("Failed to create object store: {:?}", e);
+                }
+            }
+        });
+        request.set_onupgradeneeded(Some(on_upgrade.as_ref().unchecked_ref()));
+        on_upgrade.forget();
+
+        let db = request_result(&request)
+            .await
+            .map_err(|e| js_error("Failed to open IndexedDB", e))?;
+        Ok(Self {
+            db: db.unchecked_into(),
+        })
+    }
+
+    fn store(&self, mode: IdbTransactionMode) -> AppResult<IdbObjectStore> {
+        self.db
+            .transaction_with_str_and_mode(STORE_NAME, mode)
+            .and_then(|tx| tx.object_store(STORE_NAME))
+            .map_err(|e| js_error("IndexedDB transaction failed", e))
+    }
+}
+
+impl StorageBackend for IndexedDbBackend {
+    fn name(&self) -> &'static str {
+        "IndexedDB"
+    }
+
+    fn get<'a>(&'a self, key: &'a str) -> StorageFuture<'a, Option<String>> {
+        Box::pin(async move {
+            let request = self
+                .store(IdbTransactionMode::Readonly)?
+                .get(&JsValue::from_str(key))
+                .map_err(|e| js_error("IndexedDB get failed", e))?;
+            let value = request_result(&request)
+                .await
+                .map_err(|e| js_error("IndexedDB get failed", e))?;
+            Ok(value.as_string())
+        })
+    }
+
+    fn put<'a>(&'a self, key: &'a str, value: String) -> StorageFuture<'a, ()> {
+        Box::pin(async move {
+            let request = self
+                .store(IdbTransactionMode::Readwrite)?
+                .put_with_key(&JsValue::from_str(&value), &JsValue::from_str(key))
+                .map_err(|e| js_error("IndexedDB put failed", e))?;
+            request_result(&request)
+                .await
+                .map_err(|e| js_error("IndexedDB put failed", e))?;
+            Ok(())
+        })
+    }
+
+    fn delete<'a>(&'a self, key: &'a str) -> StorageFuture<'a, ()> {
+        Box::pin(async move {
+            let request = self
+                .store(IdbTransactionMode::Readwrite)?
+                .delete(&JsValue::from_str(key))
+                .map_err(|e| js_error("IndexedDB delete failed", e))?;
+            request_result(&request)
+                .await
+                .map_err(|e| js_error("IndexedDB delete failed", e))?;
+            Ok(())
+        })
+    }
+}
+
+/// Resolve with `request.result` on success, reject on error
+async fn request_result(request: &IdbRequest) -> Result<JsValue, JsValue> {
+    let promise = js_sys::Promise::new(&mut |resolve, reject| {
+        let ok_request = request.clone();
+        let on_success = Closure::once(move |_event: JsValue| {
+            let result = ok_request.result().unwrap_or(JsValue::UNDEFINED);
+            let _ = resolve.call1(&JsValue::NULL, &result);
+        });
+        let on_error = Closure::once(move |_event: JsValue| {
+            let _ = reject.call1(
+                &JsValue::NULL,
+                &JsValue::from_str("IndexedDB request failed"),
+            );
+        });
+        request.set_onsuccess(Some(on_success.as_ref().unchecked_ref()));
+        request.set_onerror(Some(on_error.as_ref().unchecked_ref()));
+        on_success.forget();
+        on_error.forget();
+    });
+    JsFuture::from(promise).await
+}
+
+fn js_error(context: &str, e: JsValue) -> AppError {
+    AppError::storage(format!("{}: {:?}", context, e))
+}
@@ -0,0 +1,566 @@
+//! Startup integrity pass over the stored records. Every known key is parsed as the
+//! type the app reads it as; a record that no longer parses is moved aside to
+//! `corrupt_<key>` and reported, instead of the feature reading it silently falling
+//! back to defaults or failing later. Quarantined records are repaired or deleted from
+//! the Storage settings.
+
+use super::backend::{LocalStorageBackend, StorageBackend};
+use super::persistent::PersistentStore;
+use crate::models::app::{AppError, AppResult};
+use crate::utils::storage::StorageUtils;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::cell::RefCell;
+
+/// Prefix of the key a corrupt record is moved to
+pub const CORRUPT_KEY_PREFIX: &str = "corrupt_";
+/// localStorage key of the records still in quarantine
+pub const INTEGRITY_REPORT_KEY: &str = "storage_integrity_report_v1";
+
+thread_local! {
+    static THIS_LAUNCH: RefCell<Vec<QuarantinedRecord>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Where a record is kept
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StorageTier {
+    /// IndexedDB, through `PersistentStore`
+    Persistent,
+    /// localStorage
+    Local,
+}
+
+type Check = fn(&Value) -> Result<(), String>;
+
+/// A stored record and the type it must parse as
+#[derive(Debug)]
+pub struct KnownKey {
+    pub key: &'static str,
+    pub label: &'static str,
+    pub tier: StorageTier,
+    check: Check,
+}
+
+fn parses<T: DeserializeOwned>(value: &Value) -> Result<(), String> {
+    T::deserialize(value).map(|_| ()).map_err(|e| e.to_string())
+}
+
+const fn known(
+    key: &'static str,
+    label: &'static str,
+    tier: StorageTier,
+    check: Check,
+) -> KnownKey {
+    KnownKey {
+        key,
+        label,
+        tier,
+        check,
+    }
+}
+
+/// Records checked at startup
+pub const KNOWN_KEYS: &[KnownKey] = {
+    use crate::features::graphrag as rag;
+    use crate::models;
+    use StorageTier::{Local, Persistent};
+    &[
+        known(
+            super::conversation_storage::CONVERSATIONS_KEY,
+            "Conversations",
+            Persistent,
+            parses::<Vec<super::conversation_storage::Conversation>>,
+        ),
+        known(
+            "knowledge_upload_buffer_v1",
+            "Uploaded documents",
+            Persistent,
+            parses::<String>,
+        ),
+        known(
+            rag::pipeline::DOCUMENT_INDEX_KEY_V1,
+            "Document index",
+            Persistent,
+            parses::<Vec<models::graphrag::DocumentIndex>>,
+        ),
+        known(
+            models::graph_store::GRAPH_STORE_KEY_V1,
+            "Knowledge graph",
+            Persistent,
+            parses::<models::graph_store::GraphStore>,
+        ),
+        known(
+            rag::embeddings::VECTOR_INDEX_KEY,
+            "Embedding vectors",
+            Persistent,
+            parses::<rag::embeddings::VectorIndex>,
+        ),
+        known(
+            crate::pagerank_reranking::NODE_IMPORTANCE_KEY,
+            "Node importance",
+            Persistent,
+            parses::<crate::pagerank_reranking::NodeImportance>,
+        ),
+        known(
+            super::long_messages::LONG_MESSAGE_INDEX_KEY,
+            "Long message index",
+            Persistent,
+            parses::<super::long_messages::LongMessageIndex>,
+        ),
+        known(
+            super::cache::CACHE_INDEX_KEY,
+            "Cache index",
+            Persistent,
+            parses::<super::cache::CacheIndex>,
+        ),
+        known(
+            rag::content_store::STOWED_CONTENT_KEY,
+            "Passage text index",
+            Persistent,
+            parses::<rag::content_store::StowedIndex>,
+        ),
+        known(
+            models::app::APP_CONFIG_KEY_V1,
+            "App settings",
+            Persistent,
+            parses::<models::app::AppConfig>,
+        ),
+        known(
+            models::attachment::ATTACHMENTS_KEY_V1,
+            "Conversation attachments",
+            Persistent,
+            parses::<models::attachment::Attachments>,
+        ),
+        known(
+            models::collection::COLLECTIONS_KEY_V1,
+            "Collections",
+            Persistent,
+            parses::<models::collection::Collections>,
+        ),
+        known(
+            models::freshness::DOCUMENT_EXPIRY_KEY_V1,
+            "Document expiry dates",
+            Persistent,
+            parses::<models::freshness::DocumentExpiry>,
+        ),
+        known(
+            crate::graphrag_config::GRAPHRAG_CONFIG_KEY,
+            "GraphRAG settings",
+            Local,
+            parses::<crate::graphrag_config::GraphRAGConfig>,
+        ),
+        known(
+            crate::utils::generation::GENERATION_SETTINGS_KEY,
+            "Generation settings",
+            Local,
+            parses::<models::generation::GenerationSettings>,
+        ),
+        known(
+            crate::utils::import_queue::IMPORT_SETTINGS_KEY,
+            "Import settings",
+            Local,
+            parses::<crate::utils::import_queue::ImportSettings>,
+        ),
+        known(
+            crate::utils::notifications::NOTIFICATION_SETTINGS_KEY,
+            "Notification settings",
+            Local,
+            parses::<crate::utils::notifications::NotificationSettings>,
+        ),
+        known(
+            rag::index_stats::INDEX_MANIFEST_KEY,
+            "Index manifest",
+            Local,
+            parses::<rag::index_stats::IndexManifest>,
+        ),
+        known(
+            rag::index_generation::INDEX_GENERATION_KEY,
+            "Index generations",
+            Local,
+            parses::<rag::index_generation::IndexGenerations>,
+        ),
+        known(
+            crate::state::tasks_state_simple::TASKS_KEY,
+            "Tasks",
+            Local,
+            parses::<Vec<models::tasks::Task>>,
+        ),
+        known(
+            crate::state::crm_state_simple::CUSTOMERS_KEY,
+            "CRM customers",
+            Local,
+            parses::<Vec<models::crm::Customer>>,
+        ),
+        known(
+            crate::state::crm_state_simple::LEADS_KEY,
+            "CRM leads",
+            Local,
+            parses::<Vec<models::crm::Lead>>,
+        ),
+        known(
+            crate::state::crm_state_simple::DEALS_KEY,
+            "CRM deals",
+            Local,
+            parses::<Vec<models::crm::Deal>>,
+        ),
+        known(
+            crate::state::crm_state_simple::STAGES_KEY,
+            "CRM pipeline stages",
+            Local,
+            parses::<Vec<models::crm::PipelineStage>>,
+        ),
+        known(
+            crate::state::crm_state_simple::OWNERS_KEY,
+            "CRM owners",
+            Local,
+            parses::<Vec<String>>,
+        ),
+    ]
+};
+
+impl KnownKey {
+    pub fn find(key: &str) -> Option<&'static KnownKey> {
+        KNOWN_KEYS.iter().find(|k| k.key == key)
+    }
+
+    pub fn corrupt_key(&self) -> String {
+        format!("{}{}", CORRUPT_KEY_PREFIX, self.key)
+    }
+
+    /// Why `raw` cannot be read as this record, `None` when it can
+    pub fn problem(&self, raw: &str) -> Option<String> {
+        match serde_json::from_str::<Value>(raw) {
+            Ok(value) => (self.check)(&value).err(),
+            Err(e) => Some(format!("not valid JSON: {}", e)),
+        }
+    }
+
+    /// Keep the parts of a corrupt `raw` record that still parse: entries of a list
+    /// or map, or of the list and map fields of a settings object.
+    pub fn repair(&self, raw: &str) -> Result<Repair, String> {
+        let value: Value = serde_json::from_str(raw)
+            .map_err(|e| format!("Not valid JSON, nothing to salvage: {}", e))?;
+        let (value, dropped) = salvage(value, self.check)
+            .ok_or_else(|| "No part of the record has the expected shape".to_string())?;
+        Ok(Repair { value, dropped })
+    }
+}
+
+/// What a repair keeps of a quarantined record
+#[derive(Clone, Debug, PartialEq)]
+pub struct Repair {
+    pub value: Value,
+    /// Entries left out because they do not parse
+    pub dropped: usize,
+}
+
+fn salvage(value: Value, check: Check) -> Option<(Value, usize)> {
+    if check(&value).is_ok() {
+        return Some((value, 0));
+    }
+    match value {
+        Value::Array(items) => {
+            let (kept, dropped) = keep_entries(items, |item| check(&Value::Array(vec![item])));
+            let value = Value::Array(kept);
+            let salvaged = value.as_array().is_some_and(|v| !v.is_empty());
+            (salvaged && check(&value).is_ok()).then_some((value, dropped))
+        }
+        Value::Object(fields) => {
+            salvage_map(fields.clone(), check).or_else(|| salvage_fields(fields, check))
+        }
+        _ => None,
+    }
+}
+
+/// Objects read as maps, e.g. indexes keyed by id, keeping the entries that parse
+fn salvage_map(fields: Map<String, Value>, check: Check) -> Option<(Value, usize)> {
+    let (kept, dropped) = keep_entries(fields.into_iter().collect(), |(k, v)| {
+        check(&Value::Object(Map::from_iter([(k, v)])))
+    });
+    let value = Value::Object(kept.into_iter().collect());
+    (!value.as_object()?.is_empty() && check(&value).is_ok()).then_some((value, dropped))
+}
+
+/// Objects read as structs, keeping the entries of their list and map fields that
+/// parse. Every other field must be intact.
+fn salvage_fields(fields: Map<String, Value>, check: Check) -> Option<(Value, usize)> {
+    let skeleton: Map<String, Value> = fields
+        .iter()
+        .map(|(k, v)| {
+            let emptied = match v {
+                Value::Array(_) => Value::Array(Vec::new()),
+                Value::Object(_) => Value::Object(Map::new()),
+                other => other.clone(),
+            };
+            (k.clone(), emptied)
+        })
+        .collect();
+    check(&Value::Object(skeleton.clone())).ok()?;
+
+    let with_field = |name: &str, content: Value| {
+        let mut object = skeleton.clone();
+        object.insert(name.to_string(), content);
+        check(&Value::Object(object))
+    };
+    let mut repaired = skeleton.clone();
+    let mut dropped = 0;
+    for (name, content) in fields {
+        let content = match content {
+            Value::Array(items) => {
+                let (kept, n) =
+                    keep_entries(items, |item| with_field(&name, Value::Array(vec![item])));
+                dropped += n;
+                Value::Array(kept)
+            }
+            Value::Object(entries) => {
+                let (kept, n) = keep_entries(entries.into_iter().collect(), |(k, v)| {
+                    with_field(&name, Value::Object(Map::from_iter([(k, v)])))
+                });
+                dropped += n;
+                Value::Object(kept.into_iter().collect())
+            }
+            _ => continue,
+        };
+        repaired.insert(name, content);
+    }
+    let value = Value::Object(repaired);
+    check(&value).ok()?;
+    Some((value, dropped))
+}
+
+fn keep_entries<T: Clone>(
+    entries: Vec<T>,
+    parses: impl Fn(T) -> Result<(), String>,
+) -> (Vec<T>, usize) {
+    let total = entries.len();
+    let kept: Vec<T> = entries
+        .into_iter()
+        .filter(|e| parses(e.clone()).is_ok())
+        .collect();
+    let dropped = total - kept.len();
+    (kept, dropped)
+}
+
+/// A record moved aside because it did not parse
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct QuarantinedRecord {
+    pub key: String,
+    pub error: String,
+    pub bytes: usize,
+    pub quarantined_at: f64,
+}
+
+impl QuarantinedRecord {
+    pub fn label(&self) -> &str {
+        KnownKey::find(&self.key).map_or(self.key.as_str(), |k| k.label)
+    }
+}
+
+/// Records in quarantine until repaired or deleted
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct IntegrityReport {
+    pub quarantined: Vec<QuarantinedRecord>,
+}
+
+impl IntegrityReport {
+    pub fn load() -> Self {
+        StorageUtils::retrieve_local(INTEGRITY_REPORT_KEY)
+            .ok()
+            .flatten()
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> AppResult<()> {
+        StorageUtils::store_local(INTEGRITY_REPORT_KEY, self)
+    }
+
+    /// Add `record`, replacing an older quarantine of the same key
+    pub fn add(&mut self, record: QuarantinedRecord) {
+        self.quarantined.retain(|r| r.key != record.key);
+        self.quarantined.push(record);
+    }
+
+    pub fn resolve(&mut self, key: &str) {
+        self.quarantined.retain(|r| r.key != key);
+    }
+}
+
+/// Records quarantined by the startup pass of this launch
+pub fn quarantined_this_launch() -> Vec<QuarantinedRecord> {
+    THIS_LAUNCH.with(|q| q.borrow().clone())
+}
+
+/// Check every known record and move the corrupt ones to their `corrupt_` key.
+/// Persistent records are read through `preloaded`; returns the persistent keys that
+/// were moved, which the caller must forget. A record whose copy cannot be written is
+/// left in place.
+pub async fn quarantine_corrupt(
+    backend: &dyn StorageBackend,
+    preloaded: impl Fn(&str) -> Option<String>,
+) -> Vec<&'static str> {
+    let local = LocalStorageBackend;
+    let mut report = IntegrityReport::load();
+    let mut moved = Vec::new();
+    let mut quarantined = false;
+    for known in KNOWN_KEYS {
+        let (store, raw): (&dyn StorageBackend, _) = match known.tier {
+            StorageTier::Persistent => (backend, preloaded(known.key)),
+            StorageTier::Local => (&local, local.get(known.key).await.ok().flatten()),
+        };
+        let Some(raw) = raw else {
+            continue;
+        };
+        // Unreadable without the passphrase, not corrupt
+        if super::encryption::is_sealed(&raw) {
+            continue;
+        }
+        let Some(error) = known.problem(&raw) else {
+            continue;
+        };
+        log::warn!("Quarantining corrupt record {}: {}", known.key, error);
+        let copy = serde_json::to_string(&raw).unwrap_or_default();
+        if let Err(e) = store.put(&known.corrupt_key(), copy).await {
+            log::error!(
+                "Could not set aside corrupt {}, left in place: {}",
+                known.key,
+                e
+            );
+            continue;
+        }
+        if let Err(e) = store.delete(known.key).await {
+            log::error!("Failed to remove corrupt {}: {}", known.key, e);
+        }
+        let record = QuarantinedRecord {
+            key: known.key.to_string(),
+            error,
+            bytes: raw.len(),
+            quarantined_at: js_sys::Date::now(),
+        };
+        THIS_LAUNCH.with(|q| q.borrow_mut().push(record.clone()));
+        report.add(record);
+        quarantined = true;
+        if known.tier == StorageTier::Persistent {
+            moved.push(known.key);
+        }
+    }
+    if quarantined {
+        if let Err(e) = report.save() {
+            log::error!("Failed to save the integrity report: {}", e);
+        }
+    }
+    moved
+}
+
+/// Keys of the quarantined copies held by `PersistentStore`, which are never preloaded
+pub fn persistent_corrupt_keys() -> Vec<String> {
+    IntegrityReport::load()
+        .quarantined
+        .iter()
+        .filter_map(|r| KnownKey::find(&r.key))
+        .filter(|k| k.tier == StorageTier::Persistent)
+        .map(KnownKey::corrupt_key)
+        .collect()
+}
+
+/// Whether `known` holds a record again, e.g. one saved since the quarantine
+pub fn has_record(known: &KnownKey) -> bool {
+    match known.tier {
+        StorageTier::Persistent => PersistentStore::read::<Value>(known.key),
+        StorageTier::Local => StorageUtils::retrieve_local::<Value>(known.key),
+    }
+    .is_ok_and(|r| r.is_some())
+}
+
+/// Restore what still parses of the quarantined copy of `key`, replacing the current
+/// record. Returns how many entries were left out.
+pub async fn repair_quarantined(key: &str) -> AppResult<usize> {
+    let known =
+        KnownKey::find(key).ok_or_else(|| AppError::storage(format!("Unknown record: {}", key)))?;
+    let raw: Option<String> = match known.tier {
+        StorageTier::Persistent => PersistentStore::read_cold(&known.corrupt_key()).await?,
+        StorageTier::Local => StorageUtils::retrieve_local(&known.corrupt_key())?,
+    };
+    let raw = raw.ok_or_else(|| AppError::storage("The quarantined copy is gone".to_string()))?;
+    let repair = known.repair(&raw).map_err(AppError::storage)?;
+    match known.tier {
+        StorageTier::Persistent => PersistentStore::write(key, &repair.value)?,
+        StorageTier::Local => StorageUtils::store_local(key, &repair.value)?,
+    }
+    delete_quarantined(key)?;
+    Ok(repair.dropped)
+}
+
+/// Drop the quarantined copy of `key` and its report entry
+pub fn delete_quarantined(key: &str) -> AppResult<()> {
+    if let Some(known) = KnownKey::find(key) {
+        match known.tier {
+            StorageTier::Persistent => PersistentStore::remove(&known.corrupt_key())?,
+            StorageTier::Local => StorageUtils::remove_local(&known.corrupt_key())?,
+        }
+    }
+    let mut report = IntegrityReport::load();
+    report.resolve(key);
+    report.save()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Deserialize)]
+    #[allow(dead_code)]
+    struct Item {
+        id: String,
+        size: u32,
+    }
+
+    #[derive(Deserialize)]
+    #[allow(dead_code)]
+    struct Settings {
+        name: String,
+        items: Vec<Item>,
+        #[serde(default)]
+        by_id: std::collections::BTreeMap<String, Item>,
+    }
+
+    fn key(check: Check) -> KnownKey {
+        known("test", "Test", StorageTier::Local, check)
+    }
+
+    #[test]
+    fn test_repair_keeps_entries_that_still_parse() {
+        let list = key(parses::<Vec<Item>>);
+        assert_eq!(list.problem(r#"[{"id":"a","size":1}]"#), None);
+        assert!(list
+            .problem(r#"[{"id":"a","size":1}"#)
+            .is_some_and(|e| e.starts_with("not valid JSON")));
+
+        let broken = r#"[{"id":"a","size":1},{"id":"b","size":"big"},{"id":"c","size":3}]"#;
+        assert!(list.problem(broken).is_some());
+        let repair = list.repair(broken).unwrap();
+        assert_eq!(repair.dropped, 1);
+        assert_eq!(repair.value.as_array().map(Vec::len), Some(2));
+        assert!(list.repair(r#"[{"id":"a","size":1}"#).is_err());
+        assert!(list.repair(r#"[{"size":"big"}]"#).is_err());
+
+        let map = key(parses::<std::collections::BTreeMap<String, Item>>);
+        let repair = map
+            .repair(r#"{"a":{"id":"a","size":1},"b":{"id":"b"}}"#)
+            .unwrap();
+        assert_eq!(repair.dropped, 1);
+        assert!(repair.value.get("a").is_some());
+
+        let settings = key(parses::<Settings>);
+        let repair = settings
+            .repair(
+                r#"{"name":"x","items":[{"id":"a","size":1},{"id":2}],
+                    "by_id":{"a":{"id":"a","size":1},"b":null}}"#,
+            )
+            .unwrap();
+        assert_eq!(repair.dropped, 2);
+        assert_eq!(repair.value["items"].as_array().map(Vec::len), Some(1));
+        // A broken plain field cannot be guessed
+        assert!(settings.repair(r#"{"name":3,"items":[]}"#).is_err());
+    }
+}
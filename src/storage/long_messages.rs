@@ -0,0 +1,201 @@
+//! Very long message bodies are kept out of the conversation record: each one is split
+//! into chunk records and the conversation stores a short preview in its place, so
+//! saving a conversation no longer re-serializes every pasted megabyte.
+
+use super::conversation_storage::Conversation;
+use super::persistent::PersistentStore;
+use crate::features::graphrag::index_stats::fingerprint;
+use crate::models::app::AppResult;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+
+/// Record key of the chunked message index
+pub const LONG_MESSAGE_INDEX_KEY: &str = "wasm_llm_long_messages_v1";
+/// Prefix of the chunk records
+const CHUNK_KEY_PREFIX: &str = "wasm_llm_message_chunk_v1";
+/// Bodies longer than this (in chars) are stored in chunks
+pub const LONG_MESSAGE_CHARS: usize = 16_000;
+const CHUNK_CHARS: usize = 16_000;
+/// Characters of the body kept inline as a preview
+const PREVIEW_CHARS: usize = 280;
+
+/// Where the chunks of one message body live
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ChunkedBody {
+    pub chunks: u32,
+    /// Fingerprint of the full body, to notice edits of an already chunked message
+    pub fingerprint: u64,
+}
+
+/// Chunked bodies by `conversation_id/message_id`
+pub type LongMessageIndex = BTreeMap<String, ChunkedBody>;
+
+pub struct LongMessageStore;
+
+impl LongMessageStore {
+    pub fn load_index() -> LongMessageIndex {
+        PersistentStore::read(LONG_MESSAGE_INDEX_KEY)
+            .ok()
+            .flatten()
+            .unwrap_or_default()
+    }
+
+    /// Move long bodies into chunk records, leaving previews in `conversations`.
+    /// Chunks of messages that no longer exist are deleted.
+    pub fn stow(conversations: &mut [Conversation]) -> AppResult<()> {
+        let mut index = Self::load_index();
+        let before = index.clone();
+        let mut live = HashSet::new();
+        for c in conversations.iter_mut() {
+            for m in c.messages.iter_mut() {
+                let body_key = body_key(&c.id, &m.id);
+                if m.content.chars().count() <= LONG_MESSAGE_CHARS {
+                    // Already a preview of a stowed body
+                    if index.contains_key(&body_key) && is_preview(&m.content) {
+                        live.insert(body_key);
+                    }
+                    continue;
+                }
+                let fp = fingerprint(&m.content);
+                if index.get(&body_key).map(|b| b.fingerprint) != Some(fp) {
+                    let chunks = split_chunks(&m.content, CHUNK_CHARS);
+                    for (i, chunk) in chunks.iter().enumerate() {
+                        PersistentStore::write(&chunk_key(&body_key, i as u32), chunk)?;
+                    }
+                    if let Some(old) = index.get(&body_key) {
+                        for i in chunks.len() as u32..old.chunks {
+                            PersistentStore::remove(&chunk_key(&body_key, i))?;
+                        }
+                    }
+                    index.insert(
+                        body_key.clone(),
+                        ChunkedBody {
+                            chunks: chunks.len() as u32,
+                            fingerprint: fp,
+                        },
+                    );
+                }
+                m.content = preview(&m.content);
+                live.insert(body_key);
+            }
+        }
+        let stale: Vec<String> = index
+            .keys()
+            .filter(|k| !live.contains(*k))
+            .cloned()
+            .collect();
+        for key in stale {
+            if let Some(body) = index.remove(&key) {
+                for i in 0..body.chunks {
+                    PersistentStore::remove(&chunk_key(&key, i))?;
+                }
+            }
+        }
+        if index != before {
+            PersistentStore::write(LONG_MESSAGE_INDEX_KEY, &index)?;
+        }
+        Ok(())
+    }
+
+    /// Replace previews with their full bodies. A body with a missing chunk keeps its
+    /// preview.
+    pub fn restore(conversations: &mut [Conversation]) {
+        let index = Self::load_index();
+        if index.is_empty() {
+            return;
+        }
+        for c in conversations.iter_mut() {
+            for m in c.messages.iter_mut() {
+                let body_key = body_key(&c.id, &m.id);
+                let Some(body) = index.get(&body_key) else {
+                    continue;
+                };
+                let chunks: Option<Vec<String>> = (0..body.chunks)
+                    .map(|i| {
+                        PersistentStore::read::<String>(&chunk_key(&body_key, i))
+                            .ok()
+                            .flatten()
+                    })
+                    .collect();
+                match chunks {
+                    Some(chunks) => m.content = chunks.concat(),
+                    None => log::warn!("Message body {} is missing chunks", body_key),
+                }
+            }
+        }
+    }
+}
+
+/// Record keys of every chunk listed in `index`, for preloading
+pub fn chunk_keys(index: &LongMessageIndex) -> Vec<String> {
+    index
+        .iter()
+        .flat_map(|(body_key, body)| (0..body.chunks).map(move |i| chunk_key(body_key, i)))
+        .collect()
+}
+
+fn body_key(conversation_id: &str, message_id: &str) -> String {
+    format!("{}/{}", conversation_id, message_id)
+}
+
+fn chunk_key(body_key: &str, index: u32) -> String {
+    format!("{}:{}:{}", CHUNK_KEY_PREFIX, body_key, index)
+}
+
+/// Split `content` into pieces of at most `size` chars
+pub fn split_chunks(content: &str, size: usize) -> Vec<String> {
+    let chars: Vec<char> = content.chars().collect();
+    chars
+        .chunks(size.max(1))
+        .map(|c| c.iter().collect())
+        .collect()
+}
+
+const PREVIEW_MARKER: &str = "\n\n[…]";
+
+fn preview(content: &str) -> String {
+    let mut out: String = content.chars().take(PREVIEW_CHARS).collect();
+    out.push_str(PREVIEW_MARKER);
+    out
+}
+
+fn is_preview(content: &str) -> bool {
+    content.ends_with(PREVIEW_MARKER)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_chunks_round_trips() {
+        let content = "é".repeat(25) + "abc";
+        let chunks = split_chunks(&content, 10);
+        assert_eq!(chunks.len(), 3);
+        assert!(chunks.iter().all(|c| c.chars().count() <= 10));
+        assert_eq!(chunks.concat(), content);
+        assert!(split_chunks("", 10).is_empty());
+    }
+
+    #[test]
+    fn test_chunk_keys_cover_every_chunk() {
+        let mut index = LongMessageIndex::new();
+        index.insert(
+            body_key("c1", "m1"),
+            ChunkedBody {
+                chunks: 2,
+                fingerprint: 1,
+            },
+        );
+        assert_eq!(
+            chunk_keys(&index),
+            vec![
+                "wasm_llm_message_chunk_v1:c1/m1:0".to_string(),
+                "wasm_llm_message_chunk_v1:c1/m1:1".to_string()
+            ]
+        );
+        let long = "x".repeat(LONG_MESSAGE_CHARS + 1);
+        assert!(is_preview(&preview(&long)));
+        assert!(preview(&long).chars().count() < PREVIEW_CHARS + 10);
+    }
+}
@@ -0,0 +1,272 @@
+//! Forward migrations of the stored data. Each build knows the schema version it
+//! writes; at startup the migrations between the version found in the store and that
+//! one run in order, before the integrity check quarantines records that still do not
+//! parse. The version is kept in the persistent backend itself, so a store opened for
+//! the first time (e.g. IndexedDB becoming available) is migrated from the start.
+//!
+//! Every step must be safe to run again: a store whose version record is lost is
+//! migrated from the start.
+
+use super::backend::{LocalStorageBackend, StorageBackend};
+use super::integrity::StorageTier;
+use crate::models::app::AppResult;
+
+/// Record holding the schema version of the store
+pub const SCHEMA_VERSION_KEY: &str = "storage_schema_version";
+
+/// One change to the stored data
+#[derive(Debug)]
+pub enum Step {
+    /// Move records kept in localStorage by older builds into the persistent backend,
+    /// under the same keys
+    ToPersistent(&'static [&'static str]),
+    /// Move a record to a new key, possibly in the other tier
+    Rename {
+        from: &'static str,
+        from_tier: StorageTier,
+        to: &'static str,
+        to_tier: StorageTier,
+    },
+}
+
+#[derive(Debug)]
+pub struct Migration {
+    /// Schema version once the migration has run
+    pub version: u32,
+    pub description: &'static str,
+    pub steps: &'static [Step],
+}
+
+/// Records migration 1 moved out of localStorage, as the list stood when it shipped.
+/// Never extended: keys moved later get a migration of their own.
+const V1_PERSISTENT_KEYS: &[&str] = &[
+    super::conversation_storage::CONVERSATIONS_KEY,
+    "knowledge_upload_buffer_v1",
+    crate::features::graphrag::pipeline::DOCUMENT_INDEX_KEY_V1,
+    crate::models::graph_store::GRAPH_STORE_KEY_V1,
+    crate::features::graphrag::embeddings::VECTOR_INDEX_KEY,
+    crate::pagerank_reranking::NODE_IMPORTANCE_KEY,
+    super::long_messages::LONG_MESSAGE_INDEX_KEY,
+    super::cache::CACHE_INDEX_KEY,
+];
+
+/// Records served by the persistent backend since migration 1 shipped
+const V4_PERSISTENT_KEYS: &[&str] = &[
+    crate::models::app::APP_CONFIG_KEY_V1,
+    crate::features::graphrag::content_store::STOWED_CONTENT_KEY,
+    crate::models::attachment::ATTACHMENTS_KEY_V1,
+    crate::models::collection::COLLECTIONS_KEY_V1,
+    crate::models::freshness::DOCUMENT_EXPIRY_KEY_V1,
+];
+
+/// Every migration, oldest first
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "Move the large datasets from localStorage to IndexedDB",
+        steps: &[Step::ToPersistent(V1_PERSISTENT_KEYS)],
+    },
+    Migration {
+        version: 2,
+        description: "Version the GraphRAG settings key",
+        steps: &[Step::Rename {
+            from: "graphrag_config",
+            from_tier: StorageTier::Local,
+            to: "graphrag_config_v1",
+            to_tier: StorageTier::Local,
+        }],
+    },
+    Migration {
+        version: 3,
+        description: "Move the unversioned document index to the persistent store",
+        steps: &[Step::Rename {
+            from: "graphrag_document_index",
+            from_tier: StorageTier::Local,
+            to: crate::features::graphrag::pipeline::DOCUMENT_INDEX_KEY_V1,
+            to_tier: StorageTier::Persistent,
+        }],
+    },
+    Migration {
+        version: 4,
+        description:
+            "Move settings, stowed passages, attachments, collections and expiry dates to IndexedDB",
+        steps: &[Step::ToPersistent(V4_PERSISTENT_KEYS)],
+    },
+];
+
+/// Schema version written by this build
+pub fn current_version() -> u32 {
+    MIGRATIONS.last().map_or(0, |m| m.version)
+}
+
+/// Migrations still to run on a store at `stored` version
+pub fn pending(stored: u32) -> impl Iterator<Item = &'static Migration> {
+    MIGRATIONS.iter().filter(move |m| m.version > stored)
+}
+
+/// Version recorded in the store; a missing or unreadable record counts as a store
+/// never migrated
+fn parse_version(raw: Option<&str>) -> u32 {
+    raw.and_then(|raw| raw.trim().parse().ok()).unwrap_or(0)
+}
+
+/// Bring `backend` up to the current schema version. A migration that fails stops
+/// the run without recording its version, so it is tried again at the next launch.
+/// Returns the version the store is at.
+pub async fn run(backend: &dyn StorageBackend) -> u32 {
+    let stored = match backend.get(SCHEMA_VERSION_KEY).await {
+        Ok(raw) => parse_version(raw.as_deref()),
+        Err(e) => {
+            log::error!("Failed to read the storage schema version: {}", e);
+            return 0;
+        }
+    };
+    if stored > current_version() {
+        log::warn!(
+            "Stored data is at schema version {}, newer than this build ({})",
+            stored,
+            current_version()
+        );
+        return stored;
+    }
+    let mut version = stored;
+    for migration in pending(stored) {
+        let mut outcome = Ok(());
+        for step in migration.steps {
+            outcome = run_step(backend, step).await;
+            if outcome.is_err() {
+                break;
+            }
+        }
+        let outcome = match outcome {
+            Ok(()) => {
+                backend
+                    .put(SCHEMA_VERSION_KEY, migration.version.to_string())
+                    .await
+            }
+            Err(e) => Err(e),
+        };
+        if let Err(e) = outcome {
+            log::error!(
+                "Storage migration {} ({}) failed: {}",
+                migration.version,
+                migration.description,
+                e
+            );
+            break;
+        }
+        log::info!(
+            "Storage migrated to version {}: {}",
+            migration.version,
+            migration.description
+        );
+        version = migration.version;
+    }
+    version
+}
+
+async fn run_step(backend: &dyn StorageBackend, step: &Step) -> AppResult<()> {
+    let local = LocalStorageBackend;
+    match step {
+        Step::ToPersistent(keys) => {
+            // Already there when the backend is localStorage
+            if backend.name() == local.name() {
+                return Ok(());
+            }
+            for key in *keys {
+                move_record(&local, key, backend, key).await?;
+            }
+            Ok(())
+        }
+        Step::Rename {
+            from,
+            from_tier,
+            to,
+            to_tier,
+        } => {
+            let store = |tier: &StorageTier| -> &dyn StorageBackend {
+                match tier {
+                    StorageTier::Persistent => backend,
+                    StorageTier::Local => &local,
+                }
+            };
+            move_record(store(from_tier), from, store(to_tier), to).await
+        }
+    }
+}
+
+/// Copy the record at `from` to `to` unless one is already there, then free `from`.
+/// The value is copied as it is: one that does not parse is quarantined under its new
+/// key by the integrity check that follows.
+async fn move_record(
+    source: &dyn StorageBackend,
+    from: &str,
+    target: &dyn StorageBackend,
+    to: &str,
+) -> AppResult<()> {
+    let Some(raw) = source.get(from).await? else {
+        return Ok(());
+    };
+    if target.get(to).await?.is_none() {
+        target.put(to, raw).await?;
+    }
+    source.delete(from).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::persistent::PERSISTENT_KEYS;
+    use super::*;
+
+    /// Keys the migrations still to run on a store at `stored` version move out of
+    /// localStorage
+    fn keys_moved_from(stored: u32) -> Vec<&'static str> {
+        pending(stored)
+            .flat_map(|m| m.steps)
+            .flat_map(|step| match step {
+                Step::ToPersistent(keys) => keys.to_vec(),
+                Step::Rename { .. } => Vec::new(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_migrations_run_in_order_from_the_stored_version() {
+        let versions: Vec<u32> = MIGRATIONS.iter().map(|m| m.version).collect();
+        assert_eq!(versions.first(), Some(&1));
+        assert!(versions.windows(2).all(|w| w[1] == w[0] + 1));
+        assert_eq!(current_version(), *versions.last().unwrap());
+
+        assert_eq!(pending(0).count(), MIGRATIONS.len());
+        assert_eq!(
+            pending(1).map(|m| m.version).collect::<Vec<_>>(),
+            versions[1..].to_vec()
+        );
+        assert_eq!(pending(current_version()).count(), 0);
+        assert_eq!(pending(current_version() + 1).count(), 0);
+
+        assert_eq!(parse_version(None), 0);
+        assert_eq!(parse_version(Some("2")), 2);
+        assert_eq!(parse_version(Some(" 3\n")), 3);
+        assert_eq!(parse_version(Some("garbage")), 0);
+    }
+
+    #[test]
+    fn test_stores_migrated_before_a_key_moved_still_move_it() {
+        // Every record served by the persistent backend is moved by some migration
+        let all = keys_moved_from(0);
+        for key in PERSISTENT_KEYS {
+            assert!(all.contains(key), "{} is moved by no migration", key);
+        }
+
+        // A store that ran migration 1 before these keys were persistent picks them up
+        for stored in 1..=3 {
+            let moved = keys_moved_from(stored);
+            assert!(moved.contains(&crate::models::app::APP_CONFIG_KEY_V1));
+            assert!(moved.contains(&crate::models::attachment::ATTACHMENTS_KEY_V1));
+            assert!(moved.contains(&crate::models::freshness::DOCUMENT_EXPIRY_KEY_V1));
+            assert!(!moved.contains(&crate::storage::conversation_storage::CONVERSATIONS_KEY));
+        }
+        assert!(keys_moved_from(current_version()).is_empty());
+    }
+}
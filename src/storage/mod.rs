@@ -1,4 +1,22 @@
+pub mod backend;
+pub mod backup;
+pub use backend::*;
+pub mod cache;
+pub use cache::*;
 pub mod conversation_storage;
 pub use conversation_storage::*;
+pub mod encryption;
+pub mod indexed_db;
+pub use indexed_db::*;
+pub mod integrity;
+pub mod long_messages;
+pub use long_messages::*;
+pub mod migrations;
+pub mod model_metadata;
+pub mod opfs;
+pub mod originals;
+pub mod persistent;
+pub use persistent::*;
+pub mod tab_sync;
 pub mod tag_helpers;
 pub use tag_helpers::*;
@@ -0,0 +1,137 @@
+//! Metadata of custom WebLLM models (artifact URLs, name, size), kept in the origin
+//! private file system next to the original document files. Browsers without it
+//! keep the list in localStorage, where it was stored before.
+
+use super::opfs::OpfsDirectory;
+use crate::models::app::{AppError, AppResult};
+use crate::models::webllm::{LLMModel, ModelCapability};
+use crate::utils::storage::StorageUtils;
+use serde::{Deserialize, Serialize};
+
+const FOLDER: &str = "model_metadata";
+const CUSTOM_MODELS_FILE: &str = "custom_models.json";
+/// localStorage key of the list, read once to move it into the folder
+const LEGACY_KEY: &str = "webllm_custom_models";
+
+/// A model built with MLC and served from the user's own URLs
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CustomModelEntry {
+    pub model: String,
+    pub model_lib: String,
+    pub model_id: String,
+    pub name: Option<String>,
+    pub family: Option<String>,
+    pub size_mb: Option<u32>,
+}
+
+impl CustomModelEntry {
+    /// The entry as a model of the picker
+    pub fn to_model(&self) -> LLMModel {
+        let name = self.name.clone().unwrap_or_else(|| self.model_id.clone());
+        let family = self.family.clone().unwrap_or_else(|| "custom".to_string());
+        let model = LLMModel::new(self.model_id.clone(), name, "WebLLM".to_string(), family)
+            .with_capabilities(vec![ModelCapability::TextGeneration]);
+        match self.size_mb {
+            Some(size_mb) => model.with_size(size_mb),
+            None => model,
+        }
+    }
+}
+
+/// Add `entry` to `entries` unless a model of that id is listed; returns whether it
+/// was added
+fn add_entry(entries: &mut Vec<CustomModelEntry>, entry: CustomModelEntry) -> bool {
+    if entries.iter().any(|e| e.model_id == entry.model_id) {
+        return false;
+    }
+    entries.push(entry);
+    true
+}
+
+fn parse(raw: &str) -> AppResult<Vec<CustomModelEntry>> {
+    if raw.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+    serde_json::from_str(raw)
+        .map_err(|e| AppError::storage(format!("The custom model list is damaged: {}", e)))
+}
+
+fn load_legacy() -> Vec<CustomModelEntry> {
+    StorageUtils::retrieve_local(LEGACY_KEY)
+        .ok()
+        .flatten()
+        .unwrap_or_default()
+}
+
+async fn save(entries: &[CustomModelEntry]) -> AppResult<()> {
+    if !OpfsDirectory::is_supported() {
+        return StorageUtils::store_local(LEGACY_KEY, &entries);
+    }
+    let raw = serde_json::to_string(entries)
+        .map_err(|e| AppError::storage(format!("Serialization failed: {}", e)))?;
+    OpfsDirectory::open(FOLDER)
+        .await?
+        .write_text(CUSTOM_MODELS_FILE, &raw)
+        .await
+}
+
+/// Saved custom models. A list still in localStorage is moved to the folder first.
+pub async fn custom_models() -> AppResult<Vec<CustomModelEntry>> {
+    if !OpfsDirectory::is_supported() {
+        return Ok(load_legacy());
+    }
+    let dir = OpfsDirectory::open(FOLDER).await?;
+    if let Some(raw) = dir.read_text(CUSTOM_MODELS_FILE).await? {
+        return parse(&raw);
+    }
+    let legacy = load_legacy();
+    if !legacy.is_empty() {
+        save(&legacy).await?;
+        StorageUtils::remove_local(LEGACY_KEY)?;
+    }
+    Ok(legacy)
+}
+
+/// Save `entry` with the custom models, unless a model of that id is saved already.
+/// Returns the saved list.
+pub async fn add_custom_model(entry: CustomModelEntry) -> AppResult<Vec<CustomModelEntry>> {
+    let mut entries = custom_models().await?;
+    if add_entry(&mut entries, entry) {
+        save(&entries).await?;
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(model_id: &str) -> CustomModelEntry {
+        CustomModelEntry {
+            model: format!("https://models.example/{}", model_id),
+            model_lib: format!("https://models.example/{}.wasm", model_id),
+            model_id: model_id.to_string(),
+            name: None,
+            family: None,
+            size_mb: Some(900),
+        }
+    }
+
+    #[test]
+    fn test_entries_are_listed_once_and_become_models() {
+        let mut entries = parse("").unwrap();
+        assert!(add_entry(&mut entries, entry("my-llama")));
+        assert!(!add_entry(&mut entries, entry("my-llama")));
+        assert_eq!(entries.len(), 1);
+
+        // The list stored by earlier versions reads back unchanged
+        let raw = serde_json::to_string(&entries).unwrap();
+        assert_eq!(parse(&raw).unwrap(), entries);
+        assert!(parse("{not json").is_err());
+
+        let model = entries[0].to_model();
+        assert_eq!(model.name, "my-llama");
+        assert_eq!(model.logo_slug, "custom");
+        assert_eq!(model.size_mb, Some(900));
+    }
+}
@@ -0,0 +1,137 @@
+//! Origin Private File System: a per-site folder in the browser, outside the quotas of
+//! localStorage and of single IndexedDB records. Suited to multi-megabyte blobs such
+//! as original document files; they are streamed to disk and read back as `File`s,
+//! which the browser keeps on disk until their bytes are read.
+
+use crate::models::app::{AppError, AppResult};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{
+    FileSystemDirectoryHandle, FileSystemFileHandle, FileSystemGetDirectoryOptions,
+    FileSystemGetFileOptions, FileSystemWritableFileStream,
+};
+
+/// A folder of the origin private file system
+#[derive(Clone)]
+pub struct OpfsDirectory {
+    handle: FileSystemDirectoryHandle,
+}
+
+impl OpfsDirectory {
+    /// Whether the browser offers the origin private file system
+    pub fn is_supported() -> bool {
+        web_sys::window().is_some_and(|w| {
+            let storage = w.navigator().storage();
+            js_sys::Reflect::has(&storage, &JsValue::from_str("getDirectory")).unwrap_or(false)
+        })
+    }
+
+    /// Open (and on first use create) the folder `name` at the root
+    pub async fn open(name: &str) -> AppResult<Self> {
+        if !Self::is_supported() {
+            return Err(AppError::storage(
+                "The origin private file system is not supported by this browser".to_string(),
+            ));
+        }
+        let storage = web_sys::window()
+            .ok_or_else(|| AppError::storage("Window not available".to_string()))?
+            .navigator()
+            .storage();
+        let root: FileSystemDirectoryHandle = JsFuture::from(storage.get_directory())
+            .await
+            .map_err(|e| js_error("Failed to open the private file system", e))?
+            .unchecked_into();
+        let options = FileSystemGetDirectoryOptions::new();
+        options.set_create(true);
+        let handle = JsFuture::from(root.get_directory_handle_with_options(name, &options))
+            .await
+            .map_err(|e| js_error(&format!("Failed to open folder {}", name), e))?
+            .unchecked_into();
+        Ok(Self { handle })
+    }
+
+    /// Stream `blob` into the file `name`, replacing it. The bytes go to disk as they
+    /// are read instead of being loaded into memory first.
+    pub async fn write_blob(&self, name: &str, blob: &web_sys::Blob) -> AppResult<()> {
+        let writable = self.writable(name).await?;
+        // `pipeTo` closes the file once the whole blob is written
+        JsFuture::from(blob.stream().pipe_to(&writable))
+            .await
+            .map_err(|e| js_error(&format!("Failed to write {}", name), e))?;
+        Ok(())
+    }
+
+    pub async fn write_text(&self, name: &str, text: &str) -> AppResult<()> {
+        let writable = self.writable(name).await?;
+        let written = writable
+            .write_with_str(text)
+            .map_err(|e| js_error(&format!("Failed to write {}", name), e))?;
+        JsFuture::from(written)
+            .await
+            .map_err(|e| js_error(&format!("Failed to write {}", name), e))?;
+        JsFuture::from(writable.close())
+            .await
+            .map_err(|e| js_error(&format!("Failed to write {}", name), e))?;
+        Ok(())
+    }
+
+    /// The file `name`, `None` when it does not exist. Its bytes stay on disk until read,
+    /// e.g. a slice at a time.
+    pub async fn file(&self, name: &str) -> AppResult<Option<web_sys::File>> {
+        let handle: FileSystemFileHandle =
+            match JsFuture::from(self.handle.get_file_handle(name)).await {
+                Ok(handle) => handle.unchecked_into(),
+                Err(e) if is_not_found(&e) => return Ok(None),
+                Err(e) => return Err(js_error(&format!("Failed to open {}", name), e)),
+            };
+        let file = JsFuture::from(handle.get_file())
+            .await
+            .map_err(|e| js_error(&format!("Failed to read {}", name), e))?;
+        Ok(Some(file.unchecked_into()))
+    }
+
+    pub async fn read_text(&self, name: &str) -> AppResult<Option<String>> {
+        let Some(file) = self.file(name).await? else {
+            return Ok(None);
+        };
+        let text = JsFuture::from(file.text())
+            .await
+            .map_err(|e| js_error(&format!("Failed to read {}", name), e))?;
+        Ok(text.as_string())
+    }
+
+    /// Delete the file `name`; a missing file is not an error
+    pub async fn remove(&self, name: &str) -> AppResult<()> {
+        match JsFuture::from(self.handle.remove_entry(name)).await {
+            Ok(_) => Ok(()),
+            Err(e) if is_not_found(&e) => Ok(()),
+            Err(e) => Err(js_error(&format!("Failed to delete {}", name), e)),
+        }
+    }
+
+    async fn writable(&self, name: &str) -> AppResult<FileSystemWritableFileStream> {
+        let options = FileSystemGetFileOptions::new();
+        options.set_create(true);
+        let handle: FileSystemFileHandle =
+            JsFuture::from(self.handle.get_file_handle_with_options(name, &options))
+                .await
+                .map_err(|e| js_error(&format!("Failed to create {}", name), e))?
+                .unchecked_into();
+        Ok(JsFuture::from(handle.create_writable())
+            .await
+            .map_err(|e| js_error(&format!("Failed to open {} for writing", name), e))?
+            .unchecked_into())
+    }
+}
+
+fn is_not_found(e: &JsValue) -> bool {
+    js_sys::Reflect::get(e, &JsValue::from_str("name"))
+        .ok()
+        .and_then(|n| n.as_string())
+        .is_some_and(|n| n == "NotFoundError")
+}
+
+fn js_error(context: &str, e: JsValue) -> AppError {
+    AppError::storage(format!("{}: {:?}", context, e))
+}
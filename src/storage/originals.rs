@@ -0,0 +1,299 @@
+//! Original files of uploaded documents, kept next to their extracted text so they can
+//! be previewed or downloaded again. The files live in the origin private file system,
+//! listed by an index file in the same folder.
+//!
+//! Files are written as they were uploaded, so none are kept while stored data is
+//! encrypted, and turning encryption on removes those kept before.
+
+use super::encryption;
+use super::opfs::OpfsDirectory;
+use crate::models::app::{AppError, AppResult};
+use gloo_timers::future::TimeoutFuture;
+use serde::{Deserialize, Serialize};
+use std::cell::Cell;
+use wasm_bindgen_futures::JsFuture;
+
+const FOLDER: &str = "document_originals";
+const INDEX_FILE: &str = "index.json";
+/// Bytes of a text file shown in its preview
+pub const PREVIEW_BYTES: f64 = 16.0 * 1024.0;
+const LOCK_POLL_MS: u32 = 25;
+
+thread_local! {
+    // Set while the index is being updated; files are read several at once
+    static UPDATING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// A kept original file
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct OriginalFile {
+    /// Name of the uploaded file, which is also the document's title
+    pub name: String,
+    /// Name of the stored file in the folder
+    pub stored_as: String,
+    pub mime: String,
+    pub bytes: f64,
+    pub stored_at: f64,
+}
+
+impl OriginalFile {
+    /// Whether the start of the file can be shown as text
+    pub fn is_text(&self) -> bool {
+        if self.mime.starts_with("text/")
+            || matches!(
+                self.mime.as_str(),
+                "application/json" | "application/x-yaml" | "application/yaml"
+            )
+        {
+            return true;
+        }
+        let name = self.name.to_lowercase();
+        [
+            ".md",
+            ".markdown",
+            ".txt",
+            ".csv",
+            ".tsv",
+            ".json",
+            ".yaml",
+            ".yml",
+            ".rs",
+            ".py",
+            ".ts",
+            ".tsx",
+            ".html",
+            ".htm",
+        ]
+        .iter()
+        .any(|ext| name.ends_with(ext))
+    }
+
+    /// Whether the browser shows the file itself, e.g. a PDF
+    pub fn is_viewable(&self) -> bool {
+        self.mime == "application/pdf"
+            || self.mime.starts_with("image/")
+            || self.name.to_lowercase().ends_with(".pdf")
+    }
+}
+
+/// Kept originals, most recent last
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct OriginalIndex {
+    pub files: Vec<OriginalFile>,
+    /// Counter naming the stored files
+    #[serde(default)]
+    next_id: u64,
+}
+
+impl OriginalIndex {
+    pub fn find(&self, name: &str) -> Option<&OriginalFile> {
+        self.files.iter().find(|f| f.name == name)
+    }
+
+    /// Name for the next stored file; file names of uploads may not be valid in the
+    /// folder, so stored files are numbered
+    pub fn allocate(&mut self) -> String {
+        self.next_id += 1;
+        format!("original-{}", self.next_id)
+    }
+
+    /// Record `file`, returning the entry it replaces (a file uploaded again under the
+    /// same name)
+    pub fn upsert(&mut self, file: OriginalFile) -> Option<OriginalFile> {
+        let replaced = self
+            .files
+            .iter()
+            .position(|f| f.name == file.name)
+            .map(|at| self.files.remove(at));
+        self.files.push(file);
+        replaced
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<OriginalFile> {
+        let at = self.files.iter().position(|f| f.name == name)?;
+        Some(self.files.remove(at))
+    }
+
+    pub fn total_bytes(&self) -> f64 {
+        self.files.iter().map(|f| f.bytes).sum()
+    }
+}
+
+/// Whether originals can be kept in this browser
+pub fn is_supported() -> bool {
+    OpfsDirectory::is_supported()
+}
+
+/// Whether uploads are kept now: never while stored data is encrypted, as the files
+/// could not be sealed
+pub fn can_retain() -> bool {
+    is_supported() && !encryption::is_enabled()
+}
+
+/// Held while the index is read, changed and written back
+struct IndexLock;
+
+impl IndexLock {
+    async fn acquire() -> Self {
+        while UPDATING.with(|u| u.replace(true)) {
+            TimeoutFuture::new(LOCK_POLL_MS).await;
+        }
+        IndexLock
+    }
+}
+
+impl Drop for IndexLock {
+    fn drop(&mut self) {
+        UPDATING.with(|u| u.set(false));
+    }
+}
+
+async fn folder() -> AppResult<OpfsDirectory> {
+    OpfsDirectory::open(FOLDER).await
+}
+
+async fn load_index(dir: &OpfsDirectory) -> AppResult<OriginalIndex> {
+    match dir.read_text(INDEX_FILE).await? {
+        Some(raw) => serde_json::from_str(&raw)
+            .map_err(|e| AppError::storage(format!("The originals index is damaged: {}", e))),
+        None => Ok(OriginalIndex::default()),
+    }
+}
+
+async fn save_index(dir: &OpfsDirectory, index: &OriginalIndex) -> AppResult<()> {
+    let raw = serde_json::to_string(index)
+        .map_err(|e| AppError::storage(format!("Serialization failed: {}", e)))?;
+    dir.write_text(INDEX_FILE, &raw).await
+}
+
+pub async fn list() -> AppResult<OriginalIndex> {
+    load_index(&folder().await?).await
+}
+
+/// Keep `file` as the original of the document of the same name, replacing an
+/// earlier upload of it
+pub async fn retain(file: &web_sys::File) -> AppResult<OriginalFile> {
+    if encryption::is_enabled() {
+        return Err(AppError::storage(
+            "Original files are not kept while stored data is encrypted".to_string(),
+        ));
+    }
+    let _lock = IndexLock::acquire().await;
+    let dir = folder().await?;
+    let mut index = load_index(&dir).await?;
+    let stored_as = index.allocate();
+    if let Err(e) = dir.write_blob(&stored_as, file).await {
+        // e.g. over the quota: drop what was written
+        let _ = dir.remove(&stored_as).await;
+        return Err(e);
+    }
+    let original = OriginalFile {
+        name: file.name(),
+        stored_as,
+        mime: file.type_(),
+        bytes: file.size(),
+        stored_at: js_sys::Date::now(),
+    };
+    let replaced = index.upsert(original.clone());
+    save_index(&dir, &index).await?;
+    if let Some(old) = replaced {
+        dir.remove(&old.stored_as).await?;
+    }
+    Ok(original)
+}
+
+/// The kept original named `name`, read from disk as it is used
+pub async fn open(name: &str) -> AppResult<Option<(OriginalFile, web_sys::File)>> {
+    let dir = folder().await?;
+    let index = load_index(&dir).await?;
+    let Some(original) = index.find(name).cloned() else {
+        return Ok(None);
+    };
+    Ok(dir
+        .file(&original.stored_as)
+        .await?
+        .map(|file| (original, file)))
+}
+
+/// The first `PREVIEW_BYTES` of a text original; only that slice is read
+pub async fn preview_text(file: &web_sys::File) -> AppResult<String> {
+    let slice = file
+        .slice_with_f64_and_f64(0.0, PREVIEW_BYTES.min(file.size()))
+        .map_err(|e| AppError::storage(format!("Failed to read the file: {:?}", e)))?;
+    let text = JsFuture::from(slice.text())
+        .await
+        .map_err(|e| AppError::storage(format!("Failed to read the file: {:?}", e)))?;
+    Ok(text.as_string().unwrap_or_default())
+}
+
+pub async fn remove(name: &str) -> AppResult<()> {
+    let _lock = IndexLock::acquire().await;
+    let dir = folder().await?;
+    let mut index = load_index(&dir).await?;
+    if let Some(original) = index.remove(name) {
+        save_index(&dir, &index).await?;
+        dir.remove(&original.stored_as).await?;
+    }
+    Ok(())
+}
+
+/// Remove every kept original; returns how many there were
+pub async fn clear() -> AppResult<usize> {
+    let _lock = IndexLock::acquire().await;
+    let dir = folder().await?;
+    let index = load_index(&dir).await?;
+    save_index(&dir, &OriginalIndex::default()).await?;
+    for original in &index.files {
+        dir.remove(&original.stored_as).await?;
+    }
+    Ok(index.files.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn original(name: &str, stored_as: &str, mime: &str, bytes: f64) -> OriginalFile {
+        OriginalFile {
+            name: name.to_string(),
+            stored_as: stored_as.to_string(),
+            mime: mime.to_string(),
+            bytes,
+            stored_at: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_index_replaces_uploads_of_the_same_name() {
+        let mut index = OriginalIndex::default();
+        let first = index.allocate();
+        assert_eq!(
+            index.upsert(original("report.pdf", &first, "application/pdf", 10.0)),
+            None
+        );
+        let notes = index.allocate();
+        index.upsert(original("notes.md", &notes, "", 5.0));
+        assert_ne!(first, notes);
+
+        let again = index.allocate();
+        let replaced = index.upsert(original("report.pdf", &again, "application/pdf", 12.0));
+        assert_eq!(replaced.map(|r| r.stored_as), Some(first));
+        assert_eq!(index.files.len(), 2);
+        assert_eq!(index.find("report.pdf").unwrap().stored_as, again);
+        assert_eq!(index.total_bytes(), 17.0);
+
+        // The counter survives a round trip, so stored names are never reused
+        let raw = serde_json::to_string(&index).unwrap();
+        let mut back: OriginalIndex = serde_json::from_str(&raw).unwrap();
+        assert_ne!(back.allocate(), again);
+
+        assert!(index.remove("notes.md").is_some());
+        assert!(index.remove("notes.md").is_none());
+
+        assert!(original("notes.md", "", "", 1.0).is_text());
+        assert!(original("data", "", "application/json", 1.0).is_text());
+        assert!(!original("report.pdf", "", "application/pdf", 1.0).is_text());
+        assert!(original("report.PDF", "", "", 1.0).is_viewable());
+        assert!(!original("book.epub", "", "application/epub+zip", 1.0).is_viewable());
+    }
+}
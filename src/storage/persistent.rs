@@ -0,0 +1,349 @@
+//! Large datasets (conversations, uploaded documents, the document index, the graph store
+//! and embedding vectors) live in IndexedDB instead of the ~5MB localStorage quota.
+//!
+//! The synchronous repositories keep their API: records are preloaded into an in-memory
+//! mirror by `init_persistent_storage` before the app mounts, reads are served from the
+//! mirror and writes update it and are persisted through the async backend in order.
+
+use super::backend::{LocalStorageBackend, StorageBackend};
+use super::cache::{preload_keys, CacheIndex, CACHE_INDEX_KEY};
+use super::indexed_db::IndexedDbBackend;
+use super::long_messages::{chunk_keys, LongMessageIndex, LONG_MESSAGE_INDEX_KEY};
+use crate::models::app::{AppError, AppResult};
+use crate::utils::storage::StorageUtils;
+use gloo_timers::future::TimeoutFuture;
+use serde::{Deserialize, Serialize};
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, VecDeque};
+use std::rc::Rc;
+
+/// Record keys served by the persistent backend (formerly localStorage keys). A key
+/// added here needs a new migration moving it, see `migrations`.
+pub const PERSISTENT_KEYS: &[&str] = &[
+    super::conversation_storage::CONVERSATIONS_KEY,
+    "knowledge_upload_buffer_v1",
+    crate::features::graphrag::pipeline::DOCUMENT_INDEX_KEY_V1,
+    crate::models::graph_store::GRAPH_STORE_KEY_V1,
+    crate::features::graphrag::embeddings::VECTOR_INDEX_KEY,
+    crate::pagerank_reranking::NODE_IMPORTANCE_KEY,
+    super::long_messages::LONG_MESSAGE_INDEX_KEY,
+    super::cache::CACHE_INDEX_KEY,
+    crate::features::graphrag::content_store::STOWED_CONTENT_KEY,
+    crate::models::app::APP_CONFIG_KEY_V1,
+    crate::models::attachment::ATTACHMENTS_KEY_V1,
+    crate::models::collection::COLLECTIONS_KEY_V1,
+    crate::models::freshness::DOCUMENT_EXPIRY_KEY_V1,
+];
+
+thread_local! {
+    static BACKEND: RefCell<Option<Rc<dyn StorageBackend>>> = const { RefCell::new(None) };
+    static MIRROR: RefCell<HashMap<String, String>> = RefCell::new(HashMap::new());
+    // Changes not yet in the backend, applied one at a time in the order they were
+    // made, so the latest value of a record always lands last
+    static QUEUE: RefCell<VecDeque<PendingWrite>> = const { RefCell::new(VecDeque::new()) };
+    // Set while a task applies the queue
+    static DRAINING: Cell<bool> = const { Cell::new(false) };
+    // Set while `rewrite_with` moves the records; changes made meanwhile wait here
+    static PAUSED: Cell<bool> = const { Cell::new(false) };
+}
+
+const PAUSE_POLL_MS: u32 = 10;
+
+/// A change waiting to reach the backend
+enum PendingWrite {
+    /// `announce` tells other tabs, for records they keep in their mirror
+    Put {
+        key: String,
+        raw: String,
+        announce: bool,
+    },
+    Delete {
+        key: String,
+    },
+}
+
+impl PendingWrite {
+    async fn apply(self, backend: &dyn StorageBackend) {
+        match self {
+            PendingWrite::Put { key, raw, announce } => match backend.put(&key, raw).await {
+                Ok(()) if announce => super::tab_sync::announce(&key),
+                Ok(()) => {}
+                Err(e) => log::error!("Failed to persist {}: {}", key, e),
+            },
+            PendingWrite::Delete { key } => match backend.delete(&key).await {
+                Ok(()) => super::tab_sync::announce(&key),
+                Err(e) => log::error!("Failed to delete {}: {}", key, e),
+            },
+        }
+    }
+}
+
+fn enqueue(write: PendingWrite) {
+    QUEUE.with(|q| q.borrow_mut().push_back(write));
+    drain();
+}
+
+/// Apply the queued changes in order, through the backend in use when each is applied
+fn drain() {
+    if PAUSED.with(Cell::get) || DRAINING.with(|d| d.replace(true)) {
+        return;
+    }
+    wasm_bindgen_futures::spawn_local(async {
+        while !PAUSED.with(Cell::get) {
+            let Some(backend) = BACKEND.with(|b| b.borrow().clone()) else {
+                break;
+            };
+            let Some(write) = QUEUE.with(|q| q.borrow_mut().pop_front()) else {
+                break;
+            };
+            write.apply(backend.as_ref()).await;
+        }
+        DRAINING.with(|d| d.set(false));
+    });
+}
+
+/// Synchronous access to the persistent datasets
+pub struct PersistentStore;
+
+impl PersistentStore {
+    /// Name of the active backend, `None` before initialization
+    pub fn backend_name() -> Option<&'static str> {
+        BACKEND.with(|b| b.borrow().as_ref().map(|b| b.name()))
+    }
+
+    pub fn read<T: for<'de> Deserialize<'de>>(key: &str) -> AppResult<Option<T>> {
+        if Self::backend_name().is_none() {
+            return StorageUtils::retrieve_local(key);
+        }
+        MIRROR.with(|m| match m.borrow().get(key) {
+            Some(raw) => serde_json::from_str(raw)
+                .map(Some)
+                .map_err(|e| AppError::storage(format!("Deserialization failed: {}", e))),
+            None => Ok(None),
+        })
+    }
+
+    pub fn write<T: Serialize>(key: &str, data: &T) -> AppResult<()> {
+        if Self::backend_name().is_none() {
+            return StorageUtils::store_local(key, data);
+        }
+        let raw = serde_json::to_string(data)
+            .map_err(|e| AppError::storage(format!("Serialization failed: {}", e)))?;
+        MIRROR.with(|m| m.borrow_mut().insert(key.to_string(), raw.clone()));
+        enqueue(PendingWrite::Put {
+            key: key.to_string(),
+            raw,
+            announce: true,
+        });
+        Ok(())
+    }
+
+    /// Read a record straight from the backend, bypassing the mirror. For records
+    /// written with `write_cold`, which are too large to keep in memory.
+    pub async fn read_cold<T: for<'de> Deserialize<'de>>(key: &str) -> AppResult<Option<T>> {
+        let Some(backend) = BACKEND.with(|b| b.borrow().clone()) else {
+            return StorageUtils::retrieve_local(key);
+        };
+        let raw = match MIRROR.with(|m| m.borrow().get(key).cloned()) {
+            Some(raw) => Some(raw),
+            None => backend.get(key).await?,
+        };
+        raw.map(|raw| serde_json::from_str(&raw))
+            .transpose()
+            .map_err(|e| AppError::storage(format!("Deserialization failed: {}", e)))
+    }
+
+    /// Persist a record without keeping it in the mirror; read it back with `read_cold`
+    pub fn write_cold<T: Serialize>(key: &str, data: &T) -> AppResult<()> {
+        if Self::backend_name().is_none() {
+            return StorageUtils::store_local(key, data);
+        }
+        let raw = serde_json::to_string(data)
+            .map_err(|e| AppError::storage(format!("Serialization failed: {}", e)))?;
+        enqueue(PendingWrite::Put {
+            key: key.to_string(),
+            raw,
+            announce: false,
+        });
+        Ok(())
+    }
+
+    pub fn remove(key: &str) -> AppResult<()> {
+        if Self::backend_name().is_none() {
+            return StorageUtils::remove_local(key);
+        }
+        MIRROR.with(|m| m.borrow_mut().remove(key));
+        enqueue(PendingWrite::Delete {
+            key: key.to_string(),
+        });
+        Ok(())
+    }
+
+    /// Whether `key` is a record served from the mirror
+    pub fn is_mirrored(key: &str) -> bool {
+        PERSISTENT_KEYS.contains(&key) || MIRROR.with(|m| m.borrow().contains_key(key))
+    }
+
+    /// Read a record back from the backend into the mirror, e.g. after another tab
+    /// wrote it
+    pub async fn reload(key: &str) -> AppResult<()> {
+        let Some(backend) = BACKEND.with(|b| b.borrow().clone()) else {
+            return Ok(());
+        };
+        match backend.get(key).await? {
+            Some(raw) => MIRROR.with(|m| m.borrow_mut().insert(key.to_string(), raw)),
+            None => MIRROR.with(|m| m.borrow_mut().remove(key)),
+        };
+        Ok(())
+    }
+}
+
+/// Open IndexedDB (falling back to localStorage), run the pending storage migrations,
+/// preload the mirror and quarantine records that do not parse. Must complete before the app reads any dataset.
+pub async fn init_persistent_storage() {
+    init_persistent_storage_with(open_backend().await).await;
+}
+
+/// IndexedDB, or localStorage where it is unavailable
+pub async fn open_backend() -> Rc<dyn StorageBackend> {
+    match IndexedDbBackend::open().await {
+        Ok(db) => Rc::new(db),
+        Err(e) => {
+            log::warn!("IndexedDB unavailable, keeping data in localStorage: {}", e);
+            Rc::new(LocalStorageBackend)
+        }
+    }
+}
+
+/// Same as `init_persistent_storage` with a backend chosen by the caller, e.g. an
+/// embedding app that keeps the chatbot's datasets in its own store
+pub async fn init_persistent_storage_with(backend: Rc<dyn StorageBackend>) {
+    super::migrations::run(backend.as_ref()).await;
+
+    for key in PERSISTENT_KEYS {
+        match backend.get(key).await {
+            Ok(Some(raw)) => {
+                MIRROR.with(|m| m.borrow_mut().insert(key.to_string(), raw));
+            }
+            Ok(None) => {}
+            Err(e) => log::error!("Failed to load {}: {}", key, e),
+        }
+    }
+    // Records that no longer parse are set aside before any feature reads them
+    let quarantined = super::integrity::quarantine_corrupt(backend.as_ref(), |key| {
+        MIRROR.with(|m| m.borrow().get(key).cloned())
+    })
+    .await;
+    for key in quarantined {
+        MIRROR.with(|m| m.borrow_mut().remove(key));
+    }
+    // Chunk records of long messages and cache entries are listed by their indexes
+    // rather than by fixed keys
+    let long_messages = MIRROR
+        .with(|m| m.borrow().get(LONG_MESSAGE_INDEX_KEY).cloned())
+        .and_then(|raw| serde_json::from_str::<LongMessageIndex>(&raw).ok())
+        .unwrap_or_default();
+    let cache_index = MIRROR
+        .with(|m| m.borrow().get(CACHE_INDEX_KEY).cloned())
+        .and_then(|raw| serde_json::from_str::<CacheIndex>(&raw).ok())
+        .unwrap_or_default();
+    let listed = chunk_keys(&long_messages)
+        .into_iter()
+        .chain(preload_keys(&cache_index, js_sys::Date::now()));
+    for key in listed {
+        match backend.get(&key).await {
+            Ok(Some(raw)) => {
+                MIRROR.with(|m| m.borrow_mut().insert(key, raw));
+            }
+            Ok(None) => {}
+            Err(e) => log::error!("Failed to load {}: {}", key, e),
+        }
+    }
+    log::info!("Persistent storage ready ({})", backend.name());
+    BACKEND.with(|b| *b.borrow_mut() = Some(backend));
+    super::tab_sync::start();
+    match super::cache::TieredCache::purge_expired() {
+        Ok(0) => {}
+        Ok(n) => log::info!("Dropped {} expired cache entries", n),
+        Err(e) => log::warn!("Failed to purge the cache: {}", e),
+    }
+}
+
+/// Held while the records move to another backend. Changes made meanwhile stay
+/// queued and are applied, through the backend then in use, once it is dropped.
+struct PausedWrites;
+
+impl PausedWrites {
+    async fn acquire() -> Self {
+        while PAUSED.with(|p| p.replace(true)) {
+            TimeoutFuture::new(PAUSE_POLL_MS).await;
+        }
+        // Let the write being applied finish
+        while DRAINING.with(Cell::get) {
+            TimeoutFuture::new(PAUSE_POLL_MS).await;
+        }
+        PausedWrites
+    }
+}
+
+impl Drop for PausedWrites {
+    fn drop(&mut self) {
+        PAUSED.with(|p| p.set(false));
+        drain();
+    }
+}
+
+/// Rewrite every stored record through `next` and switch to it, e.g. when storage
+/// encryption is turned on or its passphrase changes. `commit` runs once all records
+/// are rewritten; when a write or `commit` fails, the records already rewritten are
+/// put back through the current backend. Changes made meanwhile are held back and
+/// applied afterwards, through `next` once switched. Returns how many records were
+/// rewritten.
+pub async fn rewrite_with(
+    next: Rc<dyn StorageBackend>,
+    commit: impl FnOnce() -> AppResult<()>,
+) -> AppResult<usize> {
+    let current = BACKEND
+        .with(|b| b.borrow().clone())
+        .ok_or_else(|| AppError::storage("Storage is not initialized".to_string()))?;
+    // Writes made during the rewrite go to `next` after the switch, over the copies
+    let _paused = PausedWrites::acquire().await;
+    let mut records: Vec<(String, String)> = MIRROR.with(|m| {
+        m.borrow()
+            .iter()
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect()
+    });
+    // Records kept out of the mirror are read back from the backend
+    let cold = crate::features::graphrag::content_store::record_keys()
+        .into_iter()
+        .chain(super::integrity::persistent_corrupt_keys());
+    for key in cold {
+        if MIRROR.with(|m| m.borrow().contains_key(&key)) {
+            continue;
+        }
+        if let Some(raw) = current.get(&key).await? {
+            records.push((key, raw));
+        }
+    }
+
+    let mut written = 0;
+    let mut outcome = Ok(());
+    for (key, raw) in &records {
+        if let Err(e) = next.put(key, raw.clone()).await {
+            outcome = Err(e);
+            break;
+        }
+        written += 1;
+    }
+    if let Err(e) = outcome.and_then(|_| commit()) {
+        for (key, raw) in records.iter().take(written) {
+            if let Err(e) = current.put(key, raw.clone()).await {
+                log::error!("Failed to restore {}: {}", key, e);
+            }
+        }
+        return Err(e);
+    }
+    BACKEND.with(|b| *b.borrow_mut() = Some(next));
+    Ok(records.len())
+}
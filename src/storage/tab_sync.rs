@@ -0,0 +1,184 @@
+//! Keeps several tabs of the app in step. A tab announces each persistent record it
+//! wrote on a `BroadcastChannel`; the other tabs reload the record into their mirror,
+//! so their next write does not overwrite it, then tell the state showing it to
+//! reload. localStorage records (CRM, tasks, settings) need no announcement: the
+//! browser fires a `storage` event in the other tabs.
+
+use super::persistent::PersistentStore;
+use serde::{Deserialize, Serialize};
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeSet;
+use std::rc::Rc;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+
+const CHANNEL_NAME: &str = "knowledge-chatbot-sync";
+
+/// What a tab posts after a write
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct SyncMessage {
+    changed: Vec<String>,
+}
+
+struct Listener {
+    id: u64,
+    keys: &'static [&'static str],
+    callback: Rc<dyn Fn()>,
+}
+
+thread_local! {
+    static CHANNEL: RefCell<Option<web_sys::BroadcastChannel>> = const { RefCell::new(None) };
+    static LISTENERS: RefCell<Vec<Listener>> = const { RefCell::new(Vec::new()) };
+    static NEXT_LISTENER: Cell<u64> = const { Cell::new(0) };
+    // Keys announced by other tabs and not reloaded yet
+    static PENDING: RefCell<BTreeSet<String>> = const { RefCell::new(BTreeSet::new()) };
+    static RELOADING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Listen to the other tabs; called once the persistent storage is open
+pub fn start() {
+    if CHANNEL.with(|c| c.borrow().is_some()) {
+        return;
+    }
+    let channel = match web_sys::BroadcastChannel::new(CHANNEL_NAME) {
+        Ok(channel) => channel,
+        Err(e) => {
+            log::warn!("Tabs will not be kept in sync: {:?}", e);
+            return;
+        }
+    };
+    let on_message = Closure::wrap(Box::new(|ev: web_sys::MessageEvent| {
+        if let Some(keys) = ev.data().as_string().and_then(|raw| parse_message(&raw)) {
+            received(keys);
+        }
+    }) as Box<dyn FnMut(_)>);
+    channel.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+    on_message.forget(); // Lives as long as the app
+    CHANNEL.with(|c| *c.borrow_mut() = Some(channel));
+
+    let on_storage = Closure::wrap(Box::new(|ev: web_sys::StorageEvent| {
+        // `key` is missing when the storage was cleared
+        let Some(key) = ev.key() else {
+            return;
+        };
+        // Persistent records live in localStorage where IndexedDB is unavailable
+        if PersistentStore::is_mirrored(&key) {
+            received(vec![key]);
+        } else {
+            notify(&BTreeSet::from([key]));
+        }
+    }) as Box<dyn FnMut(_)>);
+    if let Some(window) = web_sys::window() {
+        let _ =
+            window.add_event_listener_with_callback("storage", on_storage.as_ref().unchecked_ref());
+    }
+    on_storage.forget();
+}
+
+/// Tell the other tabs that the persistent record `key` was written or removed
+pub fn announce(key: &str) {
+    CHANNEL.with(|c| {
+        let Some(channel) = c.borrow().as_ref().cloned() else {
+            return;
+        };
+        let message = SyncMessage {
+            changed: vec![key.to_string()],
+        };
+        let raw = serde_json::to_string(&message).unwrap_or_default();
+        if let Err(e) = channel.post_message(&JsValue::from_str(&raw)) {
+            log::warn!("Failed to announce {} to other tabs: {:?}", key, e);
+        }
+    });
+}
+
+/// Run `callback` whenever another tab changes one of `keys`, until the reactive owner
+/// calling this is cleaned up
+pub fn watch(keys: &'static [&'static str], callback: impl Fn() + 'static) {
+    let id = NEXT_LISTENER.with(|n| {
+        let id = n.get();
+        n.set(id + 1);
+        id
+    });
+    LISTENERS.with(|l| {
+        l.borrow_mut().push(Listener {
+            id,
+            keys,
+            callback: Rc::new(callback),
+        })
+    });
+    leptos::prelude::on_cleanup(move || {
+        LISTENERS.with(|l| l.borrow_mut().retain(|listener| listener.id != id))
+    });
+}
+
+/// Reload announced records one batch at a time, so a burst of writes from another
+/// tab is read back in order and the state reloads once per batch
+fn received(keys: Vec<String>) {
+    PENDING.with(|p| p.borrow_mut().extend(keys));
+    if RELOADING.with(|r| r.replace(true)) {
+        return;
+    }
+    wasm_bindgen_futures::spawn_local(async {
+        loop {
+            let batch = PENDING.with(|p| std::mem::take(&mut *p.borrow_mut()));
+            if batch.is_empty() {
+                break;
+            }
+            for key in &batch {
+                if let Err(e) = PersistentStore::reload(key).await {
+                    log::warn!("Failed to reload {} changed by another tab: {}", key, e);
+                }
+            }
+            notify(&batch);
+        }
+        RELOADING.with(|r| r.set(false));
+    });
+}
+
+fn notify(changed: &BTreeSet<String>) {
+    // Callbacks may add or drop listeners, so they run outside the borrow
+    let callbacks: Vec<Rc<dyn Fn()>> = LISTENERS.with(|l| {
+        l.borrow()
+            .iter()
+            .filter(|listener| watches(listener.keys, changed))
+            .map(|listener| listener.callback.clone())
+            .collect()
+    });
+    for callback in callbacks {
+        callback();
+    }
+}
+
+fn watches(keys: &[&str], changed: &BTreeSet<String>) -> bool {
+    keys.iter().any(|key| changed.contains(*key))
+}
+
+fn parse_message(raw: &str) -> Option<Vec<String>> {
+    serde_json::from_str::<SyncMessage>(raw)
+        .ok()
+        .map(|m| m.changed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_messages_and_matching() {
+        let message = SyncMessage {
+            changed: vec!["conversations_v1".to_string()],
+        };
+        let raw = serde_json::to_string(&message).unwrap();
+        assert_eq!(
+            parse_message(&raw),
+            Some(vec!["conversations_v1".to_string()])
+        );
+        assert_eq!(parse_message("not json"), None);
+        assert_eq!(parse_message("{\"other\": 1}"), None);
+
+        let changed = BTreeSet::from(["crm_leads".to_string(), "tasks_v1".to_string()]);
+        assert!(watches(&["crm_customers", "crm_leads"], &changed));
+        assert!(!watches(&["conversations_v1"], &changed));
+        assert!(!watches(&[], &changed));
+    }
+}
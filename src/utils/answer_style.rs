@@ -0,0 +1,166 @@
+//! Learning how a user wants to be answered from what they ask for. A request such as
+//! "shorter please" is handled by the model in that turn; once the same cue has been
+//! asked for `LEARN_AFTER` times it becomes a preference of the conversation and is
+//! added to every prompt.
+
+use crate::models::{AnswerStyle, StyleCue};
+
+/// Requests for the same cue before it becomes a preference
+pub const LEARN_AFTER: u32 = 2;
+
+// Phrases asking for each cue, matched on the lowercased message. Prose is listed
+// before bullets so "no bullet points" is not read as asking for them.
+const CUE_PHRASES: [(StyleCue, &[&str]); 6] = [
+    (
+        StyleCue::Shorter,
+        &[
+            "shorter",
+            "more concise",
+            "too long",
+            "be brief",
+            "briefly",
+            "keep it short",
+            "less verbose",
+            "tl;dr",
+        ],
+    ),
+    (
+        StyleCue::Longer,
+        &[
+            "more detail",
+            "longer answer",
+            "make it longer",
+            "too short",
+            "elaborate",
+            "in more depth",
+            "go deeper",
+        ],
+    ),
+    (
+        StyleCue::Prose,
+        &[
+            "no bullet",
+            "without bullet",
+            "stop using bullet",
+            "in prose",
+            "full sentences",
+            "paragraph form",
+        ],
+    ),
+    (
+        StyleCue::Bullets,
+        &["bullet point", "bullet list", "as a list", "in bullets"],
+    ),
+    (
+        StyleCue::Simpler,
+        &[
+            "simpler",
+            "plain language",
+            "plain english",
+            "less technical",
+            "too technical",
+            "like i'm five",
+            "eli5",
+        ],
+    ),
+    (
+        StyleCue::Technical,
+        &[
+            "more technical",
+            "technical details",
+            "too basic",
+            "too simple",
+        ],
+    ),
+];
+
+/// Cues `text` asks for
+pub fn detect(text: &str) -> Vec<StyleCue> {
+    let text = text.to_lowercase();
+    let mut cues: Vec<StyleCue> = Vec::new();
+    for (cue, phrases) in CUE_PHRASES {
+        if cues.contains(&cue.opposite()) {
+            continue;
+        }
+        if phrases.iter().any(|p| text.contains(p)) {
+            cues.push(cue);
+        }
+    }
+    cues
+}
+
+/// Count the cues of a user message; returns the ones that became preferences.
+/// Asking for the opposite of a pending cue starts its count over.
+pub fn observe(style: &mut AnswerStyle, text: &str) -> Vec<StyleCue> {
+    let mut learned = Vec::new();
+    for cue in detect(text) {
+        if style.has(cue) {
+            continue;
+        }
+        style.pending.retain(|(c, _)| *c != cue.opposite());
+        let count = match style.pending.iter_mut().find(|(c, _)| *c == cue) {
+            Some((_, n)) => {
+                *n += 1;
+                *n
+            }
+            None => {
+                style.pending.push((cue, 1));
+                1
+            }
+        };
+        if count >= LEARN_AFTER {
+            style.prefer(cue);
+            learned.push(cue);
+        }
+    }
+    learned
+}
+
+/// System message carrying the conversation's answer preferences
+pub fn instruction(style: &AnswerStyle) -> Option<String> {
+    if style.is_empty() {
+        return None;
+    }
+    let mut parts: Vec<&str> = style.preferences.iter().map(|c| c.instruction()).collect();
+    let notes = style.notes.trim();
+    if !notes.is_empty() {
+        parts.push(notes);
+    }
+    Some(format!(
+        "The user's preferences for this conversation: {}",
+        parts.join(" ")
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_repeated_requests_become_preferences() {
+        assert_eq!(detect("No bullet points please"), vec![StyleCue::Prose]);
+        assert!(detect("What is the refund policy?").is_empty());
+
+        let mut style = AnswerStyle::default();
+        assert!(observe(&mut style, "Can you make that shorter?").is_empty());
+        assert_eq!(instruction(&style), None);
+        assert_eq!(
+            observe(&mut style, "Too long, be brief."),
+            vec![StyleCue::Shorter]
+        );
+        assert!(style.pending.is_empty());
+
+        // The opposite request resets the count and later replaces the preference
+        observe(&mut style, "Give me more detail");
+        observe(&mut style, "Elaborate on the second point");
+        assert_eq!(style.preferences, vec![StyleCue::Longer]);
+
+        style.notes = "Quote prices in euros.".to_string();
+        assert_eq!(
+            instruction(&style).as_deref(),
+            Some(
+                "The user's preferences for this conversation: Give thorough, detailed answers. Quote prices in euros."
+            )
+        );
+    }
+}
@@ -0,0 +1,53 @@
+//! Brainstorm mode: several replies to one prompt, sampled one after another with
+//! different temperatures and seeds so they differ, for the user to pick from.
+
+use crate::models::generation::SamplingParams;
+
+/// Replies generated per prompt in brainstorm mode
+pub const BRAINSTORM_REPLIES: usize = 4;
+// Temperature added or removed per step away from the configured one
+const TEMPERATURE_STEP: f32 = 0.3;
+const MIN_TEMPERATURE: f32 = 0.1;
+const MAX_TEMPERATURE: f32 = 1.6;
+
+/// Sampling of the replies after the first, which uses `base` as configured. Their
+/// temperatures alternate above and below it, further out each time, and each gets
+/// its own seed derived from `seed`.
+pub fn variations(base: &SamplingParams, extra: usize, seed: u32) -> Vec<SamplingParams> {
+    (0..extra)
+        .map(|i| {
+            let distance = (i / 2 + 1) as f32 * TEMPERATURE_STEP;
+            let offset = if i % 2 == 0 { distance } else { -distance };
+            SamplingParams {
+                temperature: (base.temperature + offset).clamp(MIN_TEMPERATURE, MAX_TEMPERATURE),
+                seed: Some(u64::from(seed.wrapping_add(i as u32 * 7919))),
+                ..base.clone()
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_variations_spread_temperature_and_seed() {
+        let base = SamplingParams {
+            temperature: 0.2,
+            ..SamplingParams::default()
+        };
+        let extra = variations(&base, BRAINSTORM_REPLIES - 1, 42);
+        let temperatures: Vec<f32> = extra.iter().map(|s| s.temperature).collect();
+        assert_eq!(temperatures.len(), 3);
+        assert!((temperatures[0] - 0.5).abs() < 1e-6);
+        // Never below the floor, even when the configured temperature is low
+        assert_eq!(temperatures[1], MIN_TEMPERATURE);
+        assert!((temperatures[2] - 0.8).abs() < 1e-6);
+
+        let mut seeds: Vec<u64> = extra.iter().filter_map(|s| s.seed).collect();
+        seeds.dedup();
+        assert_eq!(seeds.len(), 3);
+        assert!(extra.iter().all(|s| s.max_tokens == base.max_tokens));
+    }
+}
@@ -0,0 +1,303 @@
+//! What this browser can run. Probed once at startup: WebGPU (and whether the adapter
+//! has 16-bit float shaders), WebAssembly SIMD and the reported device memory. Models
+//! that cannot run here are hidden before anyone tries to load them.
+
+use crate::models::webllm::LLMModel;
+use js_sys::{Function, Promise, Reflect, Uint8Array};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+
+/// Smallest module using a SIMD instruction; it only validates where SIMD is supported
+const SIMD_PROBE: [u8; 31] = [
+    0, 97, 115, 109, 1, 0, 0, 0, 1, 5, 1, 96, 0, 1, 123, 3, 2, 1, 0, 10, 10, 1, 8, 0, 65, 0, 253,
+    15, 253, 98, 11,
+];
+/// `navigator.deviceMemory` never reports more than this, whatever the device has
+const REPORTED_MEMORY_CAP_MB: u32 = 8 * 1024;
+/// Fallback models suggested for one that cannot run
+const MAX_FALLBACKS: usize = 3;
+
+/// WebGPU as found by the probe
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum GpuSupport {
+    /// The browser has no WebGPU
+    Unsupported,
+    /// WebGPU exists but no adapter was granted, e.g. a blocklisted driver
+    NoAdapter,
+    Available {
+        /// Whether shaders can use 16-bit floats, needed by `q4f16` models
+        shader_f16: bool,
+    },
+}
+
+/// Capability report of this browser
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DeviceCapabilities {
+    pub gpu: GpuSupport,
+    /// WebAssembly SIMD, which makes in-browser reranking several times faster
+    pub wasm_simd: bool,
+    /// Device memory in MB as reported by the browser, when it reports it
+    pub device_memory_mb: Option<u32>,
+}
+
+/// Why a model cannot run on this device
+#[derive(Clone, Debug, PartialEq)]
+pub enum Incompatibility {
+    NoWebGpu,
+    NeedsShaderF16,
+    TooLarge { needed_mb: u32, budget_mb: u32 },
+}
+
+impl std::fmt::Display for Incompatibility {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Incompatibility::NoWebGpu => write!(f, "WebGPU is not available in this browser"),
+            Incompatibility::NeedsShaderF16 => {
+                write!(f, "the GPU does not support 16-bit float shaders")
+            }
+            Incompatibility::TooLarge {
+                needed_mb,
+                budget_mb,
+            } => write!(
+                f,
+                "it needs about {} MB of memory and the device has {} MB",
+                needed_mb, budget_mb
+            ),
+        }
+    }
+}
+
+/// Whether an MLC model id names 16-bit float weights, e.g. `...-q4f16_1-MLC`
+pub fn needs_shader_f16(model_id: &str) -> bool {
+    model_id.to_lowercase().contains("f16_")
+}
+
+impl DeviceCapabilities {
+    pub fn has_webgpu(&self) -> bool {
+        matches!(self.gpu, GpuSupport::Available { .. })
+    }
+
+    /// Memory a model may use, unknown when the browser does not say or reports its cap
+    pub fn memory_budget_mb(&self) -> Option<u32> {
+        self.device_memory_mb
+            .filter(|mb| *mb < REPORTED_MEMORY_CAP_MB)
+    }
+
+    /// Whether `model` can run here; like `WebLLMUtils::is_model_compatible`, a model
+    /// needs twice its size in memory
+    pub fn check(&self, model: &LLMModel) -> Result<(), Incompatibility> {
+        let GpuSupport::Available { shader_f16 } = self.gpu else {
+            return Err(Incompatibility::NoWebGpu);
+        };
+        if !shader_f16 && needs_shader_f16(&model.id) {
+            return Err(Incompatibility::NeedsShaderF16);
+        }
+        if let (Some(size_mb), Some(budget_mb)) = (model.size_mb, self.memory_budget_mb()) {
+            let needed_mb = size_mb.saturating_mul(2);
+            if needed_mb > budget_mb {
+                return Err(Incompatibility::TooLarge {
+                    needed_mb,
+                    budget_mb,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// The models of `models` that can run here, in their order
+    pub fn compatible(&self, models: &[LLMModel]) -> Vec<LLMModel> {
+        models
+            .iter()
+            .filter(|m| self.check(m).is_ok())
+            .cloned()
+            .collect()
+    }
+
+    /// Models to use instead of `wanted`: the same family first, then the largest that
+    /// are no larger than it
+    pub fn fallbacks(&self, wanted: &LLMModel, models: &[LLMModel]) -> Vec<LLMModel> {
+        let wanted_mb = wanted.size_mb.unwrap_or(u32::MAX);
+        let mut candidates: Vec<LLMModel> = self
+            .compatible(models)
+            .into_iter()
+            .filter(|m| m.id != wanted.id)
+            .collect();
+        candidates.sort_by_key(|m| {
+            let size_mb = m.size_mb.unwrap_or(0);
+            (
+                m.logo_slug != wanted.logo_slug,
+                size_mb > wanted_mb,
+                std::cmp::Reverse(size_mb),
+            )
+        });
+        candidates.truncate(MAX_FALLBACKS);
+        candidates
+    }
+
+    /// Rows of the capability report, label and finding
+    pub fn report(&self) -> Vec<(&'static str, String)> {
+        let gpu = match self.gpu {
+            GpuSupport::Available { shader_f16: true } => "Available, with 16-bit floats",
+            GpuSupport::Available { shader_f16: false } => "Available, without 16-bit floats",
+            GpuSupport::NoAdapter => "Blocked for this GPU",
+            GpuSupport::Unsupported => "Not supported",
+        };
+        let memory = match self.device_memory_mb {
+            Some(mb) if mb >= REPORTED_MEMORY_CAP_MB => "8 GB or more".to_string(),
+            Some(mb) => format!("{:.1} GB", mb as f64 / 1024.0),
+            None => "Not reported".to_string(),
+        };
+        vec![
+            ("WebGPU", gpu.to_string()),
+            (
+                "WebAssembly SIMD",
+                if self.wasm_simd { "Yes" } else { "No" }.to_string(),
+            ),
+            ("Device memory", memory),
+        ]
+    }
+
+    /// One-line summary for the status bar
+    pub fn summary(&self) -> &'static str {
+        match self.gpu {
+            GpuSupport::Available { shader_f16: true } => "WebGPU",
+            GpuSupport::Available { shader_f16: false } => "WebGPU (no f16)",
+            GpuSupport::NoAdapter => "WebGPU blocked",
+            GpuSupport::Unsupported => "No WebGPU",
+        }
+    }
+}
+
+/// Probe the browser; resolves quickly, requesting a GPU adapter is the only wait
+pub async fn probe() -> DeviceCapabilities {
+    DeviceCapabilities {
+        gpu: probe_gpu().await,
+        wasm_simd: has_wasm_simd(),
+        device_memory_mb: device_memory_mb(),
+    }
+}
+
+fn method(target: &JsValue, name: &str) -> Option<Function> {
+    Reflect::get(target, &JsValue::from_str(name))
+        .ok()
+        .and_then(|f| f.dyn_into::<Function>().ok())
+}
+
+async fn probe_gpu() -> GpuSupport {
+    let Some(window) = web_sys::window() else {
+        return GpuSupport::Unsupported;
+    };
+    let gpu =
+        Reflect::get(&window.navigator(), &JsValue::from_str("gpu")).unwrap_or(JsValue::UNDEFINED);
+    let Some(request_adapter) = method(&gpu, "requestAdapter") else {
+        return GpuSupport::Unsupported;
+    };
+    let adapter = match request_adapter.call0(&gpu) {
+        Ok(pending) => JsFuture::from(Promise::resolve(&pending)).await,
+        Err(e) => Err(e),
+    };
+    let adapter = match adapter {
+        Ok(adapter) if !adapter.is_null() && !adapter.is_undefined() => adapter,
+        Ok(_) => return GpuSupport::NoAdapter,
+        Err(e) => {
+            log::warn!("No WebGPU adapter: {:?}", e);
+            return GpuSupport::NoAdapter;
+        }
+    };
+    // `adapter.features` is set-like but not a `Set`
+    let features = Reflect::get(&adapter, &JsValue::from_str("features")).unwrap_or_default();
+    let shader_f16 = method(&features, "has")
+        .and_then(|has| has.call1(&features, &JsValue::from_str("shader-f16")).ok())
+        .is_some_and(|found| found.is_truthy());
+    GpuSupport::Available { shader_f16 }
+}
+
+fn has_wasm_simd() -> bool {
+    let bytes = Uint8Array::from(&SIMD_PROBE[..]);
+    js_sys::WebAssembly::validate(&bytes.into()).unwrap_or(false)
+}
+
+fn device_memory_mb() -> Option<u32> {
+    let navigator = web_sys::window()?.navigator();
+    let gb = Reflect::get(&navigator, &JsValue::from_str("deviceMemory"))
+        .ok()?
+        .as_f64()?;
+    Some((gb * 1024.0) as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn model(id: &str, family: &str, size_mb: u32) -> LLMModel {
+        LLMModel::new(
+            id.to_string(),
+            id.to_string(),
+            "WebLLM".to_string(),
+            family.to_string(),
+        )
+        .with_size(size_mb)
+    }
+
+    fn device(gpu: GpuSupport, memory_mb: Option<u32>) -> DeviceCapabilities {
+        DeviceCapabilities {
+            gpu,
+            wasm_simd: true,
+            device_memory_mb: memory_mb,
+        }
+    }
+
+    #[test]
+    fn test_incompatible_models_are_hidden_with_fallbacks() {
+        let models = vec![
+            model("Llama-3.1-8B-Instruct-q4f16_1-MLC", "llama3", 5000),
+            model("Llama-3.1-8B-Instruct-q4f32_1-MLC", "llama3", 6000),
+            model("Llama-3.2-1B-Instruct-q4f32_1-MLC", "llama3", 900),
+            model("Qwen2.5-0.5B-Instruct-q4f32_1-MLC", "qwen2.5", 500),
+            model("Qwen2.5-1.5B-Instruct-q4f32_1-MLC", "qwen2.5", 1500),
+        ];
+
+        let no_gpu = device(GpuSupport::Unsupported, None);
+        assert_eq!(no_gpu.check(&models[0]), Err(Incompatibility::NoWebGpu));
+        assert!(no_gpu.compatible(&models).is_empty());
+
+        // 4 GB of memory and no f16: the 8B models are out, one for each reason
+        let small = device(GpuSupport::Available { shader_f16: false }, Some(4096));
+        assert_eq!(
+            small.check(&models[0]),
+            Err(Incompatibility::NeedsShaderF16)
+        );
+        assert_eq!(
+            small.check(&models[1]),
+            Err(Incompatibility::TooLarge {
+                needed_mb: 12000,
+                budget_mb: 4096
+            })
+        );
+        assert_eq!(small.compatible(&models).len(), 3);
+        let ids: Vec<String> = small
+            .fallbacks(&models[0], &models)
+            .into_iter()
+            .map(|m| m.id)
+            .collect();
+        assert_eq!(
+            ids,
+            [
+                "Llama-3.2-1B-Instruct-q4f32_1-MLC",
+                "Qwen2.5-1.5B-Instruct-q4f32_1-MLC",
+                "Qwen2.5-0.5B-Instruct-q4f32_1-MLC",
+            ]
+        );
+
+        // Browsers report at most 8 GB, so that says nothing about large models
+        let capped = device(GpuSupport::Available { shader_f16: true }, Some(8192));
+        assert_eq!(capped.memory_budget_mb(), None);
+        assert_eq!(capped.compatible(&models).len(), models.len());
+
+        assert_eq!(capped.report()[2].1, "8 GB or more");
+
+        assert!(needs_shader_f16("Phi-3.5-mini-instruct-q4f16_1-MLC"));
+        assert!(!needs_shader_f16("Llama-3.2-1B-Instruct-q4f32_1-MLC"));
+    }
+}
@@ -0,0 +1,216 @@
+//! Source files indexed definition by definition. Each top-level function, type or
+//! impl becomes a chunk, with its leading doc comments and attributes; the names it
+//! defines are kept so retrieval can match "where is X implemented" on the symbol.
+
+use regex::Regex;
+
+/// Largest chunk before a definition is split along the definitions inside it
+const MAX_CODE_CHUNK_CHARS: usize = 2_000;
+
+const RUST_DEFINITION: &str = r#"^(\s*)(?:pub(?:\([^)]*\))?\s+)?(?:(?:async|const|unsafe|extern\s+"[^"]*")\s+)*(?:fn|struct|enum|trait|type|mod|union|macro_rules!)\s*([A-Za-z_][A-Za-z0-9_]*)"#;
+const RUST_IMPL: &str = r"^(\s*)(?:unsafe\s+)?impl(?:<[^>]*>)?\s+(?:[A-Za-z_][\w:]*(?:<[^>]*>)?\s+for\s+)?(?:[A-Za-z_]\w*::)*([A-Za-z_]\w*)";
+const PYTHON_DEFINITION: &str = r"^(\s*)(?:async\s+)?(?:def|class)\s+([A-Za-z_]\w*)";
+const TS_DEFINITION: &str = r"^(\s*)(?:export\s+)?(?:default\s+)?(?:declare\s+)?(?:abstract\s+)?(?:async\s+)?(?:function\*?|class|interface|type|enum|namespace)\s+([A-Za-z_$][\w$]*)";
+/// Top-level `const`/`let` bindings; nested ones are locals, not symbols
+const TS_BINDING: &str = r"^()(?:export\s+)?(?:const|let)\s+([A-Za-z_$][\w$]*)";
+const TS_METHOD: &str = r"^(\s+)(?:(?:public|private|protected|static|async|readonly|get|set)\s+)*([A-Za-z_$][\w$]*)\s*(?:<[^>]*>)?\([^)]*\)\s*(?::[^{]*)?\{\s*$";
+/// Words that look like a method header in `TS_METHOD` but are statements
+const TS_KEYWORDS: [&str; 7] = [
+    "if", "for", "while", "switch", "catch", "function", "return",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CodeLanguage {
+    Rust,
+    Python,
+    TypeScript,
+}
+
+/// A run of lines of a source file
+#[derive(Clone, Debug, PartialEq)]
+pub struct CodeChunk {
+    /// Names defined in the chunk, outermost first; empty for imports and loose code
+    pub symbols: Vec<String>,
+    /// 1-based line the chunk starts on
+    pub start_line: usize,
+    pub text: String,
+}
+
+impl CodeLanguage {
+    /// Language of a file, from its name
+    pub fn of(name: &str) -> Option<Self> {
+        let name = name.to_lowercase();
+        if name.ends_with(".rs") {
+            Some(Self::Rust)
+        } else if name.ends_with(".py") {
+            Some(Self::Python)
+        } else if name.ends_with(".ts") || name.ends_with(".tsx") {
+            Some(Self::TypeScript)
+        } else {
+            None
+        }
+    }
+
+    pub fn file_type(self) -> &'static str {
+        match self {
+            Self::Rust => "rust",
+            Self::Python => "python",
+            Self::TypeScript => "typescript",
+        }
+    }
+
+    fn patterns(self) -> Vec<Regex> {
+        let sources: &[&str] = match self {
+            Self::Rust => &[RUST_DEFINITION, RUST_IMPL],
+            Self::Python => &[PYTHON_DEFINITION],
+            Self::TypeScript => &[TS_DEFINITION, TS_BINDING, TS_METHOD],
+        };
+        sources
+            .iter()
+            .map(|p| Regex::new(p).expect("valid definition regex"))
+            .collect()
+    }
+
+    /// Whether `line` only annotates the definition below it
+    fn is_preamble(self, line: &str) -> bool {
+        let line = line.trim_start();
+        match self {
+            Self::Rust => line.starts_with("//") || line.starts_with("#["),
+            Self::Python => line.starts_with('#') || line.starts_with('@'),
+            Self::TypeScript => {
+                line.starts_with("//")
+                    || line.starts_with("/*")
+                    || line.starts_with('*')
+                    || line.starts_with('@')
+            }
+        }
+    }
+}
+
+/// A definition found on a line: its indentation and name
+struct Definition {
+    line: usize,
+    indent: usize,
+    name: String,
+}
+
+fn definitions(language: CodeLanguage, lines: &[&str]) -> Vec<Definition> {
+    let patterns = language.patterns();
+    lines
+        .iter()
+        .enumerate()
+        .filter_map(|(i, line)| {
+            let caps = patterns.iter().find_map(|re| re.captures(line))?;
+            let name = caps[2].to_string();
+            if language == CodeLanguage::TypeScript && TS_KEYWORDS.contains(&name.as_str()) {
+                return None;
+            }
+            Some(Definition {
+                line: i,
+                indent: caps[1].len(),
+                name,
+            })
+        })
+        .collect()
+}
+
+/// Chunks of `source`, one per top-level definition unless it must be split
+pub fn code_chunks(language: CodeLanguage, source: &str) -> Vec<CodeChunk> {
+    let lines: Vec<&str> = source.lines().collect();
+    let defs = definitions(language, &lines);
+    let mut out = Vec::new();
+    split(language, &lines, &defs, (0, lines.len()), None, &mut out);
+    out
+}
+
+/// Cut `lines[range]` before each of its shallowest definitions, other than `owner`
+/// (the definition the range belongs to), keeping comments and attributes with the
+/// definition below them. Pieces still too large are split along their own members.
+fn split(
+    language: CodeLanguage,
+    lines: &[&str],
+    defs: &[Definition],
+    (start, end): (usize, usize),
+    owner: Option<usize>,
+    out: &mut Vec<CodeChunk>,
+) {
+    let members: Vec<&Definition> = defs
+        .iter()
+        .filter(|d| d.line >= start && d.line < end && Some(d.line) != owner)
+        .collect();
+    let indent = members.iter().map(|d| d.indent).min();
+    let mut cuts = vec![start];
+    for def in members.iter().filter(|d| Some(d.indent) == indent) {
+        let mut cut = def.line;
+        while cut > start && language.is_preamble(lines[cut - 1]) {
+            cut -= 1;
+        }
+        cuts.push(cut);
+    }
+    cuts.push(end);
+    cuts.dedup();
+
+    for piece in cuts.windows(2) {
+        let (from, to) = (piece[0], piece[1]);
+        let text = lines[from..to].join("\n");
+        if text.trim().is_empty() {
+            continue;
+        }
+        let inside: Vec<&Definition> = defs
+            .iter()
+            .filter(|d| d.line >= from && d.line < to)
+            .collect();
+        let head = inside.first().map(|d| d.line);
+        // Only split a piece that is a single definition with members, so the
+        // recursion always makes progress
+        if text.len() > MAX_CODE_CHUNK_CHARS && inside.len() > 1 && head != owner {
+            split(language, lines, defs, (from, to), head, out);
+            continue;
+        }
+        out.push(CodeChunk {
+            symbols: inside.iter().map(|d| d.name.clone()).collect(),
+            start_line: from + 1,
+            text: text.trim_end().to_string(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rust_chunks_follow_definitions() {
+        let source = "use std::fmt;\n\n/// Parsed config\n#[derive(Debug)]\npub struct Config {\n    port: u16,\n}\n\nimpl Config {\n    pub fn load() -> Self {\n        todo!()\n    }\n}\n\npub(crate) async fn serve(config: Config) {}\n";
+        let chunks = code_chunks(CodeLanguage::Rust, source);
+        let symbols: Vec<Vec<String>> = chunks.iter().map(|c| c.symbols.clone()).collect();
+        assert_eq!(
+            symbols,
+            vec![
+                vec![],
+                vec!["Config".to_string()],
+                vec!["Config".to_string(), "load".to_string()],
+                vec!["serve".to_string()],
+            ]
+        );
+        assert!(chunks[1].text.starts_with("/// Parsed config"));
+        assert_eq!(chunks[1].start_line, 3);
+    }
+
+    #[test]
+    fn test_large_definitions_split_along_members() {
+        let body = "        x = 1\n".repeat(MAX_CODE_CHUNK_CHARS / 10);
+        let source = format!(
+            "class Store:\n    @property\n    def size(self):\n{}\n    def clear(self):\n        pass\n",
+            body
+        );
+        let chunks = code_chunks(CodeLanguage::Python, &source);
+        let symbols: Vec<&str> = chunks.iter().map(|c| c.symbols[0].as_str()).collect();
+        assert_eq!(symbols, vec!["Store", "size", "clear"]);
+        assert!(chunks[1].text.starts_with("    @property"));
+        assert_eq!(
+            CodeLanguage::of("app/main.TSX"),
+            Some(CodeLanguage::TypeScript)
+        );
+    }
+}
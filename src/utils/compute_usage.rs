@@ -0,0 +1,354 @@
+use crate::models::{Message, MessageMetadata, MessageRole};
+use crate::storage::Conversation;
+use std::collections::BTreeMap;
+
+/// Assumed average power draw of the GPU while generating, in watts
+pub const DEFAULT_DEVICE_WATTS: f32 = 25.0;
+
+/// localStorage key for the user-adjusted power draw
+pub const DEVICE_WATTS_KEY: &str = "compute_device_watts";
+
+/// Generation statistics of a single assistant reply
+#[derive(Clone, Debug, PartialEq)]
+pub struct GenerationStats {
+    pub prompt_tokens: u32,
+    pub completion_tokens: u32,
+    /// Time the model ran: prefill and decode when the reply has a latency breakdown,
+    /// otherwise its whole processing time
+    pub duration_ms: f64,
+    pub decode_tokens_per_sec: Option<f32>,
+}
+
+impl GenerationStats {
+    /// Stats are only available for replies that recorded a duration
+    pub fn from_metadata(md: &MessageMetadata) -> Option<Self> {
+        // Retrieval and queueing do not keep the GPU busy
+        let duration_ms = md
+            .latency
+            .as_ref()
+            .map(|l| l.first_token_ms + l.generation_ms)
+            .filter(|ms| *ms > 0)
+            .or(md.processing_time_ms)? as f64;
+        let (prompt_tokens, completion_tokens) = token_split(md).unwrap_or((0, 0));
+        Some(Self {
+            prompt_tokens,
+            completion_tokens,
+            duration_ms,
+            decode_tokens_per_sec: md.decode_tokens_per_sec,
+        })
+    }
+
+    pub fn from_message(message: &Message) -> Option<Self> {
+        if message.role != MessageRole::Assistant {
+            return None;
+        }
+        message.metadata.as_ref().and_then(Self::from_metadata)
+    }
+
+    /// Engine-reported decode speed, or completion tokens over wall time
+    pub fn tokens_per_sec(&self) -> Option<f64> {
+        if let Some(tps) = self.decode_tokens_per_sec {
+            return Some(tps as f64);
+        }
+        if self.completion_tokens == 0 || self.duration_ms <= 0.0 {
+            return None;
+        }
+        Some(self.completion_tokens as f64 / (self.duration_ms / 1000.0))
+    }
+
+    /// Energy estimate in watt-hours for a device drawing `watts` during generation
+    pub fn energy_wh(&self, watts: f32) -> f64 {
+        estimate_energy_wh(self.duration_ms, watts)
+    }
+}
+
+pub fn estimate_energy_wh(duration_ms: f64, watts: f32) -> f64 {
+    (duration_ms.max(0.0) / 1000.0) * watts as f64 / 3600.0
+}
+
+/// Aggregated compute usage over a set of replies
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct UsageTotals {
+    pub messages: u32,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub compute_ms: f64,
+    pub energy_wh: f64,
+}
+
+impl UsageTotals {
+    pub fn add(&mut self, stats: &GenerationStats, watts: f32) {
+        self.messages += 1;
+        self.prompt_tokens += stats.prompt_tokens as u64;
+        self.completion_tokens += stats.completion_tokens as u64;
+        self.compute_ms += stats.duration_ms;
+        self.energy_wh += stats.energy_wh(watts);
+    }
+
+    pub fn total_tokens(&self) -> u64 {
+        self.prompt_tokens + self.completion_tokens
+    }
+
+    /// Completion tokens over total generation time
+    pub fn avg_tokens_per_sec(&self) -> Option<f64> {
+        if self.completion_tokens == 0 || self.compute_ms <= 0.0 {
+            return None;
+        }
+        Some(self.completion_tokens as f64 / (self.compute_ms / 1000.0))
+    }
+}
+
+/// Engine-reported tokens of the replies in a conversation, timed or not
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct TokenTotals {
+    pub replies: u32,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+}
+
+impl TokenTotals {
+    pub fn of_messages(messages: &[Message]) -> Self {
+        let mut totals = Self::default();
+        for md in messages
+            .iter()
+            .filter(|m| m.role == MessageRole::Assistant)
+            .filter_map(|m| m.metadata.as_ref())
+        {
+            let Some((prompt, completion)) = token_split(md) else {
+                continue;
+            };
+            totals.replies += 1;
+            totals.prompt_tokens += prompt as u64;
+            totals.completion_tokens += completion as u64;
+        }
+        totals
+    }
+
+    pub fn total(&self) -> u64 {
+        self.prompt_tokens + self.completion_tokens
+    }
+}
+
+/// (prompt, completion) tokens of a reply; older replies only stored the sum, counted
+/// as completion
+pub fn token_split(md: &MessageMetadata) -> Option<(u32, u32)> {
+    match (md.prompt_tokens, md.completion_tokens, md.tokens_used) {
+        (None, None, None) => None,
+        (prompt, Some(completion), _) => Some((prompt.unwrap_or(0), completion)),
+        (prompt, None, total) => {
+            let prompt = prompt.unwrap_or(0);
+            Some((prompt, total.unwrap_or(0).saturating_sub(prompt)))
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConversationUsage {
+    pub conversation_id: String,
+    pub title: String,
+    pub totals: UsageTotals,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ComputeUsageReport {
+    pub overall: UsageTotals,
+    /// Conversations with at least one measured reply, heaviest first
+    pub per_conversation: Vec<ConversationUsage>,
+    /// (day key, totals) in ascending day order
+    pub per_day: Vec<(String, UsageTotals)>,
+}
+
+/// Aggregate usage per conversation and per day; `day_of` maps a timestamp to a day key
+pub fn build_usage_report(
+    conversations: &[Conversation],
+    watts: f32,
+    day_of: impl Fn(f64) -> String,
+) -> ComputeUsageReport {
+    let mut report = ComputeUsageReport::default();
+    let mut days: BTreeMap<String, UsageTotals> = BTreeMap::new();
+
+    for conv in conversations {
+        let mut totals = UsageTotals::default();
+        for m in &conv.messages {
+            let Some(stats) = GenerationStats::from_message(m) else {
+                continue;
+            };
+            totals.add(&stats, watts);
+            report.overall.add(&stats, watts);
+            days.entry(day_of(m.timestamp))
+                .or_default()
+                .add(&stats, watts);
+        }
+        if totals.messages > 0 {
+            report.per_conversation.push(ConversationUsage {
+                conversation_id: conv.id.clone(),
+                title: conv.title.clone(),
+                totals,
+            });
+        }
+    }
+
+    report.per_conversation.sort_by(|a, b| {
+        b.totals
+            .compute_ms
+            .partial_cmp(&a.totals.compute_ms)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    report.per_day = days.into_iter().collect();
+    report
+}
+
+/// Human readable energy amount (mWh below 1 Wh)
+pub fn format_energy(wh: f64) -> String {
+    if wh < 1.0 {
+        format!("{:.1} mWh", wh * 1000.0)
+    } else {
+        format!("{:.2} Wh", wh)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::LatencyBreakdown;
+
+    fn reply(ts: f64, md: MessageMetadata) -> Message {
+        Message {
+            id: format!("{}", ts),
+            role: MessageRole::Assistant,
+            content: "ok".to_string(),
+            timestamp: ts,
+            metadata: Some(md),
+            pinned: false,
+        }
+    }
+
+    fn md(ms: u32, prompt: Option<u32>, completion: Option<u32>) -> MessageMetadata {
+        MessageMetadata {
+            tokens_used: None,
+            processing_time_ms: Some(ms),
+            model_used: None,
+            graphrag_enhanced: false,
+            error: None,
+            provenance: None,
+            prompt_tokens: prompt,
+            completion_tokens: completion,
+            decode_tokens_per_sec: None,
+            retry_reason: None,
+            retrieval_note: None,
+            temperature: None,
+            attempts: Vec::new(),
+            tools_used: Vec::new(),
+            structured: false,
+            latency: None,
+            confidence: None,
+            query_plan: None,
+            retrieval_trace: None,
+            compared: Vec::new(),
+        }
+    }
+
+    fn conv(id: &str, messages: Vec<Message>) -> Conversation {
+        Conversation {
+            id: id.to_string(),
+            title: id.to_string(),
+            created_at: 0.0,
+            updated_at: 0.0,
+            messages,
+            system_prompt: None,
+            stop_sequences: Vec::new(),
+            history_policy: Default::default(),
+            output_format: Default::default(),
+            answer_style: Default::default(),
+            knowledge_scope: Default::default(),
+            root_id: None,
+            parent_id: None,
+            forked_at: None,
+            thread_of: None,
+        }
+    }
+
+    #[test]
+    fn test_energy_estimate() {
+        // 36 s at 100 W = 1 Wh
+        assert!((estimate_energy_wh(36_000.0, 100.0) - 1.0).abs() < 1e-9);
+        assert_eq!(estimate_energy_wh(-5.0, 100.0), 0.0);
+        assert_eq!(format_energy(0.0125), "12.5 mWh");
+        assert_eq!(format_energy(2.0), "2.00 Wh");
+    }
+
+    #[test]
+    fn test_tokens_per_sec_fallback() {
+        let stats = GenerationStats::from_metadata(&md(2000, Some(10), Some(40))).unwrap();
+        assert_eq!(stats.tokens_per_sec(), Some(20.0));
+
+        let mut legacy = md(1000, None, None);
+        legacy.tokens_used = Some(30);
+        let stats = GenerationStats::from_metadata(&legacy).unwrap();
+        assert_eq!(stats.completion_tokens, 30);
+
+        let mut no_time = md(0, None, None);
+        no_time.processing_time_ms = None;
+        assert!(GenerationStats::from_metadata(&no_time).is_none());
+    }
+
+    #[test]
+    fn test_duration_leaves_out_retrieval_and_queueing() {
+        let mut timed = md(5000, Some(10), Some(40));
+        timed.latency = Some(LatencyBreakdown {
+            retrieval_ms: 2500,
+            queue_ms: 500,
+            first_token_ms: 400,
+            generation_ms: 1600,
+            ..Default::default()
+        });
+        let stats = GenerationStats::from_metadata(&timed).unwrap();
+        assert_eq!(stats.duration_ms, 2000.0);
+        assert_eq!(stats.tokens_per_sec(), Some(20.0));
+    }
+
+    #[test]
+    fn test_token_totals_count_untimed_replies() {
+        let mut untimed = md(0, Some(7), Some(3));
+        untimed.processing_time_ms = None;
+        let mut legacy = md(0, None, None);
+        legacy.tokens_used = Some(12);
+        let mut user = reply(3.0, md(0, Some(100), Some(100)));
+        user.role = MessageRole::User;
+        let messages = vec![
+            reply(1.0, untimed),
+            reply(2.0, legacy),
+            reply(4.0, md(0, None, None)),
+            user,
+        ];
+        let totals = TokenTotals::of_messages(&messages);
+        assert_eq!(totals.replies, 2);
+        assert_eq!((totals.prompt_tokens, totals.completion_tokens), (7, 15));
+        assert_eq!(totals.total(), 22);
+    }
+
+    #[test]
+    fn test_build_usage_report() {
+        let day = |ts: f64| if ts < 100.0 { "d1" } else { "d2" }.to_string();
+        let convs = vec![
+            conv("a", vec![reply(1.0, md(1000, Some(5), Some(10)))]),
+            conv(
+                "b",
+                vec![
+                    reply(2.0, md(3000, Some(5), Some(30))),
+                    reply(200.0, md(1000, Some(5), Some(10))),
+                ],
+            ),
+            conv("empty", vec![]),
+        ];
+        let report = build_usage_report(&convs, 36.0, day);
+        assert_eq!(report.overall.messages, 3);
+        assert_eq!(report.overall.total_tokens(), 65);
+        assert_eq!(report.per_conversation.len(), 2);
+        assert_eq!(report.per_conversation[0].conversation_id, "b");
+        assert_eq!(report.per_day.len(), 2);
+        assert_eq!(report.per_day[0].0, "d1");
+        assert_eq!(report.per_day[0].1.messages, 2);
+        assert_eq!(report.overall.avg_tokens_per_sec(), Some(10.0));
+    }
+}
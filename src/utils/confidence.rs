@@ -0,0 +1,202 @@
+//! Calibrated confidence for a reply. Three signals are read once a reply is done: how
+//! relevant the passages it was grounded on were, whether it passed the completion
+//! checks, and whether the model hedged or declined in the reply itself. They are
+//! combined with the weights in `ConfidenceSettings`; a signal a reply does not have
+//! (no knowledge retrieval) is left out and the other weights are renormalized.
+
+use crate::models::{AnswerConfidence, ConfidenceLevel, ConfidenceSettings, ConfidenceSignal};
+
+// Passages averaged for the retrieval signal
+const TOP_SOURCES: usize = 3;
+// Lowest self-assessment a hedging (but not declining) reply can get
+const MIN_HEDGED: f32 = 0.2;
+const HEDGE_PENALTY: f32 = 0.2;
+
+// Phrases of a model unsure of its answer, matched on the lowercased reply
+const HEDGES: &[&str] = &[
+    "i'm not sure",
+    "i am not sure",
+    "not certain",
+    "i don't know",
+    "i do not know",
+    "may not be accurate",
+    "might be",
+    "possibly",
+    "i believe",
+    "i think",
+    "it seems",
+    "it appears",
+    "unclear",
+];
+
+// Phrases of a model saying the answer is not in what it was given
+const DECLINES: &[&str] = &[
+    "no information",
+    "does not mention",
+    "do not mention",
+    "doesn't mention",
+    "not mentioned",
+    "cannot find",
+    "can't find",
+    "couldn't find",
+    "not in the provided",
+    "unable to answer",
+    "don't have enough information",
+    "do not have enough information",
+];
+
+/// What is known about a finished reply
+pub struct ReplyEvidence<'a> {
+    /// Relevance of the passages injected into the prompt; `None` when knowledge
+    /// retrieval was off, empty when nothing passed the relevance threshold
+    pub source_scores: Option<&'a [f32]>,
+    /// The first reply failed validation and was regenerated
+    pub retried: bool,
+    /// A JSON reply still failed its schema after the repair round
+    pub structured_error: bool,
+    pub reply: &'a str,
+}
+
+fn retrieval_signal(scores: &[f32]) -> (f32, String) {
+    if scores.is_empty() {
+        return (0.0, "No passage passed the relevance threshold".to_string());
+    }
+    let mut scores = scores.to_vec();
+    scores.sort_by(|a, b| b.partial_cmp(a).unwrap_or(std::cmp::Ordering::Equal));
+    scores.truncate(TOP_SOURCES);
+    let mean = scores.iter().sum::<f32>() / scores.len() as f32;
+    (
+        mean.clamp(0.0, 1.0),
+        format!("Mean relevance of the top {} passage(s)", scores.len()),
+    )
+}
+
+fn verification_signal(retried: bool, structured_error: bool) -> (f32, String) {
+    if structured_error {
+        (0.2, "JSON reply failed its schema after repair".to_string())
+    } else if retried {
+        (
+            0.6,
+            "First reply failed validation and was regenerated".to_string(),
+        )
+    } else {
+        (1.0, "Passed the completion checks".to_string())
+    }
+}
+
+fn self_assessment_signal(reply: &str) -> (f32, String) {
+    let reply = reply.to_lowercase();
+    if DECLINES.iter().any(|p| reply.contains(p)) {
+        return (
+            0.1,
+            "The reply says the answer is not in what it was given".to_string(),
+        );
+    }
+    let hedges = HEDGES.iter().filter(|p| reply.contains(*p)).count();
+    if hedges == 0 {
+        return (1.0, "No hedging in the reply".to_string());
+    }
+    (
+        (1.0 - HEDGE_PENALTY * hedges as f32).max(MIN_HEDGED),
+        format!("{} hedging phrase(s) in the reply", hedges),
+    )
+}
+
+/// Combine the signals of a reply into a score and level
+pub fn calibrate(evidence: &ReplyEvidence, settings: &ConfidenceSettings) -> AnswerConfidence {
+    let mut raw: Vec<(&str, (f32, String), f32)> = Vec::new();
+    if let Some(scores) = evidence.source_scores {
+        raw.push((
+            "Retrieval",
+            retrieval_signal(scores),
+            settings.retrieval_weight,
+        ));
+    }
+    raw.push((
+        "Verification",
+        verification_signal(evidence.retried, evidence.structured_error),
+        settings.verification_weight,
+    ));
+    raw.push((
+        "Self-assessment",
+        self_assessment_signal(evidence.reply),
+        settings.self_assessment_weight,
+    ));
+
+    // Weights are relative; all zero counts every signal the same
+    let total: f32 = raw.iter().map(|(_, _, w)| w.max(0.0)).sum();
+    let n = raw.len() as f32;
+    let signals: Vec<ConfidenceSignal> = raw
+        .into_iter()
+        .map(|(name, (value, note), weight)| ConfidenceSignal {
+            name: name.to_string(),
+            value,
+            weight: if total > 0.0 {
+                weight.max(0.0) / total
+            } else {
+                1.0 / n
+            },
+            note,
+        })
+        .collect();
+    let score = signals.iter().map(|s| s.value * s.weight).sum::<f32>();
+    let level = if score >= settings.high_at {
+        ConfidenceLevel::High
+    } else if score >= settings.medium_at {
+        ConfidenceLevel::Medium
+    } else {
+        ConfidenceLevel::Low
+    };
+    AnswerConfidence {
+        level,
+        score,
+        signals,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn evidence<'a>(scores: Option<&'a [f32]>, reply: &'a str) -> ReplyEvidence<'a> {
+        ReplyEvidence {
+            source_scores: scores,
+            retried: false,
+            structured_error: false,
+            reply,
+        }
+    }
+
+    #[test]
+    fn test_calibration_weighs_available_signals() {
+        let settings = ConfidenceSettings::default();
+
+        // Strong sources, clean reply
+        let grounded = calibrate(
+            &evidence(
+                Some(&[0.9, 0.8, 0.7, 0.1]),
+                "Refunds are accepted for 30 days.",
+            ),
+            &settings,
+        );
+        assert_eq!(grounded.level, ConfidenceLevel::High);
+        assert!((grounded.signals[0].value - 0.8).abs() < 1e-6);
+        assert!((grounded.signals.iter().map(|s| s.weight).sum::<f32>() - 1.0).abs() < 1e-6);
+
+        // Nothing relevant retrieved and the model says so
+        let declined = calibrate(
+            &evidence(Some(&[]), "The documents do not mention a refund period."),
+            &settings,
+        );
+        assert_eq!(declined.level, ConfidenceLevel::Low);
+
+        // Without retrieval only verification and self-assessment count
+        let mut hedged = evidence(None, "I think it is possibly Tuesday.");
+        hedged.retried = true;
+        let hedged = calibrate(&hedged, &settings);
+        assert_eq!(hedged.signals.len(), 2);
+        assert!((hedged.signals[0].weight - 0.4).abs() < 1e-6);
+        assert!((hedged.score - (0.4 * 0.6 + 0.6 * 0.6)).abs() < 1e-6);
+        assert_eq!(hedged.level, ConfidenceLevel::Medium);
+    }
+}
@@ -0,0 +1,456 @@
+//! Prompt assembly within the model's context window. A conversation's `HistoryPolicy`
+//! picks the turns sent verbatim; older turns are either left out or folded into a
+//! summary written by the loaded model and sent as a system message.
+
+use crate::models::generation::{ContextSettings, GenerationSettings, HistoryPolicy};
+use crate::models::{Message, MessageRole};
+use crate::storage::{CachePolicy, TieredCache};
+use crate::webllm_binding::send_message_to_llm;
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::JsValue;
+
+/// Per-message overhead of the chat template (role markers, separators)
+const MESSAGE_OVERHEAD_TOKENS: usize = 4;
+/// Characters of each message quoted in the summarization transcript
+const TRANSCRIPT_MESSAGE_CHARS: usize = 1_500;
+/// Summaries are kept for a day, keyed by conversation
+const SUMMARY_CACHE_MINUTES: f64 = 24.0 * 60.0;
+const SUMMARY_CACHE_BYTES: usize = 32 * 1024;
+const SUMMARY_PREFIX: &str = "Summary of the earlier conversation:";
+
+/// Rough token count of `text`: about four characters or 0.75 words per token,
+/// whichever is larger
+pub fn estimate_tokens(text: &str) -> usize {
+    let by_chars = text.chars().count().div_ceil(4);
+    let by_words = (text.split_whitespace().count() * 4).div_ceil(3);
+    by_chars.max(by_words)
+}
+
+pub fn estimate_message_tokens(message: &Message) -> usize {
+    estimate_tokens(&message.content) + MESSAGE_OVERHEAD_TOKENS
+}
+
+pub fn estimate_messages_tokens(messages: &[Message]) -> usize {
+    messages.iter().map(estimate_message_tokens).sum()
+}
+
+/// Summary of a conversation's older turns, up to and including `covers_until`
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ContextSummary {
+    pub covers_until: String,
+    pub text: String,
+}
+
+/// Which history messages are sent as-is; the others are summarized or left out
+#[derive(Clone, Debug, PartialEq)]
+pub struct ContextPlan {
+    pub fold: Vec<Message>,
+    pub keep: Vec<Message>,
+    /// Replace `fold` with a summary instead of dropping it
+    pub summarize: bool,
+}
+
+/// Result of fitting a history into the window
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FittedContext {
+    pub messages: Vec<Message>,
+    /// Messages folded into the summary
+    pub summarized: usize,
+    /// Messages left out of the prompt entirely
+    pub dropped: usize,
+}
+
+/// What the next prompt will carry, for display before sending
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SendPreview {
+    pub sent: usize,
+    pub total: usize,
+    pub summary: bool,
+}
+
+impl SendPreview {
+    /// e.g. "Sending 14 of 52 messages + summary"; `None` when everything is sent
+    pub fn describe(&self) -> Option<String> {
+        if self.sent >= self.total {
+            return None;
+        }
+        Some(format!(
+            "Sending {} of {} messages{}",
+            self.sent,
+            self.total,
+            if self.summary { " + summary" } else { "" }
+        ))
+    }
+}
+
+pub struct ContextManager {
+    settings: ContextSettings,
+    policy: HistoryPolicy,
+    /// Tokens kept free for the reply
+    reply_tokens: usize,
+}
+
+impl ContextManager {
+    pub fn new(settings: &GenerationSettings) -> Self {
+        Self::with_policy(settings, HistoryPolicy::Auto)
+    }
+
+    /// Manager applying a conversation's own history policy
+    pub fn with_policy(settings: &GenerationSettings, policy: HistoryPolicy) -> Self {
+        Self {
+            settings: settings.context.clone(),
+            policy,
+            reply_tokens: settings.sampling.max_tokens as usize,
+        }
+    }
+
+    /// Prompt tokens available once the reply is reserved
+    pub fn budget(&self) -> usize {
+        (self.settings.window_tokens as usize).saturating_sub(self.reply_tokens)
+    }
+
+    fn threshold(&self) -> usize {
+        (self.budget() as f32 * self.settings.summarize_at.clamp(0.1, 1.0)) as usize
+    }
+
+    /// `None` when the whole history is sent as-is
+    pub fn plan(&self, system: &[Message], history: &[Message]) -> Option<ContextPlan> {
+        let fixed = estimate_messages_tokens(system);
+        let (keep_from, summarize) = match self.policy {
+            HistoryPolicy::Auto => {
+                if fixed + estimate_messages_tokens(history) <= self.threshold() {
+                    return None;
+                }
+                if self.settings.summarize {
+                    // The prompt being answered is always kept
+                    let keep = self.settings.keep_recent.max(1);
+                    (history.len().saturating_sub(keep), true)
+                } else {
+                    (
+                        fit_from(history, self.budget().saturating_sub(fixed)),
+                        false,
+                    )
+                }
+            }
+            HistoryPolicy::LastTurns { turns } => (turn_start(history, turns), false),
+            HistoryPolicy::TokenBudget { tokens } => {
+                let budget = (tokens as usize).min(self.budget().saturating_sub(fixed));
+                (fit_from(history, budget), false)
+            }
+            HistoryPolicy::SummaryPlusRecent { turns } => (turn_start(history, turns), true),
+        };
+        if keep_from == 0 {
+            return None;
+        }
+        Some(ContextPlan {
+            fold: history[..keep_from].to_vec(),
+            keep: history[keep_from..].to_vec(),
+            summarize,
+        })
+    }
+
+    /// What `plan` sends of `history`, ignoring system messages
+    pub fn preview(&self, history: &[Message]) -> SendPreview {
+        match self.plan(&[], history) {
+            Some(plan) => SendPreview {
+                sent: plan.keep.len(),
+                total: history.len(),
+                summary: plan.summarize,
+            },
+            None => SendPreview {
+                sent: history.len(),
+                total: history.len(),
+                summary: false,
+            },
+        }
+    }
+
+    /// Drop the oldest kept messages until `system` and `keep` fit the budget. The last
+    /// message always stays.
+    pub fn trim(&self, system: &[Message], keep: Vec<Message>) -> (Vec<Message>, usize) {
+        let mut keep = keep;
+        let mut dropped = 0;
+        let fixed = estimate_messages_tokens(system);
+        while keep.len() > 1 && fixed + estimate_messages_tokens(&keep) > self.budget() {
+            keep.remove(0);
+            dropped += 1;
+        }
+        (keep, dropped)
+    }
+
+    /// `system` followed by the history the policy selects, with older turns summarized
+    /// when it asks for it. Summaries are cached per conversation and extended
+    /// incrementally.
+    pub async fn fit(
+        &self,
+        engine: &JsValue,
+        conversation_id: Option<&str>,
+        system: Vec<Message>,
+        history: Vec<Message>,
+    ) -> FittedContext {
+        let plan = match self.plan(&system, &history) {
+            Some(plan) if plan.summarize => plan,
+            Some(plan) => {
+                let (keep, dropped) = self.trim(&system, plan.keep);
+                return FittedContext {
+                    messages: system.into_iter().chain(keep).collect(),
+                    summarized: 0,
+                    dropped: dropped + plan.fold.len(),
+                };
+            }
+            None => {
+                let (keep, dropped) = self.trim(&system, history);
+                return FittedContext {
+                    messages: system.into_iter().chain(keep).collect(),
+                    summarized: 0,
+                    dropped,
+                };
+            }
+        };
+
+        let cache_key = conversation_id.map(|id| format!("context_summary:{}", id));
+        let cached = cache_key
+            .as_deref()
+            .and_then(TieredCache::get::<ContextSummary>);
+        let summary = match summary_reuse(cached.as_ref(), &plan.fold) {
+            SummaryReuse::Whole(text) => Ok(text),
+            SummaryReuse::Extend(previous, new_turns) => {
+                summarize(engine, previous.as_deref(), new_turns).await
+            }
+        };
+
+        match summary {
+            Ok(text) => {
+                if let (Some(key), Some(last)) = (&cache_key, plan.fold.last()) {
+                    let entry = ContextSummary {
+                        covers_until: last.id.clone(),
+                        text: text.clone(),
+                    };
+                    let policy = CachePolicy::expiring(SUMMARY_CACHE_MINUTES, SUMMARY_CACHE_BYTES);
+                    if let Err(e) = TieredCache::set(key, &entry, policy) {
+                        log::warn!("Failed to cache context summary: {}", e);
+                    }
+                }
+                let mut messages = system;
+                messages.push(Message::new(
+                    MessageRole::System,
+                    format!("{} {}", SUMMARY_PREFIX, text.trim()),
+                ));
+                let (keep, dropped) = self.trim(&messages, plan.keep);
+                messages.extend(keep);
+                FittedContext {
+                    messages,
+                    summarized: plan.fold.len(),
+                    dropped,
+                }
+            }
+            Err(e) => {
+                log::warn!("Context summarization failed, dropping old turns: {:?}", e);
+                let (keep, dropped) = self.trim(&system, plan.keep);
+                FittedContext {
+                    messages: system.into_iter().chain(keep).collect(),
+                    summarized: 0,
+                    dropped: dropped + plan.fold.len(),
+                }
+            }
+        }
+    }
+}
+
+/// Index of the user message opening the last `turns` exchanges; 0 when the history
+/// has no more than that. Always leaves at least the last message.
+fn turn_start(history: &[Message], turns: usize) -> usize {
+    let mut seen = 0;
+    for (i, m) in history.iter().enumerate().rev() {
+        if m.role == MessageRole::User {
+            seen += 1;
+            if seen >= turns.max(1) {
+                return i;
+            }
+        }
+    }
+    0
+}
+
+/// Index from which the most recent messages fit in `budget` tokens, keeping at least
+/// the last message
+fn fit_from(history: &[Message], budget: usize) -> usize {
+    let mut used = 0;
+    for (i, m) in history.iter().enumerate().rev() {
+        used += estimate_message_tokens(m);
+        if used > budget {
+            return (i + 1).min(history.len().saturating_sub(1));
+        }
+    }
+    0
+}
+
+/// How much of a cached summary still applies to the turns being folded
+enum SummaryReuse<'a> {
+    /// The summary covers exactly these turns
+    Whole(String),
+    /// Summarize these turns, on top of the previous summary if any
+    Extend(Option<String>, &'a [Message]),
+}
+
+fn summary_reuse<'a>(cached: Option<&ContextSummary>, fold: &'a [Message]) -> SummaryReuse<'a> {
+    let covered = cached.and_then(|c| {
+        fold.iter()
+            .position(|m| m.id == c.covers_until)
+            .map(|i| (c.text.clone(), i))
+    });
+    match covered {
+        Some((text, i)) if i + 1 == fold.len() => SummaryReuse::Whole(text),
+        Some((text, i)) => SummaryReuse::Extend(Some(text), &fold[i + 1..]),
+        None => SummaryReuse::Extend(None, fold),
+    }
+}
+
+/// Ask the loaded model to summarize `turns`, continuing `previous` if given
+async fn summarize(
+    engine: &JsValue,
+    previous: Option<&str>,
+    turns: &[Message],
+) -> Result<String, JsValue> {
+    let request = vec![
+        Message::new(
+            MessageRole::System,
+            "Summarize the conversation below for your own later reference. Keep names, \
+             numbers, decisions, user preferences and open questions. Write at most 200 \
+             words of plain prose."
+                .to_string(),
+        ),
+        Message::new(MessageRole::User, transcript(previous, turns)),
+    ];
+    let text = send_message_to_llm(engine, request).await?;
+    if text.trim().is_empty() {
+        return Err(JsValue::from_str("empty summary"));
+    }
+    Ok(text)
+}
+
+fn transcript(previous: Option<&str>, turns: &[Message]) -> String {
+    let mut out = String::new();
+    if let Some(previous) = previous {
+        out.push_str("Earlier summary: ");
+        out.push_str(previous);
+        out.push_str("\n\n");
+    }
+    for m in turns {
+        let speaker = match m.role {
+            MessageRole::User => "User",
+            MessageRole::Assistant => "Assistant",
+            MessageRole::System => continue,
+        };
+        let text: String = m.content.chars().take(TRANSCRIPT_MESSAGE_CHARS).collect();
+        out.push_str(&format!("{}: {}\n", speaker, text));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn msg(id: &str, role: MessageRole, words: usize) -> Message {
+        Message {
+            id: id.to_string(),
+            role,
+            content: vec!["word"; words].join(" "),
+            timestamp: 0.0,
+            metadata: None,
+            pinned: false,
+        }
+    }
+
+    fn manager(window_tokens: u32) -> ContextManager {
+        let mut settings = GenerationSettings::default();
+        settings.context.window_tokens = window_tokens;
+        settings.context.keep_recent = 2;
+        settings.sampling.max_tokens = 100;
+        ContextManager::new(&settings)
+    }
+
+    #[test]
+    fn test_estimate_tokens() {
+        assert_eq!(estimate_tokens(""), 0);
+        assert_eq!(estimate_tokens("abcdefgh"), 2);
+        // Many short words count by words
+        assert_eq!(estimate_tokens("a b c"), 4);
+    }
+
+    #[test]
+    fn test_plan_folds_older_turns_only_when_near_the_limit() {
+        let history: Vec<Message> = (0..6)
+            .map(|i| msg(&i.to_string(), MessageRole::User, 30))
+            .collect();
+        assert!(manager(4096).plan(&[], &history).is_none());
+
+        let plan = manager(300).plan(&[], &history).unwrap();
+        assert_eq!(plan.fold.len(), 4);
+        assert_eq!(plan.keep.last().unwrap().id, "5");
+
+        let (kept, dropped) = manager(150).trim(&[], plan.keep);
+        assert_eq!((kept.len(), dropped), (1, 1));
+    }
+
+    #[test]
+    fn test_policies_select_recent_turns() {
+        let roles = [MessageRole::User, MessageRole::Assistant];
+        let history: Vec<Message> = (0..10)
+            .map(|i| msg(&i.to_string(), roles[i % 2].clone(), 30))
+            .collect();
+        let with = |policy| {
+            let mut settings = GenerationSettings::default();
+            settings.sampling.max_tokens = 100;
+            ContextManager::with_policy(&settings, policy)
+        };
+
+        let last = with(HistoryPolicy::LastTurns { turns: 2 }).preview(&history);
+        assert_eq!((last.sent, last.total, last.summary), (4, 10, false));
+        assert_eq!(last.describe().as_deref(), Some("Sending 4 of 10 messages"));
+
+        let recent = with(HistoryPolicy::SummaryPlusRecent { turns: 1 });
+        let plan = recent.plan(&[], &history).unwrap();
+        assert!(plan.summarize);
+        assert_eq!(plan.keep.first().unwrap().id, "8");
+
+        // Each message is 44 tokens
+        let budget = with(HistoryPolicy::TokenBudget { tokens: 100 }).preview(&history);
+        assert_eq!(budget.sent, 2);
+        assert!(with(HistoryPolicy::Auto)
+            .preview(&history)
+            .describe()
+            .is_none());
+    }
+
+    #[test]
+    fn test_cached_summary_is_extended() {
+        let fold: Vec<Message> = (0..4)
+            .map(|i| msg(&i.to_string(), MessageRole::User, 1))
+            .collect();
+        let cached = ContextSummary {
+            covers_until: "1".to_string(),
+            text: "earlier".to_string(),
+        };
+        match summary_reuse(Some(&cached), &fold) {
+            SummaryReuse::Extend(Some(text), rest) => {
+                assert_eq!(text, "earlier");
+                assert_eq!(rest.len(), 2);
+            }
+            _ => panic!("expected an extension"),
+        }
+        let whole = ContextSummary {
+            covers_until: "3".to_string(),
+            text: "all".to_string(),
+        };
+        assert!(matches!(
+            summary_reuse(Some(&whole), &fold),
+            SummaryReuse::Whole(_)
+        ));
+        assert!(matches!(
+            summary_reuse(None, &fold),
+            SummaryReuse::Extend(None, _)
+        ));
+    }
+}
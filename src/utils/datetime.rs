@@ -0,0 +1,437 @@
+//! Local date and time for the model. Models have no clock and often guess today's
+//! date wrong, so the chat injects a system line with the current date and timezone,
+//! and relative dates in the prompt ("next Tuesday") are resolved locally, the same way
+//! task extraction resolves due dates.
+
+use crate::models::generation::DateTimeSettings;
+use js_sys::{Array, Object, Reflect};
+use wasm_bindgen::{JsCast, JsValue};
+
+pub const WEEKDAYS: [&str; 7] = [
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+    "Sunday",
+];
+
+/// Longest phrase, in words, recognized as a relative date
+const MAX_PHRASE_WORDS: usize = 5;
+
+/// A proleptic Gregorian calendar date
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct CivilDate {
+    pub year: i64,
+    pub month: u32,
+    pub day: u32,
+}
+
+impl CivilDate {
+    pub fn new(year: i64, month: u32, day: u32) -> Option<Self> {
+        if !(1..=12).contains(&month) || day == 0 || day > days_in_month(year, month) {
+            return None;
+        }
+        Some(Self { year, month, day })
+    }
+
+    /// `YYYY-MM-DD`, ignoring anything after the day
+    pub fn parse_iso(text: &str) -> Option<Self> {
+        let mut parts = text.trim().splitn(3, '-');
+        let year = parts.next()?.parse().ok()?;
+        let month = parts.next()?.parse().ok()?;
+        let day = parts
+            .next()?
+            .chars()
+            .take_while(|c| c.is_ascii_digit())
+            .collect::<String>()
+            .parse()
+            .ok()?;
+        Self::new(year, month, day)
+    }
+
+    /// Days since 1970-01-01
+    pub fn to_days(self) -> i64 {
+        days_from_civil(self.year, self.month, self.day)
+    }
+
+    pub fn from_days(days: i64) -> Self {
+        let z = days + 719_468;
+        let era = if z >= 0 { z } else { z - 146_096 } / 146_097;
+        let doe = z - era * 146_097;
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+        let month = (if mp < 10 { mp + 3 } else { mp - 9 }) as u32;
+        let year = yoe + era * 400 + i64::from(month <= 2);
+        Self { year, month, day }
+    }
+
+    pub fn add_days(self, days: i64) -> Self {
+        Self::from_days(self.to_days() + days)
+    }
+
+    /// Same day `months` later, clamped to the end of shorter months
+    pub fn add_months(self, months: i64) -> Self {
+        let index = self.year * 12 + (self.month as i64 - 1) + months;
+        let (year, month) = (index.div_euclid(12), (index.rem_euclid(12) + 1) as u32);
+        Self {
+            year,
+            month,
+            day: self.day.min(days_in_month(year, month)),
+        }
+    }
+
+    /// 0 for Monday through 6 for Sunday
+    pub fn weekday(self) -> usize {
+        // 1970-01-01 was a Thursday
+        (self.to_days() + 3).rem_euclid(7) as usize
+    }
+
+    pub fn weekday_name(self) -> &'static str {
+        WEEKDAYS[self.weekday()]
+    }
+
+    pub fn iso(self) -> String {
+        format!("{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+
+    /// UTC midnight of this date, in milliseconds
+    pub fn timestamp_ms(self) -> f64 {
+        self.to_days() as f64 * 86_400_000.0
+    }
+}
+
+/// Days since 1970-01-01 for a proleptic Gregorian date
+pub fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = if y >= 0 { y } else { y - 399 } / 400;
+    let yoe = y - era * 400;
+    let m = month as i64;
+    let doy = (153 * (if m > 2 { m - 3 } else { m + 9 }) + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Wall-clock date and time in a timezone
+#[derive(Clone, Debug, PartialEq)]
+pub struct LocalNow {
+    pub date: CivilDate,
+    pub hour: u32,
+    pub minute: u32,
+    /// IANA name, e.g. `Europe/Rome`
+    pub timezone: String,
+    /// Minutes ahead of UTC
+    pub utc_offset_minutes: i64,
+}
+
+impl LocalNow {
+    /// Current time in `timezone`, or the browser's own when it is `None` or unknown
+    pub fn current(timezone: Option<&str>) -> Self {
+        let now = js_sys::Date::new_0();
+        let parts = timezone
+            .and_then(|tz| wall_clock(&now, Some(tz)))
+            .or_else(|| wall_clock(&now, None));
+        match parts {
+            Some(local) => local,
+            // No Intl support: fall back to the Date's local fields
+            None => {
+                let date = CivilDate {
+                    year: now.get_full_year() as i64,
+                    month: now.get_month() + 1,
+                    day: now.get_date(),
+                };
+                Self {
+                    date,
+                    hour: now.get_hours(),
+                    minute: now.get_minutes(),
+                    timezone: "local time".to_string(),
+                    utc_offset_minutes: -(now.get_timezone_offset() as i64),
+                }
+            }
+        }
+    }
+
+    pub fn utc_offset(&self) -> String {
+        let sign = if self.utc_offset_minutes < 0 {
+            '-'
+        } else {
+            '+'
+        };
+        let abs = self.utc_offset_minutes.abs();
+        format!("UTC{}{:02}:{:02}", sign, abs / 60, abs % 60)
+    }
+
+    /// System message content, e.g. "Current date: Friday, 2026-10-16 14:05 (Europe/Rome,
+    /// UTC+02:00)."
+    pub fn system_line(&self, include_time: bool) -> String {
+        let time = if include_time {
+            format!(" {:02}:{:02}", self.hour, self.minute)
+        } else {
+            String::new()
+        };
+        format!(
+            "Current date: {}, {}{} ({}, {}). Use it for anything that depends on today's date.",
+            self.date.weekday_name(),
+            self.date.iso(),
+            time,
+            self.timezone,
+            self.utc_offset()
+        )
+    }
+}
+
+/// Whether the browser knows `timezone`
+pub fn is_valid_timezone(timezone: &str) -> bool {
+    formatter(Some(timezone)).is_some()
+}
+
+/// `Intl.DateTimeFormat` for `timezone`; constructed through `Reflect` because an
+/// unknown zone throws
+fn formatter(timezone: Option<&str>) -> Option<js_sys::Intl::DateTimeFormat> {
+    let intl = Reflect::get(&js_sys::global(), &JsValue::from_str("Intl")).ok()?;
+    let ctor = Reflect::get(&intl, &JsValue::from_str("DateTimeFormat"))
+        .ok()?
+        .dyn_into::<js_sys::Function>()
+        .ok()?;
+    let options = Object::new();
+    let set = |key: &str, value: &str| {
+        let _ = Reflect::set(&options, &JsValue::from_str(key), &JsValue::from_str(value));
+    };
+    for key in ["year", "month", "day", "hour", "minute"] {
+        set(key, if key == "year" { "numeric" } else { "2-digit" });
+    }
+    set("hourCycle", "h23");
+    if let Some(tz) = timezone {
+        set("timeZone", tz);
+    }
+    let args = Array::of2(&JsValue::from_str("en-US"), &options);
+    Reflect::construct(&ctor, &args).ok()?.dyn_into().ok()
+}
+
+fn wall_clock(now: &js_sys::Date, timezone: Option<&str>) -> Option<LocalNow> {
+    let fmt = formatter(timezone)?;
+    let mut fields = [0i64; 5];
+    for part in fmt.format_to_parts(now).iter() {
+        let kind = Reflect::get(&part, &JsValue::from_str("type"))
+            .ok()?
+            .as_string()?;
+        let value = Reflect::get(&part, &JsValue::from_str("value"))
+            .ok()?
+            .as_string()?;
+        let slot = match kind.as_str() {
+            "year" => 0,
+            "month" => 1,
+            "day" => 2,
+            "hour" => 3,
+            "minute" => 4,
+            _ => continue,
+        };
+        fields[slot] = value.parse().ok()?;
+    }
+    let date = CivilDate::new(fields[0], fields[1] as u32, fields[2] as u32)?;
+    let resolved = fmt.resolved_options();
+    let timezone = Reflect::get(&resolved, &JsValue::from_str("timeZone"))
+        .ok()
+        .and_then(|v| v.as_string())
+        .unwrap_or_else(|| "UTC".to_string());
+    let local_minutes = date.to_days() * 1440 + fields[3] * 60 + fields[4];
+    let utc_minutes = (now.get_time() / 60_000.0).floor() as i64;
+    Some(LocalNow {
+        date,
+        hour: fields[3] as u32,
+        minute: fields[4] as u32,
+        timezone,
+        utc_offset_minutes: local_minutes - utc_minutes,
+    })
+}
+
+/// Resolve a date phrase relative to `today`: an ISO date, "today", "tomorrow",
+/// "yesterday", "in 3 days", "in two weeks", "next month", "next week" (its Monday),
+/// "end of the week" (Friday), "end of the month", or a weekday. A bare or "this"
+/// weekday is its next occurrence from today on; "next Tuesday" is strictly after today.
+pub fn resolve_relative_date(phrase: &str, today: CivilDate) -> Option<CivilDate> {
+    let lower = phrase.trim().to_lowercase();
+    if let Some(date) = CivilDate::parse_iso(&lower) {
+        return Some(date);
+    }
+    let words: Vec<&str> = lower
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty() && *w != "on" && *w != "the" && *w != "by")
+        .collect();
+    let weekday = |w: &str| WEEKDAYS.iter().position(|d| d.to_lowercase() == w);
+    let until = |target: usize| (target as i64 - today.weekday() as i64).rem_euclid(7);
+
+    match words.as_slice() {
+        ["today"] | ["tonight"] => Some(today),
+        ["tomorrow"] => Some(today.add_days(1)),
+        ["yesterday"] => Some(today.add_days(-1)),
+        ["day", "after", "tomorrow"] => Some(today.add_days(2)),
+        ["next", "week"] => Some(today.add_days(7 - today.weekday() as i64)),
+        ["next", "month"] => Some(today.add_months(1)),
+        ["end", "of", "week"] => Some(today.add_days(until(4))),
+        ["end", "of", "month"] => Some(CivilDate {
+            day: days_in_month(today.year, today.month),
+            ..today
+        }),
+        ["in", n, unit] | [n, unit, "from", "now"] => {
+            let n = count(n)?;
+            match unit.trim_end_matches('s') {
+                "day" => Some(today.add_days(n)),
+                "week" => Some(today.add_days(7 * n)),
+                "month" => Some(today.add_months(n)),
+                _ => None,
+            }
+        }
+        ["next", day] => {
+            let target = weekday(day)?;
+            Some(today.add_days(match until(target) {
+                0 => 7,
+                d => d,
+            }))
+        }
+        [day] | ["this", day] => Some(today.add_days(until(weekday(day)?))),
+        _ => None,
+    }
+}
+
+fn count(word: &str) -> Option<i64> {
+    const WORDS: [&str; 11] = [
+        "zero", "one", "two", "three", "four", "five", "six", "seven", "eight", "nine", "ten",
+    ];
+    match word {
+        "a" | "an" => Some(1),
+        _ => word
+            .parse()
+            .ok()
+            .or_else(|| WORDS.iter().position(|w| *w == word).map(|i| i as i64)),
+    }
+}
+
+/// Relative date phrases in `text` and the dates they resolve to, longest match first.
+/// "today" and literal ISO dates are left out since they need no resolving.
+pub fn find_relative_dates(text: &str, today: CivilDate) -> Vec<(String, CivilDate)> {
+    let words: Vec<&str> = text
+        .split_whitespace()
+        .map(|w| w.trim_matches(|c: char| !c.is_alphanumeric()))
+        .collect();
+    let mut found: Vec<(String, CivilDate)> = Vec::new();
+    let mut i = 0;
+    while i < words.len() {
+        let longest = (1..=MAX_PHRASE_WORDS.min(words.len() - i))
+            .rev()
+            .find_map(|n| {
+                let phrase = words[i..i + n].join(" ");
+                let skip = phrase.is_empty()
+                    || phrase.eq_ignore_ascii_case("today")
+                    || CivilDate::parse_iso(&phrase).is_some()
+                    || words[i].eq_ignore_ascii_case("on");
+                if skip {
+                    return None;
+                }
+                resolve_relative_date(&phrase, today).map(|d| (n, phrase, d))
+            });
+        match longest {
+            Some((n, phrase, date)) => {
+                if !found.iter().any(|(p, _)| p.eq_ignore_ascii_case(&phrase)) {
+                    found.push((phrase, date));
+                }
+                i += n;
+            }
+            None => i += 1,
+        }
+    }
+    found
+}
+
+/// System message for `prompt`, or `None` when injection is turned off
+pub fn system_context(settings: &DateTimeSettings, prompt: &str) -> Option<String> {
+    if !settings.inject {
+        return None;
+    }
+    let now = LocalNow::current(settings.timezone.as_deref());
+    let mut line = now.system_line(settings.include_time);
+    let resolved = find_relative_dates(prompt, now.date);
+    if !resolved.is_empty() {
+        let list: Vec<String> = resolved
+            .iter()
+            .map(|(phrase, date)| {
+                format!("\"{}\" = {} {}", phrase, date.weekday_name(), date.iso())
+            })
+            .collect();
+        line.push_str(&format!(
+            " Dates in the user's message: {}.",
+            list.join(", ")
+        ));
+    }
+    Some(line)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A Friday
+    const TODAY: CivilDate = CivilDate {
+        year: 2026,
+        month: 10,
+        day: 16,
+    };
+
+    fn resolve(phrase: &str) -> Option<String> {
+        resolve_relative_date(phrase, TODAY).map(|d| d.iso())
+    }
+
+    #[test]
+    fn test_civil_date_round_trips() {
+        assert_eq!(TODAY.weekday_name(), "Friday");
+        assert_eq!(CivilDate::from_days(TODAY.to_days()), TODAY);
+        assert_eq!(CivilDate::from_days(0).iso(), "1970-01-01");
+        assert_eq!(
+            CivilDate::new(2024, 2, 29).unwrap().add_months(12).iso(),
+            "2025-02-28"
+        );
+        assert_eq!(CivilDate::new(2025, 2, 29), None);
+    }
+
+    #[test]
+    fn test_resolve_relative_dates() {
+        assert_eq!(resolve("tomorrow").as_deref(), Some("2026-10-17"));
+        assert_eq!(resolve("next Tuesday").as_deref(), Some("2026-10-20"));
+        assert_eq!(resolve("next friday").as_deref(), Some("2026-10-23"));
+        assert_eq!(resolve("on Friday").as_deref(), Some("2026-10-16"));
+        assert_eq!(resolve("in two weeks").as_deref(), Some("2026-10-30"));
+        assert_eq!(resolve("3 days from now").as_deref(), Some("2026-10-19"));
+        assert_eq!(resolve("next week").as_deref(), Some("2026-10-19"));
+        assert_eq!(resolve("end of the month").as_deref(), Some("2026-10-31"));
+        assert_eq!(resolve("2027-01-05").as_deref(), Some("2027-01-05"));
+        assert_eq!(resolve("someday"), None);
+    }
+
+    #[test]
+    fn test_find_relative_dates_in_prompt() {
+        let found =
+            find_relative_dates("Call Acme next Tuesday, then follow up in 2 weeks.", TODAY);
+        let phrases: Vec<(&str, String)> =
+            found.iter().map(|(p, d)| (p.as_str(), d.iso())).collect();
+        assert_eq!(
+            phrases,
+            vec![
+                ("next Tuesday", "2026-10-20".to_string()),
+                ("in 2 weeks", "2026-10-30".to_string())
+            ]
+        );
+        assert!(find_relative_dates("What is on the agenda today?", TODAY).is_empty());
+    }
+}
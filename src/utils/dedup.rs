@@ -0,0 +1,180 @@
+//! Duplicate detection for imported documents. Each document gets a hash of its
+//! normalized text, which matches exact copies whatever their whitespace or casing, and
+//! a SimHash over three-word shingles, whose bits mostly agree for near-copies such as
+//! a re-exported file or a lightly edited version.
+
+use crate::utils::hash::fnv1a;
+
+/// Share of SimHash bits two documents must agree on to count as near-duplicates
+pub const NEAR_DUPLICATE_SIMILARITY: f32 = 0.85;
+/// Shorter documents have too few shingles for a meaningful SimHash
+const MIN_SHINGLE_WORDS: usize = 24;
+const SHINGLE_WORDS: usize = 3;
+
+/// Content signature of one document
+#[derive(Clone, Debug, PartialEq)]
+pub struct Signature {
+    pub name: String,
+    pub content_hash: u64,
+    pub simhash: Option<u64>,
+}
+
+impl Signature {
+    pub fn of(name: &str, text: &str) -> Self {
+        let words = normalized_words(text);
+        Self {
+            name: name.to_string(),
+            content_hash: fnv1a(words.join(" ").as_bytes()),
+            simhash: (words.len() >= MIN_SHINGLE_WORDS).then(|| simhash(&words)),
+        }
+    }
+
+    /// Share of SimHash bits both documents agree on, when both are long enough
+    pub fn similarity(&self, other: &Signature) -> Option<f32> {
+        let distance = (self.simhash? ^ other.simhash?).count_ones();
+        Some(1.0 - distance as f32 / 64.0)
+    }
+}
+
+/// Document already in the knowledge base that an import repeats
+#[derive(Clone, Debug, PartialEq)]
+pub enum Duplicate {
+    /// Same text once normalized
+    Exact { name: String },
+    /// Mostly the same text
+    Near { name: String, similarity: f32 },
+}
+
+impl Duplicate {
+    pub fn name(&self) -> &str {
+        match self {
+            Duplicate::Exact { name } | Duplicate::Near { name, .. } => name,
+        }
+    }
+}
+
+/// Closest duplicate of `incoming` among `existing`. A different text under the same
+/// name is an update of that document, not a near-duplicate.
+pub fn find_duplicate(existing: &[Signature], incoming: &Signature) -> Option<Duplicate> {
+    if let Some(found) = existing
+        .iter()
+        .find(|s| s.content_hash == incoming.content_hash)
+    {
+        return Some(Duplicate::Exact {
+            name: found.name.clone(),
+        });
+    }
+    existing
+        .iter()
+        .filter(|s| s.name != incoming.name)
+        .filter_map(|s| Some((s, s.similarity(incoming)?)))
+        .filter(|(_, similarity)| *similarity >= NEAR_DUPLICATE_SIMILARITY)
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(s, similarity)| Duplicate::Near {
+            name: s.name.clone(),
+            similarity,
+        })
+}
+
+/// `existing` followed by the paragraphs of `incoming` it does not already contain
+pub fn merge_texts(existing: &str, incoming: &str) -> String {
+    let known: Vec<String> = paragraphs(existing).map(normalized).collect();
+    let added: Vec<&str> = paragraphs(incoming)
+        .filter(|p| !known.contains(&normalized(p)))
+        .collect();
+    if added.is_empty() {
+        return existing.trim().to_string();
+    }
+    format!("{}\n\n{}", existing.trim(), added.join("\n\n"))
+}
+
+fn paragraphs(text: &str) -> impl Iterator<Item = &str> {
+    text.split("\n\n").map(str::trim).filter(|p| !p.is_empty())
+}
+
+fn normalized(text: &str) -> String {
+    normalized_words(text).join(" ")
+}
+
+fn normalized_words(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+fn simhash(words: &[String]) -> u64 {
+    let mut weights = [0i32; 64];
+    for shingle in words.windows(SHINGLE_WORDS) {
+        let hash = fnv1a(shingle.join(" ").as_bytes());
+        for (bit, weight) in weights.iter_mut().enumerate() {
+            *weight += if hash >> bit & 1 == 1 { 1 } else { -1 };
+        }
+    }
+    weights
+        .iter()
+        .enumerate()
+        .filter(|(_, w)| **w > 0)
+        .fold(0, |acc, (bit, _)| acc | 1 << bit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POLICY: &str = "Refunds are issued within thirty days of purchase when the \
+        item is returned unused in its original packaging. Gift cards and sale items \
+        cannot be refunded, but they can be exchanged for store credit at any branch. \
+        Contact support with the order number to start a return.";
+
+    #[test]
+    fn test_exact_and_near_duplicates_are_found() {
+        let existing = vec![
+            Signature::of("policy.md", POLICY),
+            Signature::of("other.md", "Shipping takes three to five working days."),
+        ];
+
+        let copy = Signature::of("copy.txt", &POLICY.to_uppercase().replace(' ', "\n "));
+        assert_eq!(
+            find_duplicate(&existing, &copy),
+            Some(Duplicate::Exact {
+                name: "policy.md".to_string()
+            })
+        );
+
+        let edited = POLICY.replace("any branch", "any of our branches");
+        match find_duplicate(&existing, &Signature::of("edited.md", &edited)) {
+            Some(Duplicate::Near { name, similarity }) => {
+                assert_eq!(name, "policy.md");
+                assert!((NEAR_DUPLICATE_SIMILARITY..1.0).contains(&similarity));
+            }
+            other => panic!("expected a near-duplicate, got {:?}", other),
+        }
+        // A new version under the same name is an update
+        assert_eq!(
+            find_duplicate(&existing, &Signature::of("policy.md", &edited)),
+            None
+        );
+
+        let unrelated = "Our warehouse ships orders from Monday to Friday, and parcels \
+            are tracked from the moment they leave until they reach the customer's door \
+            or the nearest pickup point chosen at checkout time.";
+        assert_eq!(
+            find_duplicate(&existing, &Signature::of("shipping.md", unrelated)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_merge_keeps_only_new_paragraphs() {
+        let merged = merge_texts(
+            "# Policy\n\nFirst rule.\n\nSecond rule.",
+            "first  RULE.\n\nThird rule.\n",
+        );
+        assert_eq!(
+            merged,
+            "# Policy\n\nFirst rule.\n\nSecond rule.\n\nThird rule."
+        );
+        assert_eq!(merge_texts("Same.", "same"), "Same.");
+    }
+}
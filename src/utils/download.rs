@@ -0,0 +1,65 @@
+use crate::models::app::AppError;
+use wasm_bindgen::JsCast;
+
+/// Browser file download helpers
+pub struct DownloadUtils;
+
+impl DownloadUtils {
+    /// Trigger a download of `content` as `filename` via a temporary object URL
+    pub fn download_text(filename: &str, content: &str) -> Result<(), AppError> {
+        let blob_parts = js_sys::Array::new();
+        blob_parts.push(&wasm_bindgen::JsValue::from_str(content));
+        let blob = web_sys::Blob::new_with_str_sequence(&blob_parts)
+            .map_err(|e| AppError::InternalError(format!("Failed to create blob: {:?}", e)))?;
+        let url = web_sys::Url::create_object_url_with_blob(&blob).map_err(|e| {
+            AppError::InternalError(format!("Failed to create object URL: {:?}", e))
+        })?;
+
+        let result = Self::download_url(filename, &url);
+        let _ = web_sys::Url::revoke_object_url(&url);
+        result
+    }
+
+    /// Trigger a download of binary `content`, e.g. a zip archive
+    pub fn download_bytes(filename: &str, content: &[u8]) -> Result<(), AppError> {
+        let blob_parts = js_sys::Array::new();
+        blob_parts.push(&js_sys::Uint8Array::from(content));
+        let blob = web_sys::Blob::new_with_u8_array_sequence(&blob_parts)
+            .map_err(|e| AppError::InternalError(format!("Failed to create blob: {:?}", e)))?;
+        let url = web_sys::Url::create_object_url_with_blob(&blob).map_err(|e| {
+            AppError::InternalError(format!("Failed to create object URL: {:?}", e))
+        })?;
+
+        let result = Self::download_url(filename, &url);
+        let _ = web_sys::Url::revoke_object_url(&url);
+        result
+    }
+
+    /// Trigger a download of `blob`, e.g. a file read back from disk, without copying
+    /// its bytes into memory
+    pub fn download_blob(filename: &str, blob: &web_sys::Blob) -> Result<(), AppError> {
+        let url = web_sys::Url::create_object_url_with_blob(blob).map_err(|e| {
+            AppError::InternalError(format!("Failed to create object URL: {:?}", e))
+        })?;
+
+        let result = Self::download_url(filename, &url);
+        let _ = web_sys::Url::revoke_object_url(&url);
+        result
+    }
+
+    /// Trigger a download of an object or data URL as `filename`
+    pub fn download_url(filename: &str, url: &str) -> Result<(), AppError> {
+        let document = web_sys::window()
+            .and_then(|w| w.document())
+            .ok_or_else(|| AppError::InternalError("Document not available".to_string()))?;
+        let link = document
+            .create_element("a")
+            .ok()
+            .and_then(|el| el.dyn_into::<web_sys::HtmlAnchorElement>().ok())
+            .ok_or_else(|| AppError::InternalError("Failed to create link".to_string()))?;
+        link.set_href(url);
+        link.set_download(filename);
+        link.click();
+        Ok(())
+    }
+}
@@ -0,0 +1,141 @@
+//! EPUB books read as Markdown. An EPUB is a zip archive: `META-INF/container.xml`
+//! names the package file, whose spine lists the XHTML chapters in reading order.
+//! Chapters are reduced to text with their headings, so chunking by headings follows
+//! the book's sections.
+
+use crate::utils::web_page::readable_text;
+use crate::utils::zip::ZipArchive;
+use regex::Regex;
+
+/// Path of the package file named by `META-INF/container.xml`
+pub fn package_path(container_xml: &str) -> Option<String> {
+    let rootfile = Regex::new(r#"(?is)<rootfile\b[^>]*\bfull-path\s*=\s*["']([^"']+)["']"#)
+        .expect("valid rootfile regex");
+    rootfile.captures(container_xml).map(|c| c[1].to_string())
+}
+
+/// Title and chapter paths, in reading order, of the package file at `package_path`
+pub fn spine(package_xml: &str, package_path: &str) -> (Option<String>, Vec<String>) {
+    let attr = |tag: &str, name: &str| {
+        Regex::new(&format!(r#"(?is)\b{}\s*=\s*["']([^"']*)["']"#, name))
+            .expect("valid attribute regex")
+            .captures(tag)
+            .map(|c| c[1].to_string())
+    };
+    let title = Regex::new(r"(?is)<dc:title\b[^>]*>(.*?)</dc:title>")
+        .expect("valid title regex")
+        .captures(package_xml)
+        .map(|c| readable_text(&c[1]).text)
+        .filter(|t| !t.is_empty());
+
+    let items: Vec<(String, String)> = Regex::new(r"(?is)<item\b[^>]*>")
+        .expect("valid item regex")
+        .find_iter(package_xml)
+        .filter_map(|m| Some((attr(m.as_str(), "id")?, attr(m.as_str(), "href")?)))
+        .collect();
+    let base = package_path.rsplit_once('/').map_or("", |(dir, _)| dir);
+    let chapters = Regex::new(r"(?is)<itemref\b[^>]*>")
+        .expect("valid itemref regex")
+        .find_iter(package_xml)
+        .filter(|m| attr(m.as_str(), "linear").as_deref() != Some("no"))
+        .filter_map(|m| {
+            let idref = attr(m.as_str(), "idref")?;
+            let href = &items.iter().find(|(id, _)| *id == idref)?.1;
+            Some(resolve(base, href))
+        })
+        .collect();
+    (title, chapters)
+}
+
+/// Archive path of `href`, relative to the directory `base`
+fn resolve(base: &str, href: &str) -> String {
+    let href = href.split('#').next().unwrap_or(href).replace("%20", " ");
+    let mut parts: Vec<&str> = base.split('/').filter(|p| !p.is_empty()).collect();
+    for part in href.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            part => parts.push(part),
+        }
+    }
+    parts.join("/")
+}
+
+/// Markdown text of an EPUB book: its title, then every chapter with its headings
+pub async fn epub_to_markdown(bytes: &[u8]) -> Result<String, String> {
+    let zip = ZipArchive::parse(bytes).map_err(|_| "not a valid EPUB (zip) file".to_string())?;
+    let container = zip.read_text("META-INF/container.xml").await?;
+    let package = package_path(&container).ok_or("the book has no package file")?;
+    let (title, chapters) = spine(&zip.read_text(&package).await?, &package);
+    let mut sections = Vec::new();
+    if let Some(title) = title {
+        sections.push(format!("# {}", title));
+    }
+    for chapter in chapters {
+        let text = readable_text(&zip.read_text(&chapter).await?).text;
+        if !text.is_empty() {
+            sections.push(text);
+        }
+    }
+    if sections.len() <= 1 {
+        return Err("no readable chapters (DRM-protected book?)".to_string());
+    }
+    Ok(sections.join("\n\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::zip::ZipWriter;
+
+    /// Zip archive of `files`, stored without compression
+    fn stored_zip(files: &[(&str, &str)]) -> Vec<u8> {
+        let mut zip = ZipWriter::new();
+        for (name, content) in files {
+            zip.add(name, content.as_bytes()).unwrap();
+        }
+        zip.finish()
+    }
+
+    #[test]
+    fn test_spine_lists_chapters_in_reading_order() {
+        let container = r#"<container><rootfiles>
+            <rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/>
+            </rootfiles></container>"#;
+        let package = r#"<package><metadata><dc:title>Field Guide</dc:title></metadata>
+            <manifest>
+              <item id="cover" href="cover.xhtml" media-type="application/xhtml+xml"/>
+              <item href="text/ch2.xhtml" id="ch2" media-type="application/xhtml+xml"/>
+              <item id="ch1" href="text/ch1.xhtml#start" media-type="application/xhtml+xml"/>
+              <item id="css" href="../styles/book.css" media-type="text/css"/>
+            </manifest>
+            <spine><itemref idref="cover" linear="no"/><itemref idref="ch1"/><itemref idref="ch2"/></spine>
+            </package>"#;
+        let zip = stored_zip(&[
+            ("mimetype", "application/epub+zip"),
+            ("META-INF/container.xml", container),
+            ("OEBPS/content.opf", package),
+        ]);
+        let archive = ZipArchive::parse(&zip).unwrap();
+        let names: Vec<&str> = archive.entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(
+            names,
+            ["mimetype", "META-INF/container.xml", "OEBPS/content.opf"]
+        );
+        let entry = archive.find("META-INF/container.xml").unwrap();
+        assert_eq!(archive.data(entry).unwrap(), container.as_bytes());
+        assert!(ZipArchive::parse(b"PK not a zip").is_err());
+
+        let path = package_path(container).unwrap();
+        assert_eq!(path, "OEBPS/content.opf");
+        let (title, chapters) = spine(package, &path);
+        assert_eq!(title.as_deref(), Some("Field Guide"));
+        assert_eq!(chapters, ["OEBPS/text/ch1.xhtml", "OEBPS/text/ch2.xhtml"]);
+        assert_eq!(
+            resolve("OEBPS/text", "../styles/book.css"),
+            "OEBPS/styles/book.css"
+        );
+    }
+}
@@ -0,0 +1,362 @@
+use crate::models::{Message, MessageRole, SourceAttribution};
+use serde::{Deserialize, Serialize};
+
+/// How knowledge sources are cited in an exported conversation
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CitationStyle {
+    /// No citations, message text only
+    #[default]
+    None,
+    /// `[1]` markers and a numbered reference list
+    Plain,
+    /// Markdown footnotes (`[^1]`)
+    Markdown,
+    /// `[1]` markers, a reference list and BibTeX stubs for URL sources
+    Bibtex,
+}
+
+impl CitationStyle {
+    pub const ALL: [CitationStyle; 4] = [
+        CitationStyle::None,
+        CitationStyle::Plain,
+        CitationStyle::Markdown,
+        CitationStyle::Bibtex,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            CitationStyle::None => "No citations",
+            CitationStyle::Plain => "Plain",
+            CitationStyle::Markdown => "Markdown footnotes",
+            CitationStyle::Bibtex => "BibTeX",
+        }
+    }
+
+    pub fn key(&self) -> &'static str {
+        match self {
+            CitationStyle::None => "none",
+            CitationStyle::Plain => "plain",
+            CitationStyle::Markdown => "markdown",
+            CitationStyle::Bibtex => "bibtex",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Self {
+        Self::ALL
+            .into_iter()
+            .find(|s| s.key() == key)
+            .unwrap_or_default()
+    }
+
+    fn marker(&self, n: usize) -> String {
+        match self {
+            CitationStyle::Markdown => format!("[^{}]", n),
+            _ => format!("[{}]", n),
+        }
+    }
+}
+
+/// Conversation exporters
+pub struct ConversationExporter;
+
+impl ConversationExporter {
+    /// Render a conversation as Markdown. Sources attached to assistant messages are
+    /// numbered in order of first use and cited in `style`, followed by the passages
+    /// they quote so the export stands on its own.
+    pub fn to_markdown(
+        title: &str,
+        messages: &[Message],
+        exported_on: &str,
+        style: CitationStyle,
+        format_time: impl Fn(f64) -> String,
+    ) -> String {
+        let mut out = format!("# {}\n\n*Exported on: {}*\n\n---\n\n", title, exported_on);
+        let mut sources: Vec<SourceAttribution> = Vec::new();
+
+        for message in messages {
+            let role = match message.role {
+                MessageRole::User => "## 👤 You",
+                MessageRole::Assistant => "## 🤖 Assistant",
+                MessageRole::System => "## ⚙️ System",
+            };
+            let mut content = message.content.clone();
+            if style != CitationStyle::None && message.role == MessageRole::Assistant {
+                let markers: Vec<String> = message_sources(message)
+                    .iter()
+                    .map(|src| style.marker(citation_number(&mut sources, src)))
+                    .collect();
+                if !markers.is_empty() {
+                    content.push(' ');
+                    content.push_str(&markers.join(""));
+                }
+            }
+            out.push_str(&format!(
+                "{}\n*{}*\n\n{}\n\n",
+                role,
+                format_time(message.timestamp),
+                content
+            ));
+        }
+
+        if style != CitationStyle::None && !sources.is_empty() {
+            out.push_str(&Self::references(&sources, style));
+            out.push_str(&Self::sources_appendix(&sources));
+        }
+        out
+    }
+
+    /// "Sources" section quoting the cited passages of numbered `sources`, with their
+    /// anchors; empty when no passage text was kept
+    pub fn sources_appendix(sources: &[SourceAttribution]) -> String {
+        let entries: Vec<String> = sources
+            .iter()
+            .enumerate()
+            .filter_map(|(i, src)| {
+                let snippet = src
+                    .snippet
+                    .as_deref()
+                    .map(str::trim)
+                    .filter(|s| !s.is_empty())?;
+                let mut entry = format!("### [{}] {}\n\n", i + 1, src.title);
+                if let Some(anchor) = src.anchor() {
+                    entry.push_str(&format!("Anchor: `{}`\n\n", anchor));
+                }
+                for line in snippet.lines() {
+                    entry.push_str(format!("> {}", line).trim_end());
+                    entry.push('\n');
+                }
+                Some(entry)
+            })
+            .collect();
+        if entries.is_empty() {
+            return String::new();
+        }
+        format!("\n## Sources\n\n{}", entries.join("\n"))
+    }
+
+    /// Reference section for numbered `sources`
+    pub fn references(sources: &[SourceAttribution], style: CitationStyle) -> String {
+        let mut out = String::new();
+        match style {
+            CitationStyle::None => return out,
+            CitationStyle::Markdown => {
+                out.push_str("---\n\n");
+                for (i, src) in sources.iter().enumerate() {
+                    out.push_str(&format!("[^{}]: {}\n", i + 1, describe_source(src)));
+                }
+            }
+            CitationStyle::Plain | CitationStyle::Bibtex => {
+                out.push_str("## References\n\n");
+                for (i, src) in sources.iter().enumerate() {
+                    out.push_str(&format!("{}. {}\n", i + 1, describe_source(src)));
+                }
+            }
+        }
+        if style == CitationStyle::Bibtex {
+            let entries: Vec<String> = sources
+                .iter()
+                .enumerate()
+                .filter_map(|(i, src)| bibtex_entry(i + 1, src))
+                .collect();
+            if !entries.is_empty() {
+                out.push_str(&format!("\n```bibtex\n{}\n```\n", entries.join("\n\n")));
+            }
+        }
+        out
+    }
+}
+
+fn message_sources(message: &Message) -> &[SourceAttribution] {
+    message
+        .metadata
+        .as_ref()
+        .and_then(|m| m.provenance.as_deref())
+        .unwrap_or(&[])
+}
+
+/// 1-based number of `src`, registering it on first use
+fn citation_number(sources: &mut Vec<SourceAttribution>, src: &SourceAttribution) -> usize {
+    match sources.iter().position(|s| s.source_id == src.source_id) {
+        Some(i) => i + 1,
+        None => {
+            sources.push(src.clone());
+            sources.len()
+        }
+    }
+}
+
+fn source_url(src: &SourceAttribution) -> Option<&str> {
+    [src.source_id.as_str(), src.title.as_str()]
+        .into_iter()
+        .find(|s| s.starts_with("http://") || s.starts_with("https://"))
+        // Passages of a page added by URL are titled e.g. "https://… (part 2)"
+        .map(|s| s.split(char::is_whitespace).next().unwrap_or(s))
+}
+
+fn describe_source(src: &SourceAttribution) -> String {
+    let described = match source_url(src) {
+        Some(url) if url != src.title => format!("{} <{}>", src.title, url),
+        Some(url) => format!("<{}>", url),
+        None => src.title.clone(),
+    };
+    match src.anchor() {
+        Some(anchor) => format!("{} (`{}`)", described, anchor),
+        None => described,
+    }
+}
+
+fn bibtex_entry(n: usize, src: &SourceAttribution) -> Option<String> {
+    let url = source_url(src)?;
+    let title = src.title.replace(['{', '}'], "");
+    Some(format!(
+        "@misc{{source{},\n  title = {{{}}},\n  howpublished = {{\\url{{{}}}}},\n  note = {{Knowledge base source}}\n}}",
+        n, title, url
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::MessageMetadata;
+
+    fn assistant(content: &str, sources: &[(&str, &str)]) -> Message {
+        Message {
+            id: content.to_string(),
+            role: MessageRole::Assistant,
+            content: content.to_string(),
+            timestamp: 0.0,
+            metadata: Some(MessageMetadata {
+                tokens_used: None,
+                processing_time_ms: None,
+                model_used: None,
+                graphrag_enhanced: true,
+                error: None,
+                provenance: Some(
+                    sources
+                        .iter()
+                        .map(|(id, title)| SourceAttribution {
+                            source_id: id.to_string(),
+                            title: title.to_string(),
+                            confidence: 0.8,
+                            snippet: None,
+                        })
+                        .collect(),
+                ),
+                prompt_tokens: None,
+                completion_tokens: None,
+                decode_tokens_per_sec: None,
+                retry_reason: None,
+                retrieval_note: None,
+                temperature: None,
+                attempts: Vec::new(),
+                tools_used: Vec::new(),
+                structured: false,
+                latency: None,
+                confidence: None,
+                query_plan: None,
+                retrieval_trace: None,
+                compared: Vec::new(),
+            }),
+            pinned: false,
+        }
+    }
+
+    #[test]
+    fn test_markdown_footnotes_are_numbered_by_first_use() {
+        let messages = vec![
+            assistant(
+                "First.",
+                &[("a", "notes.md"), ("b", "https://example.com/x")],
+            ),
+            assistant("Second.", &[("b", "https://example.com/x")]),
+        ];
+        let md = ConversationExporter::to_markdown(
+            "Chat",
+            &messages,
+            "today",
+            CitationStyle::Markdown,
+            |_| "t".into(),
+        );
+        assert!(md.contains("First. [^1][^2]"));
+        assert!(md.contains("Second. [^2]"));
+        assert!(md.contains("[^1]: notes.md\n[^2]: <https://example.com/x>\n"));
+
+        let plain = ConversationExporter::to_markdown(
+            "Chat",
+            &messages,
+            "today",
+            CitationStyle::None,
+            |_| "t".into(),
+        );
+        assert!(!plain.contains("[1]") && !plain.contains("References"));
+    }
+
+    #[test]
+    fn test_bibtex_stubs_only_for_url_sources() {
+        let sources = vec![
+            SourceAttribution {
+                source_id: "doc-1".into(),
+                title: "notes.md".into(),
+                confidence: 0.5,
+                snippet: None,
+            },
+            SourceAttribution {
+                source_id: "https://example.com/x".into(),
+                title: "Example {page}".into(),
+                confidence: 0.5,
+                snippet: None,
+            },
+        ];
+        let refs = ConversationExporter::references(&sources, CitationStyle::Bibtex);
+        assert!(refs.contains("1. notes.md\n2. Example {page} <https://example.com/x>\n"));
+        assert!(refs.contains("@misc{source2,\n  title = {Example page},"));
+        assert!(!refs.contains("source1"));
+        assert_eq!(CitationStyle::from_key("bibtex"), CitationStyle::Bibtex);
+        assert_eq!(CitationStyle::from_key("bogus"), CitationStyle::None);
+    }
+
+    #[test]
+    fn test_cited_passages_are_anchored_and_quoted() {
+        let mut message = assistant("Refunds take 30 days.", &[]);
+        let source = SourceAttribution {
+            source_id: "1700000000000:refund-policy.md#chunk=2".into(),
+            title: "refund-policy.md (part 2)".into(),
+            confidence: 0.9,
+            snippet: Some("Refunds are issued within 30 days.\n\nContact support.".into()),
+        };
+        assert_eq!(source.anchor(), Some("refund-policy.md#chunk=2"));
+        if let Some(md) = message.metadata.as_mut() {
+            md.provenance = Some(vec![source]);
+        }
+        let md = ConversationExporter::to_markdown(
+            "Chat",
+            &[message],
+            "today",
+            CitationStyle::Plain,
+            |_| "t".into(),
+        );
+        assert!(md.contains("1. refund-policy.md (part 2) (`refund-policy.md#chunk=2`)\n"));
+        assert!(md.contains(
+            "## Sources\n\n### [1] refund-policy.md (part 2)\n\n\
+             Anchor: `refund-policy.md#chunk=2`\n\n\
+             > Refunds are issued within 30 days.\n>\n> Contact support.\n"
+        ));
+        // URLs are not passages
+        let url = SourceAttribution {
+            source_id: "https://example.com/x".into(),
+            title: "x".into(),
+            confidence: 0.5,
+            snippet: None,
+        };
+        assert_eq!(url.anchor(), None);
+        assert_eq!(ConversationExporter::sources_appendix(&[url]), "");
+        // Passages of a page added by URL link to the page
+        let page = SourceAttribution {
+            source_id: "1700000000000:https://example.com/refunds#chunk=2".into(),
+            title: "https://example.com/refunds (part 2)".into(),
+            confidence: 0.7,
+            snippet: None,
+        };
+        assert_eq!(source_url(&page), Some("https://example.com/refunds"));
+    }
+}
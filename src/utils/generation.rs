@@ -0,0 +1,350 @@
+use crate::models::app::AppError;
+use crate::models::generation::{
+    CompletionIssue, CompletionValidation, GenerationSettings, PostProcessor,
+};
+use crate::utils::storage::StorageUtils;
+use regex::Regex;
+use std::sync::LazyLock;
+
+pub const GENERATION_SETTINGS_KEY: &str = "generation_settings_v1";
+
+// Matched case-insensitively at the start / end of a completion
+const BOILERPLATE_OPENERS: &str =
+    r"(?i)^\s*(sure|certainly|of course|absolutely|great question|good question)\s*[!.,]\s*";
+const BOILERPLATE_CLOSERS: &str = r"(?i)^(i hope (this|that) helps|hope (this|that) helps|let me know if|feel free to|is there anything else)";
+const ROLE_PREFIX: &str = r"(?i)^\s*(assistant|ai|bot|model)\s*:\s*";
+
+static OPENER_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(BOILERPLATE_OPENERS).expect("valid opener regex"));
+static CLOSER_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(BOILERPLATE_CLOSERS).expect("valid closer regex"));
+static ROLE_PREFIX_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(ROLE_PREFIX).expect("valid role prefix regex"));
+
+/// Stop sequences and completion post-processing
+pub struct GenerationUtils;
+
+impl GenerationUtils {
+    pub fn load_settings() -> GenerationSettings {
+        StorageUtils::retrieve_local::<GenerationSettings>(GENERATION_SETTINGS_KEY)
+            .ok()
+            .flatten()
+            .unwrap_or_default()
+    }
+
+    pub fn save_settings(settings: &GenerationSettings) -> Result<(), AppError> {
+        StorageUtils::store_local(GENERATION_SETTINGS_KEY, settings)
+    }
+
+    /// One stop sequence per line; `\n` and `\t` escapes allow multi-line stops
+    pub fn parse_stop_sequences(input: &str) -> Vec<String> {
+        let mut out: Vec<String> = Vec::new();
+        for line in input.lines() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let stop = line.replace("\\n", "\n").replace("\\t", "\t");
+            if !out.contains(&stop) {
+                out.push(stop);
+            }
+        }
+        out
+    }
+
+    /// Inverse of `parse_stop_sequences` for editing
+    pub fn format_stop_sequences(stops: &[String]) -> String {
+        stops
+            .iter()
+            .map(|s| s.replace('\n', "\\n").replace('\t', "\\t"))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Cut the text at the earliest stop sequence
+    pub fn apply_stop_sequences(text: &str, stops: &[String]) -> String {
+        let cut = stops
+            .iter()
+            .filter(|s| !s.is_empty())
+            .filter_map(|s| text.find(s.as_str()))
+            .min();
+        match cut {
+            Some(idx) => text[..idx].to_string(),
+            None => text.to_string(),
+        }
+    }
+
+    pub fn validate_processor(processor: &PostProcessor) -> Result<(), AppError> {
+        if let PostProcessor::RegexReplace { pattern, .. } = processor {
+            if pattern.is_empty() {
+                return Err(AppError::validation("Pattern cannot be empty".to_string()));
+            }
+            Regex::new(pattern)
+                .map_err(|e| AppError::validation(format!("Invalid pattern: {}", e)))?;
+        }
+        Ok(())
+    }
+
+    /// Run the configured processors in order; invalid regexes are skipped
+    pub fn post_process(text: &str, processors: &[PostProcessor]) -> String {
+        let mut out = text.to_string();
+        for p in processors {
+            out = match p {
+                PostProcessor::TrimBoilerplate => Self::trim_boilerplate(&out),
+                PostProcessor::StripRolePrefixes => Self::strip_role_prefixes(&out),
+                PostProcessor::RegexReplace {
+                    pattern,
+                    replacement,
+                } => match Regex::new(pattern) {
+                    Ok(re) => re.replace_all(&out, replacement.as_str()).into_owned(),
+                    Err(e) => {
+                        log::warn!("Skipping invalid post-processor /{}/: {}", pattern, e);
+                        out
+                    }
+                },
+            };
+        }
+        out
+    }
+
+    /// Full pipeline applied to a finished completion
+    pub fn finalize(text: &str, settings: &GenerationSettings) -> String {
+        let stopped = Self::apply_stop_sequences(text, &settings.stop_sequences);
+        Self::post_process(&stopped, &settings.post_processors)
+            .trim()
+            .to_string()
+    }
+
+    /// First problem found in a finished completion, if any. `prompt` is the
+    /// user message it answers, used for the language check.
+    pub fn validate_completion(
+        text: &str,
+        prompt: &str,
+        rules: &CompletionValidation,
+    ) -> Option<CompletionIssue> {
+        if !rules.enabled {
+            return None;
+        }
+        let chars = text.chars().filter(|c| !c.is_whitespace()).count();
+        if chars == 0 {
+            return Some(CompletionIssue::Empty);
+        }
+        if chars < rules.min_chars {
+            return Some(CompletionIssue::TooShort { chars });
+        }
+        let ratio = Self::repetition_ratio(text);
+        if ratio > rules.max_repetition {
+            return Some(CompletionIssue::Repetitive { ratio });
+        }
+        if rules.check_language {
+            if let (Some(p), Some(r)) = (Self::dominant_script(prompt), Self::dominant_script(text))
+            {
+                if p != r {
+                    return Some(CompletionIssue::LanguageMismatch);
+                }
+            }
+        }
+        None
+    }
+
+    /// Share of word trigrams that repeat an earlier one (0.0 for short texts)
+    pub fn repetition_ratio(text: &str) -> f32 {
+        let words: Vec<String> = text.split_whitespace().map(|w| w.to_lowercase()).collect();
+        if words.len() < 12 {
+            return 0.0;
+        }
+        let total = words.len() - 2;
+        let unique: std::collections::HashSet<&[String]> = words.windows(3).collect();
+        1.0 - unique.len() as f32 / total as f32
+    }
+
+    /// Most frequent writing system among the letters, when there are enough of them
+    fn dominant_script(text: &str) -> Option<&'static str> {
+        let mut counts: std::collections::HashMap<&'static str, usize> =
+            std::collections::HashMap::new();
+        for c in text.chars().filter(|c| c.is_alphabetic()) {
+            let script = match c as u32 {
+                0x0000..=0x024F => "latin",
+                0x0370..=0x03FF => "greek",
+                0x0400..=0x04FF => "cyrillic",
+                0x0590..=0x05FF => "hebrew",
+                0x0600..=0x06FF => "arabic",
+                0x0900..=0x097F => "devanagari",
+                0x3040..=0x30FF | 0x3400..=0x9FFF | 0xAC00..=0xD7AF => "cjk",
+                _ => "other",
+            };
+            *counts.entry(script).or_insert(0) += 1;
+        }
+        let letters: usize = counts.values().sum();
+        if letters < 8 {
+            return None;
+        }
+        counts
+            .into_iter()
+            .max_by_key(|(_, n)| *n)
+            .filter(|(_, n)| *n * 2 > letters)
+            .map(|(s, _)| s)
+    }
+
+    fn trim_boilerplate(text: &str) -> String {
+        let mut body = text.trim().to_string();
+        while let Some(m) = OPENER_RE.find(&body) {
+            // Keep the filler when it is the whole answer
+            if m.end() >= body.len() {
+                break;
+            }
+            body = body[m.end()..].to_string();
+        }
+
+        // Drop a short closing paragraph that is only a pleasantry
+        if let Some(idx) = body.rfind("\n\n") {
+            let last = body[idx..].trim();
+            if last.len() <= 200 && CLOSER_RE.is_match(last) {
+                body.truncate(idx);
+            }
+        }
+        body.trim().to_string()
+    }
+
+    fn strip_role_prefixes(text: &str) -> String {
+        ROLE_PREFIX_RE.replace(text, "").into_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::generation::{InputLength, InputLimits};
+
+    #[test]
+    fn test_parse_and_format_stop_sequences() {
+        let stops = GenerationUtils::parse_stop_sequences("\\nUser:\n\n###\n###");
+        assert_eq!(stops, vec!["\nUser:".to_string(), "###".to_string()]);
+        assert_eq!(
+            GenerationUtils::format_stop_sequences(&stops),
+            "\\nUser:\n###"
+        );
+    }
+
+    #[test]
+    fn test_apply_stop_sequences_cuts_at_earliest() {
+        let stops = vec!["###".to_string(), "\nUser:".to_string()];
+        let text = "Answer here.\nUser: next question ### more";
+        assert_eq!(
+            GenerationUtils::apply_stop_sequences(text, &stops),
+            "Answer here."
+        );
+        assert_eq!(
+            GenerationUtils::apply_stop_sequences("plain", &stops),
+            "plain"
+        );
+    }
+
+    #[test]
+    fn test_trim_boilerplate_and_role_prefix() {
+        let text = "Assistant: Sure! Certainly. Paris is the capital.\n\nI hope this helps!";
+        let out = GenerationUtils::post_process(
+            text,
+            &[
+                PostProcessor::StripRolePrefixes,
+                PostProcessor::TrimBoilerplate,
+            ],
+        );
+        assert_eq!(out, "Paris is the capital.");
+        // Filler alone is kept rather than emptied
+        assert_eq!(
+            GenerationUtils::post_process("Sure!", &[PostProcessor::TrimBoilerplate]),
+            "Sure!"
+        );
+    }
+
+    #[test]
+    fn test_regex_replace_and_validation() {
+        let p = PostProcessor::RegexReplace {
+            pattern: r"(\d+) USD".to_string(),
+            replacement: "$$$1".to_string(),
+        };
+        assert_eq!(
+            GenerationUtils::post_process("costs 5 USD", &[p]),
+            "costs $5"
+        );
+
+        let bad = PostProcessor::RegexReplace {
+            pattern: "(".to_string(),
+            replacement: String::new(),
+        };
+        assert!(GenerationUtils::validate_processor(&bad).is_err());
+        assert_eq!(GenerationUtils::post_process("keep", &[bad]), "keep");
+    }
+
+    #[test]
+    fn test_with_conversation_stops_dedups() {
+        let settings = GenerationSettings {
+            stop_sequences: vec!["###".to_string()],
+            ..Default::default()
+        };
+        let merged = settings.with_conversation_stops(&[
+            "###".to_string(),
+            "END".to_string(),
+            String::new(),
+        ]);
+        assert_eq!(merged.stop_sequences, vec!["###", "END"]);
+    }
+
+    #[test]
+    fn test_input_limits_classify() {
+        let limits = InputLimits {
+            soft_chars: 100,
+            hard_chars: 200,
+        };
+        assert_eq!(limits.classify(10), InputLength::Ok);
+        assert_eq!(limits.classify(95), InputLength::NearSoft);
+        assert_eq!(limits.classify(150), InputLength::OverSoft);
+        assert_eq!(limits.classify(201), InputLength::OverHard);
+        // A hard limit below the soft one is raised to it
+        let inverted = InputLimits {
+            soft_chars: 100,
+            hard_chars: 50,
+        };
+        assert_eq!(inverted.classify(80), InputLength::NearSoft);
+        assert_eq!(inverted.classify(101), InputLength::OverHard);
+    }
+
+    #[test]
+    fn test_validate_completion() {
+        let rules = CompletionValidation::default();
+        let check = |t: &str, p: &str| GenerationUtils::validate_completion(t, p, &rules);
+        assert_eq!(check("  \n ", "hi"), Some(CompletionIssue::Empty));
+        assert_eq!(
+            check(".", "hi"),
+            Some(CompletionIssue::TooShort { chars: 1 })
+        );
+        assert!(matches!(
+            check(&"the cat sat ".repeat(10), "hi"),
+            Some(CompletionIssue::Repetitive { .. })
+        ));
+        assert_eq!(
+            check("Это ответ на ваш вопрос", "What is the capital of France?"),
+            Some(CompletionIssue::LanguageMismatch)
+        );
+        assert_eq!(
+            check("Paris is the capital.", "What is the capital of France?"),
+            None
+        );
+        // Short prompts are too ambiguous for a language check
+        assert_eq!(check("Привет, как дела?", "hi"), None);
+
+        let off = CompletionValidation {
+            enabled: false,
+            ..Default::default()
+        };
+        assert_eq!(GenerationUtils::validate_completion("", "hi", &off), None);
+    }
+
+    #[test]
+    fn test_retry_sampling_is_cooler_and_penalized() {
+        let settings = GenerationSettings::default().for_retry();
+        assert!(settings.sampling.temperature < 0.7);
+        assert!(settings.sampling.frequency_penalty > 0.0);
+        assert_eq!(settings.sampling.max_tokens, 512);
+    }
+}
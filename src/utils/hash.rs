@@ -0,0 +1,30 @@
+//! FNV-1a, the one content hash of the app: fingerprints, graph versions and
+//! near-duplicate shingles. Stable across sessions and builds, so hashes can be stored.
+
+/// Start value of a hash built piece by piece with `fnv1a_extend`
+pub const OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+const PRIME: u64 = 0x100000001b3;
+
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    fnv1a_extend(OFFSET_BASIS, bytes)
+}
+
+/// Continue `hash` with more bytes, for hashing a value piece by piece
+pub fn fnv1a_extend(hash: u64, bytes: &[u8]) -> u64 {
+    bytes
+        .iter()
+        .fold(hash, |h, b| (h ^ *b as u64).wrapping_mul(PRIME))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fnv1a_matches_the_reference_and_extends() {
+        assert_eq!(fnv1a(b""), 0xcbf29ce484222325);
+        assert_eq!(fnv1a(b"a"), 0xaf63dc4c8601ec8c);
+        assert_eq!(fnv1a_extend(fnv1a(b"foo"), b"bar"), fnv1a(b"foobar"));
+        assert_eq!(fnv1a_extend(OFFSET_BASIS, b"a"), fnv1a(b"a"));
+    }
+}
@@ -0,0 +1,280 @@
+//! Reading uploaded files with a bounded number of reads in flight. Large files are read
+//! in slices so progress is reported per byte and a read can stop between slices.
+
+use crate::models::app::AppError;
+use crate::utils::storage::StorageUtils;
+use serde::{Deserialize, Serialize};
+use wasm_bindgen_futures::JsFuture;
+
+pub const IMPORT_SETTINGS_KEY: &str = "import_settings_v1";
+pub const MAX_PARALLELISM: usize = 8;
+/// Files above this size are read slice by slice instead of in one call
+const STREAM_THRESHOLD_BYTES: f64 = 4.0 * 1024.0 * 1024.0;
+const SLICE_BYTES: f64 = 1024.0 * 1024.0;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ImportSettings {
+    /// Files read at the same time
+    pub parallelism: usize,
+    /// CORS proxy web pages are fetched through, e.g. `https://proxy.example/?url=`
+    /// or a template with `{url}`; `None` fetches pages directly
+    pub proxy_url: Option<String>,
+    /// Keep uploaded files as they were, for preview and download
+    pub keep_originals: bool,
+}
+
+impl Default for ImportSettings {
+    fn default() -> Self {
+        Self {
+            parallelism: 3,
+            proxy_url: None,
+            keep_originals: true,
+        }
+    }
+}
+
+impl ImportSettings {
+    pub fn load() -> Self {
+        StorageUtils::retrieve_local::<ImportSettings>(IMPORT_SETTINGS_KEY)
+            .ok()
+            .flatten()
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<(), AppError> {
+        StorageUtils::store_local(IMPORT_SETTINGS_KEY, self)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ImportStatus {
+    Queued,
+    Reading,
+    Loaded,
+    Failed(String),
+    Cancelled,
+    /// Not kept because the knowledge base already has it, with the reason
+    Skipped(String),
+}
+
+impl ImportStatus {
+    pub fn is_finished(&self) -> bool {
+        matches!(
+            self,
+            ImportStatus::Loaded
+                | ImportStatus::Failed(_)
+                | ImportStatus::Cancelled
+                | ImportStatus::Skipped(_)
+        )
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImportItem {
+    pub name: String,
+    pub size_bytes: f64,
+    pub read_bytes: f64,
+    pub status: ImportStatus,
+}
+
+/// Files of one upload and how far each has been read
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ImportQueue {
+    pub items: Vec<ImportItem>,
+}
+
+impl ImportQueue {
+    pub fn new(files: impl IntoIterator<Item = (String, f64)>) -> Self {
+        Self {
+            items: files
+                .into_iter()
+                .map(|(name, size_bytes)| ImportItem {
+                    name,
+                    size_bytes,
+                    read_bytes: 0.0,
+                    status: ImportStatus::Queued,
+                })
+                .collect(),
+        }
+    }
+
+    /// Next queued file for a free reader, marked as being read
+    pub fn claim(&mut self) -> Option<usize> {
+        let i = self
+            .items
+            .iter()
+            .position(|f| f.status == ImportStatus::Queued)?;
+        self.items[i].status = ImportStatus::Reading;
+        Some(i)
+    }
+
+    pub fn set_read(&mut self, i: usize, read_bytes: f64) {
+        if let Some(f) = self.items.get_mut(i) {
+            f.read_bytes = read_bytes.min(f.size_bytes);
+        }
+    }
+
+    /// Record the outcome of a read; a file cancelled meanwhile stays cancelled
+    pub fn finish(&mut self, i: usize, result: Result<(), String>) {
+        if let Some(f) = self.items.get_mut(i) {
+            if f.status == ImportStatus::Cancelled {
+                return;
+            }
+            f.read_bytes = f.size_bytes;
+            f.status = match result {
+                Ok(()) => ImportStatus::Loaded,
+                Err(e) => ImportStatus::Failed(e),
+            };
+        }
+    }
+
+    /// Leave out a read file, e.g. a duplicate; a file cancelled meanwhile stays cancelled
+    pub fn skip(&mut self, i: usize, reason: String) {
+        if let Some(f) = self.items.get_mut(i) {
+            if f.status != ImportStatus::Cancelled {
+                f.read_bytes = f.size_bytes;
+                f.status = ImportStatus::Skipped(reason);
+            }
+        }
+    }
+
+    /// Stop a queued or running read; what it read is discarded
+    pub fn cancel(&mut self, i: usize) {
+        if let Some(f) = self.items.get_mut(i) {
+            if !f.status.is_finished() {
+                f.status = ImportStatus::Cancelled;
+            }
+        }
+    }
+
+    pub fn cancel_all(&mut self) {
+        for i in 0..self.items.len() {
+            self.cancel(i);
+        }
+    }
+
+    pub fn is_cancelled(&self, i: usize) -> bool {
+        self.items
+            .get(i)
+            .is_some_and(|f| f.status == ImportStatus::Cancelled)
+    }
+
+    /// Share of the upload's bytes handled; finished files count in full
+    pub fn progress(&self) -> f32 {
+        let total: f64 = self.items.iter().map(|f| f.size_bytes).sum();
+        if total <= 0.0 {
+            let done = self.items.iter().filter(|f| f.status.is_finished()).count();
+            return done as f32 / self.items.len().max(1) as f32;
+        }
+        let done: f64 = self
+            .items
+            .iter()
+            .map(|f| {
+                if f.status.is_finished() {
+                    f.size_bytes
+                } else {
+                    f.read_bytes
+                }
+            })
+            .sum();
+        (done / total) as f32
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.items.iter().any(|f| !f.status.is_finished())
+    }
+
+    pub fn loaded(&self) -> usize {
+        self.items
+            .iter()
+            .filter(|f| f.status == ImportStatus::Loaded)
+            .count()
+    }
+
+    pub fn skipped(&self) -> usize {
+        self.items
+            .iter()
+            .filter(|f| matches!(f.status, ImportStatus::Skipped(_)))
+            .count()
+    }
+}
+
+/// Read the bytes of `file`; large files are read in slices, reporting the bytes read so
+/// far and stopping with an error once `cancelled` returns true
+pub async fn read_bytes(
+    file: &web_sys::File,
+    mut on_progress: impl FnMut(f64),
+    cancelled: impl Fn() -> bool,
+) -> Result<Vec<u8>, String> {
+    let size = file.size();
+    if size <= STREAM_THRESHOLD_BYTES {
+        let buffer = JsFuture::from(file.array_buffer())
+            .await
+            .map_err(|e| format!("{:?}", e))?;
+        on_progress(size);
+        return Ok(js_sys::Uint8Array::new(&buffer).to_vec());
+    }
+    let mut bytes = Vec::with_capacity(size as usize);
+    let mut start = 0.0;
+    while start < size {
+        if cancelled() {
+            return Err("cancelled".to_string());
+        }
+        let end = (start + SLICE_BYTES).min(size);
+        let slice = file
+            .slice_with_f64_and_f64(start, end)
+            .map_err(|e| format!("{:?}", e))?;
+        let buffer = JsFuture::from(slice.array_buffer())
+            .await
+            .map_err(|e| format!("{:?}", e))?;
+        bytes.extend(js_sys::Uint8Array::new(&buffer).to_vec());
+        start = end;
+        on_progress(start);
+    }
+    Ok(bytes)
+}
+
+/// Read `file` as UTF-8 text, in slices when it is large; invalid bytes are replaced
+/// as the browser's own text decoding does
+pub async fn read_text(
+    file: &web_sys::File,
+    on_progress: impl FnMut(f64),
+    cancelled: impl Fn() -> bool,
+) -> Result<String, String> {
+    let bytes = read_bytes(file, on_progress, cancelled).await?;
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_queue_claims_in_order_and_keeps_cancellations() {
+        let mut queue = ImportQueue::new(vec![
+            ("a.md".to_string(), 100.0),
+            ("b.md".to_string(), 300.0),
+            ("c.md".to_string(), 0.0),
+        ]);
+        assert_eq!(queue.claim(), Some(0));
+        assert_eq!(queue.claim(), Some(1));
+        queue.set_read(1, 150.0);
+        assert_eq!(queue.progress(), 0.375);
+
+        queue.cancel(1);
+        queue.finish(1, Ok(()));
+        assert!(queue.is_cancelled(1));
+        queue.finish(0, Err("bad".to_string()));
+        assert_eq!(queue.claim(), Some(2));
+        queue.skip(1, "duplicate".to_string());
+        assert!(queue.is_cancelled(1));
+        assert_eq!(queue.skipped(), 0);
+        assert!(queue.is_running());
+        queue.finish(2, Ok(()));
+        assert_eq!(queue.claim(), None);
+        assert!(!queue.is_running());
+        assert_eq!(queue.loaded(), 1);
+        assert_eq!(queue.progress(), 1.0);
+    }
+}
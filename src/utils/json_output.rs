@@ -0,0 +1,177 @@
+//! JSON reply mode. The engine constrains decoding to the schema; replies are still
+//! checked here (models without grammar support, truncated output) and a failed check
+//! gets one repair round with the error spelled out.
+
+use crate::models::{Message, MessageRole, OutputFormat};
+use serde_json::Value;
+
+/// Schemas offered in the reply format picker, as (label, schema)
+pub const SCHEMA_PRESETS: [(&str, &str); 3] = [
+    (
+        "Key facts",
+        r#"{"type": "object", "properties": {"facts": {"type": "array", "items": {"type": "object", "properties": {"fact": {"type": "string"}, "source": {"type": "string"}}, "required": ["fact"]}}}, "required": ["facts"]}"#,
+    ),
+    (
+        "Entities",
+        r#"{"type": "object", "properties": {"entities": {"type": "array", "items": {"type": "object", "properties": {"name": {"type": "string"}, "type": {"type": "string"}, "description": {"type": "string"}}, "required": ["name", "type"]}}}, "required": ["entities"]}"#,
+    ),
+    (
+        "Action items",
+        r#"{"type": "object", "properties": {"items": {"type": "array", "items": {"type": "object", "properties": {"task": {"type": "string"}, "owner": {"type": "string"}, "due": {"type": "string"}}, "required": ["task"]}}}, "required": ["items"]}"#,
+    ),
+];
+
+/// Parse schema source typed by the user; it must be a JSON object
+pub fn parse_schema(source: &str) -> Result<Value, String> {
+    let schema: Value =
+        serde_json::from_str(source).map_err(|e| format!("Schema is not valid JSON: {}", e))?;
+    if !schema.is_object() {
+        return Err("Schema must be a JSON object".to_string());
+    }
+    Ok(schema)
+}
+
+/// System message asking for JSON only, with the schema when there is one
+pub fn instruction(format: &OutputFormat) -> Option<String> {
+    match format {
+        OutputFormat::Text => None,
+        OutputFormat::Json { schema: None } => {
+            Some("Reply with a single JSON value and nothing else.".to_string())
+        }
+        OutputFormat::Json {
+            schema: Some(schema),
+        } => Some(format!(
+            "Reply with a single JSON value and nothing else. It must match this JSON schema:\n{}",
+            schema
+        )),
+    }
+}
+
+/// The JSON value in `text`, checked against the format's schema
+pub fn check(text: &str, format: &OutputFormat) -> Result<Value, String> {
+    let value: Value = serde_json::from_str(extract_json(text))
+        .map_err(|e| format!("Reply is not valid JSON: {}", e))?;
+    if let OutputFormat::Json {
+        schema: Some(schema),
+    } = format
+    {
+        validate(&value, &parse_schema(schema)?, "$")?;
+    }
+    Ok(value)
+}
+
+/// Message sent back after a failed check, asking for a corrected reply
+pub fn repair_message(error: &str) -> Message {
+    Message::new(
+        MessageRole::User,
+        format!(
+            "{}. Reply again with only the corrected JSON, no explanation.",
+            error
+        ),
+    )
+}
+
+/// `text` without a surrounding code fence or prose before the first bracket
+pub fn extract_json(text: &str) -> &str {
+    let text = text.trim();
+    let text = text
+        .strip_prefix("```json")
+        .or_else(|| text.strip_prefix("```"))
+        .map(|t| t.trim_end().trim_end_matches("```"))
+        .unwrap_or(text)
+        .trim();
+    match (text.find(['{', '[']), text.rfind(['}', ']'])) {
+        (Some(start), Some(end)) if start < end => &text[start..=end],
+        _ => text,
+    }
+}
+
+/// Check `value` against the JSON Schema keywords the presets and most hand-written
+/// schemas use: `type`, `enum`, `properties`, `required` and `items`
+pub fn validate(value: &Value, schema: &Value, path: &str) -> Result<(), String> {
+    if let Some(expected) = schema.get("type") {
+        let allowed: Vec<&str> = match expected {
+            Value::String(t) => vec![t.as_str()],
+            Value::Array(ts) => ts.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+        if !allowed.is_empty() && !allowed.iter().any(|t| has_type(value, t)) {
+            return Err(format!("{}: expected {}", path, allowed.join(" or ")));
+        }
+    }
+    if let Some(Value::Array(options)) = schema.get("enum") {
+        if !options.contains(value) {
+            return Err(format!("{}: not one of the allowed values", path));
+        }
+    }
+    if let Value::Object(map) = value {
+        if let Some(Value::Array(required)) = schema.get("required") {
+            if let Some(missing) = required
+                .iter()
+                .filter_map(Value::as_str)
+                .find(|k| !map.contains_key(*k))
+            {
+                return Err(format!("{}: missing \"{}\"", path, missing));
+            }
+        }
+        if let Some(Value::Object(properties)) = schema.get("properties") {
+            for (key, sub) in properties {
+                if let Some(v) = map.get(key) {
+                    validate(v, sub, &format!("{}.{}", path, key))?;
+                }
+            }
+        }
+    }
+    if let (Value::Array(items), Some(item_schema)) = (value, schema.get("items")) {
+        for (i, item) in items.iter().enumerate() {
+            validate(item, item_schema, &format!("{}[{}]", path, i))?;
+        }
+    }
+    Ok(())
+}
+
+fn has_type(value: &Value, ty: &str) -> bool {
+    match ty {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_validates_against_schema() {
+        let format = OutputFormat::Json {
+            schema: Some(SCHEMA_PRESETS[2].1.to_string()),
+        };
+        let ok = "```json\n{\"items\": [{\"task\": \"Send invoice\", \"owner\": \"Ana\"}]}\n```";
+        assert!(check(ok, &format).is_ok());
+        let missing = r#"{"items": [{"owner": "Ana"}]}"#;
+        assert_eq!(
+            check(missing, &format).unwrap_err(),
+            "$.items[0]: missing \"task\""
+        );
+        let wrong_type = r#"{"items": "none"}"#;
+        assert_eq!(
+            check(wrong_type, &format).unwrap_err(),
+            "$.items: expected array"
+        );
+        assert!(check("not json", &OutputFormat::Json { schema: None }).is_err());
+    }
+
+    #[test]
+    fn test_presets_are_valid_schemas() {
+        for (label, schema) in SCHEMA_PRESETS {
+            assert!(parse_schema(schema).is_ok(), "{}", label);
+        }
+        assert!(parse_schema("[1, 2]").is_err());
+    }
+}
@@ -0,0 +1,92 @@
+use crate::models::chat::LatencyBreakdown;
+
+/// Stage of a chat request a span of time is charged to
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LatencyStage {
+    /// Knowledge base search
+    Retrieval,
+    /// Prompt assembly and context fitting before the model sees the request
+    Queue,
+    /// From sending the prompt to the first streamed token
+    FirstToken,
+    /// From the first token to the finished reply, including tool rounds and retries
+    Generation,
+}
+
+/// One timed span of a request
+#[derive(Clone, Debug, PartialEq)]
+pub struct TraceSpan {
+    pub stage: LatencyStage,
+    pub start_ms: f64,
+    pub end_ms: f64,
+}
+
+/// Spans captured while one reply is produced
+#[derive(Clone, Debug, Default)]
+pub struct RequestTrace {
+    spans: Vec<TraceSpan>,
+}
+
+impl RequestTrace {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Charge `start_ms..end_ms` to `stage`; empty or reversed spans are ignored
+    pub fn record(&mut self, stage: LatencyStage, start_ms: f64, end_ms: f64) {
+        if end_ms > start_ms {
+            self.spans.push(TraceSpan {
+                stage,
+                start_ms,
+                end_ms,
+            });
+        }
+    }
+
+    pub fn spans(&self) -> &[TraceSpan] {
+        &self.spans
+    }
+
+    /// Milliseconds per stage, summed over its spans
+    pub fn breakdown(&self) -> LatencyBreakdown {
+        let total = |stage: LatencyStage| {
+            self.spans
+                .iter()
+                .filter(|s| s.stage == stage)
+                .map(|s| s.end_ms - s.start_ms)
+                .sum::<f64>()
+                .round() as u32
+        };
+        LatencyBreakdown {
+            retrieval_ms: total(LatencyStage::Retrieval),
+            queue_ms: total(LatencyStage::Queue),
+            first_token_ms: total(LatencyStage::FirstToken),
+            generation_ms: total(LatencyStage::Generation),
+            prefill_reused_tokens: 0,
+            prefill_saved_ms: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_breakdown_sums_spans_per_stage() {
+        let mut trace = RequestTrace::new();
+        trace.record(LatencyStage::Queue, 0.0, 5.0);
+        trace.record(LatencyStage::Retrieval, 5.0, 125.0);
+        trace.record(LatencyStage::Queue, 125.0, 140.0);
+        trace.record(LatencyStage::FirstToken, 140.0, 400.0);
+        trace.record(LatencyStage::Generation, 400.0, 400.0);
+        assert_eq!(trace.spans().len(), 4);
+
+        let breakdown = trace.breakdown();
+        assert_eq!(breakdown.queue_ms, 20);
+        assert_eq!(breakdown.retrieval_ms, 120);
+        assert_eq!(breakdown.first_token_ms, 260);
+        assert_eq!(breakdown.generation_ms, 0);
+        assert_eq!(breakdown.total_ms(), 400);
+    }
+}
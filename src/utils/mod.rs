@@ -1,7 +1,40 @@
+pub mod answer_style;
+pub mod brainstorm;
+pub mod capabilities;
+pub mod code;
+pub mod compute_usage;
+pub mod confidence;
+pub mod context_window;
+pub mod datetime;
+pub mod dedup;
+pub mod download;
+pub mod epub;
 pub mod error_handling;
+pub mod exporters;
 pub mod format;
+pub mod generation;
 pub mod graphrag;
+pub mod hash;
 pub mod icons;
+pub mod import_queue;
+pub mod json_output;
+pub mod latency;
+pub mod notifications;
+pub mod pdf;
+pub mod prefill;
+pub mod pwa;
+pub mod scenario;
+pub mod settings_bundle;
+pub mod speech;
 pub mod storage;
+pub mod structured;
+pub mod tables;
+pub mod tasks;
+pub mod threads;
+pub mod titling;
+pub mod tools;
+pub mod tts;
 pub mod validation;
+pub mod web_page;
 pub mod webllm;
+pub mod zip;
@@ -0,0 +1,183 @@
+use crate::models::app::AppResult;
+use crate::utils::storage::StorageUtils;
+use serde::{Deserialize, Serialize};
+use wasm_bindgen_futures::JsFuture;
+
+/// localStorage key of the notification preferences
+pub const NOTIFICATION_SETTINGS_KEY: &str = "notification_settings_v1";
+
+/// User preferences for completion notifications
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotificationSettings {
+    pub enabled: bool,
+    /// Skip notifications while the app tab is visible
+    pub only_when_hidden: bool,
+}
+
+impl Default for NotificationSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            only_when_hidden: true,
+        }
+    }
+}
+
+/// Long-running work that reports completion
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LongTask {
+    ModelInit,
+    Reindex,
+    Reembed,
+    CoverageReport,
+    Backup,
+}
+
+impl LongTask {
+    pub fn label(&self) -> &'static str {
+        match self {
+            LongTask::ModelInit => "Model download",
+            LongTask::Reindex => "Knowledge reindex",
+            LongTask::Reembed => "Re-embedding",
+            LongTask::CoverageReport => "Coverage report",
+            LongTask::Backup => "Backup",
+        }
+    }
+
+    /// Notification tag; a newer notification replaces the previous one of the same task
+    fn tag(&self) -> &'static str {
+        match self {
+            LongTask::ModelInit => "task-model-init",
+            LongTask::Reindex => "task-reindex",
+            LongTask::Reembed => "task-reembed",
+            LongTask::CoverageReport => "task-coverage-report",
+            LongTask::Backup => "task-backup",
+        }
+    }
+}
+
+/// Browser notification permission
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PermissionState {
+    Granted,
+    Denied,
+    /// Not asked yet
+    Prompt,
+    Unsupported,
+}
+
+impl PermissionState {
+    pub fn label(&self) -> &'static str {
+        match self {
+            PermissionState::Granted => "Allowed",
+            PermissionState::Denied => "Blocked in browser settings",
+            PermissionState::Prompt => "Not requested yet",
+            PermissionState::Unsupported => "Not supported by this browser",
+        }
+    }
+}
+
+/// Web Notifications for finished long-running tasks
+pub struct NotificationUtils;
+
+impl NotificationUtils {
+    pub fn load_settings() -> NotificationSettings {
+        StorageUtils::retrieve_local(NOTIFICATION_SETTINGS_KEY)
+            .ok()
+            .flatten()
+            .unwrap_or_default()
+    }
+
+    pub fn save_settings(settings: &NotificationSettings) -> AppResult<()> {
+        StorageUtils::store_local(NOTIFICATION_SETTINGS_KEY, settings)
+    }
+
+    pub fn permission() -> PermissionState {
+        if !Self::supported() {
+            return PermissionState::Unsupported;
+        }
+        match web_sys::Notification::permission() {
+            web_sys::NotificationPermission::Granted => PermissionState::Granted,
+            web_sys::NotificationPermission::Denied => PermissionState::Denied,
+            _ => PermissionState::Prompt,
+        }
+    }
+
+    /// Show the browser permission prompt (must run from a user gesture)
+    pub async fn request_permission() -> PermissionState {
+        if !Self::supported() {
+            return PermissionState::Unsupported;
+        }
+        if let Ok(promise) = web_sys::Notification::request_permission() {
+            if let Err(e) = JsFuture::from(promise).await {
+                log::warn!("Notification permission request failed: {:?}", e);
+            }
+        }
+        Self::permission()
+    }
+
+    /// Notify that `task` finished with `Ok(detail)` or failed with `Err(detail)`.
+    /// No-op unless enabled, permitted and (optionally) the tab is hidden.
+    pub fn task_finished(task: LongTask, outcome: Result<&str, &str>) {
+        let settings = Self::load_settings();
+        if !settings.enabled || Self::permission() != PermissionState::Granted {
+            return;
+        }
+        if settings.only_when_hidden && !Self::tab_hidden() {
+            return;
+        }
+        let (title, body) = task_message(task, outcome);
+        Self::show(&title, &body, task.tag());
+    }
+
+    fn show(title: &str, body: &str, tag: &str) {
+        let options = web_sys::NotificationOptions::new();
+        options.set_body(body);
+        options.set_tag(tag);
+        if let Err(e) = web_sys::Notification::new_with_options(title, &options) {
+            log::warn!("Notification failed: {:?}", e);
+        }
+    }
+
+    fn supported() -> bool {
+        js_sys::Reflect::has(&js_sys::global(), &"Notification".into()).unwrap_or(false)
+    }
+
+    fn tab_hidden() -> bool {
+        web_sys::window()
+            .and_then(|w| w.document())
+            .map(|d| d.hidden())
+            .unwrap_or(false)
+    }
+}
+
+/// Title and body of a completion notification
+pub fn task_message(task: LongTask, outcome: Result<&str, &str>) -> (String, String) {
+    match outcome {
+        Ok(detail) => (format!("{} finished", task.label()), detail.to_string()),
+        Err(detail) => (format!("{} failed", task.label()), detail.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_task_message() {
+        assert_eq!(
+            task_message(LongTask::Reindex, Ok("Indexed 3 document(s)")),
+            (
+                "Knowledge reindex finished".to_string(),
+                "Indexed 3 document(s)".to_string()
+            )
+        );
+        assert_eq!(
+            task_message(LongTask::ModelInit, Err("Out of memory")).0,
+            "Model download failed"
+        );
+        let settings: NotificationSettings = serde_json::from_str("{}").unwrap();
+        assert_eq!(settings, NotificationSettings::default());
+    }
+}
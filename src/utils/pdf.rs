@@ -0,0 +1,161 @@
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::JsFuture;
+
+/// Marker line inserted before each page's text in the upload buffer
+const PAGE_MARKER_PREFIX: &str = "[[page ";
+const PAGE_MARKER_SUFFIX: &str = "]]";
+
+/// Text extraction through pdf.js (`window.pdfjsLib`, loaded in index.html)
+pub struct PdfUtils;
+
+impl PdfUtils {
+    /// Extract the text of every page, in page order
+    pub async fn extract_pages(data: &js_sys::Uint8Array) -> Result<Vec<String>, JsValue> {
+        let lib = js_sys::Reflect::get(&js_sys::global(), &"pdfjsLib".into())?;
+        if lib.is_undefined() {
+            return Err(JsValue::from_str("pdf.js is not loaded"));
+        }
+        let params = js_sys::Object::new();
+        js_sys::Reflect::set(&params, &"data".into(), data)?;
+        let task = call_method(&lib, "getDocument", &[params.into()])?;
+        let pdf = await_promise(js_sys::Reflect::get(&task, &"promise".into())?).await?;
+
+        let num_pages = js_sys::Reflect::get(&pdf, &"numPages".into())?
+            .as_f64()
+            .unwrap_or(0.0) as u32;
+        let mut pages = Vec::with_capacity(num_pages as usize);
+        for n in 1..=num_pages {
+            let page = await_promise(call_method(&pdf, "getPage", &[n.into()])?).await?;
+            let content = await_promise(call_method(&page, "getTextContent", &[])?).await?;
+            let items: js_sys::Array = js_sys::Reflect::get(&content, &"items".into())?.into();
+            let mut text = String::new();
+            for item in items.iter() {
+                if let Some(s) = js_sys::Reflect::get(&item, &"str".into())
+                    .ok()
+                    .and_then(|v| v.as_string())
+                {
+                    text.push_str(&s);
+                }
+                let eol = js_sys::Reflect::get(&item, &"hasEOL".into())
+                    .map(|v| v.is_truthy())
+                    .unwrap_or(false);
+                text.push(if eol { '\n' } else { ' ' });
+            }
+            pages.push(normalize_page_text(&text));
+        }
+        Ok(pages)
+    }
+
+    /// Join pages into buffer content with a marker line before each page
+    pub fn format_pages(pages: &[String]) -> String {
+        pages
+            .iter()
+            .enumerate()
+            .filter(|(_, text)| !text.trim().is_empty())
+            .map(|(i, text)| {
+                format!(
+                    "{}{}{}\n{}",
+                    PAGE_MARKER_PREFIX,
+                    i + 1,
+                    PAGE_MARKER_SUFFIX,
+                    text.trim()
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+
+    /// Inverse of `format_pages`: (page number, text) pairs.
+    /// Content without markers is returned as a single page 1.
+    pub fn split_pages(content: &str) -> Vec<(u32, String)> {
+        let mut pages: Vec<(u32, String)> = Vec::new();
+        let mut current: Option<(u32, String)> = None;
+        for line in content.lines() {
+            if let Some(n) = parse_marker(line) {
+                if let Some(p) = current.take() {
+                    pages.push(p);
+                }
+                current = Some((n, String::new()));
+                continue;
+            }
+            let slot = current.get_or_insert_with(|| (1, String::new()));
+            if !slot.1.is_empty() {
+                slot.1.push('\n');
+            }
+            slot.1.push_str(line);
+        }
+        pages.extend(current);
+        pages
+            .into_iter()
+            .map(|(n, t)| (n, t.trim().to_string()))
+            .filter(|(_, t)| !t.is_empty())
+            .collect()
+    }
+}
+
+fn parse_marker(line: &str) -> Option<u32> {
+    line.trim()
+        .strip_prefix(PAGE_MARKER_PREFIX)?
+        .strip_suffix(PAGE_MARKER_SUFFIX)?
+        .parse()
+        .ok()
+}
+
+/// Collapse runs of spaces and blank lines left by positioned text items
+fn normalize_page_text(text: &str) -> String {
+    text.lines()
+        .map(|l| l.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|l| !l.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn call_method(target: &JsValue, name: &str, args: &[JsValue]) -> Result<JsValue, JsValue> {
+    let f: js_sys::Function = js_sys::Reflect::get(target, &name.into())?.into();
+    let array = args.iter().collect::<js_sys::Array>();
+    js_sys::Reflect::apply(&f, target, &array)
+}
+
+async fn await_promise(value: JsValue) -> Result<JsValue, JsValue> {
+    JsFuture::from(js_sys::Promise::from(value)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_and_split_pages() {
+        let pages = vec![
+            "Intro text".to_string(),
+            "   ".to_string(),
+            "Results\nmore".to_string(),
+        ];
+        let content = PdfUtils::format_pages(&pages);
+        assert_eq!(
+            content,
+            "[[page 1]]\nIntro text\n\n[[page 3]]\nResults\nmore"
+        );
+        assert_eq!(
+            PdfUtils::split_pages(&content),
+            vec![
+                (1, "Intro text".to_string()),
+                (3, "Results\nmore".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_split_pages_without_markers() {
+        assert_eq!(
+            PdfUtils::split_pages("plain text"),
+            vec![(1, "plain text".to_string())]
+        );
+        assert!(PdfUtils::split_pages("").is_empty());
+    }
+
+    #[test]
+    fn test_normalize_page_text() {
+        assert_eq!(normalize_page_text("a   b \n\n  c "), "a b\nc");
+    }
+}
@@ -0,0 +1,164 @@
+//! Prompt prefill reuse across chat turns. WebLLM keeps the KV cache of its last
+//! conversation and only prefills the new message when a request repeats that
+//! conversation exactly; any other request is prefilled from scratch. Replies are
+//! post-processed before they are stored, so the history sent back would never
+//! match: the cache remembers the raw reply and puts it back in its place.
+
+use crate::models::{Message, MessageRole};
+
+/// What the engine holds after a completion
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PrefillCache {
+    /// Messages of the request, in order
+    pub turns: Vec<(MessageRole, String)>,
+    /// The reply as generated, which is what the engine's cache contains
+    pub raw_reply: String,
+    /// The reply as returned to the caller, which is what comes back in history
+    pub stored_reply: String,
+    /// Tokens in the engine's context: request and reply
+    pub context_tokens: u32,
+}
+
+/// How a request relates to the cached conversation
+#[derive(Clone, Debug, PartialEq)]
+pub enum PrefillReuse {
+    /// Nothing cached, or the cache was dropped by another use of the engine
+    Cold,
+    /// History continues the cached conversation; only the new turn is prefilled
+    Reused { tokens: u32 },
+    /// History differs from the cached conversation, which is prefilled again
+    Invalidated { reason: &'static str },
+}
+
+impl PrefillReuse {
+    pub fn reused_tokens(&self) -> u32 {
+        match self {
+            PrefillReuse::Reused { tokens } => *tokens,
+            _ => 0,
+        }
+    }
+}
+
+impl PrefillCache {
+    pub fn new(sent: &[Message], raw_reply: &str, stored_reply: &str) -> Self {
+        Self {
+            turns: sent
+                .iter()
+                .map(|m| (m.role.clone(), m.content.clone()))
+                .collect(),
+            raw_reply: raw_reply.to_string(),
+            stored_reply: stored_reply.to_string(),
+            context_tokens: 0,
+        }
+    }
+
+    /// Compare `messages`, the next request, with the cached conversation. When the
+    /// request continues it, the stored reply in it is swapped for the raw one so
+    /// the engine recognizes its own conversation.
+    pub fn prepare(&self, messages: &mut [Message]) -> PrefillReuse {
+        let Some((_, history)) = messages.split_last_mut() else {
+            return PrefillReuse::Cold;
+        };
+        let expected = self
+            .turns
+            .iter()
+            .map(|(role, content)| (role, content.as_str()))
+            .chain(std::iter::once((
+                &MessageRole::Assistant,
+                self.stored_reply.as_str(),
+            )));
+        let mut count = 0;
+        for (i, (role, content)) in expected.enumerate() {
+            let same = history
+                .get(i)
+                .is_some_and(|m| &m.role == role && m.content == content);
+            if !same {
+                let system_changed = *role == MessageRole::System
+                    || history
+                        .get(i)
+                        .is_some_and(|m| m.role == MessageRole::System);
+                return PrefillReuse::Invalidated {
+                    reason: if system_changed {
+                        "system prompt changed"
+                    } else {
+                        "history changed"
+                    },
+                };
+            }
+            count += 1;
+        }
+        if history.len() != count {
+            return PrefillReuse::Invalidated {
+                reason: "history changed",
+            };
+        }
+        history[count - 1].content = self.raw_reply.clone();
+        PrefillReuse::Reused {
+            tokens: self.context_tokens,
+        }
+    }
+}
+
+/// Milliseconds of prefill skipped by reusing `tokens`, at the engine's prefill speed
+pub fn saved_ms(tokens: u32, prefill_tokens_per_sec: Option<f32>) -> Option<u32> {
+    let speed = prefill_tokens_per_sec.filter(|s| *s > 0.0)?;
+    (tokens > 0).then(|| (tokens as f32 * 1000.0 / speed).round() as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn msg(role: MessageRole, content: &str) -> Message {
+        Message::new(role, content.to_string())
+    }
+
+    #[test]
+    fn test_follow_up_turns_reuse_the_cached_conversation() {
+        let sent = vec![
+            msg(MessageRole::System, "Be brief."),
+            msg(MessageRole::User, "Hi"),
+        ];
+        let mut cache = PrefillCache::new(&sent, "Hello!\n\n", "Hello!");
+        cache.context_tokens = 40;
+
+        let mut next = sent.clone();
+        next.push(msg(MessageRole::Assistant, "Hello!"));
+        next.push(msg(MessageRole::User, "What is RAG?"));
+        assert_eq!(
+            cache.prepare(&mut next),
+            PrefillReuse::Reused { tokens: 40 }
+        );
+        // The engine gets back exactly what it generated
+        assert_eq!(next[2].content, "Hello!\n\n");
+        assert_eq!(saved_ms(40, Some(200.0)), Some(200));
+        assert_eq!(saved_ms(40, None), None);
+
+        let mut grounded = next.clone();
+        grounded[0].content = "Be brief. Top snippets: ...".to_string();
+        assert_eq!(
+            cache.prepare(&mut grounded),
+            PrefillReuse::Invalidated {
+                reason: "system prompt changed"
+            }
+        );
+
+        let mut edited = vec![
+            msg(MessageRole::System, "Be brief."),
+            msg(MessageRole::User, "Hey"),
+            msg(MessageRole::Assistant, "Hello!"),
+            msg(MessageRole::User, "What is RAG?"),
+        ];
+        assert_eq!(
+            cache.prepare(&mut edited),
+            PrefillReuse::Invalidated {
+                reason: "history changed"
+            }
+        );
+        assert_eq!(edited[2].content, "Hello!");
+
+        // A retry of the same turn does not continue the conversation either
+        let mut retry = sent.clone();
+        assert_eq!(cache.prepare(&mut retry).reused_tokens(), 0);
+    }
+}
@@ -0,0 +1,199 @@
+//! Installable, offline app. The service worker (`public/sw.js`) precaches the app
+//! shell; this side registers it, reports when a new release is waiting to take over,
+//! and relays the browser's install prompt and connection changes.
+
+use gloo_timers::callback::Interval;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{ServiceWorkerRegistration, ServiceWorkerState};
+
+const SERVICE_WORKER: &str = "sw.js";
+/// How often an open tab looks for a new release
+const UPDATE_CHECK_MS: u32 = 60 * 60 * 1000;
+
+thread_local! {
+    static REGISTRATION: RefCell<Option<ServiceWorkerRegistration>> = const { RefCell::new(None) };
+    // Deferred `beforeinstallprompt` event, shown when the user asks to install
+    static INSTALL_PROMPT: RefCell<Option<web_sys::Event>> = const { RefCell::new(None) };
+    // Set once the user accepted an update, so the page reloads into it
+    static RELOAD_ON_CHANGE: Cell<bool> = const { Cell::new(false) };
+    static UPDATE_CHECKS: RefCell<Option<Interval>> = const { RefCell::new(None) };
+}
+
+/// Script URL of the service worker; the version makes every release install anew
+pub fn service_worker_url(version: &str) -> String {
+    format!("{}?v={}", SERVICE_WORKER, version)
+}
+
+pub fn is_supported() -> bool {
+    web_sys::window().is_some_and(|w| {
+        js_sys::Reflect::has(&w.navigator(), &JsValue::from_str("serviceWorker")).unwrap_or(false)
+    })
+}
+
+/// Register the service worker. `on_update` runs when a new release is installed and
+/// waits for `apply_update`. Debug builds skip it: the dev server reloads by itself
+/// and cached bundles would hide changes.
+pub fn register(on_update: impl Fn() + 'static) {
+    if cfg!(debug_assertions) || !is_supported() {
+        return;
+    }
+    let Some(container) = web_sys::window().map(|w| w.navigator().service_worker()) else {
+        return;
+    };
+    let on_update: Rc<dyn Fn()> = Rc::new(on_update);
+    wasm_bindgen_futures::spawn_local(async move {
+        let url = service_worker_url(env!("CARGO_PKG_VERSION"));
+        let registration: ServiceWorkerRegistration =
+            match JsFuture::from(container.register(&url)).await {
+                Ok(registration) => registration.unchecked_into(),
+                Err(e) => {
+                    log::warn!("The app will not work offline: {:?}", e);
+                    return;
+                }
+            };
+        // A first install controls no page yet, so there is nothing to update
+        let controlled = {
+            let container = container.clone();
+            move || container.controller().is_some()
+        };
+        // Installed during an earlier visit and still waiting
+        if registration.waiting().is_some() && controlled() {
+            on_update();
+        }
+
+        let watched = registration.clone();
+        let on_found = Closure::wrap(Box::new(move || {
+            let Some(installing) = watched.installing() else {
+                return;
+            };
+            let worker = installing.clone();
+            let on_update = on_update.clone();
+            let controlled = controlled.clone();
+            let on_state = Closure::wrap(Box::new(move || {
+                if worker.state() == ServiceWorkerState::Installed && controlled() {
+                    on_update();
+                }
+            }) as Box<dyn FnMut()>);
+            installing.set_onstatechange(Some(on_state.as_ref().unchecked_ref()));
+            on_state.forget(); // Lives as long as the installing worker
+        }) as Box<dyn FnMut()>);
+        registration.set_onupdatefound(Some(on_found.as_ref().unchecked_ref()));
+        on_found.forget(); // Lives as long as the app
+
+        let on_change = Closure::wrap(Box::new(|| {
+            if RELOAD_ON_CHANGE.with(|r| r.get()) {
+                if let Some(window) = web_sys::window() {
+                    let _ = window.location().reload();
+                }
+            }
+        }) as Box<dyn FnMut()>);
+        container.set_oncontrollerchange(Some(on_change.as_ref().unchecked_ref()));
+        on_change.forget();
+
+        let checked = registration.clone();
+        let checks = Interval::new(UPDATE_CHECK_MS, move || {
+            if let Ok(promise) = checked.update() {
+                wasm_bindgen_futures::spawn_local(async move {
+                    if let Err(e) = JsFuture::from(promise).await {
+                        log::debug!("Update check failed: {:?}", e);
+                    }
+                });
+            }
+        });
+        UPDATE_CHECKS.with(|c| *c.borrow_mut() = Some(checks));
+        REGISTRATION.with(|r| *r.borrow_mut() = Some(registration));
+    });
+}
+
+/// Let the waiting release take over; the page reloads once it does
+pub fn apply_update() {
+    let waiting = REGISTRATION.with(|r| r.borrow().as_ref().and_then(|r| r.waiting()));
+    let Some(waiting) = waiting else {
+        return;
+    };
+    RELOAD_ON_CHANGE.with(|r| r.set(true));
+    let message = js_sys::Object::new();
+    let _ = js_sys::Reflect::set(
+        &message,
+        &JsValue::from_str("type"),
+        &JsValue::from_str("skip-waiting"),
+    );
+    if let Err(e) = waiting.post_message(&message) {
+        log::warn!("Failed to switch to the new release: {:?}", e);
+    }
+}
+
+/// Call `on_available` with whether the browser offers to install the app
+pub fn watch_install(on_available: impl Fn(bool) + 'static) {
+    let Some(window) = web_sys::window() else {
+        return;
+    };
+    let on_available = Rc::new(on_available);
+    let offered = on_available.clone();
+    let on_prompt = Closure::wrap(Box::new(move |ev: web_sys::Event| {
+        // Keep the prompt for the install button instead of the browser's mini-bar
+        ev.prevent_default();
+        INSTALL_PROMPT.with(|p| *p.borrow_mut() = Some(ev));
+        offered(true);
+    }) as Box<dyn FnMut(_)>);
+    let _ = window.add_event_listener_with_callback(
+        "beforeinstallprompt",
+        on_prompt.as_ref().unchecked_ref(),
+    );
+    on_prompt.forget(); // Lives as long as the app
+
+    let on_installed = Closure::wrap(Box::new(move |_: web_sys::Event| {
+        INSTALL_PROMPT.with(|p| *p.borrow_mut() = None);
+        on_available(false);
+    }) as Box<dyn FnMut(_)>);
+    let _ = window
+        .add_event_listener_with_callback("appinstalled", on_installed.as_ref().unchecked_ref());
+    on_installed.forget();
+}
+
+/// Show the browser's install prompt, if it offered one; it can be shown once
+pub fn install() {
+    let Some(prompt) = INSTALL_PROMPT.with(|p| p.borrow_mut().take()) else {
+        return;
+    };
+    let shown = js_sys::Reflect::get(&prompt, &JsValue::from_str("prompt"))
+        .ok()
+        .and_then(|f| f.dyn_into::<js_sys::Function>().ok())
+        .map(|f| f.call0(&prompt));
+    if let Some(Err(e)) = shown {
+        log::warn!("Install prompt failed: {:?}", e);
+    }
+}
+
+pub fn is_online() -> bool {
+    web_sys::window().is_none_or(|w| w.navigator().on_line())
+}
+
+/// Call `on_change` with whether the browser is online whenever that changes
+pub fn watch_connection(on_change: impl Fn(bool) + 'static) {
+    let Some(window) = web_sys::window() else {
+        return;
+    };
+    let on_change = Closure::wrap(Box::new(move |_: web_sys::Event| {
+        on_change(is_online());
+    }) as Box<dyn FnMut(_)>);
+    for event in ["online", "offline"] {
+        let _ = window.add_event_listener_with_callback(event, on_change.as_ref().unchecked_ref());
+    }
+    on_change.forget(); // Lives as long as the app
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_service_worker_url_changes_with_the_release() {
+        assert_eq!(service_worker_url("0.1.0"), "sw.js?v=0.1.0");
+        assert_ne!(service_worker_url("0.1.0"), service_worker_url("0.2.0"));
+    }
+}
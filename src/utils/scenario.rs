@@ -0,0 +1,244 @@
+//! Reproducible "scenario" bundles for bug reports: the settings, a small slice of the
+//! knowledge base, the open conversation and the recent activity feed. Message and
+//! document text is masked (letters and digits become `x`, length and layout are kept)
+//! unless the user opts in to sharing it.
+
+use crate::features::graphrag::index_stats::fingerprint;
+use crate::graphrag_config::{with_graphrag_manager, GraphRAGConfig};
+use crate::models::app::{AppError, AppResult};
+use crate::models::{ActivityEvent, DocumentIndex, GenerationSettings, Message, MessageRole};
+use crate::state::KnowledgeStorageContext;
+use crate::storage::ConversationStorage;
+use crate::utils::generation::GenerationUtils;
+use serde::{Deserialize, Serialize};
+
+pub const SCENARIO_VERSION: u8 = 1;
+/// Documents kept in a bundle
+const MAX_DOCUMENTS: usize = 10;
+/// Characters kept of each document
+const MAX_DOCUMENT_CHARS: usize = 4_000;
+/// Most recent activity events kept
+const MAX_ACTIONS: usize = 200;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ScenarioOptions {
+    /// Share message and document text instead of masking it
+    pub include_contents: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ScenarioDocument {
+    pub title: String,
+    pub content: String,
+    /// Length of the original document, before truncation
+    pub chars: usize,
+    /// Fingerprint of the original content, to match it against a local copy
+    pub fingerprint: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ScenarioMessage {
+    pub role: MessageRole,
+    pub content: String,
+    pub timestamp: f64,
+    pub model_used: Option<String>,
+    pub graphrag_enhanced: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ScenarioBundle {
+    pub version: u8,
+    pub recorded_at: f64,
+    pub app_version: String,
+    pub includes_contents: bool,
+    pub model: Option<String>,
+    pub graphrag_config: GraphRAGConfig,
+    pub generation_settings: GenerationSettings,
+    pub documents: Vec<ScenarioDocument>,
+    pub conversation: Vec<ScenarioMessage>,
+    /// Activity feed, oldest first
+    pub actions: Vec<ActivityEvent>,
+}
+
+/// App state a bundle is recorded from
+pub struct ScenarioSource {
+    pub model: Option<String>,
+    pub graphrag_config: GraphRAGConfig,
+    pub generation_settings: GenerationSettings,
+    pub documents: Vec<DocumentIndex>,
+    pub messages: Vec<Message>,
+    pub events: Vec<ActivityEvent>,
+}
+
+impl ScenarioBundle {
+    pub fn record(source: ScenarioSource, options: ScenarioOptions, now: f64) -> Self {
+        let share = options.include_contents;
+        let documents = knowledge_subset(&source.documents, &source.messages)
+            .into_iter()
+            .enumerate()
+            .map(|(i, d)| {
+                let content: String = d.content.chars().take(MAX_DOCUMENT_CHARS).collect();
+                ScenarioDocument {
+                    title: if share {
+                        d.title.clone()
+                    } else {
+                        format!("Document {}", i + 1)
+                    },
+                    content: if share { content } else { mask(&content) },
+                    chars: d.content.chars().count(),
+                    fingerprint: fingerprint(&d.content),
+                }
+            })
+            .collect();
+        let conversation = source
+            .messages
+            .iter()
+            .map(|m| ScenarioMessage {
+                role: m.role.clone(),
+                content: if share {
+                    m.content.clone()
+                } else {
+                    mask(&m.content)
+                },
+                timestamp: m.timestamp,
+                model_used: m.metadata.as_ref().and_then(|md| md.model_used.clone()),
+                graphrag_enhanced: m.metadata.as_ref().is_some_and(|md| md.graphrag_enhanced),
+            })
+            .collect();
+        let skip = source.events.len().saturating_sub(MAX_ACTIONS);
+        let actions = source
+            .events
+            .into_iter()
+            .skip(skip)
+            .map(|mut e| {
+                if !share {
+                    e.message = mask_quoted(&e.message);
+                    e.detail = e.detail.map(|d| mask(&d));
+                }
+                e
+            })
+            .collect();
+        Self {
+            version: SCENARIO_VERSION,
+            recorded_at: now,
+            app_version: env!("CARGO_PKG_VERSION").to_string(),
+            includes_contents: share,
+            model: source.model,
+            graphrag_config: source.graphrag_config,
+            generation_settings: source.generation_settings,
+            documents,
+            conversation,
+            actions,
+        }
+    }
+
+    pub fn to_json(&self) -> AppResult<String> {
+        serde_json::to_string_pretty(self)
+            .map_err(|e| AppError::storage(format!("Serialization failed: {}", e)))
+    }
+
+    pub fn from_json(json: &str) -> AppResult<Self> {
+        let bundle: Self = serde_json::from_str(json)
+            .map_err(|e| AppError::validation(format!("Not a scenario bundle: {}", e)))?;
+        if bundle.version != SCENARIO_VERSION {
+            return Err(AppError::validation(format!(
+                "Unsupported scenario version: {}",
+                bundle.version
+            )));
+        }
+        Ok(bundle)
+    }
+
+    /// Load the bundle into this browser: settings are replaced, documents are added to
+    /// the knowledge base and the conversation is recreated. Returns its id.
+    pub fn apply(&self) -> AppResult<String> {
+        GenerationUtils::save_settings(&self.generation_settings)?;
+        let config = serde_json::to_string(&self.graphrag_config)
+            .map_err(|e| AppError::storage(format!("Serialization failed: {}", e)))?;
+        let mut imported = Ok(());
+        with_graphrag_manager(|m| imported = m.import_config(&config));
+        imported.map_err(AppError::validation)?;
+
+        let knowledge = KnowledgeStorageContext::new();
+        for doc in &self.documents {
+            knowledge.upsert_buffer_document(&doc.title, &doc.content)?;
+        }
+
+        let storage = ConversationStorage::new().map_err(|e| AppError::storage(e.to_string()))?;
+        let title = format!("Scenario {}", self.app_version);
+        let id = storage
+            .create_conversation(title)
+            .map_err(|e| AppError::storage(e.to_string()))?;
+        for m in &self.conversation {
+            let mut message = Message::new(m.role.clone(), m.content.clone());
+            message.id = uuid::Uuid::new_v4().to_string();
+            message.timestamp = m.timestamp;
+            storage
+                .save_message(&id, &message)
+                .map_err(|e| AppError::storage(e.to_string()))?;
+        }
+        Ok(id)
+    }
+}
+
+/// Documents cited by the conversation, or the first ones when none is
+fn knowledge_subset<'a>(docs: &'a [DocumentIndex], messages: &[Message]) -> Vec<&'a DocumentIndex> {
+    let cited: Vec<&str> = messages
+        .iter()
+        .filter_map(|m| m.metadata.as_ref()?.provenance.as_ref())
+        .flatten()
+        .map(|s| s.title.as_str())
+        .collect();
+    let mut subset: Vec<&DocumentIndex> = docs
+        .iter()
+        .filter(|d| cited.contains(&d.title.as_str()))
+        .take(MAX_DOCUMENTS)
+        .collect();
+    if subset.is_empty() {
+        subset = docs.iter().take(MAX_DOCUMENTS).collect();
+    }
+    subset
+}
+
+/// Letters and digits become `x`; whitespace, punctuation and length are kept
+pub fn mask(text: &str) -> String {
+    text.chars()
+        .map(|c| if c.is_alphanumeric() { 'x' } else { c })
+        .collect()
+}
+
+/// Mask the quoted parts of an app-written message, which hold titles and user text
+fn mask_quoted(text: &str) -> String {
+    text.split('"')
+        .enumerate()
+        .map(|(i, part)| {
+            if i % 2 == 1 {
+                mask(part)
+            } else {
+                part.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mask_keeps_shape() {
+        assert_eq!(mask("Call Bob at 5pm, ok?"), "xxxx xxx xx xxx, xx?");
+        assert_eq!(
+            mask_quoted("Saved long input as \"Q3 plan\" today"),
+            "Saved long input as \"xx xxxx\" today"
+        );
+    }
+
+    #[test]
+    fn test_bundle_rejects_other_versions() {
+        let json = r#"{"version": 9}"#;
+        assert!(ScenarioBundle::from_json(json).is_err());
+        assert!(ScenarioBundle::from_json("not json").is_err());
+    }
+}
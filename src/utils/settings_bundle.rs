@@ -0,0 +1,303 @@
+//! The app's preferences as one JSON file, so a setup can be moved to another browser:
+//! theme and other app preferences, the chat model, the global system prompt, the
+//! GraphRAG config with its profiles, and the generation, notification and import
+//! settings. Every section is optional and importing only touches the sections a file
+//! has. Keyboard shortcuts are fixed in this build, so there are none to carry;
+//! conversations and documents are data, not settings, and stay behind.
+
+use crate::features::graphrag::config_bundle::{self, ConfigBundle, ImportedConfig};
+use crate::features::webllm::ui::LAST_MODEL_KEY;
+use crate::graphrag_config::with_graphrag_manager;
+use crate::models::{AppConfig, GenerationSettings};
+use crate::storage::{CachePolicy, TieredCache};
+use crate::utils::generation::GenerationUtils;
+use crate::utils::import_queue::{ImportSettings, MAX_PARALLELISM};
+use crate::utils::notifications::{NotificationSettings, NotificationUtils};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+pub const SETTINGS_BUNDLE_FORMAT: &str = "app-settings";
+/// Bumped when a bundle written now could not be read by older builds
+pub const SETTINGS_BUNDLE_VERSION: u32 = 1;
+
+// Cache key of the global system prompt (see the sidebar's prompt editor)
+const GLOBAL_PROMPT_KEY: &str = "global_system_prompt";
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SettingsBundle {
+    pub format: String,
+    pub version: u32,
+    pub exported_at: f64,
+    #[serde(default)]
+    pub app: Option<AppConfig>,
+    /// Chat model selected when the bundle was written
+    #[serde(default)]
+    pub model_id: Option<String>,
+    #[serde(default)]
+    pub system_prompt: Option<String>,
+    #[serde(default)]
+    pub graphrag: Option<ConfigBundle>,
+    #[serde(default)]
+    pub generation: Option<GenerationSettings>,
+    #[serde(default)]
+    pub notifications: Option<NotificationSettings>,
+    #[serde(default)]
+    pub import: Option<ImportSettings>,
+}
+
+impl SettingsBundle {
+    /// A bundle without any section
+    pub fn new(exported_at: f64) -> Self {
+        Self {
+            format: SETTINGS_BUNDLE_FORMAT.to_string(),
+            version: SETTINGS_BUNDLE_VERSION,
+            exported_at,
+            app: None,
+            model_id: None,
+            system_prompt: None,
+            graphrag: None,
+            generation: None,
+            notifications: None,
+            import: None,
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+
+    /// Labels of the sections this bundle carries, in file order
+    pub fn sections(&self) -> Vec<&'static str> {
+        [
+            (self.app.is_some(), "Theme and app preferences"),
+            (self.model_id.is_some(), "Chat model"),
+            (self.system_prompt.is_some(), "Global system prompt"),
+            (self.graphrag.is_some(), "GraphRAG settings and profiles"),
+            (self.generation.is_some(), "Generation"),
+            (self.notifications.is_some(), "Notifications"),
+            (self.import.is_some(), "Import"),
+        ]
+        .into_iter()
+        .filter(|(present, _)| *present)
+        .map(|(_, label)| label)
+        .collect()
+    }
+}
+
+/// A bundle read from a file, with what to tell the user before applying it
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImportedSettings {
+    pub bundle: SettingsBundle,
+    /// The GraphRAG section, validated as a GraphRAG settings file
+    pub graphrag: Option<ImportedConfig>,
+    pub warnings: Vec<String>,
+}
+
+pub fn parse(json: &str) -> Result<ImportedSettings, String> {
+    let value: Value = serde_json::from_str(json).map_err(|e| format!("Not valid JSON: {}", e))?;
+    let format = value["format"].as_str().unwrap_or_default();
+    if format == config_bundle::CONFIG_BUNDLE_FORMAT {
+        return Err(
+            "This is a GraphRAG settings file; import it from the GraphRAG settings".to_string(),
+        );
+    }
+    if format != SETTINGS_BUNDLE_FORMAT {
+        return Err("Not an app settings file".to_string());
+    }
+    let version = value["version"].as_u64().unwrap_or(0);
+    if version > SETTINGS_BUNDLE_VERSION as u64 {
+        return Err(format!(
+            "Written by a newer version (format version {}, this build reads up to {})",
+            version, SETTINGS_BUNDLE_VERSION
+        ));
+    }
+    let mut bundle: SettingsBundle =
+        serde_json::from_value(value).map_err(|e| format!("Invalid settings file: {}", e))?;
+    bundle.model_id = bundle.model_id.filter(|id| !id.trim().is_empty());
+
+    let mut errors = Vec::new();
+    let mut warnings = Vec::new();
+    let graphrag = match &bundle.graphrag {
+        Some(section) => match config_bundle::parse(&section.to_json()) {
+            Ok(imported) => {
+                warnings.extend(imported.warnings.iter().map(|w| format!("GraphRAG: {}", w)));
+                Some(imported)
+            }
+            Err(e) => {
+                errors.push(format!("GraphRAG: {}", e));
+                None
+            }
+        },
+        None => None,
+    };
+    if let Some(import) = &bundle.import {
+        if !(1..=MAX_PARALLELISM).contains(&import.parallelism) {
+            errors.push(format!(
+                "Import: parallelism must be between 1 and {}, got {}",
+                MAX_PARALLELISM, import.parallelism
+            ));
+        }
+    }
+    if let Some(app) = &bundle.app {
+        if !(0.8..=2.0).contains(&app.accessibility.font_size_scale) {
+            errors.push(format!(
+                "App: font size scale must be between 0.8 and 2, got {}",
+                app.accessibility.font_size_scale
+            ));
+        }
+    }
+    if !errors.is_empty() {
+        return Err(errors.join("; "));
+    }
+    if bundle.sections().is_empty() {
+        return Err("The file has no settings".to_string());
+    }
+    Ok(ImportedSettings {
+        bundle,
+        graphrag,
+        warnings,
+    })
+}
+
+/// Everything this browser has saved, as a bundle
+pub fn collect() -> SettingsBundle {
+    let mut bundle = SettingsBundle::new(js_sys::Date::now());
+    bundle.app = AppConfig::load().ok();
+    bundle.model_id = TieredCache::get::<String>(LAST_MODEL_KEY).filter(|id| !id.trim().is_empty());
+    bundle.system_prompt =
+        TieredCache::get::<String>(GLOBAL_PROMPT_KEY).filter(|p| !p.trim().is_empty());
+    with_graphrag_manager(|m| bundle.graphrag = Some(m.config_bundle()));
+    bundle.generation = Some(GenerationUtils::load_settings());
+    bundle.notifications = Some(NotificationUtils::load_settings());
+    bundle.import = Some(ImportSettings::load());
+    bundle
+}
+
+/// Save every section of a validated bundle. The GraphRAG config and theme apply at
+/// once; the chat model is picked up the next time the app opens.
+pub fn apply(imported: ImportedSettings) -> Result<(), String> {
+    let ImportedSettings {
+        bundle, graphrag, ..
+    } = imported;
+    let mut errors = Vec::new();
+    if let Some(app) = bundle.app {
+        if let Some(html) = web_sys::window()
+            .and_then(|w| w.document())
+            .and_then(|d| d.document_element())
+        {
+            let _ = html.set_attribute("data-theme", app.theme.data_theme());
+        }
+        if let Err(e) = app.save() {
+            errors.push(format!("App: {}", e));
+        }
+    }
+    if let Some(id) = bundle.model_id {
+        if let Err(e) = TieredCache::set(LAST_MODEL_KEY, &id, CachePolicy::PREFERENCE) {
+            errors.push(format!("Chat model: {}", e));
+        }
+    }
+    if let Some(prompt) = bundle.system_prompt {
+        if let Err(e) = TieredCache::set(GLOBAL_PROMPT_KEY, &prompt, CachePolicy::PREFERENCE) {
+            errors.push(format!("Global system prompt: {}", e));
+        }
+    }
+    if let Some(graphrag) = graphrag {
+        with_graphrag_manager(|m| m.apply_import(graphrag));
+    }
+    if let Some(generation) = bundle.generation {
+        if let Err(e) = GenerationUtils::save_settings(&generation) {
+            errors.push(format!("Generation: {}", e));
+        }
+    }
+    if let Some(notifications) = bundle.notifications {
+        if let Err(e) = NotificationUtils::save_settings(&notifications) {
+            errors.push(format!("Notifications: {}", e));
+        }
+    }
+    if let Some(import) = bundle.import {
+        if let Err(e) = import.save() {
+            errors.push(format!("Import: {}", e));
+        }
+    }
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors.join("; "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::features::graphrag::profiles::ConfigProfile;
+    use crate::graphrag_config::GraphRAGConfig;
+    use crate::models::ThemeMode;
+
+    fn full_bundle() -> SettingsBundle {
+        let tuned = GraphRAGConfig {
+            mmr_lambda: 0.4,
+            ..GraphRAGConfig::default()
+        };
+        SettingsBundle {
+            app: Some(AppConfig {
+                theme: ThemeMode::Dark,
+                ..AppConfig::default()
+            }),
+            model_id: Some("Llama-3.2-1B-Instruct-q4f32_1-MLC".to_string()),
+            system_prompt: Some("Answer in French.".to_string()),
+            graphrag: Some(
+                ConfigBundle::new(tuned.clone(), 0.0)
+                    .with_profiles(vec![ConfigProfile::new("Legal", tuned)]),
+            ),
+            generation: Some(GenerationSettings {
+                stop_sequences: vec!["END".to_string()],
+                ..GenerationSettings::default()
+            }),
+            notifications: Some(NotificationSettings::default()),
+            import: Some(ImportSettings::default()),
+            ..SettingsBundle::new(0.0)
+        }
+    }
+
+    #[test]
+    fn test_bundle_round_trip() {
+        let bundle = full_bundle();
+        let imported = parse(&bundle.to_json()).unwrap();
+        assert_eq!(imported.bundle, bundle);
+        assert!(imported.warnings.is_empty());
+        let graphrag = imported.graphrag.unwrap();
+        assert_eq!(graphrag.config.mmr_lambda, 0.4);
+        assert_eq!(graphrag.profiles.len(), 1);
+        assert_eq!(imported.bundle.sections().len(), 7);
+
+        // Sections can be left out; only the ones present are applied
+        let partial = SettingsBundle {
+            system_prompt: Some("Be brief.".to_string()),
+            model_id: Some("  ".to_string()),
+            ..SettingsBundle::new(0.0)
+        };
+        let imported = parse(&partial.to_json()).unwrap();
+        assert_eq!(imported.bundle.sections(), vec!["Global system prompt"]);
+        assert!(imported.graphrag.is_none());
+    }
+
+    #[test]
+    fn test_invalid_bundles_are_refused() {
+        let json = full_bundle().to_json();
+        let newer = json.replacen("\"version\": 1", "\"version\": 2", 1);
+        assert!(parse(&newer).unwrap_err().contains("newer version"));
+        let broken = json.replace("\"chunk_overlap\": 200", "\"chunk_overlap\": 5000");
+        assert!(parse(&broken)
+            .unwrap_err()
+            .starts_with("GraphRAG: chunk_overlap"));
+        let too_parallel = json.replace("\"parallelism\": 3", "\"parallelism\": 0");
+        assert!(parse(&too_parallel).unwrap_err().contains("parallelism"));
+
+        let graphrag_only = ConfigBundle::new(GraphRAGConfig::default(), 0.0).to_json();
+        assert!(parse(&graphrag_only)
+            .unwrap_err()
+            .contains("GraphRAG settings file"));
+        assert!(parse(&SettingsBundle::new(0.0).to_json()).is_err());
+        assert!(parse("{}").is_err());
+    }
+}
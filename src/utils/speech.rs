@@ -0,0 +1,192 @@
+//! Speech-to-text through the browser's Web Speech API. Chrome and Safari expose it as
+//! `webkitSpeechRecognition`, Firefox not at all, so support is checked at runtime.
+
+use crate::models::app::{AppError, AppResult};
+use js_sys::Reflect;
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{SpeechRecognition, SpeechRecognitionEvent};
+
+/// Cache key of the chosen recognition language
+pub const SPEECH_LANGUAGE_KEY: &str = "speech_input_language";
+
+/// Languages offered for dictation, as (BCP 47 tag, label)
+pub const SPEECH_LANGUAGES: [(&str, &str); 10] = [
+    ("en-US", "English (US)"),
+    ("en-GB", "English (UK)"),
+    ("it-IT", "Italiano"),
+    ("es-ES", "Español"),
+    ("fr-FR", "Français"),
+    ("de-DE", "Deutsch"),
+    ("pt-BR", "Português (Brasil)"),
+    ("nl-NL", "Nederlands"),
+    ("ja-JP", "日本語"),
+    ("zh-CN", "中文 (简体)"),
+];
+
+fn constructor() -> Option<js_sys::Function> {
+    let global = js_sys::global();
+    ["SpeechRecognition", "webkitSpeechRecognition"]
+        .into_iter()
+        .filter_map(|name| Reflect::get(&global, &JsValue::from_str(name)).ok())
+        .find_map(|ctor| ctor.dyn_into::<js_sys::Function>().ok())
+}
+
+pub fn is_supported() -> bool {
+    constructor().is_some()
+}
+
+/// Offered language closest to the browser's, English otherwise
+pub fn default_language() -> String {
+    let browser = web_sys::window()
+        .and_then(|w| w.navigator().language())
+        .unwrap_or_default();
+    closest_language(&browser).to_string()
+}
+
+/// Offered language matching `tag` exactly, then by its primary subtag
+pub fn closest_language(tag: &str) -> &'static str {
+    let primary = tag.split('-').next().unwrap_or_default();
+    SPEECH_LANGUAGES
+        .iter()
+        .find(|(code, _)| code.eq_ignore_ascii_case(tag))
+        .or_else(|| {
+            SPEECH_LANGUAGES
+                .iter()
+                .find(|(code, _)| code.split('-').next() == Some(primary))
+        })
+        .map(|(code, _)| *code)
+        .unwrap_or(SPEECH_LANGUAGES[0].0)
+}
+
+/// Text already typed followed by the dictated text
+pub fn merge_transcript(typed: &str, spoken: &str) -> String {
+    let spoken = spoken.trim();
+    match (typed.trim_end(), spoken) {
+        (typed, "") => typed.to_string(),
+        ("", spoken) => spoken.to_string(),
+        (typed, spoken) => format!("{} {}", typed, spoken),
+    }
+}
+
+/// A running dictation; recognition stops when this is dropped
+pub struct SpeechInput {
+    recognition: SpeechRecognition,
+    _on_result: Closure<dyn FnMut(SpeechRecognitionEvent)>,
+    _on_error: Closure<dyn FnMut(JsValue)>,
+    _on_end: Closure<dyn FnMut(JsValue)>,
+}
+
+impl SpeechInput {
+    /// Start listening in `lang`. `on_transcript` receives everything heard so far
+    /// (final and interim results); `on_end` runs once recognition stops, with the
+    /// error code if it failed.
+    pub fn start(
+        lang: &str,
+        on_transcript: impl Fn(String) + 'static,
+        on_end: impl Fn(Option<String>) + 'static,
+    ) -> AppResult<Self> {
+        let ctor = constructor().ok_or_else(|| {
+            AppError::validation("Speech recognition is not supported in this browser".to_string())
+        })?;
+        let recognition: SpeechRecognition = Reflect::construct(&ctor, &js_sys::Array::new())
+            .map_err(|e| AppError::validation(format!("Speech recognition failed: {:?}", e)))?
+            .unchecked_into();
+        recognition.set_lang(lang);
+        recognition.set_interim_results(true);
+        let _ = recognition.set_continuous(true);
+
+        let on_result = Closure::wrap(Box::new(move |ev: SpeechRecognitionEvent| {
+            let Some(results) = ev.results() else {
+                return;
+            };
+            let transcript: String = (0..results.length())
+                .filter_map(|i| results.get(i))
+                .filter_map(|r| r.get(0))
+                .map(|alt| alt.transcript())
+                .collect();
+            on_transcript(transcript);
+        }) as Box<dyn FnMut(SpeechRecognitionEvent)>);
+
+        let on_end = std::rc::Rc::new(on_end);
+        let failed = std::rc::Rc::new(std::cell::Cell::new(false));
+        let on_error = Closure::wrap(Box::new({
+            let on_end = on_end.clone();
+            let failed = failed.clone();
+            move |ev: JsValue| {
+                let code = Reflect::get(&ev, &JsValue::from_str("error"))
+                    .ok()
+                    .and_then(|v| v.as_string())
+                    .unwrap_or_else(|| "unknown".to_string());
+                failed.set(true);
+                on_end(Some(code));
+            }
+        }) as Box<dyn FnMut(JsValue)>);
+        let on_stopped = Closure::wrap(Box::new(move |_: JsValue| {
+            // An error already reported the end
+            if !failed.get() {
+                on_end(None);
+            }
+        }) as Box<dyn FnMut(JsValue)>);
+
+        recognition.set_onresult(Some(on_result.as_ref().unchecked_ref()));
+        recognition.set_onerror(Some(on_error.as_ref().unchecked_ref()));
+        recognition.set_onend(Some(on_stopped.as_ref().unchecked_ref()));
+        recognition
+            .start()
+            .map_err(|e| AppError::validation(format!("Could not start dictation: {:?}", e)))?;
+        Ok(Self {
+            recognition,
+            _on_result: on_result,
+            _on_error: on_error,
+            _on_end: on_stopped,
+        })
+    }
+
+    /// Stop listening; results heard so far are still delivered
+    pub fn stop(&self) {
+        self.recognition.stop();
+    }
+}
+
+impl Drop for SpeechInput {
+    fn drop(&mut self) {
+        self.recognition.set_onresult(None);
+        self.recognition.set_onerror(None);
+        self.recognition.set_onend(None);
+        self.recognition.abort();
+    }
+}
+
+/// User-facing text of a recognition error code
+pub fn describe_error(code: &str) -> &'static str {
+    match code {
+        "not-allowed" | "service-not-allowed" => "Microphone access was denied",
+        "no-speech" => "No speech was detected",
+        "audio-capture" => "No microphone was found",
+        "network" => "Speech recognition needs a network connection",
+        "language-not-supported" => "This language is not supported for dictation",
+        "aborted" => "Dictation was cancelled",
+        _ => "Dictation failed",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_transcript() {
+        assert_eq!(merge_transcript("", " hello there "), "hello there");
+        assert_eq!(merge_transcript("Note: ", "call Bob"), "Note: call Bob");
+        assert_eq!(merge_transcript("typed", "  "), "typed");
+    }
+
+    #[test]
+    fn test_closest_language() {
+        assert_eq!(closest_language("it-IT"), "it-IT");
+        assert_eq!(closest_language("en-gb"), "en-GB");
+        assert_eq!(closest_language("fr-CA"), "fr-FR");
+        assert_eq!(closest_language("ko-KR"), "en-US");
+    }
+}
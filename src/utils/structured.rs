@@ -0,0 +1,156 @@
+//! JSON and YAML documents indexed field by field. Each top-level key becomes a chunk;
+//! keys too large for one chunk are split further along their children. Chunk text
+//! lists every leaf as `path: value`, so both retrieval and citations name the field.
+
+use serde_json::Value;
+
+/// Largest chunk before a subtree is split along its children
+const MAX_FIELD_CHUNK_CHARS: usize = 1_000;
+
+/// A subtree of a structured document
+#[derive(Clone, Debug, PartialEq)]
+pub struct FieldChunk {
+    /// Path of the subtree, e.g. `server.timeout` or `routes[2].path`; empty for the root
+    pub path: String,
+    pub text: String,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StructuredFormat {
+    Json,
+    Yaml,
+}
+
+impl StructuredFormat {
+    /// Format of a file, from its name
+    pub fn of(name: &str) -> Option<Self> {
+        let name = name.to_lowercase();
+        if name.ends_with(".json") {
+            Some(Self::Json)
+        } else if name.ends_with(".yaml") || name.ends_with(".yml") {
+            Some(Self::Yaml)
+        } else {
+            None
+        }
+    }
+
+    pub fn file_type(self) -> &'static str {
+        match self {
+            Self::Json => "json",
+            Self::Yaml => "yaml",
+        }
+    }
+
+    pub fn parse(self, text: &str) -> Result<Value, String> {
+        match self {
+            Self::Json => serde_json::from_str(text).map_err(|e| e.to_string()),
+            Self::Yaml => serde_yaml::from_str(text).map_err(|e| e.to_string()),
+        }
+    }
+}
+
+/// Chunks of `value`, one per top-level key (or array item) unless it must be split
+pub fn field_chunks(value: &Value) -> Vec<FieldChunk> {
+    let mut out = Vec::new();
+    match children(value, "") {
+        Some(items) if !items.is_empty() => {
+            for (path, child) in items {
+                collect(child, path, &mut out);
+            }
+        }
+        _ => out.push(FieldChunk {
+            path: String::new(),
+            text: leaf_lines(value, "").join("\n"),
+        }),
+    }
+    out
+}
+
+fn collect(value: &Value, path: String, out: &mut Vec<FieldChunk>) {
+    let text = leaf_lines(value, &path).join("\n");
+    match children(value, &path) {
+        Some(items) if text.len() > MAX_FIELD_CHUNK_CHARS && !items.is_empty() => {
+            for (child_path, child) in items {
+                collect(child, child_path, out);
+            }
+        }
+        _ => out.push(FieldChunk { path, text }),
+    }
+}
+
+/// Direct children with their paths; `None` for scalars
+fn children<'a>(value: &'a Value, path: &str) -> Option<Vec<(String, &'a Value)>> {
+    match value {
+        Value::Object(map) => Some(map.iter().map(|(k, v)| (join_key(path, k), v)).collect()),
+        Value::Array(items) => Some(
+            items
+                .iter()
+                .enumerate()
+                .map(|(i, v)| (format!("{}[{}]", path, i), v))
+                .collect(),
+        ),
+        _ => None,
+    }
+}
+
+/// `path.key`, or `path["key"]` when the key is not a plain identifier
+fn join_key(path: &str, key: &str) -> String {
+    let plain = !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '_' || c == '-');
+    match (path.is_empty(), plain) {
+        (true, true) => key.to_string(),
+        (false, true) => format!("{}.{}", path, key),
+        (_, false) => format!("{}[{:?}]", path, key),
+    }
+}
+
+/// Every leaf under `value` as a `path: value` line
+fn leaf_lines(value: &Value, path: &str) -> Vec<String> {
+    let label = if path.is_empty() { "value" } else { path };
+    match value {
+        Value::Object(map) if map.is_empty() => vec![format!("{}: {{}}", label)],
+        Value::Array(items) if items.is_empty() => vec![format!("{}: []", label)],
+        Value::Object(_) | Value::Array(_) => children(value, path)
+            .unwrap_or_default()
+            .into_iter()
+            .flat_map(|(p, v)| leaf_lines(v, &p))
+            .collect(),
+        Value::String(s) => vec![format!("{}: {}", label, s)],
+        other => vec![format!("{}: {}", label, other)],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_field_chunks_follow_top_level_keys() {
+        let value = StructuredFormat::Yaml
+            .parse("server:\n  host: example.org\n  timeout: 30\nfeatures: [search, export]\n")
+            .unwrap();
+        let chunks = field_chunks(&value);
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0].path, "features");
+        assert_eq!(chunks[0].text, "features[0]: search\nfeatures[1]: export");
+        assert_eq!(
+            chunks[1].text,
+            "server.host: example.org\nserver.timeout: 30"
+        );
+    }
+
+    #[test]
+    fn test_large_keys_split_along_children() {
+        let long = "x".repeat(MAX_FIELD_CHUNK_CHARS);
+        let json = format!(
+            r#"{{"api": {{"get": "{}", "post": "short"}}, "a.b": 1}}"#,
+            long
+        );
+        let chunks = field_chunks(&StructuredFormat::Json.parse(&json).unwrap());
+        let paths: Vec<&str> = chunks.iter().map(|c| c.path.as_str()).collect();
+        assert_eq!(paths, vec![r#"["a.b"]"#, "api.get", "api.post"]);
+        assert_eq!(chunks[0].text, r#"["a.b"]: 1"#);
+    }
+}
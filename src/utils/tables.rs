@@ -0,0 +1,458 @@
+//! Tables pasted as Markdown or HTML (spreadsheets and web pages copy HTML), and
+//! datasets uploaded as CSV or as JSON/YAML lists of records. A pasted table kept in
+//! the knowledge base is stored as Markdown under a name ending in `TABLE_SUFFIX`.
+//! Tables are indexed one row at a time, each row listing its cells as
+//! `column: value` so retrieval and citations can name the column; the graph node of
+//! a row carries its cells as typed properties.
+
+use serde_json::{Map, Value};
+
+/// Name suffix of table documents in the upload buffer
+pub const TABLE_SUFFIX: &str = ".table.md";
+
+/// A header row and the data rows under it, each padded to the header's width
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Table {
+    pub headers: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    fn new(headers: Vec<String>, rows: Vec<Vec<String>>) -> Option<Self> {
+        if headers.len() < 2 || rows.is_empty() {
+            return None;
+        }
+        let width = headers.len();
+        let rows = rows
+            .into_iter()
+            .filter(|r| r.iter().any(|c| !c.is_empty()))
+            .map(|mut r| {
+                r.resize(width, String::new());
+                r
+            })
+            .collect::<Vec<_>>();
+        (!rows.is_empty()).then_some(Self { headers, rows })
+    }
+
+    /// First Markdown pipe table in `text`
+    pub fn from_markdown(text: &str) -> Option<Self> {
+        let lines: Vec<&str> = text.lines().collect();
+        let start = (1..lines.len()).find(|&i| {
+            lines[i - 1].contains('|')
+                && is_separator(lines[i])
+                && split_row(lines[i - 1]).len() == split_row(lines[i]).len()
+        })?;
+        let headers = split_row(lines[start - 1]);
+        let rows = lines[start + 1..]
+            .iter()
+            .take_while(|l| l.contains('|'))
+            .map(|l| split_row(l))
+            .collect();
+        Self::new(headers, rows)
+    }
+
+    /// First `<table>` in an HTML fragment. The first row is the header.
+    pub fn from_html(html: &str) -> Option<Self> {
+        // ASCII lowercasing keeps byte offsets shared with `html`
+        let lower = html.to_ascii_lowercase();
+        let start = lower.find("<table")?;
+        let end = lower[start..]
+            .find("</table")
+            .map_or(html.len(), |e| start + e);
+        let mut rows: Vec<Vec<String>> = Vec::new();
+        let mut pos = start;
+        while let Some(tr) = lower[pos..end].find("<tr") {
+            let row_start = pos + tr + 3;
+            let row_end = lower[row_start..end]
+                .find("<tr")
+                .map_or(end, |e| row_start + e);
+            let cells = html_cells(&html[row_start..row_end], &lower[row_start..row_end]);
+            if !cells.is_empty() {
+                rows.push(cells);
+            }
+            pos = row_end;
+        }
+        if rows.is_empty() {
+            return None;
+        }
+        let headers = rows.remove(0);
+        Self::new(headers, rows)
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let line = |cells: &[String]| {
+            let cells: Vec<String> = cells.iter().map(|c| c.replace('|', "\\|")).collect();
+            format!("| {} |", cells.join(" | "))
+        };
+        let mut out = vec![
+            line(&self.headers),
+            format!("|{}", " --- |".repeat(self.headers.len())),
+        ];
+        out.extend(self.rows.iter().map(|r| line(r)));
+        out.join("\n")
+    }
+
+    /// Rows of a CSV file. The delimiter (comma, semicolon or tab) is the one the
+    /// header line uses most; quoted fields may hold delimiters, quotes and newlines.
+    pub fn from_csv(text: &str) -> Option<Self> {
+        let text = text.trim_start_matches('\u{feff}');
+        let header_line = text.lines().next()?;
+        let delimiter = [',', ';', '\t']
+            .into_iter()
+            .max_by_key(|d| header_line.matches(*d).count())?;
+        let mut records = csv_records(text, delimiter).into_iter();
+        let headers = records.next()?;
+        Self::new(headers, records.collect())
+    }
+
+    /// Rows of a JSON/YAML list of records, one column per key found in any record.
+    /// Nested values are kept as JSON text.
+    pub fn from_records(value: &Value) -> Option<Self> {
+        let records = value.as_array()?;
+        if records.is_empty() || !records.iter().all(Value::is_object) {
+            return None;
+        }
+        let mut headers: Vec<String> = Vec::new();
+        for key in records
+            .iter()
+            .filter_map(Value::as_object)
+            .flat_map(Map::keys)
+        {
+            if !headers.contains(key) {
+                headers.push(key.clone());
+            }
+        }
+        let rows = records
+            .iter()
+            .filter_map(Value::as_object)
+            .map(|record| {
+                headers
+                    .iter()
+                    .map(|h| match record.get(h) {
+                        None | Some(Value::Null) => String::new(),
+                        Some(Value::String(s)) => s.clone(),
+                        Some(other) => other.to_string(),
+                    })
+                    .collect()
+            })
+            .collect();
+        Self::new(headers, rows)
+    }
+
+    /// Row `index` as `column: value` lines, empty cells left out
+    pub fn row_text(&self, index: usize) -> String {
+        self.rows[index]
+            .iter()
+            .zip(&self.headers)
+            .filter(|(cell, _)| !cell.is_empty())
+            .map(|(cell, header)| format!("{}: {}", header, one_line(cell)))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Type shared by every non-empty value of a column
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ColumnType {
+    Integer,
+    Number,
+    Boolean,
+    Date,
+    Text,
+}
+
+impl ColumnType {
+    /// Type of one cell. Numbers with leading zeros (codes, zip codes) stay text.
+    pub fn of(value: &str) -> Self {
+        let digits = value.trim_start_matches('-');
+        if digits.len() > 1 && digits.starts_with('0') && !digits.starts_with("0.") {
+            return Self::Text;
+        }
+        if value.parse::<i64>().is_ok() {
+            Self::Integer
+        } else if value.parse::<f64>().is_ok_and(f64::is_finite) {
+            Self::Number
+        } else if matches!(value.to_lowercase().as_str(), "true" | "false") {
+            Self::Boolean
+        } else if is_iso_date(value) {
+            Self::Date
+        } else {
+            Self::Text
+        }
+    }
+
+    /// Common type of `values`; integers mixed with decimals are numbers
+    pub fn of_column<'a>(values: impl IntoIterator<Item = &'a str>) -> Self {
+        values
+            .into_iter()
+            .filter(|v| !v.is_empty())
+            .map(Self::of)
+            .reduce(|a, b| match (a, b) {
+                _ if a == b => a,
+                (Self::Integer, Self::Number) | (Self::Number, Self::Integer) => Self::Number,
+                _ => Self::Text,
+            })
+            .unwrap_or(Self::Text)
+    }
+
+    fn value(self, cell: &str) -> Value {
+        match self {
+            Self::Integer => cell.parse::<i64>().map_or(Value::Null, Value::from),
+            Self::Number => cell.parse::<f64>().map_or(Value::Null, Value::from),
+            Self::Boolean => Value::Bool(cell.eq_ignore_ascii_case("true")),
+            Self::Date | Self::Text => Value::String(cell.to_string()),
+        }
+    }
+}
+
+/// Cells of a row written by `Table::row_text`, as `(column, value)` pairs
+pub fn row_cells(text: &str) -> Vec<(String, String)> {
+    text.lines()
+        .filter_map(|l| l.split_once(": "))
+        .map(|(column, value)| (column.to_string(), value.to_string()))
+        .collect()
+}
+
+/// Typed properties of each row, the type of a column being inferred from all rows
+pub fn typed_properties(rows: &[Vec<(String, String)>]) -> Vec<Map<String, Value>> {
+    let mut types: Vec<(&str, ColumnType)> = Vec::new();
+    for (column, _) in rows.iter().flatten() {
+        if types.iter().all(|(c, _)| c != column) {
+            let values = rows
+                .iter()
+                .flatten()
+                .filter(|(c, _)| c == column)
+                .map(|(_, v)| v.as_str());
+            types.push((column, ColumnType::of_column(values)));
+        }
+    }
+    rows.iter()
+        .map(|row| {
+            row.iter()
+                .map(|(column, cell)| {
+                    let kind = types
+                        .iter()
+                        .find(|(c, _)| c == column)
+                        .map_or(ColumnType::Text, |(_, t)| *t);
+                    (column.clone(), kind.value(cell))
+                })
+                .collect()
+        })
+        .collect()
+}
+
+pub fn is_csv_document(name: &str) -> bool {
+    let name = name.to_lowercase();
+    name.ends_with(".csv") || name.ends_with(".tsv")
+}
+
+/// Table in a paste, preferring its HTML flavor when the clipboard had one
+pub fn detect(text: &str, html: Option<&str>) -> Option<Table> {
+    html.and_then(Table::from_html)
+        .or_else(|| Table::from_markdown(text))
+}
+
+pub fn is_table_document(name: &str) -> bool {
+    name.to_lowercase().ends_with(TABLE_SUFFIX)
+}
+
+fn one_line(cell: &str) -> String {
+    cell.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn is_iso_date(value: &str) -> bool {
+    let bytes = value.as_bytes();
+    bytes.len() >= 10
+        && bytes[4] == b'-'
+        && bytes[7] == b'-'
+        && [0, 1, 2, 3, 5, 6, 8, 9]
+            .iter()
+            .all(|&i| bytes[i].is_ascii_digit())
+        && (bytes.len() == 10 || matches!(bytes[10], b'T' | b' '))
+}
+
+/// Records of a CSV text, each a list of fields
+fn csv_records(text: &str, delimiter: char) -> Vec<Vec<String>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' if quoted => quoted = false,
+            '"' if field.is_empty() => quoted = true,
+            _ if quoted => field.push(ch),
+            '\r' => {}
+            '\n' => {
+                record.push(std::mem::take(&mut field).trim().to_string());
+                records.push(std::mem::take(&mut record));
+            }
+            _ if ch == delimiter => record.push(std::mem::take(&mut field).trim().to_string()),
+            _ => field.push(ch),
+        }
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field.trim().to_string());
+        records.push(record);
+    }
+    records.retain(|r| r.iter().any(|f| !f.is_empty()));
+    records
+}
+
+fn is_separator(line: &str) -> bool {
+    let cells = split_row(line);
+    cells.len() >= 2
+        && cells
+            .iter()
+            .all(|c| c.contains('-') && c.chars().all(|ch| ch == '-' || ch == ':'))
+}
+
+fn split_row(line: &str) -> Vec<String> {
+    let line = line.trim();
+    let line = line.strip_prefix('|').unwrap_or(line);
+    let line = line.strip_suffix('|').unwrap_or(line);
+    let mut cells = Vec::new();
+    let mut cell = String::new();
+    let mut chars = line.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\\' if chars.peek() == Some(&'|') => {
+                cell.push('|');
+                chars.next();
+            }
+            '|' => cells.push(std::mem::take(&mut cell).trim().to_string()),
+            _ => cell.push(ch),
+        }
+    }
+    cells.push(cell.trim().to_string());
+    cells
+}
+
+/// Text of the `<td>`/`<th>` cells of one row
+fn html_cells(row: &str, lower: &str) -> Vec<String> {
+    let mut cells = Vec::new();
+    let mut pos = 0;
+    loop {
+        let next = [lower[pos..].find("<td"), lower[pos..].find("<th")]
+            .into_iter()
+            .flatten()
+            .min();
+        let Some(open) = next else {
+            break;
+        };
+        let Some(body) = lower[pos + open..].find('>').map(|g| pos + open + g + 1) else {
+            break;
+        };
+        let close = lower[body..].find("</t").map_or(row.len(), |c| body + c);
+        cells.push(html_text(&row[body..close]));
+        pos = close;
+        if pos >= row.len() {
+            break;
+        }
+        pos += 3;
+    }
+    cells
+}
+
+fn html_text(fragment: &str) -> String {
+    let mut text = String::new();
+    let mut in_tag = false;
+    for ch in fragment.chars() {
+        match ch {
+            '<' => {
+                in_tag = true;
+                text.push(' ');
+            }
+            '>' if in_tag => in_tag = false,
+            _ if !in_tag => text.push(ch),
+            _ => {}
+        }
+    }
+    let text = text
+        .replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&");
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tables_are_read_from_markdown_and_html() {
+        let pasted = "Customers to follow up:\n\n| Name | Email | Plan |\n|:--|---|--:|\n\
+                      | Acme | ops@acme.io | Pro \\| annual |\n| Globex | | Free |\n\nThanks";
+        let table = Table::from_markdown(pasted).unwrap();
+        assert_eq!(table.headers, vec!["Name", "Email", "Plan"]);
+        assert_eq!(table.rows.len(), 2);
+        assert_eq!(table.rows[0][2], "Pro | annual");
+        assert_eq!(table.row_text(1), "Name: Globex\nPlan: Free");
+        // Round trip through the stored form
+        assert_eq!(
+            Table::from_markdown(&table.to_markdown()),
+            Some(table.clone())
+        );
+        assert_eq!(Table::from_markdown("a | b\nno separator"), None);
+
+        let html = "<meta charset=utf-8><TABLE><tbody><tr><th>Name</th><th>Email</th></tr>\
+                    <tr><td><b>Acme</b> &amp; Co</td><td>ops@acme.io</td></tr>\
+                    <tr><td>Initech</td></tr></tbody></TABLE>";
+        let table = detect("Name\tEmail", Some(html)).unwrap();
+        assert_eq!(table.headers, vec!["Name", "Email"]);
+        assert_eq!(
+            table.rows,
+            vec![
+                vec!["Acme & Co".to_string(), "ops@acme.io".to_string()],
+                vec!["Initech".to_string(), String::new()],
+            ]
+        );
+        assert_eq!(detect("just text", Some("<p>no table</p>")), None);
+        assert!(is_table_document("Pasted table.TABLE.md"));
+    }
+
+    #[test]
+    fn test_datasets_become_rows_with_typed_properties() {
+        let csv = "\u{feff}sku;name;price;stock;active;zip;updated\r\n\
+                   A-1;\"Desk; oak\";129.5;4;true;02134;2024-03-01\r\n\
+                   A-2;\"Lamp \"\"Nova\"\"\nwhite\";35;0;FALSE;10001;2024-03-02\r\n\r\n";
+        let table = Table::from_csv(csv).unwrap();
+        assert_eq!(table.headers[0], "sku");
+        assert_eq!(table.rows.len(), 2);
+        assert_eq!(table.rows[0][1], "Desk; oak");
+        assert_eq!(
+            table.row_text(1).lines().nth(1),
+            Some("name: Lamp \"Nova\" white")
+        );
+        assert!(is_csv_document("export.CSV"));
+
+        let rows: Vec<_> = (0..2).map(|i| row_cells(&table.row_text(i))).collect();
+        let typed = typed_properties(&rows);
+        // Integers and decimals in one column are numbers
+        assert_eq!(typed[1]["price"], serde_json::json!(35.0));
+        assert_eq!(typed[0]["stock"], serde_json::json!(4));
+        assert_eq!(typed[1]["active"], serde_json::json!(false));
+        assert_eq!(typed[0]["zip"], serde_json::json!("02134"));
+        assert_eq!(typed[0]["updated"], serde_json::json!("2024-03-01"));
+        assert_eq!(ColumnType::of("2024-03-01T10:00:00Z"), ColumnType::Date);
+
+        let records = serde_json::json!([
+            {"id": 1, "city": "Lyon", "tags": ["a"]},
+            {"id": 2, "country": "FR", "city": null}
+        ]);
+        let table = Table::from_records(&records).unwrap();
+        assert_eq!(table.headers, vec!["city", "id", "tags", "country"]);
+        assert_eq!(table.row_text(0), "city: Lyon\nid: 1\ntags: [\"a\"]");
+        assert_eq!(table.row_text(1), "id: 2\ncountry: FR");
+        assert_eq!(Table::from_records(&serde_json::json!({"id": 1})), None);
+        assert_eq!(Table::from_records(&serde_json::json!([1, 2])), None);
+    }
+}
@@ -0,0 +1,172 @@
+use crate::models::app::AppError;
+use crate::models::crm::{Customer, Deal, Lead};
+use crate::models::tasks::{CrmLink, CrmRecordType};
+use crate::models::{Message, MessageRole};
+use crate::utils::datetime::{resolve_relative_date, CivilDate};
+use serde::Deserialize;
+
+/// Action item as returned by the model, before it is turned into a `Task`
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct ExtractedTask {
+    pub title: String,
+    #[serde(default)]
+    pub due: Option<String>,
+    #[serde(default)]
+    pub related_to: Option<String>,
+}
+
+const EXTRACTION_INSTRUCTIONS: &str = "You extract action items from conversations. \
+Reply ONLY with a JSON array, no prose. Each element must be an object with the keys \
+\"title\" (short imperative sentence), \"due\" (YYYY-MM-DD, a phrase such as \"next Tuesday\", or null) and \"related_to\" \
+(customer, lead or deal name mentioned for the task, or null). Reply with [] when there \
+are no action items.";
+
+/// Helpers for turning a conversation into a task list via structured LLM output
+pub struct TaskExtractionUtils;
+
+impl TaskExtractionUtils {
+    /// Build the message list sent to the model for action item extraction; `today` is
+    /// written as "Friday, 2026-10-16"
+    pub fn build_extraction_messages(messages: &[Message], today: &str) -> Vec<Message> {
+        let mut transcript = String::new();
+        for m in messages {
+            let speaker = match m.role {
+                MessageRole::User => "User",
+                MessageRole::Assistant => "Assistant",
+                MessageRole::System => continue,
+            };
+            transcript.push_str(speaker);
+            transcript.push_str(": ");
+            transcript.push_str(m.content.trim());
+            transcript.push('\n');
+        }
+
+        vec![
+            Message::new(
+                MessageRole::System,
+                format!("{} Today is {}.", EXTRACTION_INSTRUCTIONS, today),
+            ),
+            Message::new(
+                MessageRole::User,
+                format!("Conversation:\n{}\nAction items as JSON:", transcript),
+            ),
+        ]
+    }
+
+    /// Parse the model reply, tolerating code fences and surrounding prose
+    pub fn parse_action_items(response: &str) -> Result<Vec<ExtractedTask>, AppError> {
+        let start = response.find('[');
+        let end = response.rfind(']');
+        let json = match (start, end) {
+            (Some(s), Some(e)) if e > s => &response[s..=e],
+            _ => {
+                return Err(AppError::SerializationError(
+                    "No JSON array found in model output".to_string(),
+                ))
+            }
+        };
+
+        let items: Vec<ExtractedTask> = serde_json::from_str(json)
+            .map_err(|e| AppError::SerializationError(format!("Invalid action items: {}", e)))?;
+
+        Ok(items
+            .into_iter()
+            .filter_map(|mut t| {
+                t.title = t.title.trim().to_string();
+                if t.title.is_empty() {
+                    return None;
+                }
+                t.due = t.due.filter(|d| !d.trim().is_empty());
+                t.related_to = t.related_to.filter(|r| !r.trim().is_empty());
+                Some(t)
+            })
+            .collect())
+    }
+
+    /// Convert a `YYYY-MM-DD` date into a UTC midnight timestamp in milliseconds
+    pub fn parse_due_date(date: &str) -> Option<f64> {
+        CivilDate::parse_iso(date).map(CivilDate::timestamp_ms)
+    }
+
+    /// Like `parse_due_date`, also accepting phrases such as "next Tuesday" relative to
+    /// `today`
+    pub fn resolve_due_date(due: &str, today: CivilDate) -> Option<f64> {
+        resolve_relative_date(due, today).map(CivilDate::timestamp_ms)
+    }
+
+    /// Resolve a free-text record mention to a CRM record by name/title
+    pub fn match_crm_record(
+        hint: &str,
+        customers: &[Customer],
+        leads: &[Lead],
+        deals: &[Deal],
+    ) -> Option<CrmLink> {
+        let needle = hint.trim().to_lowercase();
+        if needle.is_empty() {
+            return None;
+        }
+        let matches = |name: &str| {
+            let hay = name.to_lowercase();
+            !hay.is_empty() && (hay.contains(&needle) || needle.contains(&hay))
+        };
+
+        if let Some(d) = deals.iter().find(|d| matches(&d.title)) {
+            return Some(CrmLink {
+                record_type: CrmRecordType::Deal,
+                record_id: d.id.clone(),
+            });
+        }
+        if let Some(l) = leads.iter().find(|l| matches(&l.name)) {
+            return Some(CrmLink {
+                record_type: CrmRecordType::Lead,
+                record_id: l.id.clone(),
+            });
+        }
+        customers
+            .iter()
+            .find(|c| matches(&c.name) || c.company.as_deref().map(matches).unwrap_or(false))
+            .map(|c| CrmLink {
+                record_type: CrmRecordType::Customer,
+                record_id: c.id.clone(),
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_action_items_with_fences() {
+        let out = "Sure!\n```json\n[{\"title\": \"Send proposal\", \"due\": \"2024-03-01\", \"related_to\": \"Acme\"}, {\"title\": \"  \"}]\n```";
+        let items = TaskExtractionUtils::parse_action_items(out).unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].title, "Send proposal");
+        assert_eq!(items[0].due.as_deref(), Some("2024-03-01"));
+        assert_eq!(items[0].related_to.as_deref(), Some("Acme"));
+    }
+
+    #[test]
+    fn test_parse_action_items_rejects_prose() {
+        assert!(TaskExtractionUtils::parse_action_items("No tasks here.").is_err());
+        assert!(TaskExtractionUtils::parse_action_items("[]")
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_parse_due_date() {
+        assert_eq!(TaskExtractionUtils::parse_due_date("1970-01-01"), Some(0.0));
+        assert_eq!(
+            TaskExtractionUtils::parse_due_date("2024-03-01"),
+            Some(1_709_251_200_000.0)
+        );
+        assert_eq!(TaskExtractionUtils::parse_due_date("2024-13-01"), None);
+        assert_eq!(TaskExtractionUtils::parse_due_date("tomorrow"), None);
+        let friday = CivilDate::new(2026, 10, 16).unwrap();
+        assert_eq!(
+            TaskExtractionUtils::resolve_due_date("next Tuesday", friday),
+            TaskExtractionUtils::parse_due_date("2026-10-20")
+        );
+    }
+}
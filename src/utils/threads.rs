@@ -0,0 +1,67 @@
+//! Side threads on a message. A thread does not see the rest of the conversation:
+//! the model gets the message the thread replies to and the thread's own history.
+
+use crate::models::{Message, MessageRole};
+
+// Characters of the parent message quoted to the model
+const PARENT_CHARS: usize = 4000;
+/// Thread messages sent with each side question, most recent kept
+pub const THREAD_HISTORY: usize = 12;
+
+/// Messages for the next reply in a thread on `parent`, ending with the thread history
+pub fn thread_context(parent: &Message, history: &[Message]) -> Vec<Message> {
+    let author = match parent.role {
+        MessageRole::User => "the user",
+        _ => "the assistant",
+    };
+    let mut quoted: String = parent.content.trim().chars().take(PARENT_CHARS).collect();
+    if parent.content.trim().chars().count() > PARENT_CHARS {
+        quoted.push('…');
+    }
+    let mut out = vec![Message::new(
+        MessageRole::System,
+        format!(
+            "This is a side thread about one earlier message, written by {}. Answer the \
+             questions in the thread about that message; stay brief.\n\nMessage:\n{}",
+            author, quoted
+        ),
+    )];
+    let turns: Vec<&Message> = history
+        .iter()
+        .filter(|m| m.role != MessageRole::System)
+        .collect();
+    let skip = turns.len().saturating_sub(THREAD_HISTORY);
+    out.extend(turns.into_iter().skip(skip).cloned());
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_thread_context_quotes_the_parent_and_keeps_recent_turns() {
+        let parent = Message::new(
+            MessageRole::Assistant,
+            "Refunds are issued within 30 days.".to_string(),
+        );
+        let mut history = vec![Message::new(MessageRole::System, "stray".to_string())];
+        for i in 0..THREAD_HISTORY + 2 {
+            let role = if i % 2 == 0 {
+                MessageRole::User
+            } else {
+                MessageRole::Assistant
+            };
+            history.push(Message::new(role, format!("turn {}", i)));
+        }
+
+        let context = thread_context(&parent, &history);
+        assert_eq!(context.len(), THREAD_HISTORY + 1);
+        assert_eq!(context[0].role, MessageRole::System);
+        assert!(context[0].content.contains("written by the assistant"));
+        assert!(context[0].content.ends_with("within 30 days."));
+        assert_eq!(context[1].content, "turn 2");
+        assert_eq!(context.last().unwrap().content, "turn 13");
+        assert!(context[1..].iter().all(|m| m.role != MessageRole::System));
+    }
+}
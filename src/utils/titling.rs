@@ -0,0 +1,120 @@
+//! Naming conversations after their first exchange. Once the first reply is saved the
+//! loaded model is asked for a short title; whatever it returns is cleaned up here, and
+//! a reply that cannot be used falls back to the start of the first question.
+
+use crate::models::{Message, MessageRole};
+
+/// Title new conversations are created with; only these are named automatically
+pub const DEFAULT_TITLE: &str = "New Chat";
+
+const MAX_TITLE_WORDS: usize = 8;
+const MAX_TITLE_CHARS: usize = 60;
+// Characters of each side of the exchange shown to the model
+const EXCERPT_CHARS: usize = 600;
+
+/// Whether a conversation still carries the default title and has just had its
+/// first exchange
+pub fn needs_title(title: &str, messages: &[Message]) -> bool {
+    let count = |role: MessageRole| messages.iter().filter(|m| m.role == role).count();
+    title == DEFAULT_TITLE && count(MessageRole::User) == 1 && count(MessageRole::Assistant) == 1
+}
+
+/// Messages asking the model to name a conversation from its first exchange
+pub fn title_request(question: &str, reply: &str) -> Vec<Message> {
+    let excerpt = |s: &str| s.chars().take(EXCERPT_CHARS).collect::<String>();
+    vec![
+        Message::new(
+            MessageRole::System,
+            "Write a title of at most 6 words for the conversation below. Reply with the \
+             title only: no quotes, no trailing punctuation, no preamble."
+                .to_string(),
+        ),
+        Message::new(
+            MessageRole::User,
+            format!(
+                "User: {}\n\nAssistant: {}",
+                excerpt(question),
+                excerpt(reply)
+            ),
+        ),
+    ]
+}
+
+fn shorten(text: &str) -> String {
+    let mut title = String::new();
+    for word in text.split_whitespace().take(MAX_TITLE_WORDS) {
+        if title.chars().count() + word.chars().count() + 1 > MAX_TITLE_CHARS {
+            break;
+        }
+        if !title.is_empty() {
+            title.push(' ');
+        }
+        title.push_str(word);
+    }
+    title
+}
+
+/// A usable title from the model's reply, or the start of `question` when the reply
+/// is empty or unusable
+pub fn clean_title(raw: &str, question: &str) -> String {
+    let line = raw
+        .lines()
+        .map(str::trim)
+        .find(|l| !l.is_empty())
+        .unwrap_or_default();
+    let line = ["title:", "title -"]
+        .iter()
+        .find_map(|p| {
+            line.get(..p.len())
+                .filter(|head| head.eq_ignore_ascii_case(p))
+                .map(|_| &line[p.len()..])
+        })
+        .unwrap_or(line);
+    let line = line
+        .trim()
+        .trim_matches(|c: char| matches!(c, '"' | '\'' | '*' | '#' | '`' | '“' | '”'))
+        .trim_end_matches(['.', ':', '!'])
+        .trim();
+    let title = shorten(line);
+    if title.is_empty() || title.eq_ignore_ascii_case(DEFAULT_TITLE) {
+        let fallback = shorten(question);
+        if fallback.is_empty() {
+            return DEFAULT_TITLE.to_string();
+        }
+        return fallback;
+    }
+    title
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_titles_are_cleaned_and_fall_back_to_the_question() {
+        let exchange = vec![
+            Message::new(MessageRole::User, "How do refunds work?".to_string()),
+            Message::new(MessageRole::Assistant, "Within 30 days.".to_string()),
+        ];
+        assert!(needs_title(DEFAULT_TITLE, &exchange));
+        assert!(!needs_title("Refund policy", &exchange));
+        assert!(!needs_title(DEFAULT_TITLE, &exchange[..1]));
+
+        assert_eq!(
+            clean_title("Title: \"Refund Policy Overview.\"\nMore text", ""),
+            "Refund Policy Overview"
+        );
+        assert_eq!(
+            clean_title(
+                "A very long title that goes on and on well past the limit",
+                ""
+            ),
+            "A very long title that goes on and"
+        );
+        assert_eq!(
+            clean_title("  \n", "How do refunds work?"),
+            "How do refunds work?"
+        );
+        assert_eq!(clean_title("", ""), DEFAULT_TITLE);
+    }
+}
@@ -0,0 +1,516 @@
+//! Tools the model can call while answering. The enabled tools are described in a system
+//! message; the model calls one by replying with a `<tool_call>` block (the format
+//! Hermes and Qwen models are trained on). Results go back as a `<tool_response>`
+//! message and the model answers again, for at most `ToolSettings::max_rounds` rounds.
+
+use crate::features::graphrag::retrieval::Retriever;
+use crate::models::graphrag::{RAGQuery, SearchStrategy};
+use crate::models::{Customer, GenerationSettings, Lead, Message, MessageRole, ToolSettings};
+use crate::utils::datetime::{is_valid_timezone, resolve_relative_date, LocalNow};
+use crate::webllm_binding::{send_message_to_llm_streaming, CompletionUsage};
+use serde_json::Value;
+use wasm_bindgen::JsValue;
+
+const CALL_OPEN: &str = "<tool_call>";
+const CALL_CLOSE: &str = "</tool_call>";
+/// Knowledge results returned by `search_knowledge`
+const SEARCH_RESULTS: usize = 5;
+const SEARCH_SNIPPET_CHARS: usize = 400;
+/// CRM records returned by `lookup_crm_contact`
+const CONTACT_RESULTS: usize = 5;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Tool {
+    SearchKnowledge,
+    LookupCrmContact,
+    CurrentTime,
+    ResolveDate,
+}
+
+pub const TOOLS: [Tool; 4] = [
+    Tool::SearchKnowledge,
+    Tool::LookupCrmContact,
+    Tool::CurrentTime,
+    Tool::ResolveDate,
+];
+
+impl Tool {
+    pub fn name(self) -> &'static str {
+        match self {
+            Tool::SearchKnowledge => "search_knowledge",
+            Tool::LookupCrmContact => "lookup_crm_contact",
+            Tool::CurrentTime => "current_time",
+            Tool::ResolveDate => "resolve_date",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Tool::SearchKnowledge => "Search the user's documents and return the best passages",
+            Tool::LookupCrmContact => {
+                "Find customers and leads in the CRM by name, email or company"
+            }
+            Tool::CurrentTime => "Current local date and time",
+            Tool::ResolveDate => {
+                "Turn a date phrase such as \"next friday\" or \"in 3 days\" into a calendar date"
+            }
+        }
+    }
+
+    /// String arguments as (name, description); optional ones say so
+    pub fn parameters(self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Tool::SearchKnowledge => &[("query", "what to search for")],
+            Tool::LookupCrmContact => &[("query", "name, email or company")],
+            Tool::CurrentTime => &[("timezone", "optional IANA timezone, e.g. Europe/Rome")],
+            Tool::ResolveDate => &[("phrase", "the date phrase to resolve")],
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        TOOLS.into_iter().find(|t| t.name() == name)
+    }
+}
+
+/// A call parsed from a model reply
+#[derive(Clone, Debug, PartialEq)]
+pub struct ToolCall {
+    pub name: String,
+    pub arguments: Value,
+}
+
+impl ToolCall {
+    fn argument(&self, key: &str) -> Option<&str> {
+        self.arguments
+            .get(key)
+            .and_then(Value::as_str)
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ToolOutcome {
+    pub name: String,
+    pub output: String,
+    pub ok: bool,
+}
+
+/// App data the tools read, snapshotted when the reply starts
+#[derive(Clone, Debug)]
+pub struct ToolContext {
+    pub search_strategy: SearchStrategy,
+    /// Timezone from the date settings; `None` uses the browser's
+    pub timezone: Option<String>,
+    pub customers: Vec<Customer>,
+    pub leads: Vec<Lead>,
+}
+
+/// The tools offered to the model
+#[derive(Clone, Debug, PartialEq)]
+pub struct ToolRegistry {
+    tools: Vec<Tool>,
+    max_rounds: u8,
+}
+
+impl ToolRegistry {
+    pub fn new(settings: &ToolSettings) -> Self {
+        let tools = if settings.enabled {
+            TOOLS
+                .into_iter()
+                .filter(|t| !settings.disabled.iter().any(|d| d == t.name()))
+                .collect()
+        } else {
+            Vec::new()
+        };
+        Self {
+            tools,
+            max_rounds: settings.max_rounds,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tools.is_empty() || self.max_rounds == 0
+    }
+
+    /// System message advertising the tools and the call format
+    pub fn system_message(&self) -> Option<Message> {
+        self.system_prompt()
+            .map(|text| Message::new(MessageRole::System, text))
+    }
+
+    fn system_prompt(&self) -> Option<String> {
+        if self.is_empty() {
+            return None;
+        }
+        let mut text = String::from("You can call these tools when they help you answer:\n");
+        for tool in &self.tools {
+            let params = tool
+                .parameters()
+                .iter()
+                .map(|(name, desc)| format!("{}: {}", name, desc))
+                .collect::<Vec<_>>()
+                .join("; ");
+            text.push_str(&format!(
+                "- {}: {}. Arguments: {}\n",
+                tool.name(),
+                tool.description(),
+                params
+            ));
+        }
+        text.push_str(&format!(
+            "To call a tool, reply with only:\n{}{{\"name\": \"<tool name>\", \"arguments\": {{\"<argument>\": \"<value>\"}}}}{}\n",
+            CALL_OPEN, CALL_CLOSE
+        ));
+        text.push_str(
+            "Results come back in <tool_response> blocks. When no tool is needed, answer directly.",
+        );
+        Some(text)
+    }
+
+    pub async fn execute(&self, call: &ToolCall, ctx: &ToolContext) -> ToolOutcome {
+        let result = match Tool::from_name(&call.name).filter(|t| self.tools.contains(t)) {
+            None => Err(format!("Unknown tool: {}", call.name)),
+            Some(Tool::SearchKnowledge) => match call.argument("query") {
+                Some(query) => Ok(search_knowledge(query, ctx.search_strategy.clone()).await),
+                None => Err("Missing argument: query".to_string()),
+            },
+            Some(Tool::LookupCrmContact) => call
+                .argument("query")
+                .map(|q| lookup_contacts(q, &ctx.customers, &ctx.leads))
+                .ok_or_else(|| "Missing argument: query".to_string()),
+            Some(Tool::CurrentTime) => {
+                let timezone = call
+                    .argument("timezone")
+                    .filter(|tz| is_valid_timezone(tz))
+                    .or(ctx.timezone.as_deref());
+                Ok(describe_now(&LocalNow::current(timezone)))
+            }
+            Some(Tool::ResolveDate) => match call.argument("phrase") {
+                Some(phrase) => {
+                    let today = LocalNow::current(ctx.timezone.as_deref()).date;
+                    resolve_relative_date(phrase, today)
+                        .map(|d| format!("{} ({})", d.iso(), d.weekday_name()))
+                        .ok_or_else(|| format!("Could not resolve \"{}\" to a date", phrase))
+                }
+                None => Err("Missing argument: phrase".to_string()),
+            },
+        };
+        let ok = result.is_ok();
+        ToolOutcome {
+            name: call.name.clone(),
+            output: result.unwrap_or_else(|e| e),
+            ok,
+        }
+    }
+}
+
+/// Outcome of a completion that may have called tools
+pub struct ToolRun {
+    pub result: Result<(String, CompletionUsage), JsValue>,
+    /// Messages of the last round, tool results included, for a retry
+    pub messages: Vec<Message>,
+    pub outcomes: Vec<ToolOutcome>,
+}
+
+/// Stream completions, running the tools the model calls and sending their results
+/// back, until it answers without calling one or runs out of rounds. `on_call` runs
+/// before each call; usage is summed over all rounds.
+pub async fn complete_with_tools<F>(
+    engine: &JsValue,
+    mut messages: Vec<Message>,
+    settings: &GenerationSettings,
+    registry: &ToolRegistry,
+    ctx: &ToolContext,
+    mut on_delta: F,
+    on_call: impl Fn(&ToolCall),
+) -> ToolRun
+where
+    F: FnMut(&str),
+{
+    let mut outcomes = Vec::new();
+    let mut usage = CompletionUsage::default();
+    let mut round = 0;
+    loop {
+        let result =
+            send_message_to_llm_streaming(engine, messages.clone(), settings, &mut on_delta).await;
+        let (text, round_usage) = match result {
+            Ok(r) => r,
+            Err(e) => {
+                return ToolRun {
+                    result: Err(e),
+                    messages,
+                    outcomes,
+                }
+            }
+        };
+        usage = add_usage(usage, &round_usage);
+        let calls = parse_tool_calls(&text);
+        if registry.is_empty() || calls.is_empty() || round >= registry.max_rounds {
+            return ToolRun {
+                result: Ok((strip_tool_calls(&text), usage)),
+                messages,
+                outcomes,
+            };
+        }
+        round += 1;
+        let mut results = Vec::new();
+        for call in &calls {
+            on_call(call);
+            results.push(registry.execute(call, ctx).await);
+        }
+        messages.push(Message::new(MessageRole::Assistant, text));
+        messages.push(response_message(&results, round >= registry.max_rounds));
+        outcomes.extend(results);
+    }
+}
+
+fn add_usage(total: CompletionUsage, round: &CompletionUsage) -> CompletionUsage {
+    let sum = |a: Option<u32>, b: Option<u32>| match (a, b) {
+        (None, None) => None,
+        (a, b) => Some(a.unwrap_or(0) + b.unwrap_or(0)),
+    };
+    CompletionUsage {
+        prompt_tokens: sum(total.prompt_tokens, round.prompt_tokens),
+        completion_tokens: sum(total.completion_tokens, round.completion_tokens),
+        decode_tokens_per_sec: round.decode_tokens_per_sec.or(total.decode_tokens_per_sec),
+        prefill_tokens_per_sec: round
+            .prefill_tokens_per_sec
+            .or(total.prefill_tokens_per_sec),
+        prefill_reused_tokens: total.prefill_reused_tokens + round.prefill_reused_tokens,
+    }
+}
+
+/// Calls in a reply, in order. Arguments may be an object or a JSON-encoded string;
+/// an unterminated block at the end (cut by a stop sequence) still counts.
+pub fn parse_tool_calls(text: &str) -> Vec<ToolCall> {
+    let mut calls = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find(CALL_OPEN) {
+        let body = &rest[start + CALL_OPEN.len()..];
+        let (inner, next) = match body.find(CALL_CLOSE) {
+            Some(end) => (&body[..end], &body[end + CALL_CLOSE.len()..]),
+            None => (body, ""),
+        };
+        let inner = inner
+            .trim()
+            .trim_start_matches("```json")
+            .trim_matches('`')
+            .trim();
+        if let Ok(value) = serde_json::from_str::<Value>(inner) {
+            if let Some(name) = value.get("name").and_then(Value::as_str) {
+                let arguments = match value.get("arguments") {
+                    Some(Value::String(s)) => serde_json::from_str(s).unwrap_or(Value::Null),
+                    Some(v) => v.clone(),
+                    None => Value::Null,
+                };
+                calls.push(ToolCall {
+                    name: name.to_string(),
+                    arguments,
+                });
+            }
+        }
+        rest = next;
+    }
+    calls
+}
+
+/// Reply text with any tool call blocks removed
+pub fn strip_tool_calls(text: &str) -> String {
+    let mut out = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find(CALL_OPEN) {
+        out.push_str(&rest[..start]);
+        rest = rest[start..]
+            .find(CALL_CLOSE)
+            .map(|end| &rest[start + end + CALL_CLOSE.len()..])
+            .unwrap_or("");
+    }
+    out.push_str(rest);
+    out.trim().to_string()
+}
+
+/// Tool results as the message that follows the model's calls
+fn response_message(outcomes: &[ToolOutcome], last_round: bool) -> Message {
+    let mut text = outcomes
+        .iter()
+        .map(|o| {
+            let key = if o.ok { "content" } else { "error" };
+            let body = serde_json::json!({ "name": o.name, key: o.output });
+            format!("<tool_response>\n{}\n</tool_response>", body)
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    if last_round {
+        text.push_str("\nNo more tool calls are available; answer the user now.");
+    }
+    Message::new(MessageRole::User, text)
+}
+
+async fn search_knowledge(query: &str, strategy: SearchStrategy) -> String {
+    let mut q = RAGQuery::new(query.to_string());
+    q.config.max_results = SEARCH_RESULTS;
+    let result = Retriever::new().search(&q, strategy).await;
+    if result.nodes.is_empty() {
+        return "No matching passages.".to_string();
+    }
+    result
+        .nodes
+        .iter()
+        .take(SEARCH_RESULTS)
+        .map(|n| {
+            let snippet: String = n.content.chars().take(SEARCH_SNIPPET_CHARS).collect();
+            format!(
+                "[{}] {}",
+                n.metadata.source.as_deref().unwrap_or("Untitled source"),
+                snippet.trim()
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Customers and leads whose name, email or company contains `query`
+pub fn lookup_contacts(query: &str, customers: &[Customer], leads: &[Lead]) -> String {
+    let needle = query.to_lowercase();
+    let matches = |fields: [Option<&str>; 3]| {
+        fields
+            .into_iter()
+            .flatten()
+            .any(|f| f.to_lowercase().contains(&needle))
+    };
+    let optional = |label: &str, value: &Option<String>| {
+        value
+            .as_ref()
+            .map(|v| format!(", {}: {}", label, v))
+            .unwrap_or_default()
+    };
+    let customer_lines = customers
+        .iter()
+        .filter(|c| {
+            matches([
+                Some(c.name.as_str()),
+                c.email.as_deref(),
+                c.company.as_deref(),
+            ])
+        })
+        .map(|c| {
+            format!(
+                "Customer {} (status: {:?}{}{}{})",
+                c.name,
+                c.status,
+                optional("company", &c.company),
+                optional("email", &c.email),
+                optional("phone", &c.phone)
+            )
+        });
+    let lead_lines = leads
+        .iter()
+        .filter(|l| {
+            matches([
+                Some(l.name.as_str()),
+                l.email.as_deref(),
+                l.company.as_deref(),
+            ])
+        })
+        .map(|l| {
+            format!(
+                "Lead {} (status: {:?}{}{}{}{})",
+                l.name,
+                l.status,
+                optional("company", &l.company),
+                optional("email", &l.email),
+                optional("phone", &l.phone),
+                optional("owner", &l.owner)
+            )
+        });
+    let lines: Vec<String> = customer_lines
+        .chain(lead_lines)
+        .take(CONTACT_RESULTS)
+        .collect();
+    if lines.is_empty() {
+        format!("No CRM contact matches \"{}\".", query)
+    } else {
+        lines.join("\n")
+    }
+}
+
+fn describe_now(now: &LocalNow) -> String {
+    format!(
+        "{}, {} {:02}:{:02} ({}, {})",
+        now.date.weekday_name(),
+        now.date.iso(),
+        now.hour,
+        now.minute,
+        now.timezone,
+        now.utc_offset()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::crm::{CustomerStatus, LeadSource, LeadStatus};
+
+    #[test]
+    fn test_parse_tool_calls() {
+        let text = "Let me check.\n<tool_call>{\"name\": \"resolve_date\", \"arguments\": {\"phrase\": \"next friday\"}}</tool_call>\n<tool_call>\n{\"name\": \"current_time\", \"arguments\": \"{}\"}";
+        let calls = parse_tool_calls(text);
+        assert_eq!(calls.len(), 2);
+        assert_eq!(calls[0].name, "resolve_date");
+        assert_eq!(calls[0].argument("phrase"), Some("next friday"));
+        assert_eq!(calls[1].name, "current_time");
+        assert!(parse_tool_calls("<tool_call>not json</tool_call>").is_empty());
+        assert_eq!(strip_tool_calls(text), "Let me check.");
+    }
+
+    #[test]
+    fn test_lookup_contacts_matches_any_field() {
+        let customer = Customer {
+            id: "c1".to_string(),
+            name: "Ada Lovelace".to_string(),
+            email: None,
+            phone: None,
+            company: Some("Analytical Engines".to_string()),
+            status: CustomerStatus::Active,
+            created_at: 0.0,
+            updated_at: 0.0,
+            tags: Vec::new(),
+            custom_fields: Default::default(),
+        };
+        let lead = Lead {
+            id: "l1".to_string(),
+            name: "Bob Stone".to_string(),
+            email: Some("bob@engines.example".to_string()),
+            phone: None,
+            company: None,
+            source: LeadSource::Website,
+            status: LeadStatus::New,
+            score: None,
+            owner: None,
+            created_at: 0.0,
+            updated_at: 0.0,
+            notes: Vec::new(),
+        };
+        let found = lookup_contacts("engines", &[customer], &[lead]);
+        assert!(
+            found.starts_with("Customer Ada Lovelace (status: Active, company: Analytical Engines")
+        );
+        assert!(found.contains("Lead Bob Stone"));
+        assert!(lookup_contacts("zed", &[], &[]).starts_with("No CRM contact"));
+    }
+
+    #[test]
+    fn test_registry_respects_settings() {
+        assert!(ToolRegistry::new(&ToolSettings::default()).is_empty());
+        let settings = ToolSettings {
+            enabled: true,
+            disabled: vec!["lookup_crm_contact".to_string()],
+            ..Default::default()
+        };
+        let registry = ToolRegistry::new(&settings);
+        let prompt = registry.system_prompt().unwrap();
+        assert!(prompt.contains("search_knowledge"));
+        assert!(!prompt.contains("lookup_crm_contact"));
+    }
+}
@@ -0,0 +1,290 @@
+//! Text-to-speech through the browser's `speechSynthesis`. Text is read as a queue of
+//! segments (paragraphs, split further when long) so playback can report progress,
+//! seek and change speed between segments. Used for replies and documents alike.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{SpeechSynthesis, SpeechSynthesisUtterance};
+
+/// Longest segment handed to the engine; some engines stop long utterances midway
+const MAX_SEGMENT_CHARS: usize = 600;
+pub const MIN_RATE: f32 = 0.5;
+pub const MAX_RATE: f32 = 2.0;
+
+fn synthesis() -> Option<SpeechSynthesis> {
+    web_sys::window().and_then(|w| w.speech_synthesis().ok())
+}
+
+pub fn is_supported() -> bool {
+    synthesis().is_some()
+}
+
+/// Paragraphs of `text` as they should be read: markdown markers removed, blank
+/// paragraphs skipped
+pub fn paragraphs(text: &str) -> Vec<String> {
+    text.split("\n\n")
+        .map(|p| {
+            speakable(p)
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .filter(|p| p.chars().any(char::is_alphanumeric))
+        .collect()
+}
+
+/// `paragraph` cut at sentence ends into segments of at most `MAX_SEGMENT_CHARS`
+pub fn segments(paragraph: &str) -> Vec<String> {
+    let mut out: Vec<String> = Vec::new();
+    let mut current = String::new();
+    for sentence in paragraph.split_inclusive(['.', '!', '?', ';']) {
+        if !current.is_empty() && current.len() + sentence.len() > MAX_SEGMENT_CHARS {
+            out.push(current.trim().to_string());
+            current.clear();
+        }
+        current.push_str(sentence);
+    }
+    if !current.trim().is_empty() {
+        out.push(current.trim().to_string());
+    }
+    out
+}
+
+/// Drop markdown syntax that would be read out literally
+fn speakable(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for line in text.lines() {
+        let line = line
+            .trim_start()
+            .trim_start_matches('#')
+            .trim_start_matches(['-', '*', '>'])
+            .trim_start();
+        if line.starts_with("```") {
+            continue;
+        }
+        // [label](url) reads as its label
+        let mut rest = line;
+        while let Some(open) = rest.find('[') {
+            let Some(close) = rest[open..].find("](").map(|i| open + i) else {
+                break;
+            };
+            let Some(end) = rest[close..].find(')').map(|i| close + i) else {
+                break;
+            };
+            out.push_str(&rest[..open]);
+            out.push_str(&rest[open + 1..close]);
+            rest = &rest[end + 1..];
+        }
+        out.push_str(rest);
+        out.push('\n');
+    }
+    out.replace(['*', '_', '`'], "")
+}
+
+struct NarratorState {
+    /// (paragraph index, segment) in reading order
+    queue: Vec<(usize, String)>,
+    position: usize,
+    rate: f32,
+    /// Bumped on every restart so callbacks of a cancelled run do nothing
+    run: u32,
+    current: Option<SpeechSynthesisUtterance>,
+    callbacks: Vec<Closure<dyn FnMut(JsValue)>>,
+    on_paragraph: Rc<dyn Fn(usize)>,
+    on_done: Rc<dyn Fn()>,
+}
+
+/// Reads a list of paragraphs aloud, one utterance per segment
+#[derive(Clone)]
+pub struct Narrator {
+    state: Rc<RefCell<NarratorState>>,
+}
+
+impl Default for Narrator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Narrator {
+    pub fn new() -> Self {
+        Self {
+            state: Rc::new(RefCell::new(NarratorState {
+                queue: Vec::new(),
+                position: 0,
+                rate: 1.0,
+                run: 0,
+                current: None,
+                callbacks: Vec::new(),
+                on_paragraph: Rc::new(|_| {}),
+                on_done: Rc::new(|| {}),
+            })),
+        }
+    }
+
+    /// Read `paragraphs` from `start`. `on_paragraph` runs as each paragraph begins,
+    /// `on_done` after the last one.
+    pub fn play(
+        &self,
+        paragraphs: &[String],
+        start: usize,
+        rate: f32,
+        on_paragraph: impl Fn(usize) + 'static,
+        on_done: impl Fn() + 'static,
+    ) {
+        self.stop();
+        {
+            let mut st = self.state.borrow_mut();
+            st.queue = paragraphs
+                .iter()
+                .enumerate()
+                .flat_map(|(i, p)| segments(p).into_iter().map(move |s| (i, s)))
+                .collect();
+            st.position = st
+                .queue
+                .iter()
+                .position(|(i, _)| *i >= start)
+                .unwrap_or(st.queue.len());
+            st.rate = rate.clamp(MIN_RATE, MAX_RATE);
+            st.on_paragraph = Rc::new(on_paragraph);
+            st.on_done = Rc::new(on_done);
+        }
+        speak_current(&self.state);
+    }
+
+    /// Continue from the first segment of `paragraph`
+    pub fn seek(&self, paragraph: usize) {
+        self.restart(|st| {
+            st.position = st
+                .queue
+                .iter()
+                .position(|(i, _)| *i >= paragraph)
+                .unwrap_or(st.queue.len());
+        });
+    }
+
+    /// Change the speed, restarting the segment being read
+    pub fn set_rate(&self, rate: f32) {
+        self.restart(|st| st.rate = rate.clamp(MIN_RATE, MAX_RATE));
+    }
+
+    pub fn pause(&self) {
+        if let Some(s) = synthesis() {
+            s.pause();
+        }
+    }
+
+    pub fn resume(&self) {
+        if let Some(s) = synthesis() {
+            s.resume();
+        }
+    }
+
+    /// Stop reading and forget the queue position's callbacks
+    pub fn stop(&self) {
+        let mut st = self.state.borrow_mut();
+        st.run = st.run.wrapping_add(1);
+        detach(&mut st);
+        if let Some(s) = synthesis() {
+            s.cancel();
+        }
+        st.callbacks.clear();
+    }
+
+    fn restart(&self, update: impl FnOnce(&mut NarratorState)) {
+        let active = self.state.borrow().current.is_some();
+        {
+            let mut st = self.state.borrow_mut();
+            st.run = st.run.wrapping_add(1);
+            detach(&mut st);
+            update(&mut st);
+        }
+        if let Some(s) = synthesis() {
+            s.cancel();
+        }
+        if active {
+            speak_current(&self.state);
+        }
+    }
+}
+
+fn detach(st: &mut NarratorState) {
+    if let Some(u) = st.current.take() {
+        u.set_onend(None);
+        u.set_onerror(None);
+    }
+}
+
+fn speak_current(state: &Rc<RefCell<NarratorState>>) {
+    let Some(synth) = synthesis() else {
+        return;
+    };
+    let mut st = state.borrow_mut();
+    let Some((paragraph, text)) = st.queue.get(st.position).cloned() else {
+        st.current = None;
+        let on_done = st.on_done.clone();
+        drop(st);
+        on_done();
+        return;
+    };
+    let Ok(utterance) = SpeechSynthesisUtterance::new_with_text(&text) else {
+        return;
+    };
+    utterance.set_rate(st.rate);
+    let starts_paragraph = st.position == 0 || st.queue[st.position - 1].0 != paragraph;
+    let run = st.run;
+    let next = {
+        let state = state.clone();
+        Closure::wrap(Box::new(move |_: JsValue| {
+            {
+                let mut st = state.borrow_mut();
+                if st.run != run {
+                    return;
+                }
+                st.position += 1;
+            }
+            speak_current(&state);
+        }) as Box<dyn FnMut(JsValue)>)
+    };
+    utterance.set_onend(Some(next.as_ref().unchecked_ref()));
+    // An engine error skips the segment rather than stalling the queue
+    utterance.set_onerror(Some(next.as_ref().unchecked_ref()));
+    st.callbacks.push(next);
+    st.current = Some(utterance.clone());
+    let on_paragraph = st.on_paragraph.clone();
+    drop(st);
+    if starts_paragraph {
+        on_paragraph(paragraph);
+    }
+    synth.speak(&utterance);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paragraphs_drop_markdown() {
+        let text = "# Title\n\nSee the [pricing page](https://x.y) for **details**.\n\n```\n\n- `one`\n- two";
+        assert_eq!(
+            paragraphs(text),
+            vec![
+                "Title".to_string(),
+                "See the pricing page for details.".to_string(),
+                "one two".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_segments_split_at_sentences() {
+        let sentence = "word ".repeat(30).trim_end().to_string() + ". ";
+        let paragraph = sentence.repeat(10);
+        let parts = segments(&paragraph);
+        assert!(parts.len() > 1);
+        assert!(parts.iter().all(|p| p.len() <= MAX_SEGMENT_CHARS));
+        assert_eq!(segments("Short one."), vec!["Short one."]);
+    }
+}
@@ -0,0 +1,278 @@
+//! Web pages added to the knowledge base by URL. The page is fetched from the browser,
+//! directly when the site allows cross-origin reads (CORS) or through a user-provided
+//! proxy, and reduced to its readable text: scripts, navigation, headers, footers and
+//! forms are dropped and the main content is kept with its headings and list items.
+//! The URL names the document, so citations point back to the page.
+
+use regex::Regex;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+
+// Elements dropped with their content before the text is extracted
+const BOILERPLATE_TAGS: &[&str] = &[
+    "script", "style", "noscript", "template", "svg", "iframe", "nav", "header", "footer", "aside",
+    "form", "button", "select",
+];
+
+/// Readable content of a fetched page
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct WebPage {
+    pub title: Option<String>,
+    pub text: String,
+}
+
+impl WebPage {
+    /// Text stored in the upload buffer, led by the page title
+    pub fn document(&self) -> String {
+        match &self.title {
+            Some(title) => format!("# {}\n\n{}", title, self.text),
+            None => self.text.clone(),
+        }
+    }
+}
+
+/// Whether a document name is the URL of a web page
+pub fn is_web_source(name: &str) -> bool {
+    name.starts_with("http://") || name.starts_with("https://")
+}
+
+/// URL entered by the user, checked and without its fragment, which would clash with
+/// the `#` passage anchors of document ids
+pub fn normalize_url(input: &str) -> Result<String, String> {
+    let url = input.trim();
+    let url = url.split_once('#').map_or(url, |(page, _)| page);
+    if !is_web_source(url) {
+        return Err("Enter a URL starting with http:// or https://".to_string());
+    }
+    if url.contains(char::is_whitespace)
+        || url
+            .split_once("://")
+            .is_none_or(|(_, host)| host.is_empty())
+    {
+        return Err(format!("Not a valid URL: {}", url));
+    }
+    Ok(url.to_string())
+}
+
+/// Address to fetch `url` through `proxy`. A `{url}` placeholder in the proxy is
+/// replaced by the encoded URL; otherwise the encoded URL is appended.
+pub fn proxied(url: &str, proxy: Option<&str>) -> String {
+    match proxy.map(str::trim).filter(|p| !p.is_empty()) {
+        Some(proxy) if proxy.contains("{url}") => proxy.replace("{url}", &percent_encode(url)),
+        Some(proxy) => format!("{}{}", proxy, percent_encode(url)),
+        None => url.to_string(),
+    }
+}
+
+fn percent_encode(text: &str) -> String {
+    text.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// Readable text of an HTML page
+pub fn readable_text(html: &str) -> WebPage {
+    let title = Regex::new(r"(?is)<title[^>]*>(.*?)</title>")
+        .expect("valid title regex")
+        .captures(html)
+        .map(|c| collapse_spaces(&decode_entities(&c[1])))
+        .filter(|t| !t.is_empty());
+
+    let mut html = Regex::new(r"(?s)<!--.*?-->")
+        .expect("valid comment regex")
+        .replace_all(html, "")
+        .into_owned();
+    for tag in BOILERPLATE_TAGS {
+        let element = Regex::new(&format!(r"(?is)<{tag}\b[^>]*>.*?</{tag}\s*>"))
+            .expect("valid element regex");
+        html = element.replace_all(&html, "\n").into_owned();
+    }
+
+    // The main content when the page marks it, the body otherwise
+    let body = ["main", "article", "body"]
+        .iter()
+        .find_map(|tag| {
+            Regex::new(&format!(r"(?is)<{tag}\b[^>]*>(.*)</{tag}\s*>"))
+                .expect("valid content regex")
+                .captures(&html)
+                .map(|c| c[1].to_string())
+        })
+        .unwrap_or(html);
+    // Line breaks in the source are not breaks on the page
+    let body = collapse_spaces(&body);
+
+    let body = Regex::new(r"(?is)<h([1-6])\b[^>]*>(.*?)</h[1-6]\s*>")
+        .expect("valid heading regex")
+        .replace_all(&body, |c: &regex::Captures| {
+            let level = c[1].parse::<usize>().unwrap_or(1);
+            format!("\n\n{} {}\n\n", "#".repeat(level), c[2].trim())
+        });
+    let body = Regex::new(r"(?i)<li\b[^>]*>")
+        .expect("valid list regex")
+        .replace_all(&body, "\n- ");
+    let body = Regex::new(
+        r"(?i)</?(p|div|section|article|main|br|hr|tr|table|ul|ol|dl|dt|dd|blockquote|pre|figure|figcaption)\b[^>]*>",
+    )
+    .expect("valid block regex")
+    .replace_all(&body, "\n");
+    let body = Regex::new(r"<[^>]*>")
+        .expect("valid tag regex")
+        .replace_all(&body, "");
+    let body = decode_entities(&body);
+
+    let mut text = String::new();
+    let mut blank = false;
+    for line in body.lines().map(collapse_spaces) {
+        if line.is_empty() || line == "-" {
+            blank = !text.is_empty();
+            continue;
+        }
+        if blank {
+            text.push('\n');
+            blank = false;
+        }
+        text.push_str(&line);
+        text.push('\n');
+    }
+    WebPage {
+        title,
+        text: text.trim_end().to_string(),
+    }
+}
+
+fn collapse_spaces(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn decode_entities(text: &str) -> String {
+    Regex::new(r"&(#[0-9]+|#[xX][0-9a-fA-F]+|[a-zA-Z]+);")
+        .expect("valid entity regex")
+        .replace_all(text, |c: &regex::Captures| {
+            let entity = &c[1];
+            let code = if let Some(hex) = entity.strip_prefix("#x").or(entity.strip_prefix("#X")) {
+                u32::from_str_radix(hex, 16).ok()
+            } else if let Some(dec) = entity.strip_prefix('#') {
+                dec.parse().ok()
+            } else {
+                match entity {
+                    "amp" => Some('&' as u32),
+                    "lt" => Some('<' as u32),
+                    "gt" => Some('>' as u32),
+                    "quot" => Some('"' as u32),
+                    "apos" => Some('\'' as u32),
+                    "nbsp" => Some(' ' as u32),
+                    "mdash" => Some('—' as u32),
+                    "ndash" => Some('–' as u32),
+                    "hellip" => Some('…' as u32),
+                    "rsquo" => Some('’' as u32),
+                    "lsquo" => Some('‘' as u32),
+                    "rdquo" => Some('”' as u32),
+                    "ldquo" => Some('“' as u32),
+                    _ => None,
+                }
+            };
+            code.and_then(char::from_u32)
+                .map_or_else(|| c[0].to_string(), |ch| ch.to_string())
+        })
+        .into_owned()
+}
+
+/// Fetch `url`, through `proxy` when set, and extract its readable text. Plain text
+/// responses are kept as they are; other content types are refused.
+pub async fn fetch_page(url: &str, proxy: Option<&str>) -> Result<WebPage, String> {
+    let window = web_sys::window().ok_or("Window not available")?;
+    let address = proxied(url, proxy);
+    let response = JsFuture::from(window.fetch_with_str(&address))
+        .await
+        .map_err(|_| match proxy.filter(|p| !p.trim().is_empty()) {
+            Some(_) => "The page could not be fetched through the proxy".to_string(),
+            None => "The site does not allow reading it from the browser (CORS). Set a proxy URL and try again.".to_string(),
+        })?
+        .dyn_into::<web_sys::Response>()
+        .map_err(|_| "Unexpected fetch response".to_string())?;
+    if !response.ok() {
+        return Err(format!(
+            "The server answered {} {}",
+            response.status(),
+            response.status_text()
+        ));
+    }
+    let content_type = response
+        .headers()
+        .get("content-type")
+        .ok()
+        .flatten()
+        .unwrap_or_default()
+        .to_lowercase();
+    let body = JsFuture::from(response.text().map_err(|_| "The page has no text body")?)
+        .await
+        .ok()
+        .and_then(|t| t.as_string())
+        .ok_or("The page body could not be read")?;
+    if content_type.is_empty() || content_type.contains("html") {
+        Ok(readable_text(&body))
+    } else if content_type.starts_with("text/") {
+        Ok(WebPage {
+            title: None,
+            text: body.trim().to_string(),
+        })
+    } else {
+        Err(format!(
+            "Not a web page ({}). Download the file and upload it instead.",
+            content_type
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_readable_text_keeps_main_content() {
+        let html = r#"<!doctype html><html><head><title>Refunds &amp; returns</title>
+            <script>var tracking = "<p>no</p>";</script><style>p { color: red }</style></head>
+            <body><header><nav><a href="/">Home</a> <a href="/shop">Shop</a></nav></header>
+            <main><h1>Refund policy</h1><!-- edited --><p>Refunds are issued
+            within <b>30&nbsp;days</b>.</p><ul><li>Keep the receipt</li><li>Use the form &#8212; online</li></ul>
+            <h2>Exceptions</h2><p>Gift cards can&#39;t be refunded.</p></main>
+            <footer>© Shop Inc.</footer></body></html>"#;
+        let page = readable_text(html);
+        assert_eq!(page.title.as_deref(), Some("Refunds & returns"));
+        assert_eq!(
+            page.text,
+            "# Refund policy\n\nRefunds are issued within 30 days.\n\n\
+             - Keep the receipt\n- Use the form — online\n\n\
+             ## Exceptions\n\nGift cards can't be refunded."
+        );
+        assert!(page
+            .document()
+            .starts_with("# Refunds & returns\n\n# Refund policy"));
+    }
+
+    #[test]
+    fn test_urls_are_checked_and_proxied() {
+        assert_eq!(
+            normalize_url(" https://example.com/a?b=1#top "),
+            Ok("https://example.com/a?b=1".to_string())
+        );
+        assert!(normalize_url("example.com").is_err());
+        assert!(normalize_url("https://").is_err());
+        assert_eq!(proxied("https://a.b/c?d=1", None), "https://a.b/c?d=1");
+        assert_eq!(
+            proxied("https://a.b/c?d=1", Some("https://proxy.local/?url=")),
+            "https://proxy.local/?url=https%3A%2F%2Fa.b%2Fc%3Fd%3D1"
+        );
+        assert_eq!(
+            proxied("https://a.b/", Some("https://p.local/raw?u={url}&text=1")),
+            "https://p.local/raw?u=https%3A%2F%2Fa.b%2F&text=1"
+        );
+        assert!(is_web_source("https://example.com/a"));
+        assert!(!is_web_source("notes.md"));
+    }
+}
@@ -0,0 +1,294 @@
+//! Zip archives: written with stored (uncompressed) entries, e.g. backups, and read
+//! with stored or deflated entries, e.g. EPUB books. Deflated entries are inflated by
+//! the browser's `DecompressionStream`; every entry read is checked against the
+//! CRC-32 the archive records for it.
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::JsFuture;
+
+const LOCAL_SIGNATURE: u32 = 0x0403_4b50;
+const CENTRAL_SIGNATURE: u32 = 0x0201_4b50;
+const EOCD_SIGNATURE: u32 = 0x0605_4b50;
+const STORED: u16 = 0;
+const DEFLATED: u16 = 8;
+/// Version 2.0, the first to define stored entries in folders
+const VERSION: u16 = 20;
+/// Entry names are UTF-8
+const UTF8_NAMES: u16 = 1 << 11;
+
+/// CRC-32 (IEEE) of `bytes`, as zip entries carry it
+pub fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+/// Builds a zip archive in memory, one entry at a time
+#[derive(Default)]
+pub struct ZipWriter {
+    out: Vec<u8>,
+    central: Vec<u8>,
+    count: u16,
+}
+
+impl ZipWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, name: &str, content: &[u8]) -> Result<(), String> {
+        if self.count == u16::MAX {
+            return Err("too many entries for a zip archive".to_string());
+        }
+        let size = u32::try_from(content.len())
+            .map_err(|_| format!("{} is too large for a zip archive", name))?;
+        let offset =
+            u32::try_from(self.out.len()).map_err(|_| "the archive is too large".to_string())?;
+        let crc = crc32(content);
+        let name_len = name.len() as u16;
+
+        self.out.extend(LOCAL_SIGNATURE.to_le_bytes());
+        self.out.extend(VERSION.to_le_bytes());
+        self.out.extend(UTF8_NAMES.to_le_bytes());
+        // Method (stored), modification time and date
+        self.out.extend([0u8; 6]);
+        self.out.extend(crc.to_le_bytes());
+        self.out.extend(size.to_le_bytes());
+        self.out.extend(size.to_le_bytes());
+        self.out.extend(name_len.to_le_bytes());
+        self.out.extend(0u16.to_le_bytes());
+        self.out.extend(name.as_bytes());
+        self.out.extend(content);
+
+        self.central.extend(CENTRAL_SIGNATURE.to_le_bytes());
+        self.central.extend(VERSION.to_le_bytes());
+        self.central.extend(VERSION.to_le_bytes());
+        self.central.extend(UTF8_NAMES.to_le_bytes());
+        self.central.extend([0u8; 6]);
+        self.central.extend(crc.to_le_bytes());
+        self.central.extend(size.to_le_bytes());
+        self.central.extend(size.to_le_bytes());
+        self.central.extend(name_len.to_le_bytes());
+        // Extra and comment lengths, disk number, internal and external attributes
+        self.central.extend([0u8; 12]);
+        self.central.extend(offset.to_le_bytes());
+        self.central.extend(name.as_bytes());
+        self.count += 1;
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Vec<u8> {
+        let central_offset = self.out.len() as u32;
+        let central_len = self.central.len() as u32;
+        self.out.append(&mut self.central);
+        self.out.extend(EOCD_SIGNATURE.to_le_bytes());
+        self.out.extend([0u8; 4]);
+        self.out.extend(self.count.to_le_bytes());
+        self.out.extend(self.count.to_le_bytes());
+        self.out.extend(central_len.to_le_bytes());
+        self.out.extend(central_offset.to_le_bytes());
+        self.out.extend(0u16.to_le_bytes());
+        self.out
+    }
+}
+
+/// A file in a zip archive
+#[derive(Clone, Debug, PartialEq)]
+pub struct ZipEntry {
+    pub name: String,
+    method: u16,
+    /// CRC-32 of the uncompressed content
+    crc: u32,
+    data_start: usize,
+    compressed_size: usize,
+}
+
+/// Entries of a zip archive, read from its central directory
+pub struct ZipArchive<'a> {
+    bytes: &'a [u8],
+    pub entries: Vec<ZipEntry>,
+}
+
+fn u16_at(bytes: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(bytes.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn u32_at(bytes: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(bytes.get(at..at + 4)?.try_into().ok()?))
+}
+
+impl<'a> ZipArchive<'a> {
+    pub fn parse(bytes: &'a [u8]) -> Result<Self, String> {
+        let broken = || "not a valid zip archive".to_string();
+        let eocd = (0..=bytes.len().saturating_sub(22))
+            .rev()
+            .take(65_557)
+            .find(|&at| u32_at(bytes, at) == Some(EOCD_SIGNATURE))
+            .ok_or_else(broken)?;
+        let count = u16_at(bytes, eocd + 10).ok_or_else(broken)? as usize;
+        let mut at = u32_at(bytes, eocd + 16).ok_or_else(broken)? as usize;
+        let mut entries = Vec::with_capacity(count);
+        for _ in 0..count {
+            if u32_at(bytes, at) != Some(CENTRAL_SIGNATURE) {
+                return Err(broken());
+            }
+            let field = |offset: usize| u16_at(bytes, at + offset).map(usize::from);
+            let method = u16_at(bytes, at + 10).ok_or_else(broken)?;
+            let crc = u32_at(bytes, at + 16).ok_or_else(broken)?;
+            let compressed_size = u32_at(bytes, at + 20).ok_or_else(broken)? as usize;
+            let (name_len, extra_len, comment_len) = (
+                field(28).ok_or_else(broken)?,
+                field(30).ok_or_else(broken)?,
+                field(32).ok_or_else(broken)?,
+            );
+            let local = u32_at(bytes, at + 42).ok_or_else(broken)? as usize;
+            let name = bytes.get(at + 46..at + 46 + name_len).ok_or_else(broken)?;
+            if u32_at(bytes, local) != Some(LOCAL_SIGNATURE) {
+                return Err(broken());
+            }
+            let local_name_len = u16_at(bytes, local + 26).ok_or_else(broken)? as usize;
+            let local_extra_len = u16_at(bytes, local + 28).ok_or_else(broken)? as usize;
+            entries.push(ZipEntry {
+                name: String::from_utf8_lossy(name).into_owned(),
+                method,
+                crc,
+                data_start: local + 30 + local_name_len + local_extra_len,
+                compressed_size,
+            });
+            at += 46 + name_len + extra_len + comment_len;
+        }
+        Ok(Self { bytes, entries })
+    }
+
+    pub fn find(&self, name: &str) -> Option<&ZipEntry> {
+        self.entries.iter().find(|e| e.name == name)
+    }
+
+    /// Bytes of `entry` as stored in the archive
+    pub(crate) fn data(&self, entry: &ZipEntry) -> Result<&'a [u8], String> {
+        self.bytes
+            .get(entry.data_start..entry.data_start + entry.compressed_size)
+            .ok_or_else(|| format!("{} is truncated", entry.name))
+    }
+
+    /// Content of the entry called `name`; refused when it does not match its CRC-32
+    pub async fn read(&self, name: &str) -> Result<Vec<u8>, String> {
+        let entry = self
+            .find(name)
+            .ok_or_else(|| format!("missing {} in the archive", name))?;
+        let data = self.data(entry)?;
+        let content = match entry.method {
+            STORED => data.to_vec(),
+            DEFLATED => inflate_raw(data)
+                .await
+                .map_err(|e| format!("could not decompress {}: {:?}", name, e))?,
+            other => return Err(format!("{} uses unsupported compression {}", name, other)),
+        };
+        checked(entry, content)
+    }
+
+    pub async fn read_text(&self, name: &str) -> Result<String, String> {
+        Ok(String::from_utf8_lossy(&self.read(name).await?).into_owned())
+    }
+}
+
+/// `content` of `entry` if it matches the CRC-32 the archive records
+fn checked(entry: &ZipEntry, content: Vec<u8>) -> Result<Vec<u8>, String> {
+    if crc32(&content) != entry.crc {
+        return Err(format!("{} is damaged (checksum mismatch)", entry.name));
+    }
+    Ok(content)
+}
+
+/// Inflate raw DEFLATE data through the browser's `DecompressionStream`
+async fn inflate_raw(data: &[u8]) -> Result<Vec<u8>, JsValue> {
+    let global = js_sys::global();
+    let decompression = js_sys::Reflect::get(&global, &"DecompressionStream".into())?;
+    if decompression.is_undefined() {
+        return Err(JsValue::from_str("this browser cannot decompress"));
+    }
+    let stream = js_sys::Reflect::construct(
+        &decompression.into(),
+        &js_sys::Array::of1(&"deflate-raw".into()),
+    )?;
+    let parts = js_sys::Array::of1(&js_sys::Uint8Array::from(data));
+    let blob = web_sys::Blob::new_with_u8_array_sequence(&parts)?;
+    let piped = call_method(
+        &call_method(&blob, "stream", &[])?,
+        "pipeThrough",
+        &[stream],
+    )?;
+    let response = js_sys::Reflect::construct(
+        &js_sys::Reflect::get(&global, &"Response".into())?.into(),
+        &js_sys::Array::of1(&piped),
+    )?;
+    let buffer = JsFuture::from(js_sys::Promise::from(call_method(
+        &response,
+        "arrayBuffer",
+        &[],
+    )?))
+    .await?;
+    Ok(js_sys::Uint8Array::new(&buffer).to_vec())
+}
+
+fn call_method(target: &JsValue, name: &str, args: &[JsValue]) -> Result<JsValue, JsValue> {
+    let f: js_sys::Function = js_sys::Reflect::get(target, &name.into())?.into();
+    let array = args.iter().collect::<js_sys::Array>();
+    js_sys::Reflect::apply(&f, target, &array)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_written_archive_reads_back() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        assert_eq!(crc32(b""), 0);
+
+        let mut zip = ZipWriter::new();
+        zip.add("manifest.json", b"{\"version\":1}").unwrap();
+        zip.add("records/tasks_v1.json", b"[]").unwrap();
+        let bytes = zip.finish();
+
+        let archive = ZipArchive::parse(&bytes).unwrap();
+        let names: Vec<&str> = archive.entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec!["manifest.json", "records/tasks_v1.json"]);
+        let content = &bytes[30 + "manifest.json".len()..][..13];
+        assert_eq!(content, b"{\"version\":1}");
+        assert_eq!(
+            u32::from_le_bytes(bytes[14..18].try_into().unwrap()),
+            crc32(content)
+        );
+    }
+
+    #[test]
+    fn test_damaged_entry_is_refused() {
+        let mut zip = ZipWriter::new();
+        zip.add("records/tasks_v1.json", b"[{\"id\":1}]").unwrap();
+        let mut bytes = zip.finish();
+        let entry = {
+            let archive = ZipArchive::parse(&bytes).unwrap();
+            let entry = archive.find("records/tasks_v1.json").unwrap().clone();
+            let data = archive.data(&entry).unwrap().to_vec();
+            assert_eq!(checked(&entry, data).unwrap(), b"[{\"id\":1}]");
+            entry
+        };
+
+        // One flipped bit in the content still parses as an archive, and as JSON
+        bytes[entry.data_start + 7] ^= 0x01;
+        let archive = ZipArchive::parse(&bytes).unwrap();
+        let data = archive.data(&entry).unwrap().to_vec();
+        assert_eq!(data, b"[{\"id\":0}]");
+        assert!(checked(&entry, data).unwrap_err().contains("damaged"));
+
+        assert!(ZipArchive::parse(&bytes[..bytes.len() - 4]).is_err());
+        assert!(ZipArchive::parse(b"PK not a zip").is_err());
+    }
+}
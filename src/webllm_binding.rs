@@ -1,4 +1,9 @@
+use crate::features::webllm::pool::{EnginePool, EngineUsage, PooledEngine};
+use crate::models::generation::{GenerationSettings, OutputFormat, SamplingParams};
+use crate::utils::generation::GenerationUtils;
+use crate::utils::prefill::{PrefillCache, PrefillReuse};
 use log::{error, info};
+use std::cell::RefCell;
 use wasm_bindgen::prelude::*;
 use wasm_bindgen_futures::JsFuture;
 use web_sys::js_sys;
@@ -10,6 +15,118 @@ extern "C" {
     fn CreateMLCEngine(model: &str, config: JsValue) -> js_sys::Promise;
 }
 
+thread_local! {
+    // Chat engine of the model loaded in the chat area, and the ones kept warm
+    static ENGINES: RefCell<EnginePool<JsValue>> = RefCell::new(EnginePool::default());
+    // Conversation held in the KV cache of the engine that streamed last
+    static PREFILL: RefCell<Option<(JsValue, PrefillCache)>> = const { RefCell::new(None) };
+}
+
+/// Remember the chat engine once a model finished loading, unloading the previous one
+pub fn set_loaded_engine(model_id: &str, engine: JsValue) {
+    activate_engine(model_id, engine, false);
+}
+
+/// Make `engine` the chat engine. The previous one is kept warm when `keep_previous`
+/// is set, otherwise unloaded.
+pub fn activate_engine(model_id: &str, engine: JsValue, keep_previous: bool) {
+    let pooled = PooledEngine {
+        model_id: model_id.to_string(),
+        engine,
+        vram_mb: vram_required_mb(model_id),
+    };
+    unload(ENGINES.with(|e| e.borrow_mut().activate(pooled, keep_previous)));
+}
+
+/// Switch the chat to the warm engine of `model_id`; false when it is not warm
+pub fn switch_to_warm(model_id: &str) -> bool {
+    match ENGINES.with(|e| e.borrow_mut().promote(model_id)) {
+        Some(evicted) => {
+            unload(evicted);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Keep `engine` loaded next to the chat engine, ready to switch to
+pub fn prewarm_engine(model_id: &str, engine: JsValue) {
+    let pooled = PooledEngine {
+        model_id: model_id.to_string(),
+        engine,
+        vram_mb: vram_required_mb(model_id),
+    };
+    unload(ENGINES.with(|e| e.borrow_mut().add_warm(pooled)));
+}
+
+/// Unload the engine of `model_id`, active or warm
+pub fn unload_model(model_id: &str) {
+    let removed = ENGINES.with(|e| e.borrow_mut().remove(model_id));
+    unload(removed.into_iter().collect());
+}
+
+/// Engine of `model_id` when it is loaded, for the chat or warm
+pub fn engine_for(model_id: &str) -> Option<JsValue> {
+    ENGINES.with(|e| e.borrow().get(model_id).map(|p| p.engine.clone()))
+}
+
+pub fn is_model_loaded(model_id: &str) -> bool {
+    ENGINES.with(|e| e.borrow().is_loaded(model_id))
+}
+
+/// Loaded engines with the memory each needs
+pub fn engine_usage() -> Vec<EngineUsage> {
+    ENGINES.with(|e| e.borrow().usage())
+}
+
+/// Model id and chat engine of the loaded model, if any. Also used outside the chat
+/// (e.g. LLM extraction at index time).
+pub fn loaded_engine() -> Option<(String, JsValue)> {
+    ENGINES.with(|e| {
+        e.borrow()
+            .active()
+            .map(|a| (a.model_id.clone(), a.engine.clone()))
+    })
+}
+
+/// Free the GPU memory of engines no longer kept, via `engine.unload()`
+fn unload(engines: Vec<PooledEngine<JsValue>>) {
+    for pooled in engines {
+        info!("Unloading WebLLM engine of {}", pooled.model_id);
+        let unloaded = js_sys::Reflect::get(&pooled.engine, &"unload".into())
+            .and_then(|f| f.dyn_into::<js_sys::Function>())
+            .and_then(|f| f.call0(&pooled.engine));
+        match unloaded {
+            Ok(promise) => wasm_bindgen_futures::spawn_local(async move {
+                if let Err(e) = JsFuture::from(js_sys::Promise::resolve(&promise)).await {
+                    error!("Failed to unload {}: {:?}", pooled.model_id, e);
+                }
+            }),
+            Err(e) => error!("Failed to unload {}: {:?}", pooled.model_id, e),
+        }
+    }
+}
+
+/// GPU memory `model_id` needs, from `vram_required_MB` in WebLLM's prebuilt model list
+pub fn vram_required_mb(model_id: &str) -> Option<u32> {
+    let get = |target: &JsValue, key: &str| js_sys::Reflect::get(target, &key.into()).ok();
+    let window: JsValue = web_sys::window()?.into();
+    let list = get(&window, "webllm")
+        .and_then(|webllm| get(&webllm, "prebuiltAppConfig"))
+        .and_then(|config| get(&config, "model_list"))?
+        .dyn_into::<js_sys::Array>()
+        .ok()?;
+    let record = list.iter().find(|record| {
+        get(record, "model_id")
+            .and_then(|id| id.as_string())
+            .as_deref()
+            == Some(model_id)
+    })?;
+    get(&record, "vram_required_MB")?
+        .as_f64()
+        .map(|mb| mb.round() as u32)
+}
+
 /// Initialize WebLLM with a specific model and progress callback
 pub async fn init_webllm_with_progress<F>(
     model_id: &str,
@@ -81,13 +198,103 @@ pub async fn init_webllm(model_id: &str) -> Result<JsValue, JsValue> {
     .await
 }
 
+/// Generation statistics reported in the `usage` field of a completion
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CompletionUsage {
+    pub prompt_tokens: Option<u32>,
+    pub completion_tokens: Option<u32>,
+    pub decode_tokens_per_sec: Option<f32>,
+    pub prefill_tokens_per_sec: Option<f32>,
+    /// Prompt tokens taken from the engine's cache instead of being prefilled again
+    pub prefill_reused_tokens: u32,
+}
+
+impl CompletionUsage {
+    fn from_js(result: &JsValue) -> Self {
+        let usage = js_sys::Reflect::get(result, &"usage".into()).unwrap_or(JsValue::UNDEFINED);
+        if usage.is_undefined() || usage.is_null() {
+            return Self::default();
+        }
+        let num = |obj: &JsValue, key: &str| {
+            js_sys::Reflect::get(obj, &key.into())
+                .ok()
+                .and_then(|v| v.as_f64())
+        };
+        let extra = js_sys::Reflect::get(&usage, &"extra".into()).unwrap_or(JsValue::UNDEFINED);
+        Self {
+            prompt_tokens: num(&usage, "prompt_tokens").map(|v| v as u32),
+            completion_tokens: num(&usage, "completion_tokens").map(|v| v as u32),
+            decode_tokens_per_sec: num(&extra, "decode_tokens_per_s").map(|v| v as f32),
+            prefill_tokens_per_sec: num(&extra, "prefill_tokens_per_s").map(|v| v as f32),
+            prefill_reused_tokens: 0,
+        }
+    }
+
+    /// Count the tokens of `other`, another attempt at the same reply such as a
+    /// rejected one and its retry; the speeds stay those of this attempt
+    pub fn add_attempt(&mut self, other: &CompletionUsage) {
+        let sum = |a: Option<u32>, b: Option<u32>| match (a, b) {
+            (None, None) => None,
+            (a, b) => Some(a.unwrap_or(0) + b.unwrap_or(0)),
+        };
+        self.prompt_tokens = sum(self.prompt_tokens, other.prompt_tokens);
+        self.completion_tokens = sum(self.completion_tokens, other.completion_tokens);
+        self.prefill_reused_tokens += other.prefill_reused_tokens;
+    }
+}
+
+/// Compare a streamed request with what `engine` holds in its KV cache, restoring the
+/// raw reply in its history when the request continues that conversation
+fn prepare_prefill(engine: &JsValue, messages: &mut [crate::models::Message]) -> PrefillReuse {
+    let reuse = PREFILL.with(|p| match p.borrow().as_ref() {
+        Some((cached, cache)) if js_sys::Object::is(cached, engine) => cache.prepare(messages),
+        _ => PrefillReuse::Cold,
+    });
+    if let PrefillReuse::Invalidated { reason } = &reuse {
+        info!("WebLLM prefill cache invalidated: {}", reason);
+    }
+    reuse
+}
+
+/// Remember the conversation `engine` holds after streaming a reply to `sent`
+fn remember_prefill(
+    engine: &JsValue,
+    sent: &[crate::models::Message],
+    raw_reply: &str,
+    stored_reply: &str,
+    usage: &CompletionUsage,
+) {
+    // The engine reports the tokens it prefilled for this request only
+    let mut cache = PrefillCache::new(sent, raw_reply, stored_reply);
+    cache.context_tokens = usage.prefill_reused_tokens
+        + usage.prompt_tokens.unwrap_or(0)
+        + usage.completion_tokens.unwrap_or(0);
+    PREFILL.with(|p| *p.borrow_mut() = Some((engine.clone(), cache)));
+}
+
+/// Drop the cached conversation after a request that replaced it
+fn forget_prefill() {
+    PREFILL.with(|p| *p.borrow_mut() = None);
+}
+
 /// Send a message to the WebLLM engine and get a response
 pub async fn send_message_to_llm(
     engine: &JsValue,
     messages: Vec<crate::models::Message>,
 ) -> Result<String, JsValue> {
-    info!("Sending message to WebLLM with {} messages", messages.len());
+    send_message_to_llm_with_usage(engine, messages)
+        .await
+        .map(|(text, _)| text)
+}
 
+/// Build the chat completion request object sent to `engine.chat.completions.create`
+fn build_chat_request(
+    messages: Vec<crate::models::Message>,
+    stream: bool,
+    stop: &[String],
+    sampling: &SamplingParams,
+    format: &OutputFormat,
+) -> Result<js_sys::Object, JsValue> {
     // Create messages array manually
     let messages_array = js_sys::Array::new();
     for msg in messages {
@@ -105,10 +312,53 @@ pub async fn send_message_to_llm(
     // Create request object
     let request = js_sys::Object::new();
     js_sys::Reflect::set(&request, &"messages".into(), &messages_array)?;
-    js_sys::Reflect::set(&request, &"stream".into(), &false.into())?;
-    js_sys::Reflect::set(&request, &"max_tokens".into(), &512.into())?;
-    js_sys::Reflect::set(&request, &"temperature".into(), &0.7.into())?;
+    js_sys::Reflect::set(&request, &"stream".into(), &stream.into())?;
+    if stream {
+        // Ask for a final chunk carrying usage statistics
+        let stream_options = js_sys::Object::new();
+        js_sys::Reflect::set(&stream_options, &"include_usage".into(), &true.into())?;
+        js_sys::Reflect::set(&request, &"stream_options".into(), &stream_options)?;
+    }
+    if !stop.is_empty() {
+        let stop_array = js_sys::Array::new();
+        for s in stop {
+            stop_array.push(&JsValue::from_str(s));
+        }
+        js_sys::Reflect::set(&request, &"stop".into(), &stop_array)?;
+    }
+    js_sys::Reflect::set(&request, &"max_tokens".into(), &sampling.max_tokens.into())?;
+    js_sys::Reflect::set(
+        &request,
+        &"temperature".into(),
+        &sampling.temperature.into(),
+    )?;
+    if let Some(seed) = sampling.seed {
+        js_sys::Reflect::set(&request, &"seed".into(), &(seed as f64).into())?;
+    }
+    if sampling.frequency_penalty != 0.0 {
+        js_sys::Reflect::set(
+            &request,
+            &"frequency_penalty".into(),
+            &sampling.frequency_penalty.into(),
+        )?;
+    }
+    // JSON replies use the engine's grammar-constrained decoding
+    if let OutputFormat::Json { schema } = format {
+        let response_format = js_sys::Object::new();
+        js_sys::Reflect::set(&response_format, &"type".into(), &"json_object".into())?;
+        if let Some(schema) = schema {
+            js_sys::Reflect::set(&response_format, &"schema".into(), &schema.into())?;
+        }
+        js_sys::Reflect::set(&request, &"response_format".into(), &response_format)?;
+    }
+    Ok(request)
+}
 
+/// Call `engine.chat.completions.create(request)` and await the returned promise
+async fn create_chat_completion(
+    engine: &JsValue,
+    request: &js_sys::Object,
+) -> Result<JsValue, JsValue> {
     // Call WebLLM API using reflection to access nested methods
     let chat_completion = js_sys::Reflect::get(engine, &"chat".into()).map_err(|e| {
         error!("Failed to get chat object: {:?}", e);
@@ -126,18 +376,40 @@ pub async fn send_message_to_llm(
         e
     })?;
 
-    let args = js_sys::Array::of1(&request);
+    let args = js_sys::Array::of1(request);
     let promise = js_sys::Reflect::apply(&create_fn.into(), &completions, &args).map_err(|e| {
         error!("Failed to call create function: {:?}", e);
         e
     })?;
 
-    let result = JsFuture::from(js_sys::Promise::from(promise))
+    JsFuture::from(js_sys::Promise::from(promise))
         .await
         .map_err(|e| {
             error!("WebLLM API call failed: {:?}", e);
             e
-        })?;
+        })
+}
+
+/// Same as `send_message_to_llm`, also returning the engine's usage statistics
+pub async fn send_message_to_llm_with_usage(
+    engine: &JsValue,
+    messages: Vec<crate::models::Message>,
+) -> Result<(String, CompletionUsage), JsValue> {
+    send_message_to_llm_with_format(engine, messages, &OutputFormat::Text).await
+}
+
+/// Same as `send_message_to_llm_with_usage`, asking for replies in `format`
+pub async fn send_message_to_llm_with_format(
+    engine: &JsValue,
+    messages: Vec<crate::models::Message>,
+    format: &OutputFormat,
+) -> Result<(String, CompletionUsage), JsValue> {
+    info!("Sending message to WebLLM with {} messages", messages.len());
+
+    // The engine's cache now holds this request instead of the chat
+    forget_prefill();
+    let request = build_chat_request(messages, false, &[], &SamplingParams::default(), format)?;
+    let result = create_chat_completion(engine, &request).await?;
 
     // Extract the response
     let choices = js_sys::Reflect::get(&result, &"choices".into()).map_err(|e| {
@@ -169,5 +441,131 @@ pub async fn send_message_to_llm(
         "WebLLM response received: {} characters",
         response_text.len()
     );
-    Ok(response_text)
+    Ok((response_text, CompletionUsage::from_js(&result)))
+}
+
+/// Stream a response from the WebLLM engine, calling `on_delta` with each new text chunk.
+/// Resolves with the full text once the stream is exhausted, cut at the configured stop
+/// sequences and run through the post-processors so it is ready to be stored.
+pub async fn send_message_to_llm_streaming<F>(
+    engine: &JsValue,
+    mut messages: Vec<crate::models::Message>,
+    settings: &GenerationSettings,
+    mut on_delta: F,
+) -> Result<(String, CompletionUsage), JsValue>
+where
+    F: FnMut(&str),
+{
+    info!(
+        "Streaming message to WebLLM with {} messages",
+        messages.len()
+    );
+
+    let reuse = prepare_prefill(engine, &mut messages);
+    let sent = messages.clone();
+    let request = build_chat_request(
+        messages,
+        true,
+        &settings.stop_sequences,
+        &settings.sampling,
+        &settings.output_format,
+    )?;
+    let stream = create_chat_completion(engine, &request)
+        .await
+        .inspect_err(|_| forget_prefill())?;
+
+    // The result is an AsyncIterable of chunks; drive its iterator manually
+    let iter_fn = js_sys::Reflect::get(&stream, &js_sys::Symbol::async_iterator())?;
+    let iterator = js_sys::Reflect::apply(&iter_fn.into(), &stream, &js_sys::Array::new())?;
+    let next_fn: js_sys::Function = js_sys::Reflect::get(&iterator, &"next".into())?.into();
+
+    let mut text = String::new();
+    let mut usage = CompletionUsage::default();
+    loop {
+        let step = JsFuture::from(js_sys::Promise::from(next_fn.call0(&iterator)?))
+            .await
+            .map_err(|e| {
+                error!("WebLLM stream failed: {:?}", e);
+                forget_prefill();
+                e
+            })?;
+        if js_sys::Reflect::get(&step, &"done".into())?.is_truthy() {
+            break;
+        }
+        let chunk = js_sys::Reflect::get(&step, &"value".into())?;
+
+        let delta = js_sys::Reflect::get(&chunk, &"choices".into())
+            .and_then(|choices| js_sys::Reflect::get(&choices, &0_u32.into()))
+            .and_then(|choice| js_sys::Reflect::get(&choice, &"delta".into()))
+            .and_then(|delta| js_sys::Reflect::get(&delta, &"content".into()))
+            .ok()
+            .and_then(|c| c.as_string());
+        if let Some(delta) = delta.filter(|d| !d.is_empty()) {
+            text.push_str(&delta);
+            on_delta(&delta);
+        }
+
+        // Usage only arrives on the final chunk
+        let chunk_usage = CompletionUsage::from_js(&chunk);
+        if chunk_usage != CompletionUsage::default() {
+            usage = chunk_usage;
+        }
+    }
+
+    info!("WebLLM stream finished: {} characters", text.len());
+    usage.prefill_reused_tokens = reuse.reused_tokens();
+    let reply = GenerationUtils::finalize(&text, settings);
+    remember_prefill(engine, &sent, &text, &reply, &usage);
+    Ok((reply, usage))
+}
+
+/// Embed `texts` with an embedding engine via `engine.embeddings.create({ input })`.
+/// Returns one vector per input, in input order.
+pub async fn embed_texts(engine: &JsValue, texts: &[String]) -> Result<Vec<Vec<f32>>, JsValue> {
+    let input = js_sys::Array::new();
+    for t in texts {
+        input.push(&JsValue::from_str(t));
+    }
+    let request = js_sys::Object::new();
+    js_sys::Reflect::set(&request, &"input".into(), &input)?;
+
+    let embeddings = js_sys::Reflect::get(engine, &"embeddings".into()).map_err(|e| {
+        error!("Failed to get embeddings object: {:?}", e);
+        e
+    })?;
+    let create_fn = js_sys::Reflect::get(&embeddings, &"create".into()).map_err(|e| {
+        error!("Failed to get embeddings create function: {:?}", e);
+        e
+    })?;
+    let args = js_sys::Array::of1(&request);
+    let promise = js_sys::Reflect::apply(&create_fn.into(), &embeddings, &args)?;
+    let result = JsFuture::from(js_sys::Promise::from(promise))
+        .await
+        .map_err(|e| {
+            error!("WebLLM embeddings call failed: {:?}", e);
+            e
+        })?;
+
+    let data: js_sys::Array = js_sys::Reflect::get(&result, &"data".into())?.dyn_into()?;
+    let mut out = vec![Vec::new(); texts.len()];
+    for (pos, item) in data.iter().enumerate() {
+        let index = js_sys::Reflect::get(&item, &"index".into())
+            .ok()
+            .and_then(|v| v.as_f64())
+            .map(|v| v as usize)
+            .unwrap_or(pos);
+        let embedding: js_sys::Array =
+            js_sys::Reflect::get(&item, &"embedding".into())?.dyn_into()?;
+        let vector: Vec<f32> = embedding
+            .iter()
+            .map(|v| v.as_f64().unwrap_or(0.0) as f32)
+            .collect();
+        if let Some(slot) = out.get_mut(index) {
+            *slot = vector;
+        }
+    }
+    if out.iter().any(|v| v.is_empty()) {
+        return Err(JsValue::from_str("Embedding response is missing vectors"));
+    }
+    Ok(out)
 }
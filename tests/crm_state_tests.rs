@@ -1,4 +1,5 @@
 use wasm_bindgen_test::*;
+use wasm_knowledge_chatbot_rs::features::crm::ownership::pipeline_totals_by_owner;
 use wasm_knowledge_chatbot_rs::models::crm::{Customer, Deal, Lead, LeadSource, PipelineStage};
 use wasm_knowledge_chatbot_rs::state::CRMStateContext;
 use wasm_knowledge_chatbot_rs::utils::storage::StorageUtils;
@@ -10,6 +11,7 @@ fn clear_crm_storage() {
     let _ = StorageUtils::remove_local("crm_leads");
     let _ = StorageUtils::remove_local("crm_deals");
     let _ = StorageUtils::remove_local("crm_stages");
+    let _ = StorageUtils::remove_local("crm_owner_profiles");
 }
 
 #[wasm_bindgen_test]
@@ -70,3 +72,68 @@ fn crm_crud_and_persistence_roundtrip() {
     assert_eq!(ctx3.deals_now().len(), 0);
     assert_eq!(ctx3.stages_now().len(), 0);
 }
+
+#[wasm_bindgen_test]
+fn crm_owner_assignment_and_totals() {
+    clear_crm_storage();
+    let ctx = CRMStateContext::new();
+
+    ctx.add_owner_profile("  Ana ");
+    ctx.add_owner_profile("ana");
+    assert_eq!(ctx.owner_profiles_now(), vec!["Ana".to_string()]);
+
+    let mut a = Deal::new("A".into(), "c".into(), "s".into(), 1000.0);
+    a.id = "deal_a".into();
+    let mut b = Deal::new("B".into(), "c".into(), "s".into(), 500.0);
+    b.id = "deal_b".into();
+    ctx.upsert_deal(a);
+    ctx.upsert_deal(b);
+    ctx.assign_deal_owner("deal_a", "Ana");
+
+    let totals = pipeline_totals_by_owner(&ctx.deals_now());
+    assert_eq!(totals.len(), 2);
+    assert_eq!(totals[0].label(), "Ana");
+    assert_eq!(totals[0].total_value, 1000.0);
+    assert_eq!(totals[1].label(), "Unassigned");
+
+    // Owner and profiles survive a reload
+    let ctx2 = CRMStateContext::new();
+    let deal = ctx2
+        .deals_now()
+        .into_iter()
+        .find(|d| d.id == "deal_a")
+        .unwrap();
+    assert_eq!(deal.owner.as_deref(), Some("Ana"));
+    assert_eq!(ctx2.owner_profiles_now().len(), 1);
+
+    // Blank owner clears the assignment
+    ctx2.assign_deal_owner("deal_a", "  ");
+    assert!(ctx2.deals_now().iter().all(|d| d.owner.is_none()));
+}
+
+#[wasm_bindgen_test]
+fn crm_swimlanes_group_by_customer_and_size() {
+    use wasm_knowledge_chatbot_rs::features::crm::swimlanes::{group_deals, SwimlaneAxis};
+
+    let mut acme = Customer::new("Acme".to_string());
+    acme.id = "cust_acme".into();
+    let mut beta = Customer::new("Beta".to_string());
+    beta.id = "cust_beta".into();
+
+    let deals = vec![
+        Deal::new("Big".into(), beta.id.clone(), "s".into(), 250_000.0),
+        Deal::new("Small".into(), acme.id.clone(), "s".into(), 500.0),
+        Deal::new("Mid".into(), acme.id.clone(), "s".into(), 5_000.0),
+    ];
+    let customers = vec![acme, beta];
+
+    let by_customer = group_deals(&deals, SwimlaneAxis::Customer, &customers);
+    assert_eq!(by_customer.len(), 2);
+    assert_eq!(by_customer[0].label, "Acme");
+    assert_eq!(by_customer[0].deals.len(), 2);
+    assert_eq!(by_customer[0].total_value, 5_500.0);
+
+    let by_size = group_deals(&deals, SwimlaneAxis::DealSize, &customers);
+    let labels: Vec<_> = by_size.iter().map(|l| l.label.as_str()).collect();
+    assert_eq!(labels, vec!["< 1k", "1k – 10k", "≥ 100k"]);
+}
@@ -0,0 +1,40 @@
+use wasm_bindgen_test::*;
+use wasm_knowledge_chatbot_rs::models::activity::{ActivityCategory, ActivityEvent};
+use wasm_knowledge_chatbot_rs::state::EventBusContext;
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+#[wasm_bindgen_test]
+fn event_bus_records_and_persists_for_session() {
+    EventBusContext::new().clear();
+
+    let bus = EventBusContext::new();
+    bus.record(ActivityCategory::Model, "Model loaded");
+    bus.error("Model failed to respond", "timeout");
+    bus.emit(ActivityEvent::new(ActivityCategory::Crm, "Deal moved").with_detail("Won"));
+
+    let events = bus.events_now();
+    assert_eq!(events.len(), 3);
+    assert_eq!(events[1].category, ActivityCategory::Error);
+    assert_eq!(events[1].detail.as_deref(), Some("timeout"));
+
+    // A fresh context restores the feed from session storage
+    let reloaded = EventBusContext::new();
+    assert_eq!(reloaded.events_now().len(), 3);
+
+    reloaded.clear();
+    assert!(EventBusContext::new().events_now().is_empty());
+}
+
+#[wasm_bindgen_test]
+fn event_bus_caps_feed_length() {
+    let bus = EventBusContext::new();
+    bus.clear();
+    for i in 0..520 {
+        bus.record(ActivityCategory::Knowledge, format!("event {}", i));
+    }
+    let events = bus.events_now();
+    assert_eq!(events.len(), 500);
+    assert_eq!(events[0].message, "event 20");
+    bus.clear();
+}
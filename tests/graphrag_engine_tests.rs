@@ -5,11 +5,11 @@ use wasm_bindgen_test::*;
 wasm_bindgen_test_configure!(run_in_browser);
 
 use wasm_knowledge_chatbot_rs::advanced_graphrag::{
-    CommunityDetectionConfig, CommunityDetectionEngine, HyDEConfig, HyDEEngine,
+    CommunityDetectionConfig, CommunityDetectionEngine, HyDEConfig, HyDEEngine, WeightedGraph,
 };
 use wasm_knowledge_chatbot_rs::pagerank_reranking::{
-    AdvancedReranker, GraphAccess, PageRankConfig, PageRankEngine, RerankingConfig,
-    ResultSynthesizer, SynthesisConfig,
+    graph_version, AdvancedReranker, GraphAccess, NodeImportance, PageRankConfig, PageRankEngine,
+    RerankingConfig, ResultSynthesizer, SynthesisConfig,
 };
 
 #[wasm_bindgen_test]
@@ -380,3 +380,119 @@ fn pagerank_with_dangling_nodes_normalizes() {
         assert!(s.is_finite() && s >= 0.0);
     }
 }
+
+#[wasm_bindgen_test]
+fn louvain_splits_two_bridged_cliques() {
+    // Two 4-cliques joined by a single bridge 3-4
+    let mut edges = Vec::new();
+    for base in [0usize, 4] {
+        for i in 0..4 {
+            for j in (i + 1)..4 {
+                edges.push((base + i, base + j, 1.0));
+            }
+        }
+    }
+    edges.push((3, 4, 1.0));
+    let g = WeightedGraph::from_edges(8, &edges);
+    let engine = CommunityDetectionEngine::new(CommunityDetectionConfig::default());
+    let labels = engine.louvain(&g);
+    assert_eq!(labels, vec![0, 0, 0, 0, 1, 1, 1, 1]);
+    let q = g.modularity(&labels, 1.0);
+    assert!(q > 0.4, "modularity {}", q);
+    assert!(q > g.modularity(&[0; 8], 1.0));
+}
+
+#[wasm_bindgen_test]
+fn louvain_assignments_persist_in_graph_store() {
+    use serde_json::json;
+    use wasm_knowledge_chatbot_rs::models::graph_store::{GraphEdge, GraphNode, GraphStore};
+    let node = |id: &str, doc: Option<&str>| GraphNode {
+        id: id.to_string(),
+        label: None,
+        node_type: if doc.is_some() { "document" } else { "entity" }.to_string(),
+        source_document_id: doc.map(str::to_string),
+        metadata: json!({}),
+    };
+    let edge = |from: &str, to: &str| GraphEdge {
+        id: format!("{}->{}", from, to),
+        from: from.to_string(),
+        to: to.to_string(),
+        relation: "mentions".to_string(),
+        weight: 1.0,
+        metadata: json!({}),
+    };
+    let mut store = GraphStore::new();
+    for (id, doc) in [
+        ("doc:a", Some("a")),
+        ("doc:b", Some("b")),
+        ("doc:c", Some("c")),
+        ("ent:Rust", None),
+        ("ent:Wasm", None),
+        ("ent:Tax", None),
+    ] {
+        store.add_node(node(id, doc));
+    }
+    for (from, to) in [
+        ("doc:a", "ent:Rust"),
+        ("doc:a", "ent:Wasm"),
+        ("doc:b", "ent:Rust"),
+        ("doc:b", "ent:Wasm"),
+        ("doc:c", "ent:Tax"),
+    ] {
+        store.add_edge(edge(from, to));
+    }
+
+    let engine = CommunityDetectionEngine::new(CommunityDetectionConfig::default());
+    assert_eq!(engine.assign_communities(&mut store), 2);
+    let communities = store.document_communities();
+    assert_eq!(communities["a"], communities["b"]);
+    assert_ne!(communities["a"], communities["c"]);
+    assert_eq!(store.community_of("ent:Tax"), Some(communities["c"]));
+}
+
+#[wasm_bindgen_test]
+fn node_importance_ranks_hub_document_highest() {
+    use serde_json::json;
+    use wasm_knowledge_chatbot_rs::models::graph_store::{GraphEdge, GraphNode, GraphStore};
+    let mut store = GraphStore::new();
+    for (id, doc) in [
+        ("doc:hub", Some("hub")),
+        ("doc:leaf", Some("leaf")),
+        ("ent:A", None),
+        ("ent:B", None),
+        ("ent:C", None),
+    ] {
+        store.add_node(GraphNode {
+            id: id.to_string(),
+            label: None,
+            node_type: if doc.is_some() { "document" } else { "entity" }.to_string(),
+            source_document_id: doc.map(str::to_string),
+            metadata: json!({}),
+        });
+    }
+    for (from, to) in [
+        ("doc:hub", "ent:A"),
+        ("doc:hub", "ent:B"),
+        ("doc:hub", "ent:C"),
+        ("doc:leaf", "ent:A"),
+    ] {
+        store.add_edge(GraphEdge {
+            id: format!("{}->{}", from, to),
+            from: from.to_string(),
+            to: to.to_string(),
+            relation: "mentions".to_string(),
+            weight: 1.0,
+            metadata: json!({}),
+        });
+    }
+
+    let importance = NodeImportance::compute(&store, PageRankConfig::default());
+    assert_eq!(importance.document("hub"), 1.0);
+    assert!(importance.document("leaf") < importance.document("hub"));
+    assert_eq!(importance.document("missing"), 0.0);
+    assert_eq!(importance.graph_version, graph_version(&store));
+
+    let before = graph_version(&store);
+    store.edges.pop();
+    assert_ne!(graph_version(&store), before);
+}
@@ -2,7 +2,9 @@ use wasm_bindgen_test::wasm_bindgen_test as test;
 use wasm_bindgen_test::*;
 
 use serde_json::Value;
-use wasm_knowledge_chatbot_rs::features::graphrag::extraction::extract_entities_relations;
+use wasm_knowledge_chatbot_rs::features::graphrag::extraction::{
+    extract_entities_relations, merge_llm_extraction, parse_llm_extraction,
+};
 use wasm_knowledge_chatbot_rs::models::graphrag::{DocumentIndex, ProcessingStatus};
 
 wasm_bindgen_test_configure!(run_in_browser);
@@ -80,3 +82,42 @@ Bob is a Researcher. Bob works at Acme Corp.
         "at least one entity should have backrefs array"
     );
 }
+
+#[test]
+fn llm_extraction_adds_typed_relations_with_provenance() {
+    let reply = r#"Here you go:
+```json
+{"entities": [{"name": " Alice ", "type": "Person"}, {"name": ""}],
+ "relations": [{"subject": "Alice", "predicate": "Works For", "object": "Acme Corp"}]}
+```"#;
+    let extraction = parse_llm_extraction(reply).expect("fenced JSON should parse");
+    assert_eq!(extraction.entities.len(), 1, "blank names are dropped");
+    assert_eq!(extraction.entities[0].entity_type, "person");
+    assert_eq!(extraction.relations[0].predicate, "works_for");
+    assert!(parse_llm_extraction("I could not find anything.").is_err());
+
+    let d = doc("d1", "Test", "Alice joined Acme Corp last year.");
+    let (mut nodes, mut edges) = extract_entities_relations(std::slice::from_ref(&d));
+    merge_llm_extraction(&mut nodes, &mut edges, &d, 0, &extraction, "model-x");
+
+    // The heuristic "Alice" entity is reused and typed; "Acme Corp" is new
+    let alice = nodes.iter().find(|n| n.id == "ent:Alice").unwrap();
+    assert_eq!(alice.metadata["entity_type"], "person");
+    let acme = nodes.iter().find(|n| n.id == "ent:Acme Corp").unwrap();
+    assert_eq!(acme.metadata["source"], "llm");
+
+    let rel = edges.iter().find(|e| e.relation == "works_for").unwrap();
+    assert_eq!(rel.from, "ent:Alice");
+    assert_eq!(rel.to, "ent:Acme Corp");
+    assert_eq!(rel.metadata["model"], "model-x");
+    assert_eq!(rel.metadata["doc_id"], "d1");
+    assert_eq!(rel.metadata["passage_index"], 0);
+    assert!(edges
+        .iter()
+        .any(|e| e.relation == "mentions" && e.to == "ent:Acme Corp" && e.from == "doc:d1"));
+
+    // Merging the same passage twice does not duplicate edges
+    let before = edges.len();
+    merge_llm_extraction(&mut nodes, &mut edges, &d, 0, &extraction, "model-x");
+    assert_eq!(edges.len(), before);
+}
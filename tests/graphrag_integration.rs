@@ -1,4 +1,7 @@
 use wasm_bindgen_test::*;
+use wasm_knowledge_chatbot_rs::features::graphrag::index_generation::{
+    index_generations, IndexSnapshot,
+};
 use wasm_knowledge_chatbot_rs::features::graphrag::{GraphRAGPipeline, Retriever};
 use wasm_knowledge_chatbot_rs::models::graphrag::{
     DocumentIndex, ProcessingStatus, RAGQuery, SearchStrategy,
@@ -96,3 +99,53 @@ async fn end_to_end_search_and_rerank_metadata() {
 
     // processing_time_ms is a non-negative type; no need to assert tautology
 }
+
+#[wasm_bindgen_test(async)]
+async fn search_during_reindex_reads_committed_generation() {
+    let pipeline = GraphRAGPipeline::new();
+    let docs = seed_docs();
+    pipeline
+        .index_documents(&docs[..2])
+        .expect("indexing should succeed");
+    let committed = index_generations().active;
+
+    // A reindex is half-way through building the next generation
+    let mut shadow = IndexSnapshot::begin_build().expect("build should start");
+    shadow
+        .index
+        .remove_titles(&["Intro to GraphRAG".to_string()]);
+    shadow.index.upsert_documents(&docs[2..], 1, now());
+    assert!(IndexSnapshot::begin_build().is_err(), "one build at a time");
+    // A delete reaches the committed generation at once and the build at commit
+    pipeline
+        .delete_document_by_id("d2")
+        .expect("deletes apply during a build");
+
+    // Interleaved searches see the committed generation only
+    let q = RAGQuery::new("tfidf cooccurrence".into());
+    let during = Retriever::new().search(&q, SearchStrategy::Combined).await;
+    assert_eq!(during.metadata.index_generation, committed);
+    assert!(during.nodes.iter().any(|n| n.id == "d1"));
+    assert!(during.nodes.iter().all(|n| n.id != "d2"));
+
+    let mut pinned = RAGQuery::new("tfidf cooccurrence".into());
+    pinned.config.index_generation = Some(shadow.generation());
+    let refused = Retriever::new()
+        .search(&pinned, SearchStrategy::Combined)
+        .await;
+    assert!(refused.nodes.is_empty());
+    assert!(refused
+        .metadata
+        .algorithms_used
+        .contains(&"generation_refused".to_string()));
+
+    let next = shadow.commit().expect("commit should succeed");
+    let after = Retriever::new().search(&q, SearchStrategy::Combined).await;
+    assert_eq!(after.metadata.index_generation, next);
+    assert!(after.nodes.iter().all(|n| n.id != "d1" && n.id != "d2"));
+
+    // An abandoned build releases the lock without touching the active generation
+    drop(IndexSnapshot::begin_build().expect("build should start"));
+    assert_eq!(index_generations().active, next);
+    assert!(index_generations().building.is_none());
+}
@@ -161,3 +161,38 @@ fn export_import_replace_and_merge() {
         .expect("some msgs");
     assert_eq!(loaded.len(), 2);
 }
+
+#[wasm_bindgen_test]
+fn search_messages_across_conversations() {
+    clear_local_storage();
+    let storage = ConversationStorage::new().expect("init storage");
+    let a = storage
+        .create_conversation("Pricing".to_string())
+        .expect("create a");
+    let b = storage
+        .create_conversation("Roadmap".to_string())
+        .expect("create b");
+    let hit = Message::new(
+        MessageRole::User,
+        "What is the Enterprise price?".to_string(),
+    );
+    storage.save_message(&a, &hit).expect("save a");
+    storage
+        .save_message(
+            &b,
+            &Message::new(MessageRole::User, "Ship the price page".to_string()),
+        )
+        .expect("save b");
+
+    let hits = storage.search_messages("enterprise PRICE").expect("search");
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].conversation_id, a);
+    assert_eq!(hits[0].message_id, hit.id);
+    assert!(hits[0]
+        .snippet
+        .iter()
+        .any(|(text, is_match)| *is_match && text == "Enterprise"));
+
+    assert_eq!(storage.search_messages("price").expect("search").len(), 2);
+    assert!(storage.search_messages("   ").expect("search").is_empty());
+}
@@ -0,0 +1,56 @@
+//! WASM tests for the IndexedDB storage backend and the localStorage migration
+
+#![cfg(target_arch = "wasm32")]
+
+use wasm_bindgen_test::*;
+
+use wasm_bindgen_test::wasm_bindgen_test_configure;
+wasm_bindgen_test_configure!(run_in_browser);
+
+use wasm_knowledge_chatbot_rs::storage::conversation_storage::CONVERSATIONS_KEY;
+use wasm_knowledge_chatbot_rs::storage::{
+    migrate_from_local_storage, IndexedDbBackend, StorageBackend,
+};
+
+fn local_storage() -> web_sys::Storage {
+    web_sys::window()
+        .and_then(|w| w.local_storage().ok().flatten())
+        .expect("localStorage")
+}
+
+#[wasm_bindgen_test]
+async fn indexed_db_round_trip() {
+    let db = IndexedDbBackend::open().await.expect("open db");
+    db.put("test_key", "{\"a\":1}".to_string())
+        .await
+        .expect("put");
+    assert_eq!(
+        db.get("test_key").await.expect("get").as_deref(),
+        Some("{\"a\":1}")
+    );
+    db.delete("test_key").await.expect("delete");
+    assert_eq!(db.get("test_key").await.expect("get"), None);
+}
+
+#[wasm_bindgen_test]
+async fn migrates_legacy_local_storage_once() {
+    let db = IndexedDbBackend::open().await.expect("open db");
+    db.delete(CONVERSATIONS_KEY).await.expect("reset db");
+    let local = local_storage();
+    let _ = local.remove_item("persistent_storage_migrated_v1");
+    local.set_item(CONVERSATIONS_KEY, "[]").expect("seed");
+
+    let migrated = migrate_from_local_storage(&db).await.expect("migrate");
+    assert_eq!(migrated, 1);
+    assert_eq!(
+        db.get(CONVERSATIONS_KEY).await.expect("get").as_deref(),
+        Some("[]")
+    );
+    assert_eq!(local.get_item(CONVERSATIONS_KEY).unwrap(), None);
+
+    // The marker prevents a second run from touching new localStorage data
+    local.set_item(CONVERSATIONS_KEY, "[1]").expect("seed");
+    assert_eq!(migrate_from_local_storage(&db).await.expect("migrate"), 0);
+    let _ = local.remove_item(CONVERSATIONS_KEY);
+    let _ = local.remove_item("persistent_storage_migrated_v1");
+}